@@ -5,6 +5,7 @@ use core::fmt;
 use internals::write_err;
 
 use crate::address::{Address, NetworkUnchecked};
+use crate::blockdata::script::witness_version::WitnessVersion;
 use crate::blockdata::script::{witness_program, witness_version};
 use crate::prelude::*;
 use crate::Network;
@@ -125,6 +126,8 @@ pub enum ParseError {
     InvalidLegacyPrefix(InvalidLegacyPrefixError),
     /// Address's network differs from required one.
     NetworkValidation(NetworkValidationError),
+    /// Address pays to a future witness version, which the policy rejects.
+    FutureSegwit(FutureSegwitError),
 }
 
 internals::impl_from_infallible!(ParseError);
@@ -143,6 +146,7 @@ impl fmt::Display for ParseError {
             InvalidBase58PayloadLength(ref e) => write_err!(f, "legacy address base58 data"; e),
             InvalidLegacyPrefix(ref e) => write_err!(f, "legacy address base58 prefix"; e),
             NetworkValidation(ref e) => write_err!(f, "validation error"; e),
+            FutureSegwit(ref e) => write_err!(f, "future witness version policy"; e),
         }
     }
 }
@@ -162,6 +166,7 @@ impl std::error::Error for ParseError {
             InvalidBase58PayloadLength(ref e) => Some(e),
             InvalidLegacyPrefix(ref e) => Some(e),
             NetworkValidation(ref e) => Some(e),
+            FutureSegwit(ref e) => Some(e),
         }
     }
 }
@@ -202,6 +207,10 @@ impl From<NetworkValidationError> for ParseError {
     fn from(e: NetworkValidationError) -> Self { Self::NetworkValidation(e) }
 }
 
+impl From<FutureSegwitError> for ParseError {
+    fn from(e: FutureSegwitError) -> Self { Self::FutureSegwit(e) }
+}
+
 /// Unknown HRP error.
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[non_exhaustive]
@@ -236,6 +245,36 @@ impl fmt::Display for NetworkValidationError {
 #[cfg(feature = "std")]
 impl std::error::Error for NetworkValidationError {}
 
+/// Address pays to a future witness version, which the [`FutureSegwitPolicy`] rejects.
+///
+/// [`FutureSegwitPolicy`]: crate::address::FutureSegwitPolicy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FutureSegwitError {
+    /// The witness version of the address.
+    pub(crate) version: WitnessVersion,
+    /// The address itself.
+    pub(crate) address: Address<NetworkUnchecked>,
+}
+
+impl FutureSegwitError {
+    /// Returns the witness version of the address.
+    pub fn witness_version(&self) -> WitnessVersion { self.version }
+
+    /// Returns the rejected address.
+    pub fn address(&self) -> &Address<NetworkUnchecked> { &self.address }
+}
+
+impl fmt::Display for FutureSegwitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "address ")?;
+        fmt::Display::fmt(&self.address.0, f)?;
+        write!(f, " pays to future witness version {}", self.version)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for FutureSegwitError {}
+
 /// Decoded base58 data was an invalid length.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct InvalidBase58PayloadLengthError {
@@ -53,7 +53,7 @@ use crate::taproot::TapNodeHash;
 #[rustfmt::skip]                // Keep public re-exports separate.
 #[doc(inline)]
 pub use self::error::{
-        FromScriptError, InvalidBase58PayloadLengthError, InvalidLegacyPrefixError, LegacyAddressTooLongError,
+        FromScriptError, FutureSegwitError, InvalidBase58PayloadLengthError, InvalidLegacyPrefixError, LegacyAddressTooLongError,
         NetworkValidationError, ParseError, P2shError, UnknownAddressTypeError, UnknownHrpError
     };
 
@@ -99,6 +99,21 @@ impl FromStr for AddressType {
     }
 }
 
+/// Whether paying to segwit addresses of unassigned witness versions is allowed.
+///
+/// Outputs to witness versions 2 to 16, and to version 1 programs other than 32-byte taproot
+/// outputs, are reserved for future soft forks. They are standard to relay, but until a soft
+/// fork gives them meaning anyone can spend them. BIP350 asks senders to pay to them anyway so
+/// receivers can upgrade without waiting for every sender, which is the default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum FutureSegwitPolicy {
+    /// Allow paying to future witness versions, as relay policy does.
+    #[default]
+    Allow,
+    /// Reject addresses of future witness versions.
+    Reject,
+}
+
 mod sealed {
     pub trait NetworkValidation {}
     impl NetworkValidation for super::NetworkChecked {}
@@ -368,6 +383,31 @@ impl<V: NetworkValidation> Address<V> {
     pub fn as_unchecked(&self) -> &Address<NetworkUnchecked> {
         unsafe { &*(self as *const Address<V> as *const Address<NetworkUnchecked>) }
     }
+
+    /// Returns the witness version of the address if it is reserved for a future soft fork.
+    ///
+    /// See [`FutureSegwitPolicy`] for which witness programs this covers.
+    pub fn future_witness_version(&self) -> Option<WitnessVersion> {
+        match self.0 {
+            AddressInner::Segwit { ref program, .. }
+                if program.version() != WitnessVersion::V0 && !program.is_p2tr() =>
+            {
+                Some(program.version())
+            }
+            _ => None,
+        }
+    }
+
+    /// Checks that paying to this address is allowed by `policy`.
+    pub fn check_future_segwit(&self, policy: FutureSegwitPolicy) -> Result<(), FutureSegwitError> {
+        match (policy, self.future_witness_version()) {
+            (FutureSegwitPolicy::Reject, Some(version)) => Err(FutureSegwitError {
+                version,
+                address: self.as_unchecked().clone(),
+            }),
+            _ => Ok(()),
+        }
+    }
 }
 
 /// Methods and functions that can be called only on `Address<NetworkChecked>`.
@@ -808,6 +848,27 @@ impl Address<NetworkUnchecked> {
         }
     }
 
+    /// Checks the network of this address like [`require_network`], and that paying to it is
+    /// allowed by `policy`.
+    ///
+    /// Wallets which must not pay to witness versions they don't know about can validate the
+    /// addresses they are given with this instead of [`require_network`].
+    ///
+    /// # Errors
+    ///
+    /// [`ParseError::NetworkValidation`] or [`ParseError::FutureSegwit`].
+    ///
+    /// [`require_network`]: Address<NetworkUnchecked>::require_network
+    #[inline]
+    pub fn require_network_and_policy(
+        self,
+        required: Network,
+        policy: FutureSegwitPolicy,
+    ) -> Result<Address, ParseError> {
+        self.check_future_segwit(policy)?;
+        self.require_network(required)
+    }
+
     /// Marks, without any additional checks, network of this address as checked.
     ///
     /// Improper use of this method may lead to loss of funds. Reader will most likely prefer
@@ -1502,4 +1563,53 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn future_segwit_policy() {
+        let known = [
+            "1QJVDzdqb1VpbDK7uDeyVXy9mR27CJiyhY",
+            "33iFwdLuRpW1uK1RTRqsoi8rR4NpDzk66k",
+            "bc1qvzvkjn4q3nszqxrv3nraga2r822xjty3ykvkuw",
+            "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr",
+        ];
+        for addr in &known {
+            let addr = Address::from_str(addr).unwrap();
+            assert_eq!(addr.future_witness_version(), None);
+            assert!(addr.check_future_segwit(FutureSegwitPolicy::Reject).is_ok());
+        }
+
+        let future = [
+            (
+                "bc1pw508d6qejxtdg4y5r3zarvary0c5xw7kw508d6qejxtdg4y5r3zarvary0c5xw7kt5nd6y",
+                WitnessVersion::V1,
+            ),
+            ("bc1zw508d6qejxtdg4y5r3zarvaryvaxxpcs", WitnessVersion::V2),
+        ];
+        for (addr, version) in &future {
+            let addr = Address::from_str(addr).unwrap();
+            assert_eq!(addr.future_witness_version(), Some(*version));
+            assert!(addr
+                .check_future_segwit(FutureSegwitPolicy::default())
+                .is_ok());
+            let err = addr
+                .check_future_segwit(FutureSegwitPolicy::Reject)
+                .unwrap_err();
+            assert_eq!(err.witness_version(), *version);
+            assert_eq!(err.address(), &addr);
+
+            assert!(addr
+                .clone()
+                .require_network_and_policy(Network::Bitcoin, FutureSegwitPolicy::Allow)
+                .is_ok());
+            assert_eq!(
+                addr.clone()
+                    .require_network_and_policy(Network::Bitcoin, FutureSegwitPolicy::Reject),
+                Err(ParseError::FutureSegwit(err))
+            );
+            assert!(matches!(
+                addr.require_network_and_policy(Network::Testnet, FutureSegwitPolicy::Allow),
+                Err(ParseError::NetworkValidation(_))
+            ));
+        }
+    }
 }
@@ -0,0 +1,553 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Wallet backups.
+//!
+//! This module provides a versioned container for the data needed to restore a wallet on top of
+//! its seed: output descriptors, labels, key origins and optionally encrypted extended private
+//! keys. The container is authenticated with an HMAC-SHA256 keyed from the wallet seed, so a
+//! backup only restores against the seed it was made for and any corruption is detected.
+//!
+//! Backups are always written in the [`CURRENT_VERSION`] format. Older versions are migrated to
+//! the current [`WalletBackup`] when read.
+//!
+//! The serialization format is:
+//!
+//! ```text
+//! magic "WBAK" (4 bytes) | version (1 byte) | body | HMAC-SHA256 (32 bytes)
+//! ```
+//!
+//! where the MAC covers everything before it.
+//!
+
+use core::fmt;
+
+#[cfg(all(feature = "scrypt", feature = "chacha20poly1305"))]
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+#[cfg(all(feature = "scrypt", feature = "chacha20poly1305"))]
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use hashes::{sha256, Hash, HashEngine, Hmac, HmacEngine};
+use internals::write_err;
+use io::{BufRead, Write};
+use subtle::ConstantTimeEq;
+
+use crate::bip32::{ChildNumber, DerivationPath, Fingerprint, KeySource, Xpub};
+use crate::consensus::encode::{self, Decodable, Encodable, VarInt};
+#[cfg(all(feature = "scrypt", feature = "chacha20poly1305"))]
+use crate::crypto::scrypt::{scrypt, ScryptParams, ScryptParamsError};
+use crate::prelude::*;
+
+/// The magic bytes at the start of every backup.
+pub const BACKUP_MAGIC: [u8; 4] = *b"WBAK";
+
+/// The version of the backup format written by this library.
+///
+/// Version 1 held descriptors and labels only. Version 2 added key origins and encrypted
+/// extended private keys.
+pub const CURRENT_VERSION: u8 = 2;
+
+/// The key used to derive the MAC key from the wallet seed.
+const MAC_KEY_TAG: &[u8] = b"Bitcoin wallet backup";
+
+/// The length of the MAC at the end of a backup.
+const MAC_LEN: usize = 32;
+
+/// The contents of a wallet backup.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WalletBackup {
+    /// Output descriptors of the wallet, in their string form.
+    pub descriptors: Vec<String>,
+    /// Labels, keyed by the string form of what they label (address, txid, outpoint, ...).
+    pub labels: BTreeMap<String, String>,
+    /// The origin of each extended public key used by the wallet.
+    pub key_origins: BTreeMap<Xpub, KeySource>,
+    /// Extended private keys, encrypted by the caller.
+    ///
+    /// This format treats the contents as opaque bytes and does not define the cipher. With the
+    /// `scrypt` and `chacha20poly1305` features, [`WalletBackup::encrypt_xprivs`] encrypts them
+    /// under a passphrase.
+    pub encrypted_xprivs: Option<Vec<u8>>,
+}
+
+impl WalletBackup {
+    /// Creates an empty backup.
+    pub fn new() -> Self { Self::default() }
+
+    /// Serializes the backup in the [`CURRENT_VERSION`] format, authenticated with `seed`.
+    pub fn serialize(&self, seed: &[u8]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&BACKUP_MAGIC);
+        buf.push(CURRENT_VERSION);
+        self.encode_v1(&mut buf).expect("in-memory writers don't error");
+        self.encode_v2(&mut buf).expect("in-memory writers don't error");
+        let mac = compute_mac(seed, &buf);
+        buf.extend_from_slice(&mac[..]);
+        buf
+    }
+
+    /// Deserializes a backup authenticated with `seed`, migrating it from older versions.
+    pub fn deserialize(bytes: &[u8], seed: &[u8]) -> Result<Self, BackupError> {
+        if bytes.len() < BACKUP_MAGIC.len() + 1 + MAC_LEN {
+            return Err(BackupError::TooShort);
+        }
+        let (data, mac) = bytes.split_at(bytes.len() - MAC_LEN);
+        if !bool::from(compute_mac(seed, data)[..].ct_eq(mac)) {
+            return Err(BackupError::MacMismatch);
+        }
+        if data[..BACKUP_MAGIC.len()] != BACKUP_MAGIC {
+            return Err(BackupError::InvalidMagic);
+        }
+
+        let version = data[BACKUP_MAGIC.len()];
+        let mut r = &data[BACKUP_MAGIC.len() + 1..];
+        let mut backup = WalletBackup::new();
+        match version {
+            1 => backup.decode_v1(&mut r)?,
+            2 => {
+                backup.decode_v1(&mut r)?;
+                backup.decode_v2(&mut r)?;
+            }
+            v => return Err(BackupError::UnsupportedVersion(v)),
+        }
+        if !r.is_empty() {
+            return Err(encode::Error::ParseFailed("trailing data in backup").into());
+        }
+        Ok(backup)
+    }
+
+    /// Encodes the fields present since version 1.
+    fn encode_v1<W: Write + ?Sized>(&self, w: &mut W) -> Result<usize, io::Error> {
+        let mut len = VarInt::from(self.descriptors.len()).consensus_encode(w)?;
+        for descriptor in &self.descriptors {
+            len += descriptor.consensus_encode(w)?;
+        }
+        len += VarInt::from(self.labels.len()).consensus_encode(w)?;
+        for (item, label) in &self.labels {
+            len += item.consensus_encode(w)?;
+            len += label.consensus_encode(w)?;
+        }
+        Ok(len)
+    }
+
+    /// Encodes the fields added in version 2.
+    fn encode_v2<W: Write + ?Sized>(&self, w: &mut W) -> Result<usize, io::Error> {
+        let mut len = VarInt::from(self.key_origins.len()).consensus_encode(w)?;
+        for (xpub, (fingerprint, path)) in &self.key_origins {
+            w.write_all(&xpub.encode())?;
+            len += 78;
+            len += fingerprint.to_bytes().consensus_encode(w)?;
+            len += VarInt::from(path.len()).consensus_encode(w)?;
+            for child in path {
+                len += u32::from(*child).consensus_encode(w)?;
+            }
+        }
+        len += match self.encrypted_xprivs {
+            Some(ref xprivs) => 1u8.consensus_encode(w)? + xprivs.consensus_encode(w)?,
+            None => 0u8.consensus_encode(w)?,
+        };
+        Ok(len)
+    }
+
+    fn decode_v1<R: BufRead + ?Sized>(&mut self, r: &mut R) -> Result<(), encode::Error> {
+        let count = VarInt::consensus_decode(r)?.0;
+        for _ in 0..count {
+            self.descriptors.push(String::consensus_decode(r)?);
+        }
+        let count = VarInt::consensus_decode(r)?.0;
+        for _ in 0..count {
+            let item = String::consensus_decode(r)?;
+            let label = String::consensus_decode(r)?;
+            self.labels.insert(item, label);
+        }
+        Ok(())
+    }
+
+    fn decode_v2<R: BufRead + ?Sized>(&mut self, r: &mut R) -> Result<(), encode::Error> {
+        let count = VarInt::consensus_decode(r)?.0;
+        for _ in 0..count {
+            let mut xpub = [0u8; 78];
+            r.read_exact(&mut xpub)?;
+            let xpub = Xpub::decode(&xpub)
+                .map_err(|_| encode::Error::ParseFailed("invalid extended public key"))?;
+            let fingerprint = Fingerprint::from(<[u8; 4]>::consensus_decode(r)?);
+            let depth = VarInt::consensus_decode(r)?.0;
+            let path = (0..depth)
+                .map(|_| u32::consensus_decode(r).map(ChildNumber::from))
+                .collect::<Result<Vec<_>, _>>()?;
+            self.key_origins.insert(xpub, (fingerprint, DerivationPath::from(path)));
+        }
+        self.encrypted_xprivs = match u8::consensus_decode(r)? {
+            0 => None,
+            1 => Some(Decodable::consensus_decode(r)?),
+            _ => return Err(encode::Error::ParseFailed("invalid encrypted xprivs flag")),
+        };
+        Ok(())
+    }
+}
+
+/// The version of the passphrase encryption of [`WalletBackup::encrypted_xprivs`].
+#[cfg(all(feature = "scrypt", feature = "chacha20poly1305"))]
+const XPRIVS_VERSION: u8 = 1;
+
+/// The length of the header of the encrypted xprivs: the version, the scrypt parameters and the
+/// salt.
+#[cfg(all(feature = "scrypt", feature = "chacha20poly1305"))]
+const XPRIVS_HEADER_LEN: usize = 1 + 1 + 4 + 4 + 16;
+
+/// The length of the Poly1305 tag of the encrypted xprivs.
+#[cfg(all(feature = "scrypt", feature = "chacha20poly1305"))]
+const XPRIVS_TAG_LEN: usize = 16;
+
+/// The largest scrypt cost of the encrypted xprivs, a derivation with `r = 8` then needs 1 GiB of
+/// memory.
+#[cfg(all(feature = "scrypt", feature = "chacha20poly1305"))]
+pub const MAX_XPRIVS_LOG_N: u8 = 20;
+
+/// The largest scrypt block size of the encrypted xprivs.
+#[cfg(all(feature = "scrypt", feature = "chacha20poly1305"))]
+pub const MAX_XPRIVS_R: u32 = 32;
+
+/// The largest scrypt parallelization of the encrypted xprivs.
+#[cfg(all(feature = "scrypt", feature = "chacha20poly1305"))]
+pub const MAX_XPRIVS_P: u32 = 16;
+
+#[cfg(all(feature = "scrypt", feature = "chacha20poly1305"))]
+impl WalletBackup {
+    /// Encrypts the serialized extended private keys `xprivs` under `passphrase`, and stores them
+    /// in [`WalletBackup::encrypted_xprivs`].
+    ///
+    /// The key is derived with scrypt using `params` and a random salt, and the keys are then
+    /// encrypted with ChaCha20-Poly1305. The format is:
+    ///
+    /// ```text
+    /// version (1 byte) | log_n (1 byte) | r (4 bytes) | p (4 bytes) | salt (16 bytes)
+    ///     | encrypted xprivs | Poly1305 tag (16 bytes)
+    /// ```
+    ///
+    /// where `r` and `p` are little-endian and the header is authenticated as associated data.
+    ///
+    /// # Errors
+    ///
+    /// If `params` exceed [`MAX_XPRIVS_LOG_N`], [`MAX_XPRIVS_R`] or [`MAX_XPRIVS_P`], as
+    /// [`WalletBackup::decrypt_xprivs`] would refuse them.
+    pub fn encrypt_xprivs<R: rand::RngCore + rand::CryptoRng>(
+        &mut self,
+        xprivs: &[u8],
+        passphrase: &[u8],
+        params: ScryptParams,
+        rng: &mut R,
+    ) -> Result<(), XprivsError> {
+        check_xprivs_params(params.log_n(), params.r(), params.p())?;
+        let mut salt = [0u8; 16];
+        rng.fill_bytes(&mut salt);
+
+        let mut encrypted = Vec::with_capacity(XPRIVS_HEADER_LEN + xprivs.len() + XPRIVS_TAG_LEN);
+        encrypted.push(XPRIVS_VERSION);
+        encrypted.push(params.log_n());
+        encrypted.extend_from_slice(&params.r().to_le_bytes());
+        encrypted.extend_from_slice(&params.p().to_le_bytes());
+        encrypted.extend_from_slice(&salt);
+
+        let (cipher, nonce) = derive_xprivs_cipher(passphrase, &salt, params);
+        let ciphertext = cipher
+            .encrypt(&nonce, Payload { msg: xprivs, aad: &encrypted })
+            .expect("xprivs are much shorter than the ChaCha20 limit");
+        encrypted.extend_from_slice(&ciphertext);
+        self.encrypted_xprivs = Some(encrypted);
+        Ok(())
+    }
+
+    /// Decrypts the extended private keys encrypted by [`WalletBackup::encrypt_xprivs`].
+    ///
+    /// Returns `None` if the backup holds no extended private keys.
+    ///
+    /// The scrypt parameters come from the backup, so they are bounded like in
+    /// [`WalletBackup::encrypt_xprivs`] before deriving the key, lest a crafted backup make the
+    /// derivation exhaust the memory.
+    pub fn decrypt_xprivs(&self, passphrase: &[u8]) -> Result<Option<Vec<u8>>, XprivsError> {
+        let encrypted = match self.encrypted_xprivs {
+            Some(ref encrypted) => encrypted,
+            None => return Ok(None),
+        };
+        if encrypted.len() < XPRIVS_HEADER_LEN + XPRIVS_TAG_LEN {
+            return Err(XprivsError::TooShort);
+        }
+        match encrypted[0] {
+            XPRIVS_VERSION => {}
+            v => return Err(XprivsError::UnsupportedVersion(v)),
+        }
+        let (header, ciphertext) = encrypted.split_at(XPRIVS_HEADER_LEN);
+        let r = u32::from_le_bytes(header[2..6].try_into().expect("4 bytes"));
+        let p = u32::from_le_bytes(header[6..10].try_into().expect("4 bytes"));
+        check_xprivs_params(header[1], r, p)?;
+        let params = ScryptParams::new(header[1], r, p)?;
+
+        let (cipher, nonce) = derive_xprivs_cipher(passphrase, &header[10..], params);
+        cipher
+            .decrypt(&nonce, Payload { msg: ciphertext, aad: header })
+            .map(Some)
+            .map_err(|_| XprivsError::Decryption)
+    }
+}
+
+/// Checks that the scrypt parameters of the encrypted xprivs are within the limits.
+#[cfg(all(feature = "scrypt", feature = "chacha20poly1305"))]
+fn check_xprivs_params(log_n: u8, r: u32, p: u32) -> Result<(), XprivsError> {
+    if log_n > MAX_XPRIVS_LOG_N || r > MAX_XPRIVS_R || p > MAX_XPRIVS_P {
+        return Err(XprivsError::ExcessiveCost { log_n, r, p });
+    }
+    Ok(())
+}
+
+/// Derives the cipher and nonce encrypting the xprivs from `passphrase`.
+#[cfg(all(feature = "scrypt", feature = "chacha20poly1305"))]
+fn derive_xprivs_cipher(
+    passphrase: &[u8],
+    salt: &[u8],
+    params: ScryptParams,
+) -> (ChaCha20Poly1305, Nonce) {
+    let mut okm = [0u8; 32 + 12];
+    scrypt(passphrase, salt, params, &mut okm);
+    let key = Key::from(<[u8; 32]>::try_from(&okm[..32]).expect("32 bytes"));
+    let nonce = Nonce::from(<[u8; 12]>::try_from(&okm[32..]).expect("12 bytes"));
+    (ChaCha20Poly1305::new(&key), nonce)
+}
+
+/// Computes the backup MAC of `data` with a key derived from `seed`.
+fn compute_mac(seed: &[u8], data: &[u8]) -> Hmac<sha256::Hash> {
+    let mut key_engine = HmacEngine::<sha256::Hash>::new(MAC_KEY_TAG);
+    key_engine.input(seed);
+    let key = Hmac::from_engine(key_engine);
+
+    let mut engine = HmacEngine::<sha256::Hash>::new(&key[..]);
+    engine.input(data);
+    Hmac::from_engine(engine)
+}
+
+/// An error reading a wallet backup.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum BackupError {
+    /// The data is too short to be a backup.
+    TooShort,
+    /// The MAC does not match, either the backup is corrupted or the seed is wrong.
+    MacMismatch,
+    /// The data does not start with [`BACKUP_MAGIC`].
+    InvalidMagic,
+    /// The backup was written by a newer, unknown, version of the format.
+    UnsupportedVersion(u8),
+    /// The backup body is malformed.
+    Decode(encode::Error),
+}
+
+internals::impl_from_infallible!(BackupError);
+
+impl fmt::Display for BackupError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use BackupError::*;
+
+        match *self {
+            TooShort => f.write_str("data too short to be a wallet backup"),
+            MacMismatch => f.write_str("backup MAC mismatch, wrong seed or corrupted backup"),
+            InvalidMagic => f.write_str("invalid wallet backup magic"),
+            UnsupportedVersion(v) => write!(f, "unsupported wallet backup version {}", v),
+            Decode(ref e) => write_err!(f, "malformed wallet backup"; e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BackupError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use BackupError::*;
+
+        match *self {
+            Decode(ref e) => Some(e),
+            TooShort | MacMismatch | InvalidMagic | UnsupportedVersion(_) => None,
+        }
+    }
+}
+
+impl From<encode::Error> for BackupError {
+    fn from(e: encode::Error) -> Self { Self::Decode(e) }
+}
+
+/// An error decrypting the extended private keys of a backup.
+#[cfg(all(feature = "scrypt", feature = "chacha20poly1305"))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum XprivsError {
+    /// The data is too short to hold encrypted xprivs.
+    TooShort,
+    /// The xprivs were encrypted by a newer, unknown, version of the format.
+    UnsupportedVersion(u8),
+    /// The scrypt parameters are invalid.
+    Params(ScryptParamsError),
+    /// The scrypt parameters exceed [`MAX_XPRIVS_LOG_N`], [`MAX_XPRIVS_R`] or [`MAX_XPRIVS_P`].
+    ExcessiveCost {
+        /// The base 2 logarithm of the cost.
+        log_n: u8,
+        /// The block size.
+        r: u32,
+        /// The parallelization.
+        p: u32,
+    },
+    /// The xprivs failed authentication, either they were tampered with or the passphrase is
+    /// wrong.
+    Decryption,
+}
+
+#[cfg(all(feature = "scrypt", feature = "chacha20poly1305"))]
+internals::impl_from_infallible!(XprivsError);
+
+#[cfg(all(feature = "scrypt", feature = "chacha20poly1305"))]
+impl fmt::Display for XprivsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use XprivsError::*;
+
+        match *self {
+            TooShort => f.write_str("data too short to hold encrypted xprivs"),
+            UnsupportedVersion(v) => write!(f, "unsupported encrypted xprivs version {}", v),
+            Params(ref e) => write_err!(f, "invalid encrypted xprivs parameters"; e),
+            ExcessiveCost { log_n, r, p } => write!(
+                f,
+                "encrypted xprivs scrypt parameters log_n = {}, r = {}, p = {} exceed the limits",
+                log_n, r, p
+            ),
+            Decryption =>
+                f.write_str("xprivs decryption failed, wrong passphrase or corrupted data"),
+        }
+    }
+}
+
+#[cfg(all(feature = "std", feature = "scrypt", feature = "chacha20poly1305"))]
+impl std::error::Error for XprivsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use XprivsError::*;
+
+        match *self {
+            Params(ref e) => Some(e),
+            TooShort | UnsupportedVersion(_) | ExcessiveCost { .. } | Decryption => None,
+        }
+    }
+}
+
+#[cfg(all(feature = "scrypt", feature = "chacha20poly1305"))]
+impl From<ScryptParamsError> for XprivsError {
+    fn from(e: ScryptParamsError) -> Self { Self::Params(e) }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::str::FromStr;
+
+    use super::*;
+
+    const SEED: &[u8] = &[0x42; 32];
+
+    fn backup() -> WalletBackup {
+        let xpub = Xpub::from_str("xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8").unwrap();
+        let mut backup = WalletBackup::new();
+        backup.descriptors.push("wpkh([d34db33f/84h/0h/0h]xpub.../0/*)".to_owned());
+        backup.labels.insert("bc1qexample".to_owned(), "savings".to_owned());
+        backup.key_origins.insert(
+            xpub,
+            (Fingerprint::from([0xd3, 0x4d, 0xb3, 0x3f]), "84h/0h/0h".parse().unwrap()),
+        );
+        backup.encrypted_xprivs = Some(vec![0xaa; 48]);
+        backup
+    }
+
+    #[test]
+    fn roundtrip() {
+        let backup = backup();
+        let bytes = backup.serialize(SEED);
+        assert_eq!(&bytes[..4], b"WBAK");
+        assert_eq!(bytes[4], CURRENT_VERSION);
+        assert_eq!(WalletBackup::deserialize(&bytes, SEED).unwrap(), backup);
+
+        let empty = WalletBackup::new();
+        assert_eq!(WalletBackup::deserialize(&empty.serialize(SEED), SEED).unwrap(), empty);
+    }
+
+    #[test]
+    fn authentication() {
+        let mut bytes = backup().serialize(SEED);
+        assert!(matches!(
+            WalletBackup::deserialize(&bytes, &[0x43; 32]),
+            Err(BackupError::MacMismatch)
+        ));
+
+        bytes[10] ^= 1;
+        assert!(matches!(WalletBackup::deserialize(&bytes, SEED), Err(BackupError::MacMismatch)));
+        assert!(matches!(
+            WalletBackup::deserialize(&bytes[..20], SEED),
+            Err(BackupError::TooShort)
+        ));
+    }
+
+    #[test]
+    fn version_migration() {
+        let backup = backup();
+
+        let mut v1 = BACKUP_MAGIC.to_vec();
+        v1.push(1);
+        backup.encode_v1(&mut v1).unwrap();
+        let mac = compute_mac(SEED, &v1);
+        v1.extend_from_slice(&mac[..]);
+
+        let migrated = WalletBackup::deserialize(&v1, SEED).unwrap();
+        assert_eq!(migrated.descriptors, backup.descriptors);
+        assert_eq!(migrated.labels, backup.labels);
+        assert!(migrated.key_origins.is_empty());
+        assert_eq!(migrated.encrypted_xprivs, None);
+
+        let mut v3 = BACKUP_MAGIC.to_vec();
+        v3.push(3);
+        let mac = compute_mac(SEED, &v3);
+        v3.extend_from_slice(&mac[..]);
+        assert!(matches!(
+            WalletBackup::deserialize(&v3, SEED),
+            Err(BackupError::UnsupportedVersion(3))
+        ));
+    }
+
+    #[test]
+    #[cfg(all(feature = "scrypt", feature = "chacha20poly1305"))]
+    fn xprivs_encryption() {
+        let params = ScryptParams::new(10, 8, 1).unwrap();
+        let xprivs = [0x55; 78];
+        let mut backup = backup();
+        backup.encrypt_xprivs(&xprivs, b"passphrase", params, &mut rand::thread_rng()).unwrap();
+
+        let restored = WalletBackup::deserialize(&backup.serialize(SEED), SEED).unwrap();
+        assert_eq!(restored.decrypt_xprivs(b"passphrase"), Ok(Some(xprivs.to_vec())));
+        assert_eq!(restored.decrypt_xprivs(b"wrong"), Err(XprivsError::Decryption));
+        assert_eq!(WalletBackup::new().decrypt_xprivs(b"passphrase"), Ok(None));
+
+        let mut tampered = restored.clone();
+        tampered.encrypted_xprivs.as_mut().unwrap()[1] = 11;
+        assert_eq!(tampered.decrypt_xprivs(b"passphrase"), Err(XprivsError::Decryption));
+        tampered.encrypted_xprivs.as_mut().unwrap()[1] = 0;
+        assert_eq!(
+            tampered.decrypt_xprivs(b"passphrase"),
+            Err(XprivsError::Params(ScryptParamsError::InvalidCost(0)))
+        );
+        // A crafted header could make the derivation take terabytes of memory.
+        for (offset, value) in [(1, 21), (2, 33), (6, 17)] {
+            let mut encrypted = restored.encrypted_xprivs.clone().unwrap();
+            encrypted[offset] = value;
+            assert!(matches!(
+                WalletBackup { encrypted_xprivs: Some(encrypted), ..restored.clone() }
+                    .decrypt_xprivs(b"passphrase"),
+                Err(XprivsError::ExcessiveCost { .. })
+            ));
+        }
+        let params = ScryptParams::new(21, 8, 1).unwrap();
+        assert!(matches!(
+            backup.encrypt_xprivs(&xprivs, b"passphrase", params, &mut rand::thread_rng()),
+            Err(XprivsError::ExcessiveCost { log_n: 21, r: 8, p: 1 })
+        ));
+        tampered.encrypted_xprivs = Some(vec![XPRIVS_VERSION; 20]);
+        assert_eq!(tampered.decrypt_xprivs(b"passphrase"), Err(XprivsError::TooShort));
+    }
+}
@@ -11,19 +11,21 @@ use core::ops::Index;
 use core::str::FromStr;
 use core::{fmt, slice};
 
-use hashes::{hash160, hash_newtype, sha512, Hash, HashEngine, Hmac, HmacEngine};
+use hashes::{hash160, hash_newtype, sha512, Hash, HashEngine, Hmac};
 use internals::{impl_array_newtype, write_err};
 use io::Write;
 use k256::SecretKey;
 
-use crate::crypto::key::{CompressedPublicKey, Keypair, PrivateKey};
+use crate::address::AddressType;
+use crate::crypto::kdf::HmacSha512Engine;
+use crate::crypto::key::{CompressedPublicKey, Keypair, PrivateKey, Tweak};
 use crate::internal_macros::impl_bytes_newtype;
 use crate::key::PublicKey;
 use crate::network::NetworkKind;
 use crate::psbt::serialize::Serialize;
 use crate::utils::{add_exp_tweak, add_tweak};
 use crate::{prelude::*, CryptoError};
-use crate::{Scalar, XOnlyPublicKey};
+use crate::XOnlyPublicKey;
 
 /// Version bytes for extended public keys on the Bitcoin network.
 const VERSION_BYTES_MAINNET_PUBLIC: [u8; 4] = [0x04, 0x88, 0xB2, 0x1E];
@@ -303,6 +305,10 @@ pub trait IntoDerivationPath {
 }
 
 /// A BIP-32 derivation path.
+///
+/// Parses from strings such as `m/84'/0'/0'` or `84h/0h/0h`: the `m/` prefix is optional and
+/// hardened indices can be marked with `'` or `h`. Displays without the prefix, marking hardened
+/// indices with `'`, or with `h` in the alternate form `{:#}`.
 #[derive(Clone, PartialEq, Eq, Ord, PartialOrd, Hash)]
 pub struct DerivationPath(Vec<ChildNumber>);
 
@@ -393,6 +399,11 @@ impl FromStr for DerivationPath {
     type Err = Error;
 
     fn from_str(path: &str) -> Result<DerivationPath, Error> {
+        let path = if path == "m" {
+            ""
+        } else {
+            path.strip_prefix("m/").unwrap_or(path)
+        };
         let ret: Result<Vec<ChildNumber>, Error> = if path.is_empty() {
             Ok(vec![])
         } else {
@@ -403,6 +414,40 @@ impl FromStr for DerivationPath {
     }
 }
 
+/// The wildcard ending a derivation path such as `m/86'/0'/0'/0/*`, which stands for every
+/// child index.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Wildcard {
+    /// The path has no wildcard.
+    None,
+    /// The path ends with `*`, for normal child indices.
+    Normal,
+    /// The path ends with `*'` or `*h`, for hardened child indices.
+    Hardened,
+}
+
+impl Wildcard {
+    /// Returns the child number at `index` this wildcard stands for, `None` if there's no
+    /// wildcard.
+    pub fn child_number(self, index: u32) -> Result<Option<ChildNumber>, Error> {
+        match self {
+            Wildcard::None => Ok(None),
+            Wildcard::Normal => ChildNumber::from_normal_idx(index).map(Some),
+            Wildcard::Hardened => ChildNumber::from_hardened_idx(index).map(Some),
+        }
+    }
+}
+
+impl fmt::Display for Wildcard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Wildcard::None => Ok(()),
+            Wildcard::Normal => f.write_str("*"),
+            Wildcard::Hardened => f.write_str(if f.alternate() { "*h" } else { "*'" }),
+        }
+    }
+}
+
 /// An iterator over children of a [DerivationPath].
 ///
 /// It is returned by the methods [DerivationPath::children_from],
@@ -454,6 +499,22 @@ impl DerivationPath {
         self.0.is_empty()
     }
 
+    /// Parses a path which may end with a [`Wildcard`], such as `m/86'/0'/0'/0/*`.
+    ///
+    /// The path itself is parsed like [`DerivationPath::from_str`].
+    pub fn from_str_with_wildcard(path: &str) -> Result<(DerivationPath, Wildcard), Error> {
+        let (prefix, last) = match path.rfind('/') {
+            Some(i) => (&path[..i], &path[i + 1..]),
+            None => ("", path),
+        };
+        let wildcard = match last {
+            "*" => Wildcard::Normal,
+            "*'" | "*h" => Wildcard::Hardened,
+            _ => return Ok((path.parse()?, Wildcard::None)),
+        };
+        Ok((prefix.parse()?, wildcard))
+    }
+
     /// Create a new [DerivationPath] that is a child of this one.
     pub fn child(&self, cn: ChildNumber) -> DerivationPath {
         let mut path = self.0.clone();
@@ -527,11 +588,11 @@ impl fmt::Display for DerivationPath {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let mut iter = self.0.iter();
         if let Some(first_element) = iter.next() {
-            write!(f, "{}", first_element)?;
+            fmt::Display::fmt(first_element, f)?;
         }
         for cn in iter {
             f.write_str("/")?;
-            write!(f, "{}", cn)?;
+            fmt::Display::fmt(cn, f)?;
         }
         Ok(())
     }
@@ -547,6 +608,34 @@ impl fmt::Debug for DerivationPath {
 /// master extended public key and a derivation path from it.
 pub type KeySource = (Fingerprint, DerivationPath);
 
+/// A child index skipped because its key is invalid.
+///
+/// BIP32 child derivation fails when `IL` is not lower than the curve order or the child key is
+/// the point at infinity, and specifies to proceed with the next index. This happens with a
+/// probability lower than 1 in 2^127, but wallets should still record the skipped index, e.g. so
+/// that they don't hand out the same address for two indices.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Skipped(pub ChildNumber);
+
+/// Calls `derive` with `i` and the following indices until it doesn't fail with
+/// [`Error::InvalidChildKey`].
+fn next_valid<K>(
+    mut i: ChildNumber,
+    mut derive: impl FnMut(ChildNumber) -> Result<K, Error>,
+) -> Result<(K, Vec<Skipped>), Error> {
+    let mut skipped = Vec::new();
+    loop {
+        match derive(i) {
+            Ok(key) => return Ok((key, skipped)),
+            Err(Error::InvalidChildKey(_)) => {
+                skipped.push(Skipped(i));
+                i = i.increment()?;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
 /// A BIP32 error
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[non_exhaustive]
@@ -573,6 +662,8 @@ pub enum Error {
     InvalidPublicKeyHexLength(usize),
     /// Base58 decoded data was an invalid length.
     InvalidBase58PayloadLength(InvalidBase58PayloadLengthError),
+    /// The child key at this index is invalid, the next index should be used instead.
+    InvalidChildKey(ChildNumber),
 }
 
 internals::impl_from_infallible!(Error);
@@ -605,6 +696,7 @@ impl fmt::Display for Error {
                 got
             ),
             InvalidBase58PayloadLength(ref e) => write_err!(f, "base58 payload"; e),
+            InvalidChildKey(ref n) => write!(f, "invalid child key at index {}", n),
         }
     }
 }
@@ -625,7 +717,8 @@ impl std::error::Error for Error {
             | InvalidDerivationPathFormat
             | UnknownVersion(_)
             | WrongExtendedKeyLength(_)
-            | InvalidPublicKeyHexLength(_) => None,
+            | InvalidPublicKeyHexLength(_)
+            | InvalidChildKey(_) => None,
         }
     }
 }
@@ -651,7 +744,7 @@ impl From<InvalidBase58PayloadLengthError> for Error {
 impl Xpriv {
     /// Construct a new master key from a seed value
     pub fn new_master(network: impl Into<NetworkKind>, seed: &[u8]) -> Result<Xpriv, Error> {
-        let mut hmac_engine: HmacEngine<sha512::Hash> = HmacEngine::new(b"Bitcoin seed");
+        let mut hmac_engine = HmacSha512Engine::new(b"Bitcoin seed");
         hmac_engine.input(seed);
         let hmac_result: Hmac<sha512::Hash> = Hmac::from_engine(hmac_engine);
         let private_key =
@@ -698,9 +791,23 @@ impl Xpriv {
         Ok(sk)
     }
 
+    /// Attempts to derive an extended private key from a path string such as `m/84h/0h/0h`.
+    ///
+    /// The path is parsed like [`DerivationPath::from_str`].
+    pub fn derive_priv_str(&self, path: &str) -> Result<Xpriv, Error> {
+        self.derive_priv(&path.parse::<DerivationPath>()?)
+    }
+
+    /// Derives the child key at index `i`, or at the next valid index if it is invalid.
+    ///
+    /// Returns the child key along with the indices skipped, see [`Skipped`].
+    pub fn ckd_priv_next_valid(&self, i: ChildNumber) -> Result<(Xpriv, Vec<Skipped>), Error> {
+        next_valid(i, |i| self.ckd_priv(i))
+    }
+
     /// Private->Private child key derivation
     fn ckd_priv(&self, i: ChildNumber) -> Result<Xpriv, Error> {
-        let mut hmac_engine: HmacEngine<sha512::Hash> = HmacEngine::new(&self.chain_code[..]);
+        let mut hmac_engine = HmacSha512Engine::new(&self.chain_code[..]);
         match i {
             ChildNumber::Normal { .. } => {
                 // Non-hardened key: compute public data and use that
@@ -716,14 +823,11 @@ impl Xpriv {
         hmac_engine.input(&u32::from(i).to_be_bytes());
         let hmac_result: Hmac<sha512::Hash> = Hmac::from_engine(hmac_engine);
 
-        let sk = k256::SecretKey::from_slice(&hmac_result[..32])
-            .expect("statistically impossible to hit");
-
-        let tweaked = add_tweak(
-            sk,
-            Scalar::try_from(&self.private_key.0).expect("should be a valid secret key"),
-        )
-        .expect("statistically impossible to hit");
+        let tweak = Tweak::from_bip32_il(&hmac_result[..32].try_into().expect("half of hmac"))
+            .map_err(|_| Error::InvalidChildKey(i))?;
+        let sk = k256::SecretKey::from_slice(&self.private_key.0)
+            .expect("should be a valid secret key");
+        let tweaked = add_tweak(sk, tweak).map_err(|_| Error::InvalidChildKey(i))?;
 
         let private_key = XPrivateKey::from_secret_key(&tweaked);
 
@@ -830,36 +934,60 @@ impl Xpub {
         Ok(pk)
     }
 
+    /// Attempts to derive an extended public key from a path string such as `m/0/1`.
+    ///
+    /// The path is parsed like [`DerivationPath::from_str`].
+    pub fn derive_pub_str(&self, path: &str) -> Result<Xpub, Error> {
+        self.derive_pub(&path.parse::<DerivationPath>()?)
+    }
+
     /// Compute the scalar tweak added to this key to get a child key
     pub fn ckd_pub_tweak(&self, i: ChildNumber) -> Result<(k256::SecretKey, ChainCode), Error> {
         match i {
             ChildNumber::Hardened { .. } => Err(Error::CannotDeriveFromHardenedKey),
             ChildNumber::Normal { index: n } => {
-                let mut hmac_engine: HmacEngine<sha512::Hash> =
-                    HmacEngine::new(&self.chain_code[..]);
+                let mut hmac_engine = HmacSha512Engine::new(&self.chain_code[..]);
                 hmac_engine.input(&self.public_key.serialize()[..]);
                 hmac_engine.input(&n.to_be_bytes());
 
                 let hmac_result: Hmac<sha512::Hash> = Hmac::from_engine(hmac_engine);
 
                 let private_key = k256::SecretKey::from_slice(&hmac_result[..32])
-                    .map_err(|_| Error::Secp256k1(CryptoError::InvalidSecretKey))?;
+                    .map_err(|_| Error::InvalidChildKey(i))?;
                 let chain_code = ChainCode::from_hmac(hmac_result);
                 Ok((private_key, chain_code))
             }
         }
     }
 
+    /// Derives the child key at index `i`, or at the next valid index if it is invalid.
+    ///
+    /// Returns the child key along with the indices skipped, see [`Skipped`].
+    pub fn ckd_pub_next_valid(&self, i: ChildNumber) -> Result<(Xpub, Vec<Skipped>), Error> {
+        next_valid(i, |i| self.ckd_pub(i))
+    }
+
     /// Public->Public child key derivation
     pub fn ckd_pub(&self, i: ChildNumber) -> Result<Xpub, Error> {
+        self.ckd_pub_with_fingerprint(i, self.fingerprint())
+    }
+
+    /// Public->Public child key derivation, with the already computed fingerprint of this key.
+    fn ckd_pub_with_fingerprint(
+        &self,
+        i: ChildNumber,
+        fingerprint: Fingerprint,
+    ) -> Result<Xpub, Error> {
         let (sk, chain_code) = self.ckd_pub_tweak(i)?;
-        let tweaked = add_exp_tweak(self.public_key.inner, Scalar::from(&sk))
-            .map_err(|_| Error::Secp256k1(CryptoError::InvalidPublicKey))?;
+        let tweak =
+            Tweak::from_bip32_il(&sk.to_bytes().into()).expect("the secret key is a valid tweak");
+        let tweaked =
+            add_exp_tweak(self.public_key.inner, tweak).map_err(|_| Error::InvalidChildKey(i))?;
 
         Ok(Xpub {
             network: self.network,
             depth: self.depth + 1,
-            parent_fingerprint: self.fingerprint(),
+            parent_fingerprint: fingerprint,
             child_number: i,
             public_key: PublicKey::from(tweaked),
             chain_code,
@@ -931,6 +1059,260 @@ impl Xpub {
     }
 }
 
+/// An [`Xpub`] which keeps the keys derived on the way to its descendants.
+///
+/// Deriving the addresses of a wallet derives the same account and chain keys over and over,
+/// along with their fingerprints. The cache derives each of them once, so every address only
+/// costs a single child derivation, whose base point multiplication uses precomputed tables with
+/// the `std` feature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedXpub {
+    xpub: Xpub,
+    fingerprint: Fingerprint,
+    /// The derived parent keys with their fingerprint, by path from `xpub`.
+    cache: BTreeMap<Vec<ChildNumber>, (Xpub, Fingerprint)>,
+}
+
+impl CachedXpub {
+    /// Creates an empty cache for the descendants of `xpub`.
+    pub fn new(xpub: Xpub) -> Self {
+        CachedXpub {
+            fingerprint: xpub.fingerprint(),
+            xpub,
+            cache: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the key the paths are derived from.
+    pub fn xpub(&self) -> &Xpub {
+        &self.xpub
+    }
+
+    /// Returns the number of cached keys.
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    /// Returns `true` if no key is cached.
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
+    /// Drops the cached keys.
+    pub fn clear(&mut self) {
+        self.cache.clear()
+    }
+
+    /// Derives the key at `path` like [`Xpub::derive_pub`], caching the keys of the parent paths
+    /// of `path`.
+    pub fn derive_pub<P: AsRef<[ChildNumber]>>(&mut self, path: &P) -> Result<Xpub, Error> {
+        match path.as_ref().split_last() {
+            Some((&i, parent)) => {
+                let (parent, fingerprint) = self.parent(parent)?;
+                parent.ckd_pub_with_fingerprint(i, fingerprint)
+            }
+            None => Ok(self.xpub),
+        }
+    }
+
+    /// Returns the key at `path` with its fingerprint, deriving and caching it if needed.
+    fn parent(&mut self, path: &[ChildNumber]) -> Result<(Xpub, Fingerprint), Error> {
+        let (&i, parent) = match path.split_last() {
+            Some(split) => split,
+            None => return Ok((self.xpub, self.fingerprint)),
+        };
+        if let Some(&node) = self.cache.get(path) {
+            return Ok(node);
+        }
+
+        let (parent, fingerprint) = self.parent(parent)?;
+        let key = parent.ckd_pub_with_fingerprint(i, fingerprint)?;
+        let node = (key, key.fingerprint());
+        self.cache.insert(path.to_vec(), node);
+        Ok(node)
+    }
+}
+
+/// The SLIP-132 version of an extended key, which records the script type it is used with.
+///
+/// Many wallets export `ypub`, `zpub`, `Ypub` and `Zpub` keys (`upub`, `vpub`, `Upub` and `Vpub`
+/// on testnets), which are plain BIP32 keys with other version bytes. They can be read with
+/// [`Xpub::from_slip132_str`] and written with [`Xpub::to_slip132_string`], and likewise for
+/// [`Xpriv`].
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[non_exhaustive]
+pub enum SlipKeyVersion {
+    /// `xpub`/`tpub`, the BIP32 version, not tied to a script type.
+    Standard,
+    /// `ypub`/`upub`, for single key P2WPKH nested in P2SH.
+    P2shP2wpkh,
+    /// `zpub`/`vpub`, for single key P2WPKH.
+    P2wpkh,
+    /// `Ypub`/`Upub`, for multisig P2WSH nested in P2SH.
+    P2shP2wsh,
+    /// `Zpub`/`Vpub`, for multisig P2WSH.
+    P2wsh,
+}
+
+impl SlipKeyVersion {
+    /// The versions with their mainnet and testnet public and private version bytes.
+    const VERSION_BYTES: [(SlipKeyVersion, [[u8; 4]; 4]); 5] = [
+        (
+            SlipKeyVersion::Standard,
+            [
+                VERSION_BYTES_MAINNET_PUBLIC,
+                VERSION_BYTES_MAINNET_PRIVATE,
+                VERSION_BYTES_TESTNETS_PUBLIC,
+                VERSION_BYTES_TESTNETS_PRIVATE,
+            ],
+        ),
+        (
+            SlipKeyVersion::P2shP2wpkh,
+            [
+                [0x04, 0x9D, 0x7C, 0xB2],
+                [0x04, 0x9D, 0x78, 0x78],
+                [0x04, 0x4A, 0x52, 0x62],
+                [0x04, 0x4A, 0x4E, 0x28],
+            ],
+        ),
+        (
+            SlipKeyVersion::P2wpkh,
+            [
+                [0x04, 0xB2, 0x47, 0x46],
+                [0x04, 0xB2, 0x43, 0x0C],
+                [0x04, 0x5F, 0x1C, 0xF6],
+                [0x04, 0x5F, 0x18, 0xBC],
+            ],
+        ),
+        (
+            SlipKeyVersion::P2shP2wsh,
+            [
+                [0x02, 0x95, 0xB4, 0x3F],
+                [0x02, 0x95, 0xB0, 0x05],
+                [0x02, 0x42, 0x89, 0xEF],
+                [0x02, 0x42, 0x85, 0xB5],
+            ],
+        ),
+        (
+            SlipKeyVersion::P2wsh,
+            [
+                [0x02, 0xAA, 0x7E, 0xD3],
+                [0x02, 0xAA, 0x7A, 0x99],
+                [0x02, 0x57, 0x54, 0x83],
+                [0x02, 0x57, 0x50, 0x48],
+            ],
+        ),
+    ];
+
+    /// Returns the version bytes of public keys of this version on `network`.
+    pub fn public_version_bytes(self, network: impl Into<NetworkKind>) -> [u8; 4] {
+        self.version_bytes(network.into(), false)
+    }
+
+    /// Returns the version bytes of private keys of this version on `network`.
+    pub fn private_version_bytes(self, network: impl Into<NetworkKind>) -> [u8; 4] {
+        self.version_bytes(network.into(), true)
+    }
+
+    /// Returns the version, the network and whether the key is private for `bytes`, or `None`
+    /// if they aren't SLIP-132 version bytes.
+    pub fn from_version_bytes(bytes: [u8; 4]) -> Option<(SlipKeyVersion, NetworkKind, bool)> {
+        Self::VERSION_BYTES.iter().find_map(|&(version, all)| {
+            let i = all.iter().position(|&b| b == bytes)?;
+            let network = if i < 2 {
+                NetworkKind::Main
+            } else {
+                NetworkKind::Test
+            };
+            Some((version, network, i % 2 == 1))
+        })
+    }
+
+    /// Returns the type of the addresses of the keys of this version, `None` for
+    /// [`SlipKeyVersion::Standard`] keys, which are used with any.
+    ///
+    /// Nested segwit versions are P2SH addresses.
+    pub fn address_type(self) -> Option<AddressType> {
+        match self {
+            SlipKeyVersion::Standard => None,
+            SlipKeyVersion::P2shP2wpkh | SlipKeyVersion::P2shP2wsh => Some(AddressType::P2sh),
+            SlipKeyVersion::P2wpkh => Some(AddressType::P2wpkh),
+            SlipKeyVersion::P2wsh => Some(AddressType::P2wsh),
+        }
+    }
+
+    /// Returns `true` if the keys of this version are used in multisig scripts.
+    pub fn is_multisig(self) -> bool {
+        matches!(self, SlipKeyVersion::P2shP2wsh | SlipKeyVersion::P2wsh)
+    }
+
+    fn version_bytes(self, network: NetworkKind, private: bool) -> [u8; 4] {
+        let (_, all) = Self::VERSION_BYTES
+            .iter()
+            .find(|&&(version, _)| version == self)
+            .expect("every version has version bytes");
+        let network = match network {
+            NetworkKind::Main => 0,
+            NetworkKind::Test => 2,
+        };
+        all[network + usize::from(private)]
+    }
+}
+
+/// Decodes the base58 `inp` as a SLIP-132 key, returning its data with the BIP32 version bytes.
+fn decode_slip132(inp: &str, private: bool) -> Result<([u8; 78], SlipKeyVersion), Error> {
+    let data = base58::decode_check(inp)?;
+    let mut data: [u8; 78] = data
+        .as_slice()
+        .try_into()
+        .map_err(|_| InvalidBase58PayloadLengthError { length: data.len() })?;
+
+    let bytes = [data[0], data[1], data[2], data[3]];
+    let (version, network) = match SlipKeyVersion::from_version_bytes(bytes) {
+        Some((version, network, is_private)) if is_private == private => (version, network),
+        _ => return Err(Error::UnknownVersion(bytes)),
+    };
+    data[..4].copy_from_slice(&SlipKeyVersion::Standard.version_bytes(network, private));
+    Ok((data, version))
+}
+
+/// Encodes the BIP32 `data` in base58 with the SLIP-132 `version`.
+fn encode_slip132(
+    mut data: [u8; 78],
+    network: NetworkKind,
+    version: SlipKeyVersion,
+    private: bool,
+) -> String {
+    data[..4].copy_from_slice(&version.version_bytes(network, private));
+    base58::encode_check(&data)
+}
+
+impl Xpriv {
+    /// Parses an extended private key with any SLIP-132 version, such as a `zprv`.
+    pub fn from_slip132_str(inp: &str) -> Result<(Xpriv, SlipKeyVersion), Error> {
+        let (data, version) = decode_slip132(inp, true)?;
+        Ok((Xpriv::decode(&data)?, version))
+    }
+
+    /// Encodes the key in base58 with the SLIP-132 `version`.
+    pub fn to_slip132_string(&self, version: SlipKeyVersion) -> String {
+        encode_slip132(self.encode(), self.network, version, true)
+    }
+}
+
+impl Xpub {
+    /// Parses an extended public key with any SLIP-132 version, such as a `zpub`.
+    pub fn from_slip132_str(inp: &str) -> Result<(Xpub, SlipKeyVersion), Error> {
+        let (data, version) = decode_slip132(inp, false)?;
+        Ok((Xpub::decode(&data)?, version))
+    }
+
+    /// Encodes the key in base58 with the SLIP-132 `version`.
+    pub fn to_slip132_string(&self, version: SlipKeyVersion) -> String {
+        encode_slip132(self.encode(), self.network, version, false)
+    }
+}
+
 impl fmt::Display for Xpriv {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         base58::encode_check_to_fmt(fmt, &self.encode()[..])
@@ -1045,8 +1427,9 @@ mod tests {
             DerivationPath::from_str("").unwrap()
         );
         assert_eq!(DerivationPath::master(), DerivationPath::default());
+        assert_eq!(DerivationPath::from_str("m"), Ok(DerivationPath::master()));
         assert_eq!(
-            DerivationPath::from_str("m"),
+            DerivationPath::from_str("m/m"),
             Err(Error::InvalidChildNumberFormat)
         );
         assert_eq!(
@@ -1099,6 +1482,60 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_derivation_path_notations() {
+        let path = DerivationPath::from_str("84'/0'/0'").unwrap();
+        for s in ["m/84'/0'/0'", "84h/0h/0h", "m/84h/0'/0h"] {
+            assert_eq!(DerivationPath::from_str(s), Ok(path.clone()));
+        }
+        assert_eq!(path.to_string(), "84'/0'/0'");
+        assert_eq!(format!("{:#}", path), "84h/0h/0h");
+
+        let (path, wildcard) = DerivationPath::from_str_with_wildcard("m/86'/0'/0'/0/*").unwrap();
+        assert_eq!(path, DerivationPath::from_str("86h/0h/0h/0").unwrap());
+        assert_eq!(wildcard, Wildcard::Normal);
+        assert_eq!(
+            wildcard.child_number(7),
+            Ok(Some(ChildNumber::from_normal_idx(7).unwrap()))
+        );
+        assert_eq!(
+            DerivationPath::from_str_with_wildcard("*h"),
+            Ok((DerivationPath::master(), Wildcard::Hardened))
+        );
+        assert_eq!(
+            DerivationPath::from_str_with_wildcard("m/0/1"),
+            Ok((DerivationPath::from_str("0/1").unwrap(), Wildcard::None))
+        );
+        assert_eq!(Wildcard::None.child_number(7), Ok(None));
+        assert_eq!(
+            DerivationPath::from_str_with_wildcard("m/*/0"),
+            Err(Error::InvalidChildNumberFormat)
+        );
+        assert_eq!(
+            DerivationPath::from_str("m/0/*"),
+            Err(Error::InvalidChildNumberFormat)
+        );
+
+        let master = Xpriv::new_master(NetworkKind::Main, &[0x42; 32]).unwrap();
+        assert_eq!(
+            master.derive_priv_str("m/84h/0'/0h"),
+            master.derive_priv(&DerivationPath::from_str("84'/0'/0'").unwrap())
+        );
+        let xpub = Xpub::from_priv(&master);
+        assert_eq!(
+            xpub.derive_pub_str("m/0/1"),
+            xpub.derive_pub(&DerivationPath::from_str("0/1").unwrap())
+        );
+        assert_eq!(
+            xpub.derive_pub_str("m/0h"),
+            Err(Error::CannotDeriveFromHardenedKey)
+        );
+        assert_eq!(
+            master.derive_priv_str("m/0/x"),
+            Err(Error::InvalidChildNumberFormat)
+        );
+    }
+
     #[test]
     fn test_derivation_path_conversion_index() {
         let path = DerivationPath::from_str("0h/1/2'").unwrap();
@@ -1411,4 +1848,172 @@ mod tests {
         let xpriv_str = "xprv9s21ZrQH143K24Mfq5zL5MhWK9hUhhGbd45hLXo2Pq2oqzMMo63oStZzFAzHGBP2UuGCqWLTAPLcMtD9y5gkZ6Eq3Rjuahrv17fENZ3QzxW";
         Xpriv::from_str(xpriv_str).unwrap();
     }
+
+    #[test]
+    fn next_valid_skips_invalid_child_keys() {
+        let invalid = [ChildNumber::Normal { index: 3 }, ChildNumber::Normal { index: 4 }];
+        let derive = |i| if invalid.contains(&i) { Err(Error::InvalidChildKey(i)) } else { Ok(i) };
+
+        let first = ChildNumber::Normal { index: 2 };
+        assert_eq!(next_valid(first, derive), Ok((first, vec![])));
+        assert_eq!(
+            next_valid(invalid[0], derive),
+            Ok((ChildNumber::Normal { index: 5 }, vec![Skipped(invalid[0]), Skipped(invalid[1])]))
+        );
+
+        // Other errors aren't skipped.
+        let hardened = ChildNumber::Hardened { index: 0 };
+        assert_eq!(
+            next_valid(hardened, |_| Err::<(), _>(Error::CannotDeriveFromHardenedKey)),
+            Err(Error::CannotDeriveFromHardenedKey)
+        );
+
+        // Neither is the last index.
+        let last = ChildNumber::Normal { index: (1 << 31) - 1 };
+        assert_eq!(
+            next_valid(last, |i| Err::<(), _>(Error::InvalidChildKey(i))),
+            Err(Error::InvalidChildNumber(1 << 31))
+        );
+
+        let xpriv = Xpriv::new_master(NetworkKind::Main, &[0; 32]).unwrap();
+        let (child, skipped) = xpriv.ckd_priv_next_valid(first).unwrap();
+        assert_eq!(child, xpriv.ckd_priv(first).unwrap());
+        assert!(skipped.is_empty());
+        let xpub = Xpub::from_priv(&xpriv);
+        assert_eq!(xpub.ckd_pub_next_valid(first).unwrap(), (Xpub::from_priv(&child), vec![]));
+    }
+
+    #[test]
+    fn slip132_versions() {
+        // BIP84 vectors, for the seed of "abandon abandon ... about".
+        let seed = hex!("5eb00bbddcf069084889a8ab9155568165f5c453ccb85e70811aaed6f6da5fc19a5ac40b389cd370d086206dec8aa6c43daea6690f20ad3d8d48b2d2ce9e38e4");
+        let zprv = "zprvAWgYBBk7JR8Gjrh4UJQ2uJdG1r3WNRRfURiABBE3RvMXYSrRJL62XuezvGdPvG6GFBZduosCc1YP5wixPox7zhZLfiUm8aunE96BBa4Kei5";
+        let zpub = "zpub6rFR7y4Q2AijBEqTUquhVz398htDFrtymD9xYYfG1m4wAcvPhXNfE3EfH1r1ADqtfSdVCToUG868RvUUkgDKf31mGDtKsAYz2oz2AGutZYs";
+
+        let master = Xpriv::new_master(NetworkKind::Main, &seed).unwrap();
+        assert_eq!(
+            Xpriv::from_slip132_str(zprv),
+            Ok((master, SlipKeyVersion::P2wpkh))
+        );
+        assert_eq!(master.to_slip132_string(SlipKeyVersion::P2wpkh), zprv);
+        assert_eq!(
+            master.to_slip132_string(SlipKeyVersion::Standard),
+            master.to_string()
+        );
+
+        let account = Xpub::from_priv(&master.derive_priv_str("m/84h/0h/0h").unwrap());
+        assert_eq!(
+            Xpub::from_slip132_str(zpub),
+            Ok((account, SlipKeyVersion::P2wpkh))
+        );
+        assert_eq!(account.to_slip132_string(SlipKeyVersion::P2wpkh), zpub);
+        assert_eq!(
+            Xpub::from_str(zpub),
+            Err(Error::UnknownVersion([0x04, 0xB2, 0x47, 0x46]))
+        );
+        assert_eq!(
+            Xpriv::from_slip132_str(zpub),
+            Err(Error::UnknownVersion([0x04, 0xB2, 0x47, 0x46]))
+        );
+
+        let testnet = Xpriv::new_master(NetworkKind::Test, &seed).unwrap();
+        for (version, prefix) in [
+            (SlipKeyVersion::Standard, "tpub"),
+            (SlipKeyVersion::P2shP2wpkh, "upub"),
+            (SlipKeyVersion::P2wpkh, "vpub"),
+            (SlipKeyVersion::P2shP2wsh, "Upub"),
+            (SlipKeyVersion::P2wsh, "Vpub"),
+        ] {
+            let s = Xpub::from_priv(&testnet).to_slip132_string(version);
+            assert!(s.starts_with(prefix));
+            assert_eq!(
+                Xpub::from_slip132_str(&s),
+                Ok((Xpub::from_priv(&testnet), version))
+            );
+        }
+        for (version, prefix) in [
+            (SlipKeyVersion::P2shP2wpkh, "ypub"),
+            (SlipKeyVersion::P2shP2wsh, "Ypub"),
+            (SlipKeyVersion::P2wsh, "Zpub"),
+        ] {
+            assert!(account.to_slip132_string(version).starts_with(prefix));
+        }
+
+        assert_eq!(
+            SlipKeyVersion::P2shP2wsh.address_type(),
+            Some(AddressType::P2sh)
+        );
+        assert_eq!(SlipKeyVersion::Standard.address_type(), None);
+        assert!(SlipKeyVersion::P2wsh.is_multisig());
+        assert!(!SlipKeyVersion::P2wpkh.is_multisig());
+    }
+
+    #[test]
+    fn cached_xpub() {
+        let seed = hex!("000102030405060708090a0b0c0d0e0f");
+        let xpub = Xpub::from_priv(&Xpriv::new_master(NetworkKind::Main, &seed).unwrap());
+        let mut cached = CachedXpub::new(xpub);
+        assert_eq!(cached.derive_pub(&DerivationPath::master()), Ok(xpub));
+        assert!(cached.is_empty());
+
+        for path in ["m/0/0", "m/0/1", "m/1/5", "m/0/1/2", "m/0/1"] {
+            let path = DerivationPath::from_str(path).unwrap();
+            assert_eq!(cached.derive_pub(&path), xpub.derive_pub(&path));
+        }
+        // m/0, m/1 and m/0/1.
+        assert_eq!(cached.len(), 3);
+        assert_eq!(cached.xpub(), &xpub);
+
+        assert_eq!(
+            cached.derive_pub(&DerivationPath::from_str("m/2/0h").unwrap()),
+            Err(Error::CannotDeriveFromHardenedKey)
+        );
+        assert_eq!(
+            cached.derive_pub(&DerivationPath::from_str("m/0h/0").unwrap()),
+            Err(Error::CannotDeriveFromHardenedKey)
+        );
+        assert_eq!(cached.len(), 4);
+        cached.clear();
+        assert!(cached.is_empty());
+    }
+}
+
+#[cfg(bench)]
+mod benches {
+    use test::{black_box, Bencher};
+
+    use super::*;
+
+    /// Returns the paths of the first 16 receiving addresses of an account key, `m/0/i`.
+    fn receiving_paths() -> Vec<DerivationPath> {
+        (0..16)
+            .map(|i| DerivationPath::from_str(&format!("m/0/{}", i)).unwrap())
+            .collect()
+    }
+
+    fn account() -> Xpub {
+        Xpub::from_priv(&Xpriv::new_master(NetworkKind::Main, &[0x42; 16]).unwrap())
+    }
+
+    #[bench]
+    pub fn derive_pub_16(bh: &mut Bencher) {
+        let (xpub, paths) = (account(), receiving_paths());
+        bh.iter(|| {
+            for path in &paths {
+                black_box(xpub.derive_pub(path)).unwrap();
+            }
+        });
+    }
+
+    /// The cached counterpart of [`derive_pub_16`], deriving `m/0` once.
+    #[bench]
+    pub fn cached_derive_pub_16(bh: &mut Bencher) {
+        let (xpub, paths) = (account(), receiving_paths());
+        bh.iter(|| {
+            let mut cached = CachedXpub::new(xpub);
+            for path in &paths {
+                black_box(cached.derive_pub(path)).unwrap();
+            }
+        });
+    }
 }
@@ -0,0 +1,350 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Block analytics.
+//!
+//! This module consumes a sequence of blocks and aggregates statistics over the transactions
+//! they contain: a fee rate histogram, the distribution of output script types and input spend
+//! types, and the adoption of segwit and taproot.
+//!
+//! Blocks do not carry the values of the outputs their transactions spend, so [`BlockStats`]
+//! keeps track of the outputs created by the blocks it has seen. The fee of a transaction
+//! spending an output created before the first block cannot be computed and the transaction is
+//! only counted in [`BlockStats::unknown_fee_transactions`]. To get fee rates for every
+//! transaction, start from the genesis block.
+//!
+//! All statistics are plain data and can be serialized when the `serde` feature is enabled.
+//!
+
+use core::borrow::Borrow;
+
+use crate::blockdata::block::Block;
+use crate::blockdata::graph::ScriptType;
+use crate::blockdata::savings::SpendType;
+use crate::blockdata::transaction::{OutPoint, Transaction};
+use crate::prelude::*;
+use crate::{Amount, FeeRate};
+
+/// The default upper bounds of the buckets of a [`FeeRateHistogram`], in sat/vB.
+const DEFAULT_BUCKETS: [u32; 16] = [1, 2, 3, 4, 5, 6, 8, 10, 12, 15, 20, 30, 50, 100, 200, 500];
+
+/// A histogram of transaction fee rates.
+///
+/// Bucket `i` counts the fee rates in `bounds[i - 1]..bounds[i]`, the first bucket starting at
+/// zero and the last one, past the last bound, being unbounded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[cfg_attr(feature = "serde", serde(crate = "actual_serde"))]
+pub struct FeeRateHistogram {
+    bounds: Vec<FeeRate>,
+    counts: Vec<u64>,
+}
+
+impl FeeRateHistogram {
+    /// Creates an empty histogram with the given increasing bucket upper bounds.
+    ///
+    /// # Panics
+    ///
+    /// If `bounds` is not strictly increasing.
+    pub fn new(bounds: Vec<FeeRate>) -> Self {
+        assert!(bounds.windows(2).all(|w| w[0] < w[1]), "bucket bounds must be increasing");
+        let counts = vec![0; bounds.len() + 1];
+        FeeRateHistogram { bounds, counts }
+    }
+
+    /// Records a transaction paying `fee_rate`.
+    pub fn add(&mut self, fee_rate: FeeRate) {
+        let bucket = self.bounds.partition_point(|&bound| bound <= fee_rate);
+        self.counts[bucket] += 1;
+    }
+
+    /// Returns the upper bounds of the buckets.
+    pub fn bounds(&self) -> &[FeeRate] { &self.bounds }
+
+    /// Returns the number of fee rates in each bucket, one more than there are bounds.
+    pub fn counts(&self) -> &[u64] { &self.counts }
+
+    /// Returns the number of fee rates recorded.
+    pub fn total(&self) -> u64 { self.counts.iter().sum() }
+
+    /// Returns the buckets as `(lower bound, upper bound, count)`, the last one being unbounded.
+    pub fn buckets(&self) -> impl Iterator<Item = (FeeRate, Option<FeeRate>, u64)> + '_ {
+        self.counts.iter().enumerate().map(move |(i, &count)| {
+            let lower = if i == 0 { FeeRate::ZERO } else { self.bounds[i - 1] };
+            (lower, self.bounds.get(i).copied(), count)
+        })
+    }
+}
+
+impl Default for FeeRateHistogram {
+    fn default() -> Self {
+        FeeRateHistogram::new(
+            DEFAULT_BUCKETS.iter().map(|&sat_vb| FeeRate::from_sat_per_vb_u32(sat_vb)).collect(),
+        )
+    }
+}
+
+/// The number of inputs of each spend type, see [`SpendType`].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[cfg_attr(feature = "serde", serde(crate = "actual_serde"))]
+pub struct SpendTypeCounts {
+    /// Spends of P2PKH outputs.
+    pub p2pkh: u64,
+    /// Spends of P2WPKH outputs, possibly nested in P2SH.
+    pub p2wpkh: u64,
+    /// Spends of `OP_CHECKMULTISIG` scripts.
+    pub multisig: u64,
+    /// Taproot key-path spends.
+    pub taproot_key_path: u64,
+    /// Taproot script-path spends.
+    pub taproot_script_path: u64,
+    /// Spends of any other kind.
+    pub unknown: u64,
+}
+
+impl SpendTypeCounts {
+    fn add(&mut self, spend_type: SpendType) {
+        let count = match spend_type {
+            SpendType::P2pkh => &mut self.p2pkh,
+            SpendType::P2wpkh { .. } => &mut self.p2wpkh,
+            SpendType::Multisig { .. } => &mut self.multisig,
+            SpendType::TaprootKeyPath => &mut self.taproot_key_path,
+            SpendType::TaprootScriptPath => &mut self.taproot_script_path,
+            SpendType::Unknown => &mut self.unknown,
+        };
+        *count += 1;
+    }
+}
+
+/// Statistics aggregated over a sequence of blocks.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[cfg_attr(feature = "serde", serde(crate = "actual_serde"))]
+pub struct BlockStats {
+    /// The number of blocks seen.
+    pub blocks: u64,
+    /// The number of transactions seen, coinbase transactions included.
+    pub transactions: u64,
+    /// The number of non-coinbase transactions with at least one witness.
+    pub segwit_transactions: u64,
+    /// The number of non-coinbase transactions with at least one taproot input.
+    pub taproot_transactions: u64,
+    /// The number of non-coinbase transactions whose fee could not be computed.
+    pub unknown_fee_transactions: u64,
+    /// The total fees of the transactions whose fee could be computed.
+    pub total_fees: Amount,
+    /// The fee rates of the transactions whose fee could be computed.
+    pub fee_rates: FeeRateHistogram,
+    /// The number of outputs of each script type.
+    pub output_types: BTreeMap<ScriptType, u64>,
+    /// The number of inputs of each spend type.
+    pub spend_types: SpendTypeCounts,
+    /// The values of the unspent outputs created by the blocks seen so far.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    utxos: BTreeMap<OutPoint, Amount>,
+}
+
+impl BlockStats {
+    /// Creates empty statistics using the default fee rate buckets.
+    pub fn new() -> Self { BlockStats::default() }
+
+    /// Creates empty statistics using the fee rate buckets of `histogram`.
+    pub fn with_fee_rate_histogram(histogram: FeeRateHistogram) -> Self {
+        BlockStats { fee_rates: histogram, ..BlockStats::default() }
+    }
+
+    /// Aggregates the statistics of `blocks`, in chain order.
+    pub fn from_blocks<B: Borrow<Block>, I: IntoIterator<Item = B>>(blocks: I) -> Self {
+        let mut stats = BlockStats::new();
+        for block in blocks {
+            stats.add_block(block.borrow());
+        }
+        stats
+    }
+
+    /// Adds the transactions of `block`, which must follow the blocks added so far.
+    pub fn add_block(&mut self, block: &Block) {
+        self.blocks += 1;
+        for tx in &block.txdata {
+            self.add_transaction(tx);
+        }
+    }
+
+    fn add_transaction(&mut self, tx: &Transaction) {
+        self.transactions += 1;
+
+        if !tx.is_coinbase() {
+            let mut spent = Some(Amount::ZERO);
+            let mut taproot = false;
+            for txin in &tx.input {
+                let value = self.utxos.remove(&txin.previous_output);
+                spent = spent.zip(value).and_then(|(spent, value)| spent.checked_add(value));
+                let spend_type = SpendType::from_txin(txin);
+                taproot |=
+                    matches!(spend_type, SpendType::TaprootKeyPath | SpendType::TaprootScriptPath);
+                self.spend_types.add(spend_type);
+            }
+
+            if tx.input.iter().any(|txin| !txin.witness.is_empty()) {
+                self.segwit_transactions += 1;
+            }
+            if taproot {
+                self.taproot_transactions += 1;
+            }
+
+            let created =
+                tx.output.iter().try_fold(Amount::ZERO, |sum, txout| sum.checked_add(txout.value));
+            match spent.zip(created).and_then(|(spent, created)| spent.checked_sub(created)) {
+                Some(fee) => {
+                    self.total_fees = self.total_fees.checked_add(fee).unwrap_or(Amount::MAX);
+                    self.fee_rates
+                        .add(fee.div_by_weight_floor(tx.weight()).unwrap_or(FeeRate::MAX));
+                }
+                None => self.unknown_fee_transactions += 1,
+            }
+        }
+
+        let txid = tx.compute_txid();
+        for (vout, txout) in tx.output.iter().enumerate() {
+            let script_type = ScriptType::from_script(&txout.script_pubkey);
+            *self.output_types.entry(script_type).or_insert(0) += 1;
+            if script_type != ScriptType::OpReturn {
+                self.utxos.insert(OutPoint { txid, vout: vout as u32 }, txout.value);
+            }
+        }
+    }
+
+    /// Returns the share of non-coinbase transactions spending at least one segwit input.
+    pub fn segwit_adoption(&self) -> f64 {
+        self.segwit_transactions as f64 / self.non_coinbase_transactions().max(1) as f64
+    }
+
+    /// Returns the share of non-coinbase transactions spending at least one taproot input.
+    pub fn taproot_adoption(&self) -> f64 {
+        self.taproot_transactions as f64 / self.non_coinbase_transactions().max(1) as f64
+    }
+
+    /// Returns the number of transactions seen, excluding one coinbase transaction per block.
+    pub fn non_coinbase_transactions(&self) -> u64 { self.transactions.saturating_sub(self.blocks) }
+}
+
+#[cfg(test)]
+mod tests {
+    use hashes::Hash;
+
+    use super::*;
+    use crate::blockdata::block::{Header, Version};
+    use crate::blockdata::locktime::absolute;
+    use crate::blockdata::script::ScriptBuf;
+    use crate::blockdata::transaction::{self, TxIn, TxOut, Txid};
+    use crate::blockdata::witness::Witness;
+    use crate::{BlockHash, CompactTarget, TxMerkleNode};
+
+    fn tx(input: Vec<TxIn>, output: Vec<TxOut>) -> Transaction {
+        Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input,
+            output,
+        }
+    }
+
+    fn txout(sat: u64, script_pubkey: ScriptBuf) -> TxOut {
+        TxOut { value: Amount::from_sat(sat), script_pubkey }
+    }
+
+    fn p2tr() -> ScriptBuf {
+        let mut bytes = vec![0x51, 0x20];
+        bytes.extend_from_slice(&[0x42; 32]);
+        ScriptBuf::from_bytes(bytes)
+    }
+
+    fn block(txdata: Vec<Transaction>) -> Block {
+        let header = Header {
+            version: Version::TWO,
+            prev_blockhash: BlockHash::all_zeros(),
+            merkle_root: TxMerkleNode::all_zeros(),
+            time: 0,
+            bits: CompactTarget::from_consensus(0x207fffff),
+            nonce: 0,
+        };
+        Block { header, txdata }
+    }
+
+    fn coinbase(output: Vec<TxOut>) -> Transaction {
+        tx(vec![TxIn { previous_output: OutPoint::null(), ..TxIn::default() }], output)
+    }
+
+    #[test]
+    fn block_stats() {
+        let funding =
+            coinbase(vec![txout(100_000, p2tr()), txout(0, ScriptBuf::new_op_return([]))]);
+        let funding_txid = funding.compute_txid();
+
+        // Spends the taproot coinbase output using the key path.
+        let taproot_spend = tx(
+            vec![TxIn {
+                previous_output: OutPoint { txid: funding_txid, vout: 0 },
+                witness: Witness::from_slice(&[[1u8; 64]]),
+                ..TxIn::default()
+            }],
+            vec![txout(90_000, p2tr())],
+        );
+        // Spends an output created before the first block.
+        let unknown = tx(
+            vec![TxIn {
+                previous_output: OutPoint { txid: Txid::all_zeros(), vout: 7 },
+                ..TxIn::default()
+            }],
+            vec![txout(1_000, ScriptBuf::new_p2pkh(&hashes::hash160::Hash::all_zeros().into()))],
+        );
+        let fee_rate =
+            Amount::from_sat(10_000).div_by_weight_floor(taproot_spend.weight()).unwrap();
+
+        let blocks = [block(vec![funding]), block(vec![coinbase(vec![]), taproot_spend, unknown])];
+        let stats = BlockStats::from_blocks(&blocks);
+
+        assert_eq!(stats.blocks, 2);
+        assert_eq!(stats.transactions, 4);
+        assert_eq!(stats.non_coinbase_transactions(), 2);
+        assert_eq!(stats.segwit_transactions, 1);
+        assert_eq!(stats.taproot_transactions, 1);
+        assert_eq!(stats.taproot_adoption(), 0.5);
+        assert_eq!(stats.unknown_fee_transactions, 1);
+        assert_eq!(stats.total_fees, Amount::from_sat(10_000));
+        assert_eq!(stats.fee_rates.total(), 1);
+        let (lower, upper, _) =
+            stats.fee_rates.buckets().find(|&(_, _, count)| count == 1).unwrap();
+        assert!(lower <= fee_rate && upper.is_none_or(|upper| fee_rate < upper));
+
+        assert_eq!(stats.output_types[&ScriptType::P2tr], 2);
+        assert_eq!(stats.output_types[&ScriptType::OpReturn], 1);
+        assert_eq!(stats.output_types[&ScriptType::P2pkh], 1);
+        assert_eq!(stats.spend_types.taproot_key_path, 1);
+        assert_eq!(stats.spend_types.unknown, 1);
+
+        // Stats built by hand may count blocks without their coinbase transaction.
+        let stats = BlockStats { blocks: 1, ..BlockStats::new() };
+        assert_eq!(stats.non_coinbase_transactions(), 0);
+        assert_eq!(stats.taproot_adoption(), 0.0);
+    }
+
+    #[test]
+    fn fee_rate_histogram() {
+        let sat_vb = FeeRate::from_sat_per_vb_u32;
+        let mut histogram = FeeRateHistogram::new(vec![sat_vb(1), sat_vb(10)]);
+        for fee_rate in [FeeRate::ZERO, sat_vb(1), sat_vb(5), sat_vb(10), sat_vb(1000)] {
+            histogram.add(fee_rate);
+        }
+        assert_eq!(histogram.counts(), [1, 2, 2]);
+        assert_eq!(histogram.total(), 5);
+        assert_eq!(
+            histogram.buckets().collect::<Vec<_>>(),
+            [
+                (FeeRate::ZERO, Some(sat_vb(1)), 1),
+                (sat_vb(1), Some(sat_vb(10)), 2),
+                (sat_vb(10), None, 2)
+            ]
+        );
+    }
+}
@@ -0,0 +1,328 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Transaction graphs.
+//!
+//! This module builds a spend graph over a set of transactions. Nodes are transactions and the
+//! outputs they create, edges connect a transaction to each output it creates and an output to
+//! the transaction spending it. Outputs spent from outside the set show up as external nodes
+//! with no known value.
+//!
+//! The graph can be exported in Graphviz DOT format using [`TxGraph::write_dot`], or serialized
+//! as structured JSON when the `serde` feature is enabled.
+//!
+
+use core::fmt;
+
+use crate::blockdata::script::Script;
+use crate::blockdata::transaction::{OutPoint, Transaction, Txid};
+use crate::prelude::*;
+use crate::{Amount, Weight};
+
+/// A spend graph over a set of transactions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[cfg_attr(feature = "serde", serde(crate = "actual_serde"))]
+pub struct TxGraph {
+    transactions: Vec<TxNode>,
+    outputs: Vec<OutputNode>,
+    edges: Vec<Edge>,
+}
+
+/// A transaction node in a [`TxGraph`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[cfg_attr(feature = "serde", serde(crate = "actual_serde"))]
+pub struct TxNode {
+    /// The transaction's txid.
+    pub txid: Txid,
+    /// The weight of the transaction.
+    pub weight: Weight,
+    /// The number of inputs of the transaction.
+    pub input_count: usize,
+    /// The number of outputs of the transaction.
+    pub output_count: usize,
+}
+
+/// An output node in a [`TxGraph`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[cfg_attr(feature = "serde", serde(crate = "actual_serde"))]
+pub struct OutputNode {
+    /// The outpoint identifying this output.
+    pub outpoint: OutPoint,
+    /// The value of the output, `None` if the output was created outside the graph.
+    pub value: Option<Amount>,
+    /// The type of the output script, `None` if the output was created outside the graph.
+    pub script_type: Option<ScriptType>,
+}
+
+impl OutputNode {
+    /// Returns true if the output was not created by any transaction in the graph.
+    pub fn is_external(&self) -> bool { self.value.is_none() }
+}
+
+/// An edge in a [`TxGraph`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[cfg_attr(feature = "serde", serde(crate = "actual_serde"))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Edge {
+    /// The transaction `txid` created the output `outpoint`.
+    Creates {
+        /// The creating transaction.
+        txid: Txid,
+        /// The created output.
+        outpoint: OutPoint,
+    },
+    /// The output `outpoint` is spent by input `input_index` of transaction `txid`.
+    Spends {
+        /// The spent output.
+        outpoint: OutPoint,
+        /// The spending transaction.
+        txid: Txid,
+        /// The index of the spending input.
+        input_index: usize,
+    },
+}
+
+/// The type of an output script, as used to annotate a [`TxGraph`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[cfg_attr(feature = "serde", serde(crate = "actual_serde"))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum ScriptType {
+    /// Pay to public key.
+    P2pk,
+    /// Pay to public key hash.
+    P2pkh,
+    /// Pay to script hash.
+    P2sh,
+    /// Pay to witness public key hash.
+    P2wpkh,
+    /// Pay to witness script hash.
+    P2wsh,
+    /// Pay to taproot.
+    P2tr,
+    /// Bare multisig.
+    Multisig,
+    /// Provably unspendable `OP_RETURN` output.
+    OpReturn,
+    /// A witness program of a version not otherwise covered.
+    WitnessProgram,
+    /// Anything else.
+    NonStandard,
+}
+
+impl ScriptType {
+    /// Classifies `script` as one of the known script types.
+    pub fn from_script(script: &Script) -> Self {
+        if script.is_p2pkh() {
+            ScriptType::P2pkh
+        } else if script.is_p2sh() {
+            ScriptType::P2sh
+        } else if script.is_p2wpkh() {
+            ScriptType::P2wpkh
+        } else if script.is_p2wsh() {
+            ScriptType::P2wsh
+        } else if script.is_p2tr() {
+            ScriptType::P2tr
+        } else if script.is_witness_program() {
+            ScriptType::WitnessProgram
+        } else if script.is_p2pk() {
+            ScriptType::P2pk
+        } else if script.is_multisig() {
+            ScriptType::Multisig
+        } else if script.is_op_return() {
+            ScriptType::OpReturn
+        } else {
+            ScriptType::NonStandard
+        }
+    }
+}
+
+impl fmt::Display for ScriptType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use ScriptType::*;
+
+        let s = match *self {
+            P2pk => "p2pk",
+            P2pkh => "p2pkh",
+            P2sh => "p2sh",
+            P2wpkh => "p2wpkh",
+            P2wsh => "p2wsh",
+            P2tr => "p2tr",
+            Multisig => "multisig",
+            OpReturn => "op_return",
+            WitnessProgram => "witness_program",
+            NonStandard => "nonstandard",
+        };
+        f.write_str(s)
+    }
+}
+
+impl TxGraph {
+    /// Builds the spend graph of `txs`.
+    ///
+    /// Coinbase inputs do not spend any output and are not part of the graph.
+    pub fn new<'a, I: IntoIterator<Item = &'a Transaction>>(txs: I) -> Self {
+        let txs = txs.into_iter().collect::<Vec<_>>();
+        let mut graph = TxGraph { transactions: vec![], outputs: vec![], edges: vec![] };
+        let mut known = BTreeSet::new();
+
+        for tx in &txs {
+            let txid = tx.compute_txid();
+            graph.transactions.push(TxNode {
+                txid,
+                weight: tx.weight(),
+                input_count: tx.input.len(),
+                output_count: tx.output.len(),
+            });
+            for (vout, txout) in tx.output.iter().enumerate() {
+                let outpoint = OutPoint { txid, vout: vout as u32 };
+                graph.outputs.push(OutputNode {
+                    outpoint,
+                    value: Some(txout.value),
+                    script_type: Some(ScriptType::from_script(&txout.script_pubkey)),
+                });
+                graph.edges.push(Edge::Creates { txid, outpoint });
+                known.insert(outpoint);
+            }
+        }
+
+        for tx in txs.iter().filter(|tx| !tx.is_coinbase()) {
+            let txid = tx.compute_txid();
+            for (input_index, txin) in tx.input.iter().enumerate() {
+                let outpoint = txin.previous_output;
+                if known.insert(outpoint) {
+                    graph.outputs.push(OutputNode { outpoint, value: None, script_type: None });
+                }
+                graph.edges.push(Edge::Spends { outpoint, txid, input_index });
+            }
+        }
+
+        graph
+    }
+
+    /// Returns the transaction nodes, in the order the transactions were given.
+    pub fn transactions(&self) -> &[TxNode] { &self.transactions }
+
+    /// Returns the output nodes, including external outputs spent by the graph.
+    pub fn outputs(&self) -> &[OutputNode] { &self.outputs }
+
+    /// Returns the edges of the graph.
+    pub fn edges(&self) -> &[Edge] { &self.edges }
+
+    /// Writes the graph in Graphviz DOT format to `w`.
+    pub fn write_dot<W: fmt::Write + ?Sized>(&self, w: &mut W) -> fmt::Result {
+        writeln!(w, "digraph transactions {{")?;
+        writeln!(w, "    rankdir=LR;")?;
+        for tx in &self.transactions {
+            writeln!(
+                w,
+                "    \"tx:{}\" [shape=box, label=\"{}\\nweight: {}\"];",
+                tx.txid, tx.txid, tx.weight
+            )?;
+        }
+        for out in &self.outputs {
+            match (out.value, out.script_type) {
+                (Some(value), Some(script_type)) => writeln!(
+                    w,
+                    "    \"out:{}\" [shape=ellipse, label=\"{}\\n{}\\n{}\"];",
+                    out.outpoint, out.outpoint.vout, value, script_type
+                )?,
+                _ => writeln!(
+                    w,
+                    "    \"out:{}\" [shape=ellipse, style=dashed, label=\"{}\"];",
+                    out.outpoint, out.outpoint
+                )?,
+            }
+        }
+        for edge in &self.edges {
+            match *edge {
+                Edge::Creates { txid, outpoint } =>
+                    writeln!(w, "    \"tx:{}\" -> \"out:{}\";", txid, outpoint)?,
+                Edge::Spends { outpoint, txid, input_index } => writeln!(
+                    w,
+                    "    \"out:{}\" -> \"tx:{}\" [label=\"input {}\"];",
+                    outpoint, txid, input_index
+                )?,
+            }
+        }
+        writeln!(w, "}}")
+    }
+
+    /// Returns the graph in Graphviz DOT format.
+    pub fn to_dot(&self) -> String {
+        let mut s = String::new();
+        self.write_dot(&mut s).expect("writing to a string never fails");
+        s
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hex::test_hex_unwrap as hex;
+
+    use super::*;
+    use crate::blockdata::transaction::{TxIn, TxOut};
+    use crate::consensus::deserialize;
+    use crate::locktime::absolute;
+    use crate::transaction::Version;
+    use crate::ScriptBuf;
+
+    const SOME_TX: &str = "0100000001a15d57094aa7a21a28cb20b59aab8fc7d1149a3bdbcddba9c622e4f5f6a99ece010000006c493046022100f93bb0e7d8db7bd46e40132d1f8242026e045f03a0efe71bbb8e3f475e970d790221009337cd7f1f929f00cc6ff01f03729b069a7c21b59b1736ddfee5db5946c5da8c0121033b9b137ee87d5a812d6f506efdd37f0affa7ffc310711c06c7f3e097c9447c52ffffffff0100e1f505000000001976a9140389035a9225b3839e2bbf32d826a1e222031fd888ac00000000";
+
+    fn parent_and_child() -> (Transaction, Transaction) {
+        let parent: Transaction = deserialize(&hex!(SOME_TX)).unwrap();
+        let child = Transaction {
+            version: Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint { txid: parent.compute_txid(), vout: 0 },
+                ..Default::default()
+            }],
+            output: vec![TxOut {
+                value: Amount::from_sat(99_000_000),
+                script_pubkey: ScriptBuf::new_op_return([0u8; 4]),
+            }],
+        };
+        (parent, child)
+    }
+
+    #[test]
+    fn graph_links_spends() {
+        let (parent, child) = parent_and_child();
+        let graph = TxGraph::new([&parent, &child]);
+
+        assert_eq!(graph.transactions().len(), 2);
+        // Two created outputs and the parent's external input.
+        assert_eq!(graph.outputs().len(), 3);
+        assert_eq!(graph.outputs().iter().filter(|o| o.is_external()).count(), 1);
+        assert_eq!(graph.outputs()[0].script_type, Some(ScriptType::P2pkh));
+        assert_eq!(graph.outputs()[1].script_type, Some(ScriptType::OpReturn));
+
+        let spend = Edge::Spends {
+            outpoint: OutPoint { txid: parent.compute_txid(), vout: 0 },
+            txid: child.compute_txid(),
+            input_index: 0,
+        };
+        assert!(graph.edges().contains(&spend));
+        assert_eq!(graph.edges().len(), 4);
+    }
+
+    #[test]
+    fn graph_to_dot() {
+        let (parent, child) = parent_and_child();
+        let dot = TxGraph::new([&parent, &child]).to_dot();
+
+        assert!(dot.starts_with("digraph transactions {"));
+        assert!(dot.ends_with("}\n"));
+        assert!(dot.contains(&format!(
+            "\"out:{}:0\" -> \"tx:{}\" [label=\"input 0\"];",
+            parent.compute_txid(),
+            child.compute_txid()
+        )));
+        assert!(dot.contains("p2pkh"));
+        assert!(dot.contains("style=dashed"));
+    }
+}
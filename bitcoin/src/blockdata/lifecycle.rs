@@ -0,0 +1,345 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Transaction lifecycle.
+//!
+//! This module tracks how far along a transaction is on its way to the network in the type
+//! system. A [`Tx`] starts out [`Unsigned`], becomes [`Signed`] once every input has a script
+//! signature or a witness, and [`Finalized`] once it passes the sanity checks done before
+//! broadcasting it.
+//!
+//! Only unsigned transactions can be modified, and only finalized transactions can be serialized
+//! for broadcast, so passing a partially signed transaction to code expecting a complete one is a
+//! compile time error rather than a rejected broadcast.
+//!
+
+use core::fmt;
+use core::marker::PhantomData;
+
+use crate::blockdata::script::ScriptBuf;
+use crate::blockdata::transaction::{OutPoint, Transaction, Txid, Wtxid};
+use crate::blockdata::witness::Witness;
+use crate::consensus::encode;
+use crate::policy::MAX_STANDARD_TX_WEIGHT;
+use crate::prelude::*;
+use crate::{Amount, Weight};
+
+/// A transaction whose inputs are not all signed yet.
+pub type UnsignedTx = Tx<Unsigned>;
+
+/// A transaction with every input signed.
+pub type SignedTx = Tx<Signed>;
+
+/// A signed transaction which is ready to be broadcast.
+pub type FinalizedTx = Tx<Finalized>;
+
+/// The state of a [`Tx`] which is not yet known to have every input signed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unsigned {}
+
+/// The state of a [`Tx`] with every input signed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signed {}
+
+/// The state of a [`Tx`] which is signed and ready to be broadcast.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Finalized {}
+
+mod sealed {
+    pub trait State {}
+    impl State for super::Unsigned {}
+    impl State for super::Signed {}
+    impl State for super::Finalized {}
+}
+
+/// The state of a [`Tx`]: one of [`Unsigned`], [`Signed`] or [`Finalized`].
+pub trait State: sealed::State {}
+
+impl State for Unsigned {}
+impl State for Signed {}
+impl State for Finalized {}
+
+/// A transaction along with its lifecycle state `S`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tx<S: State> {
+    tx: Transaction,
+    state: PhantomData<S>,
+}
+
+impl<S: State> Tx<S> {
+    fn from_transaction(tx: Transaction) -> Self { Tx { tx, state: PhantomData } }
+
+    /// Returns a reference to the transaction.
+    pub fn as_transaction(&self) -> &Transaction { &self.tx }
+
+    /// Returns the transaction, forgetting its state.
+    pub fn into_transaction(self) -> Transaction { self.tx }
+}
+
+impl Tx<Unsigned> {
+    /// Starts tracking the lifecycle of `tx`.
+    ///
+    /// Inputs of `tx` may already be signed, use [`Tx::into_signed`] to check whether all are.
+    pub fn new(tx: Transaction) -> Self { Tx::from_transaction(tx) }
+
+    /// Returns a mutable reference to the transaction.
+    ///
+    /// Only unsigned transactions can be modified, since modifying a signed transaction
+    /// generally invalidates its signatures.
+    pub fn as_mut_transaction(&mut self) -> &mut Transaction { &mut self.tx }
+
+    /// Sets the script signature and the witness satisfying the input at `index`.
+    pub fn set_satisfaction(
+        &mut self,
+        index: usize,
+        script_sig: ScriptBuf,
+        witness: Witness,
+    ) -> Result<(), LifecycleError> {
+        let len = self.tx.input.len();
+        let input =
+            self.tx.input.get_mut(index).ok_or(LifecycleError::InputIndex { index, len })?;
+        input.script_sig = script_sig;
+        input.witness = witness;
+        Ok(())
+    }
+
+    /// Returns the indices of the inputs with neither a script signature nor a witness.
+    pub fn unsigned_inputs(&self) -> impl Iterator<Item = usize> + '_ {
+        self.tx
+            .input
+            .iter()
+            .enumerate()
+            .filter(|(_, input)| input.script_sig.is_empty() && input.witness.is_empty())
+            .map(|(index, _)| index)
+    }
+
+    /// Marks the transaction as signed.
+    ///
+    /// # Errors
+    ///
+    /// If the transaction has no inputs, or if any input has neither a script signature nor a
+    /// witness. The signatures themselves are not verified.
+    pub fn into_signed(self) -> Result<Tx<Signed>, LifecycleError> {
+        if self.tx.input.is_empty() {
+            return Err(LifecycleError::NoInputs);
+        }
+        if let Some(index) = self.unsigned_inputs().next() {
+            return Err(LifecycleError::UnsignedInput(index));
+        }
+        Ok(Tx::from_transaction(self.tx))
+    }
+}
+
+impl Tx<Signed> {
+    /// Computes the txid of the transaction.
+    pub fn compute_txid(&self) -> Txid { self.tx.compute_txid() }
+
+    /// Computes the wtxid of the transaction.
+    pub fn compute_wtxid(&self) -> Wtxid { self.tx.compute_wtxid() }
+
+    /// Goes back to the unsigned state, e.g. to modify the transaction and sign it again.
+    pub fn into_unsigned(self) -> Tx<Unsigned> { Tx::from_transaction(self.tx) }
+
+    /// Checks that the transaction is sane and marks it as ready to be broadcast.
+    ///
+    /// # Errors
+    ///
+    /// If the transaction has no outputs, spends the same outpoint twice, creates more than
+    /// [`Amount::MAX_MONEY`] or is larger than the standard transaction weight.
+    pub fn finalize(self) -> Result<Tx<Finalized>, LifecycleError> {
+        if self.tx.output.is_empty() {
+            return Err(LifecycleError::NoOutputs);
+        }
+
+        let mut spent = BTreeSet::new();
+        for input in &self.tx.input {
+            if !spent.insert(input.previous_output) {
+                return Err(LifecycleError::DuplicateInput(input.previous_output));
+            }
+        }
+
+        let total = self
+            .tx
+            .output
+            .iter()
+            .try_fold(Amount::ZERO, |total, output| total.checked_add(output.value));
+        match total {
+            Some(total) if total <= Amount::MAX_MONEY => {}
+            _ => return Err(LifecycleError::ValueOutOfRange),
+        }
+
+        let weight = self.tx.weight();
+        if weight > Weight::from_wu(u64::from(MAX_STANDARD_TX_WEIGHT)) {
+            return Err(LifecycleError::Oversized(weight));
+        }
+
+        Ok(Tx::from_transaction(self.tx))
+    }
+}
+
+impl Tx<Finalized> {
+    /// Computes the txid of the transaction.
+    pub fn compute_txid(&self) -> Txid { self.tx.compute_txid() }
+
+    /// Computes the wtxid of the transaction.
+    pub fn compute_wtxid(&self) -> Wtxid { self.tx.compute_wtxid() }
+
+    /// Serializes the transaction for broadcast.
+    pub fn serialize(&self) -> Vec<u8> { encode::serialize(&self.tx) }
+
+    /// Serializes the transaction for broadcast, as a hex string.
+    pub fn serialize_hex(&self) -> String { encode::serialize_hex(&self.tx) }
+}
+
+impl<S: State> AsRef<Transaction> for Tx<S> {
+    fn as_ref(&self) -> &Transaction { &self.tx }
+}
+
+impl<S: State> From<Tx<S>> for Transaction {
+    fn from(tx: Tx<S>) -> Self { tx.tx }
+}
+
+/// An error moving a [`Tx`] to the next state of its lifecycle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LifecycleError {
+    /// Input index out of range.
+    InputIndex {
+        /// The requested index.
+        index: usize,
+        /// The number of inputs of the transaction.
+        len: usize,
+    },
+    /// The transaction has no inputs.
+    NoInputs,
+    /// The input at this index has neither a script signature nor a witness.
+    UnsignedInput(usize),
+    /// The transaction has no outputs.
+    NoOutputs,
+    /// The outpoint is spent more than once.
+    DuplicateInput(OutPoint),
+    /// The outputs create more than [`Amount::MAX_MONEY`].
+    ValueOutOfRange,
+    /// The transaction is larger than the standard transaction weight.
+    Oversized(Weight),
+}
+
+internals::impl_from_infallible!(LifecycleError);
+
+impl fmt::Display for LifecycleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use LifecycleError::*;
+
+        match *self {
+            InputIndex { index, len } =>
+                write!(f, "input index {} out of range for {} inputs", index, len),
+            NoInputs => f.write_str("transaction has no inputs"),
+            UnsignedInput(index) => write!(f, "input {} is not signed", index),
+            NoOutputs => f.write_str("transaction has no outputs"),
+            DuplicateInput(ref outpoint) => write!(f, "outpoint {} is spent twice", outpoint),
+            ValueOutOfRange => f.write_str("outputs create more than the maximum amount"),
+            Oversized(weight) => write!(
+                f,
+                "transaction weight {} exceeds the standard limit {}",
+                weight, MAX_STANDARD_TX_WEIGHT
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for LifecycleError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use LifecycleError::*;
+
+        match *self {
+            InputIndex { .. }
+            | NoInputs
+            | UnsignedInput(_)
+            | NoOutputs
+            | DuplicateInput(_)
+            | ValueOutOfRange
+            | Oversized(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hashes::Hash;
+    use hex::DisplayHex;
+
+    use super::*;
+    use crate::blockdata::locktime::absolute;
+    use crate::blockdata::transaction::{self, Sequence, TxIn, TxOut};
+
+    fn unsigned_tx() -> Transaction {
+        let input = |vout| TxIn {
+            previous_output: OutPoint { txid: Txid::all_zeros(), vout },
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::MAX,
+            witness: Witness::new(),
+        };
+        Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![input(0), input(1)],
+            output: vec![TxOut { value: Amount::from_sat(1000), script_pubkey: ScriptBuf::new() }],
+        }
+    }
+
+    #[test]
+    fn lifecycle() {
+        let mut tx = Tx::new(unsigned_tx());
+        assert_eq!(tx.unsigned_inputs().collect::<Vec<_>>(), vec![0, 1]);
+        assert_eq!(tx.clone().into_signed(), Err(LifecycleError::UnsignedInput(0)));
+
+        let witness = Witness::from_slice(&[[0x01; 64]]);
+        tx.set_satisfaction(0, ScriptBuf::new(), witness.clone()).unwrap();
+        assert_eq!(tx.clone().into_signed(), Err(LifecycleError::UnsignedInput(1)));
+        assert_eq!(
+            tx.set_satisfaction(2, ScriptBuf::new(), witness.clone()),
+            Err(LifecycleError::InputIndex { index: 2, len: 2 })
+        );
+        tx.set_satisfaction(1, ScriptBuf::new(), witness).unwrap();
+
+        let signed = tx.into_signed().unwrap();
+        let txid = signed.compute_txid();
+        let finalized = signed.finalize().unwrap();
+        assert_eq!(finalized.compute_txid(), txid);
+        assert_eq!(finalized.serialize(), encode::serialize(finalized.as_transaction()));
+        assert_eq!(finalized.serialize_hex(), finalized.serialize().to_lower_hex_string());
+    }
+
+    #[test]
+    fn finalize_errors() {
+        let sign = |mut tx: Transaction| {
+            for input in &mut tx.input {
+                input.witness = Witness::from_slice(&[[0x01; 64]]);
+            }
+            Tx::new(tx).into_signed()
+        };
+
+        let mut tx = unsigned_tx();
+        tx.input.clear();
+        assert_eq!(sign(tx), Err(LifecycleError::NoInputs));
+
+        let mut tx = unsigned_tx();
+        tx.output.clear();
+        assert_eq!(sign(tx).unwrap().finalize(), Err(LifecycleError::NoOutputs));
+
+        let mut tx = unsigned_tx();
+        tx.input[1].previous_output = tx.input[0].previous_output;
+        assert_eq!(
+            sign(tx.clone()).unwrap().finalize(),
+            Err(LifecycleError::DuplicateInput(tx.input[0].previous_output))
+        );
+
+        let mut tx = unsigned_tx();
+        tx.output.push(TxOut { value: Amount::MAX_MONEY, script_pubkey: ScriptBuf::new() });
+        assert_eq!(sign(tx).unwrap().finalize(), Err(LifecycleError::ValueOutOfRange));
+
+        let mut tx = unsigned_tx();
+        tx.output[0].script_pubkey = ScriptBuf::from_bytes(vec![0x6a; 100_000]);
+        assert!(matches!(sign(tx).unwrap().finalize(), Err(LifecycleError::Oversized(_))));
+    }
+}
@@ -6,12 +6,17 @@
 //! transactions which make up the Bitcoin system.
 //!
 
+pub mod analytics;
 pub mod block;
 pub mod constants;
+pub mod graph;
+pub mod lifecycle;
 pub mod locktime;
 pub mod opcodes;
+pub mod savings;
 pub mod script;
 pub mod transaction;
+pub mod tx_builder;
 pub mod witness;
 
 #[rustfmt::skip]                // Keep public re-exports separate.
@@ -0,0 +1,270 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Weight savings analysis.
+//!
+//! Given a finalized transaction, this module estimates how much weight (and therefore fee) the
+//! transaction would have saved had each input been spent through a taproot key path, e.g. using
+//! a MuSig aggregated key in place of `OP_CHECKMULTISIG` or a cooperative key-path spend in place
+//! of a script-path spend. The report is meant to guide wallet migration decisions.
+//!
+//! The spend type of each input is inferred from its `script_sig` and witness alone, so no
+//! previous outputs are needed. Outputs are left as they are.
+//!
+
+use crate::blockdata::script::{Instruction, Script};
+use crate::blockdata::transaction::{predict_weight, InputWeightPrediction, Transaction, TxIn};
+use crate::prelude::*;
+use crate::taproot::{TAPROOT_ANNEX_PREFIX, TAPROOT_CONTROL_BASE_SIZE, TAPROOT_CONTROL_NODE_SIZE};
+use crate::{Amount, FeeRate, Weight};
+
+/// The way an input was spent, as inferred from its `script_sig` and witness.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SpendType {
+    /// Spend of a P2PKH output.
+    P2pkh,
+    /// Spend of a P2WPKH output, possibly nested in P2SH.
+    P2wpkh {
+        /// Whether the witness program is nested in a P2SH `script_sig`.
+        nested: bool,
+    },
+    /// Spend of an `m`-of-`n` `OP_CHECKMULTISIG` script, either in P2SH, P2WSH or P2SH-P2WSH.
+    Multisig {
+        /// Number of signatures required.
+        required: u8,
+        /// Number of public keys in the script.
+        total: u8,
+        /// Whether the script is in the witness.
+        segwit: bool,
+    },
+    /// Taproot key-path spend.
+    TaprootKeyPath,
+    /// Taproot script-path spend.
+    TaprootScriptPath,
+    /// Anything this module does not recognise.
+    Unknown,
+}
+
+impl SpendType {
+    /// Infers the spend type of `txin`.
+    pub fn from_txin(txin: &TxIn) -> Self {
+        let witness = &txin.witness;
+
+        if witness.is_empty() {
+            return match txin.script_sig.last_pushdata() {
+                Some(last) => match multisig_params(Script::from_bytes(last.as_bytes())) {
+                    Some((required, total)) =>
+                        SpendType::Multisig { required, total, segwit: false },
+                    None if last.len() == 33 || last.len() == 65 => SpendType::P2pkh,
+                    None => SpendType::Unknown,
+                },
+                None => SpendType::Unknown,
+            };
+        }
+
+        let nested = !txin.script_sig.is_empty();
+        // Strip the annex, if any, before looking at taproot witnesses.
+        let mut elements = witness.iter().collect::<Vec<_>>();
+        if elements.len() >= 2
+            && elements.last().and_then(|e| e.first()) == Some(&TAPROOT_ANNEX_PREFIX)
+        {
+            elements.pop();
+        }
+
+        match elements.as_slice() {
+            [sig] if !nested && (sig.len() == 64 || sig.len() == 65) => SpendType::TaprootKeyPath,
+            [_, pk] if pk.len() == 33 => SpendType::P2wpkh { nested },
+            [.., script] => match multisig_params(Script::from_bytes(script)) {
+                Some((required, total)) => SpendType::Multisig { required, total, segwit: true },
+                None if !nested && is_control_block(script) => SpendType::TaprootScriptPath,
+                None => SpendType::Unknown,
+            },
+            [] => SpendType::Unknown,
+        }
+    }
+}
+
+/// Returns `(m, n)` if `script` is an `m`-of-`n` `OP_CHECKMULTISIG` script.
+fn multisig_params(script: &Script) -> Option<(u8, u8)> {
+    if !script.is_multisig() {
+        return None;
+    }
+    let mut instructions = script.instructions().flatten();
+    let required = match instructions.next() {
+        Some(Instruction::Op(op)) => op.decode_pushnum()?,
+        _ => return None,
+    };
+    let total = instructions.filter(|i| matches!(i, Instruction::PushBytes(_))).count();
+    Some((required, total as u8))
+}
+
+/// Returns true if `bytes` looks like a taproot control block.
+fn is_control_block(bytes: &[u8]) -> bool {
+    bytes.len() >= TAPROOT_CONTROL_BASE_SIZE
+        && (bytes.len() - TAPROOT_CONTROL_BASE_SIZE).is_multiple_of(TAPROOT_CONTROL_NODE_SIZE)
+        && bytes[0] & 0xfe == 0xc0
+}
+
+/// Savings estimate for a single input.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct InputSavings {
+    /// The index of the input in the transaction.
+    pub index: usize,
+    /// How the input was spent.
+    pub spend_type: SpendType,
+    /// The weight the input currently contributes to the transaction.
+    pub current_weight: Weight,
+    /// The weight the input would contribute if it was spent using a taproot key path.
+    ///
+    /// Equal to `current_weight` for inputs which cannot be improved on, or are not recognised.
+    pub key_path_weight: Weight,
+}
+
+impl InputSavings {
+    /// Returns the weight that would be saved by spending this input using a taproot key path.
+    pub fn weight_savings(&self) -> Weight {
+        self.current_weight.checked_sub(self.key_path_weight).unwrap_or(Weight::ZERO)
+    }
+}
+
+/// Weight and fee savings report for a finalized transaction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SavingsReport {
+    inputs: Vec<InputSavings>,
+    current_weight: Weight,
+    key_path_weight: Weight,
+}
+
+impl SavingsReport {
+    /// Analyses every input of the finalized transaction `tx`.
+    pub fn new(tx: &Transaction) -> Self {
+        let mut predictions = Vec::with_capacity(tx.input.len());
+        let inputs = tx
+            .input
+            .iter()
+            .enumerate()
+            .map(|(index, txin)| {
+                let spend_type = SpendType::from_txin(txin);
+                let current = InputWeightPrediction::new(
+                    txin.script_sig.len(),
+                    txin.witness.iter().map(|elem| elem.len()),
+                );
+                let key_path = match spend_type {
+                    SpendType::Unknown => current,
+                    _ => InputWeightPrediction::P2TR_KEY_DEFAULT_SIGHASH,
+                };
+                predictions.push(key_path);
+
+                let current_weight = current.weight();
+                let key_path_weight = key_path.weight().min(current_weight);
+                InputSavings { index, spend_type, current_weight, key_path_weight }
+            })
+            .collect::<Vec<_>>();
+
+        let key_path_weight = if inputs.iter().all(|i| i.spend_type == SpendType::Unknown) {
+            tx.weight()
+        } else {
+            predict_weight(predictions, tx.script_pubkey_lens()).min(tx.weight())
+        };
+
+        SavingsReport { inputs, current_weight: tx.weight(), key_path_weight }
+    }
+
+    /// Returns the per-input savings estimates.
+    pub fn inputs(&self) -> &[InputSavings] { &self.inputs }
+
+    /// Returns the weight of the analysed transaction.
+    pub fn current_weight(&self) -> Weight { self.current_weight }
+
+    /// Returns the predicted weight of the transaction had all recognised inputs been spent
+    /// using a taproot key path.
+    pub fn key_path_weight(&self) -> Weight { self.key_path_weight }
+
+    /// Returns the total weight that would be saved.
+    pub fn weight_savings(&self) -> Weight {
+        self.current_weight.checked_sub(self.key_path_weight).unwrap_or(Weight::ZERO)
+    }
+
+    /// Returns the total fee that would be saved at `fee_rate`.
+    ///
+    /// Returns `None` on overflow.
+    pub fn fee_savings(&self, fee_rate: FeeRate) -> Option<Amount> {
+        fee_rate.fee_wu(self.weight_savings())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockdata::opcodes::all::OP_CHECKMULTISIG;
+    use crate::blockdata::script::Builder;
+    use crate::blockdata::transaction::{OutPoint, TxOut, Version};
+    use crate::locktime::absolute;
+    use crate::{ScriptBuf, Witness};
+
+    fn multisig_script(required: i64, total: usize) -> ScriptBuf {
+        let mut builder = Builder::new().push_int(required);
+        for i in 0..total {
+            builder = builder.push_slice([2 + (i as u8 % 2); 33]);
+        }
+        builder.push_int(total as i64).push_opcode(OP_CHECKMULTISIG).into_script()
+    }
+
+    fn txin(script_sig: ScriptBuf, witness: &[&[u8]]) -> TxIn {
+        TxIn {
+            previous_output: OutPoint::null(),
+            script_sig,
+            witness: Witness::from_slice(witness),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn spend_type_detection() {
+        let ms = multisig_script(2, 3);
+        let p2wsh = txin(ScriptBuf::new(), &[&[], &[0x30; 72], &[0x30; 72], ms.as_bytes()]);
+        assert_eq!(
+            SpendType::from_txin(&p2wsh),
+            SpendType::Multisig { required: 2, total: 3, segwit: true }
+        );
+
+        let p2wpkh = txin(ScriptBuf::new(), &[&[0x30; 72], &[0x02; 33]]);
+        assert_eq!(SpendType::from_txin(&p2wpkh), SpendType::P2wpkh { nested: false });
+
+        let key_path = txin(ScriptBuf::new(), &[&[0x01; 64]]);
+        assert_eq!(SpendType::from_txin(&key_path), SpendType::TaprootKeyPath);
+
+        let mut control_block = [0u8; 65];
+        control_block[0] = 0xc1;
+        let script_path = txin(ScriptBuf::new(), &[&[0x01; 64], &[0x51], &control_block]);
+        assert_eq!(SpendType::from_txin(&script_path), SpendType::TaprootScriptPath);
+
+        let p2pkh = Builder::new().push_slice([0x30; 71]).push_slice([0x02; 33]).into_script();
+        assert_eq!(SpendType::from_txin(&txin(p2pkh, &[])), SpendType::P2pkh);
+
+        assert_eq!(SpendType::from_txin(&txin(ScriptBuf::new(), &[])), SpendType::Unknown);
+    }
+
+    #[test]
+    fn multisig_savings() {
+        let ms = multisig_script(2, 3);
+        let tx = Transaction {
+            version: Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![
+                txin(ScriptBuf::new(), &[&[], &[0x30; 72], &[0x30; 72], ms.as_bytes()]),
+                txin(ScriptBuf::new(), &[&[0x01; 64]]),
+            ],
+            output: vec![TxOut { value: Amount::ONE_BTC, script_pubkey: ScriptBuf::new() }],
+        };
+
+        let report = SavingsReport::new(&tx);
+        assert_eq!(report.current_weight(), tx.weight());
+        assert!(report.inputs()[0].weight_savings() > Weight::ZERO);
+        assert_eq!(report.inputs()[1].weight_savings(), Weight::ZERO);
+        assert!(report.weight_savings() > Weight::ZERO);
+        assert_eq!(
+            report.fee_savings(FeeRate::from_sat_per_kwu(1000)),
+            Some(Amount::from_sat(report.weight_savings().to_wu()))
+        );
+    }
+}
@@ -422,7 +422,11 @@ impl Script {
     ///
     /// [`minimal_non_dust`]: Script::minimal_non_dust
     pub fn minimal_non_dust_custom(&self, dust_relay_fee: FeeRate) -> crate::Amount {
-        self.minimal_non_dust_inner(dust_relay_fee.to_sat_per_kwu() * 4)
+        let dust_relay_fee = dust_relay_fee
+            .to_sat_per_kwu()
+            .checked_mul(4)
+            .expect("dust_relay_fee should not be absurdly large");
+        self.minimal_non_dust_inner(dust_relay_fee)
     }
 
     fn minimal_non_dust_inner(&self, dust_relay_fee: u64) -> crate::Amount {
@@ -5,6 +5,7 @@ use core::fmt;
 use crate::blockdata::locktime::absolute;
 use crate::blockdata::opcodes::all::*;
 use crate::blockdata::opcodes::{self, Opcode};
+use crate::blockdata::script::limits::{self, LimitError, ScriptContext};
 use crate::blockdata::script::{opcode_to_verify, write_scriptint, PushBytes, Script, ScriptBuf};
 use crate::blockdata::transaction::Sequence;
 use crate::key::PublicKey;
@@ -124,6 +125,13 @@ impl Builder {
         self.0
     }
 
+    /// Converts the `Builder` into `ScriptBuf`, checking that the script doesn't break the
+    /// consensus or standardness limits of `context`.
+    pub fn into_standard_script(self, context: ScriptContext) -> Result<ScriptBuf, LimitError> {
+        limits::check_standard_script(self.as_script(), context)?;
+        Ok(self.into_script())
+    }
+
     /// Converts the `Builder` into script bytes
     pub fn into_bytes(self) -> Vec<u8> {
         self.0.into()
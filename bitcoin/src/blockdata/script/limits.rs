@@ -0,0 +1,232 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Script size limits.
+//!
+//! Scripts breaking a consensus limit can never be spent, and transactions breaking a
+//! standardness limit are not relayed by Bitcoin Core nodes. The limits depend on the
+//! [`ScriptContext`] the script is executed in: tapscript lifts the script size and opcode
+//! limits of legacy and segwit v0 scripts, but not the element size limit.
+//!
+//! Scripts can be checked with [`check_script`] or [`check_standard_script`], and witnesses with
+//! [`check_standard_witness`], so that outputs that couldn't be spent or transactions that
+//! couldn't be relayed are rejected before funds are sent to them. [`Builder::into_standard_script`]
+//! checks the script it builds, and [`TaprootBuilder`] rejects the leaves breaking the consensus
+//! limits.
+//!
+//! This crate has no script interpreter of its own: the limits are enforced on spends by
+//! `libbitcoinconsensus`, with the `bitcoinconsensus` feature.
+//!
+//! [`Builder::into_standard_script`]: super::Builder::into_standard_script
+//! [`TaprootBuilder`]: crate::taproot::TaprootBuilder
+//!
+
+use core::fmt;
+
+use internals::write_err;
+
+use super::{Error, Instruction, Script};
+use crate::blockdata::opcodes::all::OP_PUSHNUM_16;
+use crate::blockdata::witness::Witness;
+
+pub use crate::blockdata::constants::MAX_SCRIPT_ELEMENT_SIZE;
+
+/// The maximum size of a legacy or segwit v0 script.
+pub const MAX_SCRIPT_SIZE: usize = 10_000;
+
+/// The maximum number of non-push opcodes in a legacy or segwit v0 script.
+pub const MAX_OPS_PER_SCRIPT: usize = 201;
+
+/// The maximum number of elements on the stack.
+pub const MAX_STACK_SIZE: usize = 1000;
+
+/// The maximum size of a standard P2WSH witness script.
+pub const MAX_STANDARD_P2WSH_SCRIPT_SIZE: usize = 3600;
+
+/// The maximum number of stack items of a standard P2WSH witness, besides the witness script.
+pub const MAX_STANDARD_P2WSH_STACK_ITEMS: usize = 100;
+
+/// The maximum size of a stack item of a standard P2WSH or tapscript witness.
+pub const MAX_STANDARD_STACK_ITEM_SIZE: usize = 80;
+
+/// The context a script is executed in.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum ScriptContext {
+    /// A bare output script.
+    Bare,
+    /// A P2SH redeem script, which is pushed in the script sig.
+    P2sh,
+    /// A P2WSH witness script.
+    P2wsh,
+    /// A tapscript leaf script.
+    Tapscript,
+}
+
+impl ScriptContext {
+    /// Returns the maximum size of a script in this context, if any.
+    pub const fn max_script_size(self) -> Option<usize> {
+        match self {
+            ScriptContext::Bare | ScriptContext::P2wsh => Some(MAX_SCRIPT_SIZE),
+            ScriptContext::P2sh => Some(MAX_SCRIPT_ELEMENT_SIZE),
+            ScriptContext::Tapscript => None,
+        }
+    }
+
+    /// Returns the maximum number of non-push opcodes of a script in this context, if any.
+    pub const fn max_ops(self) -> Option<usize> {
+        match self {
+            ScriptContext::Bare | ScriptContext::P2sh | ScriptContext::P2wsh =>
+                Some(MAX_OPS_PER_SCRIPT),
+            ScriptContext::Tapscript => None,
+        }
+    }
+}
+
+/// Checks that `script` doesn't break the consensus limits of `context`.
+///
+/// The keys of `OP_CHECKMULTISIG`, which count towards the opcode limit when it is executed,
+/// are not counted.
+pub fn check_script(script: &Script, context: ScriptContext) -> Result<(), LimitError> {
+    if let Some(max) = context.max_script_size() {
+        if script.len() > max {
+            return Err(LimitError::ScriptTooLarge { size: script.len(), max });
+        }
+    }
+
+    let mut ops = 0;
+    for instruction in script.instructions() {
+        match instruction? {
+            Instruction::PushBytes(bytes) if bytes.len() > MAX_SCRIPT_ELEMENT_SIZE =>
+                return Err(LimitError::ElementTooLarge(bytes.len())),
+            Instruction::PushBytes(_) => {}
+            Instruction::Op(op) =>
+                if op.to_u8() > OP_PUSHNUM_16.to_u8() {
+                    ops += 1;
+                },
+        }
+    }
+    match context.max_ops() {
+        Some(max) if ops > max => Err(LimitError::TooManyOps(ops)),
+        _ => Ok(()),
+    }
+}
+
+/// Checks that `script` doesn't break the consensus or standardness limits of `context`.
+pub fn check_standard_script(script: &Script, context: ScriptContext) -> Result<(), LimitError> {
+    check_script(script, context)?;
+    if context == ScriptContext::P2wsh && script.len() > MAX_STANDARD_P2WSH_SCRIPT_SIZE {
+        return Err(LimitError::ScriptTooLarge {
+            size: script.len(),
+            max: MAX_STANDARD_P2WSH_SCRIPT_SIZE,
+        });
+    }
+    Ok(())
+}
+
+/// Checks that the script path spend `witness` doesn't break the consensus or standardness
+/// limits of `context`, including those of its script.
+///
+/// The witnesses of bare and P2SH scripts are expected to be empty and are not checked.
+pub fn check_standard_witness(witness: &Witness, context: ScriptContext) -> Result<(), LimitError> {
+    let (script, items, max_items) = match context {
+        ScriptContext::Bare | ScriptContext::P2sh => return Ok(()),
+        ScriptContext::P2wsh => {
+            let script = witness.last().ok_or(LimitError::MissingScript)?;
+            (Script::from_bytes(script), witness.len() - 1, MAX_STANDARD_P2WSH_STACK_ITEMS)
+        }
+        ScriptContext::Tapscript => {
+            let script = witness.tapscript().ok_or(LimitError::MissingScript)?;
+            let has_annex = witness.taproot_annex().is_some();
+            (script, witness.len() - 2 - has_annex as usize, MAX_STACK_SIZE)
+        }
+    };
+    check_standard_script(script, context)?;
+
+    if items > max_items {
+        return Err(LimitError::TooManyStackItems { count: items, max: max_items });
+    }
+    match witness.iter().take(items).map(<[u8]>::len).max() {
+        Some(size) if size > MAX_STANDARD_STACK_ITEM_SIZE =>
+            Err(LimitError::StackItemTooLarge(size)),
+        _ => Ok(()),
+    }
+}
+
+/// A script or witness breaking a consensus or standardness limit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LimitError {
+    /// The script is larger than allowed.
+    ScriptTooLarge {
+        /// The size of the script.
+        size: usize,
+        /// The maximum size.
+        max: usize,
+    },
+    /// The script pushes an element of this size, larger than [`MAX_SCRIPT_ELEMENT_SIZE`].
+    ElementTooLarge(usize),
+    /// The script has this many non-push opcodes, more than [`MAX_OPS_PER_SCRIPT`].
+    TooManyOps(usize),
+    /// The witness has more stack items than allowed.
+    TooManyStackItems {
+        /// The number of stack items.
+        count: usize,
+        /// The maximum number.
+        max: usize,
+    },
+    /// The witness has a stack item of this size, larger than [`MAX_STANDARD_STACK_ITEM_SIZE`].
+    StackItemTooLarge(usize),
+    /// The witness has no script.
+    MissingScript,
+    /// The script can't be parsed.
+    InvalidScript(Error),
+}
+
+internals::impl_from_infallible!(LimitError);
+
+impl fmt::Display for LimitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use LimitError::*;
+
+        match *self {
+            ScriptTooLarge { size, max } =>
+                write!(f, "script of {} bytes, at most {} allowed", size, max),
+            ElementTooLarge(size) => write!(
+                f,
+                "script pushes {} bytes, at most {} allowed",
+                size, MAX_SCRIPT_ELEMENT_SIZE
+            ),
+            TooManyOps(ops) =>
+                write!(f, "script has {} opcodes, at most {} allowed", ops, MAX_OPS_PER_SCRIPT),
+            TooManyStackItems { count, max } =>
+                write!(f, "witness has {} stack items, at most {} allowed", count, max),
+            StackItemTooLarge(size) => write!(
+                f,
+                "witness stack item of {} bytes, at most {} standard",
+                size, MAX_STANDARD_STACK_ITEM_SIZE
+            ),
+            MissingScript => f.write_str("witness has no script"),
+            InvalidScript(ref e) => write_err!(f, "invalid script"; e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for LimitError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use LimitError::*;
+
+        match *self {
+            InvalidScript(ref e) => Some(e),
+            ScriptTooLarge { .. }
+            | ElementTooLarge(_)
+            | TooManyOps(_)
+            | TooManyStackItems { .. }
+            | StackItemTooLarge(_)
+            | MissingScript => None,
+        }
+    }
+}
+
+impl From<Error> for LimitError {
+    fn from(e: Error) -> Self { Self::InvalidScript(e) }
+}
@@ -996,3 +996,95 @@ fn instruction_script_num_parse() {
         Some(Ok(Instruction::PushBytes(PushBytes::empty()))),
     );
 }
+
+#[test]
+fn limits() {
+    use crate::blockdata::witness::Witness;
+    use crate::taproot::{TaprootBuilder, TaprootBuilderError};
+    use limits::{
+        check_script, check_standard_script, check_standard_witness, LimitError, ScriptContext,
+    };
+
+    let push = |len: usize, count: usize| {
+        let data = vec![0u8; len];
+        let mut builder = Builder::new();
+        for _ in 0..count {
+            builder = builder.push_slice(<&PushBytes>::try_from(&data[..]).unwrap());
+        }
+        builder.into_script()
+    };
+
+    // Elements are limited in every context, tapscript included.
+    let large_element = push(521, 1);
+    assert_eq!(
+        check_script(&large_element, ScriptContext::Tapscript),
+        Err(LimitError::ElementTooLarge(521))
+    );
+    assert_eq!(
+        TaprootBuilder::new().add_leaf(0, large_element),
+        Err(TaprootBuilderError::UnspendableLeaf(LimitError::ElementTooLarge(521)))
+    );
+
+    // Script size.
+    let script = push(80, 45);
+    assert_eq!(check_script(&script, ScriptContext::P2wsh), Ok(()));
+    assert_eq!(
+        check_standard_script(&script, ScriptContext::P2wsh),
+        Err(LimitError::ScriptTooLarge { size: 3690, max: 3600 })
+    );
+    assert_eq!(
+        check_script(&script, ScriptContext::P2sh),
+        Err(LimitError::ScriptTooLarge { size: 3690, max: 520 })
+    );
+    let script = push(520, 20);
+    assert_eq!(
+        check_script(&script, ScriptContext::Bare),
+        Err(LimitError::ScriptTooLarge { size: 10460, max: 10000 })
+    );
+    assert_eq!(check_script(&script, ScriptContext::Tapscript), Ok(()));
+    let builder = Builder::from(script.to_bytes());
+    assert_eq!(
+        builder.clone().into_standard_script(ScriptContext::P2wsh),
+        Err(LimitError::ScriptTooLarge { size: 10460, max: 10000 })
+    );
+    assert_eq!(builder.into_standard_script(ScriptContext::Tapscript), Ok(script));
+
+    // Opcode count, pushes and small integers excluded.
+    let mut builder = Builder::new().push_int(16);
+    for _ in 0..201 {
+        builder = builder.push_opcode(OP_NOP);
+    }
+    assert_eq!(check_script(builder.as_script(), ScriptContext::P2wsh), Ok(()));
+    let script = builder.push_opcode(OP_NOP).into_script();
+    assert_eq!(check_script(&script, ScriptContext::P2wsh), Err(LimitError::TooManyOps(202)));
+    assert_eq!(check_script(&script, ScriptContext::Tapscript), Ok(()));
+
+    // Witnesses.
+    let op_true = [OP_PUSHNUM_1.to_u8()];
+    let witness = Witness::from_slice(&[&[0; 80][..], &op_true]);
+    assert_eq!(check_standard_witness(&witness, ScriptContext::P2wsh), Ok(()));
+    let witness = Witness::from_slice(&[&[0; 81][..], &op_true]);
+    assert_eq!(
+        check_standard_witness(&witness, ScriptContext::P2wsh),
+        Err(LimitError::StackItemTooLarge(81))
+    );
+    let mut items = vec![&[][..]; 101];
+    items.push(&op_true);
+    assert_eq!(
+        check_standard_witness(&Witness::from_slice(&items), ScriptContext::P2wsh),
+        Err(LimitError::TooManyStackItems { count: 101, max: 100 })
+    );
+    assert_eq!(
+        check_standard_witness(&Witness::new(), ScriptContext::P2wsh),
+        Err(LimitError::MissingScript)
+    );
+
+    let control_block = [0xc0; 33];
+    let witness = Witness::from_slice(&[&[0; 80][..], &op_true, &control_block, &[0x50]]);
+    assert_eq!(check_standard_witness(&witness, ScriptContext::Tapscript), Ok(()));
+    let witness = Witness::from_slice(&[&[0; 81][..], &op_true, &control_block, &[0x50]]);
+    assert_eq!(
+        check_standard_witness(&witness, ScriptContext::Tapscript),
+        Err(LimitError::StackItemTooLarge(81))
+    );
+}
@@ -758,6 +758,24 @@ impl Transaction {
         Txid::from_engine(enc)
     }
 
+    /// Checks whether two transactions are equal except for their witnesses.
+    ///
+    /// This is equivalent to comparing their [`Txid`]s, without hashing either transaction. Two
+    /// parties signing the same segwit transaction agree on it if they agree on this, while their
+    /// signatures may still differ. Use [`Transaction::compute_ntxid`] to also ignore the
+    /// `script_sig`s of legacy inputs.
+    pub fn structural_eq_ignoring_witness(&self, other: &Transaction) -> bool {
+        self.version == other.version
+            && self.lock_time == other.lock_time
+            && self.output == other.output
+            && self.input.len() == other.input.len()
+            && self.input.iter().zip(&other.input).all(|(a, b)| {
+                a.previous_output == b.previous_output
+                    && a.script_sig == b.script_sig
+                    && a.sequence == b.sequence
+            })
+    }
+
     /// Computes the segwit version of the transaction id.
     ///
     /// This method is deprecated.  Use `compute_wtxid` instead.
@@ -1909,6 +1927,30 @@ mod tests {
         assert!(old_ntxid != tx.compute_ntxid());
     }
 
+    #[test]
+    fn structural_eq_ignoring_witness() {
+        let tx_bytes = hex!("0100000001a15d57094aa7a21a28cb20b59aab8fc7d1149a3bdbcddba9c622e4f5f6a99ece010000006c493046022100f93bb0e7d8db7bd46e40132d1f8242026e045f03a0efe71bbb8e3f475e970d790221009337cd7f1f929f00cc6ff01f03729b069a7c21b59b1736ddfee5db5946c5da8c0121033b9b137ee87d5a812d6f506efdd37f0affa7ffc310711c06c7f3e097c9447c52ffffffff0100e1f505000000001976a9140389035a9225b3839e2bbf32d826a1e222031fd888ac00000000");
+        let tx: Transaction = deserialize(&tx_bytes).unwrap();
+
+        let mut other = tx.clone();
+        other.input[0].witness.push([0x42; 72]);
+        assert!(tx.structural_eq_ignoring_witness(&other));
+        assert_ne!(tx, other);
+
+        other.input[0].sequence = Sequence::ENABLE_RBF_NO_LOCKTIME;
+        assert!(!tx.structural_eq_ignoring_witness(&other));
+
+        let mut other = tx.clone();
+        other.input[0].script_sig = ScriptBuf::new();
+        assert!(!tx.structural_eq_ignoring_witness(&other));
+        assert_eq!(tx.compute_ntxid(), other.compute_ntxid());
+
+        let mut other = tx.clone();
+        other.input.push(TxIn::default());
+        assert!(!tx.structural_eq_ignoring_witness(&other));
+        assert!(!other.structural_eq_ignoring_witness(&tx));
+    }
+
     #[test]
     fn txid() {
         // segwit tx from Liquid integration tests, txid/hash from Core decoderawtransaction
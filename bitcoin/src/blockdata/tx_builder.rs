@@ -0,0 +1,436 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Transaction building.
+//!
+//! A [`TxBuilder`] spends a set of already selected inputs to a set of outputs at a target fee
+//! rate. The fee is either paid by the excess of the inputs over the outputs, taken out of one of
+//! the outputs with [`TxBuilder::subtract_fee_from_output`], or taken out of the remaining value
+//! when sweeping it with [`TxBuilder::drain_to`].
+//!
+//! The weight of the transaction is predicted from the [`InputWeightPrediction`] of each input,
+//! and the fee is computed on its virtual size rounded up, as done by Bitcoin Core when checking
+//! the fee rate of a transaction, rounding the fee itself up. The built transaction therefore
+//! never pays less than the target fee rate, whatever the size of its signatures, and outputs
+//! paying the fee are never left below the dust limit: building fails instead.
+//!
+
+use core::fmt;
+
+use crate::blockdata::lifecycle::UnsignedTx;
+use crate::blockdata::locktime::absolute;
+use crate::blockdata::script::ScriptBuf;
+use crate::blockdata::transaction::{
+    self, predict_weight, InputWeightPrediction, OutPoint, Sequence, Transaction, TxIn, TxOut,
+};
+use crate::blockdata::witness::Witness;
+use crate::prelude::*;
+use crate::{Amount, FeeRate, Weight};
+
+/// An input to spend, with the output it spends and the predicted size of its satisfaction.
+#[derive(Debug, Clone)]
+struct Input {
+    outpoint: OutPoint,
+    utxo: TxOut,
+    prediction: InputWeightPrediction,
+}
+
+/// Builds a transaction spending all of its inputs at a target fee rate.
+#[derive(Debug, Clone)]
+pub struct TxBuilder {
+    version: transaction::Version,
+    lock_time: absolute::LockTime,
+    sequence: Sequence,
+    fee_rate: FeeRate,
+    inputs: Vec<Input>,
+    outputs: Vec<TxOut>,
+    drain_to: Option<ScriptBuf>,
+    subtract_fee_from: Option<usize>,
+}
+
+impl TxBuilder {
+    /// Creates a builder of a version 2 transaction paying `fee_rate`.
+    ///
+    /// The transaction has no lock time and signals replaceability.
+    pub fn new(fee_rate: FeeRate) -> Self {
+        TxBuilder {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+            fee_rate,
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            drain_to: None,
+            subtract_fee_from: None,
+        }
+    }
+
+    /// Sets the version of the transaction.
+    pub fn version(mut self, version: transaction::Version) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Sets the lock time of the transaction.
+    pub fn lock_time(mut self, lock_time: absolute::LockTime) -> Self {
+        self.lock_time = lock_time;
+        self
+    }
+
+    /// Sets the sequence number of every input.
+    pub fn sequence(mut self, sequence: Sequence) -> Self {
+        self.sequence = sequence;
+        self
+    }
+
+    /// Spends `utxo` at `outpoint`, whose satisfaction has the weight predicted by `prediction`.
+    pub fn add_input(
+        mut self,
+        outpoint: OutPoint,
+        utxo: TxOut,
+        prediction: InputWeightPrediction,
+    ) -> Self {
+        self.inputs.push(Input { outpoint, utxo, prediction });
+        self
+    }
+
+    /// Adds an output paying exactly its value, unless the fee is subtracted from it.
+    pub fn add_output(mut self, output: TxOut) -> Self {
+        self.outputs.push(output);
+        self
+    }
+
+    /// Sends everything left after the outputs and the fee to `script_pubkey`.
+    ///
+    /// The drain output is added after all the other outputs. Without other outputs, this sweeps
+    /// all of the inputs to `script_pubkey`, the fee being taken out of the swept amount.
+    pub fn drain_to(mut self, script_pubkey: ScriptBuf) -> Self {
+        self.drain_to = Some(script_pubkey);
+        self
+    }
+
+    /// Takes the fee out of the value of the output at `index`, e.g. to send a whole balance.
+    ///
+    /// The excess of the inputs over the outputs still goes to the drain output, if any, or is
+    /// added to the fee otherwise.
+    pub fn subtract_fee_from_output(mut self, index: usize) -> Self {
+        self.subtract_fee_from = Some(index);
+        self
+    }
+
+    /// Returns the predicted weight of the transaction once all of its inputs are satisfied.
+    pub fn predicted_weight(&self) -> Weight {
+        let drain = self.drain_to.iter().map(|script_pubkey| script_pubkey.len());
+        predict_weight(
+            self.inputs.iter().map(|input| input.prediction),
+            self.outputs.iter().map(|output| output.script_pubkey.len()).chain(drain),
+        )
+    }
+
+    /// Returns the fee paid by the transaction at the target fee rate.
+    ///
+    /// This is the fee of its predicted virtual size, the weight being rounded up to whole
+    /// virtual bytes before computing the fee, and the fee being rounded up to whole satoshis.
+    pub fn fee(&self) -> Result<Amount, TxBuilderError> {
+        self.fee_rate
+            .fee_vb(self.predicted_weight().to_vbytes_ceil())
+            .ok_or(TxBuilderError::ValueOutOfRange)
+    }
+
+    /// Builds the transaction, returning it with the fee it pays.
+    ///
+    /// The fee is at least [`TxBuilder::fee`], more if the inputs exceed the outputs without a
+    /// drain output.
+    pub fn build(self) -> Result<(UnsignedTx, Amount), TxBuilderError> {
+        if self.inputs.is_empty() {
+            return Err(TxBuilderError::NoInputs);
+        }
+        if self.outputs.is_empty() && self.drain_to.is_none() {
+            return Err(TxBuilderError::NoOutputs);
+        }
+        if let Some(index) = self.subtract_fee_from {
+            if index >= self.outputs.len() {
+                return Err(TxBuilderError::OutputIndex { index, len: self.outputs.len() });
+            }
+        }
+
+        let fee = self.fee()?;
+        let available = sum(self.inputs.iter().map(|input| input.utxo.value))?;
+        let sent = sum(self.outputs.iter().map(|output| output.value))?;
+        let required = match self.subtract_fee_from {
+            Some(_) => sent,
+            None => sent.checked_add(fee).ok_or(TxBuilderError::ValueOutOfRange)?,
+        };
+        let excess = available
+            .checked_sub(required)
+            .ok_or(TxBuilderError::InsufficientFunds { available, required })?;
+
+        let mut outputs = self.outputs;
+        if let Some(index) = self.subtract_fee_from {
+            let output = &mut outputs[index];
+            output.value = output.value.checked_sub(fee).ok_or(TxBuilderError::Dust {
+                index,
+                value: output.value,
+                min: output.script_pubkey.minimal_non_dust(),
+            })?;
+        }
+        if let Some(script_pubkey) = self.drain_to {
+            outputs.push(TxOut { value: excess, script_pubkey });
+        }
+        for (index, output) in outputs.iter().enumerate() {
+            let min = output.script_pubkey.minimal_non_dust();
+            if output.value < min {
+                return Err(TxBuilderError::Dust { index, value: output.value, min });
+            }
+        }
+
+        let paid = available - sum(outputs.iter().map(|output| output.value))?;
+        let sequence = self.sequence;
+        let tx = Transaction {
+            version: self.version,
+            lock_time: self.lock_time,
+            input: self
+                .inputs
+                .into_iter()
+                .map(|input| TxIn {
+                    previous_output: input.outpoint,
+                    script_sig: ScriptBuf::new(),
+                    sequence,
+                    witness: Witness::new(),
+                })
+                .collect(),
+            output: outputs,
+        };
+        Ok((UnsignedTx::new(tx), paid))
+    }
+}
+
+/// Sums `amounts`, failing if the sum exceeds [`Amount::MAX_MONEY`].
+fn sum(mut amounts: impl Iterator<Item = Amount>) -> Result<Amount, TxBuilderError> {
+    amounts
+        .try_fold(Amount::ZERO, Amount::checked_add)
+        .filter(|total| *total <= Amount::MAX_MONEY)
+        .ok_or(TxBuilderError::ValueOutOfRange)
+}
+
+/// An error building a transaction with a [`TxBuilder`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TxBuilderError {
+    /// The transaction has no inputs.
+    NoInputs,
+    /// The transaction has neither outputs nor a drain output.
+    NoOutputs,
+    /// The output to subtract the fee from is out of range.
+    OutputIndex {
+        /// The requested index.
+        index: usize,
+        /// The number of outputs, excluding the drain output.
+        len: usize,
+    },
+    /// An amount exceeds [`Amount::MAX_MONEY`].
+    ValueOutOfRange,
+    /// The inputs don't cover the outputs, and the fee if it isn't subtracted from an output.
+    InsufficientFunds {
+        /// The value of the inputs.
+        available: Amount,
+        /// The value required.
+        required: Amount,
+    },
+    /// The output at this index would be below the dust limit after paying the fee.
+    Dust {
+        /// The index of the output, the drain output being the last one.
+        index: usize,
+        /// The value left to the output, or its whole value if it can't pay the fee.
+        value: Amount,
+        /// The dust limit of the output.
+        min: Amount,
+    },
+}
+
+internals::impl_from_infallible!(TxBuilderError);
+
+impl fmt::Display for TxBuilderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use TxBuilderError::*;
+
+        match *self {
+            NoInputs => f.write_str("transaction has no inputs"),
+            NoOutputs => f.write_str("transaction has no outputs"),
+            OutputIndex { index, len } =>
+                write!(f, "output index {} out of range for {} outputs", index, len),
+            ValueOutOfRange => f.write_str("amount exceeds the maximum amount"),
+            InsufficientFunds { available, required } =>
+                write!(f, "insufficient funds: {} available, {} required", available, required),
+            Dust { index, value, min } =>
+                write!(f, "output {} of {} is below the dust limit {}", index, value, min),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TxBuilderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use TxBuilderError::*;
+
+        match *self {
+            NoInputs
+            | NoOutputs
+            | OutputIndex { .. }
+            | ValueOutOfRange
+            | InsufficientFunds { .. }
+            | Dust { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hashes::Hash;
+
+    use super::*;
+    use crate::blockdata::transaction::Txid;
+    use crate::{PubkeyHash, WPubkeyHash};
+
+    fn p2wpkh(byte: u8) -> ScriptBuf {
+        ScriptBuf::new_p2wpkh(&WPubkeyHash::from_byte_array([byte; 20]))
+    }
+
+    fn utxo(vout: u32, sat: u64) -> (OutPoint, TxOut, InputWeightPrediction) {
+        let outpoint = OutPoint { txid: Txid::all_zeros(), vout };
+        let utxo = TxOut { value: Amount::from_sat(sat), script_pubkey: p2wpkh(0) };
+        (outpoint, utxo, InputWeightPrediction::P2WPKH_MAX)
+    }
+
+    fn builder(sats: &[u64]) -> TxBuilder {
+        sats.iter().enumerate().fold(
+            TxBuilder::new(FeeRate::from_sat_per_vb_u32(1)),
+            |builder, (vout, sat)| {
+                let (outpoint, utxo, prediction) = utxo(vout as u32, *sat);
+                builder.add_input(outpoint, utxo, prediction)
+            },
+        )
+    }
+
+    // The actual weight of a transaction with the predicted satisfactions.
+    fn satisfied_weight(tx: &UnsignedTx) -> Weight {
+        let mut tx = tx.as_transaction().clone();
+        for input in &mut tx.input {
+            input.witness = Witness::from_slice(&[[0; 72].as_slice(), &[0; 33]]);
+        }
+        tx.weight()
+    }
+
+    #[test]
+    fn drain() {
+        let (tx, fee) = builder(&[10_000, 20_000]).drain_to(p2wpkh(1)).build().unwrap();
+        let tx_out = &tx.as_transaction().output;
+        assert_eq!(tx_out.len(), 1);
+        assert_eq!(tx_out[0].value + fee, Amount::from_sat(30_000));
+
+        // A 1 sat/vB fee of the rounded up virtual size.
+        let weight = satisfied_weight(&tx);
+        assert_eq!(fee, Amount::from_sat(weight.to_vbytes_ceil()));
+        assert_eq!(fee, builder(&[10_000, 20_000]).drain_to(p2wpkh(1)).fee().unwrap());
+
+        // Draining the change next to a payment.
+        let payment = TxOut { value: Amount::from_sat(25_000), script_pubkey: p2wpkh(2) };
+        let (tx, fee) = builder(&[10_000, 20_000])
+            .add_output(payment.clone())
+            .drain_to(p2wpkh(1))
+            .build()
+            .unwrap();
+        let tx_out = &tx.as_transaction().output;
+        assert_eq!(tx_out[0], payment);
+        assert_eq!(tx_out[1].value, Amount::from_sat(5_000) - fee);
+    }
+
+    #[test]
+    fn drain_dust() {
+        let fee = builder(&[400]).drain_to(p2wpkh(1)).fee().unwrap();
+        let min = p2wpkh(1).minimal_non_dust();
+        assert_eq!(min, Amount::from_sat(294));
+
+        let exact = (min + fee).to_sat();
+        let (tx, _) = builder(&[exact]).drain_to(p2wpkh(1)).build().unwrap();
+        assert_eq!(tx.as_transaction().output[0].value, min);
+        assert_eq!(
+            builder(&[exact - 1]).drain_to(p2wpkh(1)).build(),
+            Err(TxBuilderError::Dust { index: 0, value: min - Amount::from_sat(1), min })
+        );
+        assert_eq!(
+            builder(&[fee.to_sat() - 1]).drain_to(p2wpkh(1)).build(),
+            Err(TxBuilderError::InsufficientFunds {
+                available: fee - Amount::from_sat(1),
+                required: fee
+            })
+        );
+    }
+
+    #[test]
+    fn subtract_fee_from_output() {
+        let payment = TxOut { value: Amount::from_sat(30_000), script_pubkey: p2wpkh(2) };
+        let (tx, fee) = builder(&[10_000, 20_000])
+            .add_output(payment.clone())
+            .subtract_fee_from_output(0)
+            .build()
+            .unwrap();
+        assert_eq!(tx.as_transaction().output[0].value, payment.value - fee);
+        assert_eq!(fee, Amount::from_sat(satisfied_weight(&tx).to_vbytes_ceil()));
+
+        // Without subtracting, the inputs don't cover the fee.
+        let required = payment.value + fee;
+        assert_eq!(
+            builder(&[10_000, 20_000]).add_output(payment.clone()).build(),
+            Err(TxBuilderError::InsufficientFunds { available: payment.value, required })
+        );
+
+        // The excess goes to the drain output.
+        let (tx, _) = builder(&[10_000, 30_000])
+            .add_output(payment.clone())
+            .subtract_fee_from_output(0)
+            .drain_to(p2wpkh(1))
+            .build()
+            .unwrap();
+        assert_eq!(tx.as_transaction().output[1].value, Amount::from_sat(10_000));
+
+        // A single input pays a lower fee.
+        let fee = builder(&[10_000]).add_output(payment.clone()).fee().unwrap();
+        let small = TxOut { value: fee + Amount::from_sat(100), script_pubkey: p2wpkh(2) };
+        let min = small.script_pubkey.minimal_non_dust();
+        let sats = small.value.to_sat();
+        assert_eq!(
+            builder(&[sats]).add_output(small).subtract_fee_from_output(0).build(),
+            Err(TxBuilderError::Dust { index: 0, value: Amount::from_sat(100), min })
+        );
+
+        // An output which can't pay the fee reports its whole value.
+        let tiny = TxOut { value: fee - Amount::from_sat(1), script_pubkey: p2wpkh(2) };
+        let value = tiny.value;
+        assert_eq!(
+            builder(&[sats]).add_output(tiny).subtract_fee_from_output(0).build(),
+            Err(TxBuilderError::Dust { index: 0, value, min })
+        );
+        assert_eq!(
+            builder(&[sats]).add_output(payment).subtract_fee_from_output(1).build(),
+            Err(TxBuilderError::OutputIndex { index: 1, len: 1 })
+        );
+    }
+
+    #[test]
+    fn legacy_output_dust() {
+        let script_pubkey = ScriptBuf::new_p2pkh(&PubkeyHash::all_zeros());
+        let min = script_pubkey.minimal_non_dust();
+        assert_eq!(min, Amount::from_sat(546));
+        let output = TxOut { value: min - Amount::from_sat(1), script_pubkey };
+        assert_eq!(
+            builder(&[10_000]).add_output(output).drain_to(p2wpkh(1)).build(),
+            Err(TxBuilderError::Dust { index: 0, value: min - Amount::from_sat(1), min })
+        );
+    }
+
+    #[test]
+    fn empty() {
+        assert_eq!(builder(&[]).drain_to(p2wpkh(1)).build(), Err(TxBuilderError::NoInputs));
+        assert_eq!(builder(&[1_000]).build(), Err(TxBuilderError::NoOutputs));
+    }
+}
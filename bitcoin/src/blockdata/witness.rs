@@ -13,6 +13,7 @@ use io::{BufRead, Write};
 use crate::consensus::encode::{Error, MAX_VEC_SIZE};
 use crate::consensus::{Decodable, Encodable, WriteExt};
 use crate::crypto::ecdsa;
+use crate::crypto::sighash::Annex;
 use crate::taproot::{self, TAPROOT_ANNEX_PREFIX};
 use crate::{prelude::*, PublicKey};
 use crate::{Script, VarInt};
@@ -437,6 +438,59 @@ impl Witness {
             .and_then(|script_pos_from_last| self.nth(len - script_pos_from_last))
             .map(Script::from_bytes)
     }
+
+    /// Get the taproot control block following BIP341 rules regarding accounting for an annex.
+    ///
+    /// This does not guarantee that this represents a P2TR [`Witness`]. It merely gets the last
+    /// or second to last element depending on the first byte of the last element being equal to
+    /// 0x50, if there is a script before it. See [`Witness::tapscript`].
+    pub fn taproot_control_block(&self) -> Option<&[u8]> {
+        let len = self.len();
+        let has_annex = len >= 2 && self.last()?.first() == Some(&TAPROOT_ANNEX_PREFIX);
+        let pos_from_last = if has_annex { 2 } else { 1 };
+        // a script path spend has a script before the control block
+        if len < pos_from_last + 1 {
+            return None;
+        }
+        self.nth(len - pos_from_last)
+    }
+
+    /// Get the taproot annex following BIP341 rules.
+    ///
+    /// The annex is the last element if there are at least two elements and it starts with 0x50.
+    /// Like [`Witness::tapscript`], this does not check that this is a P2TR [`Witness`].
+    pub fn taproot_annex(&self) -> Option<Annex<'_>> {
+        if self.len() < 2 {
+            return None;
+        }
+        Annex::new(self.last()?).ok()
+    }
+
+    /// Pushes `annex` as the last element of a taproot witness.
+    ///
+    /// The annex must be pushed after the script path or key path elements, an element pushed
+    /// after it would become the annex or remove it.
+    pub fn push_annex(&mut self, annex: &Annex) {
+        self.push_slice(annex.as_bytes())
+    }
+
+    /// Removes the taproot annex from the witness, returning it if there was one.
+    ///
+    /// See [`Witness::taproot_annex`] for when the last element is the annex.
+    pub fn strip_annex(&mut self) -> Option<Vec<u8>> {
+        let annex = self.taproot_annex()?.as_bytes().to_vec();
+        let remaining = self.witness_elements - 1;
+        let annex_start = decode_cursor(&self.content, self.indices_start, remaining)?;
+        // Move the indices of the remaining elements over the annex.
+        self.content.copy_within(
+            self.indices_start..self.indices_start + remaining * 4,
+            annex_start,
+        );
+        self.content.truncate(annex_start + remaining * 4);
+        self.indices_start = annex_start;
+        self.witness_elements = remaining;
+        Some(annex)
+    }
 }
 
 impl Index<usize> for Witness {
@@ -767,6 +821,40 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_annex() {
+        let annex_bytes = hex!("50deadbeef");
+        let annex = Annex::new(&annex_bytes).unwrap();
+
+        let mut witness = Witness::from_slice(&[hex!("deadbeef"), hex!("c0")]);
+        let expected = witness.clone();
+        assert_eq!(witness.taproot_annex(), None);
+        assert_eq!(witness.strip_annex(), None);
+
+        witness.push_annex(&annex);
+        assert_eq!(witness.len(), 3);
+        assert_eq!(witness.taproot_annex(), Some(annex));
+        assert_eq!(witness.tapscript(), expected.tapscript());
+        assert_eq!(
+            witness.taproot_control_block(),
+            expected.taproot_control_block()
+        );
+
+        assert_eq!(witness.strip_annex(), Some(annex_bytes.clone()));
+        assert_eq!(witness, expected);
+        assert_eq!(witness.taproot_annex(), None);
+        witness.push(hex!("01"));
+        assert_eq!(
+            witness.to_vec(),
+            vec![hex!("deadbeef"), hex!("c0"), hex!("01")]
+        );
+
+        // A single element is never an annex.
+        let mut witness = Witness::from_slice(&[annex_bytes]);
+        assert_eq!(witness.taproot_annex(), None);
+        assert_eq!(witness.strip_annex(), None);
+    }
+
     #[test]
     fn test_tx() {
         const S: &str = "02000000000102b44f26b275b8ad7b81146ba3dbecd081f9c1ea0dc05b97516f56045cfcd3df030100000000ffffffff1cb4749ae827c0b75f3d0a31e63efc8c71b47b5e3634a4c698cd53661cab09170100000000ffffffff020b3a0500000000001976a9143ea74de92762212c96f4dd66c4d72a4deb20b75788ac630500000000000016001493a8dfd1f0b6a600ab01df52b138cda0b82bb7080248304502210084622878c94f4c356ce49c8e33a063ec90f6ee9c0208540888cfab056cd1fca9022014e8dbfdfa46d318c6887afd92dcfa54510e057565e091d64d2ee3a66488f82c0121026e181ffb98ebfe5a64c983073398ea4bcd1548e7b971b4c175346a25a1c12e950247304402203ef00489a0d549114977df2820fab02df75bebb374f5eee9e615107121658cfa02204751f2d1784f8e841bff6d3bcf2396af2f1a5537c0e4397224873fbd3bfbe9cf012102ae6aa498ce2dd204e9180e71b4fb1260fe3d1a95c8025b34e56a9adf5f278af200000000";
@@ -0,0 +1,245 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Broadcasting transactions.
+//!
+//! Wallet code which publishes transactions should take a [`Broadcaster`] rather than talk to a
+//! node itself, so the application can choose the backend relaying its transactions: a node over
+//! RPC, an Esplora server, or a mock in tests. Backends implement the trait by submitting the
+//! serialized transaction and mapping their errors to a [`BroadcastError`].
+//!
+//! With the `async` feature, [`AsyncBroadcaster`] is the same interface for backends with an
+//! asynchronous client, and [`Blocking`] adapts a blocking backend to it.
+//!
+
+use core::fmt;
+#[cfg(feature = "async")]
+use core::future::Future;
+#[cfg(feature = "async")]
+use core::pin::Pin;
+
+use crate::blockdata::transaction::{Transaction, Txid};
+use crate::prelude::*;
+
+/// A backend which relays transactions to the network.
+pub trait Broadcaster {
+    /// Broadcasts `tx` and returns its txid.
+    ///
+    /// Broadcasting a transaction the backend already knows about should succeed, so a broadcast
+    /// which failed for an unknown reason can be safely repeated.
+    fn broadcast(&self, tx: &Transaction) -> Result<Txid, BroadcastError>;
+}
+
+impl<B: Broadcaster + ?Sized> Broadcaster for &B {
+    fn broadcast(&self, tx: &Transaction) -> Result<Txid, BroadcastError> { (**self).broadcast(tx) }
+}
+
+impl<B: Broadcaster + ?Sized> Broadcaster for Box<B> {
+    fn broadcast(&self, tx: &Transaction) -> Result<Txid, BroadcastError> { (**self).broadcast(tx) }
+}
+
+/// The future returned by [`AsyncBroadcaster::broadcast`].
+#[cfg(feature = "async")]
+pub type BroadcastFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<Txid, BroadcastError>> + Send + 'a>>;
+
+/// A backend which relays transactions to the network asynchronously.
+///
+/// This is the asynchronous version of [`Broadcaster`]. The future is boxed so the trait can be
+/// used as a trait object, and must be `Send` so it can run on a multi-threaded executor.
+#[cfg(feature = "async")]
+pub trait AsyncBroadcaster {
+    /// Broadcasts `tx` and returns its txid, see [`Broadcaster::broadcast`].
+    fn broadcast<'a>(&'a self, tx: &'a Transaction) -> BroadcastFuture<'a>;
+}
+
+#[cfg(feature = "async")]
+impl<B: AsyncBroadcaster + ?Sized> AsyncBroadcaster for &B {
+    fn broadcast<'a>(&'a self, tx: &'a Transaction) -> BroadcastFuture<'a> {
+        (**self).broadcast(tx)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<B: AsyncBroadcaster + ?Sized> AsyncBroadcaster for Box<B> {
+    fn broadcast<'a>(&'a self, tx: &'a Transaction) -> BroadcastFuture<'a> {
+        (**self).broadcast(tx)
+    }
+}
+
+/// Adapts a blocking [`Broadcaster`] to an [`AsyncBroadcaster`].
+///
+/// The blocking call is made when the future is first polled and blocks the executor until it
+/// returns, which is fine for backends that answer quickly, like a local node.
+#[cfg(feature = "async")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Blocking<B>(pub B);
+
+#[cfg(feature = "async")]
+impl<B: Broadcaster + Sync> AsyncBroadcaster for Blocking<B> {
+    fn broadcast<'a>(&'a self, tx: &'a Transaction) -> BroadcastFuture<'a> {
+        Box::pin(async move { self.0.broadcast(tx) })
+    }
+}
+
+/// Checks that the txid reported by a backend is the txid of `tx`.
+///
+/// Backends which return the txid they computed should pass it through this function, so a
+/// backend which misunderstood the transaction doesn't go unnoticed.
+pub fn check_txid(tx: &Transaction, reported: Txid) -> Result<Txid, BroadcastError> {
+    let expected = tx.compute_txid();
+    if reported == expected {
+        Ok(expected)
+    } else {
+        Err(BroadcastError::TxidMismatch { expected, reported })
+    }
+}
+
+/// An error returned by a [`Broadcaster`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BroadcastError {
+    /// The backend rejected the transaction, for the given reason.
+    ///
+    /// Broadcasting the same transaction again fails the same way, unless the reason is
+    /// temporary, like a missing parent transaction or a full mempool.
+    Rejected(String),
+    /// The backend couldn't be reached or failed, for the given reason.
+    ///
+    /// The transaction may or may not have been broadcast.
+    Backend(String),
+    /// The backend reported another txid than the txid of the transaction.
+    TxidMismatch {
+        /// The txid of the transaction.
+        expected: Txid,
+        /// The txid reported by the backend.
+        reported: Txid,
+    },
+}
+
+internals::impl_from_infallible!(BroadcastError);
+
+impl BroadcastError {
+    /// Returns true if broadcasting the transaction again may succeed.
+    pub fn is_transient(&self) -> bool { matches!(*self, BroadcastError::Backend(_)) }
+}
+
+impl fmt::Display for BroadcastError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use BroadcastError::*;
+
+        match *self {
+            Rejected(ref reason) => write!(f, "transaction rejected: {}", reason),
+            Backend(ref reason) => write!(f, "broadcast backend failed: {}", reason),
+            TxidMismatch { expected, reported } => write!(
+                f,
+                "broadcast backend reported txid {} for transaction {}",
+                reported, expected
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BroadcastError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use BroadcastError::*;
+
+        match *self {
+            Rejected(_) | Backend(_) | TxidMismatch { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::cell::RefCell;
+
+    use hashes::Hash;
+
+    use super::*;
+    use crate::blockdata::locktime::absolute;
+    use crate::blockdata::transaction::{self, TxIn, TxOut};
+    use crate::{Amount, ScriptBuf};
+
+    /// Keeps the broadcast transactions, or fails with an error.
+    #[derive(Default)]
+    struct Mempool {
+        txs: RefCell<Vec<Transaction>>,
+        error: Option<BroadcastError>,
+    }
+
+    impl Broadcaster for Mempool {
+        fn broadcast(&self, tx: &Transaction) -> Result<Txid, BroadcastError> {
+            if let Some(ref error) = self.error {
+                return Err(error.clone());
+            }
+            self.txs.borrow_mut().push(tx.clone());
+            check_txid(tx, tx.compute_txid())
+        }
+    }
+
+    fn tx() -> Transaction {
+        Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![TxIn::default()],
+            output: vec![TxOut { value: Amount::from_sat(1000), script_pubkey: ScriptBuf::new() }],
+        }
+    }
+
+    /// Publishes `tx` with any backend.
+    fn publish<B: Broadcaster>(broadcaster: B, tx: &Transaction) -> Result<Txid, BroadcastError> {
+        broadcaster.broadcast(tx)
+    }
+
+    #[test]
+    fn broadcast() {
+        let tx = tx();
+        let mempool = Mempool::default();
+        assert_eq!(publish(&mempool, &tx), Ok(tx.compute_txid()));
+        let boxed: Box<dyn Broadcaster> = Box::new(mempool);
+        assert_eq!(publish(&boxed, &tx), Ok(tx.compute_txid()));
+
+        let error = BroadcastError::Rejected("insufficient fee".to_owned());
+        let failing = Mempool { error: Some(error.clone()), ..Default::default() };
+        assert_eq!(publish(&failing, &tx), Err(error.clone()));
+        assert!(!error.is_transient());
+        assert!(BroadcastError::Backend("timeout".to_owned()).is_transient());
+    }
+
+    #[test]
+    fn txid_mismatch() {
+        let tx = tx();
+        let reported = Txid::all_zeros();
+        assert_eq!(
+            check_txid(&tx, reported),
+            Err(BroadcastError::TxidMismatch { expected: tx.compute_txid(), reported })
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "async")]
+    fn blocking() {
+        use core::task::{Context, Poll, Waker};
+
+        struct Counter(std::sync::Mutex<u32>);
+
+        impl Broadcaster for Counter {
+            fn broadcast(&self, tx: &Transaction) -> Result<Txid, BroadcastError> {
+                *self.0.lock().unwrap() += 1;
+                Ok(tx.compute_txid())
+            }
+        }
+
+        let tx = tx();
+        let broadcaster = Blocking(Counter(std::sync::Mutex::new(0)));
+        let async_broadcaster: &dyn AsyncBroadcaster = &broadcaster;
+        let mut future = async_broadcaster.broadcast(&tx);
+        // Nothing is broadcast until the future is polled.
+        assert_eq!(*broadcaster.0 .0.lock().unwrap(), 0);
+
+        let mut cx = Context::from_waker(Waker::noop());
+        assert_eq!(future.as_mut().poll(&mut cx), Poll::Ready(Ok(tx.compute_txid())));
+        assert_eq!(*broadcaster.0 .0.lock().unwrap(), 1);
+    }
+}
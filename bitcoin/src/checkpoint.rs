@@ -0,0 +1,494 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Wallet state checkpoints.
+//!
+//! This module persists the chain state of a wallet, the last block it scanned and its unspent
+//! outputs, so that a long-running wallet can restart without rescanning the chain. The state
+//! is kept in an append-only log: every [`Update`] is appended as a record of its own, and the
+//! log is periodically compacted into a single snapshot of the [`WalletState`].
+//!
+//! The log format is:
+//!
+//! ```text
+//! magic "WCKP" (4 bytes) | version (1 byte) | record*
+//! ```
+//!
+//! where every record is:
+//!
+//! ```text
+//! payload length (4 bytes, little endian) | payload | checksum (4 bytes)
+//! ```
+//!
+//! and the checksum is the start of the double SHA256 of the payload. A crash while appending
+//! leaves a truncated record at the end of the log, and storage corruption leaves a record with
+//! a bad checksum. In both cases [`CheckpointLog::recover`] returns the state up to the last
+//! valid record, together with the length of the valid part of the log so the caller can
+//! truncate it before appending again.
+//!
+
+use core::fmt;
+
+use hashes::{sha256d, Hash};
+use io::{BufRead, Write};
+
+use crate::blockdata::block::BlockHash;
+use crate::blockdata::transaction::{OutPoint, TxOut};
+use crate::consensus::encode::{self, Decodable, Encodable, VarInt};
+use crate::prelude::*;
+
+/// The magic bytes at the start of every checkpoint log.
+pub const CHECKPOINT_MAGIC: [u8; 4] = *b"WCKP";
+
+/// The version of the checkpoint log format written by this library.
+pub const CURRENT_VERSION: u8 = 1;
+
+/// The length of the header of a log.
+const HEADER_LEN: usize = 4 + 1;
+
+/// The length of the checksum at the end of a record.
+const CHECKSUM_LEN: usize = 4;
+
+/// The record types.
+const RECORD_SNAPSHOT: u8 = 0;
+const RECORD_TIP: u8 = 1;
+const RECORD_RECEIVE: u8 = 2;
+const RECORD_SPEND: u8 = 3;
+
+/// A block of the chain, identified by its height and hash.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BlockId {
+    /// The height of the block.
+    pub height: u32,
+    /// The hash of the block.
+    pub hash: BlockHash,
+}
+
+/// An unspent output of the wallet.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Utxo {
+    /// The output.
+    pub txout: TxOut,
+    /// The height of the block which confirmed the output.
+    pub height: u32,
+}
+
+/// The chain state of a wallet.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WalletState {
+    /// The last block scanned, `None` if no block was scanned yet.
+    pub tip: Option<BlockId>,
+    /// The unspent outputs of the wallet.
+    pub utxos: BTreeMap<OutPoint, Utxo>,
+}
+
+impl WalletState {
+    /// Creates the state of a wallet which didn't scan any block yet.
+    pub fn new() -> Self { Self::default() }
+
+    /// Applies `update` to the state.
+    ///
+    /// Spending an output which isn't in the state is a no-op.
+    pub fn apply(&mut self, update: &Update) {
+        match *update {
+            Update::Tip(tip) => self.tip = Some(tip),
+            Update::Receive { outpoint, ref utxo } => {
+                self.utxos.insert(outpoint, utxo.clone());
+            }
+            Update::Spend { outpoint } => {
+                self.utxos.remove(&outpoint);
+            }
+        }
+    }
+
+    fn encode<W: Write + ?Sized>(&self, w: &mut W) -> Result<usize, io::Error> {
+        let mut len = match self.tip {
+            Some(tip) => 1u8.consensus_encode(w)? + tip.encode(w)?,
+            None => 0u8.consensus_encode(w)?,
+        };
+        len += VarInt::from(self.utxos.len()).consensus_encode(w)?;
+        for (outpoint, utxo) in &self.utxos {
+            len += outpoint.consensus_encode(w)?;
+            len += utxo.encode(w)?;
+        }
+        Ok(len)
+    }
+
+    fn decode<R: BufRead + ?Sized>(r: &mut R) -> Result<Self, encode::Error> {
+        let tip = match u8::consensus_decode(r)? {
+            0 => None,
+            1 => Some(BlockId::decode(r)?),
+            _ => return Err(encode::Error::ParseFailed("invalid tip flag")),
+        };
+        let mut utxos = BTreeMap::new();
+        for _ in 0..VarInt::consensus_decode(r)?.0 {
+            let outpoint = OutPoint::consensus_decode(r)?;
+            utxos.insert(outpoint, Utxo::decode(r)?);
+        }
+        Ok(WalletState { tip, utxos })
+    }
+}
+
+impl BlockId {
+    fn encode<W: Write + ?Sized>(&self, w: &mut W) -> Result<usize, io::Error> {
+        Ok(self.height.consensus_encode(w)? + self.hash.consensus_encode(w)?)
+    }
+
+    fn decode<R: BufRead + ?Sized>(r: &mut R) -> Result<Self, encode::Error> {
+        Ok(BlockId {
+            height: Decodable::consensus_decode(r)?,
+            hash: Decodable::consensus_decode(r)?,
+        })
+    }
+}
+
+impl Utxo {
+    fn encode<W: Write + ?Sized>(&self, w: &mut W) -> Result<usize, io::Error> {
+        Ok(self.txout.consensus_encode(w)? + self.height.consensus_encode(w)?)
+    }
+
+    fn decode<R: BufRead + ?Sized>(r: &mut R) -> Result<Self, encode::Error> {
+        Ok(Utxo { txout: Decodable::consensus_decode(r)?, height: Decodable::consensus_decode(r)? })
+    }
+}
+
+/// An incremental change to a [`WalletState`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Update {
+    /// The wallet scanned up to the given block.
+    Tip(BlockId),
+    /// The wallet received an output.
+    Receive {
+        /// The received output.
+        outpoint: OutPoint,
+        /// The output and its confirmation height.
+        utxo: Utxo,
+    },
+    /// The wallet spent an output.
+    Spend {
+        /// The spent output.
+        outpoint: OutPoint,
+    },
+}
+
+impl Update {
+    fn encode<W: Write + ?Sized>(&self, w: &mut W) -> Result<usize, io::Error> {
+        match *self {
+            Update::Tip(tip) => Ok(RECORD_TIP.consensus_encode(w)? + tip.encode(w)?),
+            Update::Receive { outpoint, ref utxo } => Ok(RECORD_RECEIVE.consensus_encode(w)?
+                + outpoint.consensus_encode(w)?
+                + utxo.encode(w)?),
+            Update::Spend { outpoint } =>
+                Ok(RECORD_SPEND.consensus_encode(w)? + outpoint.consensus_encode(w)?),
+        }
+    }
+}
+
+/// The outcome of [`CheckpointLog::recover`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Recovery {
+    /// The number of valid records read.
+    pub records: usize,
+    /// The length of the valid part of the log.
+    ///
+    /// If this is shorter than the log, the rest must be discarded before appending to it.
+    pub valid_len: usize,
+    /// Whether the log had a truncated or corrupted record, and part of it was discarded.
+    pub corrupted: bool,
+}
+
+/// An append-only log of the [`WalletState`] of a wallet.
+///
+/// The log does not do any I/O on its own: [`CheckpointLog::append`] and
+/// [`CheckpointLog::compact`] write to the given writer, which is typically a file opened in
+/// append mode and a temporary file atomically renamed over the log, respectively.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CheckpointLog {
+    state: WalletState,
+    updates: usize,
+}
+
+impl CheckpointLog {
+    /// Creates the log of a wallet which didn't scan any block yet.
+    ///
+    /// Call [`CheckpointLog::compact`] to write the initial log.
+    pub fn new() -> Self { Self::default() }
+
+    /// Returns the current state of the wallet.
+    pub fn state(&self) -> &WalletState { &self.state }
+
+    /// Returns the number of updates appended since the last snapshot.
+    ///
+    /// This is how many records a compaction would remove, and can be used to decide when to
+    /// compact the log.
+    pub fn updates_since_snapshot(&self) -> usize { self.updates }
+
+    /// Applies `update` to the state and appends its record to `w`.
+    pub fn append<W: Write + ?Sized>(
+        &mut self,
+        w: &mut W,
+        update: Update,
+    ) -> Result<(), io::Error> {
+        let mut payload = Vec::new();
+        update.encode(&mut payload)?;
+        write_record(w, &payload)?;
+        self.state.apply(&update);
+        self.updates += 1;
+        Ok(())
+    }
+
+    /// Writes a complete log to `w`, made of a single snapshot of the current state.
+    pub fn compact<W: Write + ?Sized>(&mut self, w: &mut W) -> Result<(), io::Error> {
+        let mut payload = vec![RECORD_SNAPSHOT];
+        self.state.encode(&mut payload)?;
+        w.write_all(&CHECKPOINT_MAGIC)?;
+        w.write_all(&[CURRENT_VERSION])?;
+        write_record(w, &payload)?;
+        self.updates = 0;
+        Ok(())
+    }
+
+    /// Reads a log, recovering the state up to the last valid record.
+    ///
+    /// Only an invalid header is an error. A truncated or corrupted record ends the log and is
+    /// reported in the returned [`Recovery`].
+    pub fn recover(bytes: &[u8]) -> Result<(Self, Recovery), CheckpointError> {
+        if bytes.len() < HEADER_LEN {
+            return Err(CheckpointError::TooShort);
+        }
+        if bytes[..4] != CHECKPOINT_MAGIC {
+            return Err(CheckpointError::InvalidMagic);
+        }
+        match bytes[4] {
+            CURRENT_VERSION => {}
+            v => return Err(CheckpointError::UnsupportedVersion(v)),
+        }
+
+        let mut log = CheckpointLog::new();
+        let mut recovery = Recovery { records: 0, valid_len: HEADER_LEN, corrupted: false };
+        let mut rest = &bytes[HEADER_LEN..];
+        while !rest.is_empty() {
+            match read_record(rest)
+                .and_then(|(payload, len)| log.apply_record(payload).map(|()| len).ok_or(()))
+            {
+                Ok(len) => {
+                    rest = &rest[len..];
+                    recovery.records += 1;
+                    recovery.valid_len += len;
+                }
+                Err(()) => {
+                    recovery.corrupted = true;
+                    break;
+                }
+            }
+        }
+        Ok((log, recovery))
+    }
+
+    /// Applies the record `payload` to the state, returning `None` if it is malformed.
+    fn apply_record(&mut self, payload: &[u8]) -> Option<()> {
+        let (&record_type, mut r) = payload.split_first()?;
+        let update = match record_type {
+            RECORD_SNAPSHOT => {
+                let state = WalletState::decode(&mut r).ok()?;
+                if !r.is_empty() {
+                    return None;
+                }
+                self.state = state;
+                self.updates = 0;
+                return Some(());
+            }
+            RECORD_TIP => Update::Tip(BlockId::decode(&mut r).ok()?),
+            RECORD_RECEIVE => Update::Receive {
+                outpoint: OutPoint::consensus_decode(&mut r).ok()?,
+                utxo: Utxo::decode(&mut r).ok()?,
+            },
+            RECORD_SPEND => Update::Spend { outpoint: OutPoint::consensus_decode(&mut r).ok()? },
+            _ => return None,
+        };
+        if !r.is_empty() {
+            return None;
+        }
+        self.state.apply(&update);
+        self.updates += 1;
+        Some(())
+    }
+}
+
+/// Writes `payload` as a record.
+fn write_record<W: Write + ?Sized>(w: &mut W, payload: &[u8]) -> Result<(), io::Error> {
+    let len = u32::try_from(payload.len()).expect("wallet states are less than 4GB");
+    let mut record = Vec::with_capacity(4 + payload.len() + CHECKSUM_LEN);
+    record.extend_from_slice(&len.to_le_bytes());
+    record.extend_from_slice(payload);
+    record.extend_from_slice(&checksum(payload));
+    // A single write, so a crash leaves at most one truncated record.
+    w.write_all(&record)
+}
+
+/// Reads the record at the start of `bytes`, returning its payload and total length.
+fn read_record(bytes: &[u8]) -> Result<(&[u8], usize), ()> {
+    let len = bytes.get(..4).ok_or(())?;
+    let len = u32::from_le_bytes(len.try_into().expect("4 bytes")) as usize;
+    let total = len.checked_add(4 + CHECKSUM_LEN).ok_or(())?;
+    let record = bytes.get(..total).ok_or(())?;
+    let (payload, sum) = record[4..].split_at(len);
+    if checksum(payload) != sum {
+        return Err(());
+    }
+    Ok((payload, total))
+}
+
+/// Computes the checksum of a record payload.
+fn checksum(payload: &[u8]) -> [u8; CHECKSUM_LEN] {
+    let hash = sha256d::Hash::hash(payload);
+    let mut sum = [0u8; CHECKSUM_LEN];
+    sum.copy_from_slice(&hash[..CHECKSUM_LEN]);
+    sum
+}
+
+/// An error reading a checkpoint log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CheckpointError {
+    /// The data is too short to be a checkpoint log.
+    TooShort,
+    /// The data does not start with [`CHECKPOINT_MAGIC`].
+    InvalidMagic,
+    /// The log was written by a newer, unknown, version of the format.
+    UnsupportedVersion(u8),
+}
+
+internals::impl_from_infallible!(CheckpointError);
+
+impl fmt::Display for CheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use CheckpointError::*;
+
+        match *self {
+            TooShort => f.write_str("data too short to be a checkpoint log"),
+            InvalidMagic => f.write_str("invalid checkpoint log magic"),
+            UnsupportedVersion(v) => write!(f, "unsupported checkpoint log version {}", v),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CheckpointError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use CheckpointError::*;
+
+        match *self {
+            TooShort | InvalidMagic | UnsupportedVersion(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockdata::script::ScriptBuf;
+    use crate::blockdata::transaction::Txid;
+    use crate::Amount;
+
+    fn block(height: u32) -> BlockId {
+        BlockId { height, hash: BlockHash::from_byte_array([height as u8; 32]) }
+    }
+
+    fn receive(n: u8) -> Update {
+        Update::Receive {
+            outpoint: OutPoint { txid: Txid::from_byte_array([n; 32]), vout: 0 },
+            utxo: Utxo {
+                txout: TxOut {
+                    value: Amount::from_sat(1_000 * u64::from(n)),
+                    script_pubkey: ScriptBuf::from_bytes(vec![0x51]),
+                },
+                height: u32::from(n),
+            },
+        }
+    }
+
+    fn spend(n: u8) -> Update {
+        Update::Spend { outpoint: OutPoint { txid: Txid::from_byte_array([n; 32]), vout: 0 } }
+    }
+
+    /// Returns a log with a snapshot and a few updates, and the log itself.
+    fn log() -> (CheckpointLog, Vec<u8>) {
+        let mut log = CheckpointLog::new();
+        let mut bytes = Vec::new();
+        log.append(&mut Vec::new(), receive(1)).unwrap();
+        log.compact(&mut bytes).unwrap();
+        for update in [receive(2), receive(3), spend(1), Update::Tip(block(3))] {
+            log.append(&mut bytes, update).unwrap();
+        }
+        (log, bytes)
+    }
+
+    #[test]
+    fn append_and_recover() {
+        let (log, bytes) = log();
+        assert_eq!(log.updates_since_snapshot(), 4);
+        assert_eq!(log.state().tip, Some(block(3)));
+        assert_eq!(log.state().utxos.len(), 2);
+
+        let (recovered, recovery) = CheckpointLog::recover(&bytes).unwrap();
+        assert_eq!(recovered, log);
+        assert_eq!(recovery, Recovery { records: 5, valid_len: bytes.len(), corrupted: false });
+    }
+
+    #[test]
+    fn compaction() {
+        let (mut log, bytes) = log();
+        let mut compacted = Vec::new();
+        log.compact(&mut compacted).unwrap();
+        assert_eq!(log.updates_since_snapshot(), 0);
+        assert!(compacted.len() < bytes.len());
+
+        let (recovered, recovery) = CheckpointLog::recover(&compacted).unwrap();
+        assert_eq!(recovered.state(), log.state());
+        assert_eq!(recovery.records, 1);
+    }
+
+    #[test]
+    fn partial_recovery() {
+        let (log, bytes) = log();
+
+        // A crash while appending the last record.
+        let (recovered, recovery) = CheckpointLog::recover(&bytes[..bytes.len() - 3]).unwrap();
+        assert!(recovery.corrupted);
+        assert_eq!(recovery.records, 4);
+        assert_eq!(recovered.state().tip, None);
+        assert_eq!(recovered.state().utxos, log.state().utxos);
+
+        // Appending after truncating to the valid length gives back a valid log.
+        let mut truncated = bytes[..recovery.valid_len].to_vec();
+        let mut recovered = recovered;
+        recovered.append(&mut truncated, Update::Tip(block(3))).unwrap();
+        assert_eq!(truncated, bytes);
+        assert_eq!(recovered, log);
+
+        // A corrupted record ends the log.
+        let mut corrupted = bytes.clone();
+        corrupted[bytes.len() - 10] ^= 1;
+        let (_, recovery) = CheckpointLog::recover(&corrupted).unwrap();
+        assert!(recovery.corrupted);
+        assert_eq!(recovery.records, 4);
+        let mut corrupted = bytes;
+        corrupted[HEADER_LEN + 8] ^= 1;
+        let (recovered, recovery) = CheckpointLog::recover(&corrupted).unwrap();
+        assert_eq!(recovery, Recovery { records: 0, valid_len: HEADER_LEN, corrupted: true });
+        assert_eq!(recovered, CheckpointLog::new());
+    }
+
+    #[test]
+    fn invalid_header() {
+        let (_, bytes) = log();
+        assert_eq!(CheckpointLog::recover(&bytes[..4]), Err(CheckpointError::TooShort));
+
+        let mut bad = bytes.clone();
+        bad[0] = b'X';
+        assert_eq!(CheckpointLog::recover(&bad), Err(CheckpointError::InvalidMagic));
+        let mut bad = bytes;
+        bad[4] = 2;
+        assert_eq!(CheckpointLog::recover(&bad), Err(CheckpointError::UnsupportedVersion(2)));
+    }
+}
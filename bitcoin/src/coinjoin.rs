@@ -0,0 +1,656 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Collaborative transactions.
+//!
+//! In a collaborative transaction, such as a coinjoin, a coordinator collects the inputs and
+//! outputs registered by many participants and assembles them into a single transaction. Their
+//! order in the transaction must neither follow the order of registration, which would link the
+//! inputs and outputs of a participant, nor be chosen by the coordinator.
+//!
+//! [`shuffle_transaction`] orders them with a deterministic shuffle seeded by a [`ShuffleHash`]
+//! of the round identifier and of the set of registered inputs and outputs, which does not depend
+//! on the order they were registered in. Every participant can then check the transaction
+//! proposed by the coordinator with [`verify_shuffle`] before signing it.
+//!
+//! The fee of the transaction is shared between the participants with [`split_fee`], in
+//! proportion to the weight of the inputs and the number of outputs each of them registered.
+//!
+//! Outputs are registered anonymously with blind Schnorr credentials, so that the coordinator
+//! can't link them to the inputs of their owner:
+//!
+//! 1. Once a participant's inputs are registered, the [`Coordinator`] opens an issuance with
+//!    [`Coordinator::open_issuance`] and sends the [`IssuanceNonce`] to the participant.
+//! 2. The participant blinds the output they want to register with [`CredentialRequest::new`]
+//!    and sends the [blinded challenge](CredentialRequest::blinded_challenge) back.
+//! 3. The coordinator answers with [`Coordinator::sign_blinded`], which the participant turns
+//!    into a [`Credential`] with [`CredentialRequest::unblind`].
+//! 4. The participant reconnects over a new, anonymous, channel and registers the output with
+//!    [`Coordinator::register_output`], which checks the credential and that it wasn't used yet.
+//!
+//! A credential is a BIP340 signature by the coordinator key over the [`CredentialHash`] of the
+//! round and the output, which the coordinator never saw while signing it.
+//!
+//! Blind Schnorr signatures are only secure when the signer completes the issuances one after
+//! the other: with a few dozen concurrent issuances, the ROS attack forges one more signature
+//! than were issued. The coordinator therefore keeps at most one issuance open, opening an
+//! issuance abandons the previous one.
+//!
+
+use core::fmt;
+
+use hashes::{sha256, sha256t_hash_newtype, Hash, HashEngine};
+use rand::{CryptoRng, RngCore};
+
+use crate::blockdata::transaction::{Transaction, TxIn, TxOut};
+use crate::blockdata::weight::Weight;
+use crate::common::types::Message;
+use crate::consensus::Encodable;
+use crate::crypto::hashes::tagged_hash_to_scalar;
+use crate::crypto::key::{PublicKey, XOnlyPublicKey, G};
+use crate::crypto::scalar::{MaybeScalar, Scalar};
+use crate::crypto::schnorr::{verify_schnorr, Signature64};
+use crate::prelude::*;
+use crate::Amount;
+
+/// The tag of the BIP340 challenge hash.
+const CHALLENGE_TAG: &str = "BIP0340/challenge";
+
+sha256t_hash_newtype! {
+    pub struct ShuffleTag = hash_str("CoinJoin/shuffle");
+
+    /// Taproot-style tagged hash with tag \"CoinJoin/shuffle\".
+    ///
+    /// This is the seed of the shuffle of a collaborative transaction, committing to the round
+    /// identifier and to the registered inputs and outputs in canonical order.
+    #[hash_newtype(forward)]
+    pub struct ShuffleHash(_);
+
+    pub struct CredentialTag = hash_str("CoinJoin/credential");
+
+    /// Taproot-style tagged hash with tag \"CoinJoin/credential\".
+    ///
+    /// This is the message signed by a [`Credential`], committing to the round identifier and
+    /// to the output it registers.
+    #[hash_newtype(forward)]
+    pub struct CredentialHash(_);
+}
+
+/// Computes the shuffle seed of a round from its registered `inputs` and `outputs`.
+///
+/// Only the previous outputs of the inputs are committed to, so the seed doesn't change when
+/// the inputs are signed. The order of `inputs` and `outputs` doesn't matter.
+pub fn shuffle_seed(round_id: &[u8; 32], inputs: &[TxIn], outputs: &[TxOut]) -> ShuffleHash {
+    let mut inputs = inputs.iter().map(|input| input.previous_output).collect::<Vec<_>>();
+    inputs.sort_unstable();
+    let mut outputs = outputs.iter().collect::<Vec<_>>();
+    outputs.sort_unstable_by(|a, b| (a.value, &a.script_pubkey).cmp(&(b.value, &b.script_pubkey)));
+
+    let mut engine = ShuffleHash::engine();
+    engine.input(round_id);
+    (inputs.len() as u64).consensus_encode(&mut engine).expect("engines don't error");
+    for outpoint in inputs {
+        outpoint.consensus_encode(&mut engine).expect("engines don't error");
+    }
+    (outputs.len() as u64).consensus_encode(&mut engine).expect("engines don't error");
+    for output in outputs {
+        output.consensus_encode(&mut engine).expect("engines don't error");
+    }
+    ShuffleHash::from_engine(engine)
+}
+
+/// Shuffles `items` with a Fisher-Yates shuffle driven by `seed`.
+///
+/// The same seed always gives the same permutation of a slice of a given length.
+pub fn shuffle<T>(items: &mut [T], seed: &[u8; 32]) { ShuffleStream::new(seed).shuffle(items) }
+
+/// Orders the inputs and outputs of the collaborative transaction `tx` of round `round_id`.
+///
+/// The inputs are first sorted by previous output and the outputs by amount then script pubkey,
+/// as in BIP69, and both are then shuffled with the [`shuffle_seed`] of the round.
+pub fn shuffle_transaction(tx: &mut Transaction, round_id: &[u8; 32]) {
+    let seed = shuffle_seed(round_id, &tx.input, &tx.output);
+    tx.input.sort_by_key(|input| input.previous_output);
+    tx.output.sort_by(|a, b| (a.value, &a.script_pubkey).cmp(&(b.value, &b.script_pubkey)));
+
+    let mut stream = ShuffleStream::new(seed.as_byte_array());
+    stream.shuffle(&mut tx.input);
+    stream.shuffle(&mut tx.output);
+}
+
+/// Checks that the inputs and outputs of `tx` are in the order given by [`shuffle_transaction`].
+///
+/// Input scripts and witnesses are ignored, so this also holds once `tx` is signed.
+pub fn verify_shuffle(tx: &Transaction, round_id: &[u8; 32]) -> bool {
+    let mut expected = tx.clone();
+    shuffle_transaction(&mut expected, round_id);
+    expected.output == tx.output
+        && expected
+            .input
+            .iter()
+            .map(|input| input.previous_output)
+            .eq(tx.input.iter().map(|input| input.previous_output))
+}
+
+/// What a participant registered in a collaborative transaction, for sharing its fee.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FeeShare {
+    /// The total weight of the inputs of the participant, witnesses included.
+    pub input_weight: Weight,
+    /// The number of outputs of the participant.
+    pub output_count: u32,
+}
+
+/// Splits `fee` between participants in proportion to the weight of what they registered.
+///
+/// Every output counts for `output_weight`, e.g. the weight of a P2TR output. The shares are
+/// rounded down to the satoshi and the satoshis left over are given one by one to the
+/// participants with the largest remainders, the first ones on ties, so that the shares always
+/// add up to `fee` exactly.
+///
+/// Returns `None` if the participants registered nothing, as the fee can't be split.
+pub fn split_fee(fee: Amount, shares: &[FeeShare], output_weight: Weight) -> Option<Vec<Amount>> {
+    let weights = shares
+        .iter()
+        .map(|share| {
+            u128::from(share.input_weight.to_wu())
+                + u128::from(share.output_count) * u128::from(output_weight.to_wu())
+        })
+        .collect::<Vec<_>>();
+    let total = weights.iter().sum::<u128>();
+    if total == 0 {
+        return None;
+    }
+
+    let fee = u128::from(fee.to_sat());
+    let mut amounts = weights.iter().map(|weight| fee * weight / total).collect::<Vec<_>>();
+    let leftover = fee - amounts.iter().sum::<u128>();
+    let mut by_remainder = (0..shares.len()).collect::<Vec<_>>();
+    // Stable, so ties keep the order of the participants.
+    by_remainder.sort_by_key(|&i| core::cmp::Reverse(fee * weights[i] % total));
+    // The leftover is below the number of participants, each remainder being below `total`.
+    for &i in by_remainder.iter().take(leftover as usize) {
+        amounts[i] += 1;
+    }
+    Some(
+        amounts
+            .into_iter()
+            .map(|amount| Amount::from_sat(u64::try_from(amount).expect("share of a u64 fee")))
+            .collect(),
+    )
+}
+
+/// Computes the message signed by the credential registering `output` in round `round_id`.
+pub fn credential_hash(round_id: &[u8; 32], output: &TxOut) -> CredentialHash {
+    let mut engine = CredentialHash::engine();
+    engine.input(round_id);
+    output.consensus_encode(&mut engine).expect("engines don't error");
+    CredentialHash::from_engine(engine)
+}
+
+/// The nonce of an issuance, sent by the coordinator to the participant.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct IssuanceNonce {
+    /// The identifier of the issuance.
+    pub id: u64,
+    /// The public nonce `R` of the blind signature.
+    pub nonce: PublicKey,
+}
+
+/// The coordinator of a round: issues blind credentials and registers the outputs they sign.
+#[derive(Debug, Clone)]
+pub struct Coordinator {
+    round_id: [u8; 32],
+    /// The secret key, negated if needed so that its public key has an even Y-coordinate.
+    secret: Scalar,
+    public_key: XOnlyPublicKey,
+    /// The identifier and secret nonce of the open issuance.
+    issuance: Option<(u64, Scalar)>,
+    next_issuance: u64,
+    /// The nonces of the credentials already used, which identify them.
+    used: BTreeSet<[u8; 32]>,
+    outputs: Vec<TxOut>,
+}
+
+impl Coordinator {
+    /// Creates the coordinator of round `round_id`, issuing credentials with `secret`.
+    ///
+    /// The secret key should be used for this round only, participants check their credentials
+    /// against its [public key](Coordinator::public_key).
+    pub fn new(round_id: [u8; 32], secret: Scalar) -> Self {
+        let (public_key, parity) = (secret * G).x_only_public_key();
+        Coordinator {
+            round_id,
+            secret: secret.negate_if(parity),
+            public_key,
+            issuance: None,
+            next_issuance: 0,
+            used: BTreeSet::new(),
+            outputs: Vec::new(),
+        }
+    }
+
+    /// Returns the identifier of the round.
+    pub fn round_id(&self) -> [u8; 32] { self.round_id }
+
+    /// Returns the public key credentials are checked against.
+    pub fn public_key(&self) -> XOnlyPublicKey { self.public_key }
+
+    /// Returns the outputs registered so far, in registration order.
+    pub fn outputs(&self) -> &[TxOut] { &self.outputs }
+
+    /// Opens an issuance, abandoning the one still open if any.
+    ///
+    /// Which participant gets how many credentials is up to the caller, typically one per
+    /// registered output once their inputs are registered.
+    pub fn open_issuance<R: RngCore + CryptoRng>(&mut self, rng: &mut R) -> IssuanceNonce {
+        let id = self.next_issuance;
+        self.next_issuance += 1;
+        let secret_nonce = Scalar::from(k256::NonZeroScalar::random(&mut *rng));
+        self.issuance = Some((id, secret_nonce));
+        IssuanceNonce { id, nonce: secret_nonce * G }
+    }
+
+    /// Signs the blinded challenge of the open issuance `id`, which closes it.
+    ///
+    /// # Errors
+    ///
+    /// If `id` is not the open issuance, because it was already signed or abandoned.
+    pub fn sign_blinded(
+        &mut self,
+        id: u64,
+        blinded_challenge: MaybeScalar,
+    ) -> Result<MaybeScalar, CredentialError> {
+        match self.issuance {
+            Some((open, secret_nonce)) if open == id => {
+                // The secret nonce must never sign twice, so it is dropped before signing.
+                self.issuance = None;
+                Ok(secret_nonce + blinded_challenge * self.secret)
+            }
+            _ => Err(CredentialError::UnknownIssuance(id)),
+        }
+    }
+
+    /// Checks that `credential` is a valid credential of this round that wasn't used yet.
+    pub fn verify_credential(&self, credential: &Credential) -> Result<(), CredentialError> {
+        let msg = Message::from_digest(
+            credential_hash(&self.round_id, &credential.output).to_byte_array(),
+        );
+        verify_schnorr(&credential.signature, &msg, &self.public_key)
+            .map_err(|_| CredentialError::InvalidCredential)?;
+        if self.used.contains(&credential.nonce()) {
+            return Err(CredentialError::CredentialReused);
+        }
+        Ok(())
+    }
+
+    /// Checks `credential` and registers its output.
+    ///
+    /// Every credential registers a single output, registering it again fails.
+    pub fn register_output(&mut self, credential: &Credential) -> Result<(), CredentialError> {
+        self.verify_credential(credential)?;
+        self.used.insert(credential.nonce());
+        self.outputs.push(credential.output.clone());
+        Ok(())
+    }
+}
+
+/// The state of a participant blinding the request for a credential.
+#[derive(Debug, Clone)]
+pub struct CredentialRequest {
+    output: TxOut,
+    coordinator_key: PublicKey,
+    issuance: IssuanceNonce,
+    /// The blinding factor `alpha` of the signature.
+    alpha: Scalar,
+    /// The nonce `R' = R + alpha * G + beta * P` of the unblinded signature.
+    nonce: PublicKey,
+    /// The challenge `e' + beta` sent to the coordinator.
+    blinded_challenge: MaybeScalar,
+}
+
+impl CredentialRequest {
+    /// Blinds the request for a credential registering `output` in round `round_id`, signed by
+    /// `coordinator_key` with the nonce of `issuance`.
+    pub fn new<R: RngCore + CryptoRng>(
+        coordinator_key: &XOnlyPublicKey,
+        round_id: &[u8; 32],
+        output: TxOut,
+        issuance: IssuanceNonce,
+        rng: &mut R,
+    ) -> Self {
+        let coordinator_key = coordinator_key.lift_x();
+        let msg = credential_hash(round_id, &output).to_byte_array();
+        loop {
+            let alpha = Scalar::from(k256::NonZeroScalar::random(&mut *rng));
+            let beta = Scalar::from(k256::NonZeroScalar::random(&mut *rng));
+            // BIP340 nonces have an even Y-coordinate, so draw new factors until it is.
+            let nonce = match (issuance.nonce + alpha * G + beta * coordinator_key).into_option() {
+                Some(nonce) if nonce.has_even_y() => nonce,
+                _ => continue,
+            };
+            let challenge = tagged_hash_to_scalar(
+                CHALLENGE_TAG,
+                &[&nonce.serialize_xonly(), &coordinator_key.serialize_xonly(), &msg],
+            );
+            return CredentialRequest {
+                output,
+                coordinator_key,
+                issuance,
+                alpha,
+                nonce,
+                blinded_challenge: challenge + beta,
+            };
+        }
+    }
+
+    /// Returns the identifier of the issuance the request is for.
+    pub fn issuance_id(&self) -> u64 { self.issuance.id }
+
+    /// Returns the blinded challenge to send to the coordinator.
+    pub fn blinded_challenge(&self) -> MaybeScalar { self.blinded_challenge }
+
+    /// Checks the blind signature of the coordinator and unblinds it into a credential.
+    pub fn unblind(self, blind_signature: MaybeScalar) -> Result<Credential, CredentialError> {
+        let expected = self.issuance.nonce + self.blinded_challenge * self.coordinator_key;
+        if (blind_signature * G).serialize() != expected.serialize() {
+            return Err(CredentialError::InvalidBlindSignature);
+        }
+
+        let mut signature = [0u8; 64];
+        signature[..32].copy_from_slice(&self.nonce.serialize_xonly());
+        signature[32..].copy_from_slice(&(blind_signature + self.alpha).serialize());
+        Ok(Credential { output: self.output, signature: Signature64::from_byte_array(signature) })
+    }
+}
+
+/// A credential registering an output, unlinkable to the issuance that produced it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Credential {
+    /// The output registered.
+    pub output: TxOut,
+    /// The BIP340 signature of the coordinator over the [`credential_hash`] of the output.
+    pub signature: Signature64,
+}
+
+impl Credential {
+    /// Returns the nonce of the signature, which identifies the credential.
+    fn nonce(&self) -> [u8; 32] {
+        self.signature.as_byte_array()[..32].try_into().expect("32 bytes")
+    }
+}
+
+/// An error issuing or checking a blind credential.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CredentialError {
+    /// The issuance with this identifier is not open.
+    UnknownIssuance(u64),
+    /// The blind signature of the coordinator is invalid.
+    InvalidBlindSignature,
+    /// The credential is not signed by the coordinator of the round.
+    InvalidCredential,
+    /// The credential was already used to register an output.
+    CredentialReused,
+}
+
+internals::impl_from_infallible!(CredentialError);
+
+impl fmt::Display for CredentialError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use CredentialError::*;
+
+        match *self {
+            UnknownIssuance(id) => write!(f, "issuance {} is not open", id),
+            InvalidBlindSignature => f.write_str("invalid blind signature"),
+            InvalidCredential => f.write_str("invalid credential"),
+            CredentialReused => f.write_str("credential already used"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CredentialError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use CredentialError::*;
+
+        match *self {
+            UnknownIssuance(_) | InvalidBlindSignature | InvalidCredential | CredentialReused =>
+                None,
+        }
+    }
+}
+
+/// A stream of uniform random numbers derived from a seed.
+struct ShuffleStream {
+    seed: [u8; 32],
+    counter: u64,
+}
+
+impl ShuffleStream {
+    fn new(seed: &[u8; 32]) -> Self { ShuffleStream { seed: *seed, counter: 0 } }
+
+    /// Returns the next 64 bits of the stream, the start of `SHA256(seed || counter)`.
+    fn next_u64(&mut self) -> u64 {
+        let mut engine = sha256::Hash::engine();
+        engine.input(&self.seed);
+        engine.input(&self.counter.to_le_bytes());
+        self.counter += 1;
+        let hash = sha256::Hash::from_engine(engine);
+        u64::from_le_bytes(hash[..8].try_into().expect("8 bytes"))
+    }
+
+    /// Returns a uniform number in `0..n`, without modulo bias.
+    fn below(&mut self, n: u64) -> u64 {
+        // Values up to `zone` are an exact multiple of `n` in number, the rest are rejected.
+        let zone = u64::MAX - (u64::MAX - n + 1) % n;
+        loop {
+            let x = self.next_u64();
+            if x <= zone {
+                return x % n;
+            }
+        }
+    }
+
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.below(i as u64 + 1) as usize;
+            items.swap(i, j);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockdata::locktime::absolute;
+    use crate::blockdata::script::ScriptBuf;
+    use crate::blockdata::transaction::{self, OutPoint, Txid};
+    use crate::blockdata::witness::Witness;
+    use crate::Amount;
+
+    fn round_transaction() -> Transaction {
+        let input = (0..8u8)
+            .map(|i| TxIn {
+                previous_output: OutPoint { txid: Txid::from_byte_array([i; 32]), vout: 0 },
+                ..TxIn::default()
+            })
+            .collect();
+        let output = (0..8u8)
+            .map(|i| TxOut {
+                value: Amount::from_sat(10_000 * u64::from(i % 3 + 1)),
+                script_pubkey: ScriptBuf::from_bytes(vec![i]),
+            })
+            .collect();
+        Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input,
+            output,
+        }
+    }
+
+    #[test]
+    fn shuffle_is_independent_of_registration_order() {
+        let round_id = [0x42; 32];
+        let mut tx = round_transaction();
+        let mut reordered = round_transaction();
+        reordered.input.reverse();
+        reordered.output.rotate_left(3);
+
+        shuffle_transaction(&mut tx, &round_id);
+        shuffle_transaction(&mut reordered, &round_id);
+        assert_eq!(tx, reordered);
+        assert!(verify_shuffle(&tx, &round_id));
+
+        // Another round gives another order.
+        let mut other = round_transaction();
+        shuffle_transaction(&mut other, &[0x43; 32]);
+        assert_ne!(tx, other);
+        assert!(!verify_shuffle(&tx, &[0x43; 32]));
+    }
+
+    #[test]
+    fn verify_shuffle_detects_reordering() {
+        let round_id = [0x42; 32];
+        let mut tx = round_transaction();
+        shuffle_transaction(&mut tx, &round_id);
+
+        // Signing doesn't change the order.
+        let mut signed = tx.clone();
+        signed.input[0].witness = Witness::from_slice(&[[1u8; 64]]);
+        assert!(verify_shuffle(&signed, &round_id));
+
+        let mut swapped = tx.clone();
+        swapped.output.swap(0, 1);
+        assert!(!verify_shuffle(&swapped, &round_id));
+        let mut swapped = tx;
+        swapped.input.swap(2, 5);
+        assert!(!verify_shuffle(&swapped, &round_id));
+    }
+
+    #[test]
+    fn shuffle_permutes() {
+        let mut items: Vec<u32> = (0..100).collect();
+        shuffle(&mut items, &[7; 32]);
+        let mut again: Vec<u32> = (0..100).collect();
+        shuffle(&mut again, &[7; 32]);
+        assert_eq!(items, again);
+        assert_ne!(items, (0..100).collect::<Vec<_>>());
+
+        items.sort_unstable();
+        assert_eq!(items, (0..100).collect::<Vec<_>>());
+
+        let mut empty: [u32; 0] = [];
+        shuffle(&mut empty, &[7; 32]);
+    }
+
+    #[test]
+    fn split_fee_largest_remainder() {
+        let output_weight = Weight::from_wu(172);
+        let share = |input_weight, output_count| FeeShare {
+            input_weight: Weight::from_wu(input_weight),
+            output_count,
+        };
+
+        // Weights of 400, 400 and 200: exact shares.
+        let shares = [share(228, 1), share(56, 2), share(200, 0)];
+        let fee = Amount::from_sat(1000);
+        let split = split_fee(fee, &shares, output_weight).unwrap();
+        assert_eq!(split, [Amount::from_sat(400), Amount::from_sat(400), Amount::from_sat(200)]);
+
+        // A third each, the leftover satoshis go to the first participants.
+        let shares = [share(100, 0); 3];
+        let split = split_fee(Amount::from_sat(1001), &shares, output_weight).unwrap();
+        assert_eq!(split, [Amount::from_sat(334), Amount::from_sat(334), Amount::from_sat(333)]);
+
+        // Shares of 1.5, 2.25 and 6.25: the largest remainder gets the leftover.
+        let shares = [share(150, 0), share(225, 0), share(625, 0)];
+        let split = split_fee(Amount::from_sat(10), &shares, output_weight).unwrap();
+        assert_eq!(split, [Amount::from_sat(2), Amount::from_sat(2), Amount::from_sat(6)]);
+
+        assert_eq!(split_fee(fee, &[], output_weight), None);
+        assert_eq!(
+            split_fee(
+                fee,
+                &[FeeShare { input_weight: Weight::ZERO, output_count: 0 }],
+                output_weight
+            ),
+            None
+        );
+        assert_eq!(split_fee(Amount::ZERO, &shares, output_weight).unwrap(), [Amount::ZERO; 3]);
+    }
+
+    #[test]
+    fn blind_credentials() {
+        let mut rng = rand::thread_rng();
+        let round_id = [0x42; 32];
+        let mut coordinator = Coordinator::new(round_id, Scalar::from_u32(0xc01d).unwrap());
+        let key = coordinator.public_key();
+        let outputs = round_transaction().output;
+
+        let mut credentials = Vec::new();
+        for output in &outputs[..2] {
+            let issuance = coordinator.open_issuance(&mut rng);
+            let request =
+                CredentialRequest::new(&key, &round_id, output.clone(), issuance, &mut rng);
+            let blind_signature =
+                coordinator.sign_blinded(issuance.id, request.blinded_challenge()).unwrap();
+            assert_eq!(
+                coordinator.sign_blinded(issuance.id, request.blinded_challenge()),
+                Err(CredentialError::UnknownIssuance(issuance.id))
+            );
+            // The coordinator never sees the signature it issued.
+            let credential = request.clone().unblind(blind_signature).unwrap();
+            assert_ne!(
+                credential.signature.as_byte_array()[..32],
+                issuance.nonce.serialize_xonly()
+            );
+            assert_eq!(
+                request.unblind(blind_signature + Scalar::one()),
+                Err(CredentialError::InvalidBlindSignature)
+            );
+            credentials.push(credential);
+        }
+
+        // Opening an issuance abandons the previous one.
+        let abandoned = coordinator.open_issuance(&mut rng);
+        coordinator.open_issuance(&mut rng);
+        assert_eq!(
+            coordinator.sign_blinded(abandoned.id, MaybeScalar::one()),
+            Err(CredentialError::UnknownIssuance(abandoned.id))
+        );
+
+        for credential in &credentials {
+            coordinator.register_output(credential).unwrap();
+        }
+        assert_eq!(coordinator.outputs(), &outputs[..2]);
+        assert_eq!(
+            coordinator.register_output(&credentials[0]),
+            Err(CredentialError::CredentialReused)
+        );
+
+        let mut forged = credentials[1].clone();
+        forged.output = outputs[2].clone();
+        assert_eq!(coordinator.verify_credential(&forged), Err(CredentialError::InvalidCredential));
+        let other_round = Coordinator::new([0x43; 32], Scalar::from_u32(0xc01d).unwrap());
+        assert_eq!(
+            other_round.verify_credential(&credentials[0]),
+            Err(CredentialError::InvalidCredential)
+        );
+    }
+
+    #[test]
+    fn split_fee_sums_to_fee() {
+        let shares = (1..20u64)
+            .map(|i| FeeShare {
+                input_weight: Weight::from_wu(i * 97 % 31),
+                output_count: i as u32 % 4,
+            })
+            .collect::<Vec<_>>();
+        for fee in [0, 1, 7, 1_000, 123_457, Amount::MAX_MONEY.to_sat()] {
+            let fee = Amount::from_sat(fee);
+            let split = split_fee(fee, &shares, Weight::from_wu(124)).unwrap();
+            assert_eq!(split.iter().copied().sum::<Amount>(), fee);
+        }
+    }
+}
@@ -8,6 +8,7 @@
 
 pub mod encode;
 pub mod params;
+pub mod script_verify;
 #[cfg(feature = "serde")]
 pub mod serde;
 #[cfg(feature = "bitcoinconsensus")]
@@ -25,6 +26,7 @@ use crate::consensus;
 pub use self::{
     encode::{deserialize, deserialize_partial, serialize, Decodable, Encodable, ReadExt, WriteExt},
     params::Params,
+    script_verify::ScriptVerifyFlags,
 };
 
 #[cfg(feature = "bitcoinconsensus")]
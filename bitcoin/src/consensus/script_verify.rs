@@ -0,0 +1,568 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Script verification flags.
+//!
+//! This module defines [`ScriptVerifyFlags`], mirroring the `SCRIPT_VERIFY_*` flags of Bitcoin
+//! Core, along with the flag sets used for consensus and for standardness, and the historical
+//! flag sets used to validate old blocks.
+//!
+//! With the `bitcoinconsensus` feature enabled, [`verify_script`] checks a script signature and
+//! witness against a script pubkey under a set of flags.
+//!
+
+use core::fmt;
+use core::ops;
+use core::str::FromStr;
+
+#[cfg(feature = "bitcoinconsensus")]
+use internals::write_err;
+
+#[cfg(feature = "bitcoinconsensus")]
+use crate::amount::Amount;
+#[cfg(feature = "bitcoinconsensus")]
+use crate::blockdata::script::Script;
+#[cfg(feature = "bitcoinconsensus")]
+use crate::blockdata::witness::Witness;
+#[cfg(feature = "bitcoinconsensus")]
+use crate::consensus::validation::BitcoinconsensusError;
+use crate::consensus::Params;
+use crate::network::Network;
+use crate::prelude::*;
+
+/// A set of script verification flags, as used by Bitcoin Core.
+///
+/// The values of the flags match the `SCRIPT_VERIFY_*` constants of Bitcoin Core.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ScriptVerifyFlags(u32);
+
+impl ScriptVerifyFlags {
+    /// No flags.
+    pub const NONE: Self = Self(0);
+
+    /// Evaluate P2SH subscripts (BIP16).
+    pub const P2SH: Self = Self(1 << 0);
+
+    /// Require strict encoding of signatures and public keys.
+    pub const STRICTENC: Self = Self(1 << 1);
+
+    /// Require strict DER encoding of signatures (BIP66).
+    pub const DERSIG: Self = Self(1 << 2);
+
+    /// Require low S values in signatures (BIP146).
+    pub const LOW_S: Self = Self(1 << 3);
+
+    /// Require the dummy element of `OP_CHECKMULTISIG` to be empty (BIP147).
+    pub const NULLDUMMY: Self = Self(1 << 4);
+
+    /// Require script signatures to be push only.
+    pub const SIGPUSHONLY: Self = Self(1 << 5);
+
+    /// Require minimal encodings of pushes and numbers.
+    pub const MINIMALDATA: Self = Self(1 << 6);
+
+    /// Reject the upgradable `OP_NOP` opcodes.
+    pub const DISCOURAGE_UPGRADABLE_NOPS: Self = Self(1 << 7);
+
+    /// Require exactly one element on the stack after evaluation.
+    pub const CLEANSTACK: Self = Self(1 << 8);
+
+    /// Verify `OP_CHECKLOCKTIMEVERIFY` (BIP65).
+    pub const CHECKLOCKTIMEVERIFY: Self = Self(1 << 9);
+
+    /// Verify `OP_CHECKSEQUENCEVERIFY` (BIP112).
+    pub const CHECKSEQUENCEVERIFY: Self = Self(1 << 10);
+
+    /// Verify witness programs (BIP141).
+    pub const WITNESS: Self = Self(1 << 11);
+
+    /// Reject witness programs of unknown versions.
+    pub const DISCOURAGE_UPGRADABLE_WITNESS_PROGRAM: Self = Self(1 << 12);
+
+    /// Require the argument of `OP_IF` and `OP_NOTIF` to be minimal in segwit scripts.
+    pub const MINIMALIF: Self = Self(1 << 13);
+
+    /// Require failed signature checks to use empty signatures (BIP146).
+    pub const NULLFAIL: Self = Self(1 << 14);
+
+    /// Require compressed public keys in segwit scripts.
+    pub const WITNESS_PUBKEYTYPE: Self = Self(1 << 15);
+
+    /// Reject `OP_CODESEPARATOR` and signatures in the script code of non-segwit scripts.
+    pub const CONST_SCRIPTCODE: Self = Self(1 << 16);
+
+    /// Verify taproot spends (BIP341 and BIP342).
+    pub const TAPROOT: Self = Self(1 << 17);
+
+    /// Reject taproot leaf versions which are not defined yet.
+    pub const DISCOURAGE_UPGRADABLE_TAPROOT_VERSION: Self = Self(1 << 18);
+
+    /// Reject the `OP_SUCCESS` opcodes in tapscript.
+    pub const DISCOURAGE_OP_SUCCESS: Self = Self(1 << 19);
+
+    /// Reject public keys of unknown types in tapscript.
+    pub const DISCOURAGE_UPGRADABLE_PUBKEYTYPE: Self = Self(1 << 20);
+
+    /// The consensus rules enforced for new blocks.
+    pub const CONSENSUS: Self = Self(
+        Self::P2SH.0
+            | Self::DERSIG.0
+            | Self::NULLDUMMY.0
+            | Self::CHECKLOCKTIMEVERIFY.0
+            | Self::CHECKSEQUENCEVERIFY.0
+            | Self::WITNESS.0
+            | Self::TAPROOT.0,
+    );
+
+    /// The rules enforced for transactions relayed by Bitcoin Core, the
+    /// `STANDARD_SCRIPT_VERIFY_FLAGS`.
+    pub const STANDARD: Self = Self(
+        Self::CONSENSUS.0
+            | Self::STRICTENC.0
+            | Self::LOW_S.0
+            | Self::MINIMALDATA.0
+            | Self::DISCOURAGE_UPGRADABLE_NOPS.0
+            | Self::CLEANSTACK.0
+            | Self::DISCOURAGE_UPGRADABLE_WITNESS_PROGRAM.0
+            | Self::MINIMALIF.0
+            | Self::NULLFAIL.0
+            | Self::WITNESS_PUBKEYTYPE.0
+            | Self::CONST_SCRIPTCODE.0
+            | Self::DISCOURAGE_UPGRADABLE_TAPROOT_VERSION.0
+            | Self::DISCOURAGE_OP_SUCCESS.0
+            | Self::DISCOURAGE_UPGRADABLE_PUBKEYTYPE.0,
+    );
+
+    /// The flags which can be checked by [`verify_script`].
+    pub const LIBCONSENSUS: Self = Self(
+        Self::P2SH.0
+            | Self::DERSIG.0
+            | Self::NULLDUMMY.0
+            | Self::CHECKLOCKTIMEVERIFY.0
+            | Self::CHECKSEQUENCEVERIFY.0
+            | Self::WITNESS.0,
+    );
+
+    /// Every known flag.
+    const ALL: Self = Self((1 << 21) - 1);
+
+    /// Creates flags from their integer representation.
+    ///
+    /// Returns `None` if any unknown bit is set.
+    pub fn from_bits(bits: u32) -> Option<Self> {
+        if bits & !Self::ALL.0 == 0 {
+            Some(Self(bits))
+        } else {
+            None
+        }
+    }
+
+    /// Gets the integer representation of the flags.
+    pub fn to_u32(self) -> u32 { self.0 }
+
+    /// Checks whether all of `flags` are set.
+    pub fn contains(self, flags: Self) -> bool { self.0 & flags.0 == flags.0 }
+
+    /// Checks whether no flag is set.
+    pub fn is_empty(self) -> bool { self.0 == 0 }
+
+    /// Returns the consensus flags in effect for the block at `height`.
+    ///
+    /// This follows `GetBlockScriptFlags` of Bitcoin Core: P2SH, segwit and taproot are enforced
+    /// from genesis as buried deployments, while the other soft forks are enforced from their
+    /// activation heights. The two mainnet blocks which are exempt from P2SH or taproot validation
+    /// are not special cased.
+    pub fn for_block(params: impl AsRef<Params>, height: u32) -> Self {
+        let params = params.as_ref();
+        let (csv_height, segwit_height) = match params.network {
+            Network::Bitcoin => (419_328, 481_824),
+            Network::Testnet => (770_112, 834_624),
+            Network::Signet => (1, 1),
+            Network::Regtest => (1, 0),
+        };
+
+        let mut flags = Self::P2SH | Self::WITNESS | Self::TAPROOT;
+        if height >= params.bip66_height {
+            flags |= Self::DERSIG;
+        }
+        if height >= params.bip65_height {
+            flags |= Self::CHECKLOCKTIMEVERIFY;
+        }
+        if height >= csv_height {
+            flags |= Self::CHECKSEQUENCEVERIFY;
+        }
+        if height >= segwit_height {
+            flags |= Self::NULLDUMMY;
+        }
+        flags
+    }
+}
+
+/// The names of the flags, as used in the Bitcoin Core script tests.
+const FLAG_NAMES: [(ScriptVerifyFlags, &str); 21] = [
+    (ScriptVerifyFlags::P2SH, "P2SH"),
+    (ScriptVerifyFlags::STRICTENC, "STRICTENC"),
+    (ScriptVerifyFlags::DERSIG, "DERSIG"),
+    (ScriptVerifyFlags::LOW_S, "LOW_S"),
+    (ScriptVerifyFlags::NULLDUMMY, "NULLDUMMY"),
+    (ScriptVerifyFlags::SIGPUSHONLY, "SIGPUSHONLY"),
+    (ScriptVerifyFlags::MINIMALDATA, "MINIMALDATA"),
+    (ScriptVerifyFlags::DISCOURAGE_UPGRADABLE_NOPS, "DISCOURAGE_UPGRADABLE_NOPS"),
+    (ScriptVerifyFlags::CLEANSTACK, "CLEANSTACK"),
+    (ScriptVerifyFlags::CHECKLOCKTIMEVERIFY, "CHECKLOCKTIMEVERIFY"),
+    (ScriptVerifyFlags::CHECKSEQUENCEVERIFY, "CHECKSEQUENCEVERIFY"),
+    (ScriptVerifyFlags::WITNESS, "WITNESS"),
+    (
+        ScriptVerifyFlags::DISCOURAGE_UPGRADABLE_WITNESS_PROGRAM,
+        "DISCOURAGE_UPGRADABLE_WITNESS_PROGRAM",
+    ),
+    (ScriptVerifyFlags::MINIMALIF, "MINIMALIF"),
+    (ScriptVerifyFlags::NULLFAIL, "NULLFAIL"),
+    (ScriptVerifyFlags::WITNESS_PUBKEYTYPE, "WITNESS_PUBKEYTYPE"),
+    (ScriptVerifyFlags::CONST_SCRIPTCODE, "CONST_SCRIPTCODE"),
+    (ScriptVerifyFlags::TAPROOT, "TAPROOT"),
+    (
+        ScriptVerifyFlags::DISCOURAGE_UPGRADABLE_TAPROOT_VERSION,
+        "DISCOURAGE_UPGRADABLE_TAPROOT_VERSION",
+    ),
+    (ScriptVerifyFlags::DISCOURAGE_OP_SUCCESS, "DISCOURAGE_OP_SUCCESS"),
+    (ScriptVerifyFlags::DISCOURAGE_UPGRADABLE_PUBKEYTYPE, "DISCOURAGE_UPGRADABLE_PUBKEYTYPE"),
+];
+
+/// Formats the flags as a comma separated list of names, e.g. `P2SH,DERSIG`, or `NONE`.
+impl fmt::Display for ScriptVerifyFlags {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_empty() {
+            return f.write_str("NONE");
+        }
+        let mut first = true;
+        for (flag, name) in FLAG_NAMES.iter() {
+            if self.contains(*flag) {
+                if !first {
+                    f.write_str(",")?;
+                }
+                first = false;
+                f.write_str(name)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses a comma separated list of flag names, as used in the Bitcoin Core script tests.
+impl FromStr for ScriptVerifyFlags {
+    type Err = UnknownScriptVerifyFlagError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut flags = Self::NONE;
+        for name in s.split(',').map(str::trim) {
+            if name == "NONE" || name.is_empty() {
+                continue;
+            }
+            match FLAG_NAMES.iter().find(|(_, n)| *n == name) {
+                Some((flag, _)) => flags |= *flag,
+                None => return Err(UnknownScriptVerifyFlagError(name.to_owned())),
+            }
+        }
+        Ok(flags)
+    }
+}
+
+impl From<ScriptVerifyFlags> for u32 {
+    fn from(flags: ScriptVerifyFlags) -> Self { flags.0 }
+}
+
+impl ops::BitOr for ScriptVerifyFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self { Self(self.0 | rhs.0) }
+}
+
+impl ops::BitOrAssign for ScriptVerifyFlags {
+    fn bitor_assign(&mut self, rhs: Self) { self.0 |= rhs.0 }
+}
+
+impl ops::BitAnd for ScriptVerifyFlags {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self { Self(self.0 & rhs.0) }
+}
+
+impl ops::BitAndAssign for ScriptVerifyFlags {
+    fn bitand_assign(&mut self, rhs: Self) { self.0 &= rhs.0 }
+}
+
+/// Removes the flags of `rhs`.
+impl ops::Sub for ScriptVerifyFlags {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self { Self(self.0 & !rhs.0) }
+}
+
+impl ops::SubAssign for ScriptVerifyFlags {
+    fn sub_assign(&mut self, rhs: Self) { self.0 &= !rhs.0 }
+}
+
+/// Verifies a script signature and witness against a script pubkey.
+///
+/// The spend is checked the same way as in the Bitcoin Core script tests: the script pubkey is
+/// locked in an output of `amount` by a crediting transaction, which is spent by a transaction
+/// with a single input carrying `script_sig` and `witness`. Signatures must therefore commit to
+/// this spending transaction.
+///
+/// # Errors
+///
+/// If `flags` contains flags which are not in [`ScriptVerifyFlags::LIBCONSENSUS`], or if the
+/// verification fails.
+#[cfg(feature = "bitcoinconsensus")]
+pub fn verify_script(
+    script_sig: &Script,
+    script_pubkey: &Script,
+    witness: &Witness,
+    amount: Amount,
+    flags: ScriptVerifyFlags,
+) -> Result<(), ScriptVerifyError> {
+    let unsupported = flags - ScriptVerifyFlags::LIBCONSENSUS;
+    if !unsupported.is_empty() {
+        return Err(ScriptVerifyError::UnsupportedFlags(unsupported));
+    }
+
+    let spending_tx = spending_transaction(script_sig, script_pubkey, witness, amount);
+    crate::consensus::validation::verify_script_with_flags(
+        script_pubkey,
+        0,
+        amount,
+        &crate::consensus::encode::serialize(&spending_tx),
+        flags,
+    )?;
+    Ok(())
+}
+
+/// Builds the transaction spending `script_pubkey`, like `BuildSpendingTransaction` of the Bitcoin
+/// Core script tests.
+#[cfg(any(test, feature = "bitcoinconsensus"))]
+fn spending_transaction(
+    script_sig: &crate::blockdata::script::Script,
+    script_pubkey: &crate::blockdata::script::Script,
+    witness: &crate::blockdata::witness::Witness,
+    amount: crate::amount::Amount,
+) -> crate::blockdata::transaction::Transaction {
+    use crate::blockdata::locktime::absolute;
+    use crate::blockdata::script::{Builder, ScriptBuf};
+    use crate::blockdata::transaction::{OutPoint, Sequence, Transaction, TxIn, TxOut, Version};
+    use crate::blockdata::witness::Witness;
+
+    let crediting_tx = Transaction {
+        version: Version::ONE,
+        lock_time: absolute::LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint::null(),
+            script_sig: Builder::new().push_int(0).push_int(0).into_script(),
+            sequence: Sequence::MAX,
+            witness: Witness::new(),
+        }],
+        output: vec![TxOut { value: amount, script_pubkey: script_pubkey.to_owned() }],
+    };
+    Transaction {
+        version: Version::ONE,
+        lock_time: absolute::LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint { txid: crediting_tx.compute_txid(), vout: 0 },
+            script_sig: script_sig.to_owned(),
+            sequence: Sequence::MAX,
+            witness: witness.clone(),
+        }],
+        output: vec![TxOut { value: amount, script_pubkey: ScriptBuf::new() }],
+    }
+}
+
+/// Error parsing [`ScriptVerifyFlags`] from a string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct UnknownScriptVerifyFlagError(pub String);
+
+internals::impl_from_infallible!(UnknownScriptVerifyFlagError);
+
+impl fmt::Display for UnknownScriptVerifyFlagError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown script verification flag '{}'", self.0)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for UnknownScriptVerifyFlagError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> { None }
+}
+
+/// An error verifying a script with [`verify_script`].
+#[cfg(feature = "bitcoinconsensus")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ScriptVerifyError {
+    /// The flags can not be checked by the `bitcoinconsensus` library.
+    UnsupportedFlags(ScriptVerifyFlags),
+    /// The script verification failed.
+    Consensus(BitcoinconsensusError),
+}
+
+#[cfg(feature = "bitcoinconsensus")]
+internals::impl_from_infallible!(ScriptVerifyError);
+
+#[cfg(feature = "bitcoinconsensus")]
+impl fmt::Display for ScriptVerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use ScriptVerifyError::*;
+
+        match *self {
+            UnsupportedFlags(flags) => write!(f, "unsupported script verification flags {}", flags),
+            Consensus(ref e) => write_err!(f, "script verification failed"; e),
+        }
+    }
+}
+
+#[cfg(all(feature = "std", feature = "bitcoinconsensus"))]
+impl std::error::Error for ScriptVerifyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use ScriptVerifyError::*;
+
+        match *self {
+            UnsupportedFlags(_) => None,
+            Consensus(ref e) => Some(e),
+        }
+    }
+}
+
+#[cfg(feature = "bitcoinconsensus")]
+impl From<BitcoinconsensusError> for ScriptVerifyError {
+    fn from(e: BitcoinconsensusError) -> Self { Self::Consensus(e) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_roundtrip() {
+        assert_eq!(ScriptVerifyFlags::NONE.to_string(), "NONE");
+        assert_eq!("NONE".parse(), Ok(ScriptVerifyFlags::NONE));
+        assert_eq!(
+            (ScriptVerifyFlags::DERSIG | ScriptVerifyFlags::P2SH).to_string(),
+            "P2SH,DERSIG"
+        );
+        assert_eq!(
+            "P2SH, WITNESS".parse(),
+            Ok(ScriptVerifyFlags::P2SH | ScriptVerifyFlags::WITNESS)
+        );
+        assert_eq!(
+            "P2SH,FOO".parse::<ScriptVerifyFlags>(),
+            Err(UnknownScriptVerifyFlagError("FOO".to_owned()))
+        );
+
+        let standard = ScriptVerifyFlags::STANDARD;
+        assert_eq!(standard.to_string().parse(), Ok(standard));
+        assert_eq!(ScriptVerifyFlags::from_bits(standard.to_u32()), Some(standard));
+        assert_eq!(ScriptVerifyFlags::from_bits(1 << 21), None);
+        assert!(standard.contains(ScriptVerifyFlags::CONSENSUS));
+        assert!(ScriptVerifyFlags::CONSENSUS.contains(ScriptVerifyFlags::LIBCONSENSUS));
+        assert_eq!(
+            ScriptVerifyFlags::CONSENSUS - ScriptVerifyFlags::LIBCONSENSUS,
+            ScriptVerifyFlags::TAPROOT
+        );
+    }
+
+    #[test]
+    fn flags_for_block() {
+        let base =
+            ScriptVerifyFlags::P2SH | ScriptVerifyFlags::WITNESS | ScriptVerifyFlags::TAPROOT;
+        assert_eq!(ScriptVerifyFlags::for_block(Network::Bitcoin, 170), base);
+        assert_eq!(
+            ScriptVerifyFlags::for_block(Network::Bitcoin, 363_725),
+            base | ScriptVerifyFlags::DERSIG
+        );
+        assert_eq!(
+            ScriptVerifyFlags::for_block(Network::Bitcoin, 419_328),
+            base | ScriptVerifyFlags::DERSIG
+                | ScriptVerifyFlags::CHECKLOCKTIMEVERIFY
+                | ScriptVerifyFlags::CHECKSEQUENCEVERIFY
+        );
+        assert_eq!(
+            ScriptVerifyFlags::for_block(Network::Bitcoin, 800_000),
+            ScriptVerifyFlags::CONSENSUS
+        );
+        assert_eq!(
+            ScriptVerifyFlags::for_block(Network::Regtest, 2000),
+            ScriptVerifyFlags::CONSENSUS
+        );
+    }
+
+    #[test]
+    fn spending_transaction_shape() {
+        use crate::blockdata::script::ScriptBuf;
+        use crate::blockdata::witness::Witness;
+
+        // Only checks the shape of the transaction, `verify_script_p2wpkh` checks a spend of it.
+        let script_pubkey = ScriptBuf::from_bytes(vec![0x51]);
+        let tx = super::spending_transaction(
+            &ScriptBuf::new(),
+            &script_pubkey,
+            &Witness::new(),
+            crate::Amount::ZERO,
+        );
+        assert_eq!(tx.input.len(), 1);
+        assert_eq!(tx.input[0].previous_output.vout, 0);
+        assert_eq!(tx.output[0].value, crate::Amount::ZERO);
+        assert!(tx.output[0].script_pubkey.is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "bitcoinconsensus")]
+    fn verify_script_p2wpkh() {
+        use hashes::Hash;
+
+        use crate::blockdata::script::ScriptBuf;
+        use crate::common::types::Message;
+        use crate::crypto::ecdsa::{self, sign_ecdsa};
+        use crate::crypto::key::PrivateKey;
+        use crate::crypto::scalar::Scalar;
+        use crate::crypto::sighash::{EcdsaSighashType, SighashCache};
+        use crate::NetworkKind;
+
+        let priv_key = PrivateKey::from_slice(&[0x2a; 32], NetworkKind::Test).unwrap();
+        let pk = priv_key.public_key();
+        let script_pubkey = ScriptBuf::new_p2wpkh(&pk.wpubkey_hash().unwrap());
+        let amount = Amount::from_sat(10_000);
+
+        // The sighash of the spending transaction doesn't depend on its witness.
+        let tx =
+            super::spending_transaction(Script::new(), &script_pubkey, &Witness::new(), amount);
+        let sighash = SighashCache::new(&tx)
+            .p2wpkh_signature_hash(0, &script_pubkey, amount, EcdsaSighashType::All)
+            .unwrap();
+        let msg = Message::from_digest(sighash.to_byte_array());
+        let secret = Scalar::from(&priv_key.inner);
+        let signature = ecdsa::Signature::sighash_all(sign_ecdsa(&msg, &secret));
+        let witness = Witness::p2wpkh(&signature, &pk);
+
+        let flags = ScriptVerifyFlags::LIBCONSENSUS;
+        assert_eq!(verify_script(Script::new(), &script_pubkey, &witness, amount, flags), Ok(()));
+        // The signature commits to the amount of the spent output.
+        let other_amount = amount - Amount::ONE_SAT;
+        assert!(matches!(
+            verify_script(Script::new(), &script_pubkey, &witness, other_amount, flags),
+            Err(ScriptVerifyError::Consensus(_))
+        ));
+        assert!(matches!(
+            verify_script(Script::new(), &script_pubkey, &Witness::new(), amount, flags),
+            Err(ScriptVerifyError::Consensus(_))
+        ));
+        assert_eq!(
+            verify_script(
+                Script::new(),
+                &script_pubkey,
+                &witness,
+                amount,
+                ScriptVerifyFlags::CONSENSUS
+            ),
+            Err(ScriptVerifyError::UnsupportedFlags(ScriptVerifyFlags::TAPROOT))
+        );
+    }
+}
@@ -39,12 +39,12 @@ mod inner_operator_impl {
     use super::*;
 
     /// `Scalar` + `Scalar`
-    impl std::ops::Add<Scalar> for Scalar {
+    impl core::ops::Add<Scalar> for Scalar {
         type Output = MaybeScalar;
 
         fn add(self, other: Scalar) -> Self::Output {
             let inner_result: Option<k256::NonZeroScalar> =
-                (k256::NonZeroScalar::new(self.inner.as_ref() + other.inner.as_ref())).into();
+                (k256::NonZeroScalar::new(self.inner + other.inner)).into();
             inner_result
                 .map(MaybeScalar::from)
                 .unwrap_or(MaybeScalar::Zero)
@@ -52,7 +52,7 @@ mod inner_operator_impl {
     }
 
     /// `PublicKey` + `PublicKey`
-    impl std::ops::Add<PublicKey> for PublicKey {
+    impl core::ops::Add<PublicKey> for PublicKey {
         type Output = MaybePublicKey;
         fn add(self, other: PublicKey) -> Self::Output {
             let inner_result =
@@ -64,26 +64,28 @@ mod inner_operator_impl {
     }
 
     /// Note: `Scalar` * `Scalar` always outputs a non-zero `Scalar`.
-    impl std::ops::Mul<Scalar> for Scalar {
+    impl core::ops::Mul<Scalar> for Scalar {
         type Output = Scalar;
         fn mul(self, other: Scalar) -> Self::Output {
-            Scalar::from(self.inner * other.inner)
+            Scalar {
+                inner: self.inner * other.inner,
+            }
         }
     }
 
     /// `PublicKey` * `Scalar`
-    impl std::ops::Mul<Scalar> for PublicKey {
+    impl core::ops::Mul<Scalar> for PublicKey {
         type Output = PublicKey;
         fn mul(self, scalar: Scalar) -> Self::Output {
             let nonidentity =
                 k256::elliptic_curve::point::NonIdentity::new(self.inner.to_projective()).unwrap();
-            let inner = k256::PublicKey::from(nonidentity * scalar.inner);
+            let inner = k256::PublicKey::from(nonidentity * scalar.to_nonzero_scalar());
             PublicKey::new(inner)
         }
     }
 
     /// `Scalar` * `PublicKey`
-    impl std::ops::Mul<PublicKey> for Scalar {
+    impl core::ops::Mul<PublicKey> for Scalar {
         type Output = PublicKey;
         fn mul(self, public_key: PublicKey) -> Self::Output {
             public_key * self
@@ -91,16 +93,15 @@ mod inner_operator_impl {
     }
 
     /// -`Scalar`
-    impl std::ops::Neg for Scalar {
+    impl core::ops::Neg for Scalar {
         type Output = Scalar;
         fn neg(self) -> Self::Output {
-            let inner = -self.inner;
-            Scalar::from(inner)
+            Scalar { inner: -self.inner }
         }
     }
 
     /// -`MaybeScalar`
-    impl std::ops::Neg for MaybeScalar {
+    impl core::ops::Neg for MaybeScalar {
         type Output = MaybeScalar;
         fn neg(self) -> Self::Output {
             self.into_option()
@@ -110,7 +111,7 @@ mod inner_operator_impl {
     }
 
     /// `-PublicKey`
-    impl std::ops::Neg for PublicKey {
+    impl core::ops::Neg for PublicKey {
         type Output = PublicKey;
         fn neg(self) -> Self::Output {
             PublicKey::new(k256::PublicKey::from_affine(-self.inner.as_affine().clone()).unwrap())
@@ -118,7 +119,7 @@ mod inner_operator_impl {
     }
 
     /// `-MaybePublicKey`
-    impl std::ops::Neg for MaybePublicKey {
+    impl core::ops::Neg for MaybePublicKey {
         type Output = MaybePublicKey;
         fn neg(self) -> Self::Output {
             self.into_option()
@@ -132,7 +133,7 @@ mod generator_ops {
     use super::*;
 
     /// `G` + `G`s
-    impl std::ops::Add<G> for G {
+    impl core::ops::Add<G> for G {
         type Output = PublicKey;
         fn add(self, _: G) -> Self::Output {
             Scalar::two().base_point_mul()
@@ -140,7 +141,7 @@ mod generator_ops {
     }
 
     /// `Scalar` * `G`
-    impl std::ops::Mul<G> for Scalar {
+    impl core::ops::Mul<G> for Scalar {
         type Output = PublicKey;
         fn mul(self, _: G) -> Self::Output {
             self.base_point_mul()
@@ -148,7 +149,7 @@ mod generator_ops {
     }
 
     /// `G` * `Scalar`
-    impl std::ops::Mul<Scalar> for G {
+    impl core::ops::Mul<Scalar> for G {
         type Output = PublicKey;
         fn mul(self, scalar: Scalar) -> Self::Output {
             scalar.base_point_mul()
@@ -156,7 +157,7 @@ mod generator_ops {
     }
 
     /// `-G`
-    impl std::ops::Neg for G {
+    impl core::ops::Neg for G {
         type Output = PublicKey;
         fn neg(self) -> Self::Output {
             -PublicKey::generator()
@@ -164,6 +165,41 @@ mod generator_ops {
     }
 }
 
+mod iter_impls {
+    use super::*;
+
+    /// Sums scalars of any type which can be added to a `MaybeScalar`. The empty sum is zero.
+    impl<T> core::iter::Sum<T> for MaybeScalar
+    where
+        MaybeScalar: core::ops::Add<T, Output = MaybeScalar>,
+    {
+        fn sum<I: Iterator<Item = T>>(iter: I) -> Self {
+            iter.fold(MaybeScalar::Zero, |acc, x| acc + x)
+        }
+    }
+
+    /// Multiplies scalars of any type which can multiply a `MaybeScalar`. The empty product is one.
+    impl<T> core::iter::Product<T> for MaybeScalar
+    where
+        MaybeScalar: core::ops::Mul<T, Output = MaybeScalar>,
+    {
+        fn product<I: Iterator<Item = T>>(iter: I) -> Self {
+            iter.fold(MaybeScalar::one(), |acc, x| acc * x)
+        }
+    }
+
+    /// Sums points of any type which can be added to a `MaybePublicKey`. The empty sum is
+    /// the point at infinity.
+    impl<T> core::iter::Sum<T> for MaybePublicKey
+    where
+        MaybePublicKey: core::ops::Add<T, Output = MaybePublicKey>,
+    {
+        fn sum<I: Iterator<Item = T>>(iter: I) -> Self {
+            iter.fold(MaybePublicKey::Infinity, |acc, x| acc + x)
+        }
+    }
+}
+
 /// Adds any two types together. These could be `PublicKey`, `Scalar`, or the
 /// maybe-versions of each - as long as their shared inner type `I` is additive.
 /// The output type T3 is always either `MaybePublicKey` or `MaybeScalar` because
@@ -172,7 +208,7 @@ fn add_any<T1, T2, T3, I>(a: T1, b: T2) -> T3
 where
     T1: Optional<I>,
     T2: Optional<I>,
-    I: std::ops::Add<Output = T3>,
+    I: core::ops::Add<Output = T3>,
     T3: From<I> + Default,
 {
     match a.option() {
@@ -190,8 +226,8 @@ where
 /// Simply addition with the right-hand-side negated.
 fn subtract_any<T1, T2, N2, T3>(a: T1, b: T2) -> T3
 where
-    T1: std::ops::Add<N2, Output = T3>,
-    T2: std::ops::Neg<Output = N2>,
+    T1: core::ops::Add<N2, Output = T3>,
+    T2: core::ops::Neg<Output = N2>,
 {
     a + (-b)
 }
@@ -206,8 +242,8 @@ fn multiply_any<T1, T2, I1, I2, I3, T3>(a: T1, b: T2) -> T3
 where
     T1: Optional<I1>,
     T2: Optional<I2>,
-    I1: std::ops::Mul<I2, Output = I3>,
-    I2: std::ops::Mul<I1, Output = I3>,
+    I1: core::ops::Mul<I2, Output = I3>,
+    I2: core::ops::Mul<I1, Output = I3>,
     T3: Default + From<I3>,
 {
     match a.option().zip(b.option()) {
@@ -222,15 +258,50 @@ where
 /// - `$opfunc` is the function identifier for the trait.
 /// - `$op_logic` is a function which generically implements the operation logic.
 /// - `$lhs_type` and `$rhs_type` are types to implement the trait on.
-/// - `$operator` is the binary operator which is being implemented. This is just for decoration.
+/// - `$operator` is the binary operator which is being implemented. This is used
+///   to invoke the by-value operator from the by-reference ones.
+///
+/// Every operator is also implemented for references to either or both operands.
+/// The `@refs` form implements only the by-reference operators, for operators
+/// whose by-value implementation is written out by hand.
 macro_rules! implement_binary_ops {
+    (
+        @refs $opname:ident, $opfunc:ident, // Add, add,
+        $( $lhs_type:ident $operator:tt $rhs_type:ident -> $output_type:ident; )+ // Type1 + Type2 -> OutputType
+    ) => {
+        $(
+            impl core::ops::$opname<&$rhs_type> for $lhs_type {
+                type Output = $output_type;
+
+                fn $opfunc(self, rhs: &$rhs_type) -> Self::Output {
+                    self $operator *rhs
+                }
+            }
+
+            impl core::ops::$opname<$rhs_type> for &$lhs_type {
+                type Output = $output_type;
+
+                fn $opfunc(self, rhs: $rhs_type) -> Self::Output {
+                    *self $operator rhs
+                }
+            }
+
+            impl core::ops::$opname<&$rhs_type> for &$lhs_type {
+                type Output = $output_type;
+
+                fn $opfunc(self, rhs: &$rhs_type) -> Self::Output {
+                    *self $operator *rhs
+                }
+            }
+        )+
+    };
     (
         $opname:ident, $opfunc:ident, // Add, add,
         $op_logic:ident, // implementation function
         $( $lhs_type:ident $operator:tt $rhs_type:ident -> $output_type:ident; )+ // Type1 + Type2 -> OutputType
     ) => {
         $(
-            impl std::ops::$opname<$rhs_type> for $lhs_type {
+            impl core::ops::$opname<$rhs_type> for $lhs_type {
                 type Output = $output_type;
 
                 fn $opfunc(self, rhs: $rhs_type) -> Self::Output {
@@ -238,6 +309,11 @@ macro_rules! implement_binary_ops {
                 }
             }
         )+
+
+        implement_binary_ops!(
+            @refs $opname, $opfunc,
+            $( $lhs_type $operator $rhs_type -> $output_type; )+
+        );
     };
 }
 
@@ -248,17 +324,25 @@ macro_rules! implement_binary_ops {
 /// - `$lhs_type` and `$rhs_type` are types to implement the trait on.
 /// - `$operator` is the binary operator which is being implemented. This is used
 ///    to invoke the actual binary operator.
+///
+/// The right-hand-side may also be passed by reference.
 macro_rules! implement_assign_ops {
     (
         $opname:ident, $opfunc:ident, // AddAssign, add_assign,
         $( $lhs_type:ident $operator:tt $rhs_type:ident; )+
     ) => {
         $(
-            impl std::ops::$opname<$rhs_type> for $lhs_type {
+            impl core::ops::$opname<$rhs_type> for $lhs_type {
                 fn $opfunc(&mut self, rhs: $rhs_type) {
                     *self = *self $operator rhs;
                 }
             }
+
+            impl core::ops::$opname<&$rhs_type> for $lhs_type {
+                fn $opfunc(&mut self, rhs: &$rhs_type) {
+                    *self = *self $operator *rhs;
+                }
+            }
         )+
     };
 }
@@ -319,6 +403,26 @@ implement_binary_ops!(
     G * MaybeScalar -> MaybePublicKey;
 );
 
+// Reference variants of the operators implemented by hand in `inner_operator_impl`
+// and `generator_ops`.
+implement_binary_ops!(
+    @refs Add, add,
+
+    Scalar + Scalar -> MaybeScalar;
+    PublicKey + PublicKey -> MaybePublicKey;
+    G + G -> PublicKey;
+);
+
+implement_binary_ops!(
+    @refs Mul, mul,
+
+    Scalar * Scalar -> Scalar;
+    PublicKey * Scalar -> PublicKey;
+    Scalar * PublicKey -> PublicKey;
+    Scalar * G -> PublicKey;
+    G * Scalar -> PublicKey;
+);
+
 implement_assign_ops!(
     AddAssign, add_assign,
 
@@ -358,13 +462,13 @@ implement_assign_ops!(
     MaybePublicKey * MaybeScalar;
 );
 
-#[cfg(any(feature = "k256", feature = "secp256k1-invert"))]
 mod division {
     use super::*;
 
     /// To divide by `rhs`, we simply multiply by `rhs.inverse()`, because `rhs.inverse()`
     /// is algebraically the same as `1 / rhs`.
-    impl std::ops::Div<Scalar> for Scalar {
+    #[allow(clippy::suspicious_arithmetic_impl)] // Dividing is multiplying by the inverse.
+    impl core::ops::Div<Scalar> for Scalar {
         type Output = Scalar;
         fn div(self, rhs: Scalar) -> Self::Output {
             self * rhs.invert()
@@ -373,7 +477,8 @@ mod division {
 
     /// To divide by `rhs`, we simply multiply by `rhs.inverse()`, because `rhs.inverse()`
     /// is algebraically the same as `1 / rhs`.
-    impl std::ops::Div<Scalar> for PublicKey {
+    #[allow(clippy::suspicious_arithmetic_impl)] // Dividing is multiplying by the inverse.
+    impl core::ops::Div<Scalar> for PublicKey {
         type Output = PublicKey;
         fn div(self, rhs: Scalar) -> Self::Output {
             self * rhs.invert()
@@ -382,7 +487,8 @@ mod division {
 
     /// To divide by `rhs`, we simply multiply by `rhs.inverse()`, because `rhs.inverse()`
     /// is algebraically the same as `1 / rhs`.
-    impl std::ops::Div<Scalar> for G {
+    #[allow(clippy::suspicious_arithmetic_impl)] // Dividing is multiplying by the inverse.
+    impl core::ops::Div<Scalar> for G {
         type Output = PublicKey;
         fn div(self, rhs: Scalar) -> Self::Output {
             self * rhs.invert()
@@ -400,7 +506,7 @@ mod division {
     fn divide_any<T1, T2, I1, I3, T3>(a: T1, b: T2) -> T3
     where
         T1: Optional<I1>,
-        I1: std::ops::Div<T2, Output = I3>,
+        I1: core::ops::Div<T2, Output = I3>,
         T3: Default + From<I3>,
     {
         match a.option() {
@@ -415,6 +521,13 @@ mod division {
         MaybePublicKey / Scalar -> MaybePublicKey;
     );
 
+    implement_binary_ops!(
+        @refs Div, div,
+        Scalar / Scalar -> Scalar;
+        PublicKey / Scalar -> PublicKey;
+        G / Scalar -> PublicKey;
+    );
+
     implement_assign_ops!(
         DivAssign, div_assign,
 
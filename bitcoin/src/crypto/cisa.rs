@@ -0,0 +1,324 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Cross-input signature aggregation research mode.
+//!
+//! **Experimental.** This module prototypes one possible semantics for cross-input signature
+//! aggregation (CISA): a single BIP340 signature authorising every key path input of a
+//! transaction. It exists to support protocol research on top of this crate's sighash and MuSig2
+//! code. No aggregation scheme is part of the consensus rules, transactions relying on this one
+//! are not valid, and both the key aggregation and the commitment below may change or be removed
+//! at any time. It is only compiled with the `cisa-research` feature.
+//!
+//! The prototype is deliberately simple:
+//!
+//! * the aggregate key is the BIP327 aggregation of the taproot output keys of the spent outputs,
+//!   lifted to even y and taken in input order;
+//! * the [`AggregateSighash`] commits to the aggregate key followed by the BIP341 key path
+//!   signature hash of every input;
+//! * the owners of the inputs produce the aggregate signature with a MuSig2 [`Session`] over the
+//!   aggregate sighash, signing with their output secret keys negated to match even y.
+//!
+//! Half aggregation, in which the owners sign independently and the signatures are combined
+//! afterwards, is not covered.
+//!
+//! [`Session`]: super::musig2::Session
+//!
+
+use core::fmt;
+
+use hashes::{sha256t_hash_newtype, Hash, HashEngine};
+use internals::write_err;
+
+use super::key::{PublicKey, XOnlyPublicKey};
+use super::musig2::{KeyAggContext, Musig2Error};
+use super::schnorr::{verify_schnorr, Signature64};
+use super::sighash::{Prevouts, SighashCache, TapSighashType, TaprootError};
+use crate::blockdata::transaction::{Transaction, TxOut};
+use crate::common::types::Message;
+use crate::impl_thirty_two_byte_hash;
+use crate::prelude::*;
+use crate::CryptoError;
+
+sha256t_hash_newtype! {
+    pub struct AggregateSighashTag = hash_str("CISA/AggregateSighash");
+
+    /// Taproot-style tagged hash with tag \"CISA/AggregateSighash\".
+    ///
+    /// This is the message signed by an aggregate signature, see the [module docs](self).
+    #[hash_newtype(forward)]
+    pub struct AggregateSighash(_);
+}
+
+impl_thirty_two_byte_hash!(AggregateSighash);
+
+/// The aggregate key of the inputs of a transaction, see the [module docs](self).
+#[derive(Clone, Debug)]
+pub struct AggregateContext {
+    key_agg: KeyAggContext,
+}
+
+impl AggregateContext {
+    /// Aggregates the output keys of `prevouts`, the outputs spent by the inputs of a
+    /// transaction, in input order.
+    ///
+    /// # Errors
+    ///
+    /// If one of `prevouts` isn't a P2TR output, or if the keys cancel out.
+    pub fn from_prevouts(prevouts: &[TxOut]) -> Result<Self, CisaError> {
+        let key_agg = KeyAggContext::new(output_keys(prevouts)?)?;
+        Ok(AggregateContext { key_agg })
+    }
+
+    /// Returns the MuSig2 key aggregation context, for signing with a
+    /// [`Session`](super::musig2::Session).
+    pub fn key_agg(&self) -> &KeyAggContext {
+        &self.key_agg
+    }
+
+    /// Returns the aggregate key.
+    pub fn aggregate_key(&self) -> XOnlyPublicKey {
+        self.key_agg.x_only_public_key()
+    }
+
+    /// Computes the aggregate sighash of `tx`, which spends `prevouts`, with every input signed
+    /// with `sighash_type`.
+    ///
+    /// # Errors
+    ///
+    /// If `prevouts` aren't the outputs this context was built from, or if a per-input signature
+    /// hash can't be computed.
+    pub fn sighash(
+        &self,
+        tx: &Transaction,
+        prevouts: &[TxOut],
+        sighash_type: TapSighashType,
+    ) -> Result<AggregateSighash, CisaError> {
+        if output_keys(prevouts)? != self.key_agg.keys() {
+            return Err(CisaError::PrevoutsMismatch);
+        }
+
+        let mut engine = AggregateSighash::engine();
+        engine.input(&self.aggregate_key().serialize());
+        let mut cache = SighashCache::new(tx);
+        let all = Prevouts::All(prevouts);
+        for index in 0..tx.input.len() {
+            let sighash = cache.taproot_key_spend_signature_hash(index, &all, sighash_type)?;
+            engine.input(sighash.as_byte_array());
+        }
+        Ok(AggregateSighash::from_engine(engine))
+    }
+
+    /// Verifies that `signature` is an aggregate signature of `sighash`.
+    ///
+    /// # Errors
+    ///
+    /// If the signature is malformed or doesn't match.
+    pub fn verify(
+        &self,
+        sighash: &AggregateSighash,
+        signature: &Signature64,
+    ) -> Result<(), CryptoError> {
+        let msg = Message::from_digest(sighash.to_byte_array());
+        verify_schnorr(signature, &msg, &self.aggregate_key())
+    }
+}
+
+/// Returns the output keys of `prevouts`, lifted to even y.
+fn output_keys(prevouts: &[TxOut]) -> Result<Vec<PublicKey>, CisaError> {
+    prevouts
+        .iter()
+        .enumerate()
+        .map(|(index, prevout)| {
+            let spk = prevout.script_pubkey.as_bytes();
+            if !prevout.script_pubkey.is_p2tr() {
+                return Err(CisaError::NotTaproot(index));
+            }
+            XOnlyPublicKey::from_slice(&spk[2..])
+                .map(|key| key.lift_x())
+                .map_err(|_| CisaError::NotTaproot(index))
+        })
+        .collect()
+}
+
+/// Errors encountered while aggregating signatures across inputs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CisaError {
+    /// The output spent by the input at this index isn't a valid P2TR output.
+    NotTaproot(usize),
+    /// The spent outputs aren't the ones the aggregate key was computed from.
+    PrevoutsMismatch,
+    /// The output keys couldn't be aggregated.
+    Musig2(Musig2Error),
+    /// A per-input signature hash couldn't be computed.
+    Sighash(TaprootError),
+}
+
+internals::impl_from_infallible!(CisaError);
+
+impl fmt::Display for CisaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use CisaError::*;
+
+        match *self {
+            NotTaproot(index) => write!(f, "the output spent by input {} is not P2TR", index),
+            PrevoutsMismatch => f.write_str("the spent outputs don't match the aggregate key"),
+            Musig2(ref e) => write_err!(f, "key aggregation failed"; e),
+            Sighash(ref e) => write_err!(f, "signature hash computation failed"; e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CisaError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use CisaError::*;
+
+        match *self {
+            NotTaproot(_) | PrevoutsMismatch => None,
+            Musig2(ref e) => Some(e),
+            Sighash(ref e) => Some(e),
+        }
+    }
+}
+
+impl From<Musig2Error> for CisaError {
+    fn from(e: Musig2Error) -> Self {
+        CisaError::Musig2(e)
+    }
+}
+
+impl From<TaprootError> for CisaError {
+    fn from(e: TaprootError) -> Self {
+        CisaError::Sighash(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::RngCore;
+
+    use super::*;
+    use crate::blockdata::locktime::absolute;
+    use crate::blockdata::script::ScriptBuf;
+    use crate::blockdata::transaction::{self, OutPoint, Sequence, TxIn};
+    use crate::blockdata::witness::Witness;
+    use crate::crypto::key::{TweakedPublicKey, G};
+    use crate::crypto::musig2::{AggNonce, SecNonce, Session};
+    use crate::crypto::scalar::Scalar;
+    use crate::hash_types::Txid;
+    use crate::Amount;
+
+    fn prevout(secret: Scalar) -> TxOut {
+        let (key, _) = (secret * G).x_only_public_key();
+        TxOut {
+            value: Amount::from_sat(10_000),
+            script_pubkey: ScriptBuf::new_p2tr_tweaked(TweakedPublicKey::dangerous_assume_tweaked(
+                key,
+            )),
+        }
+    }
+
+    fn spend(inputs: u32) -> Transaction {
+        let input = (0..inputs)
+            .map(|vout| TxIn {
+                previous_output: OutPoint {
+                    txid: Txid::all_zeros(),
+                    vout,
+                },
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence::MAX,
+                witness: Witness::new(),
+            })
+            .collect();
+        let output = vec![TxOut {
+            value: Amount::from_sat(15_000),
+            script_pubkey: ScriptBuf::new(),
+        }];
+        Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input,
+            output,
+        }
+    }
+
+    #[test]
+    fn aggregate_signature() {
+        let secrets = (1..=3)
+            .map(|n| Scalar::reduce_from(&[n * 0x21; 32]))
+            .collect::<Vec<_>>();
+        let prevouts = secrets.iter().copied().map(prevout).collect::<Vec<_>>();
+        let tx = spend(3);
+
+        let context = AggregateContext::from_prevouts(&prevouts).unwrap();
+        let sighash = context
+            .sighash(&tx, &prevouts, TapSighashType::Default)
+            .unwrap();
+
+        // Every owner signs with the secret key of its output key lifted to even y.
+        let secrets = secrets
+            .into_iter()
+            .map(|secret| secret.negate_if((secret * G).x_only_public_key().1))
+            .collect::<Vec<_>>();
+        let mut rng = rand::thread_rng();
+        let sec_nonces = secrets
+            .iter()
+            .map(|&secret| {
+                let mut rand = [0u8; 32];
+                rng.fill_bytes(&mut rand);
+                SecNonce::builder(rand, secret * G)
+                    .with_secret_key(secret)
+                    .with_aggregated_key(context.aggregate_key())
+                    .with_message(sighash.as_byte_array())
+                    .build()
+            })
+            .collect::<Vec<_>>();
+        let pub_nonces = sec_nonces
+            .iter()
+            .map(SecNonce::public_nonce)
+            .collect::<Vec<_>>();
+        let session = Session::new(
+            context.key_agg(),
+            &AggNonce::sum(&pub_nonces),
+            sighash.as_byte_array(),
+        );
+        let partial_signatures = secrets
+            .iter()
+            .zip(sec_nonces)
+            .map(|(&secret, sec_nonce)| session.sign(sec_nonce, secret).unwrap())
+            .collect::<Vec<_>>();
+        let signature = session.unchecked_aggregate(&partial_signatures);
+
+        assert!(context.verify(&sighash, &signature).is_ok());
+
+        // The signature doesn't carry over to a transaction with other outputs.
+        let mut other = tx.clone();
+        other.output[0].value = Amount::from_sat(14_000);
+        let other = context
+            .sighash(&other, &prevouts, TapSighashType::Default)
+            .unwrap();
+        assert!(context.verify(&other, &signature).is_err());
+    }
+
+    #[test]
+    fn invalid_prevouts() {
+        let secrets = (1..=2)
+            .map(|n| Scalar::reduce_from(&[n * 0x21; 32]))
+            .collect::<Vec<_>>();
+        let mut prevouts = secrets.iter().copied().map(prevout).collect::<Vec<_>>();
+        let context = AggregateContext::from_prevouts(&prevouts).unwrap();
+
+        prevouts.reverse();
+        assert_eq!(
+            context.sighash(&spend(2), &prevouts, TapSighashType::Default),
+            Err(CisaError::PrevoutsMismatch)
+        );
+
+        prevouts[1].script_pubkey = ScriptBuf::new();
+        assert_eq!(
+            AggregateContext::from_prevouts(&prevouts).unwrap_err(),
+            CisaError::NotTaproot(1)
+        );
+    }
+}
@@ -0,0 +1,127 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Elliptic curve Diffie-Hellman.
+//!
+//! [`SharedSecret`] derives the same secret as the default ECDH function of libsecp256k1: the
+//! SHA256 of the compressed encoding of the shared point. Protocols which hash the shared point
+//! their own way, such as BIP324 or silent payments, can use [`shared_point`] instead.
+//!
+
+use core::fmt;
+
+use hashes::{sha256, Hash};
+use subtle::ConstantTimeEq;
+
+use super::key::PublicKey;
+use super::scalar::Scalar;
+use crate::CryptoError;
+
+/// The length of a [`SharedSecret`].
+pub const SHARED_SECRET_SIZE: usize = 32;
+
+/// A shared secret, derived from a public key and a secret scalar.
+///
+/// Equality is checked in constant time, and the secret is not shown by `Debug`.
+#[derive(Copy, Clone)]
+pub struct SharedSecret([u8; SHARED_SECRET_SIZE]);
+
+impl SharedSecret {
+    /// Derives the shared secret of `point` and `scalar`, compatible with libsecp256k1.
+    pub fn new(point: &PublicKey, scalar: &Scalar) -> SharedSecret {
+        let shared = shared_point(point, scalar);
+        SharedSecret(sha256::Hash::hash(&shared.serialize()).to_byte_array())
+    }
+
+    /// Wraps a shared secret computed elsewhere.
+    pub fn from_bytes(bytes: [u8; SHARED_SECRET_SIZE]) -> SharedSecret {
+        SharedSecret(bytes)
+    }
+
+    /// Parses a shared secret, returning [`CryptoError::InvalidSharedSecret`] if `data` is not
+    /// 32 bytes long.
+    pub fn from_slice(data: &[u8]) -> Result<SharedSecret, CryptoError> {
+        <[u8; SHARED_SECRET_SIZE]>::try_from(data)
+            .map(SharedSecret)
+            .map_err(|_| CryptoError::InvalidSharedSecret)
+    }
+
+    /// Returns the shared secret as a byte array.
+    pub fn secret_bytes(&self) -> [u8; SHARED_SECRET_SIZE] {
+        self.0
+    }
+}
+
+impl ConstantTimeEq for SharedSecret {
+    fn ct_eq(&self, other: &Self) -> subtle::Choice {
+        self.0.ct_eq(&other.0)
+    }
+}
+
+impl PartialEq for SharedSecret {
+    fn eq(&self, other: &Self) -> bool {
+        bool::from(self.ct_eq(other))
+    }
+}
+
+impl Eq for SharedSecret {}
+
+impl fmt::Debug for SharedSecret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("SharedSecret(..)")
+    }
+}
+
+/// Returns the shared point `scalar * point`, for protocols which derive their own secret from it.
+///
+/// Runs in constant time with respect to `scalar`. The point is never at infinity since the curve
+/// order is prime and `scalar` is non-zero.
+#[must_use]
+pub fn shared_point(point: &PublicKey, scalar: &Scalar) -> PublicKey {
+    *scalar * *point
+}
+
+#[cfg(test)]
+mod tests {
+    use hex::test_hex_unwrap as hex;
+
+    use super::*;
+    use crate::G;
+
+    #[test]
+    fn shared_secret() {
+        let a = Scalar::from_u32(0xabcd).unwrap();
+        let b = Scalar::reduce_from(&[0x42; 32]);
+        let (pub_a, pub_b) = (a * G, b * G);
+
+        let secret = SharedSecret::new(&pub_b, &a);
+        assert_eq!(secret, SharedSecret::new(&pub_a, &b));
+        assert_ne!(secret, SharedSecret::new(&pub_a, &a));
+        assert_eq!(shared_point(&pub_b, &a), (a * b) * G);
+        assert_eq!(
+            secret.secret_bytes(),
+            sha256::Hash::hash(&shared_point(&pub_a, &b).serialize()).to_byte_array()
+        );
+        assert_eq!(format!("{:?}", secret), "SharedSecret(..)");
+    }
+
+    #[test]
+    fn libsecp256k1_vector() {
+        // The secret of the point 2G, as derived by libsecp256k1 for the generator and the
+        // scalar two.
+        let secret = SharedSecret::new(&G, &Scalar::two());
+        assert_eq!(
+            secret.secret_bytes()[..],
+            hex!("b1c9938f01121e159887ac2c8d393a22e4476ff8212de13fe1939de2a236f0a7")[..]
+        );
+    }
+
+    #[test]
+    fn from_slice() {
+        let secret = SharedSecret::from_slice(&[7; 32]).unwrap();
+        assert_eq!(secret, SharedSecret::from_bytes([7; 32]));
+        assert_eq!(
+            SharedSecret::from_slice(&[7; 31]),
+            Err(CryptoError::InvalidSharedSecret)
+        );
+    }
+}
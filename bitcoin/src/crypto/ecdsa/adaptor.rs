@@ -0,0 +1,416 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! ECDSA adaptor signatures.
+//!
+//! An [`AdaptorSignature`] is an ECDSA signature encrypted with an encryption key `Y`: anyone can
+//! check that it decrypts to a valid signature of the message, but only the holder of the
+//! decryption key `y` can decrypt it. Once the decrypted signature is published, e.g. on chain,
+//! the holder of the adaptor signature recovers `y` from it. Discreet log contracts use this to
+//! make a contract execution transaction valid only with the signature of an oracle attesting
+//! the outcome.
+//!
+//! This follows the construction of the DLC specification, also implemented by
+//! libsecp256k1-zkp: the adaptor signature is the encrypted nonce `R = k*Y`, the nonce
+//! `R' = k*G` with a DLEQ proof that both have the same discrete logarithm, and the encrypted
+//! signature `s' = k^-1 (m + r*x)` where `r` is the X coordinate of `R`. Its 162-byte encoding
+//! is the one of the specification.
+//!
+
+use hashes::{sha256, Hash};
+
+use crate::common::types::Message;
+use crate::crypto::hashes::{tagged_hash, tagged_hash_to_scalar};
+use crate::crypto::key::{MaybePublicKey, PublicKey, G};
+use crate::crypto::scalar::{MaybeScalar, Scalar};
+use crate::CryptoError;
+
+/// The length of a serialized adaptor signature.
+pub const ADAPTOR_SIGNATURE_SIZE: usize = 162;
+
+/// The tag of the hash deriving the signature nonce.
+const NONCE_TAG: &str = "ECDSAadaptor/non";
+
+/// The tag of the hash masking the secret key with the auxiliary randomness.
+const AUX_TAG: &str = "ECDSAadaptor/aux";
+
+/// The tag of the hashes deriving the nonce and the challenge of the DLEQ proof.
+const DLEQ_TAG: &str = "DLEQ";
+
+/// An ECDSA signature encrypted with an encryption key.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct AdaptorSignature {
+    /// The encrypted nonce `R = k*Y`.
+    r: PublicKey,
+    /// The nonce `R' = k*G`.
+    r_a: PublicKey,
+    /// The encrypted signature `s'`.
+    s: Scalar,
+    /// The proof that `R` and `R'` have the same discrete logarithm.
+    proof: DleqProof,
+}
+
+impl AdaptorSignature {
+    /// Signs `msg` with `secret`, encrypting the signature with `encryption_key`.
+    ///
+    /// The nonce is derived from the secret key, the encryption key, the message and `aux_rand`,
+    /// which should be fresh randomness to protect against side channel attacks. Signing runs in
+    /// constant time with respect to `secret` and the nonce.
+    #[must_use]
+    pub fn encrypt(
+        msg: &Message,
+        secret: &Scalar,
+        encryption_key: &PublicKey,
+        aux_rand: &[u8; 32],
+    ) -> AdaptorSignature {
+        let k = nonce(
+            NONCE_TAG,
+            secret,
+            &encryption_key.serialize(),
+            &message_bytes(msg),
+            aux_rand,
+        );
+        let r = k * *encryption_key;
+        let r_a = k * G;
+        let s = (k.invert() * (message_scalar(msg) + x_scalar(&r) * *secret))
+            .not_zero()
+            .expect("a signature of zero is practically impossible");
+        let proof = DleqProof::prove(&k, encryption_key, &r_a, &r, aux_rand);
+        AdaptorSignature { r, r_a, s, proof }
+    }
+
+    /// Checks that the adaptor signature decrypts, with the decryption key of `encryption_key`,
+    /// to a signature of `msg` by `pubkey`.
+    ///
+    /// # Errors
+    ///
+    /// [`CryptoError::IncorrectSignature`] if it doesn't.
+    pub fn verify(
+        &self,
+        msg: &Message,
+        pubkey: &PublicKey,
+        encryption_key: &PublicKey,
+    ) -> Result<(), CryptoError> {
+        if !self.proof.verify(encryption_key, &self.r_a, &self.r) {
+            return Err(CryptoError::IncorrectSignature);
+        }
+        let expected = message_scalar(msg) * G + x_scalar(&self.r) * *pubkey;
+        if MaybePublicKey::Valid(self.s * self.r_a) != expected {
+            return Err(CryptoError::IncorrectSignature);
+        }
+        Ok(())
+    }
+
+    /// Decrypts the adaptor signature with `decryption_key`, the secret key of the encryption
+    /// key.
+    ///
+    /// The signature has a low S value. It is only valid if `decryption_key` is the right one,
+    /// which [`AdaptorSignature::verify`] doesn't check. Runs in constant time with respect to
+    /// `decryption_key`.
+    pub fn decrypt(&self, decryption_key: &Scalar) -> Result<k256::ecdsa::Signature, CryptoError> {
+        let r = x_scalar(&self.r)
+            .into_option()
+            .ok_or(CryptoError::InvalidSignature)?;
+        let s = self.s * decryption_key.invert();
+        let signature = k256::ecdsa::Signature::from_scalars(r.serialize(), s.serialize())
+            .map_err(|_| CryptoError::InvalidSignature)?;
+        Ok(signature.normalize_s().unwrap_or(signature))
+    }
+
+    /// Recovers the decryption key of `encryption_key` from `signature`, the decryption of this
+    /// adaptor signature.
+    ///
+    /// # Errors
+    ///
+    /// [`CryptoError::InvalidSignature`] if `signature` isn't the decryption of this adaptor
+    /// signature with the decryption key of `encryption_key`.
+    pub fn recover(
+        &self,
+        signature: &k256::ecdsa::Signature,
+        encryption_key: &PublicKey,
+    ) -> Result<Scalar, CryptoError> {
+        if x_scalar(&self.r) != MaybeScalar::from(Scalar::from(signature.r())) {
+            return Err(CryptoError::InvalidSignature);
+        }
+        // The S value may have been negated to make it low.
+        let y = self.s * Scalar::from(signature.s()).invert();
+        if y * G == *encryption_key {
+            Ok(y)
+        } else if -y * G == *encryption_key {
+            Ok(-y)
+        } else {
+            Err(CryptoError::InvalidSignature)
+        }
+    }
+
+    /// Serializes the adaptor signature as `R || R' || s' || e || s`, `(e, s)` being the DLEQ
+    /// proof.
+    pub fn serialize(&self) -> [u8; ADAPTOR_SIGNATURE_SIZE] {
+        let mut bytes = [0u8; ADAPTOR_SIGNATURE_SIZE];
+        bytes[..33].copy_from_slice(&self.r.serialize());
+        bytes[33..66].copy_from_slice(&self.r_a.serialize());
+        bytes[66..98].copy_from_slice(&self.s.serialize());
+        bytes[98..130].copy_from_slice(&self.proof.e.serialize());
+        bytes[130..].copy_from_slice(&self.proof.s.serialize());
+        bytes
+    }
+
+    /// Parses an adaptor signature serialized with [`AdaptorSignature::serialize`].
+    pub fn from_slice(data: &[u8]) -> Result<AdaptorSignature, CryptoError> {
+        if data.len() != ADAPTOR_SIGNATURE_SIZE {
+            return Err(CryptoError::InvalidSignature);
+        }
+        let point = |bytes| PublicKey::from_slice(bytes).map_err(|_| CryptoError::InvalidSignature);
+        let scalar =
+            |bytes| MaybeScalar::from_slice(bytes).map_err(|_| CryptoError::InvalidSignature);
+        Ok(AdaptorSignature {
+            r: point(&data[..33])?,
+            r_a: point(&data[33..66])?,
+            s: scalar(&data[66..98])?
+                .not_zero()
+                .map_err(|_| CryptoError::InvalidSignature)?,
+            proof: DleqProof {
+                e: scalar(&data[98..130])?,
+                s: scalar(&data[130..])?,
+            },
+        })
+    }
+}
+
+/// A proof that `P1 = x*G` and `P2 = x*Y` for the same `x`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+struct DleqProof {
+    e: MaybeScalar,
+    s: MaybeScalar,
+}
+
+impl DleqProof {
+    /// Proves that `p1 = secret*G` and `p2 = secret*gen2`.
+    fn prove(
+        secret: &Scalar,
+        gen2: &PublicKey,
+        p1: &PublicKey,
+        p2: &PublicKey,
+        aux_rand: &[u8; 32],
+    ) -> DleqProof {
+        let points = sha256::Hash::hash(&[p1.serialize(), p2.serialize()].concat());
+        let k = nonce(
+            DLEQ_TAG,
+            secret,
+            &gen2.serialize(),
+            points.as_byte_array(),
+            aux_rand,
+        );
+        let e = dleq_challenge(p1, gen2, p2, &(k * G), &(k * *gen2));
+        DleqProof {
+            e,
+            s: k + e * *secret,
+        }
+    }
+
+    /// Checks the proof that `p1 = x*G` and `p2 = x*gen2` for some `x`.
+    fn verify(&self, gen2: &PublicKey, p1: &PublicKey, p2: &PublicKey) -> bool {
+        let a1 = self.s * G - self.e * *p1;
+        let a2 = self.s * *gen2 - self.e * *p2;
+        match (a1, a2) {
+            (MaybePublicKey::Valid(a1), MaybePublicKey::Valid(a2)) => {
+                dleq_challenge(p1, gen2, p2, &a1, &a2) == self.e
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Computes the challenge of a DLEQ proof with the commitments `a1` and `a2`.
+fn dleq_challenge(
+    p1: &PublicKey,
+    gen2: &PublicKey,
+    p2: &PublicKey,
+    a1: &PublicKey,
+    a2: &PublicKey,
+) -> MaybeScalar {
+    tagged_hash_to_scalar(
+        DLEQ_TAG,
+        &[
+            &p1.serialize(),
+            &gen2.serialize(),
+            &p2.serialize(),
+            &a1.serialize(),
+            &a2.serialize(),
+        ],
+    )
+}
+
+/// Derives a nonce under `tag` like the hardened nonce function of libsecp256k1-zkp.
+fn nonce(
+    tag: &str,
+    secret: &Scalar,
+    key: &[u8; 33],
+    msg: &[u8; 32],
+    aux_rand: &[u8; 32],
+) -> Scalar {
+    let mask = tagged_hash(AUX_TAG, &[aux_rand]).to_byte_array();
+    let mut masked = secret.serialize();
+    masked
+        .iter_mut()
+        .zip(mask)
+        .for_each(|(byte, mask)| *byte ^= mask);
+    tagged_hash_to_scalar(tag, &[&masked, key, msg])
+        .not_zero()
+        .expect("a nonce hash of zero or the curve order is practically impossible")
+}
+
+fn message_bytes(msg: &Message) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    bytes.copy_from_slice(msg.as_ref());
+    bytes
+}
+
+/// Returns the message as a scalar, reduced modulo the curve order as done by ECDSA.
+fn message_scalar(msg: &Message) -> MaybeScalar {
+    MaybeScalar::reduce_from(&message_bytes(msg))
+}
+
+/// Returns the X coordinate of `point` modulo the curve order, the R value of an ECDSA signature.
+fn x_scalar(point: &PublicKey) -> MaybeScalar {
+    MaybeScalar::reduce_from(&point.serialize_xonly())
+}
+
+#[cfg(test)]
+mod tests {
+    use hex::test_hex_unwrap as hex;
+    use k256::ecdsa::signature::hazmat::PrehashVerifier;
+    use k256::ecdsa::VerifyingKey;
+
+    use super::*;
+
+    fn setup() -> (Message, Scalar, PublicKey, Scalar, PublicKey) {
+        let msg = Message::from_digest([0x42; 32]);
+        let secret = Scalar::reduce_from(&[0x11; 32]);
+        let decryption_key = Scalar::reduce_from(&[0x22; 32]);
+        (msg, secret, secret * G, decryption_key, decryption_key * G)
+    }
+
+    #[test]
+    fn encrypt_decrypt_recover() {
+        let (msg, secret, pubkey, decryption_key, encryption_key) = setup();
+        let adaptor = AdaptorSignature::encrypt(&msg, &secret, &encryption_key, &[0; 32]);
+        adaptor.verify(&msg, &pubkey, &encryption_key).unwrap();
+
+        let signature = adaptor.decrypt(&decryption_key).unwrap();
+        assert!(signature.normalize_s().is_none());
+        VerifyingKey::from(pubkey.inner)
+            .verify_prehash(msg.as_ref(), &signature)
+            .unwrap();
+
+        assert_eq!(
+            adaptor.recover(&signature, &encryption_key),
+            Ok(decryption_key)
+        );
+
+        // Another decryption key decrypts to an invalid signature, from which nothing is
+        // recovered.
+        let wrong = adaptor.decrypt(&Scalar::reduce_from(&[0x33; 32])).unwrap();
+        assert!(VerifyingKey::from(pubkey.inner)
+            .verify_prehash(msg.as_ref(), &wrong)
+            .is_err());
+        assert_eq!(
+            adaptor.recover(&wrong, &encryption_key),
+            Err(CryptoError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn verify_fails() {
+        let (msg, secret, pubkey, _, encryption_key) = setup();
+        let adaptor = AdaptorSignature::encrypt(&msg, &secret, &encryption_key, &[0; 32]);
+        let other = Scalar::reduce_from(&[0x44; 32]) * G;
+
+        let incorrect = Err(CryptoError::IncorrectSignature);
+        assert_eq!(
+            adaptor.verify(&Message::from_digest([0x43; 32]), &pubkey, &encryption_key),
+            incorrect
+        );
+        assert_eq!(adaptor.verify(&msg, &other, &encryption_key), incorrect);
+        assert_eq!(adaptor.verify(&msg, &pubkey, &other), incorrect);
+
+        let mut tampered = adaptor;
+        tampered.proof.s += MaybeScalar::one();
+        assert_eq!(tampered.verify(&msg, &pubkey, &encryption_key), incorrect);
+
+        // A nonce encrypted with another key, and a matching signature, but no valid proof.
+        let mut tampered = adaptor;
+        let k = Scalar::reduce_from(&[0x55; 32]);
+        tampered.r = k * other;
+        tampered.r_a = k * G;
+        tampered.s =
+            (k.invert() * (message_scalar(&msg) + x_scalar(&tampered.r) * secret)).unwrap();
+        assert_eq!(tampered.verify(&msg, &pubkey, &encryption_key), incorrect);
+    }
+
+    // The verification, decryption and recovery vectors of the ECDSA adaptor specification of
+    // dlcspecs, shared with libsecp256k1-zkp.
+    #[test]
+    fn dlcspecs_vectors() {
+        let adaptor = AdaptorSignature::from_slice(&hex!(
+            "03424d14a5471c048ab87b3b83f6085d125d5864249ae4297a57c84e74710bb673\
+             0223f325042fce535d040fee52ec13231bf709ccd84233c6944b90317e62528b25\
+             27dff9d659a96db4c99f9750168308633c1867b70f3a18fb0f4539a1aecedcd1fc\
+             0148fc22f36b6303083ece3f872b18e35d368b3958efe5fb081f7716736ccb598d\
+             269aa3084d57e1855e1ea9a45efc10463bbf32ae378029f5763ceb40173f"
+        ))
+        .unwrap();
+        let msg = Message::from_digest_slice(&hex!(
+            "8131e6f4b45754f2c90bd06688ceeabc0c45055460729928b4eecf11026a9e2d"
+        ))
+        .unwrap();
+        let pubkey = PublicKey::from_slice(&hex!(
+            "035be5e9478209674a96e60f1f037f6176540fd001fa1d64694770c56a7709c42c"
+        ))
+        .unwrap();
+        let encryption_key = PublicKey::from_slice(&hex!(
+            "02c2662c97488b07b6e819124b8989849206334a4c2fbdf691f7b34d2b16e9c293"
+        ))
+        .unwrap();
+        let decryption_key =
+            Scalar::from_hex("0b2aba63b885a0f0e96fa0f303920c7fb7431ddfa94376ad94d969fbf4109dc8")
+                .unwrap();
+        let signature = k256::ecdsa::Signature::from_slice(&hex!(
+            "424d14a5471c048ab87b3b83f6085d125d5864249ae4297a57c84e74710bb673\
+             29e80e0ee60e57af3e625bbae1672b1ecaa58effe613426b024fa1621d903394"
+        ))
+        .unwrap();
+
+        assert_eq!(adaptor.verify(&msg, &pubkey, &encryption_key), Ok(()));
+        assert_eq!(adaptor.decrypt(&decryption_key), Ok(signature));
+        assert_eq!(
+            adaptor.recover(&signature, &encryption_key),
+            Ok(decryption_key)
+        );
+
+        // The proof doesn't hold for another encryption key.
+        assert_eq!(
+            adaptor.verify(&msg, &pubkey, &pubkey),
+            Err(CryptoError::IncorrectSignature)
+        );
+    }
+
+    #[test]
+    fn serialization_roundtrip() {
+        let (msg, secret, _, _, encryption_key) = setup();
+        let adaptor = AdaptorSignature::encrypt(&msg, &secret, &encryption_key, &[7; 32]);
+        let bytes = adaptor.serialize();
+        assert_eq!(AdaptorSignature::from_slice(&bytes), Ok(adaptor));
+
+        assert!(AdaptorSignature::from_slice(&bytes[1..]).is_err());
+        let mut invalid = bytes;
+        invalid[1..33].copy_from_slice(&[0xff; 32]);
+        assert!(AdaptorSignature::from_slice(&invalid).is_err());
+        let mut invalid = bytes;
+        invalid[66..98].copy_from_slice(&[0; 32]);
+        assert!(AdaptorSignature::from_slice(&invalid).is_err());
+
+        // The nonce depends on the auxiliary randomness.
+        let other = AdaptorSignature::encrypt(&msg, &secret, &encryption_key, &[8; 32]);
+        assert_ne!(other.serialize(), bytes);
+    }
+}
@@ -0,0 +1,284 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Anti-exfil ECDSA signing.
+//!
+//! A signing device choosing its own nonces could leak its secret key through them, e.g. by
+//! deriving them from the key with an attacker's secret, and nobody could tell from the
+//! signatures. The anti-exfil protocol lets the host the device is connected to contribute
+//! randomness to every nonce, and check that the device did use it:
+//!
+//! 1. The host draws 32 bytes of `host_data` and sends the device their [`host_commit`]ment.
+//! 2. The device answers with its [`SignerCommitment`] from [`signer_commit`], the nonce it would
+//!    use before the host contribution.
+//! 3. The host reveals `host_data` and the device signs with [`sign`].
+//! 4. The host checks the signature with [`host_verify`].
+//!
+//! The device commits to its nonce before seeing `host_data`, and the host to `host_data` before
+//! seeing the nonce, so neither can bias the final nonce.
+//!
+//! The final nonce is a sign-to-contract commitment of the device nonce `R0` to `host_data`:
+//! `R = R0 + t*G` with `t = H(R0 || host_data)`. [`sign_to_contract`] exposes this commitment to
+//! any 32 bytes of data, e.g. to timestamp a document in a signature. The protocol is the one of
+//! libsecp256k1-zkp, as used by hardware wallets.
+//!
+
+use hashes::{sha256, Hash, HashEngine, Hmac, HmacEngine};
+use k256::ecdsa::hazmat::SignPrimitive;
+use k256::ecdsa::signature::hazmat::PrehashVerifier;
+use k256::ecdsa::VerifyingKey;
+use k256::FieldBytes;
+
+use crate::common::types::Message;
+use crate::crypto::hashes::{tagged_hash, tagged_hash_to_scalar};
+use crate::crypto::key::{PublicKey, G};
+use crate::crypto::scalar::{MaybeScalar, Scalar};
+use crate::CryptoError;
+
+/// The tag of the hash committing to the data of a sign-to-contract signature.
+const DATA_TAG: &str = "s2c/ecdsa/data";
+
+/// The tag of the hash tweaking the nonce with the data of a sign-to-contract signature.
+const POINT_TAG: &str = "s2c/ecdsa/point";
+
+/// The commitment of the host to its nonce contribution.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct HostCommitment(pub [u8; 32]);
+
+/// The nonce a signer commits to, before tweaking it with the data of the host.
+///
+/// This is the opening of the sign-to-contract commitment.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct SignerCommitment(pub PublicKey);
+
+impl SignerCommitment {
+    /// Serializes the commitment as a compressed point.
+    pub fn serialize(&self) -> [u8; 33] {
+        self.0.serialize()
+    }
+
+    /// Parses a commitment serialized as a compressed point.
+    pub fn from_slice(data: &[u8]) -> Result<SignerCommitment, CryptoError> {
+        PublicKey::from_slice(data)
+            .map(SignerCommitment)
+            .map_err(|_| CryptoError::InvalidPublicKey)
+    }
+}
+
+/// Commits to `host_data`, the nonce contribution of the host.
+pub fn host_commit(host_data: &[u8; 32]) -> HostCommitment {
+    HostCommitment(tagged_hash(DATA_TAG, &[host_data]).to_byte_array())
+}
+
+/// Returns the nonce the signer commits to for signing `msg` with `secret`, given the commitment
+/// of the host.
+///
+/// Runs in constant time with respect to `secret`.
+#[must_use]
+pub fn signer_commit(
+    msg: &Message,
+    secret: &Scalar,
+    host_commitment: &HostCommitment,
+) -> SignerCommitment {
+    SignerCommitment(rfc6979_nonce(msg, secret, &host_commitment.0) * G)
+}
+
+/// Signs `msg` with `secret` and the nonce contribution `host_data` of the host.
+///
+/// The nonce of the signature commits to `host_data`, so this must be called with the data
+/// revealed by the host after [`signer_commit`]. Signing runs in constant time with respect to
+/// `secret`.
+#[must_use]
+pub fn sign(msg: &Message, secret: &Scalar, host_data: &[u8; 32]) -> k256::ecdsa::Signature {
+    sign_to_contract(msg, secret, host_data).0
+}
+
+/// Checks that `signature` is a signature of `msg` by `pubkey`, whose nonce is the one committed
+/// to by the signer, tweaked with `host_data`.
+///
+/// # Errors
+///
+/// [`CryptoError::IncorrectSignature`] if the signature is invalid or the signer didn't use the
+/// contribution of the host.
+pub fn host_verify(
+    signature: &k256::ecdsa::Signature,
+    msg: &Message,
+    pubkey: &PublicKey,
+    host_data: &[u8; 32],
+    signer_commitment: &SignerCommitment,
+) -> Result<(), CryptoError> {
+    VerifyingKey::from(pubkey.inner)
+        .verify_prehash(msg.as_ref(), signature)
+        .map_err(|_| CryptoError::IncorrectSignature)?;
+    if !verify_contract_commitment(signature, host_data, signer_commitment) {
+        return Err(CryptoError::IncorrectSignature);
+    }
+    Ok(())
+}
+
+/// Signs `msg` with `secret`, committing the nonce to `data`.
+///
+/// Returns the signature and the opening of the commitment, the nonce before the tweak. The
+/// nonce is derived with RFC6979 from the commitment to `data` of [`host_commit`]. Signing runs
+/// in constant time with respect to `secret`.
+#[must_use]
+pub fn sign_to_contract(
+    msg: &Message,
+    secret: &Scalar,
+    data: &[u8; 32],
+) -> (k256::ecdsa::Signature, SignerCommitment) {
+    let original = rfc6979_nonce(msg, secret, &host_commit(data).0);
+    let opening = SignerCommitment(original * G);
+    let nonce = (original + commitment_tweak(&opening, data))
+        .not_zero()
+        .expect("a nonce of zero is practically impossible");
+
+    let mut z = FieldBytes::default();
+    z.copy_from_slice(msg.as_ref());
+    let (signature, _) = secret
+        .inner
+        .try_sign_prehashed(nonce.inner, &z)
+        .expect("the nonce is valid, signing can't fail");
+    (signature, opening)
+}
+
+/// Checks that the nonce of `signature` commits to `data` with the opening `opening`.
+///
+/// The signature itself isn't verified.
+#[must_use]
+pub fn verify_contract_commitment(
+    signature: &k256::ecdsa::Signature,
+    data: &[u8; 32],
+    opening: &SignerCommitment,
+) -> bool {
+    let nonce = opening.0 + commitment_tweak(opening, data) * G;
+    nonce.into_option().is_some_and(|nonce| {
+        MaybeScalar::reduce_from(&nonce.serialize_xonly()) == MaybeScalar::from(signature.r())
+    })
+}
+
+/// Returns the tweak `t = H(R0 || data)` committing the nonce `R0` to `data`.
+fn commitment_tweak(opening: &SignerCommitment, data: &[u8; 32]) -> MaybeScalar {
+    tagged_hash_to_scalar(POINT_TAG, &[&opening.serialize(), data])
+}
+
+/// Derives the RFC6979 nonce of `msg` and `secret` with the extra data `data`, as libsecp256k1
+/// does.
+fn rfc6979_nonce(msg: &Message, secret: &Scalar, data: &[u8; 32]) -> Scalar {
+    let hmac = |key: &[u8], inputs: &[&[u8]]| {
+        let mut engine = HmacEngine::<sha256::Hash>::new(key);
+        for input in inputs {
+            engine.input(input);
+        }
+        Hmac::<sha256::Hash>::from_engine(engine).to_byte_array()
+    };
+
+    let secret = secret.serialize();
+    let mut k = [0u8; 32];
+    let mut v = [1u8; 32];
+    for i in 0..=1u8 {
+        k = hmac(&k, &[&v, &[i], &secret, msg.as_ref(), data]);
+        v = hmac(&k, &[&v]);
+    }
+    loop {
+        v = hmac(&k, &[&v]);
+        if let Ok(nonce) = Scalar::from_slice(&v) {
+            return nonce;
+        }
+        k = hmac(&k, &[&v, &[0]]);
+        v = hmac(&k, &[&v]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::ecdsa::sign_ecdsa_with_noncedata;
+
+    fn setup() -> (Message, Scalar, PublicKey) {
+        let secret = Scalar::reduce_from(&[0x11; 32]);
+        (Message::from_digest([0x42; 32]), secret, secret * G)
+    }
+
+    #[test]
+    fn protocol() {
+        let (msg, secret, pubkey) = setup();
+        let host_data = [0x77; 32];
+
+        let host_commitment = host_commit(&host_data);
+        let signer_commitment = signer_commit(&msg, &secret, &host_commitment);
+        let signature = sign(&msg, &secret, &host_data);
+        host_verify(&signature, &msg, &pubkey, &host_data, &signer_commitment).unwrap();
+
+        // The untweaked nonce is the RFC6979 nonce of libsecp256k1 with the host commitment as
+        // extra data.
+        let untweaked = sign_ecdsa_with_noncedata(&msg, &secret, &host_commitment.0);
+        assert_eq!(
+            untweaked.r().to_bytes()[..],
+            signer_commitment.0.serialize_xonly()[..]
+        );
+        assert_ne!(untweaked.r().to_bytes(), signature.r().to_bytes());
+
+        let serialized = signer_commitment.serialize();
+        assert_eq!(
+            SignerCommitment::from_slice(&serialized),
+            Ok(signer_commitment)
+        );
+    }
+
+    #[test]
+    fn exfiltration_is_detected() {
+        let (msg, secret, pubkey) = setup();
+        let host_data = [0x77; 32];
+        let signer_commitment = signer_commit(&msg, &secret, &host_commit(&host_data));
+
+        // A signer ignoring the host data, or using other data.
+        let incorrect = Err(CryptoError::IncorrectSignature);
+        let signature = sign_ecdsa_with_noncedata(&msg, &secret, &[0; 32]);
+        assert_eq!(
+            host_verify(&signature, &msg, &pubkey, &host_data, &signer_commitment),
+            incorrect
+        );
+        let signature = sign(&msg, &secret, &[0x78; 32]);
+        assert_eq!(
+            host_verify(&signature, &msg, &pubkey, &host_data, &signer_commitment),
+            incorrect
+        );
+
+        // A signer committing to another nonce.
+        let other = signer_commit(&msg, &secret, &host_commit(&[0x78; 32]));
+        let signature = sign(&msg, &secret, &host_data);
+        assert_eq!(
+            host_verify(&signature, &msg, &pubkey, &host_data, &other),
+            incorrect
+        );
+
+        // A valid commitment with an invalid signature.
+        let other_msg = Message::from_digest([0x43; 32]);
+        assert_eq!(
+            host_verify(
+                &signature,
+                &other_msg,
+                &pubkey,
+                &host_data,
+                &signer_commitment
+            ),
+            incorrect
+        );
+    }
+
+    #[test]
+    fn sign_to_contract_commitment() {
+        let (msg, secret, pubkey) = setup();
+        let data = [0x99; 32];
+        let (signature, opening) = sign_to_contract(&msg, &secret, &data);
+        VerifyingKey::from(pubkey.inner)
+            .verify_prehash(msg.as_ref(), &signature)
+            .unwrap();
+        assert!(verify_contract_commitment(&signature, &data, &opening));
+        assert!(!verify_contract_commitment(
+            &signature,
+            &[0x98; 32],
+            &opening
+        ));
+    }
+}
@@ -0,0 +1,262 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Batch verification of ECDSA signatures.
+//!
+//! [`verify_batch`] checks many signatures at once, faster than checking them one at a time.
+//! Each signature is an equation between curve points, and a random linear combination of the
+//! equations is checked instead: the points are summed in one multi-scalar multiplication, which
+//! shares the point doublings between all the signatures, and the S values are inverted
+//! together.
+//!
+//! Unlike a Schnorr signature, an ECDSA signature only holds the X-coordinate of its nonce point,
+//! so the sign of each nonce point is unknown and has to be searched for. The signatures are
+//! combined in groups of [`GROUP_SIZE`] to keep the search short, and each nonce point still
+//! needs a multiplication of its own, so the speedup is much smaller than for Schnorr signatures.
+//!
+
+use hashes::{sha256, Hash, HashEngine};
+use k256::ecdsa::signature::hazmat::PrehashVerifier;
+use k256::ecdsa::VerifyingKey;
+use k256::elliptic_curve::ops::{LinearCombinationExt, Reduce};
+use k256::elliptic_curve::point::DecompressPoint;
+use k256::elliptic_curve::scalar::IsHigh;
+use k256::elliptic_curve::subtle::Choice;
+use k256::{AffinePoint, FieldBytes, ProjectivePoint, U256};
+
+use super::Signature;
+use crate::common::types::Message;
+use crate::crypto::key::PublicKey;
+use crate::crypto::scalar::Scalar;
+use crate::prelude::*;
+use crate::CryptoError;
+
+/// The number of signatures combined into each linear combination.
+///
+/// Finding the signs of the nonce points takes up to `2^GROUP_SIZE` point additions.
+const GROUP_SIZE: usize = 4;
+
+/// The secp256k1 field size `p` minus the curve order `n`, big-endian.
+///
+/// An R value below it may be the X-coordinate of the nonce point minus `n`.
+const FIELD_SIZE_MINUS_ORDER_BYTES: [u8; 32] = [
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+    0x45, 0x51, 0x23, 0x19, 0x50, 0xB7, 0x5F, 0xC4, 0x40, 0x2D, 0xA1, 0x72, 0x2F, 0xC9, 0xBA, 0xEE,
+];
+
+/// Verifies that each signature is a valid signature of its message by its public key.
+///
+/// The messages are the signed digests, as for [`sign_ecdsa_low_r`](super::sign_ecdsa_low_r).
+/// Signatures with a high S value are rejected, like the standardness rules of Bitcoin do. The
+/// result is the same as verifying every signature on its own, except with a negligible
+/// probability.
+///
+/// The random weights of the linear combination are derived by hashing the whole batch, so
+/// verification is deterministic and doesn't need a random number generator.
+///
+/// # Errors
+///
+/// [`CryptoError::IncorrectSignature`] if any signature is invalid. Verify the signatures one by
+/// one to find out which.
+pub fn verify_batch(items: &[(Message, Signature, PublicKey)]) -> Result<(), CryptoError> {
+    let mut engine = sha256::Hash::engine();
+    for (msg, sig, pk) in items {
+        engine.input(msg.as_ref());
+        engine.input(&sig.serialize_compact());
+        engine.input(&pk.inner.to_sec1_bytes());
+    }
+    let seed = sha256::Hash::from_engine(engine);
+
+    items
+        .chunks(GROUP_SIZE)
+        .enumerate()
+        .try_for_each(|(i, group)| verify_group(group, &seed, i * GROUP_SIZE))
+}
+
+/// Verifies the signatures of `group`, whose first item is at `offset` in the batch.
+fn verify_group(
+    group: &[(Message, Signature, PublicKey)],
+    seed: &sha256::Hash,
+    offset: usize,
+) -> Result<(), CryptoError> {
+    let mut combined = Vec::with_capacity(group.len());
+    let mut s_invs = Vec::with_capacity(group.len());
+    for (i, (msg, sig, pk)) in group.iter().enumerate() {
+        let sig = &sig.signature;
+        if bool::from(sig.s().is_high()) {
+            return Err(CryptoError::IncorrectSignature);
+        }
+        let r = sig.r().to_bytes();
+        if r[..] < FIELD_SIZE_MINUS_ORDER_BYTES[..] {
+            // The X-coordinate of the nonce point may be R or R + n. This is so rare that it isn't
+            // worth handling in the linear combination.
+            VerifyingKey::from(pk.inner)
+                .verify_prehash(msg.as_ref(), sig)
+                .map_err(|_| CryptoError::IncorrectSignature)?;
+            continue;
+        }
+        let nonce = Option::<AffinePoint>::from(AffinePoint::decompress(&r, Choice::from(0)))
+            .ok_or(CryptoError::IncorrectSignature)?;
+        combined.push((offset + i, msg, sig, pk, nonce));
+        s_invs.push(Scalar::from(sig.s()));
+    }
+    Scalar::batch_invert(&mut s_invs);
+
+    // With the weights `a`, the sum of `a * (z/s * G + r/s * P)` must equal the sum of `±a * R`.
+    let mut g_scalar = k256::Scalar::ZERO;
+    let mut terms = Vec::with_capacity(combined.len() + 1);
+    let mut weighted_nonces = Vec::with_capacity(combined.len());
+    for ((index, msg, sig, pk, nonce), s_inv) in combined.into_iter().zip(s_invs) {
+        let weight_bits = weight(seed, index);
+        let weight = k256::Scalar::from(weight_bits);
+        let mut z = FieldBytes::default();
+        z.copy_from_slice(msg.as_ref());
+        let z = <k256::Scalar as Reduce<U256>>::reduce_bytes(&z);
+        let s_inv = s_inv.inner * weight;
+        g_scalar += z * s_inv;
+        terms.push((pk.inner.to_projective(), *sig.r() * s_inv));
+        weighted_nonces.push(mul_u128(nonce.into(), weight_bits));
+    }
+    if weighted_nonces.is_empty() {
+        return Ok(());
+    }
+    terms.push((ProjectivePoint::GENERATOR, g_scalar));
+    let expected = ProjectivePoint::lincomb_ext(&terms[..]);
+
+    // Walk through the signs in Gray code order, flipping one sign at each step.
+    let mut sum = weighted_nonces.iter().sum::<ProjectivePoint>();
+    let mut positive = vec![true; weighted_nonces.len()];
+    for step in 1..=1usize << weighted_nonces.len() {
+        if sum == expected {
+            return Ok(());
+        }
+        let flip = step.trailing_zeros() as usize;
+        if flip == weighted_nonces.len() {
+            break;
+        }
+        let twice = weighted_nonces[flip].double();
+        sum = if positive[flip] {
+            sum - twice
+        } else {
+            sum + twice
+        };
+        positive[flip] = !positive[flip];
+    }
+    Err(CryptoError::IncorrectSignature)
+}
+
+/// Returns the weight of the item at `index` in the batch, a 128-bit number.
+///
+/// The first weight is one, which doesn't weaken the combination.
+fn weight(seed: &sha256::Hash, index: usize) -> u128 {
+    if index == 0 {
+        return 1;
+    }
+    let mut engine = sha256::Hash::engine();
+    engine.input(seed.as_ref());
+    engine.input(&(index as u64).to_le_bytes());
+    let hash = sha256::Hash::from_engine(engine).to_byte_array();
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&hash[..16]);
+    u128::from_le_bytes(bytes) | 1
+}
+
+/// Multiplies `point` by `scalar`, in variable time.
+///
+/// This takes half the doublings of a multiplication by a full scalar.
+fn mul_u128(point: ProjectivePoint, scalar: u128) -> ProjectivePoint {
+    // The multiples of `point` from 0 to 15, for a 4-bit window.
+    let mut table = [ProjectivePoint::IDENTITY; 16];
+    for i in 1..16 {
+        table[i] = table[i - 1] + point;
+    }
+    let mut result = ProjectivePoint::IDENTITY;
+    for window in (0..32).rev() {
+        result = result.double().double().double().double();
+        let digit = (scalar >> (window * 4)) as usize & 0xf;
+        if digit != 0 {
+            result += table[digit];
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::ecdsa::sign_ecdsa_low_r;
+    use crate::sighash::EcdsaSighashType;
+    use crate::G;
+
+    pub(super) fn batch(len: u32) -> Vec<(Message, Signature, PublicKey)> {
+        (1..=len)
+            .map(|n| {
+                let secret = Scalar::from_u32(n * 0x0123_4567).unwrap();
+                let msg = Message::from_digest([n as u8; 32]);
+                let sig = Signature::sighash_all(sign_ecdsa_low_r(&msg, &secret));
+                (msg, sig, secret * G)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn verify() {
+        assert_eq!(verify_batch(&[]), Ok(()));
+        for len in [1, 3, 4, 11] {
+            assert_eq!(verify_batch(&batch(len)), Ok(()));
+        }
+
+        // Whatever the sighash type.
+        let mut items = batch(5);
+        items[2].1.sighash_type = EcdsaSighashType::None;
+        assert_eq!(verify_batch(&items), Ok(()));
+    }
+
+    #[test]
+    fn verify_invalid() {
+        for i in 0..9 {
+            let mut items = batch(9);
+            items[i].0 = Message::from_digest([0xff; 32]);
+            assert_eq!(verify_batch(&items), Err(CryptoError::IncorrectSignature));
+
+            let mut items = batch(9);
+            let pk = items[i].2;
+            items[i].2 = items[(i + 1) % 9].2;
+            items[(i + 1) % 9].2 = pk;
+            assert_eq!(verify_batch(&items), Err(CryptoError::IncorrectSignature));
+
+            // The high S version of a valid signature.
+            let mut items = batch(9);
+            let sig = &mut items[i].1.signature;
+            *sig = k256::ecdsa::Signature::from_scalars(sig.r(), -*sig.s()).unwrap();
+            assert_eq!(verify_batch(&items), Err(CryptoError::IncorrectSignature));
+        }
+    }
+}
+
+#[cfg(bench)]
+mod benches {
+    use test::{black_box, Bencher};
+
+    use super::tests::batch;
+    use super::*;
+
+    #[bench]
+    pub fn verify_batch_256(bh: &mut Bencher) {
+        let items = batch(256);
+        bh.iter(|| {
+            black_box(verify_batch(&items)).unwrap();
+        });
+    }
+
+    /// The baseline of [`verify_batch_256`].
+    #[bench]
+    pub fn verify_each_256(bh: &mut Bencher) {
+        let items = batch(256);
+        bh.iter(|| {
+            for (msg, sig, pk) in &items {
+                let key = VerifyingKey::from(pk.inner);
+                black_box(key.verify_prehash(msg.as_ref(), &sig.signature)).unwrap();
+            }
+        });
+    }
+}
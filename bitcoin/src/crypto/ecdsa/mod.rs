@@ -0,0 +1,689 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! ECDSA Bitcoin signatures.
+//!
+//! This module provides ECDSA signatures used by Bitcoin that can be roundtrip (de)serialized.
+
+use core::str::FromStr;
+use core::{fmt, iter};
+
+use hex::FromHex;
+use internals::write_err;
+use io::Write;
+use k256::ecdsa::hazmat::SignPrimitive;
+use k256::sha2::Sha256;
+use k256::FieldBytes;
+
+use crate::common::types::Message;
+use crate::crypto::scalar::Scalar;
+use crate::script::PushBytes;
+use crate::sighash::{EcdsaSighashType, NonStandardSighashTypeError};
+use crate::{prelude::*, CryptoError};
+
+pub mod adaptor;
+pub mod anti_exfil;
+mod batch;
+pub mod recovery;
+
+pub use self::batch::verify_batch;
+pub use self::recovery::COMPACT_SIGNATURE_SIZE;
+
+const MAX_SIG_LEN: usize = 73;
+
+/// An ECDSA signature with the corresponding hash type.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(crate = "actual_serde"))]
+pub struct Signature {
+    /// The underlying ECDSA Signature.
+    pub signature: k256::ecdsa::Signature,
+    /// The corresponding hash type.
+    pub sighash_type: EcdsaSighashType,
+}
+
+impl core::hash::Hash for Signature {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        self.serialize().hash(state);
+    }
+}
+
+impl Signature {
+    /// Constructs an ECDSA Bitcoin signature for [`EcdsaSighashType::All`].
+    pub fn sighash_all(signature: k256::ecdsa::Signature) -> Signature {
+        Signature {
+            signature,
+            sighash_type: EcdsaSighashType::All,
+        }
+    }
+
+    /// Creates a signature from its 64-byte compact encoding, `r || s`.
+    ///
+    /// # Errors
+    ///
+    /// If `r` or `s` is zero or not below the curve order.
+    pub fn from_compact(
+        compact: &[u8; COMPACT_SIGNATURE_SIZE],
+        sighash_type: EcdsaSighashType,
+    ) -> Result<Signature, Error> {
+        let signature = k256::ecdsa::Signature::from_slice(compact)
+            .map_err(|_| Error::Secp256k1(CryptoError::InvalidSignature))?;
+        Ok(Signature {
+            signature,
+            sighash_type,
+        })
+    }
+
+    /// Parses a DER signature, without its sighash byte, accepting the non-strict encodings
+    /// which were valid before BIP66.
+    ///
+    /// This follows the lax parser of libsecp256k1, which Bitcoin Core uses to validate historical
+    /// signatures: the sequence length is ignored, integers may have long form lengths, excess
+    /// leading zeros and no sign padding, and anything after `s` is ignored.
+    ///
+    /// Historical signatures may also have a high S value, which has to be normalized with
+    /// [`k256::ecdsa::Signature::normalize_s`] before verification.
+    ///
+    /// # Errors
+    ///
+    /// If the signature can't be parsed even by these rules, or `r` or `s` is zero or not below
+    /// the curve order, in which case the signature can't be valid anyway.
+    pub fn from_der_lax(der: &[u8], sighash_type: EcdsaSighashType) -> Result<Signature, Error> {
+        let compact = parse_der_lax(der).ok_or(Error::Secp256k1(CryptoError::InvalidSignature))?;
+        Signature::from_compact(&compact, sighash_type)
+    }
+
+    /// Deserializes from slice following the standardness rules for [`EcdsaSighashType`].
+    pub fn from_slice(sl: &[u8]) -> Result<Self, Error> {
+        let (sighash_type, sig) = sl.split_last().ok_or(Error::EmptySignature)?;
+        let sighash_type = EcdsaSighashType::from_standard(*sighash_type as u32)?;
+        let signature = k256::ecdsa::Signature::from_der(sig)
+            .map_err(|_| Error::Secp256k1(CryptoError::InvalidSignature))?;
+        Ok(Signature {
+            signature,
+            sighash_type,
+        })
+    }
+
+    /// Serializes the inner secp256k1 signature in its 64-byte compact encoding, `r || s`.
+    ///
+    /// The sighash type is not included.
+    pub fn serialize_compact(&self) -> [u8; COMPACT_SIGNATURE_SIZE] {
+        let mut compact = [0u8; COMPACT_SIGNATURE_SIZE];
+        compact.copy_from_slice(&self.signature.to_bytes());
+        compact
+    }
+
+    /// Serializes the inner secp256k1 signature in strict DER format.
+    ///
+    /// The sighash type is not included, see [`Signature::serialize`] for the encoding used in
+    /// scripts.
+    pub fn serialize_der(&self) -> k256::ecdsa::DerSignature {
+        self.signature.to_der()
+    }
+
+    /// Serializes an ECDSA signature (inner secp256k1 signature in DER format).
+    ///
+    /// This does **not** perform extra heap allocation.
+    pub fn serialize(&self) -> SerializedSignature {
+        let mut buf = [0u8; MAX_SIG_LEN];
+        let der_sign = self.signature.to_der();
+        let signature = der_sign.as_bytes();
+        buf[..signature.len()].copy_from_slice(signature);
+        buf[signature.len()] = self.sighash_type as u8;
+        SerializedSignature {
+            data: buf,
+            len: signature.len() + 1,
+        }
+    }
+
+    /// Serializes an ECDSA signature (inner secp256k1 signature in DER format) into `Vec`.
+    ///
+    /// Note: this performs an extra heap allocation, you might prefer the
+    /// [`serialize`](Self::serialize) method instead.
+    pub fn to_vec(self) -> Vec<u8> {
+        self.signature
+            .to_der()
+            .as_bytes()
+            .iter()
+            .copied()
+            .chain(iter::once(self.sighash_type as u8))
+            .collect()
+    }
+
+    /// Serializes an ECDSA signature (inner secp256k1 signature in DER format) to a `writer`.
+    #[inline]
+    pub fn serialize_to_writer<W: Write + ?Sized>(&self, writer: &mut W) -> Result<(), io::Error> {
+        let sig = self.serialize();
+        sig.write_to(writer)
+    }
+}
+
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::LowerHex::fmt(&self.signature.to_der().as_bytes().as_hex(), f)?;
+        fmt::LowerHex::fmt(&[self.sighash_type as u8].as_hex(), f)
+    }
+}
+
+impl FromStr for Signature {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = Vec::from_hex(s)?;
+        let (sighash_byte, signature) = bytes.split_last().ok_or(Error::EmptySignature)?;
+        Ok(Signature {
+            signature: k256::ecdsa::Signature::from_der(signature)
+                .map_err(|_| Error::Secp256k1(CryptoError::InvalidSignature))?,
+            sighash_type: EcdsaSighashType::from_standard(*sighash_byte as u32)?,
+        })
+    }
+}
+
+/// Parses a DER signature like `ecdsa_signature_parse_der_lax` of libsecp256k1, returning its
+/// compact encoding.
+///
+/// Returns `None` if the structure can't be parsed or `r` or `s` is longer than 32 bytes.
+fn parse_der_lax(der: &[u8]) -> Option<[u8; COMPACT_SIGNATURE_SIZE]> {
+    // Reads a length byte, skipping the length bytes of the long form if `skip_long`.
+    fn read_len(der: &[u8], pos: &mut usize, skip_long: bool) -> Option<usize> {
+        let len = *der.get(*pos)?;
+        *pos += 1;
+        if len & 0x80 == 0 {
+            return Some(len.into());
+        }
+        let mut count = usize::from(len - 0x80);
+        if count > der.len() - *pos {
+            return None;
+        }
+        if skip_long {
+            *pos += count;
+            return Some(0);
+        }
+        while count > 0 && der[*pos] == 0 {
+            *pos += 1;
+            count -= 1;
+        }
+        if count >= 4 {
+            return None;
+        }
+        let mut len = 0;
+        for _ in 0..count {
+            len = (len << 8) + usize::from(der[*pos]);
+            *pos += 1;
+        }
+        Some(len)
+    }
+
+    // Reads an integer tag and length, returning the position and length of the integer.
+    fn read_int(der: &[u8], pos: &mut usize) -> Option<(usize, usize)> {
+        if der.get(*pos) != Some(&0x02) {
+            return None;
+        }
+        *pos += 1;
+        let len = read_len(der, pos, false)?;
+        if len > der.len() - *pos {
+            return None;
+        }
+        let start = *pos;
+        *pos += len;
+        Some((start, len))
+    }
+
+    let mut pos = 0;
+    if der.first() != Some(&0x30) {
+        return None;
+    }
+    pos += 1;
+    // The sequence length is not checked.
+    read_len(der, &mut pos, true)?;
+    let r = read_int(der, &mut pos)?;
+    let s = read_int(der, &mut pos)?;
+
+    let mut compact = [0u8; COMPACT_SIGNATURE_SIZE];
+    for ((start, len), out) in [r, s].into_iter().zip(compact.chunks_mut(32)) {
+        let int = &der[start..start + len];
+        let int = &int[int.iter().take_while(|&&b| b == 0).count()..];
+        if int.len() > 32 {
+            return None;
+        }
+        out[32 - int.len()..].copy_from_slice(int);
+    }
+    Some(compact)
+}
+
+/// Holds signature serialized in-line (not in `Vec`).
+///
+/// This avoids allocation and allows proving maximum size of the signature (73 bytes).
+/// The type can be used largely as a byte slice. It implements all standard traits one would
+/// expect and has familiar methods.
+/// However, the usual use case is to push it into a script. This can be done directly passing it
+/// into [`push_slice`](crate::script::ScriptBuf::push_slice).
+#[derive(Copy, Clone)]
+pub struct SerializedSignature {
+    data: [u8; MAX_SIG_LEN],
+    len: usize,
+}
+
+impl SerializedSignature {
+    /// Returns an iterator over bytes of the signature.
+    #[inline]
+    pub fn iter(&self) -> core::slice::Iter<'_, u8> {
+        self.into_iter()
+    }
+
+    /// Writes this serialized signature to a `writer`.
+    #[inline]
+    pub fn write_to<W: Write + ?Sized>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(self)
+    }
+}
+
+impl core::ops::Deref for SerializedSignature {
+    type Target = [u8];
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.data[..self.len]
+    }
+}
+
+impl core::ops::DerefMut for SerializedSignature {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.data[..self.len]
+    }
+}
+
+impl AsRef<[u8]> for SerializedSignature {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl AsMut<[u8]> for SerializedSignature {
+    #[inline]
+    fn as_mut(&mut self) -> &mut [u8] {
+        self
+    }
+}
+
+impl AsRef<PushBytes> for SerializedSignature {
+    #[inline]
+    fn as_ref(&self) -> &PushBytes {
+        &<&PushBytes>::from(&self.data)[..self.len()]
+    }
+}
+
+impl core::borrow::Borrow<[u8]> for SerializedSignature {
+    #[inline]
+    fn borrow(&self) -> &[u8] {
+        self
+    }
+}
+
+impl core::borrow::BorrowMut<[u8]> for SerializedSignature {
+    #[inline]
+    fn borrow_mut(&mut self) -> &mut [u8] {
+        self
+    }
+}
+
+impl fmt::Debug for SerializedSignature {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl fmt::Display for SerializedSignature {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::LowerHex::fmt(self, f)
+    }
+}
+
+impl fmt::LowerHex for SerializedSignature {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::LowerHex::fmt(&(**self).as_hex(), f)
+    }
+}
+
+impl fmt::UpperHex for SerializedSignature {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::UpperHex::fmt(&(**self).as_hex(), f)
+    }
+}
+
+impl PartialEq for SerializedSignature {
+    #[inline]
+    fn eq(&self, other: &SerializedSignature) -> bool {
+        **self == **other
+    }
+}
+
+impl Eq for SerializedSignature {}
+
+impl core::hash::Hash for SerializedSignature {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        core::hash::Hash::hash(&**self, state)
+    }
+}
+
+impl<'a> IntoIterator for &'a SerializedSignature {
+    type IntoIter = core::slice::Iter<'a, u8>;
+    type Item = &'a u8;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        (*self).iter()
+    }
+}
+
+/// Signs `msg` with `secret`, using the deterministic RFC6979 nonce.
+///
+/// See [`sign_ecdsa_low_r`] for signatures that are one byte shorter half of the time. Signing
+/// runs in constant time with respect to `secret`.
+#[must_use]
+pub fn sign_ecdsa(msg: &Message, secret: &Scalar) -> k256::ecdsa::Signature {
+    sign_ecdsa_with_extra_entropy(msg, secret, &[])
+}
+
+/// Signs `msg` with `secret`, grinding the nonce until the signature has a low R value.
+///
+/// An R value below `2^255` encodes to 32 bytes in DER rather than 33, so the signature is at
+/// most 71 bytes long with its sighash type. Bitcoin Core grinds its signatures the same way and
+/// estimates fees assuming this size.
+///
+/// The first attempt is the plain RFC6979 signature. Later attempts pass a counter, starting at
+/// one and encoded as 32 little-endian bytes, to RFC6979 as extra entropy, exactly like Bitcoin
+/// Core, so both produce the same signatures. Two attempts are needed on average.
+///
+/// Each attempt runs in constant time with respect to `secret`. The number of attempts only
+/// depends on the R values of the discarded signatures.
+#[must_use]
+pub fn sign_ecdsa_low_r(msg: &Message, secret: &Scalar) -> k256::ecdsa::Signature {
+    let mut extra_entropy = [0u8; 32];
+    let mut signature = sign_ecdsa_with_extra_entropy(msg, secret, &[]);
+    let mut counter = 0u32;
+    while !has_low_r(&signature) {
+        counter += 1;
+        extra_entropy[..4].copy_from_slice(&counter.to_le_bytes());
+        signature = sign_ecdsa_with_noncedata(msg, secret, &extra_entropy);
+    }
+    signature
+}
+
+/// Signs `msg` with `secret`, mixing `noncedata` into the deterministic RFC6979 nonce.
+///
+/// This is the `ndata` argument of libsecp256k1: the nonce, and so the signature, still only
+/// depends on its inputs, but each `noncedata` gives another signature of the same message.
+/// Anti-exfil protocols use it to let another party contribute to the nonce. The signature has
+/// a low S value. Signing runs in constant time with respect to `secret`.
+#[must_use]
+pub fn sign_ecdsa_with_noncedata(
+    msg: &Message,
+    secret: &Scalar,
+    noncedata: &[u8; 32],
+) -> k256::ecdsa::Signature {
+    sign_ecdsa_with_extra_entropy(msg, secret, noncedata)
+}
+
+/// Returns true if the R value of `signature` is below `2^255`.
+fn has_low_r(signature: &k256::ecdsa::Signature) -> bool {
+    signature.r().to_bytes()[0] < 0x80
+}
+
+/// Signs `msg` with `secret` and a low S value, passing `extra_entropy` to RFC6979.
+///
+/// Without extra entropy this is the plain RFC6979 signature.
+fn sign_ecdsa_with_extra_entropy(
+    msg: &Message,
+    secret: &Scalar,
+    extra_entropy: &[u8],
+) -> k256::ecdsa::Signature {
+    let mut z = FieldBytes::default();
+    z.copy_from_slice(msg.as_ref());
+    let (signature, _) = secret
+        .inner
+        .try_sign_prehashed_rfc6979::<Sha256>(&z, extra_entropy)
+        .expect("RFC6979 nonces are valid, signing can't fail");
+    signature
+}
+
+/// An ECDSA signature-related error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// Hex decoding error.
+    Hex(hex::HexToBytesError),
+    /// Non-standard sighash type.
+    SighashType(NonStandardSighashTypeError),
+    /// Signature was empty.
+    EmptySignature,
+    /// A secp256k1 error.
+    Secp256k1(CryptoError),
+}
+
+internals::impl_from_infallible!(Error);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use Error::*;
+
+        match *self {
+            Hex(ref e) => write_err!(f, "signature hex decoding error"; e),
+            SighashType(ref e) => write_err!(f, "non-standard signature hash type"; e),
+            EmptySignature => write!(f, "empty ECDSA signature"),
+            Secp256k1(ref e) => write_err!(f, "secp256k1"; e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use Error::*;
+
+        match *self {
+            Hex(ref e) => Some(e),
+            Secp256k1(ref e) => Some(e),
+            SighashType(ref e) => Some(e),
+            EmptySignature => None,
+        }
+    }
+}
+
+impl From<CryptoError> for Error {
+    fn from(e: CryptoError) -> Self {
+        Self::Secp256k1(e)
+    }
+}
+
+impl From<NonStandardSighashTypeError> for Error {
+    fn from(e: NonStandardSighashTypeError) -> Self {
+        Self::SighashType(e)
+    }
+}
+
+impl From<hex::HexToBytesError> for Error {
+    fn from(e: hex::HexToBytesError) -> Self {
+        Self::Hex(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hex::test_hex_unwrap as hex;
+    use k256::ecdsa::signature::hazmat::PrehashVerifier;
+    use k256::ecdsa::VerifyingKey;
+
+    use super::*;
+    use crate::G;
+
+    #[test]
+    fn write_serialized_signature() {
+        // let hex = "3046022100839c1fbc5304de944f697c9f4b1d01d1faeba32d751c0f7acb21ac8a0f436a72022100e89bd46bb3a5a62adc679f659b7ce876d83ee297c7a5587b2011c4fcc72eab45";
+        let hex: [u8; 72] = [
+            48, 70, 2, 33, 0, 131, 156, 31, 188, 83, 4, 222, 148, 79, 105, 124, 159, 75, 29, 1,
+            209, 250, 235, 163, 45, 117, 28, 15, 122, 203, 33, 172, 138, 15, 67, 106, 114, 2, 33,
+            0, 232, 155, 212, 107, 179, 165, 166, 42, 220, 103, 159, 101, 155, 124, 232, 118, 216,
+            62, 226, 151, 199, 165, 88, 123, 32, 17, 196, 252, 199, 46, 171, 69,
+        ];
+        let sig = Signature {
+            signature: k256::ecdsa::Signature::from_der(hex.as_slice()).unwrap(),
+            sighash_type: EcdsaSighashType::All,
+        };
+
+        let mut buf = vec![];
+        sig.serialize_to_writer(&mut buf).expect("write failed");
+
+        assert_eq!(sig.to_vec(), buf)
+    }
+
+    #[test]
+    fn low_r() {
+        // A vector of rust-secp256k1, matching Bitcoin Core.
+        let secret = Scalar::try_from(
+            &hex!("57f0148f94d13095cfda539d0da0d1541304b678d8b36e243980aab4e1b7cead")[..],
+        )
+        .unwrap();
+        let msg = Message::from_digest(
+            hex!("887d04bb1cf1b1554f1b268dfe62d13064ca67ae45348d50d1392ce2d13418ac")
+                .try_into()
+                .unwrap(),
+        );
+        assert_eq!(
+            sign_ecdsa_low_r(&msg, &secret).to_bytes()[..],
+            hex!("047dd4d049db02b430d24c41c7925b2725bcd5a85393513bdec04b4dc363632b1054d0180094122b380f4cfa391e6296244da773173e78fc745c1b9c79f7b713")[..]
+        );
+
+        for n in 1..=16u32 {
+            let secret = Scalar::from_u32(n * 0x0123_4567).unwrap();
+            let msg = Message::from_digest([n as u8; 32]);
+            let signature = sign_ecdsa_low_r(&msg, &secret);
+            assert!(has_low_r(&signature));
+            assert!(signature.to_der().len() <= 70);
+            assert!(signature.normalize_s().is_none());
+
+            let verifying_key = VerifyingKey::from((secret * G).inner);
+            assert!(verifying_key
+                .verify_prehash(msg.as_ref(), &signature)
+                .is_ok());
+
+            // When the first attempt has a low R, it is the plain RFC6979 signature.
+            let plain = recovery::sign_ecdsa_recoverable(&msg, &secret).signature;
+            assert_eq!(plain == signature, has_low_r(&plain));
+        }
+    }
+
+    #[test]
+    fn noncedata() {
+        let secret = Scalar::from_u32(0x0abc_def0).unwrap();
+        let msg = Message::from_digest([0x42; 32]);
+        let verifying_key = VerifyingKey::from((secret * G).inner);
+
+        let plain = recovery::sign_ecdsa_recoverable(&msg, &secret).signature;
+        let zero = sign_ecdsa_with_noncedata(&msg, &secret, &[0; 32]);
+        let one = sign_ecdsa_with_noncedata(&msg, &secret, &[1; 32]);
+        assert_ne!(zero, plain);
+        assert_ne!(zero, one);
+        assert_eq!(zero, sign_ecdsa_with_noncedata(&msg, &secret, &[0; 32]));
+        for signature in [zero, one] {
+            assert!(signature.normalize_s().is_none());
+            assert!(verifying_key
+                .verify_prehash(msg.as_ref(), &signature)
+                .is_ok());
+        }
+
+        // Low-R grinding passes its counter as noncedata.
+        let (n, msg) = (1..)
+            .map(|n| (n, Message::from_digest([n; 32])))
+            .find(|(_, msg)| !has_low_r(&recovery::sign_ecdsa_recoverable(msg, &secret).signature))
+            .unwrap();
+        let mut counter = [0u8; 32];
+        counter[0] = 1;
+        let first = sign_ecdsa_with_noncedata(&msg, &secret, &counter);
+        assert_eq!(
+            has_low_r(&first),
+            sign_ecdsa_low_r(&msg, &secret) == first,
+            "message {}",
+            n
+        );
+    }
+
+    #[test]
+    fn compact_and_der() {
+        let secret = Scalar::from_u32(0x0abc_def0).unwrap();
+        let msg = Message::from_digest([0x42; 32]);
+        let sig = Signature::sighash_all(sign_ecdsa_low_r(&msg, &secret));
+
+        let compact = sig.serialize_compact();
+        assert_eq!(
+            Signature::from_compact(&compact, EcdsaSighashType::All),
+            Ok(sig)
+        );
+        assert_eq!(
+            sig.serialize_der().as_bytes(),
+            &sig.serialize()[..sig.serialize().len() - 1]
+        );
+        assert_eq!(
+            Signature::from_der_lax(sig.serialize_der().as_bytes(), EcdsaSighashType::All),
+            Ok(sig)
+        );
+
+        assert!(Signature::from_compact(&[0; 64], EcdsaSighashType::All).is_err());
+        assert!(Signature::from_compact(&[0xff; 64], EcdsaSighashType::All).is_err());
+    }
+
+    #[test]
+    fn der_lax() {
+        let r = hex!("00839c1fbc5304de944f697c9f4b1d01d1faeba32d751c0f7acb21ac8a0f436a72");
+        let s = hex!("7fffffffffffffffffffffffffffffff5d576e7357a4501ddfe92f46681b20a0");
+        let mut compact = [0u8; 64];
+        compact[..32].copy_from_slice(&r[1..]);
+        compact[32..].copy_from_slice(&s);
+        let expected = Signature::from_compact(&compact, EcdsaSighashType::Single).unwrap();
+        let parse = |der: &[u8]| Signature::from_der_lax(der, EcdsaSighashType::Single);
+
+        let mut der = vec![0x30, 0x45, 0x02, 0x21];
+        der.extend_from_slice(&r);
+        der.extend_from_slice(&[0x02, 0x20]);
+        der.extend_from_slice(&s);
+        assert_eq!(parse(&der), Ok(expected));
+        assert!(k256::ecdsa::Signature::from_der(&der).is_ok());
+
+        // Wrong sequence length, trailing data and a long form R length.
+        let mut lax = vec![0x30, 0x83, 0, 0, 0x01, 0x02, 0x82, 0x00, 0x21];
+        lax.extend_from_slice(&r);
+        // Missing sign padding is ignored, excess leading zeros are dropped.
+        lax.extend_from_slice(&[0x02, 0x23, 0x00, 0x00, 0x00]);
+        lax.extend_from_slice(&s);
+        lax.extend_from_slice(&[0xde, 0xad]);
+        assert!(k256::ecdsa::Signature::from_der(&lax).is_err());
+        assert_eq!(parse(&lax), Ok(expected));
+
+        // R without sign padding.
+        let mut unpadded = vec![0x30, 0x44, 0x02, 0x20];
+        unpadded.extend_from_slice(&r[1..]);
+        unpadded.extend_from_slice(&[0x02, 0x20]);
+        unpadded.extend_from_slice(&s);
+        assert_eq!(parse(&unpadded), Ok(expected));
+
+        // Unparsable structures.
+        assert!(parse(&[]).is_err());
+        assert!(parse(&der[..der.len() - 1]).is_err());
+        assert!(parse(&[0x31, 0x06, 0x02, 0x01, 0x01, 0x02, 0x01, 0x01]).is_err());
+        assert!(parse(&[0x30, 0x06, 0x02, 0x01, 0x01, 0x03, 0x01, 0x01]).is_err());
+        assert!(parse(&[0x30, 0x06, 0x02, 0x84, 0x01, 0x00, 0x00, 0x00]).is_err());
+        // A 33-byte R, and a zero S.
+        let mut long_r = vec![0x30, 0x26, 0x02, 0x21, 0x01];
+        long_r.extend_from_slice(&r[1..]);
+        long_r.extend_from_slice(&[0x02, 0x01, 0x01]);
+        assert!(parse(&long_r).is_err());
+        assert!(parse(&[0x30, 0x06, 0x02, 0x01, 0x01, 0x02, 0x01, 0x00]).is_err());
+        assert!(parse(&[0x30, 0x06, 0x02, 0x01, 0x01, 0x02, 0x01, 0x01]).is_ok());
+    }
+}
@@ -0,0 +1,218 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! ECDSA public key recovery.
+//!
+//! A [`RecoverableSignature`] carries a [`RecoveryId`] next to the signature, which is enough
+//! to recover the public key that made it from the signed message. This is what legacy signed
+//! messages rely on, and what chains which don't commit to public keys use to identify signers.
+//!
+
+use core::fmt;
+
+use k256::ecdsa::{SigningKey, VerifyingKey};
+
+use crate::common::types::Message;
+use crate::crypto::key::PublicKey;
+use crate::crypto::scalar::Scalar;
+use crate::CryptoError;
+
+/// The length of a compact signature, without the recovery id.
+pub const COMPACT_SIGNATURE_SIZE: usize = 64;
+
+/// Selects which of the up to four public keys matching a signature is the signer's.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct RecoveryId(u8);
+
+impl RecoveryId {
+    /// Creates a recovery id from its integer value, which must be at most 3.
+    pub fn from_u8(id: u8) -> Result<RecoveryId, CryptoError> {
+        if id <= 3 {
+            Ok(RecoveryId(id))
+        } else {
+            Err(CryptoError::InvalidRecoveryId)
+        }
+    }
+
+    /// Returns the integer value of the recovery id.
+    pub fn to_u8(self) -> u8 {
+        self.0
+    }
+
+    /// Returns true if the nonce point of the signature has an odd Y-coordinate.
+    pub fn is_y_odd(self) -> bool {
+        self.0 & 1 == 1
+    }
+
+    /// Returns true if the X-coordinate of the nonce point was reduced modulo the curve order.
+    pub fn is_x_reduced(self) -> bool {
+        self.0 & 2 == 2
+    }
+
+    fn to_k256(self) -> k256::ecdsa::RecoveryId {
+        k256::ecdsa::RecoveryId::new(self.is_y_odd(), self.is_x_reduced())
+    }
+}
+
+impl TryFrom<u8> for RecoveryId {
+    type Error = CryptoError;
+
+    fn try_from(id: u8) -> Result<Self, Self::Error> {
+        RecoveryId::from_u8(id)
+    }
+}
+
+impl From<RecoveryId> for u8 {
+    fn from(id: RecoveryId) -> u8 {
+        id.0
+    }
+}
+
+impl fmt::Display for RecoveryId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+/// An ECDSA signature together with the [`RecoveryId`] of the signer's public key.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct RecoverableSignature {
+    /// The signature.
+    pub signature: k256::ecdsa::Signature,
+    /// The recovery id.
+    pub recovery_id: RecoveryId,
+}
+
+impl RecoverableSignature {
+    /// Creates a recoverable signature from a signature and a recovery id.
+    pub fn new(signature: k256::ecdsa::Signature, recovery_id: RecoveryId) -> RecoverableSignature {
+        RecoverableSignature {
+            signature,
+            recovery_id,
+        }
+    }
+
+    /// Parses a 64-byte compact signature, `r || s`, with its recovery id.
+    pub fn from_compact(
+        data: &[u8],
+        recovery_id: RecoveryId,
+    ) -> Result<RecoverableSignature, CryptoError> {
+        let signature =
+            k256::ecdsa::Signature::from_slice(data).map_err(|_| CryptoError::InvalidSignature)?;
+        Ok(RecoverableSignature::new(signature, recovery_id))
+    }
+
+    /// Serializes the signature as 64 compact bytes, `r || s`, and its recovery id.
+    pub fn serialize_compact(&self) -> (RecoveryId, [u8; COMPACT_SIGNATURE_SIZE]) {
+        let mut bytes = [0u8; COMPACT_SIGNATURE_SIZE];
+        bytes.copy_from_slice(&self.signature.to_bytes());
+        (self.recovery_id, bytes)
+    }
+
+    /// Drops the recovery id, returning a signature which can only be verified against a known
+    /// public key.
+    pub fn to_standard(&self) -> k256::ecdsa::Signature {
+        self.signature
+    }
+
+    /// Recovers the public key which signed `msg`, see [`recover`].
+    pub fn recover(&self, msg: &Message) -> Result<PublicKey, CryptoError> {
+        recover(msg, self)
+    }
+}
+
+/// Signs `msg` with `secret`, using a deterministic RFC6979 nonce.
+///
+/// The signature has a low S value, as required by the standardness rules of Bitcoin. Signing
+/// runs in constant time with respect to `secret`.
+#[must_use]
+pub fn sign_ecdsa_recoverable(msg: &Message, secret: &Scalar) -> RecoverableSignature {
+    let (signature, recovery_id) = SigningKey::from(secret.to_nonzero_scalar())
+        .sign_prehash_recoverable(msg.as_ref())
+        .expect("messages are 32 bytes, signing can't fail");
+    RecoverableSignature::new(signature, RecoveryId(recovery_id.to_byte()))
+}
+
+/// Recovers the public key which made `signature` over `msg`.
+///
+/// The recovered key is only as trustworthy as the claim that it signed `msg`: any signature
+/// recovers to some key, so compare the result with the expected key or its hash.
+///
+/// # Errors
+///
+/// [`CryptoError::InvalidSignature`] if no key matches the signature and its recovery id.
+pub fn recover(msg: &Message, signature: &RecoverableSignature) -> Result<PublicKey, CryptoError> {
+    let key = VerifyingKey::recover_from_prehash(
+        msg.as_ref(),
+        &signature.signature,
+        signature.recovery_id.to_k256(),
+    )
+    .map_err(|_| CryptoError::InvalidSignature)?;
+    Ok(PublicKey::from(k256::PublicKey::from(&key)))
+}
+
+#[cfg(test)]
+mod tests {
+    use hashes::Hash;
+    use hex::test_hex_unwrap as hex;
+
+    use super::*;
+    use crate::sign_message::signed_msg_hash;
+    use crate::G;
+
+    #[test]
+    fn recovery_id() {
+        for id in 0..4 {
+            assert_eq!(RecoveryId::from_u8(id).map(u8::from), Ok(id));
+        }
+        assert_eq!(RecoveryId::from_u8(4), Err(CryptoError::InvalidRecoveryId));
+        let id = RecoveryId::try_from(3).unwrap();
+        assert!(id.is_y_odd() && id.is_x_reduced());
+    }
+
+    #[test]
+    fn sign_and_recover() {
+        for n in 1..=8u32 {
+            let secret = Scalar::from_u32(n * 0x1234_5678).unwrap();
+            let msg = Message::from_digest([n as u8; 32]);
+
+            let signature = sign_ecdsa_recoverable(&msg, &secret);
+            assert!(signature.signature.normalize_s().is_none());
+            assert_eq!(recover(&msg, &signature), Ok(secret * G));
+
+            let (id, compact) = signature.serialize_compact();
+            assert_eq!(
+                RecoverableSignature::from_compact(&compact, id),
+                Ok(signature)
+            );
+
+            // Another message or recovery id recovers another key, if any.
+            let other = Message::from_digest([0xff; 32]);
+            assert_ne!(signature.recover(&other).ok(), Some(secret * G));
+            let flipped = RecoveryId::from_u8(id.to_u8() ^ 1).unwrap();
+            let flipped = RecoverableSignature::new(signature.signature, flipped);
+            assert_ne!(flipped.recover(&msg).ok(), Some(secret * G));
+        }
+    }
+
+    #[test]
+    fn signed_message_vector() {
+        // A Bitcoin signed message of "rust-bitcoin MessageSignature test", whose header byte
+        // 0x20 is a recovery id of 1 and a compressed key.
+        let bytes = hex!(
+            "200336a97db8b58c7f6dd05322054b843f101008eb3e52699a30789d91dd45818805ae03995ba501cc233e759ee90e621305c7e85d2950224ffd97873d64b5875a"
+        );
+        let pubkey = PublicKey::from_slice(&hex!(
+            "0351537cc127b4fa40b72dea904a34ab60dcd45132708d746b78e6c04172f90afa"
+        ))
+        .unwrap();
+
+        let id = RecoveryId::from_u8((bytes[0] - 27) & 3).unwrap();
+        let signature = RecoverableSignature::from_compact(&bytes[1..], id).unwrap();
+        let msg = Message::from_digest(
+            signed_msg_hash("rust-bitcoin MessageSignature test").to_byte_array(),
+        );
+        assert_eq!(signature.recover(&msg), Ok(pubkey));
+
+        assert!(RecoverableSignature::from_compact(&bytes[2..], id).is_err());
+    }
+}
@@ -79,12 +79,13 @@ macro_rules! simple_error {
         #[derive(Debug, PartialEq, Eq)]
         pub struct $name;
 
-        impl std::fmt::Display for $name {
-            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        impl core::fmt::Display for $name {
+            fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
                 f.write_str($error)
             }
         }
 
+        #[cfg(feature = "std")]
         impl std::error::Error for $name {}
     };
 }
@@ -120,6 +121,13 @@ simple_error!(
     or converting from a `MaybeScalar` to a `Scalar`."
 );
 
+simple_error!(
+    InvalidLagrangeIndices,
+    "invalid indices for lagrange interpolation",
+    "Returned when computing a Lagrange coefficient over indices which are \
+    empty, contain duplicates or do not contain the index of the coefficient."
+);
+
 simple_error!(
     InfinityPointError,
     "expected valid non-infinity point",
@@ -0,0 +1,597 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Distributed key generation for FROST.
+//!
+//! The Pedersen DKG with proofs of knowledge of the FROST paper, in which the `participants`
+//! generate a key together, any `threshold` of them being able to sign for it, without anyone
+//! learning the secret key. Every participant runs three parts:
+//!
+//! 1. [`part1`] draws the participant's secret polynomial. The [`Round1Package`] commits to it
+//!    and proves knowledge of its constant term, it is broadcast to all other participants.
+//! 2. [`part2`] checks the packages of all other participants and evaluates the polynomial for
+//!    each of them. Every [`Round2Package`] is sent privately to its recipient, over an encrypted
+//!    and authenticated channel.
+//! 3. [`part3`] checks the received shares against the commitments and sums them into the
+//!    participant's [`KeyShare`], along with the [`PublicKeyPackage`] of the key.
+//!
+//! The round 1 packages must be the same for all participants, the broadcast channel must
+//! guarantee it.
+//!
+
+use core::fmt;
+
+use io::{BufRead, Write};
+use rand::{CryptoRng, RngCore};
+
+use super::{decode_point, KeyShare, PublicKeyPackage};
+use crate::consensus::encode::{self, Decodable, Encodable, VarInt};
+use crate::crypto::hashes::tagged_hash_to_scalar;
+use crate::crypto::key::{MaybePublicKey, PublicKey, G};
+use crate::crypto::scalar::{MaybeScalar, Scalar};
+use crate::crypto::sss;
+use crate::prelude::*;
+
+/// The tag of the challenge of the proofs of knowledge.
+const PROOF_TAG: &str = "FROST/DKG";
+
+/// The secret state of a participant between [`part1`] and [`part2`].
+pub struct Round1Secret {
+    index: u32,
+    threshold: u32,
+    participants: u32,
+    /// The coefficients of the secret polynomial, starting with the constant term.
+    coefficients: Vec<Scalar>,
+    commitments: Vec<PublicKey>,
+}
+
+impl fmt::Debug for Round1Secret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Round1Secret")
+            .field("index", &self.index)
+            .field("threshold", &self.threshold)
+            .field("participants", &self.participants)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The secret state of a participant between [`part2`] and [`part3`].
+pub struct Round2Secret {
+    index: u32,
+    threshold: u32,
+    participants: u32,
+    /// The evaluation of the participant's own polynomial at their index.
+    share: MaybeScalar,
+    commitments: Vec<PublicKey>,
+}
+
+impl fmt::Debug for Round2Secret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Round2Secret")
+            .field("index", &self.index)
+            .field("threshold", &self.threshold)
+            .field("participants", &self.participants)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The package a participant broadcasts to all others in the first round.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Round1Package {
+    /// The commitments to the coefficients of the participant's polynomial.
+    pub commitments: Vec<PublicKey>,
+    /// The nonce of the Schnorr proof of knowledge of the constant term.
+    pub proof_nonce: PublicKey,
+    /// The response of the Schnorr proof of knowledge of the constant term.
+    pub proof_response: MaybeScalar,
+}
+
+/// The share a participant sends privately to another in the second round.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Round2Package {
+    /// The evaluation of the sender's polynomial at the index of the recipient.
+    pub share: MaybeScalar,
+}
+
+impl fmt::Debug for Round2Package {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Round2Package").finish_non_exhaustive()
+    }
+}
+
+/// Starts the key generation for the participant `index`, out of `participants` numbered from
+/// one.
+///
+/// `context` identifies the key generation, e.g. a random session identifier agreed upon by the
+/// participants, it prevents the replay of proofs from other key generations.
+///
+/// # Errors
+///
+/// If `threshold` is zero or greater than `participants`, or `index` isn't one of them.
+pub fn part1<R: RngCore + CryptoRng>(
+    index: u32,
+    threshold: u32,
+    participants: u32,
+    context: &[u8],
+    rng: &mut R,
+) -> Result<(Round1Secret, Round1Package), DkgError> {
+    if threshold == 0 || threshold > participants || index == 0 || index > participants {
+        return Err(DkgError::InvalidParameters {
+            index,
+            threshold,
+            participants,
+        });
+    }
+
+    let coefficients: Vec<Scalar> = (0..threshold)
+        .map(|_| Scalar::from(k256::NonZeroScalar::random(&mut *rng)))
+        .collect();
+    let commitments: Vec<PublicKey> = coefficients.iter().map(|&c| c * G).collect();
+
+    let nonce = Scalar::from(k256::NonZeroScalar::random(&mut *rng));
+    let proof_nonce = nonce * G;
+    let challenge = proof_challenge(index, context, &commitments[0], &proof_nonce);
+    let package = Round1Package {
+        proof_nonce,
+        proof_response: nonce + challenge * coefficients[0],
+        commitments: commitments.clone(),
+    };
+    let secret = Round1Secret {
+        index,
+        threshold,
+        participants,
+        coefficients,
+        commitments,
+    };
+    Ok((secret, package))
+}
+
+/// Checks the round 1 packages of all other participants, by index, and returns the round 2
+/// package to send to each of them.
+///
+/// # Errors
+///
+/// If a package is missing, comes from an unknown participant, or is invalid.
+pub fn part2(
+    secret: Round1Secret,
+    context: &[u8],
+    round1_packages: &BTreeMap<u32, Round1Package>,
+) -> Result<(Round2Secret, BTreeMap<u32, Round2Package>), DkgError> {
+    check_senders(
+        secret.index,
+        secret.participants,
+        round1_packages.keys().copied(),
+    )?;
+    for (&index, package) in round1_packages {
+        if package.commitments.len() != secret.threshold as usize {
+            return Err(DkgError::InvalidCommitments(index));
+        }
+        let challenge = proof_challenge(
+            index,
+            context,
+            &package.commitments[0],
+            &package.proof_nonce,
+        );
+        let expected = package.proof_nonce + challenge * package.commitments[0];
+        if (package.proof_response * G).serialize() != expected.serialize() {
+            return Err(DkgError::InvalidProof(index));
+        }
+    }
+
+    let evaluate = |index: u32| {
+        let x = Scalar::from_u32(index).expect("participant indices are non-zero");
+        sss::evaluate(&secret.coefficients, x)
+    };
+    let packages = round1_packages
+        .keys()
+        .map(|&index| {
+            (
+                index,
+                Round2Package {
+                    share: evaluate(index),
+                },
+            )
+        })
+        .collect();
+    let round2_secret = Round2Secret {
+        index: secret.index,
+        threshold: secret.threshold,
+        participants: secret.participants,
+        share: evaluate(secret.index),
+        commitments: secret.commitments,
+    };
+    Ok((round2_secret, packages))
+}
+
+/// Checks the round 2 packages received from all other participants against their round 1
+/// packages, and returns the participant's key share and the public key package.
+///
+/// # Errors
+///
+/// If a package is missing, comes from an unknown participant, or holds a share which doesn't
+/// match the commitments of its sender.
+pub fn part3(
+    secret: &Round2Secret,
+    round1_packages: &BTreeMap<u32, Round1Package>,
+    round2_packages: &BTreeMap<u32, Round2Package>,
+) -> Result<(KeyShare, PublicKeyPackage), DkgError> {
+    check_senders(
+        secret.index,
+        secret.participants,
+        round1_packages.keys().copied(),
+    )?;
+    check_senders(
+        secret.index,
+        secret.participants,
+        round2_packages.keys().copied(),
+    )?;
+
+    let x = Scalar::from_u32(secret.index).expect("participant indices are non-zero");
+    let mut share = secret.share;
+    for (index, package) in round2_packages {
+        let commitments = &round1_packages[index].commitments;
+        if commitments.len() != secret.threshold as usize
+            || (package.share * G).serialize() != evaluate_commitments(commitments, x).serialize()
+        {
+            return Err(DkgError::InvalidShare(*index));
+        }
+        share += package.share;
+    }
+
+    let all_commitments: Vec<&[PublicKey]> = round1_packages
+        .values()
+        .map(|package| &package.commitments[..])
+        .chain(Some(&secret.commitments[..]))
+        .collect();
+    let group_key = all_commitments
+        .iter()
+        .map(|commitments| commitments[0])
+        .sum::<MaybePublicKey>()
+        .into_option()
+        .ok_or(DkgError::InvalidGroupKey)?;
+    let verifying_shares = (1..=secret.participants)
+        .map(|index| {
+            let x = Scalar::from_u32(index).expect("participant indices are non-zero");
+            let share = all_commitments
+                .iter()
+                .map(|commitments| evaluate_commitments(commitments, x))
+                .sum::<MaybePublicKey>()
+                .into_option()
+                .ok_or(DkgError::InvalidGroupKey)?;
+            Ok::<_, DkgError>((index, share))
+        })
+        .collect::<Result<_, _>>()?;
+
+    let key_share = KeyShare {
+        index: secret.index,
+        secret: share.not_zero().map_err(|_| DkgError::InvalidGroupKey)?,
+        group_key,
+    };
+    let public = PublicKeyPackage {
+        threshold: secret.threshold,
+        group_key,
+        verifying_shares,
+    };
+    Ok((key_share, public))
+}
+
+/// Checks that `senders` are exactly the participants other than `index`.
+fn check_senders<I: Iterator<Item = u32>>(
+    index: u32,
+    participants: u32,
+    senders: I,
+) -> Result<(), DkgError> {
+    let senders: BTreeSet<u32> = senders.collect();
+    if let Some(&unknown) = senders
+        .iter()
+        .find(|&&sender| sender == index || sender == 0 || sender > participants)
+    {
+        return Err(DkgError::UnknownParticipant(unknown));
+    }
+    match (1..=participants).find(|i| *i != index && !senders.contains(i)) {
+        Some(missing) => Err(DkgError::MissingPackage(missing)),
+        None => Ok(()),
+    }
+}
+
+/// Returns the challenge of the proof of knowledge of the constant term committed to by
+/// `commitment`.
+fn proof_challenge(
+    index: u32,
+    context: &[u8],
+    commitment: &PublicKey,
+    nonce: &PublicKey,
+) -> MaybeScalar {
+    tagged_hash_to_scalar(
+        PROOF_TAG,
+        &[
+            &index.to_be_bytes(),
+            &(context.len() as u64).to_be_bytes(),
+            context,
+            &commitment.serialize(),
+            &nonce.serialize(),
+        ],
+    )
+}
+
+/// Evaluates the committed polynomial at `x`, the commitment to the evaluation of the polynomial.
+fn evaluate_commitments(commitments: &[PublicKey], x: Scalar) -> MaybePublicKey {
+    commitments
+        .iter()
+        .rev()
+        .fold(MaybePublicKey::Infinity, |acc, &c| acc * x + c)
+}
+
+impl Encodable for Round1Package {
+    fn consensus_encode<W: Write + ?Sized>(&self, w: &mut W) -> Result<usize, io::Error> {
+        let mut len = VarInt(self.commitments.len() as u64).consensus_encode(w)?;
+        for commitment in &self.commitments {
+            len += commitment.serialize().consensus_encode(w)?;
+        }
+        len += self.proof_nonce.serialize().consensus_encode(w)?;
+        Ok(len + self.proof_response.serialize().consensus_encode(w)?)
+    }
+}
+
+impl Decodable for Round1Package {
+    fn consensus_decode<R: BufRead + ?Sized>(r: &mut R) -> Result<Self, encode::Error> {
+        let len = VarInt::consensus_decode(r)?.0;
+        let commitments = (0..len)
+            .map(|_| decode_point(r))
+            .collect::<Result<_, _>>()?;
+        Ok(Round1Package {
+            commitments,
+            proof_nonce: decode_point(r)?,
+            proof_response: decode_scalar(r)?,
+        })
+    }
+}
+
+impl Encodable for Round2Package {
+    fn consensus_encode<W: Write + ?Sized>(&self, w: &mut W) -> Result<usize, io::Error> {
+        self.share.serialize().consensus_encode(w)
+    }
+}
+
+impl Decodable for Round2Package {
+    fn consensus_decode<R: BufRead + ?Sized>(r: &mut R) -> Result<Self, encode::Error> {
+        Ok(Round2Package {
+            share: decode_scalar(r)?,
+        })
+    }
+}
+
+/// Decodes a scalar below the curve order.
+fn decode_scalar<R: BufRead + ?Sized>(r: &mut R) -> Result<MaybeScalar, encode::Error> {
+    MaybeScalar::from_slice(&<[u8; 32]>::consensus_decode(r)?)
+        .map_err(|_| encode::Error::ParseFailed("invalid scalar"))
+}
+
+/// Errors encountered during a FROST distributed key generation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DkgError {
+    /// The threshold is zero or greater than the number of participants, or the index is not one
+    /// of the participants.
+    InvalidParameters {
+        /// The index of the participant.
+        index: u32,
+        /// The number of participants required to sign.
+        threshold: u32,
+        /// The number of participants.
+        participants: u32,
+    },
+    /// The package of the participant with this index is missing.
+    MissingPackage(u32),
+    /// A package comes from an index which is not one of the other participants.
+    UnknownParticipant(u32),
+    /// The participant with this index committed to a polynomial of the wrong degree.
+    InvalidCommitments(u32),
+    /// The proof of knowledge of the participant with this index is invalid.
+    InvalidProof(u32),
+    /// The share sent by the participant with this index doesn't match their commitments.
+    InvalidShare(u32),
+    /// The group key or a share of it is the point at infinity.
+    InvalidGroupKey,
+}
+
+internals::impl_from_infallible!(DkgError);
+
+impl fmt::Display for DkgError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use DkgError::*;
+
+        match *self {
+            InvalidParameters {
+                index,
+                threshold,
+                participants,
+            } => write!(
+                f,
+                "invalid participant {} for a {}-of-{} key",
+                index, threshold, participants
+            ),
+            MissingPackage(index) => write!(f, "missing package of participant {}", index),
+            UnknownParticipant(index) => write!(f, "package from unknown participant {}", index),
+            InvalidCommitments(index) => {
+                write!(f, "participant {} committed to the wrong degree", index)
+            }
+            InvalidProof(index) => write!(f, "invalid proof of knowledge of participant {}", index),
+            InvalidShare(index) => write!(f, "invalid share from participant {}", index),
+            InvalidGroupKey => f.write_str("the group key is the point at infinity"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DkgError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use DkgError::*;
+
+        match *self {
+            InvalidParameters { .. }
+            | MissingPackage(_)
+            | UnknownParticipant(_)
+            | InvalidCommitments(_)
+            | InvalidProof(_)
+            | InvalidShare(_)
+            | InvalidGroupKey => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{sign, SigningNonces, SigningPackage};
+    use super::*;
+    use crate::consensus::{deserialize, serialize};
+
+    const CONTEXT: &[u8] = b"test key generation";
+
+    /// Runs the first round for all participants.
+    fn round1(
+        threshold: u32,
+        participants: u32,
+    ) -> (Vec<Round1Secret>, BTreeMap<u32, Round1Package>) {
+        let mut rng = rand::thread_rng();
+        (1..=participants)
+            .map(|index| {
+                let (secret, package) =
+                    part1(index, threshold, participants, CONTEXT, &mut rng).unwrap();
+                (secret, (index, package))
+            })
+            .unzip()
+    }
+
+    /// Returns the packages of `packages` sent by participants other than `index`.
+    fn others<T: Clone>(packages: &BTreeMap<u32, T>, index: u32) -> BTreeMap<u32, T> {
+        packages
+            .iter()
+            .filter(|(&i, _)| i != index)
+            .map(|(&i, package)| (i, package.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn generate_and_sign() {
+        let (secrets, round1_packages) = round1(2, 3);
+        let (secrets, round2_packages): (Vec<_>, Vec<_>) = secrets
+            .into_iter()
+            .map(|secret| {
+                let index = secret.index;
+                let received = others(&round1_packages, index);
+                let (secret, packages) = part2(secret, CONTEXT, &received).unwrap();
+                (secret, (index, packages))
+            })
+            .unzip();
+
+        let mut keys = Vec::new();
+        for secret in &secrets {
+            let received = round2_packages
+                .iter()
+                .filter(|(sender, _)| *sender != secret.index)
+                .map(|(sender, packages)| (*sender, packages[&secret.index].clone()))
+                .collect();
+            let received1 = others(&round1_packages, secret.index);
+            keys.push(part3(secret, &received1, &received).unwrap());
+        }
+        // All participants agree on the public key package.
+        let public = keys[0].1.clone();
+        assert!(keys.iter().all(|(_, p)| *p == public));
+
+        // Any two participants sign for the group key.
+        let mut rng = rand::thread_rng();
+        let signers = [&keys[0].0, &keys[2].0];
+        let nonces = signers.map(|_| SigningNonces::new(&mut rng));
+        let package = SigningPackage {
+            message: [0x42; 32],
+            commitments: signers
+                .iter()
+                .zip(&nonces)
+                .map(|(key, nonces)| (key.index, nonces.commitments()))
+                .collect(),
+            tweak: Some(Scalar::one()),
+        };
+        let shares: Vec<_> = signers
+            .into_iter()
+            .zip(nonces)
+            .map(|(key, nonces)| sign(&package, key, nonces).unwrap())
+            .collect();
+        assert!(package.aggregate(&public, &shares).is_ok());
+    }
+
+    #[test]
+    fn invalid_packages() {
+        let mut rng = rand::thread_rng();
+        assert_eq!(
+            part1(4, 2, 3, CONTEXT, &mut rng).unwrap_err(),
+            DkgError::InvalidParameters {
+                index: 4,
+                threshold: 2,
+                participants: 3
+            }
+        );
+        assert!(part1(1, 0, 3, CONTEXT, &mut rng).is_err());
+
+        let (mut secrets, round1_packages) = round1(2, 3);
+        let mut received = others(&round1_packages, 1);
+        // A proof is only valid for its context.
+        let (secret, _) = part1(1, 2, 3, CONTEXT, &mut rng).unwrap();
+        assert_eq!(
+            part2(secret, b"another context", &received).unwrap_err(),
+            DkgError::InvalidProof(2)
+        );
+        received.remove(&3);
+        assert_eq!(
+            part2(secrets.remove(0), CONTEXT, &received).unwrap_err(),
+            DkgError::MissingPackage(3)
+        );
+
+        let (mut secrets, round1_packages) = round1(2, 3);
+        let mut received = others(&round1_packages, 1);
+        received.get_mut(&2).unwrap().commitments.pop();
+        assert_eq!(
+            part2(secrets.remove(0), CONTEXT, &received).unwrap_err(),
+            DkgError::InvalidCommitments(2)
+        );
+
+        // A share which doesn't match the commitments of its sender.
+        let (mut secrets, round1_packages) = round1(2, 3);
+        let received1 = others(&round1_packages, 1);
+        let (secret, _) = part2(secrets.remove(0), CONTEXT, &received1).unwrap();
+        let (_, packages2) =
+            part2(secrets.remove(0), CONTEXT, &others(&round1_packages, 2)).unwrap();
+        let (_, packages3) =
+            part2(secrets.remove(0), CONTEXT, &others(&round1_packages, 3)).unwrap();
+        let mut received2 = BTreeMap::new();
+        received2.insert(2, packages2[&1].clone());
+        received2.insert(3, packages3[&1].clone());
+        assert!(part3(&secret, &received1, &received2).is_ok());
+        received2.get_mut(&3).unwrap().share += Scalar::one();
+        assert_eq!(
+            part3(&secret, &received1, &received2).unwrap_err(),
+            DkgError::InvalidShare(3)
+        );
+        received2.insert(4, packages3[&1].clone());
+        assert_eq!(
+            part3(&secret, &received1, &received2).unwrap_err(),
+            DkgError::UnknownParticipant(4)
+        );
+    }
+
+    #[test]
+    fn encoding_roundtrip() {
+        let (_, round1_packages) = round1(3, 3);
+        let package = &round1_packages[&1];
+        assert_eq!(
+            deserialize::<Round1Package>(&serialize(package)).unwrap(),
+            *package
+        );
+        let package = Round2Package {
+            share: Scalar::one().into(),
+        };
+        assert_eq!(
+            deserialize::<Round2Package>(&serialize(&package)).unwrap(),
+            package
+        );
+    }
+}
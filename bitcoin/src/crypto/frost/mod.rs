@@ -0,0 +1,701 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! FROST threshold Schnorr signatures.
+//!
+//! A BIP340 compatible variant of FROST (RFC 9591), in which any `threshold` holders of shares of
+//! a key split with [`sss`](super::sss) jointly produce a Schnorr signature for the group key,
+//! optionally tweaked as for a taproot key path spend.
+//!
+//! The key is either split by a trusted dealer with [`generate_with_dealer`], or generated by the
+//! signers themselves with the distributed key generation of [`dkg`], so that nobody ever learns
+//! the secret. Signing takes two rounds:
+//!
+//! 1. Every signer draws fresh [`SigningNonces`] and sends their [`NonceCommitments`] to the
+//!    coordinator.
+//! 2. The coordinator sends a [`SigningPackage`] with the message and the commitments of all
+//!    participating signers back, and every signer answers with a [`SignatureShare`] from [`sign`].
+//!
+//! The coordinator checks every share with [`SigningPackage::verify_share`] and combines them
+//! into the final signature with [`SigningPackage::aggregate`].
+//!
+//! Nonces must never be reused, which is why [`sign`] consumes them.
+//!
+
+use core::fmt;
+
+use io::{BufRead, Write};
+use k256::schnorr::signature::hazmat::PrehashVerifier as _;
+use rand::{CryptoRng, RngCore};
+
+use super::hashes::tagged_hash_to_scalar;
+use super::key::{MaybePublicKey, PublicKey, G};
+use super::scalar::{MaybeScalar, Scalar};
+use super::sss::{self, SplitError};
+use crate::consensus::encode::{self, Decodable, Encodable, VarInt};
+use crate::prelude::*;
+use crate::Parity;
+
+pub mod dkg;
+
+/// The tag of the hash deriving the binding factor of every signer.
+const BINDING_FACTOR_TAG: &str = "FROST/binding";
+
+/// The tag of the BIP340 challenge hash.
+const CHALLENGE_TAG: &str = "BIP0340/challenge";
+
+/// A signer's share of a FROST key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyShare {
+    /// The index of the signer, starting at one.
+    pub index: u32,
+    /// The signer's share of the secret key.
+    pub secret: Scalar,
+    /// The untweaked group public key.
+    pub group_key: PublicKey,
+}
+
+/// The public parts of a FROST key, as needed by the coordinator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublicKeyPackage {
+    /// The number of signers required to produce a signature.
+    pub threshold: u32,
+    /// The untweaked group public key.
+    pub group_key: PublicKey,
+    /// The public key of every signer's share, by index.
+    pub verifying_shares: BTreeMap<u32, PublicKey>,
+}
+
+/// Splits `secret` into `shares` key shares, any `threshold` of which can sign for it.
+///
+/// This is the trusted dealer key generation of FROST: whoever runs it learns the secret.
+///
+/// # Errors
+///
+/// If `threshold` is zero or greater than `shares`.
+pub fn generate_with_dealer<R: RngCore + CryptoRng>(
+    secret: Scalar,
+    threshold: u32,
+    shares: u32,
+    rng: &mut R,
+) -> Result<(Vec<KeyShare>, PublicKeyPackage), SplitError> {
+    let group_key = secret.base_point_mul();
+    let key_shares: Vec<KeyShare> = sss::split(secret, threshold, shares, rng)?
+        .into_iter()
+        .map(|(index, secret)| KeyShare {
+            index,
+            secret,
+            group_key,
+        })
+        .collect();
+    let verifying_shares = key_shares
+        .iter()
+        .map(|share| (share.index, share.secret.base_point_mul()))
+        .collect();
+    Ok((
+        key_shares,
+        PublicKeyPackage {
+            threshold,
+            group_key,
+            verifying_shares,
+        },
+    ))
+}
+
+/// The secret nonces of a signer for a single signing session.
+pub struct SigningNonces {
+    hiding: Scalar,
+    binding: Scalar,
+}
+
+impl SigningNonces {
+    /// Draws fresh nonces from `rng`.
+    pub fn new<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+        SigningNonces {
+            hiding: Scalar::from(k256::NonZeroScalar::random(&mut *rng)),
+            binding: Scalar::from(k256::NonZeroScalar::random(&mut *rng)),
+        }
+    }
+
+    /// Returns the commitments to these nonces, to be sent to the coordinator.
+    pub fn commitments(&self) -> NonceCommitments {
+        NonceCommitments {
+            hiding: self.hiding.base_point_mul(),
+            binding: self.binding.base_point_mul(),
+        }
+    }
+}
+
+impl fmt::Debug for SigningNonces {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SigningNonces").finish_non_exhaustive()
+    }
+}
+
+/// A signer's commitments to their [`SigningNonces`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NonceCommitments {
+    /// The commitment to the hiding nonce.
+    pub hiding: PublicKey,
+    /// The commitment to the binding nonce.
+    pub binding: PublicKey,
+}
+
+/// A signer's share of a signature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignatureShare {
+    /// The index of the signer.
+    pub index: u32,
+    /// The share of the signature.
+    pub share: MaybeScalar,
+}
+
+/// Everything a signer needs to produce their [`SignatureShare`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SigningPackage {
+    /// The BIP340 message to sign.
+    pub message: [u8; 32],
+    /// The nonce commitments of all participating signers, by index.
+    pub commitments: BTreeMap<u32, NonceCommitments>,
+    /// The tweak added to the group key, e.g. a taproot tweak.
+    ///
+    /// The group key is first normalized to an even y-coordinate, as for BIP341 output keys.
+    pub tweak: Option<Scalar>,
+}
+
+/// The values shared by all signers of a [`SigningPackage`].
+struct Context {
+    /// The key the signature is valid for.
+    output_key: PublicKey,
+    /// Odd if the secret key shares are negated to match the even y-coordinate output key.
+    key_parity: Parity,
+    /// The tweak added to the secret key of the output key, adjusted for its parity.
+    tweak: MaybeScalar,
+    /// The group commitment `R`.
+    group_nonce: PublicKey,
+    /// Odd if the nonces are negated to match the even y-coordinate of `R`.
+    nonce_parity: Parity,
+    /// The BIP340 challenge.
+    challenge: MaybeScalar,
+    binding_factors: BTreeMap<u32, MaybeScalar>,
+    lagrange_coefficients: BTreeMap<u32, Scalar>,
+}
+
+impl SigningPackage {
+    /// Computes the signing context of this package for `group_key`.
+    fn context(&self, group_key: PublicKey) -> Result<Context, FrostError> {
+        let (output_key, key_parity, tweak) = match self.tweak {
+            None => (group_key, parity(&group_key), MaybeScalar::Zero),
+            Some(tweak) => {
+                let output_key = (group_key.to_even_y() + tweak * G)
+                    .into_option()
+                    .ok_or(FrostError::InvalidTweak)?;
+                let key_parity = parity(&group_key) ^ parity(&output_key);
+                let tweak = tweak.negate_if(parity(&output_key));
+                (output_key, key_parity, MaybeScalar::Valid(tweak))
+            }
+        };
+
+        if self.commitments.is_empty() {
+            return Err(FrostError::NoSigners);
+        }
+        let indices = self
+            .commitments
+            .keys()
+            .map(|&index| Scalar::from_u32(index).map_err(|_| FrostError::ZeroIndex))
+            .collect::<Result<Vec<_>, _>>()?;
+        let lagrange_coefficients = self
+            .commitments
+            .keys()
+            .copied()
+            .zip(
+                Scalar::lagrange_coefficients(&indices).expect("indices are distinct and non-zero"),
+            )
+            .collect();
+
+        // Every binding factor commits to the key, the message and all commitments.
+        let mut prefix = Vec::with_capacity(65 + self.commitments.len() * 70);
+        prefix.extend_from_slice(&output_key.serialize());
+        prefix.extend_from_slice(&self.message);
+        for (index, commitments) in &self.commitments {
+            prefix.extend_from_slice(&index.to_be_bytes());
+            prefix.extend_from_slice(&commitments.hiding.serialize());
+            prefix.extend_from_slice(&commitments.binding.serialize());
+        }
+        let binding_factors: BTreeMap<u32, MaybeScalar> = self
+            .commitments
+            .keys()
+            .map(|&index| {
+                (
+                    index,
+                    tagged_hash_to_scalar(BINDING_FACTOR_TAG, &[&prefix, &index.to_be_bytes()]),
+                )
+            })
+            .collect();
+
+        let group_nonce = self
+            .commitments
+            .iter()
+            .map(|(index, commitments)| {
+                commitments.hiding + binding_factors[index] * commitments.binding
+            })
+            .sum::<MaybePublicKey>()
+            .into_option()
+            .ok_or(FrostError::InfiniteNonce)?;
+        let challenge = tagged_hash_to_scalar(
+            CHALLENGE_TAG,
+            &[
+                &group_nonce.serialize_xonly(),
+                &output_key.serialize_xonly(),
+                &self.message,
+            ],
+        );
+
+        Ok(Context {
+            output_key,
+            key_parity,
+            tweak,
+            nonce_parity: parity(&group_nonce),
+            group_nonce,
+            challenge,
+            binding_factors,
+            lagrange_coefficients,
+        })
+    }
+
+    /// Returns the key the aggregated signature is valid for, the group key plus the tweak.
+    pub fn output_key(&self, group_key: PublicKey) -> Result<PublicKey, FrostError> {
+        Ok(self.context(group_key)?.output_key)
+    }
+
+    /// Checks the signature share `share` against the signer's verifying share.
+    pub fn verify_share(
+        &self,
+        public: &PublicKeyPackage,
+        share: &SignatureShare,
+    ) -> Result<(), FrostError> {
+        let context = self.context(public.group_key)?;
+        self.verify_share_with(&context, public, share)
+    }
+
+    fn verify_share_with(
+        &self,
+        context: &Context,
+        public: &PublicKeyPackage,
+        share: &SignatureShare,
+    ) -> Result<(), FrostError> {
+        let index = share.index;
+        let verifying_share = *public
+            .verifying_shares
+            .get(&index)
+            .ok_or(FrostError::UnknownSigner(index))?;
+        let commitments = self
+            .commitments
+            .get(&index)
+            .ok_or(FrostError::MissingCommitment(index))?;
+
+        let nonce = commitments.hiding + context.binding_factors[&index] * commitments.binding;
+        let nonce = if context.nonce_parity == Parity::Odd {
+            -nonce
+        } else {
+            nonce
+        };
+        let key = verifying_share.negate_if(context.key_parity);
+        let expected = nonce + context.challenge * context.lagrange_coefficients[&index] * key;
+
+        if (share.share * G).serialize() == expected.serialize() {
+            Ok(())
+        } else {
+            Err(FrostError::InvalidShare(index))
+        }
+    }
+
+    /// Verifies the signature shares of all signers of this package and combines them into a
+    /// BIP340 signature for the output key.
+    pub fn aggregate(
+        &self,
+        public: &PublicKeyPackage,
+        shares: &[SignatureShare],
+    ) -> Result<k256::schnorr::Signature, FrostError> {
+        if (self.commitments.len() as u64) < u64::from(public.threshold) {
+            return Err(FrostError::NotEnoughSigners {
+                required: public.threshold,
+                got: self.commitments.len(),
+            });
+        }
+        let context = self.context(public.group_key)?;
+
+        let mut sum = MaybeScalar::Zero;
+        for &index in self.commitments.keys() {
+            let share = shares
+                .iter()
+                .find(|s| s.index == index)
+                .ok_or(FrostError::MissingShare(index))?;
+            self.verify_share_with(&context, public, share)?;
+            sum += share.share;
+        }
+        let s = sum + context.challenge * context.tweak;
+
+        let mut bytes = [0u8; 64];
+        bytes[..32].copy_from_slice(&context.group_nonce.serialize_xonly());
+        bytes[32..].copy_from_slice(&s.serialize());
+        let signature = k256::schnorr::Signature::try_from(&bytes[..])
+            .map_err(|_| FrostError::InvalidSignature)?;
+        let output_key =
+            k256::schnorr::VerifyingKey::from_bytes(&context.output_key.serialize_xonly())
+                .map_err(|_| FrostError::InvalidSignature)?;
+        output_key
+            .verify_prehash(&self.message, &signature)
+            .map_err(|_| FrostError::InvalidSignature)?;
+        Ok(signature)
+    }
+}
+
+/// Produces the signature share of `key` for `package`, consuming the signer's `nonces`.
+///
+/// Runs in constant time with respect to the secret share of `key` and `nonces`.
+///
+/// # Errors
+///
+/// If the package does not contain the commitments to `nonces` under the signer's index.
+pub fn sign(
+    package: &SigningPackage,
+    key: &KeyShare,
+    nonces: SigningNonces,
+) -> Result<SignatureShare, FrostError> {
+    let commitments = package
+        .commitments
+        .get(&key.index)
+        .ok_or(FrostError::MissingCommitment(key.index))?;
+    if *commitments != nonces.commitments() {
+        return Err(FrostError::CommitmentMismatch(key.index));
+    }
+    let context = package.context(key.group_key)?;
+
+    let nonce = nonces.hiding + context.binding_factors[&key.index] * nonces.binding;
+    let nonce = nonce.negate_if(context.nonce_parity);
+    let secret =
+        (context.lagrange_coefficients[&key.index] * key.secret).negate_if(context.key_parity);
+
+    Ok(SignatureShare {
+        index: key.index,
+        share: nonce + context.challenge * secret,
+    })
+}
+
+/// Returns the parity of the Y-coordinate of `point`.
+fn parity(point: &PublicKey) -> Parity {
+    if point.has_odd_y() {
+        Parity::Odd
+    } else {
+        Parity::Even
+    }
+}
+
+impl Encodable for NonceCommitments {
+    fn consensus_encode<W: Write + ?Sized>(&self, w: &mut W) -> Result<usize, io::Error> {
+        let len = self.hiding.serialize().consensus_encode(w)?;
+        Ok(len + self.binding.serialize().consensus_encode(w)?)
+    }
+}
+
+impl Decodable for NonceCommitments {
+    fn consensus_decode<R: BufRead + ?Sized>(r: &mut R) -> Result<Self, encode::Error> {
+        Ok(NonceCommitments {
+            hiding: decode_point(r)?,
+            binding: decode_point(r)?,
+        })
+    }
+}
+
+impl Encodable for SignatureShare {
+    fn consensus_encode<W: Write + ?Sized>(&self, w: &mut W) -> Result<usize, io::Error> {
+        let len = self.index.consensus_encode(w)?;
+        Ok(len + self.share.serialize().consensus_encode(w)?)
+    }
+}
+
+impl Decodable for SignatureShare {
+    fn consensus_decode<R: BufRead + ?Sized>(r: &mut R) -> Result<Self, encode::Error> {
+        let index = u32::consensus_decode(r)?;
+        let share = MaybeScalar::from_slice(&<[u8; 32]>::consensus_decode(r)?)
+            .map_err(|_| encode::Error::ParseFailed("invalid signature share"))?;
+        Ok(SignatureShare { index, share })
+    }
+}
+
+impl Encodable for SigningPackage {
+    fn consensus_encode<W: Write + ?Sized>(&self, w: &mut W) -> Result<usize, io::Error> {
+        let mut len = self.message.consensus_encode(w)?;
+        len += encode_map(&self.commitments, w)?;
+        len += match self.tweak {
+            Some(tweak) => 1u8.consensus_encode(w)? + tweak.serialize().consensus_encode(w)?,
+            None => 0u8.consensus_encode(w)?,
+        };
+        Ok(len)
+    }
+}
+
+impl Decodable for SigningPackage {
+    fn consensus_decode<R: BufRead + ?Sized>(r: &mut R) -> Result<Self, encode::Error> {
+        let message = Decodable::consensus_decode(r)?;
+        let commitments = decode_map(r)?;
+        let tweak = match u8::consensus_decode(r)? {
+            0 => None,
+            1 => Some(
+                Scalar::from_slice(&<[u8; 32]>::consensus_decode(r)?)
+                    .map_err(|_| encode::Error::ParseFailed("invalid tweak"))?,
+            ),
+            _ => return Err(encode::Error::ParseFailed("invalid tweak presence flag")),
+        };
+        Ok(SigningPackage {
+            message,
+            commitments,
+            tweak,
+        })
+    }
+}
+
+/// Decodes a compressed point.
+fn decode_point<R: BufRead + ?Sized>(r: &mut R) -> Result<PublicKey, encode::Error> {
+    PublicKey::from_slice(&<[u8; 33]>::consensus_decode(r)?)
+        .map_err(|_| encode::Error::ParseFailed("invalid point"))
+}
+
+/// Encodes a map keyed by `u32` as a length followed by the entries in ascending key order.
+pub(crate) fn encode_map<T: Encodable, W: Write + ?Sized>(
+    map: &BTreeMap<u32, T>,
+    w: &mut W,
+) -> Result<usize, io::Error> {
+    let mut len = VarInt(map.len() as u64).consensus_encode(w)?;
+    for (key, value) in map {
+        len += key.consensus_encode(w)?;
+        len += value.consensus_encode(w)?;
+    }
+    Ok(len)
+}
+
+/// Decodes a map encoded by [`encode_map`], rejecting keys which are not strictly ascending.
+pub(crate) fn decode_map<T: Decodable, R: BufRead + ?Sized>(
+    r: &mut R,
+) -> Result<BTreeMap<u32, T>, encode::Error> {
+    let len = VarInt::consensus_decode(r)?.0;
+    let mut map = BTreeMap::new();
+    for _ in 0..len {
+        let key = u32::consensus_decode(r)?;
+        if map.keys().next_back().is_some_and(|&last| last >= key) {
+            return Err(encode::Error::ParseFailed(
+                "map keys not in ascending order",
+            ));
+        }
+        map.insert(key, T::consensus_decode(r)?);
+    }
+    Ok(map)
+}
+
+/// Errors encountered while signing with a FROST key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FrostError {
+    /// The signing package has no signers.
+    NoSigners,
+    /// The signing package has fewer signers than the threshold.
+    NotEnoughSigners {
+        /// The threshold of the key.
+        required: u32,
+        /// The number of signers in the package.
+        got: usize,
+    },
+    /// A signer has index zero.
+    ZeroIndex,
+    /// The signer with this index is not part of the key.
+    UnknownSigner(u32),
+    /// The signing package has no commitments for the signer with this index.
+    MissingCommitment(u32),
+    /// The commitments in the signing package don't match the nonces of the signer.
+    CommitmentMismatch(u32),
+    /// The signer with this index did not provide a signature share.
+    MissingShare(u32),
+    /// The signature share of the signer with this index is invalid.
+    InvalidShare(u32),
+    /// The tweak cancels out the group key.
+    InvalidTweak,
+    /// The group commitment is the point at infinity.
+    InfiniteNonce,
+    /// The aggregated signature does not verify.
+    InvalidSignature,
+}
+
+internals::impl_from_infallible!(FrostError);
+
+impl fmt::Display for FrostError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use FrostError::*;
+
+        match *self {
+            NoSigners => f.write_str("the signing package has no signers"),
+            NotEnoughSigners { required, got } => write!(
+                f,
+                "{} signers in the signing package, {} required",
+                got, required
+            ),
+            ZeroIndex => f.write_str("signer has index zero"),
+            UnknownSigner(index) => write!(f, "signer {} is not part of the key", index),
+            MissingCommitment(index) => write!(f, "no nonce commitments for signer {}", index),
+            CommitmentMismatch(index) => write!(
+                f,
+                "nonce commitments of signer {} don't match its nonces",
+                index
+            ),
+            MissingShare(index) => write!(f, "no signature share from signer {}", index),
+            InvalidShare(index) => write!(f, "invalid signature share from signer {}", index),
+            InvalidTweak => f.write_str("the tweak cancels out the group key"),
+            InfiniteNonce => f.write_str("the group commitment is the point at infinity"),
+            InvalidSignature => f.write_str("the aggregated signature does not verify"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for FrostError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use FrostError::*;
+
+        match *self {
+            NoSigners
+            | NotEnoughSigners { .. }
+            | ZeroIndex
+            | UnknownSigner(_)
+            | MissingCommitment(_)
+            | CommitmentMismatch(_)
+            | MissingShare(_)
+            | InvalidShare(_)
+            | InvalidTweak
+            | InfiniteNonce
+            | InvalidSignature => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::{deserialize, serialize};
+
+    /// Runs both signing rounds for the signers with the given indices.
+    fn sign_with(
+        key_shares: &[KeyShare],
+        signers: &[u32],
+        message: [u8; 32],
+        tweak: Option<Scalar>,
+    ) -> (SigningPackage, Vec<SignatureShare>) {
+        let mut rng = rand::thread_rng();
+        let signers: Vec<&KeyShare> = key_shares
+            .iter()
+            .filter(|share| signers.contains(&share.index))
+            .collect();
+        let nonces: Vec<SigningNonces> = signers
+            .iter()
+            .map(|_| SigningNonces::new(&mut rng))
+            .collect();
+        let commitments = signers
+            .iter()
+            .zip(&nonces)
+            .map(|(key, nonces)| (key.index, nonces.commitments()));
+        let package = SigningPackage {
+            message,
+            commitments: commitments.collect(),
+            tweak,
+        };
+        let shares = signers
+            .into_iter()
+            .zip(nonces)
+            .map(|(key, nonces)| sign(&package, key, nonces).unwrap())
+            .collect();
+        (package, shares)
+    }
+
+    #[test]
+    fn sign_and_aggregate() {
+        let mut rng = rand::thread_rng();
+        let secret = Scalar::reduce_from(&[0x17; 32]);
+        let (key_shares, public) = generate_with_dealer(secret, 2, 3, &mut rng).unwrap();
+
+        for tweak in [None, Some(Scalar::reduce_from(&[0x99; 32]))] {
+            for signers in [&[1, 2][..], &[2, 3], &[1, 2, 3]] {
+                let (package, shares) = sign_with(&key_shares, signers, [0x01; 32], tweak);
+                for share in &shares {
+                    assert_eq!(package.verify_share(&public, share), Ok(()));
+                }
+                let signature = package.aggregate(&public, &shares).unwrap();
+
+                // The signature is valid for the (tweaked) key of the full secret.
+                let output_key = package.output_key(public.group_key).unwrap();
+                let expected = match tweak {
+                    None => secret.base_point_mul(),
+                    Some(tweak) => (secret.base_point_mul().to_even_y() + tweak * G).unwrap(),
+                };
+                assert_eq!(output_key.serialize_xonly(), expected.serialize_xonly());
+                let key =
+                    k256::schnorr::VerifyingKey::from_bytes(&output_key.serialize_xonly()).unwrap();
+                assert!(key.verify_prehash(&package.message, &signature).is_ok());
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_shares() {
+        let mut rng = rand::thread_rng();
+        let (key_shares, public) =
+            generate_with_dealer(Scalar::reduce_from(&[0x17; 32]), 2, 3, &mut rng).unwrap();
+
+        let (package, mut shares) = sign_with(&key_shares, &[1, 3], [0x02; 32], None);
+        assert_eq!(
+            package.aggregate(&public, &shares[..1]),
+            Err(FrostError::MissingShare(3))
+        );
+
+        shares[1].share += Scalar::one();
+        assert_eq!(
+            package.verify_share(&public, &shares[1]),
+            Err(FrostError::InvalidShare(3))
+        );
+        assert_eq!(
+            package.aggregate(&public, &shares),
+            Err(FrostError::InvalidShare(3))
+        );
+
+        let (package, shares) = sign_with(&key_shares, &[2], [0x02; 32], None);
+        assert_eq!(
+            package.aggregate(&public, &shares),
+            Err(FrostError::NotEnoughSigners {
+                required: 2,
+                got: 1
+            })
+        );
+
+        // Nonces only sign for the commitments they were drawn for.
+        let nonces = SigningNonces::new(&mut rng);
+        assert_eq!(
+            sign(&package, &key_shares[1], nonces),
+            Err(FrostError::CommitmentMismatch(2))
+        );
+    }
+
+    #[test]
+    fn encoding_roundtrip() {
+        let mut rng = rand::thread_rng();
+        let (key_shares, _) =
+            generate_with_dealer(Scalar::reduce_from(&[0x17; 32]), 2, 3, &mut rng).unwrap();
+        let tweak = Some(Scalar::one());
+        let (package, shares) = sign_with(&key_shares, &[1, 2], [0x03; 32], tweak);
+
+        assert_eq!(
+            deserialize::<SigningPackage>(&serialize(&package)).unwrap(),
+            package
+        );
+        assert_eq!(
+            deserialize::<SignatureShare>(&serialize(&shares[0])).unwrap(),
+            shares[0]
+        );
+    }
+}
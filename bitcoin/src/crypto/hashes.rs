@@ -0,0 +1,238 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Tagged hashes.
+//!
+//! Helpers for the tagged hash construction of BIP340, `sha256(sha256(tag) || sha256(tag) || x)`,
+//! as used to derive Schnorr, MuSig and taproot challenges. With the `std` feature, the midstate
+//! after hashing the tag prefix is cached per tag, so repeated hashing under the same tag only
+//! processes the inputs.
+//!
+//! SHA256 is computed with the engine of the `hashes` crate by default. Platforms with hardware
+//! SHA256 extensions, or constrained ones, can implement [`Sha256Engine`] for their own engine
+//! and use the `_with` variants of the helpers, or
+//! [`SighashCache::taproot_signature_hash_with`](crate::sighash::SighashCache::taproot_signature_hash_with)
+//! for sighashes.
+//!
+
+#[cfg(feature = "std")]
+use std::sync::Mutex;
+
+use hashes::{sha256, Hash, HashEngine};
+#[cfg(feature = "std")]
+use once_cell::sync::Lazy;
+
+use super::scalar::MaybeScalar;
+#[cfg(feature = "std")]
+use crate::prelude::*;
+
+/// The maximum number of tags whose midstates are cached.
+#[cfg(feature = "std")]
+const MAX_CACHED_TAGS: usize = 64;
+
+/// The length of the tag prefix `sha256(tag) || sha256(tag)`.
+const TAG_PREFIX_LEN: usize = 64;
+
+#[cfg(feature = "std")]
+static MIDSTATES: Lazy<Mutex<BTreeMap<String, sha256::Midstate>>> =
+    Lazy::new(|| Mutex::new(BTreeMap::new()));
+
+/// A SHA256 implementation.
+pub trait Sha256Engine: Sized {
+    /// Creates an engine resuming from `midstate`, the state after hashing `length` bytes.
+    ///
+    /// `length` is a multiple of the 64 bytes block size.
+    fn from_midstate(midstate: sha256::Midstate, length: usize) -> Self;
+
+    /// Adds `data` to the hashed data.
+    fn update(&mut self, data: &[u8]);
+
+    /// Returns the hash of the data.
+    fn finalize(self) -> sha256::Hash;
+}
+
+impl Sha256Engine for sha256::HashEngine {
+    fn from_midstate(midstate: sha256::Midstate, length: usize) -> Self {
+        sha256::HashEngine::from_midstate(midstate, length)
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        HashEngine::input(self, data)
+    }
+
+    fn finalize(self) -> sha256::Hash {
+        sha256::Hash::from_engine(self)
+    }
+}
+
+/// Writes into a [`Sha256Engine`], to hash encodings with it.
+pub(crate) struct EngineWriter<E>(pub(crate) E);
+
+impl<E: Sha256Engine> io::Write for EngineWriter<E> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Returns a SHA256 engine which has already processed the tag prefix of `tag`.
+pub fn tagged_engine(tag: &str) -> sha256::HashEngine {
+    tagged_engine_with(tag)
+}
+
+/// Returns a SHA256 engine of type `E` which has already processed the tag prefix of `tag`.
+pub fn tagged_engine_with<E: Sha256Engine>(tag: &str) -> E {
+    E::from_midstate(tag_midstate(tag), TAG_PREFIX_LEN)
+}
+
+/// Returns the midstate after the tag prefix of `tag`, from the cache if possible.
+#[cfg(feature = "std")]
+fn tag_midstate(tag: &str) -> sha256::Midstate {
+    // The cache only holds midstates, a poisoned lock can't leave it inconsistent.
+    let mut cache = MIDSTATES.lock().unwrap_or_else(|e| e.into_inner());
+    match cache.get(tag) {
+        Some(midstate) => *midstate,
+        None => {
+            let midstate = sha256::Midstate::hash_tag(tag.as_bytes());
+            if cache.len() < MAX_CACHED_TAGS {
+                cache.insert(tag.to_owned(), midstate);
+            }
+            midstate
+        }
+    }
+}
+
+/// Returns the midstate after the tag prefix of `tag`.
+///
+/// There is no cache without `std`, the midstate is computed on every call.
+#[cfg(not(feature = "std"))]
+fn tag_midstate(tag: &str) -> sha256::Midstate {
+    sha256::Midstate::hash_tag(tag.as_bytes())
+}
+
+/// Computes the BIP340 tagged hash of the concatenation of `inputs` under `tag`.
+pub fn tagged_hash(tag: &str, inputs: &[&[u8]]) -> sha256::Hash {
+    tagged_hash_with::<sha256::HashEngine>(tag, inputs)
+}
+
+/// Computes the BIP340 tagged hash of the concatenation of `inputs` under `tag`, with the SHA256
+/// engine `E`.
+pub fn tagged_hash_with<E: Sha256Engine>(tag: &str, inputs: &[&[u8]]) -> sha256::Hash {
+    let mut engine = tagged_engine_with::<E>(tag);
+    for input in inputs {
+        engine.update(input);
+    }
+    engine.finalize()
+}
+
+/// Computes the BIP340 tagged hash of the concatenation of `inputs` under `tag`, and reduces it
+/// modulo the curve order.
+///
+/// For example, the BIP340 challenge is
+/// `tagged_hash_to_scalar("BIP0340/challenge", &[&r, &pubkey, &msg])`.
+pub fn tagged_hash_to_scalar(tag: &str, inputs: &[&[u8]]) -> MaybeScalar {
+    MaybeScalar::reduce_from(&tagged_hash(tag, inputs).to_byte_array())
+}
+
+#[cfg(test)]
+mod tests {
+    use hex::FromHex;
+
+    use super::*;
+
+    fn naive_tagged_hash(tag: &str, msg: &[u8]) -> sha256::Hash {
+        let tag_hash = sha256::Hash::hash(tag.as_bytes());
+        let mut engine = sha256::Hash::engine();
+        engine.input(tag_hash.as_ref());
+        engine.input(tag_hash.as_ref());
+        engine.input(msg);
+        sha256::Hash::from_engine(engine)
+    }
+
+    #[test]
+    fn tagged_hash_matches_definition() {
+        for tag in ["BIP0340/challenge", "TapTweak", ""] {
+            // Hash twice to go through the cache.
+            for _ in 0..2 {
+                assert_eq!(
+                    tagged_hash(tag, &[b"hello", b" ", b"world"]),
+                    naive_tagged_hash(tag, b"hello world")
+                );
+            }
+        }
+        assert_eq!(
+            tagged_hash("TapTweak", &[]),
+            naive_tagged_hash("TapTweak", &[])
+        );
+    }
+
+    /// An engine counting the bytes it hashes.
+    struct CountingEngine(sha256::HashEngine, usize);
+
+    impl Sha256Engine for CountingEngine {
+        fn from_midstate(midstate: sha256::Midstate, length: usize) -> Self {
+            CountingEngine(Sha256Engine::from_midstate(midstate, length), length)
+        }
+
+        fn update(&mut self, data: &[u8]) {
+            self.1 += data.len();
+            self.0.update(data)
+        }
+
+        fn finalize(self) -> sha256::Hash {
+            assert_eq!(self.1, TAG_PREFIX_LEN + 11);
+            self.0.finalize()
+        }
+    }
+
+    #[test]
+    fn custom_engine() {
+        assert_eq!(
+            tagged_hash_with::<CountingEngine>("TapTweak", &[b"hello", b" ", b"world"]),
+            naive_tagged_hash("TapTweak", b"hello world")
+        );
+    }
+
+    #[test]
+    fn tagged_hash_to_scalar_reduces() {
+        let hash = tagged_hash("KeyAgg coefficient", &[&[0x02; 33]]);
+        assert_eq!(
+            tagged_hash_to_scalar("KeyAgg coefficient", &[&[0x02; 33]]),
+            MaybeScalar::reduce_from(&hash.to_byte_array())
+        );
+
+        let max = MaybeScalar::reduce_from(&[0xff; 32]);
+        assert_eq!(
+            max.serialize().as_hex().to_string(),
+            "000000000000000000000000000000014551231950b75fc4402da1732fc9bebe"
+        );
+        let n = "fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141";
+        let n = <[u8; 32]>::from_hex(n).unwrap();
+        assert_eq!(MaybeScalar::reduce_from(&n), MaybeScalar::Zero);
+    }
+}
+
+#[cfg(bench)]
+mod benches {
+    use test::{black_box, Bencher};
+
+    use super::*;
+
+    /// Benchmarks the tagged hash of 64 bytes with the engine `E`.
+    ///
+    /// Implementations of [`Sha256Engine`] can be compared to the default one with this.
+    pub fn bench_tagged_hash<E: Sha256Engine>(bh: &mut Bencher) {
+        let data = [0x42; 64];
+        bh.iter(|| {
+            black_box(tagged_hash_with::<E>("BIP0340/challenge", &[&data]));
+        });
+    }
+
+    #[bench]
+    pub fn tagged_hash_64(bh: &mut Bencher) {
+        bench_tagged_hash::<sha256::HashEngine>(bh)
+    }
+}
@@ -0,0 +1,150 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Key derivation primitives.
+//!
+//! HMAC-SHA512 and PBKDF2-HMAC-SHA512, the building blocks of BIP32 key derivation and of the
+//! BIP39 mnemonic to seed conversion, and PBKDF2-HMAC-SHA256 as used by scrypt. Code which
+//! needs any of them should use this module rather than its own implementation.
+//!
+
+use hashes::{sha256, sha512, Hash, HashEngine, Hmac, HmacEngine};
+
+/// The length of an HMAC-SHA512 output.
+pub const HMAC_SHA512_SIZE: usize = 64;
+
+/// The number of PBKDF2 rounds used by BIP39 to derive a seed from a mnemonic.
+pub const BIP39_PBKDF2_ROUNDS: u32 = 2048;
+
+/// A streaming HMAC-SHA512, for messages which are not contiguous in memory.
+///
+/// Create it with [`HmacEngine::new`], feed it with [`HashEngine::input`] and finish it with
+/// [`Hmac::from_engine`].
+pub type HmacSha512Engine = HmacEngine<sha512::Hash>;
+
+/// Returns the HMAC-SHA512 of `data` under `key`.
+pub fn hmac_sha512(key: &[u8], data: &[u8]) -> [u8; HMAC_SHA512_SIZE] {
+    let mut engine = HmacSha512Engine::new(key);
+    engine.input(data);
+    Hmac::from_engine(engine).to_byte_array()
+}
+
+/// Fills `output` with the PBKDF2-HMAC-SHA512 (RFC 8018) derivation of `password` and `salt`.
+///
+/// # Panics
+///
+/// If `rounds` is zero.
+pub fn pbkdf2_hmac_sha512(password: &[u8], salt: &[u8], rounds: u32, output: &mut [u8]) {
+    pbkdf2::<sha512::Hash>(password, salt, rounds, output)
+}
+
+/// Fills `output` with the PBKDF2-HMAC-SHA256 (RFC 8018) derivation of `password` and `salt`.
+///
+/// # Panics
+///
+/// If `rounds` is zero.
+pub fn pbkdf2_hmac_sha256(password: &[u8], salt: &[u8], rounds: u32, output: &mut [u8]) {
+    pbkdf2::<sha256::Hash>(password, salt, rounds, output)
+}
+
+/// PBKDF2 with HMAC over `T`, whose output must be at most 64 bytes.
+fn pbkdf2<T: Hash>(password: &[u8], salt: &[u8], rounds: u32, output: &mut [u8]) {
+    assert!(rounds > 0, "PBKDF2 needs at least one round");
+
+    // The key is the same for every HMAC, only hash it into the inner and outer engines once.
+    let keyed = HmacEngine::<T>::new(password);
+
+    for (i, chunk) in output.chunks_mut(T::LEN).enumerate() {
+        let mut engine = keyed.clone();
+        engine.input(salt);
+        engine.input(&(i as u32 + 1).to_be_bytes());
+        let mut u = [0u8; HMAC_SHA512_SIZE];
+        u[..T::LEN].copy_from_slice(&Hmac::from_engine(engine)[..]);
+        let mut block = u;
+
+        for _ in 1..rounds {
+            let mut engine = keyed.clone();
+            engine.input(&u[..T::LEN]);
+            u[..T::LEN].copy_from_slice(&Hmac::from_engine(engine)[..]);
+            block.iter_mut().zip(u.iter()).for_each(|(b, u)| *b ^= u);
+        }
+        chunk.copy_from_slice(&block[..chunk.len()]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hex::test_hex_unwrap as hex;
+
+    use super::*;
+
+    #[test]
+    fn hmac() {
+        // RFC 4231, test case 2.
+        let expected = hex!(
+            "164b7a7bfcf819e2e395fbe73b56e0a387bd64222e831fd610270cd7ea2505549758bf75c05a994a6d034f65f8f0e6fdcaeab1a34d4a6b4b636e070a38bce737"
+        );
+        let data = b"what do ya want for nothing?";
+        assert_eq!(hmac_sha512(b"Jefe", data)[..], expected[..]);
+
+        let mut engine = HmacSha512Engine::new(b"Jefe");
+        engine.input(&data[..7]);
+        engine.input(&data[7..]);
+        assert_eq!(Hmac::from_engine(engine)[..], expected[..]);
+    }
+
+    #[test]
+    fn pbkdf2() {
+        let mut output = [0u8; 64];
+        pbkdf2_hmac_sha512(b"password", b"salt", 1, &mut output);
+        assert_eq!(
+            output[..],
+            hex!("867f70cf1ade02cff3752599a3a53dc4af34c7a669815ae5d513554e1c8cf252c02d470a285a0501bad999bfe943c08f050235d7d68b1da55e63f73b60a57fce")[..]
+        );
+        pbkdf2_hmac_sha512(b"password", b"salt", 2, &mut output);
+        assert_eq!(
+            output[..],
+            hex!("e1d9c16aa681708a45f5c7c4e215ceb66e011a2e9f0040713f18aefdb866d53cf76cab2868a39b9f7840edce4fef5a82be67335c77a6068e04112754f27ccf4e")[..]
+        );
+
+        // Outputs spanning several blocks.
+        let mut output = [0u8; 100];
+        pbkdf2_hmac_sha512(b"password", b"salt", 4096, &mut output);
+        assert_eq!(
+            output[..],
+            hex!("d197b1b33db0143e018b12f3d1d1479e6cdebdcc97c5c0f87f6902e072f457b5143f30602641b3d55cd335988cb36b84376060ecd532e039b742a239434af2d5d6883f0be4c24d363b638f4c2f8d917533cd4158937d0b490697a64adadb07f180c32308")[..]
+        );
+    }
+
+    #[test]
+    fn pbkdf2_sha256() {
+        // RFC 7914, section 11.
+        let mut output = [0u8; 64];
+        pbkdf2_hmac_sha256(b"passwd", b"salt", 1, &mut output);
+        assert_eq!(
+            output[..],
+            hex!("55ac046e56e3089fec1691c22544b605f94185216dde0465e68b9d57c20dacbc49ca9cccf179b645991664b39d77ef317c71b845b1e30bd509112041d3a19783")[..]
+        );
+        pbkdf2_hmac_sha256(b"Password", b"NaCl", 80000, &mut output);
+        assert_eq!(
+            output[..],
+            hex!("4ddcd8f60b98be21830cee5ef22701f9641a4418d04c0414aeff08876b34ab56a1d425a1225833549adb841b51c9b3176a272bdebba1d078478f62b397f33c8d")[..]
+        );
+    }
+
+    #[test]
+    fn bip39_seed() {
+        let mnemonic = "abandon abandon abandon abandon abandon abandon abandon abandon abandon \
+                        abandon abandon about";
+        let mut seed = [0u8; 64];
+        pbkdf2_hmac_sha512(
+            mnemonic.as_bytes(),
+            b"mnemonicTREZOR",
+            BIP39_PBKDF2_ROUNDS,
+            &mut seed,
+        );
+        assert_eq!(
+            seed[..],
+            hex!("c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04")[..]
+        );
+    }
+}
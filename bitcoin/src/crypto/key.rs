@@ -11,7 +11,7 @@ use core::{
     str::FromStr,
 };
 
-use hashes::{hash160, Hash};
+use hashes::{hash160, sha256, Hash, HashEngine, Hmac, HmacEngine};
 use hex::{FromHex, HexToArrayError};
 use internals::array_vec::ArrayVec;
 use internals::write_err;
@@ -20,6 +20,7 @@ use io::{Read, Write};
 //     signature::{Signer as EcdsaSigner, Verifier as EcdsaVerifier},
 //     Signature as EcdsaSignature, SigningKey as EcdsaSigningKey, VerifyingKey as EcdsaVerifyingKey,
 // };
+use k256::elliptic_curve::ops::LinearCombinationExt;
 use k256::elliptic_curve::point::AffineCoordinates as _;
 use k256::elliptic_curve::sec1::ToEncodedPoint;
 use k256::elliptic_curve::subtle::Choice;
@@ -28,8 +29,8 @@ use k256::schnorr::{
     Signature as SchnorrSignature, SigningKey as SchnorrSigningKey,
     VerifyingKey as SchnorrVerifyingKey,
 };
-use k256::{NonZeroScalar, SecretKey};
-use once_cell::sync::Lazy;
+use k256::{ProjectivePoint, SecretKey};
+use once_cell::race::OnceBox;
 use subtle::ConditionallySelectable;
 
 use crate::blockdata::script::ScriptBuf;
@@ -43,8 +44,6 @@ use crate::Parity;
 use crate::{crypto, CryptoError};
 use crate::{ecdsa, prelude::*};
 
-use rand::thread_rng;
-
 const GENERATOR_POINT_BYTES: [u8; 65] = [
     0x04, // The DER encoding tag
     //
@@ -57,8 +56,12 @@ const GENERATOR_POINT_BYTES: [u8; 65] = [
     0xfd, 0x17, 0xb4, 0x48, 0xa6, 0x85, 0x54, 0x19, 0x9c, 0x47, 0xd0, 0x8f, 0xfb, 0x10, 0xd4, 0xb8,
 ];
 
-static GENERATOR_POINT: Lazy<PublicKey> =
-    Lazy::new(|| PublicKey::try_from(&GENERATOR_POINT_BYTES).unwrap());
+static GENERATOR_POINT: OnceBox<PublicKey> = OnceBox::new();
+
+/// Returns the generator point, parsing it on first use.
+fn generator_point() -> &'static PublicKey {
+    GENERATOR_POINT.get_or_init(|| Box::new(PublicKey::try_from(&GENERATOR_POINT_BYTES).unwrap()))
+}
 
 /// This struct type represents the secp256k1 generator point, and can be
 /// used for scalar-point multiplication.
@@ -79,13 +82,13 @@ static GENERATOR_POINT: Lazy<PublicKey> =
 ///     ]
 /// );
 /// ```
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, Copy)]
 pub struct G;
 
-impl std::ops::Deref for G {
+impl core::ops::Deref for G {
     type Target = PublicKey;
     fn deref(&self) -> &Self::Target {
-        &GENERATOR_POINT
+        generator_point()
     }
 }
 
@@ -96,15 +99,40 @@ impl std::ops::Deref for G {
 pub use secp256k1::rand;
 
 use super::error::InvalidPointBytes;
-use super::scalar::Scalar;
+use super::scalar::{MaybeScalar, Scalar};
 use super::utils::from_hex;
 
+/// A BIP340 x-only public key, the X-coordinate of a point whose Y-coordinate is implicitly even.
+///
+/// This is the key format used by taproot outputs and Schnorr signatures.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct XOnlyPublicKey {
     inner: [u8; 32],
 }
 
 impl XOnlyPublicKey {
+    /// Parses a 32-byte x-only public key, failing if `bytes` is not the X-coordinate of a
+    /// point on the curve.
+    pub fn from_byte_array(bytes: &[u8; 32]) -> Result<XOnlyPublicKey, FromSliceError> {
+        XOnlyPublicKey::from_slice(bytes)
+    }
+
+    /// Returns the point with this X-coordinate and an even Y-coordinate, as specified by the
+    /// `lift_x` function of BIP340.
+    pub fn lift_x(&self) -> PublicKey {
+        let mut bytes = [0u8; common_constants::PUBLIC_KEY_SIZE];
+        bytes[0] = 2;
+        bytes[1..].copy_from_slice(&self.inner);
+        PublicKey::try_from(bytes).expect("x-only keys are valid X-coordinates")
+    }
+
+    /// Returns the point with this X-coordinate and a Y-coordinate of the given `parity`.
+    ///
+    /// This is the inverse of [`PublicKey::x_only_public_key`].
+    pub fn public_key(&self, parity: Parity) -> PublicKey {
+        self.lift_x().negate_if(parity)
+    }
+
     pub fn from_keypair(keypair: &Keypair) -> (Self, Parity) {
         let public_key = PublicKey::from(keypair);
         let x_only_public_key = Self::from(public_key);
@@ -181,7 +209,7 @@ impl fmt::LowerHex for XOnlyPublicKey {
 
 impl fmt::Display for XOnlyPublicKey {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        fmt::Display::fmt(&format!("{:?}", &self.inner), f)
+        fmt::LowerHex::fmt(self, f)
     }
 }
 
@@ -304,7 +332,7 @@ impl Default for MaybePublicKey {
 impl PublicKey {
     /// Returns the secp256k1 generator base point `G`.
     pub fn generator() -> PublicKey {
-        *GENERATOR_POINT
+        *generator_point()
     }
 
     /// Constructs a compressed ECDSA public key from the provided generic Secp256k1 public key
@@ -376,6 +404,20 @@ impl PublicKey {
         self.with_parity(subtle::Choice::from(1))
     }
 
+    /// Negates the point if `parity` is odd, in constant time.
+    ///
+    /// The counterpart of [`Scalar::negate_if`], for matching a public key to a
+    /// secret key negated the same way.
+    #[must_use]
+    pub fn negate_if(self, parity: Parity) -> Self {
+        let mut affine = *self.inner.as_affine();
+        affine.conditional_assign(&(-affine), Choice::from(parity.to_u8()));
+        PublicKey {
+            compressed: self.compressed,
+            inner: k256::PublicKey::from_affine(affine).unwrap(),
+        }
+    }
+
     /// Returns bitcoin 160-bit hash of the public key
     pub fn pubkey_hash(&self) -> PubkeyHash {
         self.with_serialized(PubkeyHash::hash)
@@ -538,6 +580,19 @@ impl PublicKey {
     //     secp.verify_ecdsa(msg, &sig.signature, &self.inner)
     // }
 
+    /// Returns the x-only public key of this point and the parity of its Y-coordinate.
+    ///
+    /// The point can be recovered with [`XOnlyPublicKey::public_key`].
+    pub fn x_only_public_key(&self) -> (XOnlyPublicKey, Parity) {
+        let parity = Parity::from_u8(self.parity().unwrap_u8()).expect("parity is 0 or 1");
+        (
+            XOnlyPublicKey {
+                inner: self.serialize_xonly(),
+            },
+            parity,
+        )
+    }
+
     /// Returns `subtle::Choice::from(0)` if the point's Y-coordinate is even, or
     /// `subtle::Choice::from(1)` if the Y-coordinate is odd.
     fn parity(&self) -> Choice {
@@ -602,6 +657,99 @@ impl PublicKey {
         // check that T_original == T_recomputed
         Ok(original_big_t == recomputed_big_t)
     }
+
+    /// Computes the multi-scalar multiplication `scalars[0] * points[0] + scalars[1] * points[1] + ...`
+    /// using Pippenger's bucket method for large inputs.
+    ///
+    /// This is much faster than summing `N` separate scalar multiplications, and is intended
+    /// for use cases such as batch signature verification and key aggregation. Zero scalars and
+    /// points at infinity contribute nothing to the sum.
+    ///
+    /// This function is not constant time and must not be used with secret scalars.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `scalars` and `points` have different lengths.
+    pub fn multi_mul(scalars: &[MaybeScalar], points: &[MaybePublicKey]) -> MaybePublicKey {
+        assert_eq!(
+            scalars.len(),
+            points.len(),
+            "multi_mul requires as many scalars as points"
+        );
+
+        let terms: Vec<(ProjectivePoint, k256::Scalar)> = scalars
+            .iter()
+            .zip(points)
+            .filter_map(|(scalar, point)| match (scalar, point) {
+                (MaybeScalar::Valid(scalar), Valid(point)) => {
+                    Some((point.inner.to_projective(), scalar.inner))
+                }
+                _ => None,
+            })
+            .collect();
+
+        k256::PublicKey::from_affine(multi_scalar_mul(&terms).to_affine())
+            .map(MaybePublicKey::from)
+            .unwrap_or(Infinity)
+    }
+}
+
+/// The number of terms from which Pippenger's method is faster than the multiplication of k256.
+pub(crate) const PIPPENGER_THRESHOLD: usize = 64;
+
+/// Computes the sum of `scalar * point` over `terms`, in variable time.
+///
+/// Large sums use Pippenger's bucket method, from [`PIPPENGER_THRESHOLD`] terms.
+pub(crate) fn multi_scalar_mul(terms: &[(ProjectivePoint, k256::Scalar)]) -> ProjectivePoint {
+    if terms.len() < PIPPENGER_THRESHOLD {
+        return ProjectivePoint::lincomb_ext(terms);
+    }
+
+    // The scalars are split into windows of `width` bits. For each window, from the most
+    // significant, the points are added into the bucket of their digit, and the buckets are summed
+    // with their digit as weight, using two running sums.
+    let width = window_width(terms.len());
+    let scalars = terms
+        .iter()
+        .map(|(_, scalar)| scalar.to_bytes().into())
+        .collect::<Vec<[u8; 32]>>();
+    let mut buckets = vec![ProjectivePoint::IDENTITY; (1 << width) - 1];
+    let mut result = ProjectivePoint::IDENTITY;
+    for window in (0..256usize.div_ceil(width)).rev() {
+        for _ in 0..width {
+            result = result.double();
+        }
+        buckets.fill(ProjectivePoint::IDENTITY);
+        for ((point, _), scalar) in terms.iter().zip(&scalars) {
+            let digit = window_digit(scalar, window * width, width);
+            if digit != 0 {
+                buckets[digit - 1] += point;
+            }
+        }
+        let mut running = ProjectivePoint::IDENTITY;
+        for bucket in buckets.iter().rev() {
+            running += bucket;
+            result += running;
+        }
+    }
+    result
+}
+
+/// Returns the window width minimizing the number of point additions for `len` points.
+fn window_width(len: usize) -> usize {
+    let additions = |width: usize| 256usize.div_ceil(width) * (len + (2 << width));
+    (1..=16)
+        .min_by_key(|&width| additions(width))
+        .expect("non-empty range")
+}
+
+/// Returns the `width` bits of the big-endian 256-bit integer `bytes` starting at bit `offset`,
+/// counting from the least significant bit.
+fn window_digit(bytes: &[u8; 32], offset: usize, width: usize) -> usize {
+    (offset..(offset + width).min(256))
+        .map(|i| ((bytes[31 - i / 8] >> (i % 8)) & 1) as usize)
+        .enumerate()
+        .fold(0, |digit, (j, bit)| digit | (bit << j))
 }
 
 /// Converts k256 PublicKey to PublicKey
@@ -931,6 +1079,10 @@ impl PrivateKey {
     }
 }
 
+/// The inner [`k256::SecretKey`] wipes itself on drop.
+#[cfg(feature = "zeroize")]
+impl zeroize::ZeroizeOnDrop for PrivateKey {}
+
 impl fmt::Display for PrivateKey {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         self.fmt_wif(f)
@@ -1060,6 +1212,119 @@ impl<'de> serde::Deserialize<'de> for PublicKey {
     }
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for XOnlyPublicKey {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        if s.is_human_readable() {
+            s.collect_str(self)
+        } else {
+            s.serialize_bytes(&self.inner)
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for XOnlyPublicKey {
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<XOnlyPublicKey, D::Error> {
+        if d.is_human_readable() {
+            struct HexVisitor;
+
+            impl<'de> serde::de::Visitor<'de> for HexVisitor {
+                type Value = XOnlyPublicKey;
+
+                fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+                    formatter.write_str("an ASCII hex string")
+                }
+
+                fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+                where
+                    E: serde::de::Error,
+                {
+                    XOnlyPublicKey::from_str(v).map_err(E::custom)
+                }
+            }
+            d.deserialize_str(HexVisitor)
+        } else {
+            struct BytesVisitor;
+
+            impl<'de> serde::de::Visitor<'de> for BytesVisitor {
+                type Value = XOnlyPublicKey;
+
+                fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+                    formatter.write_str("a 32-byte string")
+                }
+
+                fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+                where
+                    E: serde::de::Error,
+                {
+                    match <&[u8; 32]>::try_from(v) {
+                        Ok(bytes) => XOnlyPublicKey::from_byte_array(bytes).map_err(E::custom),
+                        Err(_) => Err(E::invalid_length(v.len(), &self)),
+                    }
+                }
+            }
+
+            d.deserialize_bytes(BytesVisitor)
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for MaybePublicKey {
+    /// Serializes as 33 bytes of compressed SEC1, hex encoded in human-readable formats.
+    /// The point at infinity is serialized as 33 zero bytes.
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        if s.is_human_readable() {
+            s.collect_str(&self.serialize().as_hex())
+        } else {
+            s.serialize_bytes(&self.serialize())
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for MaybePublicKey {
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<MaybePublicKey, D::Error> {
+        struct PointVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for PointVisitor {
+            type Value = MaybePublicKey;
+
+            fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+                formatter.write_str("a 33-byte compressed point or its hex encoding")
+            }
+
+            fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                let bytes =
+                    <[u8; 33]>::try_from(v).map_err(|_| E::invalid_length(v.len(), &self))?;
+                if bytes == [0; 33] {
+                    Ok(Infinity)
+                } else {
+                    PublicKey::try_from(&bytes).map(Valid).map_err(E::custom)
+                }
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                let bytes = <[u8; 33]>::from_hex(v).map_err(E::custom)?;
+                self.visit_bytes(&bytes)
+            }
+        }
+
+        if d.is_human_readable() {
+            d.deserialize_str(PointVisitor)
+        } else {
+            d.deserialize_bytes(PointVisitor)
+        }
+    }
+}
+
 #[cfg(feature = "serde")]
 impl serde::Serialize for CompressedPublicKey {
     fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
@@ -1152,6 +1417,10 @@ pub struct Keypair {
     signing_key: SchnorrSigningKey,
 }
 
+/// The inner [`SchnorrSigningKey`] wipes itself on drop.
+#[cfg(feature = "zeroize")]
+impl zeroize::ZeroizeOnDrop for Keypair {}
+
 impl k256::schnorr::signature::Keypair for Keypair {
     type VerifyingKey = SchnorrVerifyingKey;
 
@@ -1195,11 +1464,22 @@ impl Keypair {
         self.signing_key
     }
 
-    pub fn add_xonly_tweak(self, mut tweak: Scalar) -> Result<Self, CryptoError> {
+    /// Tweaks the key pair by adding `tweak` to its secret key, as done for BIP341 key path
+    /// spends.
+    ///
+    /// The tweak is a [`Tweak`], built by the derivation it comes from, e.g.
+    /// [`Tweak::from_tap_tweak_hash`]. Code which used to pass a raw [`Scalar`] must build the
+    /// [`Tweak`] from the hash it was computed from instead.
+    ///
+    /// Runs in constant time with respect to the secret key.
+    ///
+    /// # Errors
+    ///
+    /// [`CryptoError::InvalidTweak`] if the tweaked secret key is zero.
+    pub fn add_xonly_tweak(self, tweak: Tweak) -> Result<Self, CryptoError> {
         let sec_key = Scalar::from(self.signing_key.as_nonzero_scalar());
 
-        let mut tweaked_scalar_bytes = add_tweak_to_scalar(sec_key, tweak)?.serialize();
-        tweaked_scalar_bytes = Scalar::reduce_from(&tweaked_scalar_bytes).serialize();
+        let tweaked_scalar_bytes = add_tweak_to_scalar(sec_key, tweak)?.serialize();
 
         let signing_key = match SchnorrSigningKey::from_bytes(&tweaked_scalar_bytes) {
             Ok(s) => s,
@@ -1234,7 +1514,7 @@ mod std_traits {
     use super::*;
 
     impl Ord for PublicKey {
-        fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        fn cmp(&self, other: &Self) -> core::cmp::Ordering {
             // The `k256` crate implements `Ord` based on uncompressed encoding.
             // To match BIP327, we must sort keys based on their compressed encoding.
             self.inner
@@ -1244,20 +1524,20 @@ mod std_traits {
     }
 
     impl PartialOrd for PublicKey {
-        fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
             Some(self.cmp(other))
         }
     }
 
     /// Need to implement this manually because [`k256::PublicKey`] does not implement `Hash`.
-    impl std::hash::Hash for PublicKey {
-        fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+    impl core::hash::Hash for PublicKey {
+        fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
             self.serialize().hash(state);
         }
     }
 
-    impl std::hash::Hash for CompressedPublicKey {
-        fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+    impl core::hash::Hash for CompressedPublicKey {
+        fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
             let encoded_point = self.0.as_affine().to_encoded_point(true);
             let serialized = <[u8; 33]>::try_from(encoded_point.as_bytes())
                 .expect("compressed key should be hashable");
@@ -1266,8 +1546,8 @@ mod std_traits {
     }
 
     /// Need to implement this manually because [`k256::schnorr::SigningKey`] does not implement `Hash`.
-    impl std::hash::Hash for Keypair {
-        fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+    impl core::hash::Hash for Keypair {
+        fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
             self.signing_key.to_bytes().hash(state);
         }
     }
@@ -1281,7 +1561,7 @@ mod std_traits {
     impl Eq for Keypair {}
 
     impl Ord for Keypair {
-        fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        fn cmp(&self, other: &Self) -> core::cmp::Ordering {
             self.signing_key
                 .as_nonzero_scalar()
                 .cmp(&other.signing_key.as_nonzero_scalar())
@@ -1303,6 +1583,75 @@ mod std_traits {
     }
 }
 
+mod subtle_traits {
+    use k256::AffinePoint;
+    use subtle::ConstantTimeEq;
+
+    use super::*;
+
+    impl ConstantTimeEq for PublicKey {
+        /// Compares two public keys in constant time. Like `==`, this also compares
+        /// whether the keys serialize as compressed.
+        fn ct_eq(&self, other: &Self) -> Choice {
+            self.inner.as_affine().ct_eq(other.inner.as_affine())
+                & u8::from(self.compressed).ct_eq(&u8::from(other.compressed))
+        }
+    }
+
+    impl ConditionallySelectable for PublicKey {
+        /// Conditionally selects one of two public keys in constant time.
+        fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+            let affine =
+                AffinePoint::conditional_select(a.inner.as_affine(), b.inner.as_affine(), choice);
+            let compressed =
+                u8::conditional_select(&a.compressed.into(), &b.compressed.into(), choice);
+            PublicKey {
+                compressed: compressed == 1,
+                inner: k256::PublicKey::from_affine(affine)
+                    .expect("neither point is the point at infinity"),
+            }
+        }
+    }
+
+    impl ConstantTimeEq for MaybePublicKey {
+        /// Compares two points in constant time. The exception is if either `self` or `other`
+        /// is [`MaybePublicKey::Infinity`], in which case timing information about this fact
+        /// may be leaked.
+        fn ct_eq(&self, other: &Self) -> Choice {
+            match (self, other) {
+                (Valid(a), Valid(b)) => a.ct_eq(b),
+                (Infinity, Infinity) => Choice::from(1),
+                _ => Choice::from(0),
+            }
+        }
+    }
+
+    impl ConditionallySelectable for MaybePublicKey {
+        /// Conditionally selects one of two points in constant time. The exception is if
+        /// either `a` or `b` are [`MaybePublicKey::Infinity`], in which case timing information
+        /// about this fact may be leaked. No timing information about the value of a valid
+        /// point will be leaked.
+        fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+            let to_inner = |p: &Self| match p {
+                Valid(p) => (*p.inner.as_affine(), u8::from(p.compressed)),
+                Infinity => (AffinePoint::IDENTITY, 1),
+            };
+            let (a_affine, a_compressed) = to_inner(a);
+            let (b_affine, b_compressed) = to_inner(b);
+
+            let affine = AffinePoint::conditional_select(&a_affine, &b_affine, choice);
+            let compressed = u8::conditional_select(&a_compressed, &b_compressed, choice);
+            match k256::PublicKey::from_affine(affine) {
+                Ok(inner) => Valid(PublicKey {
+                    compressed: compressed == 1,
+                    inner,
+                }),
+                Err(_) => Infinity,
+            }
+        }
+    }
+}
+
 /// Untweaked BIP-340 key pair
 pub type UntweakedKeypair = Keypair;
 
@@ -1327,6 +1676,60 @@ pub type UntweakedKeypair = Keypair;
 #[cfg_attr(feature = "serde", serde(transparent))]
 pub struct TweakedKeypair(Keypair);
 
+/// A value to add to a key, derived from a hash.
+///
+/// Tweaks are only produced by the hash-based derivations that use them, the BIP341 taproot
+/// tweak, the left half of a BIP32 child key derivation and pay-to-contract commitments, so that
+/// an arbitrary [`Scalar`] can't be used as a tweak by mistake and the APIs taking one show where
+/// it comes from.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Tweak(Scalar);
+
+impl Tweak {
+    /// Returns the BIP341 taproot tweak `hash`.
+    ///
+    /// # Errors
+    ///
+    /// [`CryptoError::InvalidTweak`] if the hash is zero or not below the curve order.
+    pub fn from_tap_tweak_hash(hash: TapTweakHash) -> Result<Tweak, CryptoError> {
+        Self::from_hash(hash.as_byte_array())
+    }
+
+    /// Returns the tweak of a BIP32 child key, the left half `il` of the HMAC-SHA512 output.
+    ///
+    /// # Errors
+    ///
+    /// [`CryptoError::InvalidTweak`] if `il` is zero or not below the curve order.
+    pub fn from_bip32_il(il: &[u8; 32]) -> Result<Tweak, CryptoError> {
+        Self::from_hash(il)
+    }
+
+    /// Returns the pay-to-contract tweak committing `key` to `contract`.
+    ///
+    /// The tweak is `HMAC-SHA256(key, contract)` with the compressed serialization of `key` as
+    /// the HMAC key, as in the peg-in addresses of Elements.
+    ///
+    /// # Errors
+    ///
+    /// [`CryptoError::InvalidTweak`] if the HMAC is zero or not below the curve order.
+    pub fn pay_to_contract(key: &PublicKey, contract: &[u8]) -> Result<Tweak, CryptoError> {
+        let mut engine = HmacEngine::<sha256::Hash>::new(&key.serialize());
+        engine.input(contract);
+        Self::from_hash(Hmac::<sha256::Hash>::from_engine(engine).as_byte_array())
+    }
+
+    /// Returns the tweak as a scalar.
+    pub fn to_scalar(self) -> Scalar {
+        self.0
+    }
+
+    fn from_hash(hash: &[u8; 32]) -> Result<Tweak, CryptoError> {
+        Scalar::from_slice(hash)
+            .map(Tweak)
+            .map_err(|_| CryptoError::InvalidTweak)
+    }
+}
+
 /// A trait for tweaking BIP340 key types (x-only public keys and key pairs).
 pub trait TapTweak {
     /// Tweaked key type with optional auxiliary information
@@ -1346,12 +1749,14 @@ pub trait TapTweak {
     ///
     /// # Returns
     /// The tweaked key and its parity.
+    #[must_use]
     fn tap_tweak(self, merkle_root: Option<TapNodeHash>) -> Self::TweakedAux;
 
     /// Directly converts an [`UntweakedPublicKey`] to a [`TweakedPublicKey`]
     ///
     /// This method is dangerous and can lead to loss of funds if used incorrectly.
     /// Specifically, in multi-party protocols a peer can provide a value that allows them to steal.
+    #[must_use]
     fn dangerous_assume_tweaked(self) -> Self::TweakedKey;
 }
 
@@ -1372,15 +1777,7 @@ impl TapTweak for UntweakedPublicKey {
     /// # Returns
     /// The tweaked key and its parity.
     fn tap_tweak(self, merkle_root: Option<TapNodeHash>) -> (TweakedPublicKey, Parity) {
-        let tweak = TapTweakHash::from_key_and_tweak(self, merkle_root).to_scalar();
-        let (output_key, parity) = self.add_tweak(tweak).expect("Tap tweak failed");
-
-        let is_valid = self
-            .tweak_add_check(output_key, parity, tweak)
-            .expect("checking tweaked public key should not fail");
-
-        debug_assert!(is_valid);
-        (TweakedPublicKey(output_key), parity)
+        taproot_tweak_pubkey(self, merkle_root).expect("Tap tweak failed")
     }
 
     fn dangerous_assume_tweaked(self) -> TweakedPublicKey {
@@ -1402,14 +1799,14 @@ impl TapTweak for UntweakedKeypair {
     ///  * c is the commitment data
     /// The public key is generated from a private key by multiplying with generator point, Q = qG.
     ///
+    /// Runs in constant time with respect to the secret key.
+    ///
     /// # Returns
     /// The tweaked key and its parity.
     fn tap_tweak(self, merkle_root: Option<TapNodeHash>) -> TweakedKeypair {
-        let (pubkey, _parity) = XOnlyPublicKey::from_keypair(&self);
-        let tweak = TapTweakHash::from_key_and_tweak(pubkey, merkle_root).to_scalar();
-        let tweak = Scalar::from(tweak);
-        let tweaked = self.add_xonly_tweak(tweak).expect("Tap tweak failed");
-        TweakedKeypair(tweaked)
+        taproot_tweak_seckey(self, merkle_root)
+            .expect("Tap tweak failed")
+            .0
     }
 
     fn dangerous_assume_tweaked(self) -> TweakedKeypair {
@@ -1417,6 +1814,59 @@ impl TapTweak for UntweakedKeypair {
     }
 }
 
+/// Tweaks the internal key `internal_key` with the script tree root `merkle_root`, returning
+/// the output key and its parity.
+///
+/// This is `taproot_tweak_pubkey` of BIP341: the output key is `Q = P + t*G`, where `P` is the
+/// point with X-coordinate `internal_key` and an even Y-coordinate, and
+/// `t = hash_TapTweak(P || merkle_root)`. The parity of `Q` goes in the control blocks of
+/// script path spends.
+///
+/// # Errors
+///
+/// [`CryptoError::InvalidTweak`] if `t` is not a valid scalar or `Q` is the point at infinity,
+/// which is practically impossible.
+pub fn taproot_tweak_pubkey(
+    internal_key: UntweakedPublicKey,
+    merkle_root: Option<TapNodeHash>,
+) -> Result<(TweakedPublicKey, Parity), CryptoError> {
+    let tweak =
+        Tweak::from_tap_tweak_hash(TapTweakHash::from_key_and_tweak(internal_key, merkle_root))?;
+    let output_key = (internal_key.lift_x() + tweak.to_scalar() * G)
+        .into_option()
+        .ok_or(CryptoError::InvalidTweak)?;
+    let (output_key, parity) = output_key.x_only_public_key();
+    Ok((TweakedPublicKey(output_key), parity))
+}
+
+/// Tweaks the secret key of `keypair` with the script tree root `merkle_root`, returning the
+/// tweaked key pair and the parity of its output key.
+///
+/// This is `taproot_tweak_seckey` of BIP341: the secret key `d` is negated if `P = d*G` has an
+/// odd Y-coordinate, and the tweaked key is `d + t` with `t = hash_TapTweak(P || merkle_root)`.
+/// [`Keypair`]s always hold the secret key of the even-Y point, the returned parity tells
+/// whether `d + t` was negated to get it. The negation runs in constant time with respect to `d`.
+///
+/// # Errors
+///
+/// [`CryptoError::InvalidTweak`] if `t` is not a valid scalar or `d + t` is zero, which is
+/// practically impossible.
+pub fn taproot_tweak_seckey(
+    keypair: UntweakedKeypair,
+    merkle_root: Option<TapNodeHash>,
+) -> Result<(TweakedKeypair, Parity), CryptoError> {
+    let secret = Scalar::from(keypair.secret_key());
+    let (internal_key, parity) = secret.base_point_mul().x_only_public_key();
+    let tweak =
+        Tweak::from_tap_tweak_hash(TapTweakHash::from_key_and_tweak(internal_key, merkle_root))?;
+    let tweaked = (secret.negate_if(parity) + tweak.to_scalar())
+        .not_zero()
+        .map_err(|_| CryptoError::InvalidTweak)?;
+    let (_, parity) = tweaked.base_point_mul().x_only_public_key();
+    let keypair = Keypair::from_secret_key(&tweaked.to_secret_key()?);
+    Ok((TweakedKeypair(keypair), parity))
+}
+
 impl TweakedPublicKey {
     /// Returns the [`TweakedPublicKey`] for `keypair`.
     #[inline]
@@ -1825,12 +2275,7 @@ mod conversions {
 
         impl From<&XOnlyPublicKey> for PublicKey {
             fn from(value: &XOnlyPublicKey) -> PublicKey {
-                let mut inner = [0u8; common_constants::PUBLIC_KEY_SIZE];
-                inner[0] = 2;
-                for i in 1..inner.len() {
-                    inner[i] = value.inner[i - 1];
-                }
-                PublicKey::try_from(inner).expect("Improbable that this should fail")
+                value.lift_x()
             }
         }
     }
@@ -2136,6 +2581,26 @@ mod tests {
         assert_tokens(&pk_u.readable(), &[Token::BorrowedStr(PK_STR_U)]);
     }
 
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_maybe_public_key_serde() {
+        use serde_test::{assert_tokens, Configure, Token};
+
+        static PK_STR: &str = "039b6347398505f5ec93826dc61c19f47c66c0283ee9be980e29ce325a0f4679ef";
+
+        let pk = MaybePublicKey::Valid(PublicKey::from_str(PK_STR).unwrap());
+        assert_tokens(&pk.readable(), &[Token::Str(PK_STR)]);
+        assert_tokens(&pk.compact(), &[Token::Bytes(&pk.serialize())]);
+        assert_tokens(
+            &MaybePublicKey::Infinity.compact(),
+            &[Token::Bytes(&[0; 33])],
+        );
+        assert_tokens(
+            &MaybePublicKey::Infinity.readable(),
+            &[Token::Str(&"00".repeat(33))],
+        );
+    }
+
     fn random_key(mut seed: u8) -> PublicKey {
         loop {
             let mut data = [0; 65];
@@ -2410,4 +2875,210 @@ mod tests {
         let got = format!("{:?}", sk);
         assert_eq!(got, want)
     }
+
+    #[test]
+    fn multi_mul() {
+        use hashes::sha256;
+
+        let scalar = |i: u32, j: u8| {
+            Scalar::reduce_from(
+                &sha256::Hash::hash(&[&i.to_le_bytes()[..], &[j]].concat()).to_byte_array(),
+            )
+        };
+
+        for n in [0, 1, 2, 7, 8, 33, 200] {
+            let mut scalars: Vec<MaybeScalar> =
+                (0..n).map(|i| MaybeScalar::Valid(scalar(i, 0))).collect();
+            let mut points: Vec<MaybePublicKey> = (0..n).map(|i| Valid(scalar(i, 1) * G)).collect();
+            if n > 2 {
+                scalars[1] = MaybeScalar::Zero;
+                points[2] = Infinity;
+            }
+
+            let expected: MaybePublicKey = scalars.iter().zip(&points).map(|(&s, &p)| s * p).sum();
+            assert_eq!(PublicKey::multi_mul(&scalars, &points), expected);
+        }
+
+        // Terms cancelling out sum to infinity.
+        let p = scalar(0, 0) * G;
+        let s = MaybeScalar::Valid(scalar(1, 0));
+        assert_eq!(
+            PublicKey::multi_mul(&[s, -s], &[Valid(p), Valid(p)]),
+            Infinity
+        );
+    }
+
+    #[test]
+    fn sum() {
+        let points = [1u32, 2, 3].map(|n| Scalar::from_u32(n).unwrap() * G);
+        assert_eq!(
+            points.iter().copied().sum::<MaybePublicKey>(),
+            Valid(Scalar::from_u32(6).unwrap() * G)
+        );
+        assert_eq!(
+            [Valid(points[0]), Infinity, Valid(-points[0])]
+                .into_iter()
+                .sum::<MaybePublicKey>(),
+            Infinity
+        );
+        assert_eq!(
+            core::iter::empty::<PublicKey>().sum::<MaybePublicKey>(),
+            Infinity
+        );
+    }
+
+    #[test]
+    fn constant_time_traits() {
+        use subtle::ConstantTimeEq;
+
+        let a = Scalar::from_u32(5).unwrap() * G;
+        let b = Scalar::from_u32(7).unwrap() * G;
+        let a_uncompressed = PublicKey::new_uncompressed(a.inner);
+
+        assert!(bool::from(a.ct_eq(&a)));
+        assert!(!bool::from(a.ct_eq(&b)));
+        assert!(!bool::from(a.ct_eq(&a_uncompressed)));
+        assert_eq!(PublicKey::conditional_select(&a, &b, Choice::from(0)), a);
+        assert_eq!(PublicKey::conditional_select(&a, &b, Choice::from(1)), b);
+        assert_eq!(
+            PublicKey::conditional_select(&a, &a_uncompressed, Choice::from(1)),
+            a_uncompressed
+        );
+
+        assert!(bool::from(Valid(a).ct_eq(&Valid(a))));
+        assert!(bool::from(Infinity.ct_eq(&Infinity)));
+        assert!(!bool::from(Valid(a).ct_eq(&Infinity)));
+        assert_eq!(
+            MaybePublicKey::conditional_select(&Valid(a), &Infinity, Choice::from(0)),
+            Valid(a)
+        );
+        assert_eq!(
+            MaybePublicKey::conditional_select(&Valid(a), &Infinity, Choice::from(1)),
+            Infinity
+        );
+        assert_eq!(
+            MaybePublicKey::conditional_select(&Infinity, &Valid(b), Choice::from(1)),
+            Valid(b)
+        );
+    }
+
+    #[test]
+    fn x_only_lift_x() {
+        // From the BIP340 test vectors, the public key of the secret key 3.
+        let x_only = XOnlyPublicKey::from_str(
+            "f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9",
+        )
+        .unwrap();
+        assert_eq!(
+            x_only.to_string(),
+            "f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9"
+        );
+        assert_eq!(
+            XOnlyPublicKey::from_byte_array(&x_only.serialize()),
+            Ok(x_only)
+        );
+
+        let even = x_only.lift_x();
+        assert!(even.has_even_y());
+        assert_eq!(even.serialize_xonly(), x_only.serialize());
+        assert_eq!(PublicKey::from(x_only), even);
+        assert_eq!(even.x_only_public_key(), (x_only, Parity::Even));
+
+        let odd = x_only.public_key(Parity::Odd);
+        assert_eq!(odd, -even);
+        assert_eq!(odd.x_only_public_key(), (x_only, Parity::Odd));
+        assert_eq!(x_only.public_key(Parity::Even), even);
+
+        // Not the X-coordinate of a point on the curve, from the BIP340 test vectors.
+        assert!(XOnlyPublicKey::from_str(
+            "eefdea4cdb677750a420fee807eacf21eb9898ae79b9768766e4faa04a2d4a34"
+        )
+        .is_err());
+    }
+    #[test]
+    fn taproot_tweak_bip341() {
+        let json: serde_json::Value =
+            serde_json::from_str(include_str!("../../tests/data/bip341_tests.json")).unwrap();
+        let merkle_root = |value: &serde_json::Value| {
+            value
+                .as_str()
+                .map(|root| TapNodeHash::from_str(root).unwrap())
+        };
+
+        let mut parities = Vec::new();
+        for vector in json["scriptPubKey"].as_array().unwrap() {
+            let internal_key =
+                XOnlyPublicKey::from_str(vector["given"]["internalPubkey"].as_str().unwrap())
+                    .unwrap();
+            let (output_key, parity) = taproot_tweak_pubkey(
+                internal_key,
+                merkle_root(&vector["intermediary"]["merkleRoot"]),
+            )
+            .unwrap();
+            assert_eq!(
+                output_key.to_string(),
+                vector["intermediary"]["tweakedPubkey"].as_str().unwrap()
+            );
+            assert_eq!(
+                internal_key.tap_tweak(None).0,
+                taproot_tweak_pubkey(internal_key, None).unwrap().0
+            );
+            parities.push(parity);
+        }
+        // The vectors cover both parities.
+        assert!(parities.contains(&Parity::Even) && parities.contains(&Parity::Odd));
+
+        for input in json["keyPathSpending"][0]["inputSpending"]
+            .as_array()
+            .unwrap()
+        {
+            let keypair =
+                Keypair::from_seckey_str(input["given"]["internalPrivkey"].as_str().unwrap())
+                    .unwrap();
+            let merkle_root = merkle_root(&input["given"]["merkleRoot"]);
+            let (tweaked, parity) = taproot_tweak_seckey(keypair.clone(), merkle_root).unwrap();
+
+            // The key pair holds the negation of the BIP341 tweaked key if its point is odd.
+            let expected =
+                Scalar::from_hex(input["intermediary"]["tweakedPrivkey"].as_str().unwrap())
+                    .unwrap();
+            let secret = Scalar::from(tweaked.clone().to_inner().secret_key());
+            assert_eq!(secret, expected.negate_if(parity));
+
+            let internal_key = keypair.x_only_public_key().0;
+            let (output_key, output_parity) =
+                taproot_tweak_pubkey(internal_key, merkle_root).unwrap();
+            assert_eq!(output_parity, parity);
+            assert_eq!(tweaked.public_parts().0, output_key);
+        }
+    }
+
+    #[test]
+    fn tweak_provenance() {
+        use crate::utils::{add_exp_tweak, add_tweak};
+
+        assert_eq!(
+            Tweak::from_bip32_il(&[0xff; 32]),
+            Err(CryptoError::InvalidTweak)
+        );
+        assert_eq!(
+            Tweak::from_bip32_il(&[0; 32]),
+            Err(CryptoError::InvalidTweak)
+        );
+
+        let secret = SecretKey::from_slice(&[0x11; 32]).unwrap();
+        let key = PublicKey::from(secret.public_key());
+        let tweak = Tweak::pay_to_contract(&key, b"contract").unwrap();
+        assert_ne!(
+            tweak,
+            Tweak::pay_to_contract(&key, b"other contract").unwrap()
+        );
+
+        // Tweaking the secret key and the public key agree.
+        let tweaked = add_tweak(secret, tweak).unwrap();
+        assert_eq!(
+            add_exp_tweak(key.inner, tweak).unwrap(),
+            PublicKey::from(tweaked.public_key())
+        );
+    }
 }
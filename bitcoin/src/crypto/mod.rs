@@ -4,14 +4,145 @@
 //!
 //! Cryptography related functionality: keys and signatures.
 //!
+//! The modules follow the same conventions, for auditors as much as for users:
+//!
+//! * Signatures, verification outcomes and tweaked keys are `#[must_use]`, either directly or by
+//!   being returned in a `Result`.
+//! * Operations which skip a check the caller is responsible for, such as combining partial
+//!   signatures without verifying them, carry an `unchecked_` prefix. Operations trusting a value
+//!   to have been derived correctly, such as a tweaked key, carry a `dangerous_` prefix.
+//! * An operation on secret data which runs in constant time says so in its documentation, and
+//!   one which doesn't warns that it is not constant time. The claims are also recorded in
+//!   a table, whose entries the compiler checks against the operations.
+//!
 
+#[cfg(feature = "cisa-research")]
+pub mod cisa;
+pub mod ecdh;
 pub mod ecdsa;
+pub mod ellswift;
 pub mod error;
+pub mod frost;
+pub mod hashes;
+pub mod kdf;
 pub mod key;
+pub mod musig2;
 pub mod scalar;
+pub mod schnorr;
+#[cfg(feature = "scrypt")]
+pub mod scrypt;
 pub mod sighash;
+pub mod signer;
+pub mod sss;
 
 mod arithmetic;
 mod utils;
 // Contents re-exported in `bitcoin::taproot`.
 pub(crate) mod taproot;
+
+/// Builds the table of [`ct_guarantees`] from `path => constant_time` entries.
+///
+/// The generic parameters of an operation, if any, are given after its path, as in
+/// `sss::split<ThreadRng>`; they are only needed to name the operation.
+#[cfg(test)]
+macro_rules! ct_guarantees {
+    ($(
+        $(#[$attr:meta])*
+        $first:ident $(:: $rest:ident)* $(<$($generic:ty),+>)? => $constant_time:expr,
+    )*) => {{
+        // Naming each operation makes the compiler check that it exists.
+        $($(#[$attr])* let _ = $first $(:: $rest)* $(::<$($generic),+>)?;)*
+        const GUARANTEES: &[CtGuarantee] = &[$($(#[$attr])* CtGuarantee {
+            item: concat!(stringify!($first) $(, "::", stringify!($rest))*),
+            constant_time: $constant_time,
+        },)*];
+        GUARANTEES
+    }};
+}
+
+/// The constant-time claim of an operation, see [`ct_guarantees`].
+#[cfg(test)]
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct CtGuarantee {
+    /// The path of the operation, relative to this module.
+    pub item: &'static str,
+    /// Whether the operation claims to run in constant time with respect to its secret inputs.
+    pub constant_time: bool,
+}
+
+/// Returns the constant-time claims of the operations on secret data.
+///
+/// The documentation of each operation states the same claim.
+#[cfg(test)]
+pub(crate) fn ct_guarantees() -> &'static [CtGuarantee] {
+    use rand::rngs::ThreadRng;
+
+    use self::key::TapTweak as _;
+
+    ct_guarantees! {
+        ecdh::shared_point => true,
+        ecdsa::sign_ecdsa => true,
+        ecdsa::sign_ecdsa_low_r => true,
+        ecdsa::sign_ecdsa_with_noncedata => true,
+        ecdsa::adaptor::AdaptorSignature::encrypt => true,
+        ecdsa::adaptor::AdaptorSignature::decrypt => true,
+        ecdsa::anti_exfil::signer_commit => true,
+        ecdsa::anti_exfil::sign => true,
+        ecdsa::anti_exfil::sign_to_contract => true,
+        ecdsa::recovery::sign_ecdsa_recoverable => true,
+        frost::sign => true,
+        key::PublicKey::negate_if => true,
+        key::PublicKey::multi_mul => false,
+        key::Keypair::add_xonly_tweak => true,
+        key::UntweakedKeypair::tap_tweak => true,
+        key::taproot_tweak_seckey => true,
+        musig2::Session::sign => true,
+        scalar::MaybeScalar::from_u32 => true,
+        scalar::MaybeScalar::from_u64 => true,
+        scalar::MaybeScalar::from_u128 => true,
+        scalar::MaybeScalar::to_hex => false,
+        scalar::MaybeScalar::invert => true,
+        scalar::MaybeScalar::negate_if => true,
+        scalar::MaybeScalar::reduce_from => true,
+        scalar::Scalar::to_hex => false,
+        scalar::Scalar::invert => true,
+        scalar::Scalar::negate_if => true,
+        scalar::Scalar::base_point_mul => true,
+        scalar::Scalar::reduce_from => true,
+        schnorr::sign_schnorr => true,
+        sss::split<ThreadRng> => true,
+        utils::ct_slice_lex_cmp<u8> => true,
+        #[cfg(feature = "chacha20poly1305")]
+        crate::ecies::encrypt<ThreadRng> => true,
+        #[cfg(feature = "chacha20poly1305")]
+        crate::ecies::decrypt => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ct_guarantees_are_unique() {
+        let guarantees = ct_guarantees();
+        for (i, guarantee) in guarantees.iter().enumerate() {
+            assert!(
+                guarantees[..i]
+                    .iter()
+                    .all(|other| other.item != guarantee.item),
+                "{:?} listed twice",
+                guarantee
+            );
+        }
+    }
+
+    #[test]
+    fn signing_is_constant_time() {
+        for guarantee in ct_guarantees() {
+            if guarantee.item.contains("sign") {
+                assert!(guarantee.constant_time, "{:?}", guarantee);
+            }
+        }
+    }
+}
@@ -0,0 +1,1009 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! MuSig2 multi-signatures.
+//!
+//! An implementation of BIP327, in which `n` signers jointly produce a BIP340 signature for the
+//! aggregate of their keys, optionally tweaked as for a taproot key path spend. Signing takes two
+//! rounds:
+//!
+//! 1. Every signer draws a fresh [`SecNonce`] and sends its [`PubNonce`] to the others, or to a
+//!    coordinator which sums them into the [`AggNonce`].
+//! 2. With the aggregate nonce, every signer opens a [`Session`] for the message and answers with
+//!    a [`PartialSignature`] from [`Session::sign`].
+//!
+//! Anyone holding the session checks the partial signatures with
+//! [`Session::verify_partial_signature`] and combines them into the final signature with
+//! [`Session::unchecked_aggregate`].
+//!
+//! Nonces must never be reused, which is why [`Session::sign`] consumes them.
+//!
+
+use core::fmt;
+
+use hashes::Hash;
+use rand::{CryptoRng, RngCore};
+
+use super::hashes::{tagged_hash, tagged_hash_to_scalar};
+use super::key::{MaybePublicKey, PublicKey, XOnlyPublicKey, G};
+use super::scalar::{MaybeScalar, Scalar};
+use super::schnorr::Signature64;
+use crate::prelude::*;
+use crate::taproot::{TapNodeHash, TapTweakHash};
+use crate::Parity;
+
+/// The tag of the hash of the list of keys.
+const KEY_AGG_LIST_TAG: &str = "KeyAgg list";
+
+/// The tag of the hash deriving the aggregation coefficient of every key.
+const KEY_AGG_COEFFICIENT_TAG: &str = "KeyAgg coefficient";
+
+/// The tag of the hash masking the secret key with the nonce randomness.
+const AUX_TAG: &str = "MuSig/aux";
+
+/// The tag of the nonce hash.
+const NONCE_TAG: &str = "MuSig/nonce";
+
+/// The tag of the hash deriving the nonce coefficient.
+const NONCE_COEFFICIENT_TAG: &str = "MuSig/noncecoef";
+
+/// The tag of the BIP340 challenge hash.
+const CHALLENGE_TAG: &str = "BIP0340/challenge";
+
+/// Sorts `keys` by their compressed encoding, as the `KeySort` algorithm of BIP327.
+///
+/// The aggregate key depends on the order of the keys, sorting them first makes it independent
+/// of the order in which the signers are listed.
+pub fn sort_keys(keys: &mut [PublicKey]) {
+    keys.sort_by_key(PublicKey::serialize)
+}
+
+/// The aggregate of the keys of the signers, with the tweaks applied to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyAggContext {
+    keys: Vec<PublicKey>,
+    /// The hash of the list of keys.
+    keys_hash: [u8; 32],
+    /// The first key differing from the first key of the list, whose coefficient is one.
+    second_key: Option<[u8; 33]>,
+    /// The aggregate key, tweaked.
+    key: PublicKey,
+    /// Odd if the aggregate key was negated an odd number of times by the tweaks.
+    parity_acc: Parity,
+    /// The sum of the tweaks, adjusted for the negations of the aggregate key.
+    tweak_acc: MaybeScalar,
+}
+
+impl KeyAggContext {
+    /// Aggregates `keys`, in the given order.
+    ///
+    /// # Errors
+    ///
+    /// If there are no keys, or if they cancel out.
+    pub fn new<I: IntoIterator<Item = PublicKey>>(keys: I) -> Result<Self, Musig2Error> {
+        let keys = keys.into_iter().collect::<Vec<_>>();
+        let serialized = keys.iter().map(PublicKey::serialize).collect::<Vec<_>>();
+        let keys_hash = tagged_hash(KEY_AGG_LIST_TAG, &[&serialized.concat()]).to_byte_array();
+        let second_key = serialized
+            .iter()
+            .find(|&key| *key != serialized[0])
+            .copied();
+        let key = keys
+            .iter()
+            .zip(&serialized)
+            .map(|(&key, serialized)| {
+                coefficient(&keys_hash, second_key.as_ref(), serialized) * key
+            })
+            .sum::<MaybePublicKey>()
+            .into_option()
+            .ok_or(Musig2Error::InfiniteKey)?;
+
+        Ok(KeyAggContext {
+            keys,
+            keys_hash,
+            second_key,
+            key,
+            parity_acc: Parity::Even,
+            tweak_acc: MaybeScalar::Zero,
+        })
+    }
+
+    /// Returns the aggregated keys.
+    pub fn keys(&self) -> &[PublicKey] {
+        &self.keys
+    }
+
+    /// Returns the tweaked aggregate key.
+    pub fn pubkey(&self) -> PublicKey {
+        self.key
+    }
+
+    /// Returns the tweaked aggregate key, as the BIP340 key the signatures are valid for.
+    pub fn x_only_public_key(&self) -> XOnlyPublicKey {
+        self.key.x_only_public_key().0
+    }
+
+    /// Returns the aggregation coefficient of `key`, or `None` if it isn't one of the keys.
+    pub fn key_coefficient(&self, key: &PublicKey) -> Option<MaybeScalar> {
+        let key = key.serialize();
+        self.keys
+            .iter()
+            .any(|k| k.serialize() == key)
+            .then(|| coefficient(&self.keys_hash, self.second_key.as_ref(), &key))
+    }
+
+    /// Adds `tweak` times the generator to the aggregate key.
+    ///
+    /// This is a BIP32 style tweak, the aggregate key is used as is.
+    pub fn with_plain_tweak(self, tweak: MaybeScalar) -> Result<Self, Musig2Error> {
+        self.apply_tweak(tweak, false)
+    }
+
+    /// Adds `tweak` times the generator to the aggregate key, made even first.
+    ///
+    /// This is a BIP341 style tweak, for aggregate keys used as X-only keys.
+    pub fn with_xonly_tweak(self, tweak: MaybeScalar) -> Result<Self, Musig2Error> {
+        self.apply_tweak(tweak, true)
+    }
+
+    /// Tweaks the aggregate key into the output key of a taproot output with the script tree
+    /// `merkle_root`, for key path spends.
+    pub fn with_taproot_tweak(self, merkle_root: Option<TapNodeHash>) -> Result<Self, Musig2Error> {
+        let tweak = TapTweakHash::from_key_and_tweak(self.x_only_public_key(), merkle_root);
+        let tweak = MaybeScalar::from_slice(tweak.as_byte_array())
+            .map_err(|_| Musig2Error::InvalidTweak)?;
+        self.with_xonly_tweak(tweak)
+    }
+
+    fn apply_tweak(mut self, tweak: MaybeScalar, is_xonly: bool) -> Result<Self, Musig2Error> {
+        let parity = if is_xonly {
+            parity(&self.key)
+        } else {
+            Parity::Even
+        };
+        self.key = (self.key.negate_if(parity) + tweak * G)
+            .into_option()
+            .ok_or(Musig2Error::InvalidTweak)?;
+        self.parity_acc = self.parity_acc ^ parity;
+        self.tweak_acc = tweak + self.tweak_acc.negate_if(parity);
+        Ok(self)
+    }
+}
+
+/// The secret nonce of a signer for a single signing session.
+pub struct SecNonce {
+    k1: Scalar,
+    k2: Scalar,
+    /// The key of the signer, signing with another key is refused.
+    pubkey: PublicKey,
+}
+
+impl SecNonce {
+    /// Draws a fresh nonce from `rng`, for the signer with the key `pubkey`.
+    pub fn new<R: RngCore + CryptoRng>(rng: &mut R, pubkey: PublicKey) -> Self {
+        let mut rand = [0u8; 32];
+        rng.fill_bytes(&mut rand);
+        SecNonce::builder(rand, pubkey).build()
+    }
+
+    /// Returns a builder for the nonce derived from `rand` for the signer with the key `pubkey`,
+    /// as the `NonceGen` algorithm of BIP327.
+    ///
+    /// `rand` must be fresh random bytes. Adding the secret key, the aggregate key, the message
+    /// and any extra input to the builder makes the nonce safe even if `rand` isn't entirely
+    /// random.
+    pub fn builder<'a>(rand: [u8; 32], pubkey: PublicKey) -> SecNonceBuilder<'a> {
+        SecNonceBuilder {
+            rand,
+            pubkey,
+            secret: None,
+            aggregated_key: None,
+            message: None,
+            extra_input: &[],
+        }
+    }
+
+    /// Returns the public nonce, to be sent to the other signers.
+    pub fn public_nonce(&self) -> PubNonce {
+        PubNonce {
+            r1: self.k1 * G,
+            r2: self.k2 * G,
+        }
+    }
+}
+
+impl fmt::Debug for SecNonce {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SecNonce").finish_non_exhaustive()
+    }
+}
+
+/// A builder of a [`SecNonce`], returned by [`SecNonce::builder`].
+#[derive(Debug, Clone)]
+pub struct SecNonceBuilder<'a> {
+    rand: [u8; 32],
+    pubkey: PublicKey,
+    secret: Option<Scalar>,
+    aggregated_key: Option<XOnlyPublicKey>,
+    message: Option<&'a [u8]>,
+    extra_input: &'a [u8],
+}
+
+impl<'a> SecNonceBuilder<'a> {
+    /// Mixes the secret key of the signer into the nonce.
+    pub fn with_secret_key(mut self, secret: Scalar) -> Self {
+        self.secret = Some(secret);
+        self
+    }
+
+    /// Mixes the aggregate key into the nonce.
+    pub fn with_aggregated_key(mut self, key: XOnlyPublicKey) -> Self {
+        self.aggregated_key = Some(key);
+        self
+    }
+
+    /// Mixes the message to sign into the nonce.
+    pub fn with_message(mut self, message: &'a [u8]) -> Self {
+        self.message = Some(message);
+        self
+    }
+
+    /// Mixes any extra input, like a session identifier or a counter, into the nonce.
+    pub fn with_extra_input(mut self, extra_input: &'a [u8]) -> Self {
+        self.extra_input = extra_input;
+        self
+    }
+
+    /// Derives the nonce.
+    pub fn build(self) -> SecNonce {
+        let mut rand = self.rand;
+        if let Some(secret) = self.secret {
+            let mask = tagged_hash(AUX_TAG, &[&self.rand]).to_byte_array();
+            rand = secret.serialize();
+            rand.iter_mut()
+                .zip(mask)
+                .for_each(|(byte, mask)| *byte ^= mask);
+        }
+        let pubkey = self.pubkey.serialize();
+        let aggregated_key = self.aggregated_key.map(|key| key.serialize());
+        let aggregated_key = aggregated_key.as_ref().map_or(&[][..], |key| &key[..]);
+        let mut message = Vec::new();
+        match self.message {
+            None => message.push(0),
+            Some(msg) => {
+                message.push(1);
+                message.extend_from_slice(&(msg.len() as u64).to_be_bytes());
+                message.extend_from_slice(msg);
+            }
+        }
+
+        let nonce = |index: u8| {
+            tagged_hash_to_scalar(
+                NONCE_TAG,
+                &[
+                    &rand,
+                    &[pubkey.len() as u8],
+                    &pubkey,
+                    &[aggregated_key.len() as u8],
+                    aggregated_key,
+                    &message,
+                    &(self.extra_input.len() as u32).to_be_bytes(),
+                    self.extra_input,
+                    &[index],
+                ],
+            )
+            .not_zero()
+            .expect("a nonce hash of zero or the curve order is practically impossible")
+        };
+        SecNonce {
+            k1: nonce(0),
+            k2: nonce(1),
+            pubkey: self.pubkey,
+        }
+    }
+}
+
+/// The public nonce of a signer, made of two points.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(crate = "actual_serde"))]
+pub struct PubNonce {
+    /// The first nonce point.
+    pub r1: PublicKey,
+    /// The second nonce point.
+    pub r2: PublicKey,
+}
+
+impl PubNonce {
+    /// Serializes the nonce as the compressed encodings of both points.
+    pub fn serialize(&self) -> [u8; 66] {
+        let mut bytes = [0u8; 66];
+        bytes[..33].copy_from_slice(&self.r1.serialize());
+        bytes[33..].copy_from_slice(&self.r2.serialize());
+        bytes
+    }
+
+    /// Parses a nonce serialized by [`PubNonce::serialize`].
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, Musig2Error> {
+        if bytes.len() != 66 {
+            return Err(Musig2Error::InvalidNonce);
+        }
+        let point = |bytes| PublicKey::from_slice(bytes).map_err(|_| Musig2Error::InvalidNonce);
+        Ok(PubNonce {
+            r1: point(&bytes[..33])?,
+            r2: point(&bytes[33..])?,
+        })
+    }
+}
+
+/// The sum of the public nonces of all signers, as computed by the `NonceAgg` algorithm of
+/// BIP327.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AggNonce {
+    /// The sum of the first nonce points.
+    pub r1: MaybePublicKey,
+    /// The sum of the second nonce points.
+    pub r2: MaybePublicKey,
+}
+
+impl AggNonce {
+    /// Sums the public nonces of all signers.
+    pub fn sum<'a, I: IntoIterator<Item = &'a PubNonce>>(nonces: I) -> Self {
+        nonces.into_iter().fold(
+            AggNonce {
+                r1: MaybePublicKey::Infinity,
+                r2: MaybePublicKey::Infinity,
+            },
+            |sum, nonce| AggNonce {
+                r1: sum.r1 + nonce.r1,
+                r2: sum.r2 + nonce.r2,
+            },
+        )
+    }
+
+    /// Serializes the nonce as the compressed encodings of both points, the point at infinity
+    /// being encoded as 33 zero bytes.
+    pub fn serialize(&self) -> [u8; 66] {
+        let mut bytes = [0u8; 66];
+        bytes[..33].copy_from_slice(&self.r1.serialize());
+        bytes[33..].copy_from_slice(&self.r2.serialize());
+        bytes
+    }
+
+    /// Parses a nonce serialized by [`AggNonce::serialize`].
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, Musig2Error> {
+        if bytes.len() != 66 {
+            return Err(Musig2Error::InvalidNonce);
+        }
+        let point = |bytes: &[u8]| {
+            if bytes == [0; 33] {
+                Ok(MaybePublicKey::Infinity)
+            } else {
+                PublicKey::from_slice(bytes)
+                    .map(MaybePublicKey::Valid)
+                    .map_err(|_| Musig2Error::InvalidNonce)
+            }
+        };
+        Ok(AggNonce {
+            r1: point(&bytes[..33])?,
+            r2: point(&bytes[33..])?,
+        })
+    }
+}
+
+/// A signer's share of a signature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartialSignature(pub MaybeScalar);
+
+impl PartialSignature {
+    /// Serializes the partial signature as a 32-byte scalar.
+    pub fn serialize(&self) -> [u8; 32] {
+        self.0.serialize()
+    }
+
+    /// Parses a partial signature, which must be a scalar below the curve order.
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, Musig2Error> {
+        MaybeScalar::from_slice(bytes)
+            .map(PartialSignature)
+            .map_err(|_| Musig2Error::InvalidPartialSignature)
+    }
+}
+
+impl core::hash::Hash for PartialSignature {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        self.serialize().hash(state)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for PartialSignature {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        PartialSignature::serialize(self).serialize(s)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for PartialSignature {
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let bytes = <[u8; 32]>::deserialize(d)?;
+        PartialSignature::from_slice(&bytes).map_err(serde::de::Error::custom)
+    }
+}
+
+/// A signing session: the aggregate key and nonce, and the message to sign.
+#[derive(Debug, Clone)]
+pub struct Session<'a> {
+    key_agg: &'a KeyAggContext,
+    /// The coefficient of the second nonce points.
+    nonce_coefficient: MaybeScalar,
+    /// The nonce point `R` of the signature.
+    final_nonce: PublicKey,
+    /// The BIP340 challenge.
+    challenge: MaybeScalar,
+}
+
+impl<'a> Session<'a> {
+    /// Opens the session signing `msg` with the aggregate key of `key_agg` and the aggregate
+    /// nonce `agg_nonce`.
+    ///
+    /// BIP340 signs messages of any length, taproot signatures sign a 32-byte sighash.
+    pub fn new(key_agg: &'a KeyAggContext, agg_nonce: &AggNonce, msg: &[u8]) -> Self {
+        let key = key_agg.key.serialize_xonly();
+        let nonce_coefficient =
+            tagged_hash_to_scalar(NONCE_COEFFICIENT_TAG, &[&agg_nonce.serialize(), &key, msg]);
+        // A final nonce at infinity can only be caused by dishonest signers, who then learn
+        // nothing from the signature.
+        let final_nonce = (agg_nonce.r1 + nonce_coefficient * agg_nonce.r2)
+            .into_option()
+            .unwrap_or(*G);
+        let challenge =
+            tagged_hash_to_scalar(CHALLENGE_TAG, &[&final_nonce.serialize_xonly(), &key, msg]);
+        Session {
+            key_agg,
+            nonce_coefficient,
+            final_nonce,
+            challenge,
+        }
+    }
+
+    /// Returns the nonce point `R` of the final signature.
+    pub fn final_nonce(&self) -> PublicKey {
+        self.final_nonce
+    }
+
+    /// Produces the partial signature of the signer with the secret key `secret`, consuming their
+    /// `sec_nonce`.
+    ///
+    /// Runs in constant time with respect to `secret` and `sec_nonce`.
+    ///
+    /// # Errors
+    ///
+    /// If `sec_nonce` was drawn for another key, or if the key of the signer isn't one of the
+    /// aggregated keys.
+    pub fn sign(
+        &self,
+        sec_nonce: SecNonce,
+        secret: Scalar,
+    ) -> Result<PartialSignature, Musig2Error> {
+        let pubkey = secret * G;
+        if pubkey.serialize() != sec_nonce.pubkey.serialize() {
+            return Err(Musig2Error::NonceKeyMismatch);
+        }
+        let coefficient = self
+            .key_agg
+            .key_coefficient(&pubkey)
+            .ok_or(Musig2Error::UnknownKey)?;
+
+        let nonce_parity = parity(&self.final_nonce);
+        let k1 = sec_nonce.k1.negate_if(nonce_parity);
+        let k2 = sec_nonce.k2.negate_if(nonce_parity);
+        let secret = secret.negate_if(self.key_parity());
+        Ok(PartialSignature(
+            k1 + self.nonce_coefficient * k2 + self.challenge * coefficient * secret,
+        ))
+    }
+
+    /// Checks the partial signature of the signer with the key `pubkey` and the public nonce
+    /// `pub_nonce`.
+    pub fn verify_partial_signature(
+        &self,
+        partial_signature: &PartialSignature,
+        pub_nonce: &PubNonce,
+        pubkey: &PublicKey,
+    ) -> Result<(), Musig2Error> {
+        let coefficient = self
+            .key_agg
+            .key_coefficient(pubkey)
+            .ok_or(Musig2Error::UnknownKey)?;
+        let mut nonce = pub_nonce.r1 + self.nonce_coefficient * pub_nonce.r2;
+        if self.final_nonce.has_odd_y() {
+            nonce = -nonce;
+        }
+        let key = pubkey.negate_if(self.key_parity());
+        let expected = nonce + self.challenge * coefficient * key;
+
+        if (partial_signature.0 * G).serialize() == expected.serialize() {
+            Ok(())
+        } else {
+            Err(Musig2Error::InvalidPartialSignature)
+        }
+    }
+
+    /// Combines the partial signatures of all signers into a BIP340 signature for the aggregate
+    /// key.
+    ///
+    /// The partial signatures aren't checked, the signature is only valid if they all are. Check
+    /// them first with [`Session::verify_partial_signature`].
+    #[must_use]
+    pub fn unchecked_aggregate<'b, I: IntoIterator<Item = &'b PartialSignature>>(
+        &self,
+        partial_signatures: I,
+    ) -> Signature64 {
+        let sum = partial_signatures
+            .into_iter()
+            .fold(MaybeScalar::Zero, |sum, partial_signature| {
+                sum + partial_signature.0
+            });
+        let tweak = self.key_agg.tweak_acc.negate_if(parity(&self.key_agg.key));
+        let s = sum + self.challenge * tweak;
+
+        let mut bytes = [0u8; 64];
+        bytes[..32].copy_from_slice(&self.final_nonce.serialize_xonly());
+        bytes[32..].copy_from_slice(&s.serialize());
+        Signature64::from_byte_array(bytes)
+    }
+
+    /// Returns odd if the secret keys must be negated to sign for the tweaked aggregate key.
+    fn key_parity(&self) -> Parity {
+        parity(&self.key_agg.key) ^ self.key_agg.parity_acc
+    }
+}
+
+/// Returns the aggregation coefficient of `key`, as the `KeyAggCoeff` algorithm of BIP327.
+///
+/// The coefficient of the second distinct key is one, which saves a multiplication.
+fn coefficient(keys_hash: &[u8; 32], second_key: Option<&[u8; 33]>, key: &[u8; 33]) -> MaybeScalar {
+    if second_key == Some(key) {
+        MaybeScalar::one()
+    } else {
+        tagged_hash_to_scalar(KEY_AGG_COEFFICIENT_TAG, &[keys_hash, key])
+    }
+}
+
+/// Returns the parity of the Y-coordinate of `point`.
+fn parity(point: &PublicKey) -> Parity {
+    if point.has_odd_y() {
+        Parity::Odd
+    } else {
+        Parity::Even
+    }
+}
+
+/// Errors encountered while signing with MuSig2.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Musig2Error {
+    /// There are no keys to aggregate, or they cancel out.
+    InfiniteKey,
+    /// The tweak is not below the curve order, or cancels out the aggregate key.
+    InvalidTweak,
+    /// The key is not one of the aggregated keys.
+    UnknownKey,
+    /// The secret nonce was drawn for another key.
+    NonceKeyMismatch,
+    /// A public or aggregate nonce couldn't be parsed.
+    InvalidNonce,
+    /// A partial signature is invalid.
+    InvalidPartialSignature,
+}
+
+internals::impl_from_infallible!(Musig2Error);
+
+impl fmt::Display for Musig2Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use Musig2Error::*;
+
+        match *self {
+            InfiniteKey => f.write_str("the aggregate key is the point at infinity"),
+            InvalidTweak => f.write_str("invalid tweak of the aggregate key"),
+            UnknownKey => f.write_str("the key is not one of the aggregated keys"),
+            NonceKeyMismatch => f.write_str("the secret nonce was drawn for another key"),
+            InvalidNonce => f.write_str("invalid nonce encoding"),
+            InvalidPartialSignature => f.write_str("invalid partial signature"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Musig2Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use Musig2Error::*;
+
+        match *self {
+            InfiniteKey
+            | InvalidTweak
+            | UnknownKey
+            | NonceKeyMismatch
+            | InvalidNonce
+            | InvalidPartialSignature => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hex::test_hex_unwrap as hex;
+
+    use super::*;
+    use crate::common::types::Message;
+    use crate::crypto::key::{TapTweak, UntweakedPublicKey};
+    use crate::crypto::schnorr::verify_schnorr;
+
+    fn key(s: &str) -> PublicKey {
+        PublicKey::from_slice(&hex!(s)).unwrap()
+    }
+
+    // https://github.com/bitcoin/bips/blob/master/bip-0327/vectors/key_agg_vectors.json
+    #[test]
+    fn key_agg_vectors() {
+        let keys = [
+            key("02F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9"),
+            key("03DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659"),
+            key("023590A94E768F8E1815C2F24B4D80A8E3149316C3518CE7B7AD338368D038CA66"),
+        ];
+        let vectors: [(&[usize], &str); 4] = [
+            (
+                &[0, 1, 2],
+                "90539EEDE565F5D054F32CC0C220126889ED1E5D193BAF15AEF344FE59D4610C",
+            ),
+            (
+                &[2, 1, 0],
+                "6204DE8B083426DC6EAF9502D27024D53FC826BF7D2012148A0575435DF54B2B",
+            ),
+            (
+                &[0, 0, 0],
+                "B436E3BAD62B8CD409969A224731C193D051162D8C5AE8B109306127DA3AA935",
+            ),
+            (
+                &[0, 0, 1, 1],
+                "69BC22BFA5D106306E48A20679DE1D7389386124D07571D0D872686028C26A3E",
+            ),
+        ];
+        for (indices, expected) in vectors {
+            let context = KeyAggContext::new(indices.iter().map(|&i| keys[i])).unwrap();
+            assert_eq!(
+                context.x_only_public_key().serialize()[..],
+                hex!(expected)[..]
+            );
+        }
+
+        assert_eq!(
+            KeyAggContext::new(Vec::new()),
+            Err(Musig2Error::InfiniteKey)
+        );
+    }
+
+    #[test]
+    fn key_sorting() {
+        let mut keys = [
+            key("03DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659"),
+            key("02F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9"),
+            key("023590A94E768F8E1815C2F24B4D80A8E3149316C3518CE7B7AD338368D038CA66"),
+        ];
+        let expected = [keys[2], keys[1], keys[0]];
+        sort_keys(&mut keys);
+        assert_eq!(keys, expected);
+    }
+
+    // https://github.com/bitcoin/bips/blob/master/bip-0327/vectors/nonce_gen_vectors.json
+    #[test]
+    fn nonce_gen_vectors() {
+        let pubkey = key("024D4B6CD1361032CA9BD2AEB9D900AA4D45D9EAD80AC9423374C451A7254D0766");
+        let secret = Scalar::from_slice(&[0x02; 32]).unwrap();
+        let aggregated_key = XOnlyPublicKey::from_slice(&[0x07; 32]).unwrap();
+        let vectors: [(&[u8], &str); 2] = [
+            (
+                &[0x01; 32],
+                "B114E502BEAA4E301DD08A50264172C84E41650E6CB726B410C0694D59EFFB64\
+                 95B5CAF28D045B973D63E3C99A44B807BDE375FD6CB39E46DC4A511708D0E9D2",
+            ),
+            (
+                &[],
+                "E862B068500320088138468D47E0E6F147E01B6024244AE45EAC40ACE5929B9F\
+                 0789E051170B9E705D0B9EB49049A323BBBBB206D8E05C19F46C6228742AA7A9",
+            ),
+        ];
+        for (msg, expected) in vectors {
+            let sec_nonce = SecNonce::builder([0x0f; 32], pubkey)
+                .with_secret_key(secret)
+                .with_aggregated_key(aggregated_key)
+                .with_message(msg)
+                .with_extra_input(&[0x08; 32])
+                .build();
+            let expected = hex!(expected);
+            assert_eq!(sec_nonce.k1.serialize()[..], expected[..32]);
+            assert_eq!(sec_nonce.k2.serialize()[..], expected[32..]);
+        }
+
+        let sec_nonce = SecNonce::builder([0x0f; 32], pubkey)
+            .with_secret_key(secret)
+            .with_aggregated_key(aggregated_key)
+            .with_message(&[0x01; 32])
+            .with_extra_input(&[0x08; 32])
+            .build();
+        assert_eq!(
+            sec_nonce.public_nonce().serialize()[..],
+            hex!(
+                "02F7BE7089E8376EB355272368766B17E88E7DB72047D05E56AA881EA52B3B35DF\
+                 02C29C8046FDD0DED4C7E55869137200FBDBFE2EB654267B6D7013602CAED3115A"
+            )[..]
+        );
+    }
+
+    // https://github.com/bitcoin/bips/blob/master/bip-0327/vectors/sign_verify_vectors.json
+    #[test]
+    fn sign_verify_vectors() {
+        let secret =
+            Scalar::from_hex("7FB9E0E687ADA1EEBF7ECFE2F21E73EBDB51A7D450948DFE8D76D7F2D1007671")
+                .unwrap();
+        let keys = [
+            key("03935F972DA013F80AE011890FA89B67A27B7BE6CCB24D3274D18B2D4067F261A9"),
+            key("02F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9"),
+            key("02DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA661"),
+        ];
+        let sec_nonce = hex!(
+            "508B81A611F100A6B2B6B29656590898AF488BCF2E1F55CF22E5CFB84421FE61\
+             FA27FD49B1D50085B481285E1CA205D55C82CC1B31FF5CD54A489829355901F7"
+        );
+        let pub_nonces = [
+            "0337C87821AFD50A8644D820A8F3E02E499C931865C2360FB43D0A0D20DAFE07EA\
+             0287BF891D2A6DEAEBADC909352AA9405D1428C15F4B75F04DAE642A95C2548480",
+            "0279BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798\
+             0279BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798",
+            "032DE2662628C90B03F5E720284EB52FF7D71F4284F627B68A853D78C78E1FFE93\
+             03E4C5524E83FFE1493B9077CF1CA6BEB2090C93D930321071AD40B2F44E599046",
+        ]
+        .map(|nonce| PubNonce::from_slice(&hex!(nonce)).unwrap());
+        let msg = hex!("F95466D086770E689964664219266FE5ED215C92AE20BAB5C9D79ADDDDF3C0CF");
+        assert_eq!(secret * G, keys[0]);
+        assert_eq!(
+            AggNonce::sum(&pub_nonces).serialize()[..],
+            hex!(
+                "028465FCF0BBDBCF443AABCCE533D42B4B5A10966AC09A49655E8C42DAAB8FCD61\
+                 037496A3CC86926D452CAFCFD55D25972CA1675D549310DE296BFF42F72EEEA8C9"
+            )[..]
+        );
+
+        // The signer is at every position in turn.
+        let vectors = [
+            (
+                [0, 1, 2],
+                "012ABBCB52B3016AC03AD82395A1A415C48B93DEF78718E62A7A90052FE224FB",
+            ),
+            (
+                [1, 0, 2],
+                "9FF2F7AAA856150CC8819254218D3ADEEB0535269051897724F9DB3789513A52",
+            ),
+            (
+                [1, 2, 0],
+                "FA23C359F6FAC4E7796BB93BC9F0532A95468C539BA20FF86D7C76ED92227900",
+            ),
+        ];
+        for (indices, expected) in vectors {
+            let key_agg = KeyAggContext::new(indices.map(|i| keys[i])).unwrap();
+            let agg_nonce = AggNonce::sum(&indices.map(|i| pub_nonces[i]));
+            let session = Session::new(&key_agg, &agg_nonce, &msg);
+            let sec_nonce = SecNonce {
+                k1: Scalar::from_slice(&sec_nonce[..32]).unwrap(),
+                k2: Scalar::from_slice(&sec_nonce[32..]).unwrap(),
+                pubkey: keys[0],
+            };
+            assert_eq!(sec_nonce.public_nonce(), pub_nonces[0]);
+
+            let partial_signature = session.sign(sec_nonce, secret).unwrap();
+            assert_eq!(partial_signature.serialize()[..], hex!(expected)[..]);
+            assert_eq!(
+                session.verify_partial_signature(&partial_signature, &pub_nonces[0], &keys[0]),
+                Ok(())
+            );
+            assert_eq!(
+                session.verify_partial_signature(&partial_signature, &pub_nonces[1], &keys[0]),
+                Err(Musig2Error::InvalidPartialSignature)
+            );
+        }
+    }
+
+    // https://github.com/bitcoin/bips/blob/master/bip-0327/vectors/sig_agg_vectors.json
+    #[test]
+    fn sig_agg_vectors() {
+        let keys = [
+            key("03935F972DA013F80AE011890FA89B67A27B7BE6CCB24D3274D18B2D4067F261A9"),
+            key("02D2DC6F5DF7C56ACF38C7FA0AE7A759AE30E19B37359DFDE015872324C7EF6E05"),
+        ];
+        let pub_nonces = [
+            "036E5EE6E28824029FEA3E8A9DDD2C8483F5AF98F7177C3AF3CB6F47CAF8D94AE9\
+             02DBA67E4A1F3680826172DA15AFB1A8CA85C7C5CC88900905C8DC8C328511B53E",
+            "03E4F798DA48A76EEC1C9CC5AB7A880FFBA201A5F064E627EC9CB0031D1D58FC51\
+             03E06180315C5A522B7EC7C08B69DCD721C313C940819296D0A7AB8E8795AC1F00",
+        ]
+        .map(|nonce| PubNonce::from_slice(&hex!(nonce)).unwrap());
+        let partial_signatures = [
+            "B15D2CD3C3D22B04DAE438CE653F6B4ECF042F42CFDED7C41B64AAF9B4AF53FB",
+            "6193D6AC61B354E9105BBDC8937A3454A6D705B6D57322A5A472A02CE99FCB64",
+        ]
+        .map(|signature| PartialSignature::from_slice(&hex!(signature)).unwrap());
+        let msg = Message::from_digest_slice(&hex!(
+            "599C67EA410D005B9DA90817CF03ED3B1C868E4DA4EDF00A5880B0082C237869"
+        ))
+        .unwrap();
+
+        let key_agg = KeyAggContext::new(keys).unwrap();
+        let agg_nonce = AggNonce::sum(&pub_nonces);
+        assert_eq!(
+            agg_nonce.serialize()[..],
+            hex!(
+                "0341432722C5CD0268D829C702CF0D1CBCE57033EED201FD335191385227C3210C\
+                 03D377F2D258B64AADC0E16F26462323D701D286046A2EA93365656AFD9875982B"
+            )[..]
+        );
+        let session = Session::new(&key_agg, &agg_nonce, msg.as_ref());
+        let signature = session.unchecked_aggregate(&partial_signatures);
+        assert_eq!(
+            signature.to_byte_array()[..],
+            hex!(
+                "041DA22223CE65C92C9A0D6C2CAC828AAF1EEE56304FEC371DDF91EBB2B9EF09\
+                 12F1038025857FEDEB3FF696F8B99FA4BB2C5812F6095A2E0004EC99CE18DE1E"
+            )[..]
+        );
+        assert_eq!(
+            verify_schnorr(&signature, &msg, &key_agg.x_only_public_key()),
+            Ok(())
+        );
+    }
+
+    /// Runs both signing rounds with the keys of `secrets`.
+    fn sign_with(
+        secrets: &[Scalar],
+        key_agg: &KeyAggContext,
+        msg: &[u8],
+    ) -> (Signature64, Vec<PartialSignature>) {
+        let mut rng = rand::thread_rng();
+        let sec_nonces = secrets
+            .iter()
+            .map(|&secret| {
+                let mut rand = [0u8; 32];
+                rng.fill_bytes(&mut rand);
+                SecNonce::builder(rand, secret * G)
+                    .with_secret_key(secret)
+                    .with_aggregated_key(key_agg.x_only_public_key())
+                    .with_message(msg)
+                    .build()
+            })
+            .collect::<Vec<_>>();
+        let pub_nonces = sec_nonces
+            .iter()
+            .map(SecNonce::public_nonce)
+            .collect::<Vec<_>>();
+        let agg_nonce = AggNonce::sum(&pub_nonces);
+        let session = Session::new(key_agg, &agg_nonce, msg);
+
+        let partial_signatures = secrets
+            .iter()
+            .zip(sec_nonces)
+            .map(|(&secret, sec_nonce)| session.sign(sec_nonce, secret).unwrap())
+            .collect::<Vec<_>>();
+        for ((partial_signature, pub_nonce), secret) in
+            partial_signatures.iter().zip(&pub_nonces).zip(secrets)
+        {
+            assert_eq!(
+                session.verify_partial_signature(partial_signature, pub_nonce, &(*secret * G)),
+                Ok(())
+            );
+        }
+        (
+            session.unchecked_aggregate(&partial_signatures),
+            partial_signatures,
+        )
+    }
+
+    #[test]
+    fn sign_and_aggregate() {
+        let secrets = (1..=3)
+            .map(|n| Scalar::reduce_from(&[n * 0x11; 32]))
+            .collect::<Vec<_>>();
+        let mut keys = secrets.iter().map(|&secret| secret * G).collect::<Vec<_>>();
+        sort_keys(&mut keys);
+        let key_agg = KeyAggContext::new(keys).unwrap();
+        let msg = Message::from_digest([0x42; 32]);
+
+        let merkle_root = Some(TapNodeHash::from_byte_array([0x07; 32]));
+        let tweaked = [
+            key_agg.clone(),
+            key_agg.clone().with_taproot_tweak(None).unwrap(),
+            key_agg.clone().with_taproot_tweak(merkle_root).unwrap(),
+            key_agg
+                .clone()
+                .with_plain_tweak(MaybeScalar::one())
+                .unwrap(),
+            key_agg
+                .clone()
+                .with_plain_tweak(Scalar::reduce_from(&[0x55; 32]).into())
+                .unwrap()
+                .with_xonly_tweak(Scalar::reduce_from(&[0x66; 32]).into())
+                .unwrap(),
+        ];
+        for key_agg in &tweaked {
+            let (signature, _) = sign_with(&secrets, key_agg, msg.as_ref());
+            assert_eq!(
+                verify_schnorr(&signature, &msg, &key_agg.x_only_public_key()),
+                Ok(())
+            );
+        }
+
+        // The taproot tweak gives the BIP341 output key of the aggregate key.
+        let internal_key: UntweakedPublicKey = key_agg.x_only_public_key();
+        let (output_key, _) = internal_key.tap_tweak(merkle_root);
+        assert_eq!(tweaked[2].x_only_public_key(), output_key.to_inner());
+    }
+
+    #[test]
+    fn invalid_signers() {
+        let secrets = [
+            Scalar::reduce_from(&[0x11; 32]),
+            Scalar::reduce_from(&[0x22; 32]),
+        ];
+        let key_agg = KeyAggContext::new(secrets.iter().map(|&secret| secret * G)).unwrap();
+        let msg = [0x42; 32];
+        let sec_nonces = secrets.map(|secret| SecNonce::new(&mut rand::thread_rng(), secret * G));
+        let pub_nonces = sec_nonces
+            .iter()
+            .map(SecNonce::public_nonce)
+            .collect::<Vec<_>>();
+        let session = Session::new(&key_agg, &AggNonce::sum(&pub_nonces), &msg);
+
+        let [first, second] = sec_nonces;
+        assert_eq!(
+            session.sign(first, secrets[1]),
+            Err(Musig2Error::NonceKeyMismatch)
+        );
+        let outsider = Scalar::reduce_from(&[0x33; 32]);
+        let nonce = SecNonce::new(&mut rand::thread_rng(), outsider * G);
+        assert_eq!(session.sign(nonce, outsider), Err(Musig2Error::UnknownKey));
+
+        let mut partial_signature = session.sign(second, secrets[1]).unwrap();
+        partial_signature.0 += Scalar::one();
+        assert_eq!(
+            session.verify_partial_signature(&partial_signature, &pub_nonces[1], &(secrets[1] * G)),
+            Err(Musig2Error::InvalidPartialSignature)
+        );
+    }
+
+    #[test]
+    fn encoding_roundtrip() {
+        let nonce = SecNonce::new(&mut rand::thread_rng(), *G).public_nonce();
+        assert_eq!(PubNonce::from_slice(&nonce.serialize()), Ok(nonce));
+        assert_eq!(
+            PubNonce::from_slice(&[0; 66]),
+            Err(Musig2Error::InvalidNonce)
+        );
+
+        // The nonces cancel out.
+        let negated = PubNonce {
+            r1: -nonce.r1,
+            r2: nonce.r2,
+        };
+        let agg_nonce = AggNonce::sum(&[nonce, negated]);
+        assert_eq!(agg_nonce.r1, MaybePublicKey::Infinity);
+        assert_eq!(&agg_nonce.serialize()[..33], &[0; 33]);
+        assert_eq!(AggNonce::from_slice(&agg_nonce.serialize()), Ok(agg_nonce));
+
+        let partial_signature = PartialSignature(Scalar::reduce_from(&[0x99; 32]).into());
+        assert_eq!(
+            PartialSignature::from_slice(&partial_signature.serialize()),
+            Ok(partial_signature)
+        );
+        assert_eq!(
+            PartialSignature::from_slice(&[0xff; 32]),
+            Err(Musig2Error::InvalidPartialSignature)
+        );
+    }
+}
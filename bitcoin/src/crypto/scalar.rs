@@ -1,13 +1,18 @@
-use k256::SecretKey;
-use once_cell::sync::Lazy;
+use core::fmt;
+use core::str::FromStr;
+
+use hex::DisplayHex;
+use k256::elliptic_curve::ops::{Invert, MulByGenerator};
+use k256::{ProjectivePoint, SecretKey};
 use subtle::{ConditionallySelectable, ConstantTimeEq, ConstantTimeGreater};
 
 use crate::{
     crypto::{
         key::PublicKey,
-        utils::{ct_slice_lex_cmp, xor_arrays},
+        utils::{ct_slice_lex_cmp, from_hex, xor_arrays},
     },
-    CryptoError,
+    prelude::*,
+    CryptoError, Parity,
 };
 
 /// The largest possible 256-bit integer, represented as a byte array.
@@ -41,30 +46,55 @@ pub enum MaybeScalar {
 
 use MaybeScalar::*;
 
-use super::error::{InvalidScalarBytes, ZeroScalarError};
+use super::error::{
+    InvalidLagrangeIndices, InvalidScalarBytes, InvalidScalarString, ZeroScalarError,
+};
 
 impl MaybeScalar {
     /// Returns a valid `MaybeScalar` with a value of 1.
-    pub fn one() -> MaybeScalar {
+    pub const fn one() -> MaybeScalar {
         Valid(Scalar::one())
     }
 
     /// Returns a valid `MaybeScalar` with a value of two.
-    pub fn two() -> MaybeScalar {
+    pub const fn two() -> MaybeScalar {
         Valid(Scalar::two())
     }
 
     /// Returns half of the curve order `n`, specifically `n >> 1`.
-    pub fn half_order() -> MaybeScalar {
+    pub const fn half_order() -> MaybeScalar {
         Valid(Scalar::half_order())
     }
 
     /// Returns a valid `MaybeScalar` with the maximum possible value less
     /// than the curve order, `n - 1`.
-    pub fn max() -> MaybeScalar {
+    pub const fn max() -> MaybeScalar {
         Valid(Scalar::max())
     }
 
+    /// Constructs a scalar from a `u32`. Runs in constant time.
+    pub fn from_u32(n: u32) -> MaybeScalar {
+        MaybeScalar::from_k256(k256::Scalar::from(n))
+    }
+
+    /// Constructs a scalar from a `u64`. Runs in constant time.
+    pub fn from_u64(n: u64) -> MaybeScalar {
+        MaybeScalar::from_k256(k256::Scalar::from(n))
+    }
+
+    /// Constructs a scalar from a `u128`. Runs in constant time.
+    ///
+    /// Every `u128` is smaller than the curve order, so no reduction takes place.
+    pub fn from_u128(n: u128) -> MaybeScalar {
+        MaybeScalar::from_k256(k256::Scalar::from(n))
+    }
+
+    fn from_k256(scalar: k256::Scalar) -> MaybeScalar {
+        Option::<k256::NonZeroScalar>::from(k256::NonZeroScalar::new(scalar))
+            .map(MaybeScalar::from)
+            .unwrap_or(Zero)
+    }
+
     /// Returns true if this scalar represents zero.
     pub fn is_zero(&self) -> bool {
         self == &Zero
@@ -111,6 +141,54 @@ impl MaybeScalar {
             })
     }
 
+    /// Parses a scalar in the range `[0, n)` from a 64-character big-endian hex string.
+    pub fn from_hex(s: &str) -> Result<Self, InvalidScalarString> {
+        let mut bytes = [0u8; 32];
+        match from_hex(s, &mut bytes) {
+            Ok(32) => MaybeScalar::from_slice(&bytes).map_err(|_| InvalidScalarString),
+            _ => Err(InvalidScalarString),
+        }
+    }
+
+    /// Serializes the scalar to a 64-character lowercase big-endian hex string.
+    ///
+    /// # Warning
+    ///
+    /// Use cautiously. The string is formatted in non-constant time and
+    /// could reveal secret key material.
+    pub fn to_hex(&self) -> String {
+        format!("{:x}", self)
+    }
+
+    /// Computes the multiplicative inverse of the scalar modulo the curve order `n`,
+    /// in constant time. Returns [`ZeroScalarError`] if `self == MaybeScalar::Zero`,
+    /// as zero has no inverse.
+    pub fn invert(self) -> Result<Scalar, ZeroScalarError> {
+        self.not_zero().map(Scalar::invert)
+    }
+
+    /// Inverts every non-zero scalar in `scalars` in place, using a single scalar
+    /// inversion. See [`Scalar::batch_invert`].
+    ///
+    /// Zero scalars have no inverse and are left as [`MaybeScalar::Zero`]. Timing
+    /// information may be leaked about which scalars are zero, but not about the
+    /// values of the non-zero scalars.
+    pub fn batch_invert(scalars: &mut [MaybeScalar]) {
+        let mut valid: Vec<Scalar> = scalars.iter().filter_map(|s| s.into_option()).collect();
+        Scalar::batch_invert(&mut valid);
+
+        let mut inverses = valid.into_iter();
+        for scalar in scalars.iter_mut().filter(|s| !s.is_zero()) {
+            *scalar = MaybeScalar::Valid(inverses.next().expect("one inverse per non-zero scalar"));
+        }
+    }
+
+    /// Negates the scalar if `parity` is odd, in constant time. See [`Scalar::negate_if`].
+    #[must_use]
+    pub fn negate_if(self, parity: Parity) -> MaybeScalar {
+        MaybeScalar::conditional_select(&self, &-self, subtle::Choice::from(parity.to_u8()))
+    }
+
     /// Coerces the `MaybeScalar` into a [`Scalar`]. Panics if `self == MaybeScalar::Zero`.
     pub fn unwrap(self) -> Scalar {
         match self {
@@ -119,6 +197,14 @@ impl MaybeScalar {
         }
     }
 
+    /// Converts a 32-byte array into a `MaybeScalar` by interpreting it as a
+    /// big-endian integer `z` and returning `z % n`, where `n` is the secp256k1
+    /// curve order. This is the `int(x) mod n` reduction used by BIP340. Runs in
+    /// constant time.
+    pub fn reduce_from(z_bytes: &[u8; 32]) -> Self {
+        MaybeScalar::reduce_from_internal(z_bytes, &CURVE_ORDER_BYTES)
+    }
+
     /// This impl is a courtesy of the secp crate.
     ///
     /// Converts a 32-byte array into a `MaybeScalar` by interpreting it as
@@ -164,7 +250,8 @@ impl MaybeScalar {
         // MAX_U256.
         let z_bytes_neg = xor_arrays(z_bytes, &MAX_U256);
 
-        let z_needs_reduction = ct_slice_lex_cmp(z_bytes, modulus).ct_gt(&std::cmp::Ordering::Less);
+        let z_needs_reduction =
+            ct_slice_lex_cmp(z_bytes, modulus).ct_gt(&core::cmp::Ordering::Less);
 
         let q_bytes = <[u8; 32]>::conditional_select(
             z_bytes,      // `z < modulus`; set `q = z`
@@ -203,33 +290,11 @@ impl From<Scalar> for MaybeScalar {
     }
 }
 
-static SCALAR_ONE: Lazy<Scalar> = Lazy::new(|| {
-    Scalar::try_from(&[
-        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-        0, 1u8,
-    ])
-    .unwrap()
-});
-
-static SCALAR_TWO: Lazy<Scalar> = Lazy::new(|| {
-    Scalar::try_from(&[
-        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-        0, 2u8,
-    ])
-    .unwrap()
-});
-
-static SCALAR_HALF_ORDER: Lazy<Scalar> = Lazy::new(|| {
-    Scalar::try_from(&[
-        0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
-        0xff, 0x5d, 0x57, 0x6e, 0x73, 0x57, 0xa4, 0x50, 0x1d, 0xdf, 0xe9, 0x2f, 0x46, 0x68, 0x1b,
-        0x20, 0xa0u8,
-    ])
-    .unwrap()
-});
-
-static SCALAR_MAX: Lazy<Scalar> =
-    Lazy::new(|| Scalar::try_from(&CURVE_ORDER_MINUS_ONE_BYTES).unwrap());
+/// This is a big-endian representation of half the secp256k1 curve order, `n >> 1`.
+const HALF_CURVE_ORDER_BYTES: [u8; 32] = [
+    0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0x5d, 0x57, 0x6e, 0x73, 0x57, 0xa4, 0x50, 0x1d, 0xdf, 0xe9, 0x2f, 0x46, 0x68, 0x1b, 0x20, 0xa0,
+];
 
 /// This is a big-endian representation of the secp256k1 curve order `n`.
 const CURVE_ORDER_BYTES: [u8; 32] = [
@@ -243,31 +308,109 @@ const CURVE_ORDER_MINUS_ONE_BYTES: [u8; 32] = [
     0xBA, 0xAE, 0xDC, 0xE6, 0xAF, 0x48, 0xA0, 0x3B, 0xBF, 0xD2, 0x5E, 0x8C, 0xD0, 0x36, 0x41, 0x40,
 ];
 
+/// The scalar one, as a k256 scalar.
+const SCALAR_ONE: k256::Scalar = k256::Scalar::ONE;
+
+/// The scalar two, as a k256 scalar.
+const SCALAR_TWO: k256::Scalar = SCALAR_ONE.add(&SCALAR_ONE);
+
+/// The scalar `n - 1`, as a k256 scalar.
+const SCALAR_MAX: k256::Scalar = SCALAR_ONE.negate();
+
 #[derive(Copy, Clone)]
 pub struct Scalar {
-    pub(crate) inner: k256::NonZeroScalar,
+    /// The value of the scalar, which is never zero.
+    pub(crate) inner: k256::Scalar,
 }
 
 impl Scalar {
     /// Returns a valid `Scalar` with a value of 1.
-    pub fn one() -> Scalar {
-        *SCALAR_ONE
+    pub const fn one() -> Scalar {
+        Scalar::from_const(SCALAR_ONE)
     }
 
     /// Returns a valid `Scalar` with a value of two.
-    pub fn two() -> Scalar {
-        *SCALAR_TWO
+    pub const fn two() -> Scalar {
+        Scalar::from_const(SCALAR_TWO)
     }
 
     /// Returns half of the curve order `n`, specifically `n >> 1`.
-    pub fn half_order() -> Scalar {
-        *SCALAR_HALF_ORDER
+    pub const fn half_order() -> Scalar {
+        Scalar::from_const_bytes(&HALF_CURVE_ORDER_BYTES)
     }
 
     /// Returns a valid `Scalar` with the maximum possible value less
     /// than the curve order, `n - 1`.
-    pub fn max() -> Scalar {
-        *SCALAR_MAX
+    pub const fn max() -> Scalar {
+        Scalar::from_const(SCALAR_MAX)
+    }
+
+    /// Returns the big-endian representation of the curve order `n`.
+    ///
+    /// The curve order is not itself a valid scalar, so it is only available as bytes.
+    pub const fn curve_order_bytes() -> [u8; 32] {
+        CURVE_ORDER_BYTES
+    }
+
+    /// Wraps one of the non-zero k256 scalar constants of this module.
+    const fn from_const(scalar: k256::Scalar) -> Scalar {
+        Scalar { inner: scalar }
+    }
+
+    /// Parses a non-zero scalar in the range `[1, n)` from its big-endian bytes, in const
+    /// contexts. This is not constant time, it is meant for constants.
+    ///
+    /// # Panics
+    ///
+    /// If `bytes` is zero or at least the curve order. Use [`Scalar::from_slice`] to parse
+    /// bytes which are not known to be valid.
+    pub const fn from_const_bytes(bytes: &[u8; 32]) -> Scalar {
+        let mut inner = k256::Scalar::ZERO;
+        let mut is_zero = true;
+        // Whether the bytes are below the curve order, once they differ from it.
+        let mut below_order = None;
+        let mut i = 0;
+        while i < 32 {
+            let byte = bytes[i];
+            is_zero &= byte == 0;
+            if below_order.is_none() && byte != CURVE_ORDER_BYTES[i] {
+                below_order = Some(byte < CURVE_ORDER_BYTES[i]);
+            }
+            let mut bit = 8;
+            while bit > 0 {
+                bit -= 1;
+                inner = inner.add(&inner);
+                if (byte >> bit) & 1 == 1 {
+                    inner = inner.add(&SCALAR_ONE);
+                }
+            }
+            i += 1;
+        }
+        assert!(
+            !is_zero && matches!(below_order, Some(true)),
+            "scalar bytes out of range"
+        );
+        Scalar { inner }
+    }
+
+    /// Returns the scalar as a k256 non-zero scalar.
+    pub(crate) fn to_nonzero_scalar(self) -> k256::NonZeroScalar {
+        k256::NonZeroScalar::new(self.inner).expect("a scalar is never zero")
+    }
+
+    /// Constructs a non-zero scalar from a `u32`, returning [`ZeroScalarError`] if `n == 0`.
+    pub fn from_u32(n: u32) -> Result<Scalar, ZeroScalarError> {
+        MaybeScalar::from_u32(n).not_zero()
+    }
+
+    /// Constructs a non-zero scalar from a `u64`, returning [`ZeroScalarError`] if `n == 0`.
+    pub fn from_u64(n: u64) -> Result<Scalar, ZeroScalarError> {
+        MaybeScalar::from_u64(n).not_zero()
+    }
+
+    /// Constructs a non-zero scalar from a `u128`, returning [`ZeroScalarError`] if `n == 0`.
+    pub fn from_u128(n: u128) -> Result<Scalar, ZeroScalarError> {
+        MaybeScalar::from_u128(n).not_zero()
     }
 
     /// Generates a new random scalar from the given CSPRNG.
@@ -295,17 +438,159 @@ impl Scalar {
         Ok(Scalar::from(inner))
     }
 
+    /// Parses a non-zero scalar in the range `[1, n)` from a 64-character
+    /// big-endian hex string.
+    pub fn from_hex(s: &str) -> Result<Self, InvalidScalarString> {
+        let mut bytes = [0u8; 32];
+        match from_hex(s, &mut bytes) {
+            Ok(32) => Scalar::from_slice(&bytes).map_err(|_| InvalidScalarString),
+            _ => Err(InvalidScalarString),
+        }
+    }
+
+    /// Serializes the scalar to a 64-character lowercase big-endian hex string.
+    ///
+    /// # Warning
+    ///
+    /// Use cautiously. The string is formatted in non-constant time and
+    /// could reveal secret key material.
+    pub fn to_hex(&self) -> String {
+        format!("{:x}", self)
+    }
+
+    /// Computes the multiplicative inverse of the scalar modulo the curve order `n`,
+    /// such that `x * x.invert() == 1`. Runs in constant time.
+    ///
+    /// The inverse of a non-zero scalar is always non-zero, so this cannot fail.
+    pub fn invert(self) -> Scalar {
+        Scalar::from(Invert::invert(&self.to_nonzero_scalar()))
+    }
+
+    /// Inverts every scalar in `scalars` in place.
+    ///
+    /// This uses Montgomery's trick to replace `N` scalar inversions with a single
+    /// inversion and `3(N-1)` multiplications, which is considerably faster when
+    /// many inversions are needed at once. Runs in constant time.
+    pub fn batch_invert(scalars: &mut [Scalar]) {
+        if scalars.is_empty() {
+            return;
+        }
+
+        // prefix[i] = scalars[0] * ... * scalars[i]
+        let mut prefix = Vec::with_capacity(scalars.len());
+        let mut acc = scalars[0];
+        prefix.push(acc);
+        for &scalar in &scalars[1..] {
+            acc *= scalar;
+            prefix.push(acc);
+        }
+
+        // inv = 1 / (scalars[0] * ... * scalars[i])
+        let mut inv = acc.invert();
+        for i in (1..scalars.len()).rev() {
+            let scalar = scalars[i];
+            scalars[i] = inv * prefix[i - 1];
+            inv *= scalar;
+        }
+        scalars[0] = inv;
+    }
+
+    /// Computes the Lagrange coefficient of `my_index` for interpolating a polynomial at zero
+    /// from its values at `indices`, that is the product of `x / (x - my_index)` over every
+    /// other index `x`.
+    ///
+    /// The denominators are inverted with [`Scalar::batch_invert`]. Returns
+    /// [`InvalidLagrangeIndices`] if `indices` does not contain `my_index` or contains duplicates.
+    pub fn lagrange_coefficient(
+        indices: &[Scalar],
+        my_index: Scalar,
+    ) -> Result<Scalar, InvalidLagrangeIndices> {
+        let has_duplicates = indices
+            .iter()
+            .enumerate()
+            .any(|(i, index)| indices[..i].contains(index));
+        if has_duplicates || !indices.contains(&my_index) {
+            return Err(InvalidLagrangeIndices);
+        }
+
+        // The indices are distinct, so none of the denominators is zero.
+        let mut numerator = Scalar::one();
+        let mut denominators = Vec::with_capacity(indices.len() - 1);
+        for &index in indices.iter().filter(|&&index| index != my_index) {
+            numerator *= index;
+            denominators.push((index - my_index).unwrap());
+        }
+        Self::batch_invert(&mut denominators);
+
+        Ok(denominators
+            .into_iter()
+            .fold(numerator, |acc, inv| acc * inv))
+    }
+
+    /// Computes the Lagrange coefficients of all `indices` at once, in the same order, as by
+    /// [`Scalar::lagrange_coefficient`] but with a single batch inversion.
+    ///
+    /// Returns [`InvalidLagrangeIndices`] if `indices` is empty or contains duplicates.
+    pub fn lagrange_coefficients(
+        indices: &[Scalar],
+    ) -> Result<Vec<Scalar>, InvalidLagrangeIndices> {
+        if indices.is_empty() {
+            return Err(InvalidLagrangeIndices);
+        }
+
+        let mut numerators = Vec::with_capacity(indices.len());
+        let mut denominators = Vec::with_capacity(indices.len());
+        for (i, &x_i) in indices.iter().enumerate() {
+            let mut numerator = Scalar::one();
+            let mut denominator = Scalar::one();
+            for (j, &x_j) in indices.iter().enumerate() {
+                if i != j {
+                    numerator *= x_j;
+                    denominator *= (x_j - x_i).not_zero().map_err(|_| InvalidLagrangeIndices)?;
+                }
+            }
+            numerators.push(numerator);
+            denominators.push(denominator);
+        }
+        Self::batch_invert(&mut denominators);
+
+        Ok(numerators
+            .into_iter()
+            .zip(denominators)
+            .map(|(num, inv)| num * inv)
+            .collect())
+    }
+
     /// Multiplies the secp256k1 base point by this scalar. This is how
     /// public keys (points) are derived from private keys (scalars).
     /// Since this scalar is non-zero, the point derived from base-point
     /// multiplication is also guaranteed to be valid.
     ///
+    /// With the `std` feature, uses a lazily built table of precomputed
+    /// multiples of the base point, so repeated calls are several times faster
+    /// than a generic scalar multiplication. The table lookups are constant time.
+    ///
     /// Assumes the public key is compressed
     pub fn base_point_mul(&self) -> PublicKey {
-        let inner = k256::PublicKey::from_secret_scalar(&self.inner);
+        let point = ProjectivePoint::mul_by_generator(&self.inner);
+        let inner = k256::PublicKey::from_affine(point.to_affine())
+            .expect("non-zero scalar times the base point is never infinity");
         PublicKey::new(inner)
     }
 
+    /// Negates the scalar if `parity` is odd, in constant time.
+    ///
+    /// This is how a secret key is matched to the even Y-coordinate of a public key,
+    /// for instance the tweaked output key of a taproot spend, without branching on
+    /// the secret.
+    #[must_use]
+    pub fn negate_if(self, parity: Parity) -> Scalar {
+        let choice = subtle::Choice::from(parity.to_u8());
+        Scalar {
+            inner: k256::Scalar::conditional_select(&self.inner, &-self.inner, choice),
+        }
+    }
+
     /// Checks if the scalar is greater than the SECP256k1 curve - 1
     pub fn greater_than_curve_order_minus_one(&self) -> bool {
         bool::from(self.ct_gt(&Self::max()))
@@ -364,6 +649,28 @@ mod conversions {
             }
         }
 
+        macro_rules! impl_from_int {
+            ($($int:ty => $from_int:ident),*) => {
+                $(
+                    impl From<$int> for MaybeScalar {
+                        fn from(n: $int) -> Self {
+                            MaybeScalar::$from_int(n)
+                        }
+                    }
+
+                    impl TryFrom<$int> for Scalar {
+                        type Error = ZeroScalarError;
+
+                        /// Returns [`ZeroScalarError`] if `n == 0`.
+                        fn try_from(n: $int) -> Result<Self, Self::Error> {
+                            Scalar::$from_int(n)
+                        }
+                    }
+                )*
+            };
+        }
+        impl_from_int!(u32 => from_u32, u64 => from_u64, u128 => from_u128);
+
         impl TryFrom<&[u8]> for MaybeScalar {
             type Error = InvalidScalarBytes;
 
@@ -440,15 +747,13 @@ mod conversions {
 
         impl From<k256::NonZeroScalar> for Scalar {
             fn from(nz_scalar: k256::NonZeroScalar) -> Self {
-                return Scalar { inner: nz_scalar };
+                Scalar { inner: *nz_scalar }
             }
         }
 
         impl From<&k256::NonZeroScalar> for Scalar {
             fn from(nz_scalar: &k256::NonZeroScalar) -> Self {
-                return Scalar {
-                    inner: nz_scalar.to_owned(),
-                };
+                Scalar { inner: **nz_scalar }
             }
         }
 
@@ -470,7 +775,7 @@ mod subtle_traits {
         #[inline]
         fn ct_gt(&self, other: &Self) -> subtle::Choice {
             ct_slice_lex_cmp(&self.serialize(), &other.serialize())
-                .ct_eq(&std::cmp::Ordering::Greater)
+                .ct_eq(&core::cmp::Ordering::Greater)
         }
     }
 
@@ -482,11 +787,11 @@ mod subtle_traits {
         fn conditional_select(&a: &Self, &b: &Self, choice: subtle::Choice) -> Self {
             let a_inner = a
                 .into_option()
-                .map(|scalar| scalar.inner.as_ref().clone())
+                .map(|scalar| scalar.inner)
                 .unwrap_or(k256::Scalar::ZERO);
             let b_inner = b
                 .into_option()
-                .map(|scalar| scalar.inner.as_ref().clone())
+                .map(|scalar| scalar.inner)
                 .unwrap_or(k256::Scalar::ZERO);
 
             let inner_scalar = k256::Scalar::conditional_select(&a_inner, &b_inner, choice);
@@ -498,28 +803,134 @@ mod subtle_traits {
     }
 }
 
+#[cfg(feature = "serde")]
+mod serde_traits {
+    use core::marker::PhantomData;
+
+    use super::*;
+
+    /// Serializes as 64 hex characters in human-readable formats, or as 32 raw bytes otherwise.
+    impl serde::Serialize for Scalar {
+        fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+            if s.is_human_readable() {
+                s.collect_str(&format_args!("{:x}", self))
+            } else {
+                s.serialize_bytes(&self.serialize())
+            }
+        }
+    }
+
+    /// Serializes as 64 hex characters in human-readable formats, or as 32 raw bytes otherwise.
+    impl serde::Serialize for MaybeScalar {
+        fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+            if s.is_human_readable() {
+                s.collect_str(&format_args!("{:x}", self))
+            } else {
+                s.serialize_bytes(&self.serialize())
+            }
+        }
+    }
+
+    impl<'de> serde::Deserialize<'de> for Scalar {
+        /// Raw bytes are range checked in constant time.
+        fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+            deserialize_scalar(d)
+        }
+    }
+
+    impl<'de> serde::Deserialize<'de> for MaybeScalar {
+        /// Raw bytes are range checked in constant time.
+        fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+            deserialize_scalar(d)
+        }
+    }
+
+    fn deserialize_scalar<'de, D, T>(d: D) -> Result<T, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+        T: FromStr<Err = InvalidScalarString>
+            + for<'a> TryFrom<&'a [u8], Error = InvalidScalarBytes>,
+    {
+        struct ScalarVisitor<T>(PhantomData<T>);
+
+        impl<'de, T> serde::de::Visitor<'de> for ScalarVisitor<T>
+        where
+            T: FromStr<Err = InvalidScalarString>
+                + for<'a> TryFrom<&'a [u8], Error = InvalidScalarBytes>,
+        {
+            type Value = T;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a 32-byte scalar or its hex encoding")
+            }
+
+            fn visit_bytes<E: serde::de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+                T::try_from(v).map_err(E::custom)
+            }
+
+            fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                T::from_str(v).map_err(E::custom)
+            }
+        }
+
+        if d.is_human_readable() {
+            d.deserialize_str(ScalarVisitor(PhantomData))
+        } else {
+            d.deserialize_bytes(ScalarVisitor(PhantomData))
+        }
+    }
+}
+
+#[cfg(feature = "zeroize")]
+mod zeroize_traits {
+    use zeroize::Zeroize;
+
+    use super::*;
+
+    /// `Scalar` is `Copy`, so it cannot wipe itself on drop. Callers holding secret
+    /// scalars should call [`Zeroize::zeroize`] once they are done with them.
+    impl Zeroize for Scalar {
+        /// Overwrites the scalar with one, since a `Scalar` can never be zero.
+        fn zeroize(&mut self) {
+            self.inner.zeroize();
+            self.inner = SCALAR_ONE;
+        }
+    }
+
+    impl Zeroize for MaybeScalar {
+        /// Overwrites the scalar and sets it to [`MaybeScalar::Zero`].
+        fn zeroize(&mut self) {
+            if let Valid(scalar) = self {
+                scalar.zeroize();
+            }
+            *self = Zero;
+        }
+    }
+}
+
 mod std_traits {
     use super::*;
 
     /// This implementation was duplicated from the [`secp256k1`] crate, because
     /// [`k256::NonZeroScalar`] doesn't implement `Debug`.
-    impl std::fmt::Debug for Scalar {
-        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-            use std::hash::Hasher as _;
+    impl core::fmt::Debug for Scalar {
+        fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+            use hashes::{sha256, Hash as _, HashEngine as _};
+
             const DEBUG_HASH_TAG: &[u8] = &[
                 0x66, 0xa6, 0x77, 0x1b, 0x9b, 0x6d, 0xae, 0xa1, 0xb2, 0xee, 0x4e, 0x07, 0x49, 0x4a,
                 0xac, 0x87, 0xa9, 0xb8, 0x5b, 0x4b, 0x35, 0x02, 0xaa, 0x6d, 0x0f, 0x79, 0xcb, 0x63,
                 0xe6, 0xf8, 0x66, 0x22,
             ]; // =SHA256(b"rust-secp256k1DEBUG");
 
-            let mut hasher = std::collections::hash_map::DefaultHasher::new();
-            hasher.write(DEBUG_HASH_TAG);
-            hasher.write(DEBUG_HASH_TAG);
-            hasher.write(&self.serialize());
-            let hash = hasher.finish();
+            let mut engine = sha256::Hash::engine();
+            engine.input(DEBUG_HASH_TAG);
+            engine.input(DEBUG_HASH_TAG);
+            engine.input(&self.serialize());
+            let hash = sha256::Hash::from_engine(engine);
 
             f.debug_tuple(stringify!(Scalar))
-                .field(&format_args!("#{:016x}", hash))
+                .field(&format_args!("#{:.16}", hash))
                 .finish()
         }
     }
@@ -540,4 +951,376 @@ mod std_traits {
             MaybeScalar::Zero
         }
     }
+
+    impl FromStr for Scalar {
+        type Err = InvalidScalarString;
+
+        /// Parses a non-zero scalar from a 64-character big-endian hex string.
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            Scalar::from_hex(s)
+        }
+    }
+
+    impl FromStr for MaybeScalar {
+        type Err = InvalidScalarString;
+
+        /// Parses a possibly zero scalar from a 64-character big-endian hex string.
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            MaybeScalar::from_hex(s)
+        }
+    }
+
+    // Scalars are usually secret, so there is deliberately no `Display` implementation.
+    // Hex formatting must be asked for explicitly with `{:x}` or `{:X}`.
+    impl fmt::LowerHex for Scalar {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            fmt::LowerHex::fmt(&self.serialize().as_hex(), f)
+        }
+    }
+
+    impl fmt::UpperHex for Scalar {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            fmt::UpperHex::fmt(&self.serialize().as_hex(), f)
+        }
+    }
+
+    impl fmt::LowerHex for MaybeScalar {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            fmt::LowerHex::fmt(&self.serialize().as_hex(), f)
+        }
+    }
+
+    impl fmt::UpperHex for MaybeScalar {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            fmt::UpperHex::fmt(&self.serialize().as_hex(), f)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lagrange_coefficients() {
+        let indices: Vec<Scalar> = [1, 3, 4]
+            .iter()
+            .map(|&i| Scalar::from_u32(i).unwrap())
+            .collect();
+        let coefficients = Scalar::lagrange_coefficients(&indices).unwrap();
+        for (&index, &coefficient) in indices.iter().zip(&coefficients) {
+            assert_eq!(
+                Scalar::lagrange_coefficient(&indices, index),
+                Ok(coefficient)
+            );
+        }
+
+        // Interpolating f(x) = 5 + 2x + x^2 at zero.
+        let f = |x: u32| Scalar::from_u32(5 + 2 * x + x * x).unwrap();
+        let secret = [1, 3, 4]
+            .iter()
+            .zip(&coefficients)
+            .fold(MaybeScalar::Zero, |acc, (&x, &c)| acc + f(x) * c);
+        assert_eq!(secret, MaybeScalar::from_u32(5));
+
+        // A single index has the coefficient one.
+        assert_eq!(
+            Scalar::lagrange_coefficient(&indices[..1], indices[0]),
+            Ok(Scalar::one())
+        );
+
+        assert_eq!(
+            Scalar::lagrange_coefficient(&indices, Scalar::two()),
+            Err(InvalidLagrangeIndices)
+        );
+        let duplicates = [indices[0], indices[1], indices[1]];
+        assert_eq!(
+            Scalar::lagrange_coefficient(&duplicates, indices[0]),
+            Err(InvalidLagrangeIndices)
+        );
+        assert_eq!(
+            Scalar::lagrange_coefficients(&duplicates),
+            Err(InvalidLagrangeIndices)
+        );
+        assert_eq!(
+            Scalar::lagrange_coefficients(&[]),
+            Err(InvalidLagrangeIndices)
+        );
+    }
+
+    #[test]
+    fn constants() {
+        assert_eq!(Scalar::one(), Scalar::from_u32(1).unwrap());
+        assert_eq!(MaybeScalar::two(), Scalar::one() + Scalar::one());
+        assert_eq!(Scalar::max() + Scalar::one(), MaybeScalar::Zero);
+        assert_eq!(
+            Scalar::half_order() + Scalar::half_order() + Scalar::one(),
+            MaybeScalar::Zero
+        );
+        assert_eq!(
+            MaybeScalar::reduce_from(&Scalar::curve_order_bytes()),
+            MaybeScalar::Zero
+        );
+
+        // The constructors are usable in const contexts.
+        const HALF_ORDER: Scalar = Scalar::half_order();
+        const MAX: MaybeScalar = MaybeScalar::max();
+        assert_eq!(
+            HALF_ORDER,
+            Scalar::try_from(&HALF_CURVE_ORDER_BYTES).unwrap()
+        );
+        assert_eq!(MAX, MaybeScalar::Valid(Scalar::max()));
+        let bytes = [0xab; 32];
+        assert_eq!(
+            Scalar::from_const_bytes(&bytes),
+            Scalar::from_slice(&bytes).unwrap()
+        );
+        assert_eq!(
+            Scalar::from_const_bytes(&CURVE_ORDER_MINUS_ONE_BYTES),
+            Scalar::max()
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn const_bytes_curve_order() {
+        Scalar::from_const_bytes(&CURVE_ORDER_BYTES);
+    }
+
+    #[test]
+    #[should_panic]
+    fn const_bytes_zero() {
+        Scalar::from_const_bytes(&[0; 32]);
+    }
+
+    #[test]
+    fn scalar_invert() {
+        assert_eq!(Scalar::one().invert(), Scalar::one());
+        assert_eq!(Scalar::max().invert(), Scalar::max());
+
+        let x = Scalar::reduce_from(&[0xab; 32]);
+        assert_eq!(x * x.invert(), Scalar::one());
+        assert_eq!(x / x, Scalar::one());
+        assert_eq!(x.invert().invert(), x);
+    }
+
+    #[test]
+    fn maybe_scalar_invert() {
+        assert_eq!(MaybeScalar::Zero.invert(), Err(ZeroScalarError));
+        assert_eq!(MaybeScalar::two().invert(), Ok(Scalar::two().invert()));
+    }
+
+    #[test]
+    fn scalar_batch_invert() {
+        let scalars: Vec<Scalar> =
+            (1..=5u8).map(|i| Scalar::reduce_from(&[i.wrapping_mul(37); 32])).collect();
+
+        let mut inverted = scalars.clone();
+        Scalar::batch_invert(&mut inverted);
+        for (scalar, inverse) in scalars.iter().zip(&inverted) {
+            assert_eq!(scalar.invert(), *inverse);
+        }
+
+        let mut single = [Scalar::two()];
+        Scalar::batch_invert(&mut single);
+        assert_eq!(single[0], Scalar::two().invert());
+
+        Scalar::batch_invert(&mut []);
+    }
+
+    #[test]
+    fn maybe_scalar_batch_invert() {
+        let mut scalars =
+            [MaybeScalar::Zero, MaybeScalar::two(), MaybeScalar::Zero, MaybeScalar::max()];
+        MaybeScalar::batch_invert(&mut scalars);
+        assert_eq!(
+            scalars,
+            [
+                MaybeScalar::Zero,
+                MaybeScalar::Valid(Scalar::two().invert()),
+                MaybeScalar::Zero,
+                MaybeScalar::Valid(Scalar::max().invert()),
+            ]
+        );
+    }
+
+    #[test]
+    fn base_point_mul() {
+        assert_eq!(
+            Scalar::one().base_point_mul().inner,
+            PublicKey::generator().inner
+        );
+
+        for x in [
+            Scalar::two(),
+            Scalar::max(),
+            Scalar::reduce_from(&[0xab; 32]),
+        ] {
+            let expected =
+                PublicKey::new(k256::PublicKey::from_secret_scalar(&x.to_nonzero_scalar()));
+            assert_eq!(x.base_point_mul(), expected);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "zeroize")]
+    fn zeroize() {
+        use zeroize::Zeroize;
+
+        let mut x = Scalar::reduce_from(&[0xab; 32]);
+        x.zeroize();
+        assert_eq!(x, Scalar::one());
+
+        let mut y = MaybeScalar::Valid(Scalar::max());
+        y.zeroize();
+        assert_eq!(y, MaybeScalar::Zero);
+    }
+
+    #[test]
+    fn hex_roundtrip() {
+        let hex = "ab".repeat(32);
+        let x: Scalar = hex.parse().unwrap();
+        assert_eq!(x, Scalar::try_from(&[0xab; 32]).unwrap());
+        assert_eq!(x.to_hex(), hex);
+        assert_eq!(format!("{:X}", x), hex.to_uppercase());
+        assert_eq!(Scalar::from_hex(&hex.to_uppercase()).unwrap(), x);
+
+        let zero = "00".repeat(32);
+        assert_eq!(zero.parse::<MaybeScalar>().unwrap(), MaybeScalar::Zero);
+        assert_eq!(MaybeScalar::Zero.to_hex(), zero);
+        assert_eq!(MaybeScalar::from_hex(&hex).unwrap(), MaybeScalar::Valid(x));
+        assert!(Scalar::from_hex(&zero).is_err());
+
+        // Wrong length, invalid characters and values not below the curve order.
+        assert!(Scalar::from_hex("abab").is_err());
+        assert!(Scalar::from_hex(&"ab".repeat(33)).is_err());
+        assert!(Scalar::from_hex(&"zz".repeat(32)).is_err());
+        assert!(MaybeScalar::from_hex(&"ff".repeat(32)).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde() {
+        use serde_test::{
+            assert_de_tokens_error, assert_tokens, Compact, Configure, Readable, Token,
+        };
+
+        let x = Scalar::try_from(&[0xab; 32]).unwrap();
+        let hex = "ab".repeat(32);
+        assert_tokens(&x.readable(), &[Token::Str(&hex)]);
+        assert_tokens(&x.compact(), &[Token::Bytes(&[0xab; 32])]);
+        assert_tokens(&MaybeScalar::Zero.compact(), &[Token::Bytes(&[0; 32])]);
+        assert_tokens(&MaybeScalar::Valid(x).readable(), &[Token::Str(&hex)]);
+
+        assert_de_tokens_error::<Compact<Scalar>>(
+            &[Token::Bytes(&[0; 32])],
+            "received invalid scalar bytes",
+        );
+        assert_de_tokens_error::<Readable<MaybeScalar>>(
+            &[Token::Str(&"ff".repeat(32))],
+            "received invalid scalar hex string",
+        );
+    }
+
+    #[test]
+    fn from_int() {
+        assert_eq!(MaybeScalar::from_u32(0), MaybeScalar::Zero);
+        assert_eq!(MaybeScalar::from(1u64), MaybeScalar::one());
+        assert_eq!(Scalar::from_u32(2).unwrap(), Scalar::two());
+        assert_eq!(Scalar::try_from(0u128), Err(ZeroScalarError));
+
+        let mut bytes = [0u8; 32];
+        bytes[16..].copy_from_slice(&u128::MAX.to_be_bytes());
+        assert_eq!(Scalar::from_u128(u128::MAX).unwrap().serialize(), bytes);
+        bytes[24..].copy_from_slice(&0x0102_0304_0506_0708u64.to_be_bytes());
+        assert_eq!(
+            MaybeScalar::from(0x0102_0304_0506_0708u64).serialize()[24..],
+            bytes[24..]
+        );
+        assert_eq!(
+            Scalar::try_from(7u32).unwrap() + Scalar::one(),
+            MaybeScalar::from(8u32)
+        );
+    }
+
+    #[test]
+    #[allow(clippy::op_ref)]
+    fn reference_ops() {
+        use crate::crypto::key::G;
+
+        let a = Scalar::from_u32(3).unwrap();
+        let b = Scalar::from_u32(5).unwrap();
+        let m = MaybeScalar::from(7u32);
+        let p = b * G;
+        let (a_ref, b_ref, m_ref, p_ref) = (&a, &b, &m, &p);
+
+        assert_eq!(a_ref + b_ref, a + b);
+        assert_eq!(a_ref - b, a - b);
+        assert_eq!(a * b_ref, a * b);
+        assert_eq!(m_ref / a_ref, m / a);
+        assert_eq!(p_ref * a_ref, p * a);
+        assert_eq!(a_ref * G, a * G);
+        assert_eq!(p_ref + &G, p + G);
+
+        let mut sum = MaybeScalar::Zero;
+        sum += a_ref;
+        sum -= m_ref;
+        assert_eq!(sum, a - m);
+        assert_eq!([a, b].iter().sum::<MaybeScalar>(), a + b);
+    }
+
+    #[test]
+    fn sum_and_product() {
+        let scalars = [1u32, 2, 3, 4].map(|n| Scalar::from_u32(n).unwrap());
+        assert_eq!(
+            scalars.iter().copied().sum::<MaybeScalar>(),
+            MaybeScalar::from(10u32)
+        );
+        assert_eq!(
+            scalars.iter().copied().product::<MaybeScalar>(),
+            MaybeScalar::from(24u32)
+        );
+
+        let maybe = [
+            MaybeScalar::from(5u32),
+            MaybeScalar::Zero,
+            -MaybeScalar::from(5u32),
+        ];
+        assert_eq!(
+            maybe.iter().copied().sum::<MaybeScalar>(),
+            MaybeScalar::Zero
+        );
+        assert_eq!(
+            maybe.iter().copied().product::<MaybeScalar>(),
+            MaybeScalar::Zero
+        );
+
+        assert_eq!(
+            core::iter::empty::<Scalar>().sum::<MaybeScalar>(),
+            MaybeScalar::Zero
+        );
+        assert_eq!(
+            core::iter::empty::<Scalar>().product::<MaybeScalar>(),
+            MaybeScalar::one()
+        );
+    }
+
+    #[test]
+    fn negate_if() {
+        let scalar = Scalar::from_u32(7).unwrap();
+        assert_eq!(scalar.negate_if(Parity::Even), scalar);
+        assert_eq!(scalar.negate_if(Parity::Odd), -scalar);
+        assert_eq!(MaybeScalar::Zero.negate_if(Parity::Odd), MaybeScalar::Zero);
+
+        // Negating the secret key negates its public key.
+        let point = scalar.base_point_mul();
+        for parity in [Parity::Even, Parity::Odd] {
+            assert_eq!(
+                scalar.negate_if(parity).base_point_mul().serialize(),
+                point.negate_if(parity).serialize()
+            );
+        }
+        let even = scalar.negate_if(Parity::from_u8(point.has_odd_y() as u8).unwrap());
+        assert!(even.base_point_mul().has_even_y());
+    }
 }
@@ -0,0 +1,221 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Batch verification of BIP340 signatures.
+//!
+//! [`verify_batch`] checks many signatures at once, as described by BIP340: a random linear
+//! combination of the signature equations is checked instead of each equation. All the points are
+//! summed in a single multi-scalar multiplication, computed with Pippenger's bucket method for
+//! large batches, so the cost per signature falls as the batch grows.
+//!
+
+use core::fmt;
+
+use hashes::{sha256, Hash, HashEngine};
+use internals::write_err;
+use k256::ProjectivePoint;
+
+use super::{verify, Signature64, CHALLENGE_TAG};
+use crate::common::types::Message;
+use crate::crypto::hashes::tagged_hash_to_scalar;
+use crate::crypto::key::{multi_scalar_mul, XOnlyPublicKey};
+use crate::crypto::scalar::MaybeScalar;
+use crate::prelude::*;
+use crate::CryptoError;
+
+/// Verifies that each signature is a valid signature of its message by its public key.
+///
+/// The result is the same as verifying every signature with
+/// [`verify_schnorr`](super::verify_schnorr), except with a negligible probability.
+///
+/// The random weights of the linear combination are derived by hashing the whole batch, so
+/// verification is deterministic and doesn't need a random number generator.
+///
+/// # Errors
+///
+/// If the batch is invalid, the signatures are verified one by one and the error is the one of the
+/// first invalid signature.
+pub fn verify_batch(
+    items: &[(Message, Signature64, XOnlyPublicKey)],
+) -> Result<(), BatchVerifyError> {
+    if combine(items) == Some(true) {
+        return Ok(());
+    }
+    for (index, (msg, sig, pubkey)) in items.iter().enumerate() {
+        verify(sig, msg.as_ref(), pubkey).map_err(|error| BatchVerifyError { index, error })?;
+    }
+    // Only reached if the weights make a valid linear combination of invalid equations.
+    Ok(())
+}
+
+/// Checks the linear combination of the signature equations of `items`.
+///
+/// Returns `None` if a signature or public key can't be parsed.
+fn combine(items: &[(Message, Signature64, XOnlyPublicKey)]) -> Option<bool> {
+    let mut engine = sha256::Hash::engine();
+    for (msg, sig, pubkey) in items {
+        engine.input(msg.as_ref());
+        engine.input(sig.as_ref());
+        engine.input(&pubkey.serialize());
+    }
+    let seed = sha256::Hash::from_engine(engine);
+
+    // With the weights `a`, the sum of `a * s` times G must equal the sum of `a * R + a * e * P`.
+    let mut g_scalar = k256::Scalar::ZERO;
+    let mut terms = Vec::with_capacity(2 * items.len() + 1);
+    for (index, (msg, sig, pubkey)) in items.iter().enumerate() {
+        let (r, s) = sig.as_byte_array().split_at(32);
+        let s = MaybeScalar::from_slice(s).ok()?;
+        let nonce = XOnlyPublicKey::from_slice(r).ok()?.lift_x();
+        let challenge =
+            tagged_hash_to_scalar(CHALLENGE_TAG, &[r, &pubkey.serialize(), msg.as_ref()]);
+
+        let weight = weight(&seed, index);
+        g_scalar += weight * to_k256(s);
+        terms.push((nonce.inner.to_projective(), -weight));
+        terms.push((
+            pubkey.lift_x().inner.to_projective(),
+            -(weight * to_k256(challenge)),
+        ));
+    }
+    terms.push((ProjectivePoint::GENERATOR, g_scalar));
+    Some(multi_scalar_mul(&terms) == ProjectivePoint::IDENTITY)
+}
+
+/// Returns the weight of the item at `index` in the batch.
+///
+/// The first weight is one, which doesn't weaken the combination.
+fn weight(seed: &sha256::Hash, index: usize) -> k256::Scalar {
+    if index == 0 {
+        return k256::Scalar::ONE;
+    }
+    let mut engine = sha256::Hash::engine();
+    engine.input(seed.as_ref());
+    engine.input(&(index as u64).to_le_bytes());
+    to_k256(MaybeScalar::reduce_from(
+        &sha256::Hash::from_engine(engine).to_byte_array(),
+    ))
+}
+
+fn to_k256(scalar: MaybeScalar) -> k256::Scalar {
+    match scalar {
+        MaybeScalar::Valid(scalar) => scalar.inner,
+        MaybeScalar::Zero => k256::Scalar::ZERO,
+    }
+}
+
+/// A signature of a batch failed to verify, returned by [`verify_batch`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct BatchVerifyError {
+    /// The index of the first invalid signature in the batch.
+    pub index: usize,
+    /// Why the signature is invalid.
+    pub error: CryptoError,
+}
+
+impl fmt::Display for BatchVerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_err!(f, "signature {} of the batch is invalid", self.index; self.error)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BatchVerifyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::key::{Keypair, PIPPENGER_THRESHOLD};
+    use crate::crypto::scalar::Scalar;
+    use crate::crypto::schnorr::sign_schnorr;
+    use crate::G;
+
+    pub(super) fn batch(len: u32) -> Vec<(Message, Signature64, XOnlyPublicKey)> {
+        (1..=len)
+            .map(|n| {
+                let secret = Scalar::from_u32(n * 0x0123_4567).unwrap();
+                let keypair = Keypair::from_seckey_slice(&secret.serialize()).unwrap();
+                let msg = Message::from_digest([n as u8; 32]);
+                let sig = sign_schnorr(&msg, &keypair, &[0; 32]);
+                (msg, sig, (secret * G).x_only_public_key().0)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn verify() {
+        assert_eq!(verify_batch(&[]), Ok(()));
+        for len in [1, 2, 7, PIPPENGER_THRESHOLD as u32 / 2 + 1] {
+            assert_eq!(verify_batch(&batch(len)), Ok(()));
+        }
+    }
+
+    #[test]
+    fn verify_invalid() {
+        let len = PIPPENGER_THRESHOLD / 2 + 1;
+        for i in [0, 1, len / 2, len - 1] {
+            let mut items = batch(len as u32);
+            items[i].0 = Message::from_digest([0xff; 32]);
+            let error = BatchVerifyError {
+                index: i,
+                error: CryptoError::IncorrectSignature,
+            };
+            assert_eq!(verify_batch(&items), Err(error.clone()));
+
+            let mut items = batch(len as u32);
+            items[i].2 = items[(i + 1) % len].2;
+            assert_eq!(verify_batch(&items), Err(error));
+
+            // An S value of at least the curve order can't be parsed.
+            let mut items = batch(len as u32);
+            let mut bytes = items[i].1.to_byte_array();
+            bytes[32..].fill(0xff);
+            items[i].1 = Signature64::from_byte_array(bytes);
+            let error = BatchVerifyError {
+                index: i,
+                error: CryptoError::InvalidSignature,
+            };
+            assert_eq!(verify_batch(&items), Err(error));
+        }
+    }
+}
+
+#[cfg(bench)]
+mod benches {
+    use test::{black_box, Bencher};
+
+    use super::tests::batch;
+    use super::*;
+
+    fn bench_batch(bh: &mut Bencher, len: u32) {
+        let items = batch(len);
+        bh.iter(|| {
+            black_box(verify_batch(&items)).unwrap();
+        });
+    }
+
+    #[bench]
+    pub fn verify_batch_16(bh: &mut Bencher) {
+        bench_batch(bh, 16)
+    }
+
+    #[bench]
+    pub fn verify_batch_256(bh: &mut Bencher) {
+        bench_batch(bh, 256)
+    }
+
+    /// The baseline of [`verify_batch_256`].
+    #[bench]
+    pub fn verify_each_256(bh: &mut Bencher) {
+        let items = batch(256);
+        bh.iter(|| {
+            for (msg, sig, pubkey) in &items {
+                black_box(verify(sig, msg.as_ref(), pubkey)).unwrap();
+            }
+        });
+    }
+}
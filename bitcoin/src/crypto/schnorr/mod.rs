@@ -0,0 +1,291 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! BIP340 Schnorr signatures.
+//!
+//! Signing and verification as specified by BIP340, implemented with the [`Scalar`] and
+//! [`PublicKey`](super::key::PublicKey) arithmetic of this crate. Taproot key path and script
+//! path spends sign their sighash with [`sign_schnorr`], the signing key being tweaked first for
+//! key path spends.
+//!
+
+use core::fmt;
+
+use hashes::Hash;
+use hex::DisplayHex;
+
+use super::hashes::{tagged_hash, tagged_hash_to_scalar};
+use super::key::{Keypair, MaybePublicKey, XOnlyPublicKey, G};
+use super::scalar::{MaybeScalar, Scalar};
+use crate::common::types::Message;
+use crate::CryptoError;
+
+mod batch;
+
+pub use self::batch::{verify_batch, BatchVerifyError};
+
+/// The tag of the hash masking the secret key with the auxiliary randomness.
+const AUX_TAG: &str = "BIP0340/aux";
+
+/// The tag of the nonce hash.
+const NONCE_TAG: &str = "BIP0340/nonce";
+
+/// The tag of the challenge hash.
+const CHALLENGE_TAG: &str = "BIP0340/challenge";
+
+/// The length of a BIP340 signature.
+pub const SIGNATURE_SIZE: usize = 64;
+
+/// A BIP340 signature: the X-coordinate of the nonce point `R`, followed by the scalar `s`.
+///
+/// The signature is only parsed when it is verified, any 64 bytes make a `Signature64`.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Signature64([u8; SIGNATURE_SIZE]);
+
+impl Signature64 {
+    /// Creates a signature from its 64 bytes.
+    pub fn from_byte_array(bytes: [u8; SIGNATURE_SIZE]) -> Signature64 {
+        Signature64(bytes)
+    }
+
+    /// Creates a signature from a slice, which must be 64 bytes long.
+    pub fn from_slice(bytes: &[u8]) -> Result<Signature64, CryptoError> {
+        let bytes = bytes
+            .try_into()
+            .map_err(|_| CryptoError::InvalidSignature)?;
+        Ok(Signature64(bytes))
+    }
+
+    /// Returns the 64 bytes of the signature.
+    pub fn to_byte_array(self) -> [u8; SIGNATURE_SIZE] {
+        self.0
+    }
+
+    /// Returns a reference to the 64 bytes of the signature.
+    pub fn as_byte_array(&self) -> &[u8; SIGNATURE_SIZE] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for Signature64 {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Signature64 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl fmt::Display for Signature64 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::LowerHex::fmt(&self.0.as_hex(), f)
+    }
+}
+
+impl From<k256::schnorr::Signature> for Signature64 {
+    fn from(signature: k256::schnorr::Signature) -> Signature64 {
+        Signature64(signature.to_bytes())
+    }
+}
+
+impl TryFrom<Signature64> for k256::schnorr::Signature {
+    type Error = CryptoError;
+
+    fn try_from(signature: Signature64) -> Result<Self, Self::Error> {
+        k256::schnorr::Signature::try_from(&signature.0[..])
+            .map_err(|_| CryptoError::InvalidSignature)
+    }
+}
+
+/// Signs `msg` with `keypair`, as specified by BIP340.
+///
+/// `aux_rand` should be 32 fresh random bytes. They protect the signature against side channel
+/// attacks, but the nonce is derived from the secret key and the message too, so signing stays
+/// safe with all-zero or reused auxiliary randomness. Runs in constant time with respect to the
+/// secret key and the nonce.
+#[must_use]
+pub fn sign_schnorr(msg: &Message, keypair: &Keypair, aux_rand: &[u8; 32]) -> Signature64 {
+    sign(msg.as_ref(), Scalar::from(keypair.secret_key()), aux_rand)
+}
+
+/// Verifies that `signature` is a signature of `msg` by `pubkey`, as specified by BIP340.
+///
+/// # Errors
+///
+/// [`CryptoError::InvalidSignature`] if the signature is malformed, and
+/// [`CryptoError::IncorrectSignature`] if it doesn't match the message or the key.
+pub fn verify_schnorr(
+    signature: &Signature64,
+    msg: &Message,
+    pubkey: &XOnlyPublicKey,
+) -> Result<(), CryptoError> {
+    verify(signature, msg.as_ref(), pubkey)
+}
+
+/// Signs `msg`, which BIP340 allows to be of any length, with `secret`.
+fn sign(msg: &[u8], secret: Scalar, aux_rand: &[u8; 32]) -> Signature64 {
+    let (pubkey, parity) = (secret * G).x_only_public_key();
+    let secret = secret.negate_if(parity);
+
+    let mask = tagged_hash(AUX_TAG, &[aux_rand]).to_byte_array();
+    let mut masked = secret.serialize();
+    masked
+        .iter_mut()
+        .zip(mask)
+        .for_each(|(byte, mask)| *byte ^= mask);
+    let nonce = tagged_hash(NONCE_TAG, &[&masked, &pubkey.serialize(), msg]);
+    let nonce = MaybeScalar::reduce_from(&nonce.to_byte_array())
+        .not_zero()
+        .expect("a nonce hash of zero or the curve order is practically impossible");
+
+    let (nonce_point, nonce_parity) = (nonce * G).x_only_public_key();
+    let nonce = nonce.negate_if(nonce_parity);
+    let challenge = tagged_hash_to_scalar(
+        CHALLENGE_TAG,
+        &[&nonce_point.serialize(), &pubkey.serialize(), msg],
+    );
+
+    let mut bytes = [0u8; SIGNATURE_SIZE];
+    bytes[..32].copy_from_slice(&nonce_point.serialize());
+    bytes[32..].copy_from_slice(&(nonce + challenge * secret).serialize());
+    Signature64(bytes)
+}
+
+/// Verifies a signature of `msg`, which BIP340 allows to be of any length.
+fn verify(signature: &Signature64, msg: &[u8], pubkey: &XOnlyPublicKey) -> Result<(), CryptoError> {
+    let (r, s) = signature.0.split_at(32);
+    let s = MaybeScalar::from_slice(s).map_err(|_| CryptoError::InvalidSignature)?;
+    let challenge = tagged_hash_to_scalar(CHALLENGE_TAG, &[r, &pubkey.serialize(), msg]);
+
+    // An R value of at least the field size can't match the X-coordinate of any point, there is
+    // no need to check it separately.
+    match s * G - challenge * pubkey.lift_x() {
+        MaybePublicKey::Valid(nonce) if nonce.has_even_y() && nonce.serialize_xonly() == r => {
+            Ok(())
+        }
+        _ => Err(CryptoError::IncorrectSignature),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hex::test_hex_unwrap as hex;
+
+    use super::*;
+    use crate::crypto::key::{TapTweak, UntweakedPublicKey};
+    use crate::taproot::TapNodeHash;
+
+    /// A row of the BIP340 test vectors, without the comment column.
+    struct Vector {
+        index: usize,
+        secret: &'static str,
+        pubkey: &'static str,
+        aux_rand: &'static str,
+        msg: &'static str,
+        signature: &'static str,
+        valid: bool,
+    }
+
+    // https://github.com/bitcoin/bips/blob/master/bip-0340/test-vectors.csv
+    #[rustfmt::skip]
+    const VECTORS: &[Vector] = &[
+        Vector { index: 0, secret: "0000000000000000000000000000000000000000000000000000000000000003", pubkey: "F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9", aux_rand: "0000000000000000000000000000000000000000000000000000000000000000", msg: "0000000000000000000000000000000000000000000000000000000000000000", signature: "E907831F80848D1069A5371B402410364BDF1C5F8307B0084C55F1CE2DCA821525F66A4A85EA8B71E482A74F382D2CE5EBEEE8FDB2172F477DF4900D310536C0", valid: true },
+        Vector { index: 1, secret: "B7E151628AED2A6ABF7158809CF4F3C762E7160F38B4DA56A784D9045190CFEF", pubkey: "DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659", aux_rand: "0000000000000000000000000000000000000000000000000000000000000001", msg: "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89", signature: "6896BD60EEAE296DB48A229FF71DFE071BDE413E6D43F917DC8DCF8C78DE33418906D11AC976ABCCB20B091292BFF4EA897EFCB639EA871CFA95F6DE339E4B0A", valid: true },
+        Vector { index: 2, secret: "C90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74020BBEA63B14E5C9", pubkey: "DD308AFEC5777E13121FA72B9CC1B7CC0139715309B086C960E18FD969774EB8", aux_rand: "C87AA53824B4D7AE2EB035A2B5BBBCCC080E76CDC6D1692C4B0B62D798E6D906", msg: "7E2D58D8B3BCDF1ABADEC7829054F90DDA9805AAB56C77333024B9D0A508B75C", signature: "5831AAEED7B44BB74E5EAB94BA9D4294C49BCF2A60728D8B4C200F50DD313C1BAB745879A5AD954A72C45A91C3A51D3C7ADEA98D82F8481E0E1E03674A6F3FB7", valid: true },
+        Vector { index: 3, secret: "0B432B2677937381AEF05BB02A66ECD012773062CF3FA2549E44F58ED2401710", pubkey: "25D1DFF95105F5253C4022F628A996AD3A0D95FBF21D468A1B33F8C160D8F517", aux_rand: "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF", msg: "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF", signature: "7EB0509757E246F19449885651611CB965ECC1A187DD51B64FDA1EDC9637D5EC97582B9CB13DB3933705B32BA982AF5AF25FD78881EBB32771FC5922EFC66EA3", valid: true },
+        Vector { index: 4, secret: "", pubkey: "D69C3509BB99E412E68B0FE8544E72837DFA30746D8BE2AA65975F29D22DC7B9", aux_rand: "", msg: "4DF3C3F68FCC83B27E9D42C90431A72499F17875C81A599B566C9889B9696703", signature: "00000000000000000000003B78CE563F89A0ED9414F5AA28AD0D96D6795F9C6376AFB1548AF603B3EB45C9F8207DEE1060CB71C04E80F593060B07D28308D7F4", valid: true },
+        Vector { index: 5, secret: "", pubkey: "EEFDEA4CDB677750A420FEE807EACF21EB9898AE79B9768766E4FAA04A2D4A34", aux_rand: "", msg: "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89", signature: "6CFF5C3BA86C69EA4B7376F31A9BCB4F74C1976089B2D9963DA2E5543E17776969E89B4C5564D00349106B8497785DD7D1D713A8AE82B32FA79D5F7FC407D39B", valid: false },
+        Vector { index: 6, secret: "", pubkey: "DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659", aux_rand: "", msg: "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89", signature: "FFF97BD5755EEEA420453A14355235D382F6472F8568A18B2F057A14602975563CC27944640AC607CD107AE10923D9EF7A73C643E166BE5EBEAFA34B1AC553E2", valid: false },
+        Vector { index: 7, secret: "", pubkey: "DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659", aux_rand: "", msg: "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89", signature: "1FA62E331EDBC21C394792D2AB1100A7B432B013DF3F6FF4F99FCB33E0E1515F28890B3EDB6E7189B630448B515CE4F8622A954CFE545735AAEA5134FCCDB2BD", valid: false },
+        Vector { index: 8, secret: "", pubkey: "DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659", aux_rand: "", msg: "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89", signature: "6CFF5C3BA86C69EA4B7376F31A9BCB4F74C1976089B2D9963DA2E5543E177769961764B3AA9B2FFCB6EF947B6887A226E8D7C93E00C5ED0C1834FF0D0C2E6DA6", valid: false },
+        Vector { index: 9, secret: "", pubkey: "DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659", aux_rand: "", msg: "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89", signature: "0000000000000000000000000000000000000000000000000000000000000000123DDA8328AF9C23A94C1FEECFD123BA4FB73476F0D594DCB65C6425BD186051", valid: false },
+        Vector { index: 10, secret: "", pubkey: "DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659", aux_rand: "", msg: "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89", signature: "00000000000000000000000000000000000000000000000000000000000000017615FBAF5AE28864013C099742DEADB4DBA87F11AC6754F93780D5A1837CF197", valid: false },
+        Vector { index: 11, secret: "", pubkey: "DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659", aux_rand: "", msg: "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89", signature: "4A298DACAE57395A15D0795DDBFD1DCB564DA82B0F269BC70A74F8220429BA1D69E89B4C5564D00349106B8497785DD7D1D713A8AE82B32FA79D5F7FC407D39B", valid: false },
+        Vector { index: 12, secret: "", pubkey: "DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659", aux_rand: "", msg: "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89", signature: "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEFFFFFC2F69E89B4C5564D00349106B8497785DD7D1D713A8AE82B32FA79D5F7FC407D39B", valid: false },
+        Vector { index: 13, secret: "", pubkey: "DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659", aux_rand: "", msg: "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89", signature: "6CFF5C3BA86C69EA4B7376F31A9BCB4F74C1976089B2D9963DA2E5543E177769FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141", valid: false },
+        Vector { index: 14, secret: "", pubkey: "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEFFFFFC30", aux_rand: "", msg: "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89", signature: "6CFF5C3BA86C69EA4B7376F31A9BCB4F74C1976089B2D9963DA2E5543E17776969E89B4C5564D00349106B8497785DD7D1D713A8AE82B32FA79D5F7FC407D39B", valid: false },
+        Vector { index: 15, secret: "0340034003400340034003400340034003400340034003400340034003400340", pubkey: "778CAA53B4393AC467774D09497A87224BF9FAB6F6E68B23086497324D6FD117", aux_rand: "0000000000000000000000000000000000000000000000000000000000000000", msg: "", signature: "71535DB165ECD9FBBC046E5FFAEA61186BB6AD436732FCCC25291A55895464CF6069CE26BF03466228F19A3A62DB8A649F2D560FAC652827D1AF0574E427AB63", valid: true },
+        Vector { index: 16, secret: "0340034003400340034003400340034003400340034003400340034003400340", pubkey: "778CAA53B4393AC467774D09497A87224BF9FAB6F6E68B23086497324D6FD117", aux_rand: "0000000000000000000000000000000000000000000000000000000000000000", msg: "11", signature: "08A20A0AFEF64124649232E0693C583AB1B9934AE63B4C3511F3AE1134C6A303EA3173BFEA6683BD101FA5AA5DBC1996FE7CACFC5A577D33EC14564CEC2BACBF", valid: true },
+        Vector { index: 17, secret: "0340034003400340034003400340034003400340034003400340034003400340", pubkey: "778CAA53B4393AC467774D09497A87224BF9FAB6F6E68B23086497324D6FD117", aux_rand: "0000000000000000000000000000000000000000000000000000000000000000", msg: "0102030405060708090A0B0C0D0E0F1011", signature: "5130F39A4059B43BC7CAC09A19ECE52B5D8699D1A71E3C52DA9AFDB6B50AC370C4A482B77BF960F8681540E25B6771ECE1E5A37FD80E5A51897C5566A97EA5A5", valid: true },
+        Vector { index: 18, secret: "0340034003400340034003400340034003400340034003400340034003400340", pubkey: "778CAA53B4393AC467774D09497A87224BF9FAB6F6E68B23086497324D6FD117", aux_rand: "0000000000000000000000000000000000000000000000000000000000000000", msg: "99999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999", signature: "403B12B0D8555A344175EA7EC746566303321E5DBFA8BE6F091635163ECA79A8585ED3E3170807E7C03B720FC54C7B23897FCBA0E9D0B4A06894CFD249F22367", valid: true },
+    ];
+
+    #[test]
+    fn bip340_vectors() {
+        for vector in VECTORS {
+            let msg = hex!(vector.msg);
+            let signature = Signature64::from_slice(&hex!(vector.signature)).unwrap();
+
+            if !vector.secret.is_empty() {
+                let secret = Scalar::from_slice(&hex!(vector.secret)).unwrap();
+                let aux_rand = hex!(vector.aux_rand).try_into().unwrap();
+                assert_eq!(
+                    (secret * G).serialize_xonly()[..],
+                    hex!(vector.pubkey)[..],
+                    "vector {}",
+                    vector.index
+                );
+                assert_eq!(
+                    sign(&msg, secret, &aux_rand),
+                    signature,
+                    "vector {}",
+                    vector.index
+                );
+            }
+
+            // The public keys of vectors 5 and 14 are not on the curve.
+            let valid = match XOnlyPublicKey::from_slice(&hex!(vector.pubkey)) {
+                Ok(pubkey) => verify(&signature, &msg, &pubkey).is_ok(),
+                Err(_) => false,
+            };
+            assert_eq!(valid, vector.valid, "vector {}", vector.index);
+        }
+    }
+
+    #[test]
+    fn sign_and_verify() {
+        let keypair = Keypair::from_seckey_str(
+            "b7e151628aed2a6abf7158809cf4f3c762e7160f38b4da56a784d9045190cfef",
+        )
+        .unwrap();
+        let (pubkey, _) = keypair.x_only_public_key();
+        let msg = Message::from_digest([0xab; 32]);
+
+        let signature = sign_schnorr(&msg, &keypair, &[7; 32]);
+        assert_eq!(verify_schnorr(&signature, &msg, &pubkey), Ok(()));
+        assert_ne!(sign_schnorr(&msg, &keypair, &[8; 32]), signature);
+        assert_eq!(
+            verify_schnorr(&signature, &Message::from_digest([0xac; 32]), &pubkey),
+            Err(CryptoError::IncorrectSignature)
+        );
+
+        // Agrees with k256.
+        let k256_signature = k256::schnorr::Signature::try_from(signature).unwrap();
+        assert_eq!(Signature64::from(k256_signature), signature);
+    }
+
+    #[test]
+    fn taproot_key_path() {
+        // The key path spend of an output committing to a script tree.
+        let keypair = Keypair::from_seckey_str(
+            "c90fdaa22168c234c4c6628b80dc1cd129024e088a67cc74020bbea63b14e5c9",
+        )
+        .unwrap();
+        let merkle_root = Some(TapNodeHash::from_byte_array([0x42; 32]));
+        let (internal_key, _): (UntweakedPublicKey, _) = keypair.x_only_public_key();
+        let (output_key, _) = internal_key.tap_tweak(merkle_root);
+
+        let tweaked = keypair.tap_tweak(merkle_root).to_inner();
+        assert_eq!(tweaked.x_only_public_key().0, output_key.to_inner());
+
+        let sighash = Message::from_digest([0x5a; 32]);
+        let signature = sign_schnorr(&sighash, &tweaked, &[0; 32]);
+        assert_eq!(
+            verify_schnorr(&signature, &sighash, &output_key.to_inner()),
+            Ok(())
+        );
+        assert!(verify_schnorr(&signature, &sighash, &internal_key).is_err());
+    }
+}
@@ -0,0 +1,219 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! The scrypt password-based key derivation function (RFC 7914).
+//!
+//! scrypt makes brute forcing a passphrase expensive in both time and memory. It derives the key
+//! encrypting the extended private keys of a [`WalletBackup`](crate::backup::WalletBackup). The
+//! derivation itself is done by the [`scrypt`](::scrypt) crate.
+//!
+//! The cost is set by [`ScryptParams`]. Deriving a key with the [default](ScryptParams::default)
+//! parameters takes 32 MiB of memory and about a tenth of a second on a desktop CPU, which is
+//! as much as an interactive user should be made to wait. Run
+//! `RUSTFLAGS='--cfg=bench' cargo +nightly bench scrypt` to measure other parameters on the
+//! target hardware.
+//!
+
+use core::fmt;
+
+/// The cost parameters of scrypt.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub struct ScryptParams {
+    log_n: u8,
+    r: u32,
+    p: u32,
+}
+
+impl ScryptParams {
+    /// The default parameters, suitable for interactive use.
+    pub const INTERACTIVE: ScryptParams = ScryptParams {
+        log_n: 15,
+        r: 8,
+        p: 1,
+    };
+
+    /// Creates parameters with a CPU/memory cost of `2^log_n`, a block size of `r` and a
+    /// parallelization of `p`.
+    ///
+    /// Derivations need `128 * r * 2^log_n` bytes of memory, and `p` times as long as a single
+    /// derivation with the same `log_n` and `r`.
+    ///
+    /// Any parameters allowed by RFC 7914 are accepted, which includes derivations taking
+    /// terabytes of memory. Bound parameters read from untrusted data before using them.
+    pub fn new(log_n: u8, r: u32, p: u32) -> Result<ScryptParams, ScryptParamsError> {
+        if r == 0 || p == 0 {
+            return Err(ScryptParamsError::ZeroBlockSizeOrParallelization);
+        }
+        // RFC 7914 requires `N < 2^(128 * r / 8)` and `p * r < 2^30`.
+        if log_n == 0 || u64::from(log_n) >= 16 * u64::from(r) {
+            return Err(ScryptParamsError::InvalidCost(log_n));
+        }
+        if u64::from(p) * u64::from(r) >= 1 << 30 {
+            return Err(ScryptParamsError::TooLarge);
+        }
+        // The memory for the `2^log_n` blocks must be addressable.
+        to_params(log_n, r, p).map_err(|_| ScryptParamsError::TooLarge)?;
+        Ok(ScryptParams { log_n, r, p })
+    }
+
+    /// Returns the base 2 logarithm of the CPU/memory cost.
+    pub fn log_n(&self) -> u8 {
+        self.log_n
+    }
+
+    /// Returns the block size.
+    pub fn r(&self) -> u32 {
+        self.r
+    }
+
+    /// Returns the parallelization.
+    pub fn p(&self) -> u32 {
+        self.p
+    }
+}
+
+impl Default for ScryptParams {
+    fn default() -> Self {
+        ScryptParams::INTERACTIVE
+    }
+}
+
+/// Fills `output` with the scrypt derivation of `password` and `salt`.
+///
+/// # Panics
+///
+/// If `output` is empty.
+pub fn scrypt(password: &[u8], salt: &[u8], params: ScryptParams, output: &mut [u8]) {
+    let params =
+        to_params(params.log_n, params.r, params.p).expect("ScryptParams are always valid");
+    ::scrypt::scrypt(password, salt, &params, output).expect("output must not be empty");
+}
+
+/// Converts the parameters to the ones of the [`scrypt`](::scrypt) crate.
+fn to_params(
+    log_n: u8,
+    r: u32,
+    p: u32,
+) -> Result<::scrypt::Params, ::scrypt::errors::InvalidParams> {
+    // The output length is only used by the password hashing API of the crate, not by the
+    // derivation.
+    ::scrypt::Params::new(log_n, r, p, ::scrypt::Params::RECOMMENDED_LEN)
+}
+
+/// Invalid scrypt parameters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ScryptParamsError {
+    /// The cost is zero or too large for the block size.
+    InvalidCost(u8),
+    /// The block size or the parallelization is zero.
+    ZeroBlockSizeOrParallelization,
+    /// The parameters need more memory than can be addressed.
+    TooLarge,
+}
+
+internals::impl_from_infallible!(ScryptParamsError);
+
+impl fmt::Display for ScryptParamsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use ScryptParamsError::*;
+
+        match *self {
+            InvalidCost(log_n) => write!(f, "invalid scrypt cost 2^{}", log_n),
+            ZeroBlockSizeOrParallelization => {
+                f.write_str("scrypt block size and parallelization must be non-zero")
+            }
+            TooLarge => f.write_str("scrypt parameters too large"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ScryptParamsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use ScryptParamsError::*;
+
+        match *self {
+            InvalidCost(_) | ZeroBlockSizeOrParallelization | TooLarge => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hex::test_hex_unwrap as hex;
+
+    use super::*;
+
+    #[test]
+    fn params() {
+        assert_eq!(ScryptParams::new(15, 8, 1), Ok(ScryptParams::INTERACTIVE));
+        assert_eq!(ScryptParams::default().log_n(), 15);
+        assert_eq!(
+            ScryptParams::new(0, 8, 1),
+            Err(ScryptParamsError::InvalidCost(0))
+        );
+        assert_eq!(
+            ScryptParams::new(16, 1, 1),
+            Err(ScryptParamsError::InvalidCost(16))
+        );
+        assert_eq!(
+            ScryptParams::new(10, 0, 1),
+            Err(ScryptParamsError::ZeroBlockSizeOrParallelization)
+        );
+        assert_eq!(
+            ScryptParams::new(10, 1 << 15, 1 << 15),
+            Err(ScryptParamsError::TooLarge)
+        );
+    }
+
+    #[test]
+    fn rfc7914_vectors() {
+        let mut output = [0u8; 64];
+        scrypt(b"", b"", ScryptParams::new(4, 1, 1).unwrap(), &mut output);
+        assert_eq!(
+            output[..],
+            hex!("77d6576238657b203b19ca42c18a0497f16b4844e3074ae8dfdffa3fede21442fcd0069ded0948f8326a753a0fc81f17e8d3e0fb2e0d3628cf35e20c38d18906")[..]
+        );
+        scrypt(
+            b"password",
+            b"NaCl",
+            ScryptParams::new(10, 8, 16).unwrap(),
+            &mut output,
+        );
+        assert_eq!(
+            output[..],
+            hex!("fdbabe1c9d3472007856e7190d01e9fe7c6ad7cbc8237830e77376634b3731622eaf30d92e22a3886ff109279d9830dac727afb94a83ee6d8360cbdfa2cc0640")[..]
+        );
+    }
+}
+
+#[cfg(bench)]
+mod benches {
+    use test::{black_box, Bencher};
+
+    use super::*;
+
+    fn bench_params(bh: &mut Bencher, log_n: u8) {
+        let params = ScryptParams::new(log_n, 8, 1).unwrap();
+        let mut output = [0u8; 32];
+        bh.iter(|| {
+            scrypt(b"passphrase", b"salt", params, &mut output);
+            black_box(&output);
+        });
+    }
+
+    #[bench]
+    pub fn scrypt_log_n_12(bh: &mut Bencher) {
+        bench_params(bh, 12)
+    }
+
+    #[bench]
+    pub fn scrypt_log_n_14(bh: &mut Bencher) {
+        bench_params(bh, 14)
+    }
+
+    #[bench]
+    pub fn scrypt_interactive(bh: &mut Bencher) {
+        bench_params(bh, ScryptParams::INTERACTIVE.log_n)
+    }
+}
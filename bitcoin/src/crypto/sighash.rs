@@ -10,6 +10,11 @@
 //! Computing signature hashes is required to sign a transaction and this module is designed to
 //! handle its complexity efficiently. Computing these hashes is as simple as creating
 //! [`SighashCache`] and calling its methods.
+//!
+//! The signed data is streamed into the hash engine, or any [`io::Write`] with the
+//! `*_encode_signing_data_to` methods, instead of being materialized for every input. Segwit v0
+//! and taproot signature hashes reuse the hashes of the prevouts, sequences and outputs cached by
+//! the [`SighashCache`], so signing all the inputs of a large transaction takes linear time.
 
 use core::{fmt, str};
 
@@ -19,9 +24,10 @@ use io::Write;
 
 use crate::blockdata::witness::Witness;
 use crate::consensus::{encode, Encodable};
+use crate::crypto::hashes::{tagged_engine_with, EngineWriter, Sha256Engine};
 use crate::taproot::{LeafVersion, TapLeafHash, TAPROOT_ANNEX_PREFIX};
 use crate::{impl_thirty_two_byte_hash, prelude::*};
-use crate::{transaction, Amount, Script, ScriptBuf, Sequence, Transaction, TxIn, TxOut};
+use crate::{transaction, Amount, Script, Sequence, Transaction, TxOut, VarInt};
 
 /// Used for signature hash for invalid use of SIGHASH_SINGLE.
 #[rustfmt::skip]
@@ -318,17 +324,7 @@ impl<'s> ScriptPath<'s> {
     }
     /// Computes the leaf hash for this `ScriptPath`.
     pub fn leaf_hash(&self) -> TapLeafHash {
-        let mut enc = TapLeafHash::engine();
-
-        self.leaf_version
-            .to_consensus()
-            .consensus_encode(&mut enc)
-            .expect("writing to hash enging should never fail");
-        self.script
-            .consensus_encode(&mut enc)
-            .expect("writing to hash enging should never fail");
-
-        TapLeafHash::from_engine(enc)
+        TapLeafHash::from_script(self.script, self.leaf_version)
     }
 }
 
@@ -473,6 +469,12 @@ impl EcdsaSighashType {
     }
 }
 
+impl From<EcdsaSighashType> for u32 {
+    fn from(t: EcdsaSighashType) -> u32 {
+        t.to_u32()
+    }
+}
+
 impl From<EcdsaSighashType> for TapSighashType {
     fn from(s: EcdsaSighashType) -> Self {
         use TapSighashType::*;
@@ -621,6 +623,12 @@ impl<R: Borrow<Transaction>> SighashCache<R> {
 
         let (sighash, anyone_can_pay) = sighash_type.split_anyonecanpay_flag();
 
+        // Checked before anything is written, so a failed call leaves `writer` untouched.
+        if sighash == TapSighashType::Single {
+            self.check_single_output(input_index)
+                .map_err(SigningDataError::sighash)?;
+        }
+
         // epoch
         0u8.consensus_encode(writer)?;
 
@@ -706,18 +714,7 @@ impl<R: Borrow<Transaction>> SighashCache<R> {
         //      sha_single_output (32): the SHA256 of the corresponding output in CTxOut format.
         if sighash == TapSighashType::Single {
             let mut enc = sha256::Hash::engine();
-            self.tx
-                .borrow()
-                .output
-                .get(input_index)
-                .ok_or(TaprootError::SingleMissingOutput(
-                    SingleMissingOutputError {
-                        input_index,
-                        outputs_length: self.tx.borrow().output.len(),
-                    },
-                ))
-                .map_err(SigningDataError::Sighash)?
-                .consensus_encode(&mut enc)?;
+            self.tx.borrow().output[input_index].consensus_encode(&mut enc)?;
             let hash = sha256::Hash::from_engine(enc);
             hash.consensus_encode(writer)?;
         }
@@ -757,6 +754,33 @@ impl<R: Borrow<Transaction>> SighashCache<R> {
         Ok(TapSighash::from_engine(enc))
     }
 
+    /// Computes the BIP341 sighash for any flag type with the SHA256 engine `E`.
+    ///
+    /// Behaves like [`SighashCache::taproot_signature_hash`], the hashes of the transaction parts
+    /// cached by `self` are still computed with the default engine.
+    pub fn taproot_signature_hash_with<E: Sha256Engine, T: Borrow<TxOut>>(
+        &mut self,
+        input_index: usize,
+        prevouts: &Prevouts<T>,
+        annex: Option<Annex>,
+        leaf_hash_code_separator: Option<(TapLeafHash, u32)>,
+        sighash_type: TapSighashType,
+    ) -> Result<TapSighash, TaprootError> {
+        let mut writer = EngineWriter(tagged_engine_with::<E>("TapSighash"));
+        self.taproot_encode_signing_data_to(
+            &mut writer,
+            input_index,
+            prevouts,
+            annex,
+            leaf_hash_code_separator,
+            sighash_type,
+        )
+        .map_err(SigningDataError::unwrap_sighash)?;
+        Ok(TapSighash::from_byte_array(
+            writer.0.finalize().to_byte_array(),
+        ))
+    }
+
     /// Computes the BIP341 sighash for a key spend.
     pub fn taproot_key_spend_signature_hash<T: Borrow<TxOut>>(
         &mut self,
@@ -807,17 +831,27 @@ impl<R: Borrow<Transaction>> SighashCache<R> {
     /// `script_code` is dependent on the type of the spend transaction. For p2wpkh use
     /// [`Script::p2wpkh_script_code`], for p2wsh just pass in the witness script. (Also see
     /// [`Self::p2wpkh_signature_hash`] and [`SighashCache::p2wsh_signature_hash`].)
-    pub fn segwit_v0_encode_signing_data_to<W: Write + ?Sized>(
+    ///
+    /// Matching consensus, `SIGHASH_SINGLE` without an output at `input_index` commits to a zero
+    /// hash in place of the output. The higher level signature hash functions return
+    /// [`SingleMissingOutputError`] instead, since such a signature commits to no output at all.
+    ///
+    /// The `sighash_type` supports an arbitrary `u32` value, instead of just [`EcdsaSighashType`],
+    /// so that signatures with non-standard sighash types can be verified: the value is committed
+    /// to as is, and interpreted as in [`EcdsaSighashType::from_consensus`].
+    pub fn segwit_v0_encode_signing_data_to<W: Write + ?Sized, U: Into<u32>>(
         &mut self,
         writer: &mut W,
         input_index: usize,
         script_code: &Script,
         value: Amount,
-        sighash_type: EcdsaSighashType,
+        sighash_type: U,
     ) -> Result<(), SigningDataError<transaction::InputsIndexError>> {
         let zero_hash = sha256d::Hash::all_zeros();
 
-        let (sighash, anyone_can_pay) = sighash_type.split_anyonecanpay_flag();
+        let sighash_type: u32 = sighash_type.into();
+        let (sighash, anyone_can_pay) =
+            EcdsaSighashType::from_consensus(sighash_type).split_anyonecanpay_flag();
 
         self.tx.borrow().version.consensus_encode(writer)?;
 
@@ -861,7 +895,7 @@ impl<R: Borrow<Transaction>> SighashCache<R> {
         }
 
         self.tx.borrow().lock_time.consensus_encode(writer)?;
-        sighash_type.to_u32().consensus_encode(writer)?;
+        sighash_type.consensus_encode(writer)?;
         Ok(())
     }
 
@@ -879,6 +913,9 @@ impl<R: Borrow<Transaction>> SighashCache<R> {
         let script_code = script_pubkey
             .p2wpkh_script_code()
             .ok_or(P2wpkhError::NotP2wpkhScript)?;
+        if sighash_type.split_anyonecanpay_flag().0 == EcdsaSighashType::Single {
+            self.check_single_output(input_index)?;
+        }
 
         let mut enc = SegwitV0Sighash::engine();
         self.segwit_v0_encode_signing_data_to(
@@ -893,13 +930,22 @@ impl<R: Borrow<Transaction>> SighashCache<R> {
     }
 
     /// Computes the BIP143 sighash to spend a p2wsh transaction for any flag type.
+    ///
+    /// # Errors
+    ///
+    /// [`P2wshError::SingleMissingOutput`] for `SIGHASH_SINGLE` without an output at the index of
+    /// the input, besides the [`P2wshError::Sighash`] of an input index out of range.
     pub fn p2wsh_signature_hash(
         &mut self,
         input_index: usize,
         witness_script: &Script,
         value: Amount,
         sighash_type: EcdsaSighashType,
-    ) -> Result<SegwitV0Sighash, transaction::InputsIndexError> {
+    ) -> Result<SegwitV0Sighash, P2wshError> {
+        if sighash_type.split_anyonecanpay_flag().0 == EcdsaSighashType::Single {
+            self.check_single_output(input_index)?;
+        }
+
         let mut enc = SegwitV0Sighash::engine();
         self.segwit_v0_encode_signing_data_to(
             &mut enc,
@@ -969,66 +1015,50 @@ impl<R: Borrow<Transaction>> SighashCache<R> {
             let (sighash, anyone_can_pay) =
                 EcdsaSighashType::from_consensus(sighash_type).split_anyonecanpay_flag();
 
-            // Build tx to sign
-            let mut tx = Transaction {
-                version: self_.version,
-                lock_time: self_.lock_time,
-                input: vec![],
-                output: vec![],
-            };
+            // Encode the tx to sign without building it, the inputs other than `input_index` have
+            // an empty script sig and no witness is encoded.
+            self_.version.consensus_encode(writer)?;
             // Add all inputs necessary..
-            if anyone_can_pay {
-                tx.input = vec![TxIn {
-                    previous_output: self_.input[input_index].previous_output,
-                    script_sig: script_pubkey.to_owned(),
-                    sequence: self_.input[input_index].sequence,
-                    witness: Witness::default(),
-                }];
+            let inputs = if anyone_can_pay {
+                input_index..input_index + 1
             } else {
-                tx.input = Vec::with_capacity(self_.input.len());
-                for (n, input) in self_.input.iter().enumerate() {
-                    tx.input.push(TxIn {
-                        previous_output: input.previous_output,
-                        script_sig: if n == input_index {
-                            script_pubkey.to_owned()
-                        } else {
-                            ScriptBuf::new()
-                        },
-                        sequence: if n != input_index
-                            && (sighash == EcdsaSighashType::Single
-                                || sighash == EcdsaSighashType::None)
-                        {
-                            Sequence::ZERO
-                        } else {
-                            input.sequence
-                        },
-                        witness: Witness::default(),
-                    });
+                0..self_.input.len()
+            };
+            VarInt::from(inputs.len()).consensus_encode(writer)?;
+            for n in inputs {
+                let input = &self_.input[n];
+                input.previous_output.consensus_encode(writer)?;
+                if n == input_index {
+                    encode::consensus_encode_with_size(script_pubkey.as_bytes(), writer)?;
+                } else {
+                    VarInt(0).consensus_encode(writer)?;
                 }
+                let sequence = if n != input_index
+                    && (sighash == EcdsaSighashType::Single || sighash == EcdsaSighashType::None)
+                {
+                    Sequence::ZERO
+                } else {
+                    input.sequence
+                };
+                sequence.consensus_encode(writer)?;
             }
             // ..then all outputs
-            tx.output = match sighash {
-                EcdsaSighashType::All => self_.output.clone(),
+            match sighash {
+                EcdsaSighashType::All => self_.output.consensus_encode(writer)?,
                 EcdsaSighashType::Single => {
-                    let output_iter = self_
-                        .output
-                        .iter()
-                        .take(input_index + 1) // sign all outputs up to and including this one, but erase
-                        .enumerate() // all of them except for this one
-                        .map(|(n, out)| {
-                            if n == input_index {
-                                out.clone()
-                            } else {
-                                TxOut::NULL
-                            }
-                        });
-                    output_iter.collect()
+                    // sign all outputs up to and including this one, but erase all of them except
+                    // for this one
+                    VarInt::from(input_index + 1).consensus_encode(writer)?;
+                    for _ in 0..input_index {
+                        TxOut::NULL.consensus_encode(writer)?;
+                    }
+                    self_.output[input_index].consensus_encode(writer)?
                 }
-                EcdsaSighashType::None => vec![],
+                EcdsaSighashType::None => VarInt(0).consensus_encode(writer)?,
                 _ => unreachable!(),
             };
+            self_.lock_time.consensus_encode(writer)?;
             // hash the result
-            tx.consensus_encode(writer)?;
             sighash_type.to_le_bytes().consensus_encode(writer)?;
             Ok(())
         }
@@ -1081,6 +1111,19 @@ impl<R: Borrow<Transaction>> SighashCache<R> {
         }
     }
 
+    /// Checks that there is an output at `input_index` for `SIGHASH_SINGLE` to commit to.
+    fn check_single_output(&self, input_index: usize) -> Result<(), SingleMissingOutputError> {
+        let outputs_length = self.tx.borrow().output.len();
+        if input_index < outputs_length {
+            Ok(())
+        } else {
+            Err(SingleMissingOutputError {
+                input_index,
+                outputs_length,
+            })
+        }
+    }
+
     #[inline]
     fn common_cache(&mut self) -> &CommonCache {
         Self::common_cache_minimal_borrow(&mut self.common_cache, self.tx.borrow())
@@ -1090,26 +1133,10 @@ impl<R: Borrow<Transaction>> SighashCache<R> {
         common_cache: &'a mut Option<CommonCache>,
         tx: &Transaction,
     ) -> &'a CommonCache {
-        common_cache.get_or_insert_with(|| {
-            let mut enc_prevouts = sha256::Hash::engine();
-            let mut enc_sequences = sha256::Hash::engine();
-            for txin in tx.input.iter() {
-                txin.previous_output
-                    .consensus_encode(&mut enc_prevouts)
-                    .unwrap();
-                txin.sequence.consensus_encode(&mut enc_sequences).unwrap();
-            }
-            CommonCache {
-                prevouts: sha256::Hash::from_engine(enc_prevouts),
-                sequences: sha256::Hash::from_engine(enc_sequences),
-                outputs: {
-                    let mut enc = sha256::Hash::engine();
-                    for txout in tx.output.iter() {
-                        txout.consensus_encode(&mut enc).unwrap();
-                    }
-                    sha256::Hash::from_engine(enc)
-                },
-            }
+        common_cache.get_or_insert_with(|| CommonCache {
+            prevouts: sha_encoded(tx.input.iter().map(|txin| &txin.previous_output)),
+            sequences: sha_encoded(tx.input.iter().map(|txin| &txin.sequence)),
+            outputs: sha_encoded(&tx.output),
         })
     }
 
@@ -1127,25 +1154,13 @@ impl<R: Borrow<Transaction>> SighashCache<R> {
     }
 
     fn taproot_cache<T: Borrow<TxOut>>(&mut self, prevouts: &[T]) -> &TaprootCache {
-        self.taproot_cache.get_or_insert_with(|| {
-            let mut enc_amounts = sha256::Hash::engine();
-            let mut enc_script_pubkeys = sha256::Hash::engine();
-            for prevout in prevouts {
-                prevout
-                    .borrow()
-                    .value
-                    .consensus_encode(&mut enc_amounts)
-                    .unwrap();
-                prevout
-                    .borrow()
-                    .script_pubkey
-                    .consensus_encode(&mut enc_script_pubkeys)
-                    .unwrap();
-            }
-            TaprootCache {
-                amounts: sha256::Hash::from_engine(enc_amounts),
-                script_pubkeys: sha256::Hash::from_engine(enc_script_pubkeys),
-            }
+        self.taproot_cache.get_or_insert_with(|| TaprootCache {
+            amounts: sha_encoded(prevouts.iter().map(|prevout| &prevout.borrow().value)),
+            script_pubkeys: sha_encoded(
+                prevouts
+                    .iter()
+                    .map(|prevout| &prevout.borrow().script_pubkey),
+            ),
         })
     }
 }
@@ -1273,6 +1288,12 @@ impl From<transaction::InputsIndexError> for TaprootError {
     }
 }
 
+impl From<SingleMissingOutputError> for TaprootError {
+    fn from(e: SingleMissingOutputError) -> Self {
+        Self::SingleMissingOutput(e)
+    }
+}
+
 impl From<PrevoutsSizeError> for TaprootError {
     fn from(e: PrevoutsSizeError) -> Self {
         Self::PrevoutsSize(e)
@@ -1299,6 +1320,8 @@ pub enum P2wpkhError {
     Sighash(transaction::InputsIndexError),
     /// Script is not a witness program for a p2wpkh output.
     NotP2wpkhScript,
+    /// `SIGHASH_SINGLE` used without an output at the index of the input.
+    SingleMissingOutput(SingleMissingOutputError),
 }
 
 internals::impl_from_infallible!(P2wpkhError);
@@ -1309,6 +1332,12 @@ impl From<transaction::InputsIndexError> for P2wpkhError {
     }
 }
 
+impl From<SingleMissingOutputError> for P2wpkhError {
+    fn from(value: SingleMissingOutputError) -> Self {
+        P2wpkhError::SingleMissingOutput(value)
+    }
+}
+
 impl fmt::Display for P2wpkhError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         use P2wpkhError::*;
@@ -1316,6 +1345,7 @@ impl fmt::Display for P2wpkhError {
         match *self {
             Sighash(ref e) => write_err!(f, "error encoding segwit v0 signing data"; e),
             NotP2wpkhScript => write!(f, "script is not a script pubkey for a p2wpkh output"),
+            SingleMissingOutput(ref e) => write_err!(f, "sighash single"; e),
         }
     }
 }
@@ -1328,6 +1358,54 @@ impl std::error::Error for P2wpkhError {
         match *self {
             Sighash(ref e) => Some(e),
             NotP2wpkhScript => None,
+            SingleMissingOutput(ref e) => Some(e),
+        }
+    }
+}
+
+/// Error computing a P2WSH sighash.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum P2wshError {
+    /// Error computing the sighash.
+    Sighash(transaction::InputsIndexError),
+    /// `SIGHASH_SINGLE` used without an output at the index of the input.
+    SingleMissingOutput(SingleMissingOutputError),
+}
+
+internals::impl_from_infallible!(P2wshError);
+
+impl From<transaction::InputsIndexError> for P2wshError {
+    fn from(value: transaction::InputsIndexError) -> Self {
+        P2wshError::Sighash(value)
+    }
+}
+
+impl From<SingleMissingOutputError> for P2wshError {
+    fn from(value: SingleMissingOutputError) -> Self {
+        P2wshError::SingleMissingOutput(value)
+    }
+}
+
+impl fmt::Display for P2wshError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use P2wshError::*;
+
+        match *self {
+            Sighash(ref e) => write_err!(f, "error encoding segwit v0 signing data"; e),
+            SingleMissingOutput(ref e) => write_err!(f, "sighash single"; e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for P2wshError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use P2wshError::*;
+
+        match *self {
+            Sighash(ref e) => Some(e),
+            SingleMissingOutput(ref e) => Some(e),
         }
     }
 }
@@ -1398,8 +1476,24 @@ impl std::error::Error for AnnexError {
     }
 }
 
+/// Returns the SHA256 of the concatenated consensus encodings of `items`, as the `sha_prevouts`,
+/// `sha_amounts`, `sha_scriptpubkeys`, `sha_sequences` and `sha_outputs` fields of BIP341.
+pub(crate) fn sha_encoded<'a, T, I>(items: I) -> sha256::Hash
+where
+    T: Encodable + 'a,
+    I: IntoIterator<Item = &'a T>,
+{
+    let mut enc = sha256::Hash::engine();
+    for item in items {
+        item.consensus_encode(&mut enc)
+            .expect("engines don't error");
+    }
+    sha256::Hash::from_engine(enc)
+}
+
 fn is_invalid_use_of_sighash_single(sighash: u32, input_index: usize, outputs_len: usize) -> bool {
-    let ty = EcdsaSighashType::from_consensus(sighash);
+    // Like Bitcoin Core, ignore `SIGHASH_ANYONECANPAY`.
+    let (ty, _) = EcdsaSighashType::from_consensus(sighash).split_anyonecanpay_flag();
     ty == EcdsaSighashType::Single && input_index >= outputs_len
 }
 
@@ -1538,6 +1632,7 @@ mod tests {
     use super::*;
     use crate::blockdata::locktime::absolute;
     use crate::consensus::deserialize;
+    use crate::{ScriptBuf, TxIn};
 
     extern crate serde_json;
 
@@ -1560,7 +1655,59 @@ mod tests {
             .expect("sighash");
         let want = LegacySighash::from_slice(&UINT256_ONE).unwrap();
 
-        assert_eq!(got, want)
+        assert_eq!(got, want);
+
+        // The other bits, including SIGHASH_ANYONECANPAY, don't matter.
+        for sighash_type in [0x83, 0x43, 0xe3] {
+            let got = cache
+                .legacy_signature_hash(1, &script, sighash_type)
+                .expect("sighash");
+            assert_eq!(got, want);
+            assert_ne!(
+                cache
+                    .legacy_signature_hash(0, &script, sighash_type)
+                    .unwrap(),
+                want
+            );
+        }
+    }
+
+    #[test]
+    fn segwit_v0_non_standard_sighash_type() {
+        let tx = Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![TxIn::default(), TxIn::default()],
+            output: vec![TxOut::NULL, TxOut::NULL],
+        };
+        let mut cache = SighashCache::new(&tx);
+        let mut encode = |sighash_type: u32| {
+            let mut data = Vec::new();
+            cache
+                .segwit_v0_encode_signing_data_to(
+                    &mut data,
+                    1,
+                    Script::new(),
+                    Amount::ZERO,
+                    sighash_type,
+                )
+                .unwrap();
+            data
+        };
+
+        // A non-standard type is interpreted as the standard one with the same low bits and
+        // SIGHASH_ANYONECANPAY flag, but committed to as is.
+        for (non_standard, standard) in [
+            (0x43, EcdsaSighashType::Single),
+            (0x00, EcdsaSighashType::All),
+            (0xc2, EcdsaSighashType::NonePlusAnyoneCanPay),
+        ] {
+            let got = encode(non_standard);
+            let want = encode(standard.to_u32());
+            let len = want.len();
+            assert_eq!(got[..len - 4], want[..len - 4]);
+            assert_eq!(got[len - 4..], non_standard.to_le_bytes());
+        }
     }
 
     #[test]
@@ -1795,6 +1942,47 @@ mod tests {
                 outputs_length: 0
             }))
         );
+        let mut enc = Vec::new();
+        assert!(c
+            .taproot_encode_signing_data_to(
+                &mut enc,
+                0,
+                &prevout,
+                None,
+                None,
+                TapSighashType::SinglePlusAnyoneCanPay
+            )
+            .is_err());
+        assert!(enc.is_empty());
+
+        let missing = SingleMissingOutputError { input_index: 0, outputs_length: 0 };
+        let p2wpkh = ScriptBuf::from_hex("00140000000000000000000000000000000000000000").unwrap();
+        assert_eq!(
+            c.p2wpkh_signature_hash(0, &p2wpkh, Amount::ZERO, EcdsaSighashType::Single),
+            Err(P2wpkhError::SingleMissingOutput(missing.clone()))
+        );
+        assert_eq!(
+            c.p2wsh_signature_hash(
+                0,
+                Script::new(),
+                Amount::ZERO,
+                EcdsaSighashType::SinglePlusAnyoneCanPay
+            ),
+            Err(P2wshError::SingleMissingOutput(missing))
+        );
+        // The consensus encoding commits to a zero hash instead.
+        let mut enc = Vec::new();
+        c.segwit_v0_encode_signing_data_to(
+            &mut enc,
+            0,
+            Script::new(),
+            Amount::ZERO,
+            EcdsaSighashType::Single,
+        )
+        .unwrap();
+        assert!(c
+            .p2wsh_signature_hash(0, Script::new(), Amount::ZERO, EcdsaSighashType::None)
+            .is_ok());
         assert_eq!(
             c.legacy_signature_hash(10, Script::new(), 0u32),
             Err(InputsIndexError(IndexOutOfBoundsError {
@@ -1860,10 +2048,27 @@ mod tests {
         let mut sighash_cache = SighashCache::new(&tx);
 
         let hash = sighash_cache
-            .taproot_signature_hash(input_index, &prevouts, annex, leaf_hash, sighash_type)
+            .taproot_signature_hash(
+                input_index,
+                &prevouts,
+                annex.clone(),
+                leaf_hash,
+                sighash_type,
+            )
             .unwrap();
         let expected = Vec::from_hex(expected_hash).unwrap();
         assert_eq!(expected, hash.to_byte_array());
+
+        let hash_with = sighash_cache
+            .taproot_signature_hash_with::<sha256::HashEngine, _>(
+                input_index,
+                &prevouts,
+                annex,
+                leaf_hash,
+                sighash_type,
+            )
+            .unwrap();
+        assert_eq!(hash_with, hash);
     }
 
     #[cfg(feature = "serde")]
@@ -2116,14 +2321,13 @@ mod tests {
 
     #[test]
     fn bip143_p2wpkh() {
-        let tx = deserialize::<Transaction>(
-            &hex!(
-                "0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f000000\
-                0000eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a01000000\
-                00ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093\
-                510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac11000000"
-            ),
-        ).unwrap();
+        let tx = deserialize::<Transaction>(&hex!(
+            "0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f0000000000\
+             eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffff\
+             ffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d0000\
+             00001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac11000000"
+        ))
+        .unwrap();
 
         let spk = ScriptBuf::from_hex("00141d0f172a0ecb48aee1be1f2687d2963ae33f71a1").unwrap();
         let value = Amount::from_sat(600_000_000);
@@ -2200,6 +2404,97 @@ mod tests {
         );
     }
 
+    #[test]
+    fn shared_cache() {
+        // The transaction of `bip143_p2wpkh`, spending one output of each kind in turn.
+        let tx = deserialize::<Transaction>(&hex!(
+            "0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f0000000000\
+             eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffff\
+             ffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d0000\
+             00001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac11000000"
+        ))
+        .unwrap();
+        let p2pkh =
+            ScriptBuf::from_hex("76a9141d0f172a0ecb48aee1be1f2687d2963ae33f71a188ac").unwrap();
+        let p2wpkh = ScriptBuf::from_hex("00141d0f172a0ecb48aee1be1f2687d2963ae33f71a1").unwrap();
+        let p2tr = ScriptBuf::from_hex(
+            "51201d0f172a0ecb48aee1be1f2687d2963ae33f71a11d0f172a0ecb48aee1be1f2687",
+        )
+        .unwrap();
+        let value = Amount::from_sat(600_000_000);
+        let prevouts = [
+            TxOut {
+                value,
+                script_pubkey: p2tr.clone(),
+            },
+            TxOut {
+                value,
+                script_pubkey: p2wpkh.clone(),
+            },
+        ];
+        let prevouts = Prevouts::All(&prevouts);
+        let leaf_hash = TapLeafHash::from_script(&p2pkh, LeafVersion::TapScript);
+
+        // The midstates computed for one input are reused for the others.
+        let mut cache = SighashCache::new(&tx);
+        for input_index in 0..2 {
+            let fresh = || SighashCache::new(&tx);
+            assert_eq!(
+                cache.legacy_signature_hash(input_index, &p2pkh, 1).unwrap(),
+                fresh()
+                    .legacy_signature_hash(input_index, &p2pkh, 1)
+                    .unwrap()
+            );
+            for sighash_type in [
+                EcdsaSighashType::All,
+                EcdsaSighashType::NonePlusAnyoneCanPay,
+            ] {
+                assert_eq!(
+                    cache
+                        .p2wpkh_signature_hash(input_index, &p2wpkh, value, sighash_type)
+                        .unwrap(),
+                    fresh()
+                        .p2wpkh_signature_hash(input_index, &p2wpkh, value, sighash_type)
+                        .unwrap()
+                );
+            }
+            for sighash_type in [
+                TapSighashType::Default,
+                TapSighashType::SinglePlusAnyoneCanPay,
+            ] {
+                assert_eq!(
+                    cache
+                        .taproot_key_spend_signature_hash(input_index, &prevouts, sighash_type)
+                        .unwrap(),
+                    fresh()
+                        .taproot_key_spend_signature_hash(input_index, &prevouts, sighash_type)
+                        .unwrap()
+                );
+                let annex = Annex::new(&[TAPROOT_ANNEX_PREFIX, 1]).unwrap();
+                assert_eq!(
+                    cache
+                        .taproot_signature_hash(
+                            input_index,
+                            &prevouts,
+                            Some(annex.clone()),
+                            Some((leaf_hash, 0xFFFFFFFF)),
+                            sighash_type
+                        )
+                        .unwrap(),
+                    fresh()
+                        .taproot_signature_hash(
+                            input_index,
+                            &prevouts,
+                            Some(annex),
+                            Some((leaf_hash, 0xFFFFFFFF)),
+                            sighash_type
+                        )
+                        .unwrap()
+                );
+            }
+        }
+    }
+
     // Note, if you are looking at the test vectors in BIP-143 and wondering why there is a `cf`
     // prepended to all the script_code hex it is the length byte, it gets added when we consensus
     // encode a script.
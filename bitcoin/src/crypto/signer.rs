@@ -0,0 +1,221 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Transaction signers.
+//!
+//! A [`Signer`] signs sighash messages with a key it doesn't have to expose, so that PSBT signing
+//! and wallets can be written once for keys held in memory, by a hardware device or by a remote
+//! key management service. [`SoftwareSigner`] is the in-memory implementation.
+//!
+//! With the `async` feature, [`AsyncSigner`] is the same interface for keys behind an HSM or a
+//! network boundary, where signing is inherently asynchronous, and [`Blocking`] adapts a
+//! [`Signer`] to it.
+//!
+
+use core::convert::Infallible;
+use core::fmt;
+#[cfg(feature = "async")]
+use core::future::Future;
+#[cfg(feature = "async")]
+use core::pin::Pin;
+
+use k256::schnorr::Signature as SchnorrSignature;
+
+use super::key::{Keypair, PrivateKey, PublicKey, TapTweak};
+use super::scalar::Scalar;
+use super::{ecdsa, schnorr};
+use crate::common::types::Message;
+#[cfg(feature = "async")]
+use crate::prelude::*;
+use crate::taproot::TapNodeHash;
+
+/// How a taproot output is spent, which decides whether the signing key is tweaked.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum TapSpend {
+    /// A key path spend, signed with the key tweaked with the merkle root of the script tree.
+    KeyPath {
+        /// The merkle root of the script tree of the output, if it has one.
+        merkle_root: Option<TapNodeHash>,
+    },
+    /// A script path spend, signed with the untweaked key.
+    ScriptPath,
+}
+
+/// A key signing transactions.
+pub trait Signer {
+    /// The error returned when signing fails, e.g. when a device is disconnected.
+    type Error;
+
+    /// Returns the public key of the signer.
+    fn pubkey(&self) -> Result<PublicKey, Self::Error>;
+
+    /// Signs `msg`, a sighash, with a low R ECDSA signature.
+    fn sign_ecdsa(&self, msg: &Message) -> Result<k256::ecdsa::Signature, Self::Error>;
+
+    /// Signs `msg`, a taproot sighash, with a BIP340 signature for a `spend` of the output.
+    fn sign_schnorr(&self, msg: &Message, spend: TapSpend)
+        -> Result<SchnorrSignature, Self::Error>;
+}
+
+/// The future returned by the methods of [`AsyncSigner`].
+#[cfg(feature = "async")]
+pub type SignerFuture<'a, T, E> = Pin<Box<dyn Future<Output = Result<T, E>> + Send + 'a>>;
+
+/// A key signing transactions asynchronously.
+///
+/// This is the asynchronous version of [`Signer`]. The futures are boxed so the trait can be used
+/// as a trait object, and must be `Send` so they can run on a multi-threaded executor.
+#[cfg(feature = "async")]
+pub trait AsyncSigner {
+    /// The error returned when signing fails, e.g. when the connection to the HSM is lost.
+    type Error;
+
+    /// Returns the public key of the signer, see [`Signer::pubkey`].
+    fn pubkey(&self) -> SignerFuture<'_, PublicKey, Self::Error>;
+
+    /// Signs `msg` with a low R ECDSA signature, see [`Signer::sign_ecdsa`].
+    fn sign_ecdsa<'a>(
+        &'a self,
+        msg: &'a Message,
+    ) -> SignerFuture<'a, k256::ecdsa::Signature, Self::Error>;
+
+    /// Signs `msg` with a BIP340 signature, see [`Signer::sign_schnorr`].
+    fn sign_schnorr<'a>(
+        &'a self,
+        msg: &'a Message,
+        spend: TapSpend,
+    ) -> SignerFuture<'a, SchnorrSignature, Self::Error>;
+}
+
+/// Adapts a blocking [`Signer`] to an [`AsyncSigner`].
+///
+/// The blocking call is made when the future is first polled and blocks the executor until it
+/// returns, which is fine for signers that answer quickly, like a [`SoftwareSigner`].
+#[cfg(feature = "async")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Blocking<S>(pub S);
+
+#[cfg(feature = "async")]
+impl<S> AsyncSigner for Blocking<S>
+where
+    S: Signer + Sync,
+    S::Error: Send,
+{
+    type Error = S::Error;
+
+    fn pubkey(&self) -> SignerFuture<'_, PublicKey, Self::Error> {
+        Box::pin(async move { self.0.pubkey() })
+    }
+
+    fn sign_ecdsa<'a>(
+        &'a self,
+        msg: &'a Message,
+    ) -> SignerFuture<'a, k256::ecdsa::Signature, Self::Error> {
+        Box::pin(async move { self.0.sign_ecdsa(msg) })
+    }
+
+    fn sign_schnorr<'a>(
+        &'a self,
+        msg: &'a Message,
+        spend: TapSpend,
+    ) -> SignerFuture<'a, SchnorrSignature, Self::Error> {
+        Box::pin(async move { self.0.sign_schnorr(msg, spend) })
+    }
+}
+
+/// A [`Signer`] holding its private key in memory.
+///
+/// BIP340 signatures are created with all zero auxiliary randomness, like
+/// [`Psbt::sign`](crate::psbt::Psbt::sign).
+#[derive(Clone, PartialEq, Eq)]
+pub struct SoftwareSigner {
+    key: PrivateKey,
+}
+
+impl SoftwareSigner {
+    /// Creates a signer signing with `key`.
+    pub fn new(key: PrivateKey) -> Self {
+        SoftwareSigner { key }
+    }
+
+    /// Returns the private key of the signer.
+    pub fn private_key(&self) -> &PrivateKey {
+        &self.key
+    }
+}
+
+impl fmt::Debug for SoftwareSigner {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SoftwareSigner")
+            .field("pubkey", &self.key.public_key())
+            .finish()
+    }
+}
+
+impl Signer for SoftwareSigner {
+    type Error = Infallible;
+
+    fn pubkey(&self) -> Result<PublicKey, Self::Error> {
+        Ok(self.key.public_key())
+    }
+
+    fn sign_ecdsa(&self, msg: &Message) -> Result<k256::ecdsa::Signature, Self::Error> {
+        Ok(ecdsa::sign_ecdsa_low_r(msg, &Scalar::from(&self.key.inner)))
+    }
+
+    fn sign_schnorr(
+        &self,
+        msg: &Message,
+        spend: TapSpend,
+    ) -> Result<SchnorrSignature, Self::Error> {
+        let keypair = Keypair::from_secret_key(&self.key.inner);
+        let keypair = match spend {
+            TapSpend::KeyPath { merkle_root } => keypair.tap_tweak(merkle_root).to_inner(),
+            TapSpend::ScriptPath => keypair,
+        };
+        Ok(schnorr::sign_schnorr(msg, &keypair, &[0; 32])
+            .try_into()
+            .expect("a BIP340 signature is always valid"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use k256::ecdsa::signature::hazmat::PrehashVerifier;
+    use k256::ecdsa::VerifyingKey;
+
+    use super::*;
+    use crate::crypto::schnorr::Signature64;
+    use crate::network::NetworkKind;
+
+    #[test]
+    fn software_signer() {
+        let key = PrivateKey::from_slice(&[0x42; 32], NetworkKind::Test).unwrap();
+        let signer = SoftwareSigner::new(key.clone());
+        let pubkey = signer.pubkey().unwrap();
+        assert_eq!(pubkey, key.public_key());
+        let msg = Message::from_digest([0x01; 32]);
+
+        let signature = signer.sign_ecdsa(&msg).unwrap();
+        let verifying_key = VerifyingKey::from(pubkey.inner);
+        assert!(verifying_key
+            .verify_prehash(msg.as_ref(), &signature)
+            .is_ok());
+
+        let schnorr_verify = |signature: SchnorrSignature, key| {
+            let signature = Signature64::from_byte_array(signature.to_bytes());
+            schnorr::verify_schnorr(&signature, &msg, key)
+        };
+        let (internal_key, _) = pubkey.x_only_public_key();
+        let signature = signer.sign_schnorr(&msg, TapSpend::ScriptPath).unwrap();
+        assert!(schnorr_verify(signature, &internal_key).is_ok());
+
+        let output_key = internal_key.tap_tweak(None).0.to_inner();
+        let signature = signer
+            .sign_schnorr(&msg, TapSpend::KeyPath { merkle_root: None })
+            .unwrap();
+        assert!(schnorr_verify(signature, &output_key).is_ok());
+        assert!(schnorr_verify(signature, &internal_key).is_err());
+
+        assert!(format!("{:?}", signer).starts_with("SoftwareSigner { pubkey: "));
+    }
+}
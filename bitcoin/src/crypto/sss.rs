@@ -0,0 +1,225 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Shamir secret sharing.
+//!
+//! Splits a secret [`Scalar`] into shares such that any `threshold` of them recover the secret,
+//! while fewer reveal nothing about it. Shares are points `(i, f(i))` of a random polynomial `f`
+//! of degree `threshold - 1` over the scalar field with `f(0)` the secret, the indices `i` start
+//! at 1.
+//!
+
+use core::fmt;
+
+use rand::{CryptoRng, RngCore};
+
+use super::scalar::{MaybeScalar, Scalar};
+use crate::prelude::*;
+
+/// Splits `secret` into `shares` shares, any `threshold` of which recover it.
+///
+/// The returned shares are `(index, value)` pairs with indices `1..=shares`. The polynomial is
+/// evaluated in constant time with respect to the secret and the random coefficients.
+///
+/// # Errors
+///
+/// If `threshold` is zero or greater than `shares`.
+pub fn split<R: RngCore + CryptoRng>(
+    secret: Scalar,
+    threshold: u32,
+    shares: u32,
+    rng: &mut R,
+) -> Result<Vec<(u32, Scalar)>, SplitError> {
+    if threshold == 0 || threshold > shares {
+        return Err(SplitError { threshold, shares });
+    }
+
+    'retry: loop {
+        // The coefficients of `f`, starting with the constant term.
+        let mut coefficients = Vec::with_capacity(threshold as usize);
+        coefficients.push(secret);
+        for _ in 1..threshold {
+            coefficients.push(Scalar::from(k256::NonZeroScalar::random(&mut *rng)));
+        }
+
+        let mut result = Vec::with_capacity(shares as usize);
+        for index in 1..=shares {
+            let x = Scalar::from_u32(index).expect("share indices are non-zero");
+            match evaluate(&coefficients, x).into_option() {
+                Some(value) => result.push((index, value)),
+                // `f(x) = 0` happens with negligible probability, draw another polynomial rather
+                // than handing out a share which can't be represented.
+                None => continue 'retry,
+            }
+        }
+        return Ok(result);
+    }
+}
+
+/// Recovers the secret from at least `threshold` of the shares returned by [`split`].
+///
+/// Recovering from fewer shares than the threshold the secret was split with, or from shares of
+/// different secrets, returns an unrelated scalar.
+///
+/// # Errors
+///
+/// If `shares` is empty, has a share with index zero, has two shares with the same index, or the
+/// interpolated secret is zero.
+pub fn recover(shares: &[(u32, Scalar)]) -> Result<Scalar, RecoverError> {
+    if shares.is_empty() {
+        return Err(RecoverError::NoShares);
+    }
+    let mut indices = BTreeSet::new();
+    for (index, _) in shares {
+        if *index == 0 {
+            return Err(RecoverError::ZeroIndex);
+        }
+        if !indices.insert(*index) {
+            return Err(RecoverError::DuplicateIndex(*index));
+        }
+    }
+
+    let xs: Vec<Scalar> = shares
+        .iter()
+        .map(|(index, _)| Scalar::from_u32(*index).expect("checked above"))
+        .collect();
+
+    let coefficients = Scalar::lagrange_coefficients(&xs).expect("indices checked above");
+    let secret = shares
+        .iter()
+        .zip(coefficients)
+        .fold(MaybeScalar::Zero, |acc, ((_, y), c)| acc + y * c);
+    secret.not_zero().map_err(|_| RecoverError::ZeroSecret)
+}
+
+/// Evaluates the polynomial with the given coefficients at `x` using Horner's method.
+pub(crate) fn evaluate(coefficients: &[Scalar], x: Scalar) -> MaybeScalar {
+    coefficients
+        .iter()
+        .rev()
+        .fold(MaybeScalar::Zero, |acc, c| acc * x + c)
+}
+
+/// Error splitting a secret, the threshold is zero or greater than the number of shares.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SplitError {
+    /// The requested threshold.
+    pub threshold: u32,
+    /// The requested number of shares.
+    pub shares: u32,
+}
+
+internals::impl_from_infallible!(SplitError);
+
+impl fmt::Display for SplitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "invalid threshold {} for {} shares",
+            self.threshold, self.shares
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SplitError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        None
+    }
+}
+
+/// Error recovering a secret from shares.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RecoverError {
+    /// No shares were given.
+    NoShares,
+    /// A share has index zero, which would be the secret itself.
+    ZeroIndex,
+    /// Two shares have the same index.
+    DuplicateIndex(u32),
+    /// The interpolated secret is zero, the shares are not from a valid split.
+    ZeroSecret,
+}
+
+internals::impl_from_infallible!(RecoverError);
+
+impl fmt::Display for RecoverError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use RecoverError::*;
+
+        match *self {
+            NoShares => f.write_str("no shares to recover the secret from"),
+            ZeroIndex => f.write_str("share has index zero"),
+            DuplicateIndex(index) => write!(f, "duplicate share index {}", index),
+            ZeroSecret => f.write_str("recovered secret is zero"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for RecoverError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use RecoverError::*;
+
+        match *self {
+            NoShares | ZeroIndex | DuplicateIndex(_) | ZeroSecret => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_and_recover() {
+        let mut rng = rand::thread_rng();
+        let secret = Scalar::reduce_from(&[0x42; 32]);
+
+        let shares = split(secret, 3, 5, &mut rng).unwrap();
+        assert_eq!(shares.len(), 5);
+        assert_eq!(
+            shares.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+            [1, 2, 3, 4, 5]
+        );
+
+        assert_eq!(recover(&shares), Ok(secret));
+        assert_eq!(recover(&shares[..3]), Ok(secret));
+        assert_eq!(recover(&shares[2..]), Ok(secret));
+        assert_eq!(recover(&[shares[4], shares[0], shares[2]]), Ok(secret));
+        assert_ne!(recover(&shares[..2]), Ok(secret));
+
+        // A threshold of one hands out the secret itself.
+        let shares = split(secret, 1, 2, &mut rng).unwrap();
+        assert_eq!(shares, [(1, secret), (2, secret)]);
+    }
+
+    #[test]
+    fn errors() {
+        let mut rng = rand::thread_rng();
+        let secret = Scalar::one();
+
+        assert_eq!(
+            split(secret, 0, 3, &mut rng),
+            Err(SplitError {
+                threshold: 0,
+                shares: 3
+            })
+        );
+        assert_eq!(
+            split(secret, 4, 3, &mut rng),
+            Err(SplitError {
+                threshold: 4,
+                shares: 3
+            })
+        );
+
+        assert_eq!(recover(&[]), Err(RecoverError::NoShares));
+        assert_eq!(recover(&[(0, secret)]), Err(RecoverError::ZeroIndex));
+        assert_eq!(
+            recover(&[(1, secret), (1, secret)]),
+            Err(RecoverError::DuplicateIndex(1))
+        );
+    }
+}
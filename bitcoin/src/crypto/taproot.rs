@@ -26,14 +26,14 @@ pub struct Signature {
 }
 
 /// Need to implement this manually because [`k256::schnorr::Signature`] does not implement `Hash`.
-impl std::hash::Hash for Signature {
-    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+impl core::hash::Hash for Signature {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
         self.serialize().hash(state);
     }
 }
 
 impl Ord for Signature {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
         self.signature.to_bytes().cmp(&other.signature.to_bytes())
     }
 }
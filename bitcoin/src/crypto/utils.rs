@@ -3,7 +3,7 @@ use subtle::{ConditionallySelectable, ConstantTimeEq, ConstantTimeGreater};
 // Perform elementwise XOR on two arrays and return the resulting output array.
 pub fn xor_arrays<T, const SIZE: usize>(arr1: &[T; SIZE], arr2: &[T; SIZE]) -> [T; SIZE]
 where
-    T: Copy + Default + std::ops::BitXor<Output = T>,
+    T: Copy + Default + core::ops::BitXor<Output = T>,
 {
     let mut xored = [T::default(); SIZE];
     for i in 0..SIZE {
@@ -22,7 +22,7 @@ where
 /// - `Ordering::Greater` if `lhs > rhs`
 ///
 /// Duplicated from [This PR](https://github.com/dalek-cryptography/subtle/pull/116).
-pub fn ct_slice_lex_cmp<T>(lhs: &[T], rhs: &[T]) -> std::cmp::Ordering
+pub fn ct_slice_lex_cmp<T>(lhs: &[T], rhs: &[T]) -> core::cmp::Ordering
 where
     T: ConstantTimeEq + ConstantTimeGreater,
 {
@@ -49,22 +49,22 @@ where
     let rhs_is_longer = r_len.ct_gt(&l_len);
 
     // Fallback: lhs < rhs
-    let mut order = std::cmp::Ordering::Less;
+    let mut order = core::cmp::Ordering::Less;
 
     // both slices up to `min(l_len, r_len)` were equal.
-    order.conditional_assign(&std::cmp::Ordering::Equal, whole_slice_is_eq);
+    order.conditional_assign(&core::cmp::Ordering::Equal, whole_slice_is_eq);
 
     // `rhs` is a prefix of `lhs`. `lhs` is lexicographically greater.
     order.conditional_assign(
-        &std::cmp::Ordering::Greater,
+        &core::cmp::Ordering::Greater,
         whole_slice_is_eq & lhs_is_longer,
     );
 
     // `lhs` is a prefix of `rhs`. `rhs` is lexicographically greater.
-    order.conditional_assign(&std::cmp::Ordering::Less, whole_slice_is_eq & rhs_is_longer);
+    order.conditional_assign(&core::cmp::Ordering::Less, whole_slice_is_eq & rhs_is_longer);
 
     // `lhs` contains the earliest strictly-greater element.
-    order.conditional_assign(&std::cmp::Ordering::Greater, whole_slice_is_gt);
+    order.conditional_assign(&core::cmp::Ordering::Greater, whole_slice_is_gt);
 
     order
 }
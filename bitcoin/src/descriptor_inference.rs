@@ -0,0 +1,397 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Output descriptor inference from spends.
+//!
+//! A spend reveals what a hash based output commits to: the public key of a `pkh()` or `wpkh()`,
+//! the redeem script of a `sh()`, the witness script of a `wsh()` and the spent leaf of a `tr()`.
+//! [`infer_from_spend`] reconstructs the descriptor of the spent output from an input and the
+//! output it spends, which lets recovery tools find the descriptor of funds they only know a
+//! spend of, and analytics classify the inputs of a transaction.
+//!
+//! The spent output is classified with [`ScriptType`], and the revealed scripts are matched
+//! against the `pk()`, `multi()` and `multi_a()` scripts built by [`multisig::multi_script`]. A
+//! spend only reveals one leaf of a taproot tree, so the descriptor of a taproot output is only
+//! [complete](InferredDescriptor::Complete) if it has a single leaf.
+//!
+
+use core::fmt;
+
+use crate::blockdata::graph::ScriptType;
+use crate::blockdata::opcodes::all::OP_CHECKSIG;
+use crate::blockdata::script::{Instruction, Script, ScriptBuf};
+use crate::blockdata::transaction::{TxIn, TxOut};
+use crate::common::types::Parity;
+use crate::crypto::key::{PublicKey, XOnlyPublicKey};
+use crate::multisig::{self, MultiContext};
+use crate::multisig_setup::descriptor_checksum;
+use crate::prelude::*;
+use crate::taproot::{ControlBlock, LeafVersion};
+
+/// A descriptor inferred by [`infer_from_spend`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InferredDescriptor {
+    /// The descriptor of the spent output, with its checksum.
+    Complete(String),
+    /// A script path spend of a taproot output whose other leaves are hidden by the spend.
+    TapLeaf {
+        /// The internal key of the output.
+        internal_key: XOnlyPublicKey,
+        /// The descriptor fragment of the spent leaf, e.g. `multi_a(2,...)`.
+        leaf: String,
+        /// The depth of the leaf in the tree.
+        depth: usize,
+    },
+}
+
+/// Infers the descriptor of `prevout` from `txin` spending it.
+///
+/// Supports `pk()`, `pkh()`, `wpkh()`, `sh(wpkh())`, bare and `sh()`/`wsh()`/`sh(wsh())` wrapped
+/// `multi()`, `tr()` with a `pk()` or `multi_a()` leaf, and `rawtr()` for key path spends, whose
+/// internal key isn't revealed. Every revealed key and script is checked against `prevout`.
+pub fn infer_from_spend(txin: &TxIn, prevout: &TxOut) -> Result<InferredDescriptor, InferError> {
+    let script_pubkey = &prevout.script_pubkey;
+    let witness = &txin.witness;
+    let descriptor = match ScriptType::from_script(script_pubkey) {
+        ScriptType::P2pk | ScriptType::Multisig =>
+            infer_fragment(script_pubkey, MultiContext::Bare)?,
+        ScriptType::P2pkh => {
+            let pubkey = parse_key(last_push(&txin.script_sig)?, MultiContext::Bare)?;
+            check(ScriptBuf::new_p2pkh(&pubkey.pubkey_hash()) == *script_pubkey)?;
+            format!("pkh({})", pubkey)
+        }
+        ScriptType::P2wpkh => format!("wpkh({})", witness_key(witness.last(), script_pubkey)?),
+        ScriptType::P2wsh => {
+            let script = Script::from_bytes(witness.last().ok_or(InferError::InvalidSpend)?);
+            check(ScriptBuf::new_p2wsh(&script.wscript_hash()) == *script_pubkey)?;
+            format!("wsh({})", infer_fragment(script, MultiContext::Wsh)?)
+        }
+        ScriptType::P2sh => {
+            let redeem_script = Script::from_bytes(last_push(&txin.script_sig)?);
+            check(ScriptBuf::new_p2sh(&redeem_script.script_hash()) == *script_pubkey)?;
+            match ScriptType::from_script(redeem_script) {
+                ScriptType::P2wpkh =>
+                    format!("sh(wpkh({}))", witness_key(witness.last(), redeem_script)?),
+                ScriptType::P2wsh => {
+                    let script =
+                        Script::from_bytes(witness.last().ok_or(InferError::InvalidSpend)?);
+                    check(ScriptBuf::new_p2wsh(&script.wscript_hash()) == *redeem_script)?;
+                    format!("sh(wsh({}))", infer_fragment(script, MultiContext::Wsh)?)
+                }
+                _ => format!("sh({})", infer_fragment(redeem_script, MultiContext::Sh)?),
+            }
+        }
+        ScriptType::P2tr => {
+            let output_key = XOnlyPublicKey::from_slice(&script_pubkey.as_bytes()[2..])
+                .map_err(|_| InferError::InvalidSpend)?;
+            let (script, control_block) =
+                match (witness.tapscript(), witness.taproot_control_block()) {
+                    (Some(script), Some(control_block)) => (script, control_block),
+                    _ => return Ok(complete(format!("rawtr({})", output_key))),
+                };
+            let control_block =
+                ControlBlock::decode(control_block).map_err(|_| InferError::InvalidSpend)?;
+            check(control_block.verify_taproot_commitment(output_key, script))?;
+            if control_block.leaf_version != LeafVersion::TapScript {
+                return Err(InferError::UnsupportedScript(script.to_owned()));
+            }
+            let leaf = infer_fragment(script, MultiContext::Tapscript)?;
+            let internal_key = control_block.internal_key;
+            match control_block.merkle_branch.len() {
+                0 => format!("tr({},{})", internal_key, leaf),
+                depth => return Ok(InferredDescriptor::TapLeaf { internal_key, leaf, depth }),
+            }
+        }
+        script_type @ (ScriptType::OpReturn
+        | ScriptType::WitnessProgram
+        | ScriptType::NonStandard) => return Err(InferError::UnsupportedOutput(script_type)),
+    };
+    Ok(complete(descriptor))
+}
+
+/// Returns the complete descriptor `descriptor` with its checksum.
+fn complete(descriptor: String) -> InferredDescriptor {
+    let checksum = descriptor_checksum(&descriptor).expect("inferred descriptors are valid");
+    InferredDescriptor::Complete(format!("{}#{}", descriptor, checksum))
+}
+
+/// Returns `Ok` if the spend matches the spent script.
+fn check(matches: bool) -> Result<(), InferError> {
+    if matches {
+        Ok(())
+    } else {
+        Err(InferError::InvalidSpend)
+    }
+}
+
+/// Returns the last push of the push only `script_sig`.
+fn last_push(script_sig: &Script) -> Result<&[u8], InferError> {
+    match script_sig.instructions().last() {
+        Some(Ok(Instruction::PushBytes(bytes))) => Ok(bytes.as_bytes()),
+        _ => Err(InferError::InvalidSpend),
+    }
+}
+
+/// Returns the key of a P2WPKH spend of `script_pubkey` revealing `key`.
+fn witness_key(key: Option<&[u8]>, script_pubkey: &Script) -> Result<PublicKey, InferError> {
+    let pubkey = parse_key(key.ok_or(InferError::InvalidSpend)?, MultiContext::Wsh)?;
+    let wpubkey_hash = pubkey.wpubkey_hash().map_err(|_| InferError::InvalidSpend)?;
+    check(ScriptBuf::new_p2wpkh(&wpubkey_hash) == *script_pubkey)?;
+    Ok(pubkey)
+}
+
+/// Parses a key pushed in `context`, an x-only key in tapscript.
+fn parse_key(bytes: &[u8], context: MultiContext) -> Result<PublicKey, InferError> {
+    match context {
+        MultiContext::Tapscript => XOnlyPublicKey::from_slice(bytes)
+            .map(|key| key.public_key(Parity::Even))
+            .map_err(|_| InferError::InvalidSpend),
+        _ => PublicKey::from_slice(bytes).map_err(|_| InferError::InvalidSpend),
+    }
+}
+
+/// Formats `key` as it is written in a descriptor fragment for `context`.
+fn key_string(key: &PublicKey, context: MultiContext) -> String {
+    match context {
+        MultiContext::Tapscript => key.x_only_public_key().0.to_string(),
+        _ => key.to_string(),
+    }
+}
+
+/// Infers the `pk()`, `multi()` or `multi_a()` descriptor fragment of `script`.
+fn infer_fragment(script: &Script, context: MultiContext) -> Result<String, InferError> {
+    let unsupported = || InferError::UnsupportedScript(script.to_owned());
+    let instructions =
+        script.instructions().collect::<Result<Vec<_>, _>>().map_err(|_| unsupported())?;
+
+    if let [Instruction::PushBytes(key), Instruction::Op(op)] = instructions[..] {
+        if op == OP_CHECKSIG {
+            let key = parse_key(key.as_bytes(), context).map_err(|_| unsupported())?;
+            return Ok(format!("pk({})", key_string(&key, context)));
+        }
+    }
+
+    let (threshold, keys) = multisig::parse_multi(script, context).ok_or_else(unsupported)?;
+    let name = match context {
+        MultiContext::Tapscript => "multi_a",
+        _ => "multi",
+    };
+
+    let mut fragment = format!("{}({}", name, threshold);
+    for key in &keys {
+        fragment.push(',');
+        fragment.push_str(&key_string(key, context));
+    }
+    fragment.push(')');
+    Ok(fragment)
+}
+
+/// The descriptor of a spent output can't be inferred.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum InferError {
+    /// The spent output is of a type without a descriptor.
+    UnsupportedOutput(ScriptType),
+    /// The spent output has a script which isn't a `pk()`, `multi()` or `multi_a()`.
+    UnsupportedScript(ScriptBuf),
+    /// The input doesn't reveal what the spent output commits to.
+    InvalidSpend,
+}
+
+internals::impl_from_infallible!(InferError);
+
+impl fmt::Display for InferError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use InferError::*;
+
+        match *self {
+            UnsupportedOutput(script_type) =>
+                write!(f, "can't infer the descriptor of a {} output", script_type),
+            UnsupportedScript(ref script) =>
+                write!(f, "can't infer the descriptor of script {}", script.to_asm_string()),
+            InvalidSpend => f.write_str("input doesn't spend the output"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for InferError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use InferError::*;
+
+        match *self {
+            UnsupportedOutput(_) | UnsupportedScript(_) | InvalidSpend => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hex::test_hex_unwrap as hex;
+
+    use super::*;
+    use crate::blockdata::opcodes::all::OP_NOP;
+    use crate::blockdata::script::{Builder, PushBytesBuf};
+    use crate::blockdata::witness::Witness;
+    use crate::crypto::key::G;
+    use crate::crypto::scalar::Scalar;
+    use crate::taproot::TaprootBuilder;
+    use crate::Amount;
+
+    fn key(i: u8) -> PublicKey { Scalar::reduce_from(&[i; 32]) * G }
+
+    fn prevout(script_pubkey: ScriptBuf) -> TxOut {
+        TxOut { value: Amount::from_sat(10_000), script_pubkey }
+    }
+
+    fn txin(script_sig: ScriptBuf, witness: &[&[u8]]) -> TxIn {
+        TxIn { script_sig, witness: Witness::from_slice(witness), ..Default::default() }
+    }
+
+    fn push(bytes: &[u8]) -> ScriptBuf {
+        Builder::new().push_slice(PushBytesBuf::try_from(bytes.to_vec()).unwrap()).into_script()
+    }
+
+    fn descriptor(inferred: InferredDescriptor) -> String {
+        match inferred {
+            InferredDescriptor::Complete(descriptor) =>
+                descriptor.split('#').next().unwrap().to_owned(),
+            InferredDescriptor::TapLeaf { .. } => panic!("incomplete descriptor {:?}", inferred),
+        }
+    }
+
+    #[test]
+    fn key_hash_spends() {
+        let pubkey = key(1);
+        let sig = hex!("3044");
+        let bytes = pubkey.to_bytes();
+
+        let p2pkh = prevout(ScriptBuf::new_p2pkh(&pubkey.pubkey_hash()));
+        let spend =
+            txin(Builder::new().push_slice([0x30, 0x44]).push_key(&pubkey).into_script(), &[]);
+        assert_eq!(
+            descriptor(infer_from_spend(&spend, &p2pkh).unwrap()),
+            format!("pkh({})", pubkey)
+        );
+
+        let wpkh = ScriptBuf::new_p2wpkh(&pubkey.wpubkey_hash().unwrap());
+        let spend = txin(ScriptBuf::new(), &[&sig, &bytes]);
+        let inferred = infer_from_spend(&spend, &prevout(wpkh.clone())).unwrap();
+        assert_eq!(descriptor(inferred.clone()), format!("wpkh({})", pubkey));
+        let InferredDescriptor::Complete(with_checksum) = inferred else { unreachable!() };
+        assert_eq!(
+            descriptor_checksum(with_checksum.split('#').next().unwrap()).unwrap(),
+            with_checksum[with_checksum.len() - 8..]
+        );
+
+        let spend = txin(push(wpkh.as_bytes()), &[&sig, &bytes]);
+        let p2sh = prevout(ScriptBuf::new_p2sh(&wpkh.script_hash()));
+        assert_eq!(
+            descriptor(infer_from_spend(&spend, &p2sh).unwrap()),
+            format!("sh(wpkh({}))", pubkey)
+        );
+
+        // The revealed key must hash to the spent output.
+        let spend = txin(ScriptBuf::new(), &[&sig, &key(2).to_bytes()]);
+        assert_eq!(infer_from_spend(&spend, &prevout(wpkh)), Err(InferError::InvalidSpend));
+    }
+
+    #[test]
+    fn multisig_spends() {
+        let keys = [key(1), key(2), key(3)];
+        let multi = multisig::multi_script(2, &keys, MultiContext::Wsh).unwrap();
+        let expected = format!("multi(2,{},{},{})", keys[0], keys[1], keys[2]);
+
+        let spend = txin(ScriptBuf::new(), &[&[], &hex!("3044"), multi.as_bytes()]);
+        let wsh = ScriptBuf::new_p2wsh(&multi.wscript_hash());
+        assert_eq!(
+            descriptor(infer_from_spend(&spend, &prevout(wsh.clone())).unwrap()),
+            format!("wsh({})", expected)
+        );
+
+        let spend = txin(push(wsh.as_bytes()), &[&[], &hex!("3044"), multi.as_bytes()]);
+        let p2sh = prevout(ScriptBuf::new_p2sh(&wsh.script_hash()));
+        assert_eq!(
+            descriptor(infer_from_spend(&spend, &p2sh).unwrap()),
+            format!("sh(wsh({}))", expected)
+        );
+
+        let bare = prevout(multi.clone());
+        assert_eq!(descriptor(infer_from_spend(&TxIn::default(), &bare).unwrap()), expected);
+
+        let other = Builder::new().push_opcode(OP_NOP).into_script();
+        let spend = txin(ScriptBuf::new(), &[other.as_bytes()]);
+        assert_eq!(
+            infer_from_spend(&spend, &prevout(ScriptBuf::new_p2wsh(&other.wscript_hash()))),
+            Err(InferError::UnsupportedScript(other))
+        );
+        let op_return = ScriptBuf::new_op_return([1, 2, 3]);
+        assert_eq!(
+            infer_from_spend(&TxIn::default(), &prevout(op_return)),
+            Err(InferError::UnsupportedOutput(ScriptType::OpReturn))
+        );
+    }
+
+    #[test]
+    fn taproot_spends() {
+        let (internal_key, _) = key(1).x_only_public_key();
+        let keys = [key(2), key(3)];
+        let multi_a = multisig::multi_script(1, &keys, MultiContext::Tapscript).unwrap();
+        let pk = Builder::new()
+            .push_x_only_key(&key(4).x_only_public_key().0)
+            .push_opcode(OP_CHECKSIG)
+            .into_script();
+        let expected = format!(
+            "multi_a(1,{},{})",
+            keys[0].x_only_public_key().0,
+            keys[1].x_only_public_key().0
+        );
+
+        let spend_info = TaprootBuilder::new()
+            .add_leaf(0, multi_a.clone())
+            .unwrap()
+            .finalize(internal_key)
+            .unwrap();
+        let output = prevout(ScriptBuf::new_p2tr_tweaked(spend_info.output_key()));
+        let control_block =
+            spend_info.control_block(&(multi_a.clone(), LeafVersion::TapScript)).unwrap();
+        let spend =
+            txin(ScriptBuf::new(), &[&[0x01; 64], multi_a.as_bytes(), &control_block.serialize()]);
+        assert_eq!(
+            descriptor(infer_from_spend(&spend, &output).unwrap()),
+            format!("tr({},{})", internal_key, expected)
+        );
+
+        let spend = txin(ScriptBuf::new(), &[&[0x01; 64]]);
+        let output_key = spend_info.output_key().to_inner();
+        assert_eq!(
+            descriptor(infer_from_spend(&spend, &output).unwrap()),
+            format!("rawtr({})", output_key)
+        );
+
+        let spend_info = TaprootBuilder::new()
+            .add_leaf(1, multi_a.clone())
+            .unwrap()
+            .add_leaf(1, pk.clone())
+            .unwrap()
+            .finalize(internal_key)
+            .unwrap();
+        let output = prevout(ScriptBuf::new_p2tr_tweaked(spend_info.output_key()));
+        let control_block =
+            spend_info.control_block(&(pk.clone(), LeafVersion::TapScript)).unwrap();
+        let spend =
+            txin(ScriptBuf::new(), &[&[0x01; 64], pk.as_bytes(), &control_block.serialize()]);
+        assert_eq!(
+            infer_from_spend(&spend, &output),
+            Ok(InferredDescriptor::TapLeaf {
+                internal_key,
+                leaf: format!("pk({})", key(4).x_only_public_key().0),
+                depth: 1
+            })
+        );
+
+        // The control block must commit to the spent script.
+        let spend =
+            txin(ScriptBuf::new(), &[&[0x01; 64], multi_a.as_bytes(), &control_block.serialize()]);
+        assert_eq!(infer_from_spend(&spend, &output), Err(InferError::InvalidSpend));
+    }
+}
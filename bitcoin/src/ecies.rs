@@ -0,0 +1,241 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! ECIES encryption to secp256k1 keys.
+//!
+//! This module encrypts small payloads, such as wallet backups, notes or the messages of a
+//! signing session, to a [`PublicKey`]. The sender draws an ephemeral key and derives the
+//! encryption key from its ECDH shared secret with the recipient key using HKDF-SHA256. The
+//! payload is then encrypted with ChaCha20-Poly1305.
+//!
+//! The ciphertext format is:
+//!
+//! ```text
+//! version (1 byte) | ephemeral key (33 bytes) | encrypted payload | Poly1305 tag (16 bytes)
+//! ```
+//!
+//! The version and the ephemeral key are authenticated as associated data. This scheme does
+//! not authenticate the sender, see [`envelope`](crate::envelope) for that.
+//!
+
+use core::fmt;
+
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use hashes::{sha256, Hash, HashEngine, Hmac, HmacEngine};
+use k256::SecretKey;
+use rand::{CryptoRng, RngCore};
+
+use crate::crypto::key::PublicKey;
+use crate::crypto::scalar::Scalar;
+use crate::prelude::*;
+
+/// The version of the ciphertext format written by this library.
+pub const CURRENT_VERSION: u8 = 1;
+
+/// The length of the header of a ciphertext: the version and the ephemeral key.
+const HEADER_LEN: usize = 1 + 33;
+
+/// The length of the Poly1305 tag.
+const TAG_LEN: usize = 16;
+
+/// The HKDF info string of the [`CURRENT_VERSION`] format.
+const HKDF_INFO: &[u8] = b"bitcoin ecies v1";
+
+/// Encrypts `plaintext` to `recipient`.
+///
+/// Runs in constant time with respect to the ephemeral secret key.
+pub fn encrypt<R: RngCore + CryptoRng>(
+    recipient: &PublicKey,
+    plaintext: &[u8],
+    rng: &mut R,
+) -> Vec<u8> {
+    let ephemeral_secret = Scalar::from(k256::NonZeroScalar::random(&mut *rng));
+    let ephemeral_key = ephemeral_secret.base_point_mul();
+
+    let mut ciphertext = Vec::with_capacity(HEADER_LEN + plaintext.len() + TAG_LEN);
+    ciphertext.push(CURRENT_VERSION);
+    ciphertext.extend_from_slice(&ephemeral_key.serialize());
+
+    let (cipher, nonce) =
+        derive_cipher(&ephemeral_key, recipient, &(ephemeral_secret * *recipient));
+    let encrypted = cipher
+        .encrypt(&nonce, Payload { msg: plaintext, aad: &ciphertext })
+        .expect("payloads are much shorter than the ChaCha20 limit");
+    ciphertext.extend_from_slice(&encrypted);
+    ciphertext
+}
+
+/// Decrypts a `ciphertext` encrypted to the public key of `recipient`.
+///
+/// Runs in constant time with respect to `recipient`.
+pub fn decrypt(recipient: &SecretKey, ciphertext: &[u8]) -> Result<Vec<u8>, EciesError> {
+    if ciphertext.len() < HEADER_LEN + TAG_LEN {
+        return Err(EciesError::TooShort);
+    }
+    match ciphertext[0] {
+        CURRENT_VERSION => {}
+        v => return Err(EciesError::UnsupportedVersion(v)),
+    }
+    let (header, encrypted) = ciphertext.split_at(HEADER_LEN);
+    let ephemeral_key =
+        PublicKey::from_slice(&header[1..]).map_err(|_| EciesError::InvalidEphemeralKey)?;
+
+    let recipient_secret = Scalar::from(recipient);
+    let (cipher, nonce) = derive_cipher(
+        &ephemeral_key,
+        &recipient_secret.base_point_mul(),
+        &(recipient_secret * ephemeral_key),
+    );
+    cipher
+        .decrypt(&nonce, Payload { msg: encrypted, aad: header })
+        .map_err(|_| EciesError::Decryption)
+}
+
+/// Derives the cipher and nonce of a ciphertext from the ECDH shared secret `shared`.
+///
+/// The HKDF salt commits to both keys so the ciphertext is bound to its recipient.
+fn derive_cipher(
+    ephemeral_key: &PublicKey,
+    recipient: &PublicKey,
+    shared: &PublicKey,
+) -> (ChaCha20Poly1305, Nonce) {
+    let mut salt = [0u8; 66];
+    salt[..33].copy_from_slice(&ephemeral_key.serialize());
+    salt[33..].copy_from_slice(&recipient.serialize());
+
+    let mut okm = [0u8; 32 + 12];
+    hkdf_sha256(&salt, &shared.serialize(), HKDF_INFO, &mut okm);
+    let key = Key::from(<[u8; 32]>::try_from(&okm[..32]).expect("32 bytes"));
+    let nonce = Nonce::from(<[u8; 12]>::try_from(&okm[32..]).expect("12 bytes"));
+    (ChaCha20Poly1305::new(&key), nonce)
+}
+
+/// Fills `okm` with the HKDF-SHA256 (RFC 5869) expansion of `ikm`.
+///
+/// # Panics
+///
+/// If `okm` is longer than 255 SHA256 blocks.
+fn hkdf_sha256(salt: &[u8], ikm: &[u8], info: &[u8], okm: &mut [u8]) {
+    assert!(okm.len() <= 255 * 32, "HKDF output too long");
+
+    let mut engine = HmacEngine::<sha256::Hash>::new(salt);
+    engine.input(ikm);
+    let prk = Hmac::<sha256::Hash>::from_engine(engine);
+
+    let mut t = [0u8; 32];
+    for (i, chunk) in okm.chunks_mut(32).enumerate() {
+        let mut engine = HmacEngine::<sha256::Hash>::new(prk.as_byte_array());
+        if i > 0 {
+            engine.input(&t);
+        }
+        engine.input(info);
+        engine.input(&[i as u8 + 1]);
+        t = Hmac::<sha256::Hash>::from_engine(engine).to_byte_array();
+        chunk.copy_from_slice(&t[..chunk.len()]);
+    }
+}
+
+/// An error decrypting an ECIES ciphertext.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum EciesError {
+    /// The data is too short to be a ciphertext.
+    TooShort,
+    /// The ciphertext was written by a newer, unknown, version of the format.
+    UnsupportedVersion(u8),
+    /// The ephemeral key is not a valid public key.
+    InvalidEphemeralKey,
+    /// The ciphertext failed authentication, either it was tampered with or the key is wrong.
+    Decryption,
+}
+
+internals::impl_from_infallible!(EciesError);
+
+impl fmt::Display for EciesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use EciesError::*;
+
+        match *self {
+            TooShort => f.write_str("data too short to be an ECIES ciphertext"),
+            UnsupportedVersion(v) => write!(f, "unsupported ECIES ciphertext version {}", v),
+            InvalidEphemeralKey => f.write_str("invalid ECIES ephemeral key"),
+            Decryption => f.write_str("ECIES decryption failed"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for EciesError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use EciesError::*;
+
+        match *self {
+            TooShort | UnsupportedVersion(_) | InvalidEphemeralKey | Decryption => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hex::test_hex_unwrap as hex;
+
+    use super::*;
+
+    #[test]
+    fn hkdf_rfc5869_vector() {
+        // Test case 1 of RFC 5869.
+        let mut okm = [0u8; 42];
+        hkdf_sha256(
+            &hex!("000102030405060708090a0b0c"),
+            &[0x0b; 22],
+            &hex!("f0f1f2f3f4f5f6f7f8f9"),
+            &mut okm,
+        );
+        assert_eq!(
+            okm[..],
+            hex!("3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865")[..]
+        );
+    }
+
+    #[test]
+    fn encrypt_decrypt() {
+        let mut rng = rand::thread_rng();
+        let secret = SecretKey::from_slice(&[0x11; 32]).unwrap();
+        let public = Scalar::from(&secret).base_point_mul();
+
+        for plaintext in [&b""[..], b"a wallet backup"] {
+            let ciphertext = encrypt(&public, plaintext, &mut rng);
+            assert_eq!(ciphertext.len(), HEADER_LEN + plaintext.len() + TAG_LEN);
+            assert_eq!(decrypt(&secret, &ciphertext).unwrap(), plaintext);
+        }
+
+        // Every encryption uses a fresh ephemeral key.
+        assert_ne!(encrypt(&public, b"note", &mut rng), encrypt(&public, b"note", &mut rng));
+    }
+
+    #[test]
+    fn decrypt_errors() {
+        let mut rng = rand::thread_rng();
+        let secret = SecretKey::from_slice(&[0x11; 32]).unwrap();
+        let other = SecretKey::from_slice(&[0x22; 32]).unwrap();
+        let ciphertext = encrypt(&Scalar::from(&secret).base_point_mul(), b"note", &mut rng);
+
+        assert_eq!(decrypt(&other, &ciphertext), Err(EciesError::Decryption));
+        assert_eq!(decrypt(&secret, &ciphertext[..HEADER_LEN]), Err(EciesError::TooShort));
+
+        let mut bad = ciphertext.clone();
+        bad[0] = 2;
+        assert_eq!(decrypt(&secret, &bad), Err(EciesError::UnsupportedVersion(2)));
+
+        let mut bad = ciphertext.clone();
+        bad[2..HEADER_LEN].fill(0xff);
+        assert_eq!(decrypt(&secret, &bad), Err(EciesError::InvalidEphemeralKey));
+
+        // Flipping the parity of the ephemeral key or any bit of the payload is detected.
+        for i in [1, HEADER_LEN, ciphertext.len() - 1] {
+            let mut bad = ciphertext.clone();
+            bad[i] ^= 1;
+            assert_eq!(decrypt(&secret, &bad), Err(EciesError::Decryption));
+        }
+    }
+}
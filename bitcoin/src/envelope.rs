@@ -0,0 +1,477 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Encrypted envelopes.
+//!
+//! This module seals PSBTs and the messages of multi-party signing sessions, such as the FROST
+//! messages in [`psbt`](crate::psbt), for a single recipient so that they are not sent between
+//! parties in the clear.
+//!
+//! The sender and the recipient are identified by secp256k1 keys, and envelopes name them by
+//! their [`key_fingerprint`]. The key of every envelope is derived from two ECDH shared secrets:
+//! one between a fresh ephemeral key and the recipient key, so that every envelope has its own
+//! key, and one between the sender key and the recipient key, which authenticates the sender.
+//! The payload is encrypted with ChaCha20-Poly1305 under this key, with the envelope header as
+//! associated data.
+//!
+//! Every envelope also carries a session identifier and a sequence number. A [`ReplayGuard`]
+//! kept by the recipient rejects envelopes from other sessions and envelopes whose sequence
+//! number is not greater than the last one accepted from the same sender.
+//!
+//! The serialization format is:
+//!
+//! ```text
+//! magic "PENV" (4 bytes) | version (1 byte) | sender fingerprint (4 bytes) |
+//! recipient fingerprint (4 bytes) | session id (32 bytes) | sequence (8 bytes) |
+//! ephemeral key (33 bytes) | ciphertext (compact size prefixed)
+//! ```
+//!
+//! where the ciphertext ends with the 16 byte Poly1305 tag and everything before it is the
+//! header.
+//!
+
+use core::fmt;
+
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use hashes::{hash160, sha256t_hash_newtype, Hash, HashEngine};
+use internals::write_err;
+use k256::SecretKey;
+use rand::{CryptoRng, RngCore};
+
+use crate::bip32::Fingerprint;
+use crate::consensus::encode::{self, Decodable, Encodable};
+use crate::crypto::key::PublicKey;
+use crate::crypto::scalar::Scalar;
+use crate::prelude::*;
+
+/// The magic bytes at the start of every envelope.
+pub const ENVELOPE_MAGIC: [u8; 4] = *b"PENV";
+
+/// The version of the envelope format written by this library.
+pub const CURRENT_VERSION: u8 = 1;
+
+/// The length of the envelope header.
+const HEADER_LEN: usize = 4 + 1 + 4 + 4 + 32 + 8 + 33;
+
+sha256t_hash_newtype! {
+    pub struct EnvelopeKeyTag = hash_str("Envelope/key");
+
+    /// Taproot-style tagged hash with tag \"Envelope/key\".
+    ///
+    /// This is the ChaCha20-Poly1305 key of an envelope, committing to the ephemeral, sender and
+    /// recipient keys and to both ECDH shared secrets.
+    #[hash_newtype(forward)]
+    pub struct EnvelopeKeyHash(_);
+}
+
+/// Returns the fingerprint naming `key` in envelopes.
+///
+/// This is the first four bytes of the HASH160 of the compressed key, the same as the
+/// fingerprint of a BIP32 extended key.
+pub fn key_fingerprint(key: &PublicKey) -> Fingerprint {
+    hash160::Hash::hash(&key.serialize())[0..4].try_into().expect("4 is the fingerprint length")
+}
+
+/// A payload encrypted for a single recipient.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Envelope {
+    /// The fingerprint of the sender key.
+    pub sender: Fingerprint,
+    /// The fingerprint of the recipient key.
+    pub recipient: Fingerprint,
+    /// The identifier of the session the envelope belongs to.
+    pub session_id: [u8; 32],
+    /// The sequence number of the envelope among those sent by the sender in the session.
+    pub sequence: u64,
+    /// The ephemeral key the envelope key is derived from.
+    pub ephemeral_key: PublicKey,
+    /// The encrypted payload, followed by the Poly1305 tag.
+    pub ciphertext: Vec<u8>,
+}
+
+impl Envelope {
+    /// Encrypts `payload` from `sender` to `recipient`.
+    ///
+    /// The sender must use a new `sequence` number, greater than the previous one, for every
+    /// envelope it sends in the session `session_id`.
+    pub fn seal<R: RngCore + CryptoRng>(
+        payload: &[u8],
+        sender: &SecretKey,
+        recipient: &PublicKey,
+        session_id: [u8; 32],
+        sequence: u64,
+        rng: &mut R,
+    ) -> Envelope {
+        let sender_secret = Scalar::from(sender);
+        let sender_key = sender_secret.base_point_mul();
+        let ephemeral_secret = Scalar::from(k256::NonZeroScalar::random(&mut *rng));
+        let ephemeral_key = ephemeral_secret.base_point_mul();
+
+        let mut envelope = Envelope {
+            sender: key_fingerprint(&sender_key),
+            recipient: key_fingerprint(recipient),
+            session_id,
+            sequence,
+            ephemeral_key,
+            ciphertext: Vec::new(),
+        };
+        let key = envelope_key(
+            &ephemeral_key,
+            &sender_key,
+            recipient,
+            &(ephemeral_secret * *recipient),
+            &(sender_secret * *recipient),
+        );
+        let header = envelope.header();
+        envelope.ciphertext = cipher(&key)
+            .encrypt(&Nonce::default(), Payload { msg: payload, aad: &header })
+            .expect("payloads are much shorter than the ChaCha20 limit");
+        envelope
+    }
+
+    /// Decrypts the payload of an envelope sent by `sender` to `recipient`.
+    ///
+    /// The envelope is checked against `guard`, which is updated only if decryption succeeds.
+    pub fn open(
+        &self,
+        recipient: &SecretKey,
+        sender: &PublicKey,
+        guard: &mut ReplayGuard,
+    ) -> Result<Vec<u8>, EnvelopeError> {
+        let recipient_secret = Scalar::from(recipient);
+        let recipient_key = recipient_secret.base_point_mul();
+        if self.recipient != key_fingerprint(&recipient_key) {
+            return Err(EnvelopeError::WrongRecipient);
+        }
+        if self.sender != key_fingerprint(sender) {
+            return Err(EnvelopeError::WrongSender);
+        }
+        guard.check(self)?;
+
+        let key = envelope_key(
+            &self.ephemeral_key,
+            sender,
+            &recipient_key,
+            &(recipient_secret * self.ephemeral_key),
+            &(recipient_secret * *sender),
+        );
+        let header = self.header();
+        let payload = cipher(&key)
+            .decrypt(&Nonce::default(), Payload { msg: &self.ciphertext, aad: &header })
+            .map_err(|_| EnvelopeError::Decryption)?;
+
+        guard.last.insert(self.sender, self.sequence);
+        Ok(payload)
+    }
+
+    /// Serializes the envelope in the [`CURRENT_VERSION`] format.
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut buf = self.header();
+        self.ciphertext.consensus_encode(&mut buf).expect("in-memory writers don't error");
+        buf
+    }
+
+    /// Deserializes an envelope.
+    pub fn deserialize(bytes: &[u8]) -> Result<Self, EnvelopeError> {
+        if bytes.len() < HEADER_LEN {
+            return Err(EnvelopeError::TooShort);
+        }
+        if bytes[..ENVELOPE_MAGIC.len()] != ENVELOPE_MAGIC {
+            return Err(EnvelopeError::InvalidMagic);
+        }
+        match bytes[ENVELOPE_MAGIC.len()] {
+            CURRENT_VERSION => {}
+            v => return Err(EnvelopeError::UnsupportedVersion(v)),
+        }
+
+        let mut r = &bytes[ENVELOPE_MAGIC.len() + 1..];
+        let sender = Fingerprint::from(<[u8; 4]>::consensus_decode(&mut r)?);
+        let recipient = Fingerprint::from(<[u8; 4]>::consensus_decode(&mut r)?);
+        let session_id = Decodable::consensus_decode(&mut r)?;
+        let sequence = Decodable::consensus_decode(&mut r)?;
+        let ephemeral_key = PublicKey::from_slice(&<[u8; 33]>::consensus_decode(&mut r)?)
+            .map_err(|_| encode::Error::ParseFailed("invalid ephemeral key"))?;
+        let ciphertext = Decodable::consensus_decode(&mut r)?;
+        if !r.is_empty() {
+            return Err(encode::Error::ParseFailed("trailing data in envelope").into());
+        }
+
+        Ok(Envelope { sender, recipient, session_id, sequence, ephemeral_key, ciphertext })
+    }
+
+    /// Returns the serialized header, the associated data of the ciphertext.
+    fn header(&self) -> Vec<u8> {
+        let mut header = Vec::with_capacity(HEADER_LEN);
+        header.extend_from_slice(&ENVELOPE_MAGIC);
+        header.push(CURRENT_VERSION);
+        header.extend_from_slice(self.sender.as_bytes());
+        header.extend_from_slice(self.recipient.as_bytes());
+        header.extend_from_slice(&self.session_id);
+        header.extend_from_slice(&self.sequence.to_le_bytes());
+        header.extend_from_slice(&self.ephemeral_key.serialize());
+        header
+    }
+}
+
+/// Derives the key of an envelope.
+fn envelope_key(
+    ephemeral_key: &PublicKey,
+    sender: &PublicKey,
+    recipient: &PublicKey,
+    ephemeral_shared: &PublicKey,
+    static_shared: &PublicKey,
+) -> EnvelopeKeyHash {
+    let mut engine = EnvelopeKeyHash::engine();
+    for point in [ephemeral_key, sender, recipient, ephemeral_shared, static_shared] {
+        engine.input(&point.serialize());
+    }
+    EnvelopeKeyHash::from_engine(engine)
+}
+
+/// Returns the cipher keyed with `key`.
+///
+/// Every envelope has its own key so the nonce is always zero.
+fn cipher(key: &EnvelopeKeyHash) -> ChaCha20Poly1305 {
+    ChaCha20Poly1305::new(&Key::from(key.to_byte_array()))
+}
+
+/// The recipient's record of the envelopes accepted in a session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplayGuard {
+    session_id: [u8; 32],
+    /// The last sequence number accepted from every sender.
+    last: BTreeMap<Fingerprint, u64>,
+}
+
+impl ReplayGuard {
+    /// Creates a guard accepting envelopes of the session `session_id`.
+    pub fn new(session_id: [u8; 32]) -> Self { ReplayGuard { session_id, last: BTreeMap::new() } }
+
+    /// Returns the last sequence number accepted from the sender with fingerprint `sender`.
+    pub fn last_sequence(&self, sender: Fingerprint) -> Option<u64> {
+        self.last.get(&sender).copied()
+    }
+
+    /// Checks that `envelope` belongs to the session and was not accepted before.
+    fn check(&self, envelope: &Envelope) -> Result<(), EnvelopeError> {
+        if envelope.session_id != self.session_id {
+            return Err(EnvelopeError::WrongSession);
+        }
+        match self.last_sequence(envelope.sender) {
+            Some(last) if envelope.sequence <= last =>
+                Err(EnvelopeError::Replayed { sequence: envelope.sequence, last }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// An error reading or opening an envelope.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum EnvelopeError {
+    /// The data is too short to be an envelope.
+    TooShort,
+    /// The data does not start with [`ENVELOPE_MAGIC`].
+    InvalidMagic,
+    /// The envelope was written by a newer, unknown, version of the format.
+    UnsupportedVersion(u8),
+    /// The envelope is malformed.
+    Decode(encode::Error),
+    /// The envelope is addressed to another key.
+    WrongRecipient,
+    /// The envelope was sent by another key.
+    WrongSender,
+    /// The envelope belongs to another session.
+    WrongSession,
+    /// The sequence number of the envelope is not greater than the last one accepted.
+    Replayed {
+        /// The sequence number of the envelope.
+        sequence: u64,
+        /// The last sequence number accepted from the sender.
+        last: u64,
+    },
+    /// The ciphertext failed authentication, either the envelope was tampered with or the keys
+    /// are wrong.
+    Decryption,
+}
+
+internals::impl_from_infallible!(EnvelopeError);
+
+impl fmt::Display for EnvelopeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use EnvelopeError::*;
+
+        match *self {
+            TooShort => f.write_str("data too short to be an envelope"),
+            InvalidMagic => f.write_str("invalid envelope magic"),
+            UnsupportedVersion(v) => write!(f, "unsupported envelope version {}", v),
+            Decode(ref e) => write_err!(f, "malformed envelope"; e),
+            WrongRecipient => f.write_str("envelope addressed to another key"),
+            WrongSender => f.write_str("envelope sent by another key"),
+            WrongSession => f.write_str("envelope from another session"),
+            Replayed { sequence, last } => write!(
+                f,
+                "replayed envelope, sequence number {} but {} was already accepted",
+                sequence, last
+            ),
+            Decryption => f.write_str("envelope decryption failed"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for EnvelopeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use EnvelopeError::*;
+
+        match *self {
+            Decode(ref e) => Some(e),
+            TooShort
+            | InvalidMagic
+            | UnsupportedVersion(_)
+            | WrongRecipient
+            | WrongSender
+            | WrongSession
+            | Replayed { .. }
+            | Decryption => None,
+        }
+    }
+}
+
+impl From<encode::Error> for EnvelopeError {
+    fn from(e: encode::Error) -> Self { Self::Decode(e) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockdata::locktime::absolute;
+    use crate::blockdata::transaction::{self, Transaction, TxIn, TxOut};
+    use crate::psbt::Psbt;
+
+    fn secret_key(byte: u8) -> SecretKey { SecretKey::from_slice(&[byte; 32]).unwrap() }
+
+    fn public_key(secret: &SecretKey) -> PublicKey { Scalar::from(secret).base_point_mul() }
+
+    #[test]
+    fn seal_and_open() {
+        let mut rng = rand::thread_rng();
+        let (coordinator, signer) = (secret_key(1), secret_key(2));
+        let session_id = [0xab; 32];
+
+        let psbt = Psbt::from_unsigned_tx(Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![TxIn::default()],
+            output: vec![TxOut::NULL],
+        })
+        .unwrap();
+        let envelope = Envelope::seal(
+            &psbt.serialize(),
+            &coordinator,
+            &public_key(&signer),
+            session_id,
+            0,
+            &mut rng,
+        );
+        assert_eq!(envelope.sender, key_fingerprint(&public_key(&coordinator)));
+        assert_eq!(envelope.recipient, key_fingerprint(&public_key(&signer)));
+
+        let envelope = Envelope::deserialize(&envelope.serialize()).unwrap();
+        let mut guard = ReplayGuard::new(session_id);
+        let payload = envelope.open(&signer, &public_key(&coordinator), &mut guard).unwrap();
+        assert_eq!(Psbt::deserialize(&payload).unwrap(), psbt);
+        assert_eq!(guard.last_sequence(envelope.sender), Some(0));
+
+        // The same envelope, or an older one, is rejected.
+        assert!(matches!(
+            envelope.open(&signer, &public_key(&coordinator), &mut guard),
+            Err(EnvelopeError::Replayed { sequence: 0, last: 0 })
+        ));
+        let next =
+            Envelope::seal(b"next", &coordinator, &public_key(&signer), session_id, 1, &mut rng);
+        assert_eq!(next.open(&signer, &public_key(&coordinator), &mut guard).unwrap(), b"next");
+
+        let mut other_session = ReplayGuard::new([0xcd; 32]);
+        assert!(matches!(
+            next.open(&signer, &public_key(&coordinator), &mut other_session),
+            Err(EnvelopeError::WrongSession)
+        ));
+    }
+
+    #[test]
+    fn rejects_wrong_keys_and_tampering() {
+        let mut rng = rand::thread_rng();
+        let (sender, recipient, other) = (secret_key(1), secret_key(2), secret_key(3));
+        let session_id = [0u8; 32];
+        let envelope =
+            Envelope::seal(b"payload", &sender, &public_key(&recipient), session_id, 7, &mut rng);
+        let mut guard = ReplayGuard::new(session_id);
+
+        assert!(matches!(
+            envelope.open(&other, &public_key(&sender), &mut guard),
+            Err(EnvelopeError::WrongRecipient)
+        ));
+        assert!(matches!(
+            envelope.open(&recipient, &public_key(&other), &mut guard),
+            Err(EnvelopeError::WrongSender)
+        ));
+
+        // Claiming to be another sender doesn't give the right key.
+        let mut forged =
+            Envelope::seal(b"forged", &other, &public_key(&recipient), session_id, 8, &mut rng);
+        forged.sender = envelope.sender;
+        assert!(matches!(
+            forged.open(&recipient, &public_key(&sender), &mut guard),
+            Err(EnvelopeError::Decryption)
+        ));
+
+        // Both the header and the ciphertext are authenticated.
+        let mut tampered = envelope.clone();
+        tampered.sequence += 1;
+        assert!(matches!(
+            tampered.open(&recipient, &public_key(&sender), &mut guard),
+            Err(EnvelopeError::Decryption)
+        ));
+        let mut tampered = envelope.clone();
+        tampered.ciphertext[0] ^= 1;
+        assert!(matches!(
+            tampered.open(&recipient, &public_key(&sender), &mut guard),
+            Err(EnvelopeError::Decryption)
+        ));
+
+        // Failed attempts don't advance the guard.
+        assert_eq!(guard.last_sequence(envelope.sender), None);
+        assert_eq!(
+            envelope.open(&recipient, &public_key(&sender), &mut guard).unwrap(),
+            b"payload"
+        );
+    }
+
+    #[test]
+    fn deserialize_errors() {
+        let mut rng = rand::thread_rng();
+        let envelope =
+            Envelope::seal(b"", &secret_key(1), &public_key(&secret_key(2)), [0; 32], 0, &mut rng);
+        let bytes = envelope.serialize();
+        assert_eq!(bytes.len(), HEADER_LEN + 1 + 16);
+
+        assert!(matches!(
+            Envelope::deserialize(&bytes[..HEADER_LEN]),
+            Err(EnvelopeError::Decode(_))
+        ));
+        assert!(matches!(Envelope::deserialize(&bytes[..10]), Err(EnvelopeError::TooShort)));
+
+        let mut bad = bytes.clone();
+        bad[0] = b'X';
+        assert!(matches!(Envelope::deserialize(&bad), Err(EnvelopeError::InvalidMagic)));
+
+        let mut bad = bytes.clone();
+        bad[4] = 2;
+        assert!(matches!(Envelope::deserialize(&bad), Err(EnvelopeError::UnsupportedVersion(2))));
+
+        let mut bad = bytes;
+        bad.push(0);
+        assert!(matches!(Envelope::deserialize(&bad), Err(EnvelopeError::Decode(_))));
+    }
+}
@@ -0,0 +1,266 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Key validity windows.
+//!
+//! Key-hygiene policies give every key a validity window after which it is rotated out. This
+//! module supports an extension of the output descriptor syntax annotating a key with its window,
+//! as unix timestamps:
+//!
+//! ```text
+//! wsh(sortedmulti(2,[d34db33f/48'/0'/0'/2']xpub.../0/*?valid=1700000000..1800000000,...))
+//! ```
+//!
+//! Annotations are not understood by other descriptor parsers, [`AnnotatedDescriptor::parse`]
+//! strips them and returns the windows of the annotated keys. A wallet should not derive new
+//! receive addresses from a key outside its window, and should flag keys whose window ends within
+//! its rotation warning period, see [`KeyValidity::status`].
+//!
+
+use core::fmt;
+use core::str::FromStr;
+
+use crate::multisig_setup::descriptor_checksum;
+use crate::prelude::*;
+
+/// The separator between a descriptor key and its validity annotation.
+pub const ANNOTATION_SEPARATOR: char = '?';
+
+/// The validity window of a key, in unix timestamps.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct KeyValidity {
+    not_before: u32,
+    not_after: u32,
+}
+
+impl KeyValidity {
+    /// Creates the window from `not_before` to `not_after`, both inclusive.
+    pub fn new(not_before: u32, not_after: u32) -> Result<Self, ExpiryError> {
+        if not_before > not_after {
+            return Err(ExpiryError::InvalidWindow { not_before, not_after });
+        }
+        Ok(KeyValidity { not_before, not_after })
+    }
+
+    /// Returns the first time the key is valid.
+    pub fn not_before(&self) -> u32 { self.not_before }
+
+    /// Returns the last time the key is valid.
+    pub fn not_after(&self) -> u32 { self.not_after }
+
+    /// Returns the status of the key at `now`, nearing expiry if it ends within
+    /// `rotation_warning` seconds.
+    pub fn status(&self, now: u32, rotation_warning: u32) -> KeyStatus {
+        if now < self.not_before {
+            KeyStatus::NotYetValid
+        } else if now > self.not_after {
+            KeyStatus::Expired
+        } else if self.not_after - now < rotation_warning {
+            KeyStatus::NearingExpiry
+        } else {
+            KeyStatus::Valid
+        }
+    }
+}
+
+impl fmt::Display for KeyValidity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "valid={}..{}", self.not_before, self.not_after)
+    }
+}
+
+impl FromStr for KeyValidity {
+    type Err = ExpiryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ExpiryError::InvalidAnnotation(s.to_owned());
+        let (not_before, not_after) =
+            s.strip_prefix("valid=").and_then(|s| s.split_once("..")).ok_or_else(invalid)?;
+        let not_before = not_before.parse().map_err(|_| invalid())?;
+        let not_after = not_after.parse().map_err(|_| invalid())?;
+        KeyValidity::new(not_before, not_after)
+    }
+}
+
+/// The status of a key at a given time.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum KeyStatus {
+    /// The window of the key hasn't started yet.
+    NotYetValid,
+    /// The key is valid.
+    Valid,
+    /// The key is valid but its window ends within the rotation warning period.
+    NearingExpiry,
+    /// The window of the key has ended.
+    Expired,
+}
+
+impl KeyStatus {
+    /// Returns whether new addresses may be derived from the key.
+    pub fn is_usable(self) -> bool { matches!(self, KeyStatus::Valid | KeyStatus::NearingExpiry) }
+}
+
+impl fmt::Display for KeyStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            KeyStatus::NotYetValid => f.write_str("not yet valid"),
+            KeyStatus::Valid => f.write_str("valid"),
+            KeyStatus::NearingExpiry => f.write_str("nearing expiry"),
+            KeyStatus::Expired => f.write_str("expired"),
+        }
+    }
+}
+
+/// Returns `key` annotated with its validity window.
+pub fn annotate_key(key: &str, validity: &KeyValidity) -> String {
+    format!("{}{}{}", key, ANNOTATION_SEPARATOR, validity)
+}
+
+/// A descriptor with validity annotations, split into the plain descriptor and the windows.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AnnotatedDescriptor {
+    /// The descriptor without annotations, with a checksum if the annotated one had one.
+    pub descriptor: String,
+    /// The annotated keys, as written in the descriptor, and their windows.
+    pub keys: Vec<(String, KeyValidity)>,
+}
+
+impl AnnotatedDescriptor {
+    /// Parses the annotations of `descriptor`.
+    ///
+    /// If the descriptor has a checksum it is checked against the annotated descriptor, and the
+    /// plain descriptor gets its own checksum.
+    pub fn parse(descriptor: &str) -> Result<Self, ExpiryError> {
+        let (body, checksum) = match descriptor.rsplit_once('#') {
+            Some((body, checksum)) => (body, Some(checksum)),
+            None => (descriptor, None),
+        };
+        if let Some(checksum) = checksum {
+            if descriptor_checksum(body).as_deref() != Some(checksum) {
+                return Err(ExpiryError::InvalidChecksum);
+            }
+        }
+
+        let mut plain = String::with_capacity(body.len());
+        let mut keys = Vec::new();
+        let mut rest = body;
+        while let Some(pos) = rest.find(ANNOTATION_SEPARATOR) {
+            let (before, after) = rest.split_at(pos);
+            let key_start = before.rfind(['(', ',']).map_or(0, |i| i + 1);
+            let end = after.find([')', ',']).unwrap_or(after.len());
+            let validity = after[1..end].parse()?;
+            keys.push((before[key_start..].to_owned(), validity));
+            plain.push_str(before);
+            rest = &after[end..];
+        }
+        plain.push_str(rest);
+
+        if checksum.is_some() {
+            let checksum = descriptor_checksum(&plain).ok_or(ExpiryError::InvalidChecksum)?;
+            plain = format!("{}#{}", plain, checksum);
+        }
+        Ok(AnnotatedDescriptor { descriptor: plain, keys })
+    }
+
+    /// Returns the window of `key`, if it is annotated.
+    pub fn validity(&self, key: &str) -> Option<KeyValidity> {
+        self.keys.iter().find(|(k, _)| k == key).map(|(_, validity)| *validity)
+    }
+}
+
+/// An invalid key validity annotation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ExpiryError {
+    /// The annotation is not of the form `valid=<not_before>..<not_after>`.
+    InvalidAnnotation(String),
+    /// The window ends before it starts.
+    InvalidWindow {
+        /// The start of the window.
+        not_before: u32,
+        /// The end of the window.
+        not_after: u32,
+    },
+    /// The descriptor checksum is invalid.
+    InvalidChecksum,
+}
+
+internals::impl_from_infallible!(ExpiryError);
+
+impl fmt::Display for ExpiryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use ExpiryError::*;
+
+        match *self {
+            InvalidAnnotation(ref s) => write!(f, "invalid key validity annotation '{}'", s),
+            InvalidWindow { not_before, not_after } => write!(
+                f,
+                "key validity window ends at {} before it starts at {}",
+                not_after, not_before
+            ),
+            InvalidChecksum => f.write_str("invalid descriptor checksum"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ExpiryError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use ExpiryError::*;
+
+        match *self {
+            InvalidAnnotation(_) | InvalidWindow { .. } | InvalidChecksum => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status() {
+        let validity = KeyValidity::new(1000, 2000).unwrap();
+        assert_eq!(validity.status(999, 100), KeyStatus::NotYetValid);
+        assert_eq!(validity.status(1000, 100), KeyStatus::Valid);
+        assert_eq!(validity.status(1901, 100), KeyStatus::NearingExpiry);
+        assert_eq!(validity.status(2000, 100), KeyStatus::NearingExpiry);
+        assert_eq!(validity.status(2001, 100), KeyStatus::Expired);
+        assert!(!KeyStatus::Expired.is_usable());
+        assert!(KeyStatus::NearingExpiry.is_usable());
+
+        assert_eq!(
+            KeyValidity::new(2000, 1000),
+            Err(ExpiryError::InvalidWindow { not_before: 2000, not_after: 1000 })
+        );
+        assert_eq!("valid=1000..2000".parse::<KeyValidity>(), Ok(validity));
+        assert_eq!(validity.to_string(), "valid=1000..2000");
+        assert!("valid=1000".parse::<KeyValidity>().is_err());
+        assert!("after=1000..2000".parse::<KeyValidity>().is_err());
+    }
+
+    #[test]
+    fn parse_annotations() {
+        let validity = KeyValidity::new(1000, 2000).unwrap();
+        let key_a = "[d34db33f/48'/0'/0'/2']xpubA/0/*";
+        let body = format!("wsh(sortedmulti(2,{},xpubB/0/*))", annotate_key(key_a, &validity));
+        let plain = format!("wsh(sortedmulti(2,{},xpubB/0/*))", key_a);
+
+        let parsed = AnnotatedDescriptor::parse(&body).unwrap();
+        assert_eq!(parsed.descriptor, plain);
+        assert_eq!(parsed.keys, vec![(key_a.to_owned(), validity)]);
+        assert_eq!(parsed.validity(key_a), Some(validity));
+        assert_eq!(parsed.validity("xpubB/0/*"), None);
+
+        let checksum = descriptor_checksum(&body).unwrap();
+        let parsed = AnnotatedDescriptor::parse(&format!("{}#{}", body, checksum)).unwrap();
+        assert_eq!(
+            parsed.descriptor,
+            format!("{}#{}", plain, descriptor_checksum(&plain).unwrap())
+        );
+        assert_eq!(
+            AnnotatedDescriptor::parse(&format!("{}#qqqqqqqq", body)),
+            Err(ExpiryError::InvalidChecksum)
+        );
+        assert!(AnnotatedDescriptor::parse("wpkh(xpub/0/*?valid=2..1)").is_err());
+    }
+}
@@ -93,24 +93,48 @@ mod utils;
 
 #[macro_use]
 pub mod address;
+pub mod backup;
 pub mod bip152;
 pub mod bip158;
 pub mod bip32;
 pub mod blockdata;
+pub mod broadcast;
+pub mod checkpoint;
+pub mod coinjoin;
 pub mod consensus;
+pub mod descriptor_inference;
+#[cfg(feature = "chacha20poly1305")]
+pub mod ecies;
+#[cfg(feature = "chacha20poly1305")]
+pub mod envelope;
 pub mod p2p;
 // // Private until we either make this a crate or flatten it - still to be decided.
 pub mod common;
 pub(crate) mod crypto;
 pub mod error;
 pub mod hash_types;
+pub mod key_expiry;
 pub mod merkle_tree;
+pub mod multisig;
+pub mod multisig_setup;
 pub mod network;
+pub mod network_check;
+pub mod payment_request;
 pub mod policy;
 pub mod pow;
+pub mod presigned;
 pub mod psbt;
 pub mod sign_message;
+pub mod signing_session;
+pub mod silent_payments;
+pub mod spend_proof;
 pub mod taproot;
+pub mod template;
+pub mod vault;
+pub mod wallet_registration;
+pub mod watch_only;
+#[cfg(feature = "chacha20poly1305")]
+pub mod watchtower;
 
 #[rustfmt::skip]                // Keep public re-exports separate.
 #[doc(inline)]
@@ -133,18 +157,33 @@ pub use crate::{
     common::types::{InvalidParityValue, Parity},
     consensus::encode::VarInt,
     consensus::params,
+    crypto::ecdh,
     crypto::ecdsa,
+    crypto::ellswift,
     crypto::error::Error as CryptoError,
+    crypto::frost,
+    crypto::hashes::{tagged_engine, tagged_hash, tagged_hash_to_scalar},
+    crypto::kdf,
+    crypto::musig2,
     crypto::key::{self, PrivateKey, PubkeyHash, PublicKey, CompressedPublicKey, WPubkeyHash, MaybePublicKey, G, XOnlyPublicKey},
     crypto::scalar::{Scalar, MaybeScalar},
+    crypto::schnorr,
     crypto::sighash::{self, LegacySighash, SegwitV0Sighash, TapSighash, TapSighashTag},
-    merkle_tree::MerkleBlock,
+    crypto::signer::{self, Signer, SoftwareSigner},
+    crypto::sss,
+    merkle_tree::{MerkleBlock, TxInclusionProof},
     network::{Network, NetworkKind},
     pow::{CompactTarget, Target, Work},
     psbt::Psbt,
     sighash::{EcdsaSighashType, TapSighashType},
     taproot::{TapBranchTag, TapLeafHash, TapLeafTag, TapNodeHash, TapTweakHash, TapTweakTag},
 };
+#[cfg(feature = "cisa-research")]
+#[doc(inline)]
+pub use crate::crypto::cisa;
+#[cfg(feature = "scrypt")]
+#[doc(inline)]
+pub use crate::crypto::scrypt;
 
 #[rustfmt::skip]
 #[allow(unused_imports)]
@@ -14,6 +14,7 @@
 //! ```
 
 mod block;
+mod proof;
 
 use core::cmp::min;
 use core::iter;
@@ -26,7 +27,10 @@ use crate::prelude::*;
 
 #[rustfmt::skip]
 #[doc(inline)]
-pub use self::block::{MerkleBlock, MerkleBlockError, PartialMerkleTree};
+pub use self::{
+    block::{MerkleBlock, MerkleBlockError, PartialMerkleTree},
+    proof::{TxInclusionProof, TxInclusionProofError},
+};
 
 /// Calculates the merkle root of a list of *hashes*, inline (in place) in `hashes`.
 ///
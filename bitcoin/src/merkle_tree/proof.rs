@@ -0,0 +1,288 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Transaction inclusion proofs.
+//!
+//! A [`TxInclusionProof`] proves that a transaction was mined: it holds the header of the block
+//! including the transaction, the merkle path from the txid to the merkle root of the header, the
+//! position of the transaction in the block and optionally the headers of the blocks built on
+//! top of it. [`TxInclusionProof::verify`] checks all of them at once, so that consumers of
+//! payment proofs don't have to assemble the header, merkle and txid checks themselves.
+//!
+//! Only proof of work is checked, a proof doesn't tell whether its blocks are in the best
+//! chain. The work required from the proof should be high enough to make forging it more
+//! expensive than what it proves.
+//!
+
+use core::{cmp, fmt};
+
+use hashes::Hash;
+use internals::write_err;
+
+use crate::blockdata::block::{self, Block, BlockHash, TxMerkleNode, ValidationError};
+use crate::blockdata::transaction::Txid;
+use crate::consensus::encode::Encodable;
+use crate::pow::Work;
+use crate::prelude::*;
+
+/// A proof that a transaction is included in a block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TxInclusionProof {
+    /// The txid of the transaction.
+    pub txid: Txid,
+    /// The header of the block including the transaction.
+    pub header: block::Header,
+    /// The headers of the blocks built on top of [`TxInclusionProof::header`], in chain order.
+    pub confirmations: Vec<block::Header>,
+    /// The hashes of the siblings of the transaction in the merkle tree, from the bottom up.
+    pub merkle_path: Vec<TxMerkleNode>,
+    /// The position of the transaction in the block.
+    pub position: u32,
+}
+
+crate::internal_macros::impl_consensus_encoding!(
+    TxInclusionProof,
+    txid,
+    header,
+    confirmations,
+    merkle_path,
+    position
+);
+
+impl TxInclusionProof {
+    /// Creates the proof of inclusion of the transaction at `position` in `block`.
+    ///
+    /// Returns `None` if the block has no transaction at `position`.
+    pub fn from_block(block: &Block, position: u32) -> Option<Self> {
+        let txids = block.txdata.iter().map(|tx| tx.compute_txid()).collect::<Vec<_>>();
+        Self::from_txids(block.header, &txids, position)
+    }
+
+    /// Creates the proof of inclusion of the transaction at `position` in the block with header
+    /// `header` and transactions `txids`.
+    ///
+    /// Returns `None` if `txids` has no transaction at `position`. The txids are not checked
+    /// against the header.
+    pub fn from_txids(header: block::Header, txids: &[Txid], position: u32) -> Option<Self> {
+        let txid = *txids.get(position as usize)?;
+        let mut level = txids.iter().map(|txid| TxMerkleNode::from(*txid)).collect::<Vec<_>>();
+        let mut index = position as usize;
+        let mut merkle_path = Vec::new();
+        while level.len() > 1 {
+            // The last node of an odd level is its own sibling.
+            let sibling = cmp::min(index ^ 1, level.len() - 1);
+            merkle_path.push(level[sibling]);
+            level = level
+                .chunks(2)
+                .map(|pair| parent(&pair[0], pair.get(1).unwrap_or(&pair[0])))
+                .collect();
+            index /= 2;
+        }
+        Some(TxInclusionProof { txid, header, confirmations: Vec::new(), merkle_path, position })
+    }
+
+    /// Returns the number of blocks proven to include the transaction or build on top of it.
+    pub fn depth(&self) -> usize { self.confirmations.len() + 1 }
+
+    /// Returns the total work of the headers of the proof.
+    pub fn work(&self) -> Work {
+        self.confirmations.iter().fold(self.header.work(), |work, header| work + header.work())
+    }
+
+    /// Computes the merkle root committed to by the merkle path.
+    pub fn compute_merkle_root(&self) -> Result<TxMerkleNode, TxInclusionProofError> {
+        if self.merkle_path.len() < 32 && self.position >> self.merkle_path.len() != 0 {
+            return Err(TxInclusionProofError::PositionOutOfRange);
+        }
+        let mut node = TxMerkleNode::from(self.txid);
+        for (level, sibling) in self.merkle_path.iter().enumerate() {
+            node = if level < 32 && self.position >> level & 1 == 1 {
+                // A right node is never a copy of its left sibling, see CVE-2012-2459.
+                if *sibling == node {
+                    return Err(TxInclusionProofError::IdenticalHashesFound);
+                }
+                parent(sibling, &node)
+            } else {
+                parent(&node, sibling)
+            };
+        }
+        Ok(node)
+    }
+
+    /// Verifies the proof, returning the hash of the block including the transaction.
+    ///
+    /// Checks the proof of work of every header, that the headers form a chain with at least
+    /// `expected_chain_work_threshold` work, and that the merkle path proves the inclusion of
+    /// the transaction in the first one.
+    pub fn verify(
+        &self,
+        expected_chain_work_threshold: Work,
+    ) -> Result<BlockHash, TxInclusionProofError> {
+        let block_hash = self
+            .header
+            .validate_pow(self.header.target())
+            .map_err(|error| TxInclusionProofError::ProofOfWork { depth: 0, error })?;
+        let mut prev_blockhash = block_hash;
+        for (i, header) in self.confirmations.iter().enumerate() {
+            if header.prev_blockhash != prev_blockhash {
+                return Err(TxInclusionProofError::DisconnectedHeader { depth: i + 1 });
+            }
+            prev_blockhash = header
+                .validate_pow(header.target())
+                .map_err(|error| TxInclusionProofError::ProofOfWork { depth: i + 1, error })?;
+        }
+
+        let work = self.work();
+        if work < expected_chain_work_threshold {
+            return Err(TxInclusionProofError::InsufficientWork {
+                work,
+                required: expected_chain_work_threshold,
+            });
+        }
+
+        if self.compute_merkle_root()? != self.header.merkle_root {
+            return Err(TxInclusionProofError::MerkleRootMismatch);
+        }
+        Ok(block_hash)
+    }
+}
+
+/// Returns the parent of two nodes of a merkle tree.
+fn parent(left: &TxMerkleNode, right: &TxMerkleNode) -> TxMerkleNode {
+    let mut engine = TxMerkleNode::engine();
+    left.consensus_encode(&mut engine).expect("engines don't error");
+    right.consensus_encode(&mut engine).expect("engines don't error");
+    TxMerkleNode::from_engine(engine)
+}
+
+/// An error verifying a [`TxInclusionProof`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TxInclusionProofError {
+    /// A header has an invalid proof of work.
+    ProofOfWork {
+        /// The depth of the header, zero for the header including the transaction.
+        depth: usize,
+        /// The proof of work error.
+        error: ValidationError,
+    },
+    /// A header doesn't build on the previous one.
+    DisconnectedHeader {
+        /// The depth of the header.
+        depth: usize,
+    },
+    /// The headers have less work than required.
+    InsufficientWork {
+        /// The work of the headers.
+        work: Work,
+        /// The required work.
+        required: Work,
+    },
+    /// The position doesn't fit in a tree of the height of the merkle path.
+    PositionOutOfRange,
+    /// A right node of the merkle path is a copy of its left sibling.
+    IdenticalHashesFound,
+    /// The merkle path doesn't lead to the merkle root of the header.
+    MerkleRootMismatch,
+}
+
+internals::impl_from_infallible!(TxInclusionProofError);
+
+impl fmt::Display for TxInclusionProofError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use TxInclusionProofError::*;
+
+        match *self {
+            ProofOfWork { depth, ref error } =>
+                write_err!(f, "invalid proof of work of the header at depth {}", depth; error),
+            DisconnectedHeader { depth } =>
+                write!(f, "header at depth {} doesn't build on the previous one", depth),
+            InsufficientWork { work, required } =>
+                write!(f, "headers have {:x} work, {:x} required", work, required),
+            PositionOutOfRange => f.write_str("position out of range of the merkle path"),
+            IdenticalHashesFound => f.write_str("merkle path node is a copy of its sibling"),
+            MerkleRootMismatch =>
+                f.write_str("merkle path doesn't match the merkle root of the header"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TxInclusionProofError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use TxInclusionProofError::*;
+
+        match *self {
+            ProofOfWork { ref error, .. } => Some(error),
+            DisconnectedHeader { .. }
+            | InsufficientWork { .. }
+            | PositionOutOfRange
+            | IdenticalHashesFound
+            | MerkleRootMismatch => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::encode::{deserialize, serialize};
+
+    fn block() -> Block {
+        // testnet block 000000000000045e0b1660b6445b5e5c5ab63c9a4f956be7e1e69be04fa4497b
+        let raw = include_bytes!("../../tests/data/testnet_block_000000000000045e0b1660b6445b5e5c5ab63c9a4f956be7e1e69be04fa4497b.raw");
+        deserialize(&raw[..]).unwrap()
+    }
+
+    #[test]
+    fn verify() {
+        let block = block();
+        let work = block.header.work();
+        for position in 0..block.txdata.len() as u32 {
+            let proof = TxInclusionProof::from_block(&block, position).unwrap();
+            assert_eq!(proof.txid, block.txdata[position as usize].compute_txid());
+            assert_eq!(proof.merkle_path.len(), 4);
+            assert_eq!(proof.verify(work), Ok(block.block_hash()));
+        }
+        assert_eq!(TxInclusionProof::from_block(&block, block.txdata.len() as u32), None);
+
+        let proof = TxInclusionProof::from_block(&block, 14).unwrap();
+        assert_eq!(deserialize::<TxInclusionProof>(&serialize(&proof)).unwrap(), proof);
+        assert_eq!(proof.depth(), 1);
+        assert_eq!(
+            proof.verify(work + work),
+            Err(TxInclusionProofError::InsufficientWork { work, required: work + work })
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        let block = block();
+        let work = block.header.work();
+        let proof = TxInclusionProof::from_block(&block, 5).unwrap();
+
+        let mut wrong = proof.clone();
+        wrong.position = 4;
+        assert_eq!(wrong.verify(work), Err(TxInclusionProofError::MerkleRootMismatch));
+        wrong.position = 16;
+        assert_eq!(wrong.verify(work), Err(TxInclusionProofError::PositionOutOfRange));
+
+        let mut wrong = proof.clone();
+        wrong.txid = block.txdata[0].compute_txid();
+        assert_eq!(wrong.verify(work), Err(TxInclusionProofError::MerkleRootMismatch));
+
+        let mut wrong = proof.clone();
+        wrong.merkle_path[0] = TxMerkleNode::from(wrong.txid);
+        assert_eq!(wrong.verify(work), Err(TxInclusionProofError::IdenticalHashesFound));
+
+        let mut wrong = proof.clone();
+        wrong.header.nonce ^= 1;
+        assert!(matches!(
+            wrong.verify(work),
+            Err(TxInclusionProofError::ProofOfWork { depth: 0, .. })
+        ));
+
+        let mut wrong = proof;
+        wrong.confirmations.push(block.header);
+        assert_eq!(wrong.verify(work), Err(TxInclusionProofError::DisconnectedHeader { depth: 1 }));
+    }
+}
@@ -0,0 +1,305 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! `multi()` and `sortedmulti()` key validation.
+//!
+//! This module checks the keys of a `k`-of-`n` multisig before a descriptor is parsed or a
+//! script is built: the threshold must be between 1 and `n`, no key may appear twice and `n`
+//! may not exceed the limit of the [`MultiContext`] the script is used in.
+//!
+//! [`sort_keys`] orders the keys of a `sortedmulti()` like Bitcoin Core, and [`multi_script`]
+//! builds the script of a checked multisig, using `OP_CHECKSIGADD` in tapscript. [`parse_multi`]
+//! reads the threshold and keys back from such a script.
+//!
+
+use core::fmt;
+
+use crate::blockdata::opcodes::all::{OP_CHECKMULTISIG, OP_CHECKSIG, OP_CHECKSIGADD, OP_NUMEQUAL};
+use crate::blockdata::script::{self, Builder, Instruction, Script, ScriptBuf};
+use crate::common::types::Parity;
+use crate::crypto::key::{PublicKey, XOnlyPublicKey};
+use crate::prelude::*;
+
+/// The script context of a multisig, which limits its number of keys.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum MultiContext {
+    /// A bare `OP_CHECKMULTISIG` output script, standard with up to 3 keys.
+    Bare,
+    /// A P2SH redeem script, whose 520 byte limit fits 15 compressed keys.
+    Sh,
+    /// A P2WSH witness script, limited to the 20 keys of `OP_CHECKMULTISIG`.
+    Wsh,
+    /// A tapscript `multi_a()`, limited to the 999 keys of the tapscript stack.
+    Tapscript,
+}
+
+impl MultiContext {
+    /// Returns the maximum number of keys of a multisig in this context.
+    pub const fn max_keys(self) -> usize {
+        match self {
+            MultiContext::Bare => 3,
+            MultiContext::Sh => 15,
+            MultiContext::Wsh => 20,
+            MultiContext::Tapscript => 999,
+        }
+    }
+}
+
+impl fmt::Display for MultiContext {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MultiContext::Bare => f.write_str("bare"),
+            MultiContext::Sh => f.write_str("p2sh"),
+            MultiContext::Wsh => f.write_str("p2wsh"),
+            MultiContext::Tapscript => f.write_str("tapscript"),
+        }
+    }
+}
+
+/// Sorts `keys` in the order of `sortedmulti()`.
+///
+/// Compressed keys are sorted as in BIP67, followed by uncompressed keys.
+pub fn sort_keys(keys: &mut [PublicKey]) { keys.sort_unstable_by_key(|key| key.to_sort_key()) }
+
+/// Checks the `threshold`-of-`keys.len()` multisig `keys` for `context`.
+///
+/// In tapscript keys are x-only, so two keys differing only by their parity are duplicates.
+pub fn check_multi(
+    threshold: usize,
+    keys: &[PublicKey],
+    context: MultiContext,
+) -> Result<(), MultiError> {
+    if threshold == 0 || threshold > keys.len() {
+        return Err(MultiError::InvalidThreshold { threshold, keys: keys.len() });
+    }
+    if keys.len() > context.max_keys() {
+        return Err(MultiError::TooManyKeys { keys: keys.len(), context });
+    }
+
+    let same = |a: &PublicKey, b: &PublicKey| match context {
+        MultiContext::Tapscript => a.x_only_public_key().0 == b.x_only_public_key().0,
+        _ => a == b,
+    };
+    let mut duplicates: Vec<PublicKey> = Vec::new();
+    for (i, key) in keys.iter().enumerate() {
+        if keys[..i].iter().any(|other| same(other, key))
+            && !duplicates.iter().any(|other| same(other, key))
+        {
+            duplicates.push(*key);
+        }
+    }
+    if !duplicates.is_empty() {
+        return Err(MultiError::DuplicateKeys(duplicates));
+    }
+    Ok(())
+}
+
+/// Builds the script of the `threshold`-of-`keys.len()` multisig `keys` for `context`.
+///
+/// The keys are used in the given order, [`sort_keys`] them first for a `sortedmulti()`.
+pub fn multi_script(
+    threshold: usize,
+    keys: &[PublicKey],
+    context: MultiContext,
+) -> Result<ScriptBuf, MultiError> {
+    check_multi(threshold, keys, context)?;
+    let builder = match context {
+        MultiContext::Tapscript => {
+            let mut builder = Builder::new();
+            for (i, key) in keys.iter().enumerate() {
+                let opcode = if i == 0 { OP_CHECKSIG } else { OP_CHECKSIGADD };
+                builder = builder.push_x_only_key(&key.x_only_public_key().0).push_opcode(opcode);
+            }
+            builder.push_int(threshold as i64).push_opcode(OP_NUMEQUAL)
+        }
+        MultiContext::Bare | MultiContext::Sh | MultiContext::Wsh => {
+            let mut builder = Builder::new().push_int(threshold as i64);
+            for key in keys {
+                builder = builder.push_key(key);
+            }
+            builder.push_int(keys.len() as i64).push_opcode(OP_CHECKMULTISIG)
+        }
+    };
+    Ok(builder.into_script())
+}
+
+/// Parses the threshold and keys of a multisig `script` built by [`multi_script`] for `context`.
+///
+/// Tapscript keys are x-only, they are returned with an even y. Returns `None` if `script` isn't
+/// exactly the script of a valid multisig.
+pub fn parse_multi(script: &Script, context: MultiContext) -> Option<(usize, Vec<PublicKey>)> {
+    let instructions = script.instructions().collect::<Result<Vec<_>, _>>().ok()?;
+    let threshold = match context {
+        MultiContext::Tapscript => instructions.len().checked_sub(2).map(|i| &instructions[i]),
+        _ => instructions.first(),
+    };
+    let threshold = threshold.and_then(pushed_number)?;
+    let keys = instructions
+        .iter()
+        .filter_map(|instruction| match instruction {
+            Instruction::PushBytes(bytes) => match context {
+                MultiContext::Tapscript => XOnlyPublicKey::from_slice(bytes.as_bytes())
+                    .ok()
+                    .map(|key| key.public_key(Parity::Even)),
+                _ => PublicKey::from_slice(bytes.as_bytes()).ok(),
+            },
+            Instruction::Op(_) => None,
+        })
+        .collect::<Vec<_>>();
+    // Only scripts built exactly like a multisig of these keys are multisigs.
+    match multi_script(threshold, &keys, context) {
+        Ok(multi) if multi.as_script() == script => Some((threshold, keys)),
+        _ => None,
+    }
+}
+
+/// Returns the value of a number pushed by `instruction`.
+fn pushed_number(instruction: &Instruction) -> Option<usize> {
+    match *instruction {
+        Instruction::Op(op) => op.decode_pushnum().map(usize::from),
+        Instruction::PushBytes(bytes) =>
+            script::read_scriptint(bytes.as_bytes()).ok().and_then(|n| usize::try_from(n).ok()),
+    }
+}
+
+/// An invalid `multi()` or `sortedmulti()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MultiError {
+    /// The threshold is zero or larger than the number of keys.
+    InvalidThreshold {
+        /// The threshold.
+        threshold: usize,
+        /// The number of keys.
+        keys: usize,
+    },
+    /// There are more keys than allowed in the context.
+    TooManyKeys {
+        /// The number of keys.
+        keys: usize,
+        /// The context of the multisig.
+        context: MultiContext,
+    },
+    /// These keys appear more than once.
+    DuplicateKeys(Vec<PublicKey>),
+}
+
+internals::impl_from_infallible!(MultiError);
+
+impl fmt::Display for MultiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use MultiError::*;
+
+        match *self {
+            InvalidThreshold { threshold, keys } =>
+                write!(f, "invalid {}-of-{} multisig threshold", threshold, keys),
+            TooManyKeys { keys, context } => write!(
+                f,
+                "{} keys in a {} multisig, at most {} allowed",
+                keys,
+                context,
+                context.max_keys()
+            ),
+            DuplicateKeys(ref keys) => {
+                f.write_str("duplicate multisig keys:")?;
+                for key in keys {
+                    write!(f, " {}", key)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for MultiError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use MultiError::*;
+
+        match *self {
+            InvalidThreshold { .. } | TooManyKeys { .. } | DuplicateKeys(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::str::FromStr;
+
+    use super::*;
+    use crate::crypto::key::G;
+    use crate::crypto::scalar::Scalar;
+
+    fn key(i: u8) -> PublicKey { Scalar::reduce_from(&[i; 32]) * G }
+
+    #[test]
+    fn check() {
+        let keys = [key(1), key(2), key(3)];
+        assert_eq!(check_multi(2, &keys, MultiContext::Bare), Ok(()));
+        assert_eq!(
+            check_multi(0, &keys, MultiContext::Wsh),
+            Err(MultiError::InvalidThreshold { threshold: 0, keys: 3 })
+        );
+        assert_eq!(
+            check_multi(4, &keys, MultiContext::Wsh),
+            Err(MultiError::InvalidThreshold { threshold: 4, keys: 3 })
+        );
+
+        let keys = (1..=21).map(key).collect::<Vec<_>>();
+        for (context, max) in [
+            (MultiContext::Bare, 3),
+            (MultiContext::Sh, 15),
+            (MultiContext::Wsh, 20),
+            (MultiContext::Tapscript, 999),
+        ] {
+            assert_eq!(check_multi(1, &keys[..max.min(21)], context), Ok(()));
+            if max < 21 {
+                assert_eq!(
+                    check_multi(1, &keys[..max + 1], context),
+                    Err(MultiError::TooManyKeys { keys: max + 1, context })
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn duplicates() {
+        let keys = [key(1), key(2), key(1), key(3), key(2), key(1)];
+        let err = check_multi(1, &keys, MultiContext::Tapscript).unwrap_err();
+        assert_eq!(err, MultiError::DuplicateKeys(vec![key(1), key(2)]));
+        assert!(err.to_string().contains(&key(2).to_string()));
+
+        // Keys with the same x coordinate are only duplicates in tapscript.
+        let negated = -key(1);
+        assert_eq!(check_multi(1, &[key(1), negated], MultiContext::Wsh), Ok(()));
+        assert_eq!(
+            check_multi(1, &[key(1), negated], MultiContext::Tapscript),
+            Err(MultiError::DuplicateKeys(vec![negated]))
+        );
+    }
+
+    #[test]
+    fn scripts() {
+        let pk = |s| PublicKey::from_str(s).unwrap();
+        let mut keys = [
+            pk("038f47dcd43ba6d97fc9ed2e3bba09b175a45fac55f0683e8cf771e8ced4572354"),
+            pk("028bde91b10013e08949a318018fedbd896534a549a278e220169ee2a36517c7aa"),
+            pk("032b8324c93575034047a52e9bca05a46d8347046b91a032eff07d5de8d3f2730b"),
+        ];
+        sort_keys(&mut keys);
+        assert_eq!(keys[0].to_string()[..4], *"028b");
+        assert_eq!(keys[2].to_string()[..4], *"038f");
+
+        let script = multi_script(2, &keys, MultiContext::Wsh).unwrap();
+        let pushes = keys.iter().map(|key| format!("OP_PUSHBYTES_33 {} ", key)).collect::<String>();
+        assert_eq!(
+            script.to_asm_string(),
+            format!("OP_PUSHNUM_2 {}OP_PUSHNUM_3 OP_CHECKMULTISIG", pushes)
+        );
+        assert_eq!(parse_multi(&script, MultiContext::Wsh), Some((2, keys.to_vec())));
+        assert_eq!(parse_multi(&script, MultiContext::Tapscript), None);
+        let script = multi_script(2, &keys, MultiContext::Tapscript).unwrap();
+        assert_eq!(script.len(), 3 * 34 + 2);
+        assert_eq!(parse_multi(&script, MultiContext::Tapscript).map(|(k, _)| k), Some(2));
+        assert!(multi_script(2, &keys[..1], MultiContext::Bare).is_err());
+    }
+}
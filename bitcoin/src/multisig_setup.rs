@@ -0,0 +1,569 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Multisig setup ceremony.
+//!
+//! This module provides helpers for coordinating the setup of a sorted `m`-of-`n` P2WSH multisig
+//! wallet between several cosigners:
+//!
+//! 1. The coordinator picks a random 32 byte challenge and sends it to every cosigner.
+//! 2. Each cosigner answers with a [`CosignerKey`]: an xpub, its key origin and a BIP340 proof
+//!    that it controls the xpub, signed over the challenge.
+//! 3. The coordinator collects the keys into a [`MultisigSetup`], which checks every proof.
+//! 4. Each cosigner independently builds the same setup and returns a [`SetupSummary`] of the
+//!    descriptor and first few addresses it derived. [`verify_agreement`] checks that all
+//!    cosigners agree before any funds are sent to the wallet.
+//!
+//! The finished setup can be exported as a Coldcard-style multisig registration file with
+//! [`MultisigSetup::to_coldcard_file`].
+//!
+//! Cosigner keys can be given validity windows, see [`crate::key_expiry`]: no new receive address
+//! is derived with [`MultisigSetup::new_receive_address`] once a key has expired.
+//!
+
+use core::fmt;
+
+use hashes::{sha256t_hash_newtype, Hash, HashEngine};
+use internals::write_err;
+use k256::schnorr::signature::hazmat::PrehashVerifier;
+
+use crate::address::Address;
+use crate::bip32::{self, ChildNumber, DerivationPath, Fingerprint, KeySource, Xpriv, Xpub};
+use crate::blockdata::script::ScriptBuf;
+use crate::crypto::key::PublicKey;
+use crate::key_expiry::{self, AnnotatedDescriptor, ExpiryError, KeyStatus, KeyValidity};
+use crate::multisig::{self, MultiContext, MultiError};
+use crate::network::Network;
+use crate::network_check::{self, NetworkCheckError};
+use crate::prelude::*;
+use crate::wallet_registration::ColdcardFile;
+use crate::watch_only::WalletId;
+
+/// The maximum number of keys in a standard P2WSH `OP_CHECKMULTISIG` script.
+pub const MAX_COSIGNERS: usize = 15;
+
+sha256t_hash_newtype! {
+    pub struct KeyOriginTag = hash_str("MultisigSetup/KeyOrigin");
+
+    /// Taproot-style tagged hash with tag \"MultisigSetup/KeyOrigin\".
+    ///
+    /// This is the message signed by a cosigner to prove control of its xpub, committing to the
+    /// coordinator's challenge, the key origin and the xpub itself.
+    #[hash_newtype(forward)]
+    pub struct KeyOriginHash(_);
+}
+
+/// An xpub contributed by a cosigner, with its key origin and proof of control.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CosignerKey {
+    /// The cosigner's account-level xpub.
+    pub xpub: Xpub,
+    /// The master key fingerprint and the path from the master key to `xpub`.
+    pub origin: KeySource,
+    /// BIP340 signature over [`CosignerKey::proof_hash`] with the key of `xpub`.
+    pub proof: k256::schnorr::Signature,
+}
+
+impl CosignerKey {
+    /// Derives the xpub at `path` from `master` and proves control of it over `challenge`.
+    ///
+    /// `aux_rand` is the BIP340 auxiliary randomness and should be freshly generated.
+    pub fn new(
+        master: &Xpriv,
+        path: &DerivationPath,
+        challenge: &[u8; 32],
+        aux_rand: &[u8; 32],
+    ) -> Result<Self, bip32::Error> {
+        let xpriv = master.derive_priv(path)?;
+        let xpub = Xpub::from_priv(&xpriv);
+        let origin = (master.fingerprint(), path.clone());
+        let msg = Self::proof_hash(challenge, &origin, &xpub).to_byte_array();
+        let proof = xpriv
+            .to_keypair()
+            .to_signing_key()
+            .sign_prehash_with_aux_rand(&msg, aux_rand)
+            .expect("signing a 32 byte digest never fails");
+        Ok(CosignerKey { xpub, origin, proof })
+    }
+
+    /// Computes the message signed to prove control of `xpub` with origin `origin`.
+    pub fn proof_hash(challenge: &[u8; 32], origin: &KeySource, xpub: &Xpub) -> KeyOriginHash {
+        let mut engine = KeyOriginHash::engine();
+        engine.input(challenge);
+        engine.input(origin.0.as_bytes());
+        for child in origin.1.as_ref() {
+            engine.input(&u32::from(*child).to_le_bytes());
+        }
+        engine.input(&xpub.encode());
+        KeyOriginHash::from_engine(engine)
+    }
+
+    /// Verifies the proof of control against `challenge`.
+    pub fn verify(&self, challenge: &[u8; 32]) -> Result<(), SetupError> {
+        let msg = Self::proof_hash(challenge, &self.origin, &self.xpub).to_byte_array();
+        k256::schnorr::VerifyingKey::from_bytes(&self.xpub.to_x_only_pub().serialize())
+            .and_then(|key| key.verify_prehash(&msg, &self.proof))
+            .map_err(|_| SetupError::InvalidProof(self.origin.0))
+    }
+
+    /// Returns the key in descriptor notation, e.g. `[d34db33f/48'/0'/0'/2']xpub.../0/*`.
+    fn descriptor_key(&self) -> String {
+        let (fingerprint, path) = &self.origin;
+        if path.is_master() {
+            format!("[{}]{}/0/*", fingerprint, self.xpub)
+        } else {
+            format!("[{}/{}]{}/0/*", fingerprint, path, self.xpub)
+        }
+    }
+}
+
+/// A sorted `m`-of-`n` P2WSH multisig wallet whose cosigner keys have all been verified.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MultisigSetup {
+    threshold: usize,
+    cosigners: Vec<CosignerKey>,
+    validity: Vec<Option<KeyValidity>>,
+    network: Network,
+}
+
+impl MultisigSetup {
+    /// Creates a `threshold`-of-`cosigners.len()` setup, checking every cosigner's proof of
+    /// control against `challenge` and that every xpub is for `network`.
+    pub fn new(
+        threshold: usize,
+        cosigners: Vec<CosignerKey>,
+        network: Network,
+        challenge: &[u8; 32],
+    ) -> Result<Self, SetupError> {
+        let keys = cosigners.len();
+        if threshold == 0 || threshold > keys || keys > MAX_COSIGNERS {
+            return Err(SetupError::InvalidThreshold { threshold, keys });
+        }
+        for (i, cosigner) in cosigners.iter().enumerate() {
+            if cosigners[..i].iter().any(|other| other.xpub == cosigner.xpub) {
+                return Err(SetupError::DuplicateKey(cosigner.origin.0));
+            }
+            network_check::check_xpub(&cosigner.xpub, network)?;
+            cosigner.verify(challenge)?;
+        }
+        let validity = vec![None; keys];
+        Ok(MultisigSetup { threshold, cosigners, validity, network })
+    }
+
+    /// Returns the number of signatures required to spend.
+    pub fn threshold(&self) -> usize { self.threshold }
+
+    /// Returns the cosigner keys, in the order they were given.
+    pub fn cosigners(&self) -> &[CosignerKey] { &self.cosigners }
+
+    /// Returns the network the wallet's addresses are for.
+    pub fn network(&self) -> Network { self.network }
+
+    /// Returns the output descriptor of the receive chain, including its checksum.
+    ///
+    /// The descriptor has the form `wsh(sortedmulti(m,[origin]xpub/0/*,...))#checksum`.
+    pub fn descriptor(&self) -> String { self.format_descriptor(false) }
+
+    /// Returns the output descriptor of the receive chain with the validity annotations of the
+    /// keys, see [`crate::key_expiry`].
+    pub fn annotated_descriptor(&self) -> String { self.format_descriptor(true) }
+
+    fn format_descriptor(&self, annotated: bool) -> String {
+        let keys = self
+            .cosigners
+            .iter()
+            .zip(&self.validity)
+            .map(|(cosigner, validity)| match validity {
+                Some(validity) if annotated =>
+                    key_expiry::annotate_key(&cosigner.descriptor_key(), validity),
+                _ => cosigner.descriptor_key(),
+            })
+            .collect::<Vec<_>>();
+        let desc = format!("wsh(sortedmulti({},{}))", self.threshold, keys.join(","));
+        let checksum = descriptor_checksum(&desc).expect("descriptor only uses valid characters");
+        format!("{}#{}", desc, checksum)
+    }
+
+    /// Returns the validity windows of the cosigner keys, in the order of the cosigners.
+    pub fn key_validity(&self) -> &[Option<KeyValidity>] { &self.validity }
+
+    /// Sets the validity window of the key of the cosigner at `index`.
+    ///
+    /// # Panics
+    ///
+    /// If there is no cosigner at `index`.
+    pub fn set_key_validity(&mut self, index: usize, validity: Option<KeyValidity>) {
+        self.validity[index] = validity;
+    }
+
+    /// Sets the validity windows of the keys from the annotations of `descriptor`.
+    ///
+    /// Keys without annotation have no window, and every annotated key must be a cosigner key.
+    pub fn apply_annotations(&mut self, descriptor: &str) -> Result<(), SetupError> {
+        let annotated = AnnotatedDescriptor::parse(descriptor)?;
+        let keys = self.cosigners.iter().map(CosignerKey::descriptor_key).collect::<Vec<_>>();
+        if let Some((key, _)) = annotated.keys.iter().find(|(key, _)| !keys.contains(key)) {
+            return Err(SetupError::UnknownKey(key.clone()));
+        }
+        self.validity = keys.iter().map(|key| annotated.validity(key)).collect();
+        Ok(())
+    }
+
+    /// Returns the status at `now` of every cosigner key with a validity window, nearing expiry
+    /// if the window ends within `rotation_warning` seconds.
+    pub fn key_statuses(&self, now: u32, rotation_warning: u32) -> Vec<(Fingerprint, KeyStatus)> {
+        self.cosigners
+            .iter()
+            .zip(&self.validity)
+            .filter_map(|(cosigner, validity)| {
+                validity.map(|validity| (cosigner.origin.0, validity.status(now, rotation_warning)))
+            })
+            .collect()
+    }
+
+    /// Returns the receive address at `index`, checking that every key is valid at `now`.
+    ///
+    /// Unlike [`MultisigSetup::address`], this refuses to derive an address for receiving new
+    /// funds from a key which has expired or isn't valid yet.
+    pub fn new_receive_address(&self, index: u32, now: u32) -> Result<Address, SetupError> {
+        if let Some((cosigner, status)) =
+            self.key_statuses(now, 0).into_iter().find(|(_, status)| !status.is_usable())
+        {
+            return Err(SetupError::UnusableKey { cosigner, status });
+        }
+        self.address(false, index)
+    }
+
+    /// Returns the witness script at `index` of the receive (`change == false`) or change chain.
+    pub fn witness_script(&self, change: bool, index: u32) -> Result<ScriptBuf, SetupError> {
+        let path =
+            [ChildNumber::from_normal_idx(change as u32)?, ChildNumber::from_normal_idx(index)?];
+        let mut keys = self
+            .cosigners
+            .iter()
+            .map(|c| c.xpub.derive_pub(&path).map(|xpub| PublicKey::from(xpub.to_pub())))
+            .collect::<Result<Vec<_>, _>>()?;
+        multisig::sort_keys(&mut keys);
+        Ok(multisig::multi_script(self.threshold, &keys, MultiContext::Wsh)?)
+    }
+
+    /// Returns the address at `index` of the receive (`change == false`) or change chain.
+    pub fn address(&self, change: bool, index: u32) -> Result<Address, SetupError> {
+        Ok(Address::p2wsh(&self.witness_script(change, index)?, self.network))
+    }
+
+    /// Returns the id of the wallet, from its first receive address.
+    pub fn wallet_id(&self) -> Result<WalletId, SetupError> {
+        Ok(WalletId::from_first_script_pubkey(&self.address(false, 0)?.script_pubkey()))
+    }
+
+    /// Summarizes the setup as the descriptor and the first `count` receive addresses, for
+    /// comparison between cosigners with [`verify_agreement`].
+    pub fn summary(&self, count: u32) -> Result<SetupSummary, SetupError> {
+        let addresses = (0..count).map(|i| self.address(false, i)).collect::<Result<_, _>>()?;
+        Ok(SetupSummary { descriptor: self.descriptor(), addresses })
+    }
+
+    /// Returns a Coldcard-style multisig registration file for the wallet named `name`.
+    ///
+    /// See [`ColdcardFile`] for parsing files exported back from the device.
+    pub fn to_coldcard_file(&self, name: &str) -> String {
+        ColdcardFile::from_setup(self, name).to_string()
+    }
+}
+
+/// What a cosigner derived from a [`MultisigSetup`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SetupSummary {
+    /// The output descriptor of the receive chain, including its checksum.
+    pub descriptor: String,
+    /// The first receive addresses.
+    pub addresses: Vec<Address>,
+}
+
+/// Checks that every cosigner derived the same descriptor and addresses.
+///
+/// On disagreement returns the index of the first summary differing from the first one.
+pub fn verify_agreement(summaries: &[SetupSummary]) -> Result<(), SetupError> {
+    match summaries.iter().position(|summary| *summary != summaries[0]) {
+        Some(cosigner) => Err(SetupError::Disagreement { cosigner }),
+        None => Ok(()),
+    }
+}
+
+/// Computes the BIP380 checksum of `desc`, returns `None` if it contains an invalid character.
+pub(crate) fn descriptor_checksum(desc: &str) -> Option<String> {
+    const INPUT_CHARSET: &str = "0123456789()[],'/*abcdefgh@:$%{}IJKLMNOPQRSTUVWXYZ&+-.;<=>?!^_|~ijklmnopqrstuvwxyzABCDEFGH`#\"\\ ";
+    const CHECKSUM_CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+    fn poly_mod(mut c: u64, val: u64) -> u64 {
+        let c0 = c >> 35;
+        c = ((c & 0x7ffffffff) << 5) ^ val;
+        if c0 & 1 != 0 {
+            c ^= 0xf5dee51989;
+        }
+        if c0 & 2 != 0 {
+            c ^= 0xa9fdca3312;
+        }
+        if c0 & 4 != 0 {
+            c ^= 0x1bab10e32d;
+        }
+        if c0 & 8 != 0 {
+            c ^= 0x3706b1677a;
+        }
+        if c0 & 16 != 0 {
+            c ^= 0x644d626ffd;
+        }
+        c
+    }
+
+    let mut c = 1;
+    let mut cls = 0;
+    let mut clscount = 0;
+    for ch in desc.chars() {
+        let pos = INPUT_CHARSET.find(ch)? as u64;
+        c = poly_mod(c, pos & 31);
+        cls = cls * 3 + (pos >> 5);
+        clscount += 1;
+        if clscount == 3 {
+            c = poly_mod(c, cls);
+            cls = 0;
+            clscount = 0;
+        }
+    }
+    if clscount > 0 {
+        c = poly_mod(c, cls);
+    }
+    for _ in 0..8 {
+        c = poly_mod(c, 0);
+    }
+    c ^= 1;
+
+    Some((0..8).map(|j| CHECKSUM_CHARSET[((c >> (5 * (7 - j))) & 31) as usize] as char).collect())
+}
+
+/// An error in a multisig setup ceremony.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SetupError {
+    /// The threshold is zero or larger than the number of keys, or there are too many keys.
+    InvalidThreshold {
+        /// The requested threshold.
+        threshold: usize,
+        /// The number of keys.
+        keys: usize,
+    },
+    /// The same xpub was contributed more than once.
+    DuplicateKey(Fingerprint),
+    /// The proof of control of the cosigner with this master fingerprint is invalid.
+    InvalidProof(Fingerprint),
+    /// Key derivation failed.
+    Derivation(bip32::Error),
+    /// The derived keys don't form a valid multisig.
+    Multi(MultiError),
+    /// A cosigner xpub is for another network than the wallet.
+    Network(NetworkCheckError),
+    /// The summary at this index differs from the first one.
+    Disagreement {
+        /// The index of the disagreeing cosigner.
+        cosigner: usize,
+    },
+    /// A key validity annotation is invalid.
+    Expiry(ExpiryError),
+    /// This annotated key is not a cosigner key.
+    UnknownKey(String),
+    /// The key of a cosigner is not valid.
+    UnusableKey {
+        /// The master fingerprint of the cosigner.
+        cosigner: Fingerprint,
+        /// The status of its key.
+        status: KeyStatus,
+    },
+}
+
+internals::impl_from_infallible!(SetupError);
+
+impl fmt::Display for SetupError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use SetupError::*;
+
+        match *self {
+            InvalidThreshold { threshold, keys } =>
+                write!(f, "invalid {}-of-{} multisig policy", threshold, keys),
+            DuplicateKey(fingerprint) => write!(f, "duplicate xpub from cosigner {}", fingerprint),
+            InvalidProof(fingerprint) =>
+                write!(f, "invalid key origin proof from cosigner {}", fingerprint),
+            Derivation(ref e) => write_err!(f, "key derivation failed"; e),
+            Multi(ref e) => write_err!(f, "invalid multisig script"; e),
+            Network(ref e) => write_err!(f, "cosigner key on the wrong network"; e),
+            Disagreement { cosigner } =>
+                write!(f, "cosigner {} derived a different wallet", cosigner),
+            Expiry(ref e) => write_err!(f, "invalid key validity"; e),
+            UnknownKey(ref key) => write!(f, "annotated key {} is not a cosigner key", key),
+            UnusableKey { cosigner, status } =>
+                write!(f, "key of cosigner {} is {}", cosigner, status),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SetupError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use SetupError::*;
+
+        match *self {
+            Derivation(ref e) => Some(e),
+            Multi(ref e) => Some(e),
+            Network(ref e) => Some(e),
+            Expiry(ref e) => Some(e),
+            InvalidThreshold { .. }
+            | DuplicateKey(_)
+            | InvalidProof(_)
+            | Disagreement { .. }
+            | UnknownKey(_)
+            | UnusableKey { .. } => None,
+        }
+    }
+}
+
+impl From<bip32::Error> for SetupError {
+    fn from(e: bip32::Error) -> Self { Self::Derivation(e) }
+}
+
+impl From<MultiError> for SetupError {
+    fn from(e: MultiError) -> Self { Self::Multi(e) }
+}
+
+impl From<NetworkCheckError> for SetupError {
+    fn from(e: NetworkCheckError) -> Self { Self::Network(e) }
+}
+
+impl From<ExpiryError> for SetupError {
+    fn from(e: ExpiryError) -> Self { Self::Expiry(e) }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::str::FromStr;
+
+    use super::*;
+
+    const CHALLENGE: [u8; 32] = [0x42; 32];
+
+    fn cosigners() -> Vec<CosignerKey> {
+        let path = DerivationPath::from_str("48h/1h/0h/2h").unwrap();
+        (1..=3u8)
+            .map(|i| {
+                let master = Xpriv::new_master(Network::Testnet, &[i; 32]).unwrap();
+                CosignerKey::new(&master, &path, &CHALLENGE, &[i; 32]).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn checksum() {
+        assert_eq!(descriptor_checksum("raw(deadbeef)").unwrap(), "89f8spxm");
+        assert_eq!(descriptor_checksum("raw(deadbeef)\u{e9}"), None);
+    }
+
+    #[test]
+    fn key_origin_proofs() {
+        let keys = cosigners();
+        assert!(keys.iter().all(|key| key.verify(&CHALLENGE).is_ok()));
+        assert_eq!(keys[0].verify(&[0x43; 32]), Err(SetupError::InvalidProof(keys[0].origin.0)));
+
+        let mut forged = keys[0].clone();
+        forged.xpub = keys[1].xpub;
+        assert!(forged.verify(&CHALLENGE).is_err());
+
+        let mut bad = keys.clone();
+        bad[2] = forged;
+        assert!(MultisigSetup::new(2, bad, Network::Testnet, &CHALLENGE).is_err());
+
+        let mut duplicate = keys.clone();
+        duplicate[2] = duplicate[0].clone();
+        assert_eq!(
+            MultisigSetup::new(2, duplicate, Network::Testnet, &CHALLENGE),
+            Err(SetupError::DuplicateKey(keys[0].origin.0))
+        );
+        assert!(matches!(
+            MultisigSetup::new(2, keys.clone(), Network::Bitcoin, &CHALLENGE),
+            Err(SetupError::Network(_))
+        ));
+        assert_eq!(
+            MultisigSetup::new(4, keys, Network::Testnet, &CHALLENGE),
+            Err(SetupError::InvalidThreshold { threshold: 4, keys: 3 })
+        );
+    }
+
+    #[test]
+    fn cosigners_agree() {
+        let setup = MultisigSetup::new(2, cosigners(), Network::Testnet, &CHALLENGE).unwrap();
+        let mut reversed = cosigners();
+        reversed.reverse();
+        let other = MultisigSetup::new(2, reversed, Network::Testnet, &CHALLENGE).unwrap();
+
+        // Keys are sorted in the script, so key order only affects the descriptor.
+        assert_eq!(setup.address(false, 0).unwrap(), other.address(false, 0).unwrap());
+        assert_ne!(setup.address(false, 0).unwrap(), setup.address(true, 0).unwrap());
+
+        // Nor does it affect the wallet id, which only depends on the addresses.
+        assert_eq!(setup.wallet_id().unwrap(), other.wallet_id().unwrap());
+        let three = MultisigSetup::new(3, cosigners(), Network::Testnet, &CHALLENGE).unwrap();
+        assert_ne!(three.wallet_id().unwrap(), setup.wallet_id().unwrap());
+
+        let summary = setup.summary(3).unwrap();
+        assert_eq!(summary.addresses.len(), 3);
+        assert!(summary.descriptor.starts_with("wsh(sortedmulti(2,["));
+        assert_eq!(verify_agreement(&[summary.clone(), setup.summary(3).unwrap()]), Ok(()));
+        assert_eq!(
+            verify_agreement(&[summary, other.summary(3).unwrap()]),
+            Err(SetupError::Disagreement { cosigner: 1 })
+        );
+    }
+
+    #[test]
+    fn key_expiry() {
+        let mut setup = MultisigSetup::new(2, cosigners(), Network::Testnet, &CHALLENGE).unwrap();
+        let fingerprint = setup.cosigners()[1].origin.0;
+        let validity = KeyValidity::new(1000, 2000).unwrap();
+        setup.set_key_validity(1, Some(validity));
+
+        assert_eq!(setup.new_receive_address(0, 1500).unwrap(), setup.address(false, 0).unwrap());
+        assert_eq!(setup.key_statuses(1950, 100), vec![(fingerprint, KeyStatus::NearingExpiry)]);
+        assert_eq!(
+            setup.new_receive_address(0, 2001),
+            Err(SetupError::UnusableKey { cosigner: fingerprint, status: KeyStatus::Expired })
+        );
+        assert!(setup.new_receive_address(0, 999).is_err());
+
+        // The annotations round trip, and the plain descriptor is unchanged.
+        let annotated = setup.annotated_descriptor();
+        assert!(annotated.contains("/0/*?valid=1000..2000,"));
+        assert_eq!(AnnotatedDescriptor::parse(&annotated).unwrap().descriptor, setup.descriptor());
+        let mut other = MultisigSetup::new(2, cosigners(), Network::Testnet, &CHALLENGE).unwrap();
+        other.apply_annotations(&annotated).unwrap();
+        assert_eq!(other.key_validity(), setup.key_validity());
+
+        let unknown = format!("wsh(sortedmulti(1,xpub/0/*?{}))", validity);
+        assert_eq!(
+            other.apply_annotations(&unknown),
+            Err(SetupError::UnknownKey("xpub/0/*".to_owned()))
+        );
+    }
+
+    #[test]
+    fn coldcard_file() {
+        let keys = cosigners();
+        let setup = MultisigSetup::new(2, keys.clone(), Network::Testnet, &CHALLENGE).unwrap();
+        let file = setup.to_coldcard_file("vault");
+
+        assert!(file.contains("Name: vault\n"));
+        assert!(file.contains("Policy: 2 of 3\n"));
+        assert!(file.contains("Derivation: m/48'/1'/0'/2'\n"));
+        assert!(file.contains("Format: P2WSH\n"));
+        for key in &keys {
+            let line = format!("{}: {}\n", key.origin.0.to_string().to_uppercase(), key.xpub);
+            assert!(file.contains(&line));
+        }
+    }
+}
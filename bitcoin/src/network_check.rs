@@ -0,0 +1,268 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Network sanity checks.
+//!
+//! Addresses, extended keys and WIF private keys all encode the network they are meant for,
+//! as an address HRP or version byte or as a key version prefix. Mixing them up, for example by
+//! deriving mainnet addresses from a `tpub` or by sending from a mainnet wallet to a testnet
+//! address, sends funds to a destination nobody can spend from.
+//!
+//! The helpers in this module check an item against the [`Network`] the caller is operating on
+//! and return a [`NetworkCheckError`] naming the offending item. [`check_descriptor`] checks
+//! every extended key, WIF key and `addr()` address found in an output descriptor.
+//!
+//! Extended keys and WIF keys only distinguish mainnet from the test networks, so a `tpub` is
+//! accepted on testnet, signet and regtest alike. Addresses distinguish regtest from the other
+//! test networks.
+//!
+
+use core::fmt;
+use core::str::FromStr;
+
+use internals::write_err;
+
+use crate::address::{Address, NetworkUnchecked};
+use crate::bip32::{self, Fingerprint, Xpriv, Xpub};
+use crate::crypto::key::PrivateKey;
+use crate::network::{Network, NetworkKind};
+use crate::prelude::*;
+
+/// Checks that `address` is valid on `network`.
+pub fn check_address(
+    address: &Address<NetworkUnchecked>,
+    network: Network,
+) -> Result<(), NetworkCheckError> {
+    if address.is_valid_for_network(network) {
+        Ok(())
+    } else {
+        Err(NetworkCheckError::Address { address: address.clone(), expected: network })
+    }
+}
+
+/// Checks that `xpub` is for `network`.
+pub fn check_xpub(xpub: &Xpub, network: Network) -> Result<(), NetworkCheckError> {
+    check_kind(xpub.network, network, || Item::ExtendedKey(xpub.fingerprint()))
+}
+
+/// Checks that `xpriv` is for `network`.
+pub fn check_xpriv(xpriv: &Xpriv, network: Network) -> Result<(), NetworkCheckError> {
+    check_kind(xpriv.network, network, || Item::ExtendedKey(xpriv.fingerprint()))
+}
+
+/// Checks that `key` is for `network`.
+pub fn check_private_key(key: &PrivateKey, network: Network) -> Result<(), NetworkCheckError> {
+    check_kind(key.network, network, || Item::PrivateKey)
+}
+
+/// Checks that every key and address of the output `descriptor` is for `network`.
+///
+/// The descriptor is not otherwise validated: extended keys, WIF private keys and the
+/// arguments of `addr()` are picked out of it and checked on their own. Raw public keys do not
+/// encode a network and are ignored.
+pub fn check_descriptor(descriptor: &str, network: Network) -> Result<(), NetworkCheckError> {
+    // Drop the checksum, which could be mistaken for a key.
+    let descriptor = descriptor.split('#').next().unwrap_or_default();
+
+    for token in descriptor.split(|c: char| !is_base58(c)) {
+        if ["xpub", "tpub"].iter().any(|prefix| token.starts_with(prefix)) {
+            let xpub = Xpub::from_str(token)
+                .map_err(|error| NetworkCheckError::InvalidKey { key: token.to_owned(), error })?;
+            check_xpub(&xpub, network)?;
+        } else if ["xprv", "tprv"].iter().any(|prefix| token.starts_with(prefix)) {
+            let xpriv = Xpriv::from_str(token)
+                .map_err(|error| NetworkCheckError::InvalidKey { key: token.to_owned(), error })?;
+            check_xpriv(&xpriv, network)?;
+        } else if token.len() == 51 || token.len() == 52 {
+            // Could be a WIF private key, or part of something else of the same length.
+            if let Ok(key) = PrivateKey::from_wif(token) {
+                check_private_key(&key, network)?;
+            }
+        }
+    }
+
+    for (start, _) in descriptor.match_indices("addr(") {
+        let rest = &descriptor[start + "addr(".len()..];
+        let address = &rest[..rest.find(')').unwrap_or(rest.len())];
+        // Anything that doesn't parse will be rejected by the descriptor parser.
+        if let Ok(address) = address.parse::<Address<NetworkUnchecked>>() {
+            check_address(&address, network)?;
+        }
+    }
+    Ok(())
+}
+
+fn check_kind(
+    found: NetworkKind,
+    expected: Network,
+    item: impl FnOnce() -> Item,
+) -> Result<(), NetworkCheckError> {
+    if found == NetworkKind::from(expected) {
+        Ok(())
+    } else {
+        Err(NetworkCheckError::Key { item: item(), expected })
+    }
+}
+
+/// Returns true if `c` is in the base58 alphabet.
+fn is_base58(c: char) -> bool { c.is_ascii_alphanumeric() && !matches!(c, '0' | 'O' | 'I' | 'l') }
+
+/// A key whose network was checked, see [`NetworkCheckError::Key`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Item {
+    /// An extended key, identified by its fingerprint.
+    ExtendedKey(Fingerprint),
+    /// A WIF private key.
+    PrivateKey,
+}
+
+impl fmt::Display for Item {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Item::ExtendedKey(ref fingerprint) => write!(f, "extended key {}", fingerprint),
+            Item::PrivateKey => f.write_str("private key"),
+        }
+    }
+}
+
+/// A key, address or descriptor is for another network than the expected one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum NetworkCheckError {
+    /// The address is not valid on the expected network.
+    Address {
+        /// The offending address.
+        address: Address<NetworkUnchecked>,
+        /// The network the address was checked against.
+        expected: Network,
+    },
+    /// The key is for another network.
+    Key {
+        /// The offending key.
+        item: Item,
+        /// The network the key was checked against.
+        expected: Network,
+    },
+    /// An extended key of a descriptor could not be parsed.
+    InvalidKey {
+        /// The key, as found in the descriptor.
+        key: String,
+        /// The parsing error.
+        error: bip32::Error,
+    },
+}
+
+internals::impl_from_infallible!(NetworkCheckError);
+
+impl fmt::Display for NetworkCheckError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use NetworkCheckError::*;
+
+        match *self {
+            Address { ref address, expected } =>
+                write!(f, "address {} is not valid on {}", address.assume_checked_ref(), expected),
+            Key { ref item, expected } => write!(f, "{} is not for {}", item, expected),
+            InvalidKey { ref key, ref error } =>
+                write_err!(f, "invalid extended key {}", key; error),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for NetworkCheckError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use NetworkCheckError::*;
+
+        match *self {
+            InvalidKey { ref error, .. } => Some(error),
+            Address { .. } | Key { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn addresses() {
+        let mainnet = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4".parse().unwrap();
+        let testnet = "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx".parse().unwrap();
+
+        assert!(check_address(&mainnet, Network::Bitcoin).is_ok());
+        assert!(check_address(&testnet, Network::Testnet).is_ok());
+        assert!(check_address(&testnet, Network::Signet).is_ok());
+        assert_eq!(
+            check_address(&testnet, Network::Bitcoin),
+            Err(NetworkCheckError::Address {
+                address: testnet.clone(),
+                expected: Network::Bitcoin
+            })
+        );
+        assert!(check_address(&testnet, Network::Regtest).is_err());
+        assert!(check_address(&mainnet, Network::Testnet).is_err());
+    }
+
+    #[test]
+    fn keys() {
+        let xpriv = Xpriv::new_master(Network::Testnet, &[1; 32]).unwrap();
+        let xpub = Xpub::from_priv(&xpriv);
+        for network in [Network::Testnet, Network::Signet, Network::Regtest] {
+            assert!(check_xpub(&xpub, network).is_ok());
+            assert!(check_xpriv(&xpriv, network).is_ok());
+        }
+        assert_eq!(
+            check_xpub(&xpub, Network::Bitcoin),
+            Err(NetworkCheckError::Key {
+                item: Item::ExtendedKey(xpub.fingerprint()),
+                expected: Network::Bitcoin
+            })
+        );
+        assert!(check_xpriv(&xpriv, Network::Bitcoin).is_err());
+
+        let key =
+            PrivateKey::from_wif("cVt4o7BGAig1UXywgGSmARhxMdzP5qvQsxKkSsc1XEkw3tDTQFpy").unwrap();
+        assert!(check_private_key(&key, Network::Regtest).is_ok());
+        assert_eq!(
+            check_private_key(&key, Network::Bitcoin),
+            Err(NetworkCheckError::Key { item: Item::PrivateKey, expected: Network::Bitcoin })
+        );
+    }
+
+    #[test]
+    fn descriptors() {
+        let xpub = Xpub::from_priv(&Xpriv::new_master(Network::Testnet, &[1; 32]).unwrap());
+        let mainnet = Xpub::from_priv(&Xpriv::new_master(Network::Bitcoin, &[2; 32]).unwrap());
+
+        let descriptor =
+            format!("wsh(sortedmulti(1,[d34db33f/48'/1'/0'/2']{}/0/*))#abcdefgh", xpub);
+        assert!(check_descriptor(&descriptor, Network::Testnet).is_ok());
+        assert!(check_descriptor(&descriptor, Network::Bitcoin).is_err());
+
+        let mixed = format!("wsh(sortedmulti(1,{}/0/*,{}/0/*))", xpub, mainnet);
+        assert_eq!(
+            check_descriptor(&mixed, Network::Testnet),
+            Err(NetworkCheckError::Key {
+                item: Item::ExtendedKey(mainnet.fingerprint()),
+                expected: Network::Testnet
+            })
+        );
+
+        let wif = "wpkh(cVt4o7BGAig1UXywgGSmARhxMdzP5qvQsxKkSsc1XEkw3tDTQFpy)";
+        assert!(check_descriptor(wif, Network::Testnet).is_ok());
+        assert!(check_descriptor(wif, Network::Bitcoin).is_err());
+
+        let addr = "addr(tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx)";
+        assert!(check_descriptor(addr, Network::Testnet).is_ok());
+        assert!(matches!(
+            check_descriptor(addr, Network::Bitcoin),
+            Err(NetworkCheckError::Address { .. })
+        ));
+
+        let truncated = format!("wpkh({})", &xpub.to_string()[..100]);
+        assert!(matches!(
+            check_descriptor(&truncated, Network::Testnet),
+            Err(NetworkCheckError::InvalidKey { .. })
+        ));
+    }
+}
@@ -0,0 +1,1108 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Payment requests.
+//!
+//! This module provides a minimal signed payment protocol in the spirit of BIP70, using BIP340
+//! Schnorr signatures in place of X.509 certificates:
+//!
+//! * [`PaymentRequest`]: an invoice naming an address or a BIP352 silent payment code, an amount,
+//!   an expiry time and a memo, signed by the merchant.
+//! * [`Payment`]: the transactions paying a request together with a refund address, signed by the
+//!   payer.
+//! * [`PaymentAck`]: the merchant's acknowledgement of a payment.
+//! * [`RefundUpdate`]: a change of refund address, signed by the same payer key as the payment.
+//!
+//! Every message has a canonical consensus encoding, and signatures commit to a tagged hash of
+//! every field of the message except the signature itself.
+//!
+//! A [`PaymentProof`] shows that a transaction paying a request was included in a block.
+//! Verifying it only checks it against the block header it carries. Callers must separately
+//! check that this header is part of the best chain and sufficiently buried. The output paying a
+//! silent payment code depends on the inputs of the payer, so only the merchant can check it,
+//! with its secret scan key and the outputs spent by the transaction.
+//!
+
+use core::fmt;
+
+use hashes::{sha256t_hash_newtype, Hash};
+use internals::write_err;
+use io::{BufRead, Write};
+use k256::schnorr::signature::hazmat::PrehashVerifier;
+
+use crate::address::{Address, NetworkUnchecked};
+use crate::consensus::{encode, Decodable, Encodable};
+use crate::crypto::key::{Keypair, PublicKey, XOnlyPublicKey};
+use crate::crypto::scalar::Scalar;
+use crate::internal_macros::impl_consensus_encoding;
+use crate::merkle_tree::{MerkleBlock, MerkleBlockError};
+use crate::prelude::*;
+use crate::silent_payments::{self, SilentPaymentCode, SilentPaymentError};
+use crate::{Amount, Transaction, TxOut, Txid};
+
+sha256t_hash_newtype! {
+    pub struct PaymentRequestTag = hash_str("PaymentRequest");
+
+    /// Taproot-style tagged hash with tag \"PaymentRequest\".
+    ///
+    /// This is the message signed by the merchant, committing to every field of a
+    /// [`PaymentRequest`] except the signature itself.
+    #[hash_newtype(forward)]
+    pub struct PaymentRequestHash(_);
+
+    pub struct PaymentTag = hash_str("Payment");
+
+    /// Taproot-style tagged hash with tag \"Payment\".
+    ///
+    /// This is the message signed by the payer, committing to every field of a [`Payment`]
+    /// except the signature itself.
+    #[hash_newtype(forward)]
+    pub struct PaymentHash(_);
+
+    pub struct PaymentAckTag = hash_str("PaymentAck");
+
+    /// Taproot-style tagged hash with tag \"PaymentAck\".
+    ///
+    /// This is the message signed by the merchant, committing to every field of a
+    /// [`PaymentAck`] except the signature itself.
+    #[hash_newtype(forward)]
+    pub struct PaymentAckHash(_);
+
+    pub struct RefundUpdateTag = hash_str("RefundUpdate");
+
+    /// Taproot-style tagged hash with tag \"RefundUpdate\".
+    ///
+    /// This is the message signed by the payer, committing to every field of a
+    /// [`RefundUpdate`] except the signature itself.
+    #[hash_newtype(forward)]
+    pub struct RefundUpdateHash(_);
+}
+
+/// A BIP340 signature over one of the messages in this module, together with the signer's key.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PaymentSignature {
+    /// The signer's public key.
+    pub public_key: XOnlyPublicKey,
+    /// BIP340 signature over the tagged hash of the message.
+    pub signature: k256::schnorr::Signature,
+}
+
+impl PaymentSignature {
+    fn sign(keypair: &Keypair, msg: [u8; 32], aux_rand: &[u8; 32]) -> Self {
+        let signature = keypair
+            .clone()
+            .to_signing_key()
+            .sign_prehash_with_aux_rand(&msg, aux_rand)
+            .expect("signing a 32 byte digest never fails");
+        let (public_key, _) = keypair.x_only_public_key();
+        PaymentSignature { public_key, signature }
+    }
+
+    fn verify(
+        sig: Option<&Self>,
+        signer: &XOnlyPublicKey,
+        msg: [u8; 32],
+    ) -> Result<(), PaymentSignatureError> {
+        let sig = sig.ok_or(PaymentSignatureError::Unsigned)?;
+        if sig.public_key != *signer {
+            return Err(PaymentSignatureError::WrongKey);
+        }
+        let key = k256::schnorr::VerifyingKey::from_bytes(&signer.serialize())
+            .map_err(|_| PaymentSignatureError::InvalidSignature)?;
+        key.verify_prehash(&msg, &sig.signature)
+            .map_err(|_| PaymentSignatureError::InvalidSignature)
+    }
+}
+
+impl Encodable for PaymentSignature {
+    fn consensus_encode<W: Write + ?Sized>(&self, w: &mut W) -> Result<usize, io::Error> {
+        let len = self.public_key.serialize().consensus_encode(w)?;
+        w.write_all(&self.signature.to_bytes())?;
+        Ok(len + 64)
+    }
+}
+
+impl Decodable for PaymentSignature {
+    fn consensus_decode<R: BufRead + ?Sized>(r: &mut R) -> Result<Self, encode::Error> {
+        let public_key = <[u8; 32]>::consensus_decode(r)?;
+        let mut signature = [0u8; 64];
+        r.read_exact(&mut signature)?;
+        Ok(PaymentSignature {
+            public_key: XOnlyPublicKey::from_slice(&public_key)
+                .map_err(|_| encode::Error::ParseFailed("invalid signer public key"))?,
+            signature: k256::schnorr::Signature::try_from(&signature[..])
+                .map_err(|_| encode::Error::ParseFailed("invalid schnorr signature"))?,
+        })
+    }
+}
+
+/// Encodes an optional signature as a presence byte followed by the signature.
+fn encode_signature<W: Write + ?Sized>(
+    sig: Option<&PaymentSignature>,
+    w: &mut W,
+) -> Result<usize, io::Error> {
+    match sig {
+        Some(sig) => Ok(1u8.consensus_encode(w)? + sig.consensus_encode(w)?),
+        None => 0u8.consensus_encode(w),
+    }
+}
+
+/// Decodes an optional signature encoded by [`encode_signature`].
+fn decode_signature<R: BufRead + ?Sized>(
+    r: &mut R,
+) -> Result<Option<PaymentSignature>, encode::Error> {
+    match u8::consensus_decode(r)? {
+        0 => Ok(None),
+        1 => Ok(Some(Decodable::consensus_decode(r)?)),
+        _ => Err(encode::Error::ParseFailed("invalid signature presence flag")),
+    }
+}
+
+fn encode_address<W: Write + ?Sized>(
+    address: &Address<NetworkUnchecked>,
+    w: &mut W,
+) -> Result<usize, io::Error> {
+    address.assume_checked_ref().to_string().consensus_encode(w)
+}
+
+fn decode_address<R: BufRead + ?Sized>(
+    r: &mut R,
+) -> Result<Address<NetworkUnchecked>, encode::Error> {
+    String::consensus_decode(r)?.parse().map_err(|_| encode::Error::ParseFailed("invalid address"))
+}
+
+/// Encodes a destination as its address or silent payment code string.
+fn encode_destination<W: Write + ?Sized>(
+    destination: &PaymentDestination,
+    w: &mut W,
+) -> Result<usize, io::Error> {
+    match destination {
+        PaymentDestination::Address(address) => encode_address(address, w),
+        PaymentDestination::SilentPayment(code) => code.to_string().consensus_encode(w),
+    }
+}
+
+/// Decodes a destination encoded by [`encode_destination`].
+fn decode_destination<R: BufRead + ?Sized>(r: &mut R) -> Result<PaymentDestination, encode::Error> {
+    let s = String::consensus_decode(r)?;
+    // Silent payment codes are never valid addresses, as their prefixes differ.
+    if let Ok(code) = s.parse::<SilentPaymentCode>() {
+        return Ok(PaymentDestination::SilentPayment(code));
+    }
+    s.parse()
+        .map(PaymentDestination::Address)
+        .map_err(|_| encode::Error::ParseFailed("invalid payment destination"))
+}
+
+/// Where a [`PaymentRequest`] asks to be paid.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PaymentDestination {
+    /// A plain address.
+    Address(Address<NetworkUnchecked>),
+    /// A BIP352 silent payment code, paid to a fresh output derived from the payer's inputs.
+    SilentPayment(SilentPaymentCode),
+}
+
+impl From<Address> for PaymentDestination {
+    fn from(address: Address) -> Self {
+        PaymentDestination::Address(address.as_unchecked().clone())
+    }
+}
+
+impl From<SilentPaymentCode> for PaymentDestination {
+    fn from(code: SilentPaymentCode) -> Self { PaymentDestination::SilentPayment(code) }
+}
+
+/// An invoice-style request for payment.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PaymentRequest {
+    destination: PaymentDestination,
+    amount: Amount,
+    expiry: u32,
+    memo: String,
+    signature: Option<PaymentSignature>,
+}
+
+impl PaymentRequest {
+    /// Creates a new, unsigned, payment request.
+    ///
+    /// `expiry` is a UNIX timestamp, compared against the time of the block a payment is
+    /// included in.
+    pub fn new(
+        destination: impl Into<PaymentDestination>,
+        amount: Amount,
+        expiry: u32,
+        memo: impl Into<String>,
+    ) -> Self {
+        PaymentRequest {
+            destination: destination.into(),
+            amount,
+            expiry,
+            memo: memo.into(),
+            signature: None,
+        }
+    }
+
+    /// Returns where the request asks to be paid.
+    pub fn destination(&self) -> &PaymentDestination { &self.destination }
+
+    /// Returns the address to be paid, if the request names one.
+    ///
+    /// The network is not checked when decoding a request, use
+    /// [`Address::require_network`] before paying it.
+    pub fn address(&self) -> Option<&Address<NetworkUnchecked>> {
+        match self.destination {
+            PaymentDestination::Address(ref address) => Some(address),
+            PaymentDestination::SilentPayment(_) => None,
+        }
+    }
+
+    /// Returns the silent payment code to be paid, if the request names one.
+    ///
+    /// The network is not checked when decoding a request, use
+    /// [`SilentPaymentCode::is_valid_for_network`] before paying it.
+    pub fn silent_payment_code(&self) -> Option<&SilentPaymentCode> {
+        match self.destination {
+            PaymentDestination::Address(_) => None,
+            PaymentDestination::SilentPayment(ref code) => Some(code),
+        }
+    }
+
+    /// Returns the requested amount.
+    pub fn amount(&self) -> Amount { self.amount }
+
+    /// Returns the expiry time of the request as a UNIX timestamp.
+    pub fn expiry(&self) -> u32 { self.expiry }
+
+    /// Returns true if the request has expired at UNIX time `now`.
+    pub fn is_expired(&self, now: u32) -> bool { now > self.expiry }
+
+    /// Returns the memo attached to the request.
+    pub fn memo(&self) -> &str { &self.memo }
+
+    /// Returns the merchant signature, if the request is signed.
+    pub fn signature(&self) -> Option<&PaymentSignature> { self.signature.as_ref() }
+
+    /// Returns the tagged hash of the request as signed by the merchant.
+    pub fn signature_hash(&self) -> PaymentRequestHash {
+        let mut engine = PaymentRequestHash::engine();
+        self.encode_unsigned(&mut engine).expect("engines don't error");
+        PaymentRequestHash::from_engine(engine)
+    }
+
+    /// Signs the request with the merchant's `keypair`, replacing any existing signature.
+    ///
+    /// `aux_rand` is the BIP340 auxiliary randomness and should be freshly generated.
+    pub fn sign(&mut self, keypair: &Keypair, aux_rand: &[u8; 32]) {
+        let msg = self.signature_hash().to_byte_array();
+        self.signature = Some(PaymentSignature::sign(keypair, msg, aux_rand));
+    }
+
+    /// Verifies that the request is signed by `merchant`.
+    pub fn verify_signature(&self, merchant: &XOnlyPublicKey) -> Result<(), PaymentSignatureError> {
+        let msg = self.signature_hash().to_byte_array();
+        PaymentSignature::verify(self.signature.as_ref(), merchant, msg)
+    }
+
+    /// Returns true if `txout` pays at least the requested amount to the requested address.
+    ///
+    /// Always false for silent payment codes, whose outputs can only be recognized with the
+    /// merchant's secret scan key, see [`PaymentProof::verify_silent_payment`].
+    pub fn is_paid_by(&self, txout: &TxOut) -> bool {
+        match self.destination {
+            PaymentDestination::Address(ref address) =>
+                address.assume_checked_ref().matches_script_pubkey(&txout.script_pubkey)
+                    && txout.value >= self.amount,
+            PaymentDestination::SilentPayment(_) => false,
+        }
+    }
+
+    fn encode_unsigned<W: Write + ?Sized>(&self, w: &mut W) -> Result<usize, io::Error> {
+        let mut len = 0;
+        len += encode_destination(&self.destination, w)?;
+        len += self.amount.to_sat().consensus_encode(w)?;
+        len += self.expiry.consensus_encode(w)?;
+        len += self.memo.consensus_encode(w)?;
+        Ok(len)
+    }
+}
+
+impl Encodable for PaymentRequest {
+    fn consensus_encode<W: Write + ?Sized>(&self, w: &mut W) -> Result<usize, io::Error> {
+        Ok(self.encode_unsigned(w)? + encode_signature(self.signature.as_ref(), w)?)
+    }
+}
+
+impl Decodable for PaymentRequest {
+    fn consensus_decode<R: BufRead + ?Sized>(r: &mut R) -> Result<Self, encode::Error> {
+        Ok(PaymentRequest {
+            destination: decode_destination(r)?,
+            amount: Amount::from_sat(Decodable::consensus_decode(r)?),
+            expiry: Decodable::consensus_decode(r)?,
+            memo: Decodable::consensus_decode(r)?,
+            signature: decode_signature(r)?,
+        })
+    }
+}
+
+/// A payment for a [`PaymentRequest`], sent by the payer to the merchant.
+///
+/// The payer's signature authenticates the refund address, so that it can later only be changed
+/// using a [`RefundUpdate`] signed by the same key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Payment {
+    request_hash: PaymentRequestHash,
+    transactions: Vec<Transaction>,
+    refund_address: Address<NetworkUnchecked>,
+    memo: String,
+    signature: Option<PaymentSignature>,
+}
+
+impl Payment {
+    /// Creates a new, unsigned, payment for `request`.
+    pub fn new(
+        request: &PaymentRequest,
+        transactions: Vec<Transaction>,
+        refund_address: Address,
+        memo: impl Into<String>,
+    ) -> Self {
+        Payment {
+            request_hash: request.signature_hash(),
+            transactions,
+            refund_address: refund_address.as_unchecked().clone(),
+            memo: memo.into(),
+            signature: None,
+        }
+    }
+
+    /// Returns the hash of the request this payment is for.
+    pub fn request_hash(&self) -> PaymentRequestHash { self.request_hash }
+
+    /// Returns the transactions paying the request.
+    pub fn transactions(&self) -> &[Transaction] { &self.transactions }
+
+    /// Returns the address refunds should be sent to.
+    pub fn refund_address(&self) -> &Address<NetworkUnchecked> { &self.refund_address }
+
+    /// Returns the memo attached to the payment.
+    pub fn memo(&self) -> &str { &self.memo }
+
+    /// Returns the payer signature, if the payment is signed.
+    pub fn signature(&self) -> Option<&PaymentSignature> { self.signature.as_ref() }
+
+    /// Returns true if this payment is for `request` and one of its transactions pays it.
+    ///
+    /// Always false for requests naming a silent payment code, see [`PaymentRequest::is_paid_by`].
+    pub fn pays(&self, request: &PaymentRequest) -> bool {
+        self.request_hash == request.signature_hash()
+            && self.transactions.iter().flat_map(|tx| &tx.output).any(|out| request.is_paid_by(out))
+    }
+
+    /// Returns the tagged hash of the payment as signed by the payer.
+    pub fn signature_hash(&self) -> PaymentHash {
+        let mut engine = PaymentHash::engine();
+        self.encode_unsigned(&mut engine).expect("engines don't error");
+        PaymentHash::from_engine(engine)
+    }
+
+    /// Signs the payment with the payer's `keypair`, replacing any existing signature.
+    ///
+    /// `aux_rand` is the BIP340 auxiliary randomness and should be freshly generated.
+    pub fn sign(&mut self, keypair: &Keypair, aux_rand: &[u8; 32]) {
+        let msg = self.signature_hash().to_byte_array();
+        self.signature = Some(PaymentSignature::sign(keypair, msg, aux_rand));
+    }
+
+    /// Verifies that the payment is signed by `payer`.
+    pub fn verify_signature(&self, payer: &XOnlyPublicKey) -> Result<(), PaymentSignatureError> {
+        let msg = self.signature_hash().to_byte_array();
+        PaymentSignature::verify(self.signature.as_ref(), payer, msg)
+    }
+
+    fn encode_unsigned<W: Write + ?Sized>(&self, w: &mut W) -> Result<usize, io::Error> {
+        let mut len = 0;
+        len += self.request_hash.to_byte_array().consensus_encode(w)?;
+        len += self.transactions.consensus_encode(w)?;
+        len += encode_address(&self.refund_address, w)?;
+        len += self.memo.consensus_encode(w)?;
+        Ok(len)
+    }
+}
+
+impl Encodable for Payment {
+    fn consensus_encode<W: Write + ?Sized>(&self, w: &mut W) -> Result<usize, io::Error> {
+        Ok(self.encode_unsigned(w)? + encode_signature(self.signature.as_ref(), w)?)
+    }
+}
+
+impl Decodable for Payment {
+    fn consensus_decode<R: BufRead + ?Sized>(r: &mut R) -> Result<Self, encode::Error> {
+        Ok(Payment {
+            request_hash: PaymentRequestHash::from_byte_array(Decodable::consensus_decode(r)?),
+            transactions: Decodable::consensus_decode(r)?,
+            refund_address: decode_address(r)?,
+            memo: Decodable::consensus_decode(r)?,
+            signature: decode_signature(r)?,
+        })
+    }
+}
+
+/// The merchant's acknowledgement of a [`Payment`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PaymentAck {
+    payment_hash: PaymentHash,
+    memo: String,
+    signature: Option<PaymentSignature>,
+}
+
+impl PaymentAck {
+    /// Creates a new, unsigned, acknowledgement of `payment`.
+    pub fn new(payment: &Payment, memo: impl Into<String>) -> Self {
+        PaymentAck { payment_hash: payment.signature_hash(), memo: memo.into(), signature: None }
+    }
+
+    /// Returns the hash of the acknowledged payment.
+    pub fn payment_hash(&self) -> PaymentHash { self.payment_hash }
+
+    /// Returns the memo attached to the acknowledgement.
+    pub fn memo(&self) -> &str { &self.memo }
+
+    /// Returns the merchant signature, if the acknowledgement is signed.
+    pub fn signature(&self) -> Option<&PaymentSignature> { self.signature.as_ref() }
+
+    /// Returns true if this acknowledges `payment`.
+    pub fn acknowledges(&self, payment: &Payment) -> bool {
+        self.payment_hash == payment.signature_hash()
+    }
+
+    /// Returns the tagged hash of the acknowledgement as signed by the merchant.
+    pub fn signature_hash(&self) -> PaymentAckHash {
+        let mut engine = PaymentAckHash::engine();
+        self.encode_unsigned(&mut engine).expect("engines don't error");
+        PaymentAckHash::from_engine(engine)
+    }
+
+    /// Signs the acknowledgement with the merchant's `keypair`, replacing any existing signature.
+    ///
+    /// `aux_rand` is the BIP340 auxiliary randomness and should be freshly generated.
+    pub fn sign(&mut self, keypair: &Keypair, aux_rand: &[u8; 32]) {
+        let msg = self.signature_hash().to_byte_array();
+        self.signature = Some(PaymentSignature::sign(keypair, msg, aux_rand));
+    }
+
+    /// Verifies that the acknowledgement is signed by `merchant`.
+    pub fn verify_signature(&self, merchant: &XOnlyPublicKey) -> Result<(), PaymentSignatureError> {
+        let msg = self.signature_hash().to_byte_array();
+        PaymentSignature::verify(self.signature.as_ref(), merchant, msg)
+    }
+
+    fn encode_unsigned<W: Write + ?Sized>(&self, w: &mut W) -> Result<usize, io::Error> {
+        Ok(self.payment_hash.to_byte_array().consensus_encode(w)?
+            + self.memo.consensus_encode(w)?)
+    }
+}
+
+impl Encodable for PaymentAck {
+    fn consensus_encode<W: Write + ?Sized>(&self, w: &mut W) -> Result<usize, io::Error> {
+        Ok(self.encode_unsigned(w)? + encode_signature(self.signature.as_ref(), w)?)
+    }
+}
+
+impl Decodable for PaymentAck {
+    fn consensus_decode<R: BufRead + ?Sized>(r: &mut R) -> Result<Self, encode::Error> {
+        Ok(PaymentAck {
+            payment_hash: PaymentHash::from_byte_array(Decodable::consensus_decode(r)?),
+            memo: Decodable::consensus_decode(r)?,
+            signature: decode_signature(r)?,
+        })
+    }
+}
+
+/// A change of the refund address of a [`Payment`].
+///
+/// Only valid if signed by the same key that signed the payment.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RefundUpdate {
+    payment_hash: PaymentHash,
+    refund_address: Address<NetworkUnchecked>,
+    signature: Option<PaymentSignature>,
+}
+
+impl RefundUpdate {
+    /// Creates a new, unsigned, refund address update for `payment`.
+    pub fn new(payment: &Payment, refund_address: Address) -> Self {
+        RefundUpdate {
+            payment_hash: payment.signature_hash(),
+            refund_address: refund_address.as_unchecked().clone(),
+            signature: None,
+        }
+    }
+
+    /// Returns the hash of the updated payment.
+    pub fn payment_hash(&self) -> PaymentHash { self.payment_hash }
+
+    /// Returns the new refund address.
+    pub fn refund_address(&self) -> &Address<NetworkUnchecked> { &self.refund_address }
+
+    /// Returns the payer signature, if the update is signed.
+    pub fn signature(&self) -> Option<&PaymentSignature> { self.signature.as_ref() }
+
+    /// Returns the tagged hash of the update as signed by the payer.
+    pub fn signature_hash(&self) -> RefundUpdateHash {
+        let mut engine = RefundUpdateHash::engine();
+        self.encode_unsigned(&mut engine).expect("engines don't error");
+        RefundUpdateHash::from_engine(engine)
+    }
+
+    /// Signs the update with the payer's `keypair`, replacing any existing signature.
+    ///
+    /// `aux_rand` is the BIP340 auxiliary randomness and should be freshly generated.
+    pub fn sign(&mut self, keypair: &Keypair, aux_rand: &[u8; 32]) {
+        let msg = self.signature_hash().to_byte_array();
+        self.signature = Some(PaymentSignature::sign(keypair, msg, aux_rand));
+    }
+
+    /// Verifies that this update applies to `payment` and is signed by the key which signed it.
+    pub fn verify(&self, payment: &Payment) -> Result<(), PaymentSignatureError> {
+        let payer = payment.signature.as_ref().ok_or(PaymentSignatureError::Unsigned)?.public_key;
+        payment.verify_signature(&payer)?;
+        if self.payment_hash != payment.signature_hash() {
+            return Err(PaymentSignatureError::WrongMessage);
+        }
+        let msg = self.signature_hash().to_byte_array();
+        PaymentSignature::verify(self.signature.as_ref(), &payer, msg)
+    }
+
+    fn encode_unsigned<W: Write + ?Sized>(&self, w: &mut W) -> Result<usize, io::Error> {
+        Ok(self.payment_hash.to_byte_array().consensus_encode(w)?
+            + encode_address(&self.refund_address, w)?)
+    }
+}
+
+impl Encodable for RefundUpdate {
+    fn consensus_encode<W: Write + ?Sized>(&self, w: &mut W) -> Result<usize, io::Error> {
+        Ok(self.encode_unsigned(w)? + encode_signature(self.signature.as_ref(), w)?)
+    }
+}
+
+impl Decodable for RefundUpdate {
+    fn consensus_decode<R: BufRead + ?Sized>(r: &mut R) -> Result<Self, encode::Error> {
+        Ok(RefundUpdate {
+            payment_hash: PaymentHash::from_byte_array(Decodable::consensus_decode(r)?),
+            refund_address: decode_address(r)?,
+            signature: decode_signature(r)?,
+        })
+    }
+}
+
+/// Proof that a [`PaymentRequest`] was paid by a transaction included in a block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PaymentProof {
+    /// The paying transaction.
+    pub transaction: Transaction,
+    /// Merkle proof of the inclusion of `transaction`, together with the block header.
+    pub merkle_block: MerkleBlock,
+    /// The index of the output paying the request.
+    pub vout: u32,
+}
+
+impl_consensus_encoding!(PaymentProof, transaction, merkle_block, vout);
+
+impl PaymentProof {
+    /// Creates a new payment proof.
+    pub fn new(transaction: Transaction, merkle_block: MerkleBlock, vout: u32) -> Self {
+        PaymentProof { transaction, merkle_block, vout }
+    }
+
+    /// Returns the txid of the paying transaction.
+    pub fn txid(&self) -> Txid { self.transaction.compute_txid() }
+
+    /// Verifies that this proof pays `request`.
+    ///
+    /// Checks that the transaction is committed to by the block header, that the output pays the
+    /// requested amount to the requested address and that the block was mined before the request
+    /// expired. Returns the amount paid.
+    ///
+    /// Payments of silent payment codes can only be verified by the merchant, with
+    /// [`verify_silent_payment`](Self::verify_silent_payment).
+    pub fn verify(&self, request: &PaymentRequest) -> Result<Amount, PaymentProofError> {
+        let address = match request.destination {
+            PaymentDestination::Address(ref address) => address,
+            PaymentDestination::SilentPayment(_) => return Err(PaymentProofError::ScanKeyRequired),
+        };
+        let txout = self.included_output()?;
+        if !address.assume_checked_ref().matches_script_pubkey(&txout.script_pubkey) {
+            return Err(PaymentProofError::WrongAddress);
+        }
+        self.check_payment(request, txout)
+    }
+
+    /// Verifies that this proof pays `request` as the merchant.
+    ///
+    /// Performs the checks of [`verify`](Self::verify), recognizing the output paying a silent
+    /// payment code with the merchant's secret scan key `scan_secret`, its unlabeled spend key
+    /// `spend_key` and the `labels` of its codes which may share the transaction. `prevouts`
+    /// are the outputs spent by the inputs of the transaction, in the same order. They must be
+    /// looked up by the merchant rather than taken from the payer, as the output recognized
+    /// depends on them.
+    ///
+    /// Requests naming an address are verified as by [`verify`](Self::verify).
+    pub fn verify_silent_payment(
+        &self,
+        request: &PaymentRequest,
+        prevouts: &[TxOut],
+        scan_secret: &Scalar,
+        spend_key: &PublicKey,
+        labels: &[u32],
+    ) -> Result<Amount, PaymentProofError> {
+        let code = match request.destination {
+            PaymentDestination::Address(_) => return self.verify(request),
+            PaymentDestination::SilentPayment(ref code) => code,
+        };
+        if scan_secret.base_point_mul() != code.scan_key() {
+            return Err(PaymentProofError::WrongScanKey);
+        }
+        let txout = self.included_output()?;
+        let output =
+            silent_payments::scan(&self.transaction, prevouts, scan_secret, spend_key, labels)?
+                .into_iter()
+                .find(|output| output.vout == self.vout)
+                .ok_or(PaymentProofError::WrongAddress)?;
+        let paid_key = match output.label {
+            None => *spend_key,
+            Some(m) => silent_payments::labeled_spend_key(scan_secret, spend_key, m),
+        };
+        if paid_key != code.spend_key() {
+            return Err(PaymentProofError::WrongAddress);
+        }
+        self.check_payment(request, txout)
+    }
+
+    /// Returns the output paying the request, checking that the transaction is included.
+    fn included_output(&self) -> Result<&TxOut, PaymentProofError> {
+        let mut matches = vec![];
+        let mut indexes = vec![];
+        self.merkle_block.extract_matches(&mut matches, &mut indexes)?;
+
+        let txid = self.txid();
+        if !matches.contains(&txid) {
+            return Err(PaymentProofError::NotIncluded(txid));
+        }
+
+        self.transaction
+            .output
+            .get(self.vout as usize)
+            .ok_or(PaymentProofError::OutputIndexOutOfRange(self.vout))
+    }
+
+    /// Checks the amount paid by `txout` and the expiry of `request`.
+    fn check_payment(
+        &self,
+        request: &PaymentRequest,
+        txout: &TxOut,
+    ) -> Result<Amount, PaymentProofError> {
+        if txout.value < request.amount {
+            return Err(PaymentProofError::InsufficientAmount {
+                required: request.amount,
+                paid: txout.value,
+            });
+        }
+
+        let time = self.merkle_block.header.time;
+        if request.is_expired(time) {
+            return Err(PaymentProofError::Expired { expiry: request.expiry, block_time: time });
+        }
+
+        Ok(txout.value)
+    }
+}
+
+/// An error verifying the signature of one of the messages in this module.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PaymentSignatureError {
+    /// The message is not signed.
+    Unsigned,
+    /// The message is signed by a different key than the expected one.
+    WrongKey,
+    /// The message refers to a different message than the one given.
+    WrongMessage,
+    /// The signature is not valid.
+    InvalidSignature,
+}
+
+internals::impl_from_infallible!(PaymentSignatureError);
+
+impl fmt::Display for PaymentSignatureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use PaymentSignatureError::*;
+
+        match *self {
+            Unsigned => f.write_str("message is not signed"),
+            WrongKey => f.write_str("message is signed by a different key"),
+            WrongMessage => f.write_str("message refers to a different message"),
+            InvalidSignature => f.write_str("invalid signature"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PaymentSignatureError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use PaymentSignatureError::*;
+
+        match *self {
+            Unsigned | WrongKey | WrongMessage | InvalidSignature => None,
+        }
+    }
+}
+
+/// An error verifying a [`PaymentProof`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PaymentProofError {
+    /// The merkle proof is invalid.
+    MerkleBlock(MerkleBlockError),
+    /// The transaction is not committed to by the merkle proof.
+    NotIncluded(Txid),
+    /// The transaction has no output at the given index.
+    OutputIndexOutOfRange(u32),
+    /// The output does not pay to the requested address or silent payment code.
+    WrongAddress,
+    /// The request names a silent payment code, which only the merchant can verify.
+    ScanKeyRequired,
+    /// The scan key given is not the one of the requested silent payment code.
+    WrongScanKey,
+    /// Scanning the transaction for silent payments failed.
+    SilentPayment(SilentPaymentError),
+    /// The output pays less than the requested amount.
+    InsufficientAmount {
+        /// The requested amount.
+        required: Amount,
+        /// The amount paid.
+        paid: Amount,
+    },
+    /// The block was mined after the request expired.
+    Expired {
+        /// The expiry time of the request.
+        expiry: u32,
+        /// The time of the block including the payment.
+        block_time: u32,
+    },
+}
+
+internals::impl_from_infallible!(PaymentProofError);
+
+impl fmt::Display for PaymentProofError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use PaymentProofError::*;
+
+        match *self {
+            MerkleBlock(ref e) => write_err!(f, "invalid merkle proof"; e),
+            NotIncluded(ref txid) => write!(f, "transaction {} is not included in the proof", txid),
+            OutputIndexOutOfRange(vout) => write!(f, "transaction has no output {}", vout),
+            WrongAddress => f.write_str("output does not pay the requested address"),
+            ScanKeyRequired => f.write_str("verifying a silent payment requires the scan key"),
+            WrongScanKey => f.write_str("scan key is not the one of the requested code"),
+            SilentPayment(ref e) => write_err!(f, "scanning for silent payments failed"; e),
+            InsufficientAmount { required, paid } =>
+                write!(f, "output pays {} but {} was requested", paid, required),
+            Expired { expiry, block_time } =>
+                write!(f, "payment mined at {} after the request expired at {}", block_time, expiry),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PaymentProofError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use PaymentProofError::*;
+
+        match *self {
+            MerkleBlock(ref e) => Some(e),
+            SilentPayment(ref e) => Some(e),
+            NotIncluded(_)
+            | OutputIndexOutOfRange(_)
+            | WrongAddress
+            | ScanKeyRequired
+            | WrongScanKey
+            | InsufficientAmount { .. }
+            | Expired { .. } => None,
+        }
+    }
+}
+
+impl From<MerkleBlockError> for PaymentProofError {
+    fn from(e: MerkleBlockError) -> Self { Self::MerkleBlock(e) }
+}
+
+impl From<SilentPaymentError> for PaymentProofError {
+    fn from(e: SilentPaymentError) -> Self { Self::SilentPayment(e) }
+}
+
+#[cfg(test)]
+mod tests {
+    use hashes::Hash;
+
+    use super::*;
+    use crate::block::{self, Block, Header};
+    use crate::consensus::{deserialize, serialize};
+    use crate::locktime::absolute;
+    use crate::silent_payments::InputSecretKey;
+    use crate::transaction::Version;
+    use crate::{
+        BlockHash, CompactTarget, Network, NetworkKind, OutPoint, PubkeyHash, ScriptBuf,
+        TxMerkleNode, Witness,
+    };
+
+    fn keypair() -> Keypair { Keypair::from_seckey_slice(&[0x11; 32]).unwrap() }
+
+    fn request() -> PaymentRequest {
+        let address = Address::p2pkh(PubkeyHash::all_zeros(), NetworkKind::Main);
+        PaymentRequest::new(address, Amount::from_sat(50_000), 1_700_000_000, "order #42")
+    }
+
+    fn paid_block(request: &PaymentRequest, value: Amount, time: u32) -> (Transaction, Block) {
+        let tx = Transaction {
+            version: Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![Default::default()],
+            output: vec![TxOut {
+                value,
+                script_pubkey: request.address().unwrap().assume_checked_ref().script_pubkey(),
+            }],
+        };
+        let block = block_with(&tx, time);
+        (tx, block)
+    }
+
+    /// Returns a block including `tx` after another transaction.
+    fn block_with(tx: &Transaction, time: u32) -> Block {
+        let other = Transaction { lock_time: absolute::LockTime::from_consensus(1), ..tx.clone() };
+        let mut block = Block {
+            header: Header {
+                version: block::Version::TWO,
+                prev_blockhash: BlockHash::all_zeros(),
+                merkle_root: TxMerkleNode::all_zeros(),
+                time,
+                bits: CompactTarget::from_consensus(0x1d00ffff),
+                nonce: 0,
+            },
+            txdata: vec![other, tx.clone()],
+        };
+        block.header.merkle_root = block.compute_merkle_root().unwrap();
+        block
+    }
+
+    fn proof_for(tx: Transaction, block: &Block) -> PaymentProof {
+        let txid = tx.compute_txid();
+        PaymentProof::new(tx, MerkleBlock::from_block_with_predicate(block, |t| *t == txid), 0)
+    }
+
+    /// Returns a transaction paying `code` from a P2WPKH input, and the output it spends.
+    fn silent_payment(code: &SilentPaymentCode, value: Amount) -> (Transaction, TxOut) {
+        let input_secret = Scalar::from_slice(&[0x66; 32]).unwrap();
+        let input_key = input_secret.base_point_mul();
+        let outpoint = OutPoint::new(Txid::from_byte_array([0x77; 32]), 0);
+        let outputs = silent_payments::create_outputs(
+            &[outpoint],
+            &[InputSecretKey::Ecdsa(input_secret)],
+            &[*code],
+        )
+        .unwrap();
+        let mut witness = Witness::new();
+        witness.push([0x30; 71]);
+        witness.push(input_key.serialize());
+        let tx = Transaction {
+            version: Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![crate::TxIn { previous_output: outpoint, witness, ..Default::default() }],
+            output: vec![TxOut {
+                value,
+                script_pubkey: ScriptBuf::new_p2tr_tweaked(
+                    crate::crypto::key::TweakedPublicKey::dangerous_assume_tweaked(outputs[0]),
+                ),
+            }],
+        };
+        let prevout = TxOut {
+            value: Amount::from_sat(100_000),
+            script_pubkey: ScriptBuf::new_p2wpkh(&input_key.wpubkey_hash().unwrap()),
+        };
+        (tx, prevout)
+    }
+
+    #[test]
+    fn request_signature() {
+        let keypair = keypair();
+        let (merchant, _) = keypair.x_only_public_key();
+
+        let mut request = request();
+        assert_eq!(request.verify_signature(&merchant), Err(PaymentSignatureError::Unsigned));
+
+        request.sign(&keypair, &[0u8; 32]);
+        assert_eq!(request.verify_signature(&merchant), Ok(()));
+
+        let (other, _) = Keypair::from_seckey_slice(&[0x22; 32]).unwrap().x_only_public_key();
+        assert_eq!(request.verify_signature(&other), Err(PaymentSignatureError::WrongKey));
+
+        let mut tampered = request.clone();
+        tampered.amount = Amount::from_sat(1);
+        assert_eq!(
+            tampered.verify_signature(&merchant),
+            Err(PaymentSignatureError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn request_roundtrip() {
+        let mut request = request();
+        assert_eq!(deserialize::<PaymentRequest>(&serialize(&request)).unwrap(), request);
+
+        request.sign(&keypair(), &[0u8; 32]);
+        let decoded = deserialize::<PaymentRequest>(&serialize(&request)).unwrap();
+        assert_eq!(decoded, request);
+        assert!(decoded.address().unwrap().is_valid_for_network(Network::Bitcoin));
+        assert_eq!(decoded.silent_payment_code(), None);
+    }
+
+    #[test]
+    fn silent_payment_request_roundtrip() {
+        let code = "sp1qqgste7k9hx0qftg6qmwlkqtwuy6cycyavzmzj85c6qdfhjdpdjtdgqjuexzk6murw56suy3e0rd2cgqvycxttddwsvgxe2usfpxumr70xc9pkqwv"
+            .parse::<SilentPaymentCode>()
+            .unwrap();
+        let mut request = PaymentRequest::new(code, Amount::from_sat(50_000), 1_700_000_000, "");
+        request.sign(&keypair(), &[0u8; 32]);
+        let decoded = deserialize::<PaymentRequest>(&serialize(&request)).unwrap();
+        assert_eq!(decoded, request);
+        assert_eq!(decoded.silent_payment_code(), Some(&code));
+        assert_eq!(decoded.address(), None);
+    }
+
+    #[test]
+    fn silent_payment_proof_verification() {
+        let scan_secret = Scalar::from_slice(&[0x44; 32]).unwrap();
+        let spend_key = Scalar::from_slice(&[0x55; 32]).unwrap().base_point_mul();
+        let code =
+            SilentPaymentCode::new(scan_secret.base_point_mul(), spend_key, Network::Bitcoin);
+        let labeled = code.with_label(&scan_secret, 1);
+        let request = PaymentRequest::new(labeled, Amount::from_sat(50_000), 1_700_000_000, "");
+
+        let (tx, prevout) = silent_payment(&labeled, Amount::from_sat(60_000));
+        assert!(!request.is_paid_by(&tx.output[0]));
+        let proof = proof_for(tx.clone(), &block_with(&tx, 1_600_000_000));
+        let prevouts = [prevout];
+        assert_eq!(proof.verify(&request), Err(PaymentProofError::ScanKeyRequired));
+        assert_eq!(
+            proof.verify_silent_payment(&request, &prevouts, &scan_secret, &spend_key, &[1]),
+            Ok(Amount::from_sat(60_000))
+        );
+        // The output is only recognized with its label.
+        assert_eq!(
+            proof.verify_silent_payment(&request, &prevouts, &scan_secret, &spend_key, &[]),
+            Err(PaymentProofError::WrongAddress)
+        );
+        let other_secret = Scalar::from_slice(&[0x45; 32]).unwrap();
+        assert_eq!(
+            proof.verify_silent_payment(&request, &prevouts, &other_secret, &spend_key, &[1]),
+            Err(PaymentProofError::WrongScanKey)
+        );
+        assert_eq!(
+            proof.verify_silent_payment(&request, &[], &scan_secret, &spend_key, &[1]),
+            Err(PaymentProofError::SilentPayment(SilentPaymentError::PrevoutsLength {
+                inputs: 1,
+                prevouts: 0
+            }))
+        );
+
+        // Paying the receiver under another code doesn't pay the request.
+        let (tx, prevout) = silent_payment(&code, Amount::from_sat(60_000));
+        let proof = proof_for(tx.clone(), &block_with(&tx, 1_600_000_000));
+        assert_eq!(
+            proof.verify_silent_payment(&request, &[prevout], &scan_secret, &spend_key, &[1]),
+            Err(PaymentProofError::WrongAddress)
+        );
+    }
+
+    #[test]
+    fn proof_verification() {
+        let request = request();
+        let (tx, block) = paid_block(&request, Amount::from_sat(60_000), 1_600_000_000);
+        let txid = tx.compute_txid();
+        let merkle_block = MerkleBlock::from_block_with_predicate(&block, |t| *t == txid);
+
+        let proof = PaymentProof::new(tx.clone(), merkle_block.clone(), 0);
+        assert_eq!(proof.verify(&request), Ok(Amount::from_sat(60_000)));
+        // Partial merkle tree bits are padded to a whole byte when decoded.
+        let decoded = deserialize::<PaymentProof>(&serialize(&proof)).unwrap();
+        assert_eq!(serialize(&decoded), serialize(&proof));
+        assert_eq!(decoded.verify(&request), Ok(Amount::from_sat(60_000)));
+
+        let proof = PaymentProof::new(tx.clone(), merkle_block.clone(), 1);
+        assert_eq!(proof.verify(&request), Err(PaymentProofError::OutputIndexOutOfRange(1)));
+
+        let other = block.txdata[0].clone();
+        let proof = PaymentProof::new(other.clone(), merkle_block, 0);
+        assert_eq!(
+            proof.verify(&request),
+            Err(PaymentProofError::NotIncluded(other.compute_txid()))
+        );
+
+        let (tx, block) = paid_block(&request, Amount::from_sat(40_000), 1_600_000_000);
+        let txid = tx.compute_txid();
+        let proof = PaymentProof::new(
+            tx,
+            MerkleBlock::from_block_with_predicate(&block, |t| *t == txid),
+            0,
+        );
+        assert!(matches!(
+            proof.verify(&request),
+            Err(PaymentProofError::InsufficientAmount { .. })
+        ));
+
+        let (tx, block) = paid_block(&request, Amount::from_sat(60_000), 1_800_000_000);
+        let txid = tx.compute_txid();
+        let proof = PaymentProof::new(
+            tx,
+            MerkleBlock::from_block_with_predicate(&block, |t| *t == txid),
+            0,
+        );
+        assert!(matches!(proof.verify(&request), Err(PaymentProofError::Expired { .. })));
+    }
+
+    #[test]
+    fn payment_flow() {
+        let merchant = keypair();
+        let payer = Keypair::from_seckey_slice(&[0x33; 32]).unwrap();
+        let (merchant_key, _) = merchant.x_only_public_key();
+        let (payer_key, _) = payer.x_only_public_key();
+
+        let mut request = request();
+        request.sign(&merchant, &[0u8; 32]);
+
+        let (tx, _) = paid_block(&request, Amount::from_sat(50_000), 1_600_000_000);
+        let refund = Address::p2pkh(PubkeyHash::hash(&[1]), NetworkKind::Main);
+        let mut payment = Payment::new(&request, vec![tx], refund, "thanks");
+        assert!(payment.pays(&request));
+        payment.sign(&payer, &[0u8; 32]);
+        assert_eq!(payment.verify_signature(&payer_key), Ok(()));
+        assert_eq!(deserialize::<Payment>(&serialize(&payment)).unwrap(), payment);
+
+        let mut ack = PaymentAck::new(&payment, "shipped");
+        ack.sign(&merchant, &[0u8; 32]);
+        assert!(ack.acknowledges(&payment));
+        assert_eq!(ack.verify_signature(&merchant_key), Ok(()));
+        assert_eq!(deserialize::<PaymentAck>(&serialize(&ack)).unwrap(), ack);
+
+        let new_refund = Address::p2pkh(PubkeyHash::hash(&[2]), NetworkKind::Main);
+        let mut update = RefundUpdate::new(&payment, new_refund);
+        assert_eq!(update.verify(&payment), Err(PaymentSignatureError::Unsigned));
+        update.sign(&merchant, &[0u8; 32]);
+        assert_eq!(update.verify(&payment), Err(PaymentSignatureError::WrongKey));
+        update.sign(&payer, &[0u8; 32]);
+        assert_eq!(update.verify(&payment), Ok(()));
+        assert_eq!(deserialize::<RefundUpdate>(&serialize(&update)).unwrap(), update);
+
+        let other =
+            Payment::new(&request, vec![], payment.refund_address().clone().assume_checked(), "");
+        assert_eq!(update.verify(&other), Err(PaymentSignatureError::Unsigned));
+    }
+}
@@ -0,0 +1,377 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Pre-signed transaction stores.
+//!
+//! Vaults, DLCs and inheritance setups sign transactions long before they are broadcast: a
+//! cancel transaction, a refund, a recovery path. These transactions are only useful as long as
+//! the outputs they spend are unspent, and often only until a timelock makes an alternative
+//! spend of the same outputs valid, such as the unvault path of a vault after its delay or the
+//! refund of a DLC after its timeout.
+//!
+//! A [`PresignedStore`] keeps pre-signed transactions with the [`Deadline`] of their alternative
+//! path, and checks them against the [`WalletState`] of the chain scanner, see the
+//! [`checkpoint`](crate::checkpoint) module, whose outputs must include the spent ones. Every
+//! [`PresignedStore::update`] returns the [`PresignedAlert`]s of the assumptions that broke since
+//! the previous one, so the application can react before it's too late.
+//!
+
+use core::fmt;
+
+use crate::blockdata::locktime::{absolute, relative};
+use crate::blockdata::transaction::{OutPoint, Transaction, Txid};
+use crate::checkpoint::WalletState;
+use crate::prelude::*;
+
+/// The height from which an alternative spend of the inputs of a pre-signed transaction becomes
+/// valid, and the pre-signed transaction may no longer confirm.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Deadline {
+    /// The alternative spend is valid from this block height, e.g. an absolute timelock.
+    Height(u32),
+    /// The alternative spend is valid this many blocks after the input at `input_index` is
+    /// confirmed, e.g. a relative timelock.
+    AfterConfirmation {
+        /// The index of the input.
+        input_index: usize,
+        /// The number of blocks.
+        blocks: u16,
+    },
+}
+
+/// A pre-signed transaction and the deadline of its alternative path, if it has one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Presigned {
+    /// The signed transaction.
+    pub tx: Transaction,
+    /// The deadline of the alternative path.
+    pub deadline: Option<Deadline>,
+}
+
+impl Presigned {
+    /// Returns the height of the deadline, if it is known in `state`.
+    ///
+    /// The height of a deadline after the confirmation of an input is only known once the input
+    /// is confirmed.
+    pub fn deadline_height(&self, state: &WalletState) -> Option<u32> {
+        match self.deadline? {
+            Deadline::Height(height) => Some(height),
+            Deadline::AfterConfirmation { input_index, blocks } => {
+                let outpoint = self.tx.input.get(input_index)?.previous_output;
+                Some(state.utxos.get(&outpoint)?.height + u32::from(blocks))
+            }
+        }
+    }
+
+    /// Returns whether the transaction can be broadcast at the tip of `state`.
+    ///
+    /// All its inputs must be unspent and its timelocks satisfied in the next block. Time based
+    /// timelocks are never considered satisfied, as the state doesn't know the time of blocks.
+    pub fn is_broadcastable(&self, state: &WalletState) -> bool {
+        let next_height = match state.tip {
+            Some(tip) => tip.height + 1,
+            None => return false,
+        };
+        let absolute_ok = match self.tx.lock_time {
+            absolute::LockTime::Blocks(height) => height.to_consensus_u32() < next_height,
+            absolute::LockTime::Seconds(_) => !self.tx.is_lock_time_enabled(),
+        };
+        absolute_ok
+            && self.tx.input.iter().all(|txin| {
+                let utxo = match state.utxos.get(&txin.previous_output) {
+                    Some(utxo) => utxo,
+                    None => return false,
+                };
+                match txin.sequence.to_relative_lock_time() {
+                    None => true,
+                    Some(relative::LockTime::Blocks(blocks)) =>
+                        utxo.height + u32::from(blocks.value()) <= next_height,
+                    Some(relative::LockTime::Time(_)) => false,
+                }
+            })
+    }
+}
+
+/// A broken assumption about a pre-signed transaction, returned by [`PresignedStore::update`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PresignedAlert {
+    /// An input of the transaction was spent by another transaction.
+    InputSpent {
+        /// The txid of the pre-signed transaction.
+        txid: Txid,
+        /// The spent output.
+        outpoint: OutPoint,
+    },
+    /// The deadline of the transaction is within the warning period.
+    DeadlineNear {
+        /// The txid of the pre-signed transaction.
+        txid: Txid,
+        /// The height of the deadline.
+        height: u32,
+    },
+    /// The deadline of the transaction passed, the alternative path may be taken.
+    DeadlinePassed {
+        /// The txid of the pre-signed transaction.
+        txid: Txid,
+        /// The height of the deadline.
+        height: u32,
+    },
+}
+
+/// A stored pre-signed transaction and what is known about it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Entry {
+    presigned: Presigned,
+    /// The inputs seen unspent, whose disappearance means they were spent.
+    seen: BTreeSet<OutPoint>,
+    /// The alerts already returned, which are not returned again.
+    alerts: BTreeSet<PresignedAlert>,
+}
+
+/// A store of pre-signed transactions, see the [module docs](self).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PresignedStore {
+    entries: BTreeMap<Txid, Entry>,
+    warning_blocks: u32,
+}
+
+impl PresignedStore {
+    /// Creates an empty store alerting `warning_blocks` blocks before a deadline.
+    pub fn new(warning_blocks: u32) -> Self {
+        PresignedStore { entries: BTreeMap::new(), warning_blocks }
+    }
+
+    /// Adds `presigned` to the store and returns its txid.
+    ///
+    /// The transaction is rejected if its own timelocks can never be satisfied before its
+    /// deadline.
+    pub fn insert(&mut self, presigned: Presigned) -> Result<Txid, PresignedError> {
+        let txid = presigned.tx.compute_txid();
+        if self.entries.contains_key(&txid) {
+            return Err(PresignedError::Duplicate(txid));
+        }
+        match presigned.deadline {
+            Some(Deadline::Height(deadline)) => match presigned.tx.lock_time {
+                absolute::LockTime::Blocks(height)
+                    if presigned.tx.is_lock_time_enabled()
+                        && height.to_consensus_u32() >= deadline =>
+                    return Err(PresignedError::UnreachableDeadline(txid)),
+                _ => {}
+            },
+            Some(Deadline::AfterConfirmation { input_index, blocks }) => {
+                let txin = presigned.tx.input.get(input_index).ok_or(
+                    PresignedError::InvalidInputIndex {
+                        index: input_index,
+                        inputs: presigned.tx.input.len(),
+                    },
+                )?;
+                if let Some(relative::LockTime::Blocks(delay)) =
+                    txin.sequence.to_relative_lock_time()
+                {
+                    if delay.value() >= blocks {
+                        return Err(PresignedError::UnreachableDeadline(txid));
+                    }
+                }
+            }
+            None => {}
+        }
+        let entry = Entry { presigned, seen: BTreeSet::new(), alerts: BTreeSet::new() };
+        self.entries.insert(txid, entry);
+        Ok(txid)
+    }
+
+    /// Removes the transaction `txid` from the store, e.g. once it is broadcast.
+    pub fn remove(&mut self, txid: Txid) -> Option<Presigned> {
+        self.entries.remove(&txid).map(|entry| entry.presigned)
+    }
+
+    /// Returns the pre-signed transaction `txid`.
+    pub fn get(&self, txid: Txid) -> Option<&Presigned> {
+        self.entries.get(&txid).map(|entry| &entry.presigned)
+    }
+
+    /// Returns the stored transactions.
+    pub fn iter(&self) -> impl Iterator<Item = &Presigned> {
+        self.entries.values().map(|entry| &entry.presigned)
+    }
+
+    /// Returns the stored transactions which can be broadcast at the tip of `state`.
+    pub fn broadcastable<'a>(
+        &'a self,
+        state: &'a WalletState,
+    ) -> impl Iterator<Item = &'a Presigned> + 'a {
+        self.iter().filter(move |presigned| presigned.is_broadcastable(state))
+    }
+
+    /// Checks the stored transactions against `state` and returns the alerts raised since the
+    /// previous update.
+    ///
+    /// An input which was never seen unspent, e.g. the output of a transaction which isn't
+    /// confirmed yet, isn't reported as spent. Transactions should be removed once broadcast, as
+    /// their own spends are reported as well.
+    pub fn update(&mut self, state: &WalletState) -> Vec<PresignedAlert> {
+        let mut alerts = vec![];
+        for (&txid, entry) in &mut self.entries {
+            let mut raised = vec![];
+            for txin in &entry.presigned.tx.input {
+                let outpoint = txin.previous_output;
+                if state.utxos.contains_key(&outpoint) {
+                    entry.seen.insert(outpoint);
+                } else if entry.seen.contains(&outpoint) {
+                    raised.push(PresignedAlert::InputSpent { txid, outpoint });
+                }
+            }
+            if let (Some(height), Some(tip)) = (entry.presigned.deadline_height(state), state.tip) {
+                if tip.height + 1 >= height {
+                    raised.push(PresignedAlert::DeadlinePassed { txid, height });
+                } else if tip.height + self.warning_blocks >= height {
+                    raised.push(PresignedAlert::DeadlineNear { txid, height });
+                }
+            }
+            alerts.extend(raised.into_iter().filter(|alert| entry.alerts.insert(*alert)));
+        }
+        alerts
+    }
+}
+
+/// A pre-signed transaction can't be stored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PresignedError {
+    /// The transaction is already in the store.
+    Duplicate(Txid),
+    /// The input of a deadline doesn't exist.
+    InvalidInputIndex {
+        /// The index of the input.
+        index: usize,
+        /// The number of inputs of the transaction.
+        inputs: usize,
+    },
+    /// The timelocks of the transaction can't be satisfied before its deadline.
+    UnreachableDeadline(Txid),
+}
+
+internals::impl_from_infallible!(PresignedError);
+
+impl fmt::Display for PresignedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use PresignedError::*;
+
+        match *self {
+            Duplicate(txid) => write!(f, "pre-signed transaction {} is already stored", txid),
+            InvalidInputIndex { index, inputs } =>
+                write!(f, "deadline of input {} of a transaction with {} inputs", index, inputs),
+            UnreachableDeadline(txid) =>
+                write!(f, "pre-signed transaction {} is timelocked past its deadline", txid),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PresignedError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use PresignedError::*;
+
+        match *self {
+            Duplicate(_) | InvalidInputIndex { .. } | UnreachableDeadline(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hashes::Hash;
+
+    use super::*;
+    use crate::blockdata::block::BlockHash;
+    use crate::blockdata::transaction::{self, Sequence, TxIn, TxOut};
+    use crate::checkpoint::{BlockId, Update, Utxo};
+
+    fn outpoint(vout: u32) -> OutPoint { OutPoint { txid: Txid::all_zeros(), vout } }
+
+    fn tx(lock_time: u32, sequence: Sequence) -> Transaction {
+        Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::from_consensus(lock_time),
+            input: vec![TxIn { previous_output: outpoint(0), sequence, ..Default::default() }],
+            output: vec![TxOut::NULL],
+        }
+    }
+
+    fn tip(height: u32) -> Update { Update::Tip(BlockId { height, hash: BlockHash::all_zeros() }) }
+
+    fn receive(vout: u32, height: u32) -> Update {
+        Update::Receive { outpoint: outpoint(vout), utxo: Utxo { txout: TxOut::NULL, height } }
+    }
+
+    #[test]
+    fn insert() {
+        let mut store = PresignedStore::new(6);
+        let presigned =
+            Presigned { tx: tx(0, Sequence::MAX), deadline: Some(Deadline::Height(100)) };
+        let txid = store.insert(presigned.clone()).unwrap();
+        assert_eq!(store.insert(presigned.clone()), Err(PresignedError::Duplicate(txid)));
+        assert_eq!(store.get(txid), Some(&presigned));
+        assert_eq!(store.remove(txid), Some(presigned));
+
+        let late = Presigned {
+            tx: tx(100, Sequence::ENABLE_LOCKTIME_NO_RBF),
+            deadline: Some(Deadline::Height(100)),
+        };
+        assert!(matches!(store.insert(late), Err(PresignedError::UnreachableDeadline(_))));
+        let relative = Presigned {
+            tx: tx(0, Sequence::from_height(144)),
+            deadline: Some(Deadline::AfterConfirmation { input_index: 0, blocks: 144 }),
+        };
+        assert!(matches!(store.insert(relative), Err(PresignedError::UnreachableDeadline(_))));
+        let invalid = Presigned {
+            tx: tx(0, Sequence::MAX),
+            deadline: Some(Deadline::AfterConfirmation { input_index: 1, blocks: 144 }),
+        };
+        assert_eq!(
+            store.insert(invalid),
+            Err(PresignedError::InvalidInputIndex { index: 1, inputs: 1 })
+        );
+    }
+
+    #[test]
+    fn update() {
+        let mut store = PresignedStore::new(6);
+        let presigned = Presigned {
+            tx: tx(0, Sequence::from_height(10)),
+            deadline: Some(Deadline::AfterConfirmation { input_index: 0, blocks: 144 }),
+        };
+        let txid = store.insert(presigned).unwrap();
+
+        // The input isn't confirmed yet.
+        let mut state = WalletState::new();
+        state.apply(&tip(100));
+        assert_eq!(store.update(&state), vec![]);
+        assert_eq!(store.broadcastable(&state).count(), 0);
+
+        state.apply(&receive(0, 100));
+        state.apply(&tip(108));
+        assert_eq!(store.update(&state), vec![]);
+        assert_eq!(store.broadcastable(&state).count(), 0);
+        state.apply(&tip(109));
+        assert_eq!(store.broadcastable(&state).count(), 1);
+
+        state.apply(&tip(238));
+        let near = PresignedAlert::DeadlineNear { txid, height: 244 };
+        assert_eq!(store.update(&state), vec![near]);
+        // Alerts are only returned once.
+        assert_eq!(store.update(&state), vec![]);
+
+        state.apply(&tip(243));
+        assert_eq!(
+            store.update(&state),
+            vec![PresignedAlert::DeadlinePassed { txid, height: 244 }]
+        );
+
+        state.apply(&Update::Spend { outpoint: outpoint(0) });
+        assert_eq!(
+            store.update(&state),
+            vec![PresignedAlert::InputSpent { txid, outpoint: outpoint(0) }]
+        );
+        assert_eq!(store.broadcastable(&state).count(), 0);
+    }
+}
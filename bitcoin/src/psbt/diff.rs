@@ -0,0 +1,235 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Comparing PSBTs.
+//!
+//! When the parties of a signing session end up with diverging PSBTs, [`Psbt::diff`] shows where
+//! they diverge. The comparison is done on the raw key-value pairs of each map, so it covers every
+//! field, including proprietary and unknown ones, and doesn't depend on how fields are decoded.
+//!
+
+use super::map::{Map, SIGNATURE_TYPES};
+use super::raw;
+use super::Psbt;
+use crate::prelude::*;
+
+/// The differences between two PSBTs, returned by [`Psbt::diff`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PsbtDiff {
+    /// The differences of the global maps, which hold the unsigned transaction.
+    pub global: MapDiff,
+    /// The differences of the input maps, by input index.
+    ///
+    /// If one PSBT has more inputs, all the pairs of its extra inputs are added or removed.
+    pub inputs: Vec<MapDiff>,
+    /// The differences of the output maps, by output index.
+    pub outputs: Vec<MapDiff>,
+}
+
+impl PsbtDiff {
+    /// Returns true if the PSBTs are the same.
+    pub fn is_empty(&self) -> bool {
+        self.global.is_empty()
+            && self.inputs.iter().all(MapDiff::is_empty)
+            && self.outputs.iter().all(MapDiff::is_empty)
+    }
+
+    /// Returns the indices of the inputs whose partial or final signatures differ.
+    pub fn inputs_with_differing_signatures(&self) -> impl Iterator<Item = usize> + '_ {
+        self.inputs
+            .iter()
+            .enumerate()
+            .filter(|(_, diff)| diff.has_signature_changes())
+            .map(|(i, _)| i)
+    }
+}
+
+/// The differences between two PSBT maps, as raw key-value pairs.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MapDiff {
+    /// The pairs only found in the other map.
+    pub added: Vec<raw::Pair>,
+    /// The pairs only found in this map.
+    pub removed: Vec<raw::Pair>,
+    /// The keys found in both maps, with different values.
+    pub changed: Vec<ChangedValue>,
+}
+
+/// A key found in both maps compared by a [`MapDiff`], with different values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangedValue {
+    /// The key.
+    pub key: raw::Key,
+    /// The value in this map.
+    pub ours: Vec<u8>,
+    /// The value in the other map.
+    pub theirs: Vec<u8>,
+}
+
+impl MapDiff {
+    /// Compares the pairs of two maps.
+    fn new(ours: Vec<raw::Pair>, theirs: Vec<raw::Pair>) -> MapDiff {
+        let mut theirs = theirs
+            .into_iter()
+            .map(|pair| (pair.key, pair.value))
+            .collect::<BTreeMap<_, _>>();
+        let mut diff = MapDiff::default();
+        for pair in ours {
+            match theirs.remove(&pair.key) {
+                None => diff.removed.push(pair),
+                Some(value) if value != pair.value => diff.changed.push(ChangedValue {
+                    key: pair.key,
+                    ours: pair.value,
+                    theirs: value,
+                }),
+                Some(_) => {}
+            }
+        }
+        diff.added = theirs
+            .into_iter()
+            .map(|(key, value)| raw::Pair { key, value })
+            .collect();
+        diff
+    }
+
+    /// Returns true if the maps are the same.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// Returns the keys which differ between the maps.
+    pub fn keys(&self) -> impl Iterator<Item = &raw::Key> {
+        self.added
+            .iter()
+            .map(|pair| &pair.key)
+            .chain(self.removed.iter().map(|pair| &pair.key))
+            .chain(self.changed.iter().map(|changed| &changed.key))
+    }
+
+    /// Returns true if a signature differs, assuming these are input maps.
+    ///
+    /// The signatures are the ECDSA and taproot partial signatures, and the final script sig and
+    /// witness.
+    pub fn has_signature_changes(&self) -> bool {
+        self.keys()
+            .any(|key| SIGNATURE_TYPES.contains(&key.type_value))
+    }
+}
+
+impl Psbt {
+    /// Compares this PSBT with `other`, field by field.
+    ///
+    /// Pairs found in `other` only are reported as added, and pairs found in this PSBT only as
+    /// removed.
+    pub fn diff(&self, other: &Psbt) -> PsbtDiff {
+        PsbtDiff {
+            global: MapDiff::new(self.get_pairs(), other.get_pairs()),
+            inputs: diff_maps(&self.inputs, &other.inputs),
+            outputs: diff_maps(&self.outputs, &other.outputs),
+        }
+    }
+}
+
+/// Compares the maps of `ours` and `theirs` by index.
+fn diff_maps<T: Map>(ours: &[T], theirs: &[T]) -> Vec<MapDiff> {
+    let pairs = |maps: &[T], i| maps.get(i).map(Map::get_pairs).unwrap_or_default();
+    (0..ours.len().max(theirs.len()))
+        .map(|i| MapDiff::new(pairs(ours, i), pairs(theirs, i)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockdata::transaction::{self, Transaction, TxIn, TxOut};
+    use crate::common::types::Message;
+    use crate::crypto::ecdsa;
+    use crate::crypto::scalar::Scalar;
+    use crate::locktime::absolute;
+    use crate::psbt::{Input, Output};
+    use crate::{Amount, EcdsaSighashType, ScriptBuf, G};
+
+    fn psbt() -> Psbt {
+        let tx = Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![TxIn::default(), TxIn::default()],
+            output: vec![TxOut {
+                value: Amount::from_sat(1000),
+                script_pubkey: ScriptBuf::new(),
+            }],
+        };
+        Psbt::from_unsigned_tx(tx).unwrap()
+    }
+
+    #[test]
+    fn diff() {
+        let ours = psbt();
+        assert!(ours.diff(&ours).is_empty());
+
+        let secret = Scalar::from_u32(7).unwrap();
+        let pk = secret * G;
+        let signature = ecdsa::sign_ecdsa_low_r(&Message::from_digest([1; 32]), &secret);
+        let sig = ecdsa::Signature {
+            signature,
+            sighash_type: EcdsaSighashType::All,
+        };
+        let mut theirs = ours.clone();
+        theirs.inputs[1].partial_sigs.insert(pk, sig);
+        theirs.outputs[0].redeem_script = Some(ScriptBuf::from_hex("51").unwrap());
+
+        let diff = ours.diff(&theirs);
+        assert!(!diff.is_empty());
+        assert!(diff.global.is_empty());
+        assert!(diff.inputs[0].is_empty());
+        assert_eq!(diff.inputs[1].added.len(), 1);
+        assert_eq!(diff.inputs[1].added[0].key.key, pk.to_bytes());
+        assert_eq!(
+            diff.inputs_with_differing_signatures().collect::<Vec<_>>(),
+            [1]
+        );
+        assert_eq!(diff.outputs[0].added.len(), 1);
+        assert!(!diff.outputs[0].has_signature_changes());
+
+        // The reverse diff removes what this one adds.
+        let reverse = theirs.diff(&ours);
+        assert_eq!(reverse.inputs[1].removed, diff.inputs[1].added);
+
+        // Another signature by the same key is a changed value.
+        let mut other_sig = sig;
+        other_sig.sighash_type = EcdsaSighashType::None;
+        let mut changed = theirs.clone();
+        changed.inputs[1].partial_sigs.insert(pk, other_sig);
+        let diff = theirs.diff(&changed);
+        assert_eq!(diff.inputs[1].changed.len(), 1);
+        assert_eq!(diff.inputs[1].changed[0].ours, sig.to_vec());
+        assert_eq!(diff.inputs[1].changed[0].theirs, other_sig.to_vec());
+        assert_eq!(
+            diff.inputs_with_differing_signatures().collect::<Vec<_>>(),
+            [1]
+        );
+    }
+
+    #[test]
+    fn diff_transactions() {
+        let ours = psbt();
+        let mut theirs = ours.clone();
+        theirs.unsigned_tx.output[0].value = Amount::from_sat(900);
+
+        let diff = ours.diff(&theirs);
+        assert_eq!(diff.global.changed.len(), 1);
+        assert_eq!(diff.global.changed[0].key.type_value, 0x00);
+
+        // Extra inputs are added in full.
+        theirs.unsigned_tx.input.push(TxIn::default());
+        theirs.inputs.push(Input {
+            redeem_script: Some(ScriptBuf::new()),
+            ..Default::default()
+        });
+        theirs.outputs.push(Output::default());
+        let diff = ours.diff(&theirs);
+        assert_eq!(diff.inputs.len(), 3);
+        assert_eq!(diff.inputs[2].added.len(), 1);
+        assert_eq!(diff.outputs.len(), 2);
+        assert!(diff.outputs[1].is_empty());
+    }
+}
@@ -69,6 +69,9 @@ pub enum Error {
     /// Conflicting data during combine procedure:
     /// global extended public key has inconsistent key sources
     CombineInconsistentKeySources(Box<Xpub>),
+    /// Conflicting data during combine procedure:
+    /// the key has a different value in each PSBT
+    CombineConflict(raw::Key),
     /// Serialization error in bitcoin consensus-encoded structures
     ConsensusEncoding(encode::Error),
     /// Negative fee
@@ -89,10 +92,12 @@ pub enum Error {
     InvalidControlBlock,
     /// Parsing error indicating invalid leaf version
     InvalidLeafVersion,
+    /// Parsing error indicating invalid MuSig2 public nonces or partial signatures
+    InvalidMusig2(crate::crypto::musig2::Musig2Error),
     /// Parsing error indicating a taproot error
     Taproot(&'static str),
     /// Taproot tree deserilaization error
-    TapTree(crate::taproot::IncompleteBuilderError),
+    TapTree(crate::taproot::TapTreeError),
     /// Error related to an xpub key
     XPubKey(&'static str),
     /// Error related to PSBT version
@@ -156,6 +161,7 @@ impl fmt::Display for Error {
             CombineInconsistentKeySources(ref s) => {
                 write!(f, "combine conflict: {}", s)
             }
+            CombineConflict(ref key) => write!(f, "combine conflict: {}", key),
             ConsensusEncoding(ref e) => write_err!(f, "bitcoin consensus encoding error"; e),
             NegativeFee => f.write_str("PSBT has a negative fee which is not allowed"),
             FeeOverflow => f.write_str("integer overflow in fee calculation"),
@@ -166,6 +172,7 @@ impl fmt::Display for Error {
             InvalidTaprootSignature(ref e) => write_err!(f, "invalid taproot signature"; e),
             InvalidControlBlock => f.write_str("invalid control block"),
             InvalidLeafVersion => f.write_str("invalid leaf version"),
+            InvalidMusig2(ref e) => write_err!(f, "invalid MuSig2 field"; e),
             Taproot(s) => write!(f, "taproot error -  {}", s),
             TapTree(ref e) => write_err!(f, "taproot tree error"; e),
             XPubKey(s) => write!(f, "xpub key error -  {}", s),
@@ -187,6 +194,7 @@ impl std::error::Error for Error {
             InvalidHash(ref e) => Some(e),
             ConsensusEncoding(ref e) => Some(e),
             Io(ref e) => Some(e),
+            TapTree(ref e) => Some(e),
             InvalidMagic
             | MissingUtxo
             | InvalidSeparator
@@ -202,6 +210,7 @@ impl std::error::Error for Error {
             | NonStandardSighashType(_)
             | InvalidPreimageHashPair { .. }
             | CombineInconsistentKeySources(_)
+            | CombineConflict(_)
             | NegativeFee
             | FeeOverflow
             | InvalidPublicKey(_)
@@ -211,8 +220,8 @@ impl std::error::Error for Error {
             | InvalidTaprootSignature(_)
             | InvalidControlBlock
             | InvalidLeafVersion
+            | InvalidMusig2(_)
             | Taproot(_)
-            | TapTree(_)
             | XPubKey(_)
             | Version(_)
             | PartialDataConsumption => None,
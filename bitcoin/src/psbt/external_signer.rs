@@ -0,0 +1,411 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Signing taproot key path spends with external signers.
+//!
+//! Keys which live in a hardware security module or a remote service can't be handed out through
+//! [`GetKey`](super::GetKey). Instead such signers implement [`ExternalSigner`], which is asked to
+//! sign a sighash with the key at a derivation path, and [`Psbt::sign_external`] drives it for
+//! every taproot key path spend of a PSBT, retrying failed requests according to a [`RetryPolicy`]
+//! and reporting its progress as it goes.
+//!
+//! The interface is blocking, an asynchronous signer can be adapted by blocking on its futures
+//! within [`ExternalSigner::sign_taproot_digest`].
+//!
+
+use core::fmt;
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::time::Instant;
+
+use hashes::Hash;
+use internals::write_err;
+use k256::schnorr::signature::hazmat::PrehashVerifier as _;
+use k256::schnorr::Signature as SchnorrSignature;
+
+use super::{Psbt, SignError};
+use crate::bip32::KeySource;
+use crate::crypto::key::XOnlyPublicKey;
+use crate::crypto::sighash::SighashCache;
+use crate::crypto::taproot;
+use crate::prelude::*;
+use crate::taproot::TapTweakHash;
+use crate::TapSighash;
+
+/// A signer holding keys outside of this process, for example in a hardware security module.
+pub trait ExternalSigner {
+    /// An error returned by the signer.
+    type Error: fmt::Debug;
+
+    /// Signs `digest` with the key at `path`, tweaked by `tweak`.
+    ///
+    /// The signing key is the key at `path` with an even y-coordinate, plus `tweak`, as specified
+    /// by BIP341 for key path spends. `digest` is the BIP340 message itself, it must not be hashed
+    /// again before signing.
+    fn sign_taproot_digest(
+        &self,
+        digest: &TapSighash,
+        path: &KeySource,
+        tweak: &TapTweakHash,
+    ) -> Result<SchnorrSignature, Self::Error>;
+
+    /// Returns whether a request which failed with `error` may be retried.
+    ///
+    /// Defaults to retrying every error.
+    fn is_transient(&self, _error: &Self::Error) -> bool { true }
+}
+
+/// How failed requests to an [`ExternalSigner`] are retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RetryPolicy {
+    /// The maximum number of requests for a single input, including the first one.
+    pub max_attempts: u32,
+    /// The time after the first request for an input after which no further requests are made.
+    ///
+    /// A request is never interrupted, so a slow signer may overrun this. Without the `std`
+    /// feature there is no clock and the timeout is ignored.
+    pub timeout: Option<Duration>,
+}
+
+impl RetryPolicy {
+    /// A policy which never retries.
+    pub const NO_RETRY: Self = Self { max_attempts: 1, timeout: None };
+}
+
+/// Retries each request up to three times, with no timeout.
+impl Default for RetryPolicy {
+    fn default() -> Self { Self { max_attempts: 3, timeout: None } }
+}
+
+/// An event reported while signing with an [`ExternalSigner`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum SigningProgress {
+    /// A signature for the input is requested from the signer, `attempt` starts at one.
+    Requested {
+        /// The index of the input.
+        input_index: usize,
+        /// The number of this request for the input.
+        attempt: u32,
+    },
+    /// The input was signed.
+    Signed {
+        /// The index of the input.
+        input_index: usize,
+    },
+    /// The input could not be signed.
+    Failed {
+        /// The index of the input.
+        input_index: usize,
+    },
+}
+
+impl Psbt {
+    /// Attempts to create the key path signatures of all taproot inputs using `signer`.
+    ///
+    /// An input is signed if it has a `tap_internal_key` with an entry in `tap_key_origins`
+    /// without leaf hashes, and no `tap_key_sig` yet. Other inputs are skipped. Every signature
+    /// returned by the signer is verified against the output key before it's added to the input.
+    ///
+    /// `progress` is called for every request to the signer and once each input is done.
+    ///
+    /// # Returns
+    ///
+    /// Either `Ok` with the indices of the signed inputs, or `Err` with the indices of the signed
+    /// inputs and a map of input index -> the error encountered while attempting to sign it.
+    /// Signatures are added to the PSBT even if other inputs fail.
+    #[allow(clippy::type_complexity)]
+    pub fn sign_external<S, F>(
+        &mut self,
+        signer: &S,
+        policy: RetryPolicy,
+        mut progress: F,
+    ) -> Result<Vec<usize>, (Vec<usize>, BTreeMap<usize, ExternalSignError<S::Error>>)>
+    where
+        S: ExternalSigner,
+        F: FnMut(SigningProgress),
+    {
+        let tx = self.unsigned_tx.clone(); // clone because we need to mutably borrow when signing.
+        let mut cache = SighashCache::new(&tx);
+
+        let mut signed = vec![];
+        let mut errors = BTreeMap::new();
+
+        for input_index in 0..self.inputs.len() {
+            let input = &self.inputs[input_index];
+            if input.tap_key_sig.is_some() {
+                continue;
+            }
+            let (internal_key, key_source) = match input.tap_internal_key.and_then(|key| {
+                input
+                    .tap_key_origins
+                    .get(&key)
+                    .filter(|(leaf_hashes, _)| leaf_hashes.is_empty())
+                    .map(|(_, key_source)| (key, key_source.clone()))
+            }) {
+                Some(key) => key,
+                None => continue,
+            };
+
+            let result = self
+                .sighash_taproot(input_index, &mut cache, None)
+                .map_err(ExternalSignError::Sign)
+                .and_then(|(msg, sighash_type)| {
+                    let output_key = self.output_key(input_index)?;
+                    let digest = TapSighash::from_slice(msg.as_bytes()).expect("32 byte digest");
+                    let tweak = TapTweakHash::from_key_and_tweak(
+                        internal_key,
+                        self.inputs[input_index].tap_merkle_root,
+                    );
+                    let signature = request_signature(
+                        signer,
+                        &digest,
+                        &key_source,
+                        &tweak,
+                        policy,
+                        |attempt| progress(SigningProgress::Requested { input_index, attempt }),
+                    )?;
+                    output_key
+                        .verify_prehash(msg.as_bytes(), &signature)
+                        .map_err(|_| ExternalSignError::InvalidSignature)?;
+                    Ok(taproot::Signature { signature, sighash_type })
+                });
+
+            match result {
+                Ok(signature) => {
+                    self.inputs[input_index].tap_key_sig = Some(signature);
+                    signed.push(input_index);
+                    progress(SigningProgress::Signed { input_index });
+                }
+                Err(e) => {
+                    errors.insert(input_index, e);
+                    progress(SigningProgress::Failed { input_index });
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(signed)
+        } else {
+            Err((signed, errors))
+        }
+    }
+
+    /// Returns the verifying key for the taproot output spent by the input at `input_index`.
+    fn output_key<E>(
+        &self,
+        input_index: usize,
+    ) -> Result<k256::schnorr::VerifyingKey, ExternalSignError<E>> {
+        let spk = &self.spend_utxo(input_index).map_err(ExternalSignError::Sign)?.script_pubkey;
+        XOnlyPublicKey::from_slice(&spk.as_bytes()[2..])
+            .ok()
+            .and_then(|key| key.try_into().ok())
+            .ok_or(ExternalSignError::Sign(SignError::UnknownOutputType))
+    }
+}
+
+/// Requests a signature from `signer`, retrying according to `policy`.
+fn request_signature<S, F>(
+    signer: &S,
+    digest: &TapSighash,
+    key_source: &KeySource,
+    tweak: &TapTweakHash,
+    policy: RetryPolicy,
+    mut on_request: F,
+) -> Result<SchnorrSignature, ExternalSignError<S::Error>>
+where
+    S: ExternalSigner,
+    F: FnMut(u32),
+{
+    #[cfg(feature = "std")]
+    let start = Instant::now();
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        on_request(attempt);
+        let error = match signer.sign_taproot_digest(digest, key_source, tweak) {
+            Ok(signature) => return Ok(signature),
+            Err(e) => e,
+        };
+
+        if !signer.is_transient(&error) || attempt >= policy.max_attempts {
+            return Err(ExternalSignError::Signer { attempts: attempt, error });
+        }
+        #[cfg(feature = "std")]
+        if policy.timeout.is_some_and(|timeout| start.elapsed() >= timeout) {
+            return Err(ExternalSignError::TimedOut { attempts: attempt, error });
+        }
+    }
+}
+
+/// Errors encountered while signing an input with an [`ExternalSigner`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ExternalSignError<E> {
+    /// Unable to compute the sighash or find the output key of the input.
+    Sign(SignError),
+    /// The signer failed, and the request was not retried any further.
+    Signer {
+        /// The number of requests made.
+        attempts: u32,
+        /// The error of the last request.
+        error: E,
+    },
+    /// The signer failed, and the timeout of the retry policy ran out.
+    TimedOut {
+        /// The number of requests made.
+        attempts: u32,
+        /// The error of the last request.
+        error: E,
+    },
+    /// The signer returned a signature which is not valid for the output key.
+    InvalidSignature,
+}
+
+impl<E: fmt::Display> fmt::Display for ExternalSignError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use ExternalSignError::*;
+
+        match *self {
+            Sign(ref e) => write_err!(f, "external signing"; e),
+            Signer { attempts, ref error } =>
+                write!(f, "external signer failed after {} attempts: {}", attempts, error),
+            TimedOut { attempts, ref error } =>
+                write!(f, "external signer timed out after {} attempts: {}", attempts, error),
+            InvalidSignature => f.write_str("external signer returned an invalid signature"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: std::error::Error + 'static> std::error::Error for ExternalSignError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use ExternalSignError::*;
+
+        match *self {
+            Sign(ref e) => Some(e),
+            Signer { ref error, .. } | TimedOut { ref error, .. } => Some(error),
+            InvalidSignature => None,
+        }
+    }
+}
+
+impl<E> From<SignError> for ExternalSignError<E> {
+    fn from(e: SignError) -> Self { Self::Sign(e) }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::cell::Cell;
+
+    use k256::schnorr::signature::hazmat::PrehashSigner as _;
+    use k256::schnorr::SigningKey as SchnorrSigningKey;
+
+    use super::*;
+    use crate::bip32::{DerivationPath, Fingerprint};
+    use crate::blockdata::locktime::absolute;
+    use crate::blockdata::script::ScriptBuf;
+    use crate::blockdata::transaction::{self, Transaction, TxIn, TxOut};
+    use crate::crypto::key::{Keypair, PrivateKey};
+    use crate::crypto::scalar::Scalar;
+    use crate::{Amount, NetworkKind};
+
+    /// Signs with a single key, failing the first `failures` requests.
+    struct TestSigner {
+        key: PrivateKey,
+        failures: Cell<u32>,
+    }
+
+    impl ExternalSigner for TestSigner {
+        type Error = &'static str;
+
+        fn sign_taproot_digest(
+            &self,
+            digest: &TapSighash,
+            _path: &KeySource,
+            tweak: &TapTweakHash,
+        ) -> Result<SchnorrSignature, Self::Error> {
+            if self.failures.get() > 0 {
+                self.failures.set(self.failures.get() - 1);
+                return Err("device busy");
+            }
+            // The signing key normalizes the internal and the tweaked key to an even y-coordinate.
+            let internal = Keypair::from_secret_key(&self.key.inner).to_signing_key();
+            let tweaked = Scalar::from(internal.as_nonzero_scalar()) + tweak.to_scalar();
+            let signer = SchnorrSigningKey::from_bytes(&tweaked.serialize()).unwrap();
+            signer.sign_prehash(digest.as_ref()).map_err(|_| "signing failed")
+        }
+    }
+
+    fn psbt_and_signer(failures: u32) -> (Psbt, TestSigner) {
+        let key = PrivateKey::from_slice(&[0x2a; 32], NetworkKind::Test).unwrap();
+        let (internal_key, _) = Keypair::from_secret_key(&key.inner).x_only_public_key();
+
+        let unsigned_tx = Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![TxIn::default(), TxIn::default()],
+            output: vec![TxOut::NULL],
+        };
+        let mut psbt = Psbt::from_unsigned_tx(unsigned_tx).unwrap();
+        for input in psbt.inputs.iter_mut() {
+            input.witness_utxo = Some(TxOut {
+                value: Amount::from_sat(10_000),
+                script_pubkey: ScriptBuf::new_p2tr(internal_key, None),
+            });
+        }
+        // Only the first input has the key origin of its internal key.
+        psbt.inputs[0].tap_internal_key = Some(internal_key);
+        psbt.inputs[0]
+            .tap_key_origins
+            .insert(internal_key, (vec![], (Fingerprint::default(), DerivationPath::default())));
+
+        (psbt, TestSigner { key, failures: Cell::new(failures) })
+    }
+
+    #[test]
+    fn sign_external_retries() {
+        let (mut psbt, signer) = psbt_and_signer(2);
+        let mut events = vec![];
+        let signed = psbt.sign_external(&signer, RetryPolicy::default(), |e| events.push(e));
+
+        assert_eq!(signed, Ok(vec![0]));
+        assert!(psbt.inputs[0].tap_key_sig.is_some());
+        assert!(psbt.inputs[1].tap_key_sig.is_none());
+        assert_eq!(
+            events,
+            [
+                SigningProgress::Requested { input_index: 0, attempt: 1 },
+                SigningProgress::Requested { input_index: 0, attempt: 2 },
+                SigningProgress::Requested { input_index: 0, attempt: 3 },
+                SigningProgress::Signed { input_index: 0 },
+            ]
+        );
+
+        // Already signed inputs are not signed again.
+        assert_eq!(psbt.sign_external(&signer, RetryPolicy::NO_RETRY, |_| ()), Ok(vec![]));
+    }
+
+    #[test]
+    fn sign_external_errors() {
+        let (mut psbt, signer) = psbt_and_signer(1);
+        let (signed, errors) =
+            psbt.sign_external(&signer, RetryPolicy::NO_RETRY, |_| ()).unwrap_err();
+        assert!(signed.is_empty());
+        assert_eq!(errors[&0], ExternalSignError::Signer { attempts: 1, error: "device busy" });
+
+        let (mut psbt, signer) = psbt_and_signer(1);
+        let policy = RetryPolicy { max_attempts: 3, timeout: Some(Duration::ZERO) };
+        let (_, errors) = psbt.sign_external(&signer, policy, |_| ()).unwrap_err();
+        assert_eq!(errors[&0], ExternalSignError::TimedOut { attempts: 1, error: "device busy" });
+
+        // A signer holding a different key produces signatures which don't verify.
+        let (mut psbt, _) = psbt_and_signer(0);
+        let signer = TestSigner {
+            key: PrivateKey::from_slice(&[0x2b; 32], NetworkKind::Test).unwrap(),
+            failures: Cell::new(0),
+        };
+        let (_, errors) = psbt.sign_external(&signer, RetryPolicy::default(), |_| ()).unwrap_err();
+        assert_eq!(errors[&0], ExternalSignError::InvalidSignature);
+        assert!(psbt.inputs[0].tap_key_sig.is_none());
+    }
+}
@@ -0,0 +1,666 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Finalizing PSBTs.
+//!
+//! [`Psbt::finalize`] is the BIP174 input finalizer for standard outputs: it turns the signatures
+//! collected in the PSBT into the final scriptSig and witness of every input, after which
+//! [`Psbt::extract_tx`] returns the signed transaction. The supported outputs are:
+//!
+//! * P2PKH and P2WPKH, on their own or nested in P2SH;
+//! * P2SH, P2WSH and P2SH-P2WSH whose script is `<key> OP_CHECKSIG` or a `multi()`;
+//! * P2TR key path spends, and script path spends of leaves which are `<key> OP_CHECKSIG` or a
+//!   `multi_a()`. When several leaves can be satisfied, the one with the smallest witness is
+//!   spent.
+//!
+//! [`Psbt::unsigned_tx_weight_upper_bound`] predicts the weight of the transaction the finalizer
+//! will produce, before any signature is collected.
+//!
+
+use core::{fmt, mem};
+
+use internals::write_err;
+
+use super::{IndexOutOfBoundsError, Input, Psbt};
+use crate::blockdata::constants::MAX_SCRIPT_ELEMENT_SIZE;
+use crate::blockdata::opcodes::all::OP_CHECKSIG;
+use crate::blockdata::script::{Builder, Instruction, PushBytesBuf, Script, ScriptBuf};
+use crate::blockdata::transaction::{predict_weight, InputWeightPrediction};
+use crate::blockdata::witness::Witness;
+use crate::crypto::key::{PublicKey, XOnlyPublicKey};
+use crate::multisig::{self, MultiContext};
+use crate::prelude::*;
+use crate::taproot::{LeafVersion, TapLeafHash};
+use crate::Weight;
+
+/// The size of the largest DER encoded ECDSA signature, with its sighash byte.
+const MAX_ECDSA_SIG_SIZE: usize = 73;
+/// The size of a BIP340 signature with a non-default sighash byte.
+const MAX_SCHNORR_SIG_SIZE: usize = 65;
+
+impl Psbt {
+    /// Finalizes every input that isn't final yet, see the [module docs](self).
+    ///
+    /// Finalization stops at the first error, the inputs finalized before it stay final.
+    pub fn finalize(&mut self) -> Result<(), FinalizeError> {
+        for input_index in 0..self.inputs.len() {
+            let input = &self.inputs[input_index];
+            if input.final_script_sig.is_none() && input.final_script_witness.is_none() {
+                self.finalize_input(input_index)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Finalizes the input at `input_index` from its signatures.
+    ///
+    /// As required by BIP174, every field of the input but the spent output, the final scriptSig
+    /// and witness, and the proprietary and unknown fields is cleared.
+    pub fn finalize_input(&mut self, input_index: usize) -> Result<(), FinalizeError> {
+        self.check_index_is_within_bounds(input_index)?;
+        let into_error = |error| FinalizeError::Input { input_index, error };
+        let utxo = self
+            .spend_utxo(input_index)
+            .map_err(|_| into_error(FinalizeInputError::MissingSpendUtxo))?;
+        let (script_sig, witness) =
+            satisfy(&self.inputs[input_index], &utxo.script_pubkey).map_err(into_error)?;
+
+        let input = &mut self.inputs[input_index];
+        *input = Input {
+            non_witness_utxo: input.non_witness_utxo.take(),
+            witness_utxo: input.witness_utxo.take(),
+            final_script_sig: (!script_sig.is_empty()).then_some(script_sig),
+            final_script_witness: (!witness.is_empty()).then_some(witness),
+            proprietary: mem::take(&mut input.proprietary),
+            unknown: mem::take(&mut input.unknown),
+            ..Default::default()
+        };
+        Ok(())
+    }
+
+    /// Returns an upper bound on the weight of the transaction once every input is finalized.
+    ///
+    /// Final inputs are counted with their scriptSig and witness. The others are counted with the
+    /// largest scriptSig and witness [`Psbt::finalize`] could produce for them, assuming the
+    /// largest signatures, so that fees computed from the bound are never too low.
+    pub fn unsigned_tx_weight_upper_bound(&self) -> Result<Weight, FinalizeError> {
+        let inputs = (0..self.inputs.len())
+            .map(|input_index| {
+                let into_error = |error| FinalizeError::Input { input_index, error };
+                let input = &self.inputs[input_index];
+                if input.final_script_sig.is_some() || input.final_script_witness.is_some() {
+                    let script_sig =
+                        input.final_script_sig.as_ref().map_or(0, |script| script.len());
+                    let witness =
+                        input.final_script_witness.iter().flat_map(|witness| witness.iter());
+                    return Ok(InputWeightPrediction::new(script_sig, witness.map(<[u8]>::len)));
+                }
+                let utxo = self
+                    .spend_utxo(input_index)
+                    .map_err(|_| into_error(FinalizeInputError::MissingSpendUtxo))?;
+                let (script_sig, witness) =
+                    max_satisfaction(input, &utxo.script_pubkey).map_err(into_error)?;
+                Ok(InputWeightPrediction::new(script_sig_size(&script_sig), witness))
+            })
+            .collect::<Result<Vec<_>, FinalizeError>>()?;
+        let outputs = self.unsigned_tx.output.iter().map(|output| output.script_pubkey.len());
+        Ok(predict_weight(inputs, outputs))
+    }
+}
+
+/// Returns the scriptSig and witness of `input`, which spends `script_pubkey`.
+fn satisfy(
+    input: &Input,
+    script_pubkey: &Script,
+) -> Result<(ScriptBuf, Witness), FinalizeInputError> {
+    if script_pubkey.is_p2pkh() {
+        let (key, signature) =
+            key_signature(input, |key| ScriptBuf::new_p2pkh(&key.pubkey_hash()) == *script_pubkey)?;
+        return Ok((script_sig(&[signature, key.to_bytes()]), Witness::new()));
+    }
+    if script_pubkey.is_p2wpkh() {
+        return Ok((ScriptBuf::new(), wpkh_witness(input, script_pubkey)?));
+    }
+    if script_pubkey.is_p2wsh() {
+        return Ok((ScriptBuf::new(), wsh_witness(input, script_pubkey)?));
+    }
+    if script_pubkey.is_p2sh() {
+        let redeem_script =
+            input.redeem_script.as_ref().ok_or(FinalizeInputError::MissingRedeemScript)?;
+        if ScriptBuf::new_p2sh(&redeem_script.script_hash()) != *script_pubkey {
+            return Err(FinalizeInputError::ScriptMismatch);
+        }
+        if redeem_script.len() > MAX_SCRIPT_ELEMENT_SIZE {
+            return Err(FinalizeInputError::UnsupportedScript(redeem_script.clone()));
+        }
+        if redeem_script.is_p2wpkh() {
+            let witness = wpkh_witness(input, redeem_script)?;
+            return Ok((script_sig(&[redeem_script.to_bytes()]), witness));
+        }
+        if redeem_script.is_p2wsh() {
+            let witness = wsh_witness(input, redeem_script)?;
+            return Ok((script_sig(&[redeem_script.to_bytes()]), witness));
+        }
+        let mut stack = satisfy_script(input, redeem_script, MultiContext::Sh)?;
+        stack.push(redeem_script.to_bytes());
+        return Ok((script_sig(&stack), Witness::new()));
+    }
+    if script_pubkey.is_p2tr() {
+        return Ok((ScriptBuf::new(), tr_witness(input, script_pubkey)?));
+    }
+    Err(FinalizeInputError::UnsupportedScript(script_pubkey.to_owned()))
+}
+
+/// Returns the element sizes of the largest scriptSig and witness [`satisfy`] could return for
+/// `input`, whatever its signatures.
+fn max_satisfaction(
+    input: &Input,
+    script_pubkey: &Script,
+) -> Result<(Vec<usize>, Vec<usize>), FinalizeInputError> {
+    if script_pubkey.is_p2pkh() {
+        // Without a known key, assume an uncompressed one.
+        let key_size = input
+            .partial_sigs
+            .keys()
+            .chain(input.bip32_derivation.keys())
+            .find(|key| ScriptBuf::new_p2pkh(&key.pubkey_hash()) == *script_pubkey)
+            .map_or(65, |key| key.to_bytes().len());
+        return Ok((vec![MAX_ECDSA_SIG_SIZE, key_size], vec![]));
+    }
+    if script_pubkey.is_p2wpkh() {
+        return Ok((vec![], vec![MAX_ECDSA_SIG_SIZE, 33]));
+    }
+    if script_pubkey.is_p2wsh() {
+        return Ok((vec![], max_wsh_witness(input, script_pubkey)?));
+    }
+    if script_pubkey.is_p2sh() {
+        let redeem_script =
+            input.redeem_script.as_ref().ok_or(FinalizeInputError::MissingRedeemScript)?;
+        if ScriptBuf::new_p2sh(&redeem_script.script_hash()) != *script_pubkey {
+            return Err(FinalizeInputError::ScriptMismatch);
+        }
+        if redeem_script.len() > MAX_SCRIPT_ELEMENT_SIZE {
+            return Err(FinalizeInputError::UnsupportedScript(redeem_script.clone()));
+        }
+        if redeem_script.is_p2wpkh() {
+            return Ok((vec![redeem_script.len()], vec![MAX_ECDSA_SIG_SIZE, 33]));
+        }
+        if redeem_script.is_p2wsh() {
+            return Ok((vec![redeem_script.len()], max_wsh_witness(input, redeem_script)?));
+        }
+        let mut stack = max_script_stack(redeem_script, MultiContext::Sh)?;
+        stack.push(redeem_script.len());
+        return Ok((stack, vec![]));
+    }
+    if script_pubkey.is_p2tr() {
+        return Ok((vec![], max_tr_witness(input, script_pubkey)));
+    }
+    Err(FinalizeInputError::UnsupportedScript(script_pubkey.to_owned()))
+}
+
+/// Returns the element sizes of the largest witness spending the P2WSH `script_pubkey`.
+fn max_wsh_witness(
+    input: &Input,
+    script_pubkey: &Script,
+) -> Result<Vec<usize>, FinalizeInputError> {
+    let witness_script =
+        input.witness_script.as_ref().ok_or(FinalizeInputError::MissingWitnessScript)?;
+    if ScriptBuf::new_p2wsh(&witness_script.wscript_hash()) != *script_pubkey {
+        return Err(FinalizeInputError::ScriptMismatch);
+    }
+    let mut stack = max_script_stack(witness_script, MultiContext::Wsh)?;
+    stack.push(witness_script.len());
+    Ok(stack)
+}
+
+/// Returns the element sizes of the largest witness spending the P2TR `script_pubkey`, by its
+/// key path or by any of the leaves [`tr_witness`] can satisfy.
+fn max_tr_witness(input: &Input, script_pubkey: &Script) -> Vec<usize> {
+    let key_path = vec![MAX_SCHNORR_SIG_SIZE];
+    if input.tap_key_sig.is_some() {
+        return key_path;
+    }
+    let output_key = match XOnlyPublicKey::from_slice(&script_pubkey.as_bytes()[2..]) {
+        Ok(output_key) => output_key,
+        Err(_) => return key_path,
+    };
+    input
+        .tap_scripts
+        .iter()
+        .filter(|(control_block, (script, version))| {
+            *version == LeafVersion::TapScript
+                && control_block.verify_taproot_commitment(output_key, script)
+        })
+        .filter_map(|(control_block, (script, _))| {
+            let mut stack = max_leaf_stack(script)?;
+            stack.push(script.len());
+            stack.push(control_block.size());
+            Some(stack)
+        })
+        .chain(Some(key_path))
+        .max_by_key(|stack| stack.iter().sum::<usize>())
+        .expect("the key path is always a candidate")
+}
+
+/// Returns the element sizes of the largest stack satisfying the `<key> OP_CHECKSIG` or `multi()`
+/// `script`.
+fn max_script_stack(
+    script: &Script,
+    context: MultiContext,
+) -> Result<Vec<usize>, FinalizeInputError> {
+    if checksig_key(script).is_some() {
+        return Ok(vec![MAX_ECDSA_SIG_SIZE]);
+    }
+    let (threshold, _) = multisig::parse_multi(script, context)
+        .ok_or_else(|| FinalizeInputError::UnsupportedScript(script.to_owned()))?;
+    let mut stack = vec![0];
+    stack.resize(threshold + 1, MAX_ECDSA_SIG_SIZE);
+    Ok(stack)
+}
+
+/// Returns the element sizes of the largest stack satisfying the `<key> OP_CHECKSIG` or
+/// `multi_a()` leaf `script`.
+fn max_leaf_stack(script: &Script) -> Option<Vec<usize>> {
+    if checksig_key(script).is_some() {
+        return Some(vec![MAX_SCHNORR_SIG_SIZE]);
+    }
+    let (threshold, keys) = multisig::parse_multi(script, MultiContext::Tapscript)?;
+    let mut stack = vec![MAX_SCHNORR_SIG_SIZE; threshold];
+    stack.resize(keys.len(), 0);
+    Some(stack)
+}
+
+/// Returns the size of the scriptSig pushing elements of `sizes`, as built by [`script_sig`].
+fn script_sig_size(sizes: &[usize]) -> usize {
+    sizes
+        .iter()
+        .map(|&size| match size {
+            0..=0x4b => 1 + size,
+            0x4c..=0xff => 2 + size,
+            _ => 3 + size,
+        })
+        .sum()
+}
+
+/// Returns the first key of the partial signatures of `input` matching `is_key`, along with its
+/// serialized signature.
+fn key_signature(
+    input: &Input,
+    is_key: impl Fn(&PublicKey) -> bool,
+) -> Result<(PublicKey, Vec<u8>), FinalizeInputError> {
+    input
+        .partial_sigs
+        .iter()
+        .find(|(key, _)| is_key(key))
+        .map(|(key, signature)| (*key, signature.to_vec()))
+        .ok_or(FinalizeInputError::MissingSignatures)
+}
+
+/// Returns the witness of `input` spending the P2WPKH `script_pubkey`.
+fn wpkh_witness(input: &Input, script_pubkey: &Script) -> Result<Witness, FinalizeInputError> {
+    let is_key = |key: &PublicKey| {
+        key.wpubkey_hash().map(|hash| ScriptBuf::new_p2wpkh(&hash) == *script_pubkey) == Ok(true)
+    };
+    let (key, signature) = key_signature(input, is_key)?;
+    Ok(Witness::from_slice(&[signature, key.to_bytes()]))
+}
+
+/// Returns the witness of `input` spending the P2WSH `script_pubkey`.
+fn wsh_witness(input: &Input, script_pubkey: &Script) -> Result<Witness, FinalizeInputError> {
+    let witness_script =
+        input.witness_script.as_ref().ok_or(FinalizeInputError::MissingWitnessScript)?;
+    if ScriptBuf::new_p2wsh(&witness_script.wscript_hash()) != *script_pubkey {
+        return Err(FinalizeInputError::ScriptMismatch);
+    }
+    let mut stack = satisfy_script(input, witness_script, MultiContext::Wsh)?;
+    stack.push(witness_script.to_bytes());
+    Ok(Witness::from_slice(&stack))
+}
+
+/// Returns the witness of `input` spending the P2TR `script_pubkey`, by its key path if it has
+/// a key signature and by its cheapest satisfiable leaf otherwise.
+fn tr_witness(input: &Input, script_pubkey: &Script) -> Result<Witness, FinalizeInputError> {
+    if let Some(signature) = &input.tap_key_sig {
+        return Ok(Witness::p2tr_key_spend(signature));
+    }
+
+    let output_key = XOnlyPublicKey::from_slice(&script_pubkey.as_bytes()[2..])
+        .map_err(|_| FinalizeInputError::UnsupportedScript(script_pubkey.to_owned()))?;
+    input
+        .tap_scripts
+        .iter()
+        .filter(|(control_block, (script, version))| {
+            *version == LeafVersion::TapScript
+                && control_block.verify_taproot_commitment(output_key, script)
+        })
+        .filter_map(|(control_block, (script, version))| {
+            let mut stack =
+                satisfy_leaf(input, script, TapLeafHash::from_script(script, *version))?;
+            stack.push(script.to_bytes());
+            stack.push(control_block.serialize());
+            Some(stack)
+        })
+        .min_by_key(|stack| stack.iter().map(Vec::len).sum::<usize>())
+        .map(|stack| Witness::from_slice(&stack))
+        .ok_or(FinalizeInputError::MissingSignatures)
+}
+
+/// Returns the stack satisfying the `<key> OP_CHECKSIG` or `multi()` `script` with the partial
+/// signatures of `input`.
+fn satisfy_script(
+    input: &Input,
+    script: &Script,
+    context: MultiContext,
+) -> Result<Vec<Vec<u8>>, FinalizeInputError> {
+    if let Some(key) = checksig_key(script) {
+        let key = PublicKey::from_slice(key)
+            .map_err(|_| FinalizeInputError::UnsupportedScript(script.to_owned()))?;
+        let signature =
+            input.partial_sigs.get(&key).ok_or(FinalizeInputError::MissingSignatures)?;
+        return Ok(vec![signature.to_vec()]);
+    }
+
+    let (threshold, keys) = multisig::parse_multi(script, context)
+        .ok_or_else(|| FinalizeInputError::UnsupportedScript(script.to_owned()))?;
+    // `OP_CHECKMULTISIG` pops an extra element, and takes the signatures in key order.
+    let mut stack = vec![vec![]];
+    stack.extend(
+        keys.iter()
+            .filter_map(|key| input.partial_sigs.get(key))
+            .take(threshold)
+            .map(|signature| signature.to_vec()),
+    );
+    if stack.len() <= threshold {
+        return Err(FinalizeInputError::MissingSignatures);
+    }
+    Ok(stack)
+}
+
+/// Returns the stack satisfying the `<key> OP_CHECKSIG` or `multi_a()` leaf `script` with the
+/// script signatures of `input`, if it has enough of them.
+fn satisfy_leaf(input: &Input, script: &Script, leaf_hash: TapLeafHash) -> Option<Vec<Vec<u8>>> {
+    let signature = |key: XOnlyPublicKey| {
+        input.tap_script_sigs.get(&(key, leaf_hash)).map(|signature| signature.to_vec())
+    };
+    if let Some(key) = checksig_key(script) {
+        return signature(XOnlyPublicKey::from_slice(key).ok()?).map(|signature| vec![signature]);
+    }
+
+    let (threshold, keys) = multisig::parse_multi(script, MultiContext::Tapscript)?;
+    // Exactly `threshold` keys must sign, the others get an empty signature.
+    let mut signed = 0;
+    let mut stack = keys
+        .iter()
+        .map(|key| match signature(key.x_only_public_key().0) {
+            Some(signature) if signed < threshold => {
+                signed += 1;
+                signature
+            }
+            _ => vec![],
+        })
+        .collect::<Vec<_>>();
+    if signed < threshold {
+        return None;
+    }
+    // The first key checks the signature on top of the stack.
+    stack.reverse();
+    Some(stack)
+}
+
+/// Returns the key of a `<key> OP_CHECKSIG` script.
+fn checksig_key(script: &Script) -> Option<&[u8]> {
+    let mut instructions = script.instructions();
+    match (instructions.next(), instructions.next(), instructions.next()) {
+        (Some(Ok(Instruction::PushBytes(key))), Some(Ok(Instruction::Op(OP_CHECKSIG))), None) =>
+            Some(key.as_bytes()),
+        _ => None,
+    }
+}
+
+/// Returns the scriptSig pushing `stack`, whose elements are at most 520 bytes.
+fn script_sig(stack: &[Vec<u8>]) -> ScriptBuf {
+    stack
+        .iter()
+        .fold(Builder::new(), |builder, element| {
+            let element =
+                PushBytesBuf::try_from(element.clone()).expect("elements are at most 520 bytes");
+            builder.push_slice(element)
+        })
+        .into_script()
+}
+
+/// An error finalizing a PSBT.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FinalizeError {
+    /// The input index is out of bounds.
+    IndexOutOfBounds(IndexOutOfBoundsError),
+    /// An input couldn't be finalized.
+    Input {
+        /// The index of the input.
+        input_index: usize,
+        /// Why the input couldn't be finalized.
+        error: FinalizeInputError,
+    },
+}
+
+internals::impl_from_infallible!(FinalizeError);
+
+impl fmt::Display for FinalizeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use FinalizeError::*;
+
+        match *self {
+            IndexOutOfBounds(ref e) => write_err!(f, "input index out of bounds"; e),
+            Input { input_index, ref error } => {
+                write_err!(f, "failed to finalize input {}", input_index; error)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for FinalizeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use FinalizeError::*;
+
+        match *self {
+            IndexOutOfBounds(ref e) => Some(e),
+            Input { ref error, .. } => Some(error),
+        }
+    }
+}
+
+impl From<IndexOutOfBoundsError> for FinalizeError {
+    fn from(e: IndexOutOfBoundsError) -> Self { FinalizeError::IndexOutOfBounds(e) }
+}
+
+/// An error finalizing an input of a PSBT.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FinalizeInputError {
+    /// The input has neither a witness UTXO nor a non-witness UTXO.
+    MissingSpendUtxo,
+    /// The input spends a P2SH output without a redeem script.
+    MissingRedeemScript,
+    /// The input spends a P2WSH output without a witness script.
+    MissingWitnessScript,
+    /// The redeem script or witness script doesn't match the spent output.
+    ScriptMismatch,
+    /// The spent output or its script can't be satisfied by the finalizer.
+    UnsupportedScript(ScriptBuf),
+    /// The input doesn't have enough signatures to be spent.
+    MissingSignatures,
+}
+
+internals::impl_from_infallible!(FinalizeInputError);
+
+impl fmt::Display for FinalizeInputError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use FinalizeInputError::*;
+
+        match *self {
+            MissingSpendUtxo => f.write_str("missing spend utxo"),
+            MissingRedeemScript => f.write_str("missing redeem script"),
+            MissingWitnessScript => f.write_str("missing witness script"),
+            ScriptMismatch => f.write_str("the script doesn't match the spent output"),
+            UnsupportedScript(ref script) => write!(f, "unsupported script: {}", script),
+            MissingSignatures => f.write_str("not enough signatures to spend the input"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for FinalizeInputError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use FinalizeInputError::*;
+
+        match *self {
+            MissingSpendUtxo | MissingRedeemScript | MissingWitnessScript | ScriptMismatch
+            | UnsupportedScript(_) | MissingSignatures => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bip32::KeySource;
+    use crate::blockdata::locktime::absolute;
+    use crate::blockdata::transaction::{self, Transaction, TxIn, TxOut};
+    use crate::crypto::key::PrivateKey;
+    use crate::multisig::multi_script;
+    use crate::psbt::FeeRateError;
+    use crate::taproot::TaprootBuilder;
+    use crate::{Amount, FeeRate, NetworkKind};
+
+    fn private_key(byte: u8) -> PrivateKey {
+        PrivateKey::from_slice(&[byte; 32], NetworkKind::Test).unwrap()
+    }
+
+    fn psbt(inputs: usize) -> Psbt {
+        let unsigned_tx = Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![TxIn::default(); inputs],
+            output: vec![TxOut { value: Amount::from_sat(1_000), script_pubkey: ScriptBuf::new() }],
+        };
+        Psbt::from_unsigned_tx(unsigned_tx).unwrap()
+    }
+
+    fn utxo(script_pubkey: ScriptBuf) -> Option<TxOut> {
+        Some(TxOut { value: Amount::from_sat(10_000), script_pubkey })
+    }
+
+    #[test]
+    fn finalize_every_input_type() {
+        let (a, b) = (private_key(0x2a), private_key(0x2b));
+        let (pk_a, pk_b) = (a.public_key(), b.public_key());
+        let (xonly_a, _) = pk_a.x_only_public_key();
+        let mut key_map = BTreeMap::new();
+        key_map.insert(pk_a, a);
+        key_map.insert(pk_b, b);
+        let origin = KeySource::default();
+
+        let wpkh = ScriptBuf::new_p2wpkh(&pk_a.wpubkey_hash().unwrap());
+        let multi = multi_script(2, &[pk_a, pk_b], MultiContext::Wsh).unwrap();
+        let wsh = ScriptBuf::new_p2wsh(&multi.wscript_hash());
+        let leaf = Builder::new().push_x_only_key(&xonly_a).push_opcode(OP_CHECKSIG).into_script();
+        let multi_a = multi_script(2, &[pk_a, pk_b], MultiContext::Tapscript).unwrap();
+        let (internal_key, _) = private_key(0x2c).public_key().x_only_public_key();
+        let spend_info = TaprootBuilder::new()
+            .add_leaf(1, multi_a.clone())
+            .unwrap()
+            .add_leaf(1, leaf.clone())
+            .unwrap()
+            .finalize(internal_key)
+            .unwrap();
+
+        let mut psbt = psbt(7);
+        let inputs = &mut psbt.inputs;
+        inputs[0].witness_utxo = utxo(ScriptBuf::new_p2pkh(&pk_a.pubkey_hash()));
+        inputs[1].witness_utxo = utxo(wpkh.clone());
+        inputs[2].witness_utxo = utxo(ScriptBuf::new_p2sh(&wpkh.script_hash()));
+        inputs[2].redeem_script = Some(wpkh);
+        inputs[3].witness_utxo = utxo(wsh.clone());
+        inputs[3].witness_script = Some(multi.clone());
+        inputs[4].witness_utxo = utxo(ScriptBuf::new_p2sh(&wsh.script_hash()));
+        inputs[4].redeem_script = Some(wsh);
+        inputs[4].witness_script = Some(multi);
+        for input in &mut inputs[..5] {
+            input.bip32_derivation.insert(pk_a, origin.clone());
+            input.bip32_derivation.insert(pk_b, origin.clone());
+        }
+        inputs[5].witness_utxo = utxo(ScriptBuf::new_p2tr(xonly_a, None));
+        inputs[5].tap_internal_key = Some(xonly_a);
+        inputs[5].tap_key_origins.insert(xonly_a, (vec![], origin.clone()));
+        inputs[6].witness_utxo = utxo(ScriptBuf::new_p2tr_tweaked(spend_info.output_key()));
+        inputs[6].tap_internal_key = Some(internal_key);
+        inputs[6].tap_merkle_root = spend_info.merkle_root();
+        for script in [&leaf, &multi_a] {
+            let script_ver = (script.clone(), LeafVersion::TapScript);
+            let control_block = spend_info.control_block(&script_ver).unwrap();
+            inputs[6].tap_scripts.insert(control_block, script_ver);
+        }
+        let leaf_hash = TapLeafHash::from_script(&leaf, LeafVersion::TapScript);
+        let multi_a_hash = TapLeafHash::from_script(&multi_a, LeafVersion::TapScript);
+        let (xonly_b, _) = pk_b.x_only_public_key();
+        inputs[6].tap_key_origins.insert(xonly_a, (vec![leaf_hash, multi_a_hash], origin.clone()));
+        inputs[6].tap_key_origins.insert(xonly_b, (vec![multi_a_hash], origin));
+        let bound = psbt.unsigned_tx_weight_upper_bound().unwrap();
+        let fee_rate = psbt.fee_rate_checked(Psbt::DEFAULT_MAX_FEE_RATE).unwrap();
+        assert!(matches!(
+            psbt.fee_rate_checked(FeeRate::from_sat_per_kwu(fee_rate.to_sat_per_kwu() - 1)),
+            Err(FeeRateError::AbsurdFeeRate(rate)) if rate == fee_rate
+        ));
+        psbt.sign(&key_map).unwrap();
+        // The BIP 371 fields survive a round trip, as when another wallet finalizes.
+        let mut psbt = Psbt::deserialize(&psbt.serialize()).unwrap();
+        assert_eq!(psbt.inputs[6].tap_script_sigs.len(), 3);
+        psbt.finalize().unwrap();
+
+        let stack_sizes = psbt
+            .inputs
+            .iter()
+            .map(|input| {
+                let script_sig = input.final_script_sig.clone().unwrap_or_default();
+                let witness = input.final_script_witness.clone().unwrap_or_default();
+                (script_sig.instructions().count(), witness.len())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(stack_sizes, [(2, 0), (0, 2), (1, 2), (0, 4), (1, 4), (0, 1), (0, 3)]);
+        // The single key leaf is cheaper than the `multi_a()` leaf.
+        let witness = psbt.inputs[6].final_script_witness.as_ref().unwrap();
+        assert_eq!(witness.nth(1), Some(leaf.as_bytes()));
+        assert!(psbt.inputs.iter().all(|input| input.partial_sigs.is_empty()
+            && input.tap_scripts.is_empty()
+            && input.witness_utxo.is_some()));
+
+        // Final inputs are counted exactly.
+        let weight = psbt.unsigned_tx_weight_upper_bound().unwrap();
+        let tx = psbt.extract_tx().unwrap();
+        assert_eq!(tx.input[6].witness.len(), 3);
+        assert_eq!(tx.weight(), weight);
+        assert!(weight <= bound);
+    }
+
+    #[test]
+    fn finalize_errors() {
+        let key = private_key(0x2a).public_key();
+        let multi = multi_script(1, &[key], MultiContext::Wsh).unwrap();
+        let mut psbt = psbt(3);
+        psbt.inputs[1].witness_utxo = utxo(ScriptBuf::new_p2wsh(&multi.wscript_hash()));
+        psbt.inputs[2].witness_utxo = utxo(ScriptBuf::new_p2wsh(&multi.wscript_hash()));
+        psbt.inputs[2].witness_script = Some(multi);
+
+        let error = |input_index, error| Err(FinalizeError::Input { input_index, error });
+        assert_eq!(psbt.finalize_input(0), error(0, FinalizeInputError::MissingSpendUtxo));
+        assert_eq!(psbt.finalize_input(1), error(1, FinalizeInputError::MissingWitnessScript));
+        assert_eq!(psbt.finalize_input(2), error(2, FinalizeInputError::MissingSignatures));
+        assert!(matches!(psbt.finalize_input(3), Err(FinalizeError::IndexOutOfBounds(_))));
+        assert_eq!(psbt.finalize(), error(0, FinalizeInputError::MissingSpendUtxo));
+        assert_eq!(
+            psbt.unsigned_tx_weight_upper_bound().unwrap_err(),
+            FinalizeError::Input { input_index: 0, error: FinalizeInputError::MissingSpendUtxo }
+        );
+    }
+}
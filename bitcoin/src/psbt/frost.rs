@@ -0,0 +1,476 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! FROST threshold signing of PSBTs.
+//!
+//! Coordinates the signers of a [`frost`] key to produce the key path signatures of the taproot
+//! inputs whose internal key is the group key:
+//!
+//! 1. Every signer sends a [`CommitmentsMessage`] with fresh nonce commitments for every input.
+//! 2. The coordinator collects them in a [`FrostCoordinator`] and sends the [`SigningRequest`]
+//!    it builds, which carries the sighash of every input, to the signers.
+//! 3. Every signer checks the request against its own copy of the PSBT and answers with a
+//!    [`SharesMessage`] from [`SigningRequest::sign`].
+//! 4. The coordinator verifies the signature shares and adds the aggregated signatures to the
+//!    PSBT with [`FrostCoordinator::finalize`].
+//!
+//! All messages have a consensus encoding to send them between the parties.
+//!
+
+use core::fmt;
+
+use internals::write_err;
+use io::{BufRead, Write};
+
+use super::{Psbt, SignError};
+use crate::blockdata::transaction::Transaction;
+use crate::consensus::encode::{self, Decodable, Encodable};
+use crate::crypto::frost::{
+    self, decode_map, encode_map, FrostError, KeyShare, NonceCommitments, PublicKeyPackage,
+    SignatureShare, SigningNonces, SigningPackage,
+};
+use crate::crypto::key::XOnlyPublicKey;
+use crate::crypto::scalar::Scalar;
+use crate::crypto::sighash::{SighashCache, TapSighashType};
+use crate::crypto::taproot;
+use crate::prelude::*;
+use crate::taproot::TapTweakHash;
+
+/// A signer's nonce commitments for every input, by input index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitmentsMessage {
+    /// The index of the signer.
+    pub index: u32,
+    /// The nonce commitments for every input.
+    pub commitments: BTreeMap<u32, NonceCommitments>,
+}
+
+/// The signing packages for every input, by input index, sent by the coordinator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SigningRequest {
+    /// The signing package of every input.
+    pub packages: BTreeMap<u32, SigningPackage>,
+}
+
+/// A signer's signature shares for every input, by input index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SharesMessage {
+    /// The index of the signer.
+    pub index: u32,
+    /// The signature shares for every input.
+    pub shares: BTreeMap<u32, SignatureShare>,
+}
+
+impl SigningRequest {
+    /// Produces the signature shares of `key` for every input it has committed to, consuming the
+    /// `nonces` drawn for those inputs.
+    ///
+    /// The message and tweak of every package are recomputed from the signer's own copy `psbt`
+    /// of the PSBT, so that the coordinator can't get anything else signed. Nothing is signed
+    /// unless every package matches.
+    pub fn sign(
+        &self,
+        psbt: &Psbt,
+        key: &KeyShare,
+        mut nonces: BTreeMap<u32, SigningNonces>,
+    ) -> Result<SharesMessage, FrostSignError> {
+        let tx = psbt.unsigned_tx.clone();
+        let mut cache = SighashCache::new(&tx);
+
+        let group_key = XOnlyPublicKey::from(key.group_key);
+        let mut packages = Vec::with_capacity(self.packages.len());
+        for (&input, package) in &self.packages {
+            if !package.commitments.contains_key(&key.index) {
+                continue;
+            }
+            let i = input as usize;
+            let psbt_input = psbt.inputs.get(i).ok_or(FrostSignError::UnexpectedInput(input))?;
+            if psbt_input.tap_internal_key != Some(group_key) {
+                return Err(FrostSignError::UnexpectedInput(input));
+            }
+            let (message, tweak, _) = input_package(psbt, group_key, i, &mut cache)?;
+            if message != package.message {
+                return Err(FrostSignError::SighashMismatch(input));
+            }
+            if package.tweak != Some(tweak) {
+                return Err(FrostSignError::TweakMismatch(input));
+            }
+            packages.push((input, package));
+        }
+
+        let mut shares = BTreeMap::new();
+        for (input, package) in packages {
+            let nonces = nonces.remove(&input).ok_or(FrostSignError::MissingNonces(input))?;
+            let share = frost::sign(package, key, nonces)
+                .map_err(|error| FrostSignError::Frost { input, error })?;
+            shares.insert(input, share);
+        }
+        Ok(SharesMessage { index: key.index, shares })
+    }
+}
+
+/// Collects the messages of the signers of a FROST key and signs a PSBT with them.
+#[derive(Debug, Clone)]
+pub struct FrostCoordinator {
+    public: PublicKeyPackage,
+    /// The nonce commitments received, by input index then signer index.
+    commitments: BTreeMap<u32, BTreeMap<u32, NonceCommitments>>,
+}
+
+impl FrostCoordinator {
+    /// Creates a coordinator for the key `public`.
+    pub fn new(public: PublicKeyPackage) -> Self {
+        FrostCoordinator { public, commitments: BTreeMap::new() }
+    }
+
+    /// Returns the indices of the inputs of `psbt` this coordinator signs.
+    ///
+    /// These are the taproot inputs with the group key as internal key and no key path signature.
+    pub fn inputs(&self, psbt: &Psbt) -> Vec<usize> {
+        let group_key = XOnlyPublicKey::from(self.public.group_key);
+        (0..psbt.inputs.len())
+            .filter(|&i| {
+                let input = &psbt.inputs[i];
+                input.tap_internal_key == Some(group_key) && input.tap_key_sig.is_none()
+            })
+            .collect()
+    }
+
+    /// Adds the nonce commitments of a signer, replacing any previous ones.
+    pub fn add_commitments(&mut self, message: CommitmentsMessage) -> Result<(), FrostSignError> {
+        if !self.public.verifying_shares.contains_key(&message.index) {
+            return Err(FrostSignError::UnknownSigner(message.index));
+        }
+        for (input, commitments) in message.commitments {
+            self.commitments.entry(input).or_default().insert(message.index, commitments);
+        }
+        Ok(())
+    }
+
+    /// Builds the signing request for all [inputs](Self::inputs) of `psbt`.
+    ///
+    /// Every signer which sent commitments for an input is asked to sign it.
+    pub fn signing_request(&self, psbt: &Psbt) -> Result<SigningRequest, FrostSignError> {
+        let tx = psbt.unsigned_tx.clone();
+        let mut cache = SighashCache::new(&tx);
+
+        let mut packages = BTreeMap::new();
+        for i in self.inputs(psbt) {
+            let input = i as u32;
+            let commitments = self.commitments.get(&input).cloned().unwrap_or_default();
+            if (commitments.len() as u64) < u64::from(self.public.threshold) {
+                return Err(FrostSignError::NotEnoughCommitments {
+                    input,
+                    required: self.public.threshold,
+                    got: commitments.len(),
+                });
+            }
+
+            let group_key = XOnlyPublicKey::from(self.public.group_key);
+            let (message, tweak, _) = input_package(psbt, group_key, i, &mut cache)?;
+            packages.insert(input, SigningPackage { message, commitments, tweak: Some(tweak) });
+        }
+
+        if packages.is_empty() {
+            return Err(FrostSignError::NoInputs);
+        }
+        Ok(SigningRequest { packages })
+    }
+
+    /// Verifies the signature shares for `request` and adds the aggregated key path signatures
+    /// to `psbt`.
+    ///
+    /// No signature is added unless every input of the request can be signed.
+    ///
+    /// # Returns
+    ///
+    /// The indices of the signed inputs.
+    pub fn finalize(
+        &self,
+        psbt: &mut Psbt,
+        request: &SigningRequest,
+        messages: &[SharesMessage],
+    ) -> Result<Vec<usize>, FrostSignError> {
+        let tx = psbt.unsigned_tx.clone();
+        let mut cache = SighashCache::new(&tx);
+
+        let mut signatures = Vec::with_capacity(request.packages.len());
+        for (&input, package) in &request.packages {
+            let i = input as usize;
+            let group_key = XOnlyPublicKey::from(self.public.group_key);
+            let (message, _, sighash_type) = input_package(psbt, group_key, i, &mut cache)?;
+            if message != package.message {
+                return Err(FrostSignError::SighashMismatch(input));
+            }
+
+            let shares: Vec<SignatureShare> =
+                messages.iter().filter_map(|message| message.shares.get(&input).copied()).collect();
+            let signature = package
+                .aggregate(&self.public, &shares)
+                .map_err(|error| FrostSignError::Frost { input, error })?;
+            signatures.push((i, taproot::Signature { signature, sighash_type }));
+        }
+
+        let mut signed = Vec::with_capacity(signatures.len());
+        for (i, signature) in signatures {
+            psbt.inputs[i].tap_key_sig = Some(signature);
+            signed.push(i);
+        }
+        Ok(signed)
+    }
+}
+
+/// Returns the key path sighash, taproot tweak and sighash type of input `i` of `psbt`, spent by
+/// `group_key`.
+fn input_package(
+    psbt: &Psbt,
+    group_key: XOnlyPublicKey,
+    i: usize,
+    cache: &mut SighashCache<&Transaction>,
+) -> Result<([u8; 32], Scalar, TapSighashType), FrostSignError> {
+    let (msg, sighash_type) = psbt.sighash_taproot(i, cache, None)?;
+    let mut message = [0u8; 32];
+    message.copy_from_slice(msg.as_bytes());
+    let tweak = TapTweakHash::from_key_and_tweak(group_key, psbt.inputs[i].tap_merkle_root);
+    Ok((message, tweak.to_scalar(), sighash_type))
+}
+
+impl Encodable for CommitmentsMessage {
+    fn consensus_encode<W: Write + ?Sized>(&self, w: &mut W) -> Result<usize, io::Error> {
+        let len = self.index.consensus_encode(w)?;
+        Ok(len + encode_map(&self.commitments, w)?)
+    }
+}
+
+impl Decodable for CommitmentsMessage {
+    fn consensus_decode<R: BufRead + ?Sized>(r: &mut R) -> Result<Self, encode::Error> {
+        Ok(CommitmentsMessage {
+            index: Decodable::consensus_decode(r)?,
+            commitments: decode_map(r)?,
+        })
+    }
+}
+
+impl Encodable for SigningRequest {
+    fn consensus_encode<W: Write + ?Sized>(&self, w: &mut W) -> Result<usize, io::Error> {
+        encode_map(&self.packages, w)
+    }
+}
+
+impl Decodable for SigningRequest {
+    fn consensus_decode<R: BufRead + ?Sized>(r: &mut R) -> Result<Self, encode::Error> {
+        Ok(SigningRequest { packages: decode_map(r)? })
+    }
+}
+
+impl Encodable for SharesMessage {
+    fn consensus_encode<W: Write + ?Sized>(&self, w: &mut W) -> Result<usize, io::Error> {
+        let len = self.index.consensus_encode(w)?;
+        Ok(len + encode_map(&self.shares, w)?)
+    }
+}
+
+impl Decodable for SharesMessage {
+    fn consensus_decode<R: BufRead + ?Sized>(r: &mut R) -> Result<Self, encode::Error> {
+        Ok(SharesMessage { index: Decodable::consensus_decode(r)?, shares: decode_map(r)? })
+    }
+}
+
+/// Errors encountered while threshold signing a PSBT.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FrostSignError {
+    /// Unable to compute the sighash of an input.
+    Sign(SignError),
+    /// Signing or aggregating the signature of an input failed.
+    Frost {
+        /// The index of the input.
+        input: u32,
+        /// The error.
+        error: FrostError,
+    },
+    /// The signer with this index is not part of the key.
+    UnknownSigner(u32),
+    /// Fewer signers than the threshold sent commitments for an input.
+    NotEnoughCommitments {
+        /// The index of the input.
+        input: u32,
+        /// The threshold of the key.
+        required: u32,
+        /// The number of signers which sent commitments.
+        got: usize,
+    },
+    /// The PSBT has no input to sign with the key.
+    NoInputs,
+    /// The sighash of the input with this index doesn't match the signing request.
+    SighashMismatch(u32),
+    /// The taproot tweak of the input with this index doesn't match the signing request.
+    TweakMismatch(u32),
+    /// The signing request asks to sign the input with this index, which the key doesn't spend.
+    UnexpectedInput(u32),
+    /// The signer has no nonces for the input with this index.
+    MissingNonces(u32),
+}
+
+internals::impl_from_infallible!(FrostSignError);
+
+impl fmt::Display for FrostSignError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use FrostSignError::*;
+
+        match *self {
+            Sign(ref e) => write_err!(f, "threshold signing"; e),
+            Frost { input, ref error } => write_err!(f, "threshold signing input {}", input; error),
+            UnknownSigner(index) => write!(f, "signer {} is not part of the key", index),
+            NotEnoughCommitments { input, required, got } => write!(
+                f,
+                "{} signers sent nonce commitments for input {}, {} required",
+                got, input, required
+            ),
+            NoInputs => f.write_str("no input to sign with the key"),
+            SighashMismatch(input) =>
+                write!(f, "the sighash of input {} does not match the signing request", input),
+            TweakMismatch(input) =>
+                write!(f, "the tweak of input {} does not match the signing request", input),
+            UnexpectedInput(input) => write!(f, "input {} is not spent by the key", input),
+            MissingNonces(input) => write!(f, "no nonces for input {}", input),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for FrostSignError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use FrostSignError::*;
+
+        match *self {
+            Sign(ref e) => Some(e),
+            Frost { ref error, .. } => Some(error),
+            UnknownSigner(_)
+            | NotEnoughCommitments { .. }
+            | NoInputs
+            | SighashMismatch(_)
+            | TweakMismatch(_)
+            | UnexpectedInput(_)
+            | MissingNonces(_) => None,
+        }
+    }
+}
+
+impl From<SignError> for FrostSignError {
+    fn from(e: SignError) -> Self { Self::Sign(e) }
+}
+
+#[cfg(test)]
+mod tests {
+    use hashes::Hash;
+    use k256::schnorr::signature::hazmat::PrehashVerifier as _;
+
+    use super::*;
+    use crate::blockdata::locktime::absolute;
+    use crate::blockdata::script::ScriptBuf;
+    use crate::blockdata::transaction::{self, Transaction, TxIn, TxOut};
+    use crate::consensus::{deserialize, serialize};
+    use crate::crypto::scalar::Scalar;
+    use crate::taproot::TapNodeHash;
+    use crate::Amount;
+
+    #[test]
+    fn threshold_sign_psbt() {
+        let mut rng = rand::thread_rng();
+        let (key_shares, public) =
+            frost::generate_with_dealer(Scalar::reduce_from(&[0x51; 32]), 2, 3, &mut rng).unwrap();
+        let internal_key = XOnlyPublicKey::from(public.group_key);
+        let merkle_root = TapNodeHash::from_byte_array([0x07; 32]);
+
+        let unsigned_tx = Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![TxIn::default(), TxIn::default(), TxIn::default()],
+            output: vec![TxOut::NULL],
+        };
+        let mut psbt = Psbt::from_unsigned_tx(unsigned_tx).unwrap();
+        for (i, root) in [(0, None), (1, Some(merkle_root))] {
+            psbt.inputs[i].witness_utxo = Some(TxOut {
+                value: Amount::from_sat(10_000),
+                script_pubkey: ScriptBuf::new_p2tr(internal_key, root),
+            });
+            psbt.inputs[i].tap_internal_key = Some(internal_key);
+            psbt.inputs[i].tap_merkle_root = root;
+        }
+        // An input spent by another key.
+        psbt.inputs[2].witness_utxo = Some(TxOut {
+            value: Amount::from_sat(10_000),
+            script_pubkey: ScriptBuf::new_p2tr(
+                XOnlyPublicKey::from(Scalar::one().base_point_mul()),
+                None,
+            ),
+        });
+
+        let mut coordinator = FrostCoordinator::new(public);
+        assert_eq!(coordinator.inputs(&psbt), [0, 1]);
+        assert!(matches!(
+            coordinator.signing_request(&psbt),
+            Err(FrostSignError::NotEnoughCommitments { input: 0, required: 2, got: 0 })
+        ));
+
+        // Signers 1 and 3 take part.
+        let signers = [&key_shares[0], &key_shares[2]];
+        let mut all_nonces = vec![];
+        for key in signers {
+            let nonces: BTreeMap<u32, SigningNonces> =
+                [0, 1].iter().map(|&i| (i, SigningNonces::new(&mut rng))).collect();
+            let commitments = nonces.iter().map(|(&i, n)| (i, n.commitments())).collect();
+            let message = CommitmentsMessage { index: key.index, commitments };
+            coordinator.add_commitments(deserialize(&serialize(&message)).unwrap()).unwrap();
+            all_nonces.push(nonces);
+        }
+
+        let request = coordinator.signing_request(&psbt).unwrap();
+        let request: SigningRequest = deserialize(&serialize(&request)).unwrap();
+
+        // Signers reject requests which don't match their copy of the PSBT.
+        let mut tampered = request.clone();
+        tampered.packages.get_mut(&1).unwrap().message = [0; 32];
+        assert_eq!(
+            tampered.sign(&psbt, signers[0], BTreeMap::new()),
+            Err(FrostSignError::SighashMismatch(1))
+        );
+        let mut tampered = request.clone();
+        tampered.packages.get_mut(&1).unwrap().tweak = None;
+        assert_eq!(
+            tampered.sign(&psbt, signers[0], BTreeMap::new()),
+            Err(FrostSignError::TweakMismatch(1))
+        );
+        let mut tampered = request.clone();
+        tampered.packages.insert(2, request.packages[&0].clone());
+        assert_eq!(
+            tampered.sign(&psbt, signers[0], BTreeMap::new()),
+            Err(FrostSignError::UnexpectedInput(2))
+        );
+        let messages: Vec<SharesMessage> = signers
+            .iter()
+            .zip(all_nonces)
+            .map(|(key, nonces)| {
+                let message = request.sign(&psbt, key, nonces).unwrap();
+                deserialize(&serialize(&message)).unwrap()
+            })
+            .collect();
+
+        assert_eq!(
+            coordinator.finalize(&mut psbt, &request, &messages[..1]),
+            Err(FrostSignError::Frost { input: 0, error: FrostError::MissingShare(3) })
+        );
+        assert!(psbt.inputs[0].tap_key_sig.is_none());
+
+        assert_eq!(coordinator.finalize(&mut psbt, &request, &messages), Ok(vec![0, 1]));
+        for i in 0..2 {
+            let (msg, _) =
+                psbt.sighash_taproot(i, &mut SighashCache::new(&psbt.unsigned_tx), None).unwrap();
+            let spk = &psbt.inputs[i].witness_utxo.as_ref().unwrap().script_pubkey;
+            let output_key = k256::schnorr::VerifyingKey::from_bytes(&spk.as_bytes()[2..]).unwrap();
+            let signature = psbt.inputs[i].tap_key_sig.unwrap().signature;
+            assert!(output_key.verify_prehash(msg.as_bytes(), &signature).is_ok());
+        }
+        assert!(coordinator.inputs(&psbt).is_empty());
+    }
+}
@@ -1,14 +1,5 @@
 // SPDX-License-Identifier: CC0-1.0
 
-#[allow(unused_macros)]
-macro_rules! combine {
-    ($thing:ident, $slf:ident, $other:ident) => {
-        if let (&None, Some($thing)) = (&$slf.$thing, $other.$thing) {
-            $slf.$thing = Some($thing);
-        }
-    };
-}
-
 macro_rules! impl_psbt_de_serialize {
     ($thing:ty) => {
         impl_psbt_serialize!($thing);
@@ -85,6 +76,59 @@ macro_rules! impl_psbtmap_ser_de_serialize {
     };
 }
 
+macro_rules! impl_psbt_proprietary {
+    ($thing:ty) => {
+        impl $thing {
+            /// Returns the value of the proprietary `key`, if present.
+            pub fn get_proprietary<Subtype>(
+                &self,
+                key: &$crate::psbt::raw::ProprietaryKey<Subtype>,
+            ) -> Option<&[u8]>
+            where
+                Subtype: Copy + From<u8> + Into<u8>,
+            {
+                self.proprietary
+                    .get(&key.to_raw())
+                    .map(|value| value.as_slice())
+            }
+
+            /// Inserts a proprietary key-value pair, returning the previous value of `key`.
+            pub fn insert_proprietary<Subtype>(
+                &mut self,
+                key: $crate::psbt::raw::ProprietaryKey<Subtype>,
+                value: Vec<u8>,
+            ) -> Option<Vec<u8>>
+            where
+                Subtype: Copy + From<u8> + Into<u8>,
+            {
+                self.proprietary.insert(key.to_raw(), value)
+            }
+
+            /// Returns the proprietary key-value pairs under `prefix`, with their subtypes
+            /// converted to `Subtype`.
+            pub fn proprietary_with_prefix<'a, Subtype>(
+                &'a self,
+                prefix: &'a [u8],
+            ) -> impl Iterator<Item = ($crate::psbt::raw::ProprietaryKey<Subtype>, &'a [u8])> + 'a
+            where
+                Subtype: Copy + From<u8> + Into<u8>,
+            {
+                self.proprietary
+                    .iter()
+                    .filter(move |(key, _)| key.prefix == prefix)
+                    .map(|(key, value)| {
+                        let key = $crate::psbt::raw::ProprietaryKey {
+                            prefix: key.prefix.clone(),
+                            subtype: Subtype::from(key.subtype),
+                            key: key.key.clone(),
+                        };
+                        (key, value.as_slice())
+                    })
+            }
+        }
+    };
+}
+
 #[rustfmt::skip]
 macro_rules! impl_psbt_insert_pair {
     ($slf:ident.$unkeyed_name:ident <= <$raw_key:ident: _>|<$raw_value:ident: $unkeyed_value_type:ty>) => {
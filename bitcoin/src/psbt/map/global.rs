@@ -212,3 +212,5 @@ impl Psbt {
         }
     }
 }
+
+impl_psbt_proprietary!(Psbt);
@@ -11,9 +11,10 @@ use crate::blockdata::script::ScriptBuf;
 use crate::blockdata::transaction::{Transaction, TxOut};
 use crate::blockdata::witness::Witness;
 use crate::crypto::key::PublicKey;
+use crate::crypto::musig2::{PartialSignature, PubNonce};
 use crate::crypto::{ecdsa, taproot};
 use crate::prelude::*;
-use crate::psbt::map::Map;
+use crate::psbt::map::{self, Map};
 use crate::psbt::serialize::Deserialize;
 use crate::psbt::{self, error, raw, Error};
 use crate::sighash::{
@@ -52,7 +53,7 @@ const PSBT_IN_HASH256: u8 = 0x0d;
 const PSBT_IN_TAP_KEY_SIG: u8 = 0x13;
 /// Type: Taproot Signature in Script Spend PSBT_IN_TAP_SCRIPT_SIG = 0x14
 const PSBT_IN_TAP_SCRIPT_SIG: u8 = 0x14;
-/// Type: Taproot Leaf Script PSBT_IN_TAP_LEAF_SCRIPT = 0x14
+/// Type: Taproot Leaf Script PSBT_IN_TAP_LEAF_SCRIPT = 0x15
 const PSBT_IN_TAP_LEAF_SCRIPT: u8 = 0x15;
 /// Type: Taproot Key BIP 32 Derivation Path PSBT_IN_TAP_BIP32_DERIVATION = 0x16
 const PSBT_IN_TAP_BIP32_DERIVATION: u8 = 0x16;
@@ -60,9 +61,25 @@ const PSBT_IN_TAP_BIP32_DERIVATION: u8 = 0x16;
 const PSBT_IN_TAP_INTERNAL_KEY: u8 = 0x17;
 /// Type: Taproot Merkle Root PSBT_IN_TAP_MERKLE_ROOT = 0x18
 const PSBT_IN_TAP_MERKLE_ROOT: u8 = 0x18;
+/// Type: MuSig2 Participant Public Keys PSBT_IN_MUSIG2_PARTICIPANT_PUBKEYS = 0x1a
+const PSBT_IN_MUSIG2_PARTICIPANT_PUBKEYS: u8 = 0x1a;
+/// Type: MuSig2 Public Nonce PSBT_IN_MUSIG2_PUB_NONCE = 0x1b
+const PSBT_IN_MUSIG2_PUB_NONCE: u8 = 0x1b;
+/// Type: MuSig2 Participant Partial Signature PSBT_IN_MUSIG2_PARTIAL_SIG = 0x1c
+const PSBT_IN_MUSIG2_PARTIAL_SIG: u8 = 0x1c;
 /// Type: Proprietary Use Type PSBT_IN_PROPRIETARY = 0xFC
 const PSBT_IN_PROPRIETARY: u8 = 0xFC;
 
+/// The types of the pairs holding partial or final signatures.
+pub(in crate::psbt) const SIGNATURE_TYPES: [u8; 6] = [
+    PSBT_IN_PARTIAL_SIG,
+    PSBT_IN_FINAL_SCRIPTSIG,
+    PSBT_IN_FINAL_SCRIPTWITNESS,
+    PSBT_IN_TAP_KEY_SIG,
+    PSBT_IN_TAP_SCRIPT_SIG,
+    PSBT_IN_MUSIG2_PARTIAL_SIG,
+];
+
 /// A key-value map for an input of the corresponding index in the unsigned
 /// transaction.
 #[derive(Clone, Default, Debug, PartialEq, Eq, Hash)]
@@ -136,6 +153,18 @@ pub struct Input {
     pub tap_internal_key: Option<XOnlyPublicKey>,
     /// Taproot Merkle root.
     pub tap_merkle_root: Option<TapNodeHash>,
+    /// Map of MuSig2 aggregate keys to the keys of their participants, in aggregation order.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::btreemap_as_seq"))]
+    pub musig2_participant_pubkeys: BTreeMap<PublicKey, Vec<PublicKey>>,
+    /// Map of `<participant key>|<aggregate key>|<leafhash>` with the public nonce of the
+    /// participant. There is no leaf hash for key path spends.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::btreemap_as_seq"))]
+    pub musig2_pub_nonces: BTreeMap<(PublicKey, PublicKey, Option<TapLeafHash>), PubNonce>,
+    /// Map of `<participant key>|<aggregate key>|<leafhash>` with the partial signature of the
+    /// participant. There is no leaf hash for key path spends.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::btreemap_as_seq"))]
+    pub musig2_partial_sigs:
+        BTreeMap<(PublicKey, PublicKey, Option<TapLeafHash>), PartialSignature>,
     /// Proprietary key-value pairs for this input.
     #[cfg_attr(
         feature = "serde",
@@ -382,6 +411,21 @@ impl Input {
                     self.tap_merkle_root <= <raw_key: _>|< raw_value: TapNodeHash>
                 }
             }
+            PSBT_IN_MUSIG2_PARTICIPANT_PUBKEYS => {
+                impl_psbt_insert_pair! {
+                    self.musig2_participant_pubkeys <= <raw_key: PublicKey>|<raw_value: Vec<PublicKey>>
+                }
+            }
+            PSBT_IN_MUSIG2_PUB_NONCE => {
+                impl_psbt_insert_pair! {
+                    self.musig2_pub_nonces <= <raw_key: (PublicKey, PublicKey, Option<TapLeafHash>)>|<raw_value: PubNonce>
+                }
+            }
+            PSBT_IN_MUSIG2_PARTIAL_SIG => {
+                impl_psbt_insert_pair! {
+                    self.musig2_partial_sigs <= <raw_key: (PublicKey, PublicKey, Option<TapLeafHash>)>|<raw_value: PartialSignature>
+                }
+            }
             PSBT_IN_PROPRIETARY => {
                 let key = raw::ProprietaryKey::try_from(raw_key.clone())?;
                 match self.proprietary.entry(key) {
@@ -403,33 +447,18 @@ impl Input {
     }
 
     /// Combines this [`Input`] with `other` `Input` (as described by BIP 174).
-    pub fn combine(&mut self, other: Self) {
-        combine!(non_witness_utxo, self, other);
-
-        if let (&None, Some(witness_utxo)) = (&self.witness_utxo, other.witness_utxo) {
-            self.witness_utxo = Some(witness_utxo);
-            self.non_witness_utxo = None; // Clear out any non-witness UTXO when we set a witness one
-        }
-
-        self.partial_sigs.extend(other.partial_sigs);
-        self.bip32_derivation.extend(other.bip32_derivation);
-        self.ripemd160_preimages.extend(other.ripemd160_preimages);
-        self.sha256_preimages.extend(other.sha256_preimages);
-        self.hash160_preimages.extend(other.hash160_preimages);
-        self.hash256_preimages.extend(other.hash256_preimages);
-        self.tap_script_sigs.extend(other.tap_script_sigs);
-        self.tap_scripts.extend(other.tap_scripts);
-        self.tap_key_origins.extend(other.tap_key_origins);
-        self.proprietary.extend(other.proprietary);
-        self.unknown.extend(other.unknown);
-
-        combine!(redeem_script, self, other);
-        combine!(witness_script, self, other);
-        combine!(final_script_sig, self, other);
-        combine!(final_script_witness, self, other);
-        combine!(tap_key_sig, self, other);
-        combine!(tap_internal_key, self, other);
-        combine!(tap_merkle_root, self, other);
+    ///
+    /// Key-value pairs present in both maps are only kept once.
+    ///
+    /// # Errors
+    ///
+    /// [`Error::CombineConflict`] if a key has a different value in each map, in which case
+    /// this map is left unchanged.
+    pub fn combine(&mut self, other: Self) -> Result<(), Error> {
+        for pair in map::new_pairs(self, &other)? {
+            self.insert_pair(pair)?;
+        }
+        Ok(())
     }
 }
 
@@ -512,6 +541,18 @@ impl Map for Input {
         impl_psbt_get_pair! {
             rv.push(self.tap_merkle_root, PSBT_IN_TAP_MERKLE_ROOT)
         }
+
+        impl_psbt_get_pair! {
+            rv.push_map(self.musig2_participant_pubkeys, PSBT_IN_MUSIG2_PARTICIPANT_PUBKEYS)
+        }
+
+        impl_psbt_get_pair! {
+            rv.push_map(self.musig2_pub_nonces, PSBT_IN_MUSIG2_PUB_NONCE)
+        }
+
+        impl_psbt_get_pair! {
+            rv.push_map(self.musig2_partial_sigs, PSBT_IN_MUSIG2_PARTIAL_SIG)
+        }
         for (key, value) in self.proprietary.iter() {
             rv.push(raw::Pair {
                 key: key.to_key(),
@@ -531,6 +572,7 @@ impl Map for Input {
 }
 
 impl_psbtmap_ser_de_serialize!(Input);
+impl_psbt_proprietary!(Input);
 
 fn psbt_insert_hash_pair<H>(
     map: &mut BTreeMap<H, Vec<u8>>,
@@ -5,8 +5,8 @@ mod input;
 mod output;
 
 use crate::prelude::*;
-use crate::psbt::raw;
 use crate::psbt::serialize::Serialize;
+use crate::psbt::{raw, Error};
 
 #[rustfmt::skip]                // Keep public re-exports separate.
 #[doc(inline)]
@@ -14,6 +14,7 @@ pub use self::{
     input::{Input, PsbtSighashType},
     output::Output,
 };
+pub(super) use self::input::SIGNATURE_TYPES;
 
 /// A trait that describes a PSBT key-value map.
 pub(super) trait Map {
@@ -36,3 +37,47 @@ pub(super) trait Map {
         buf
     }
 }
+
+/// Returns the key-value pairs of `other` missing from `map`, to combine them as described by
+/// BIP174.
+///
+/// # Errors
+///
+/// [`Error::CombineConflict`] if a key has a different value in each map.
+pub(super) fn new_pairs<M: Map>(map: &M, other: &M) -> Result<Vec<raw::Pair>, Error> {
+    let pairs = map
+        .get_pairs()
+        .into_iter()
+        .map(|pair| (pair.key, pair.value))
+        .collect::<BTreeMap<_, _>>();
+    let mut new = vec![];
+    for pair in other.get_pairs() {
+        match pairs.get(&pair.key) {
+            None => new.push(pair),
+            Some(value) if *value == pair.value => {}
+            Some(_) => return Err(Error::CombineConflict(pair.key)),
+        }
+    }
+    Ok(new)
+}
+
+/// Adds the entries of `other` missing from `map`.
+///
+/// # Errors
+///
+/// The first key with a different value in each map.
+pub(super) fn combine_map<K: Ord, V: PartialEq>(
+    map: &mut BTreeMap<K, V>,
+    other: BTreeMap<K, V>,
+) -> Result<(), K> {
+    for (key, value) in other {
+        match map.get(&key) {
+            None => {
+                map.insert(key, value);
+            }
+            Some(existing) if *existing == value => {}
+            Some(_) => return Err(key),
+        }
+    }
+    Ok(())
+}
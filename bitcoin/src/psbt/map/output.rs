@@ -5,7 +5,7 @@ use crate::{PublicKey, XOnlyPublicKey};
 use crate::bip32::KeySource;
 use crate::blockdata::script::ScriptBuf;
 use crate::prelude::*;
-use crate::psbt::map::Map;
+use crate::psbt::map::{self, Map};
 use crate::psbt::{raw, Error};
 use crate::taproot::{TapLeafHash, TapTree};
 
@@ -21,6 +21,8 @@ const PSBT_OUT_TAP_INTERNAL_KEY: u8 = 0x05;
 const PSBT_OUT_TAP_TREE: u8 = 0x06;
 /// Type: Taproot Key BIP 32 Derivation Path PSBT_OUT_TAP_BIP32_DERIVATION = 0x07
 const PSBT_OUT_TAP_BIP32_DERIVATION: u8 = 0x07;
+/// Type: MuSig2 Participant Public Keys PSBT_OUT_MUSIG2_PARTICIPANT_PUBKEYS = 0x08
+const PSBT_OUT_MUSIG2_PARTICIPANT_PUBKEYS: u8 = 0x08;
 /// Type: Proprietary Use Type PSBT_IN_PROPRIETARY = 0xFC
 const PSBT_OUT_PROPRIETARY: u8 = 0xFC;
 
@@ -45,6 +47,9 @@ pub struct Output {
     /// Map of tap root x only keys to origin info and leaf hashes contained in it.
     #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::btreemap_as_seq"))]
     pub tap_key_origins: BTreeMap<XOnlyPublicKey, (Vec<TapLeafHash>, KeySource)>,
+    /// Map of MuSig2 aggregate keys to the keys of their participants, in aggregation order.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::btreemap_as_seq"))]
+    pub musig2_participant_pubkeys: BTreeMap<PublicKey, Vec<PublicKey>>,
     /// Proprietary key-value pairs for this output.
     #[cfg_attr(
         feature = "serde",
@@ -106,6 +111,11 @@ impl Output {
                     self.tap_key_origins <= <raw_key: XOnlyPublicKey>|< raw_value: (Vec<TapLeafHash>, KeySource)>
                 }
             }
+            PSBT_OUT_MUSIG2_PARTICIPANT_PUBKEYS => {
+                impl_psbt_insert_pair! {
+                    self.musig2_participant_pubkeys <= <raw_key: PublicKey>|<raw_value: Vec<PublicKey>>
+                }
+            }
             _ => match self.unknown.entry(raw_key) {
                 btree_map::Entry::Vacant(empty_key) => {
                     empty_key.insert(raw_value);
@@ -118,16 +128,18 @@ impl Output {
     }
 
     /// Combines this [`Output`] with `other` `Output` (as described by BIP 174).
-    pub fn combine(&mut self, other: Self) {
-        self.bip32_derivation.extend(other.bip32_derivation);
-        self.proprietary.extend(other.proprietary);
-        self.unknown.extend(other.unknown);
-        self.tap_key_origins.extend(other.tap_key_origins);
-
-        combine!(redeem_script, self, other);
-        combine!(witness_script, self, other);
-        combine!(tap_internal_key, self, other);
-        combine!(tap_tree, self, other);
+    ///
+    /// Key-value pairs present in both maps are only kept once.
+    ///
+    /// # Errors
+    ///
+    /// [`Error::CombineConflict`] if a key has a different value in each map, in which case
+    /// this map is left unchanged.
+    pub fn combine(&mut self, other: Self) -> Result<(), Error> {
+        for pair in map::new_pairs(self, &other)? {
+            self.insert_pair(pair)?;
+        }
+        Ok(())
     }
 }
 
@@ -159,6 +171,10 @@ impl Map for Output {
             rv.push_map(self.tap_key_origins, PSBT_OUT_TAP_BIP32_DERIVATION)
         }
 
+        impl_psbt_get_pair! {
+            rv.push_map(self.musig2_participant_pubkeys, PSBT_OUT_MUSIG2_PARTICIPANT_PUBKEYS)
+        }
+
         for (key, value) in self.proprietary.iter() {
             rv.push(raw::Pair {
                 key: key.to_key(),
@@ -178,3 +194,4 @@ impl Map for Output {
 }
 
 impl_psbtmap_ser_de_serialize!(Output);
+impl_psbt_proprietary!(Output);
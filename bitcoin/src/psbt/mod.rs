@@ -9,10 +9,16 @@
 
 #[macro_use]
 mod macros;
+mod diff;
 mod error;
+mod external_signer;
+mod finalizer;
+mod frost;
 mod map;
+mod musig2;
 pub mod raw;
 pub mod serialize;
+mod signer;
 mod types;
 
 use core::{cmp, fmt};
@@ -21,20 +27,16 @@ use std::collections::{HashMap, HashSet};
 
 use hashes::Hash;
 use internals::write_err;
-use k256::ecdsa::{
-    signature::Signer as EcdsaSigner, Signature as EcdsaSignature, SigningKey as EcdsaSigningKey,
-};
-use k256::schnorr::{
-    signature::{Signer as _, Verifier as _},
-    Signature as SchnorrSignature,
-};
+use k256::schnorr::Signature as SchnorrSignature;
 // use secp256k1::{Keypair, Message, Secp256k1, Signing, Verification};
 
+use crate::amount::CheckedSum;
 use crate::bip32::{self, KeySource, Xpriv, Xpub};
-use crate::blockdata::transaction::{self, Transaction, TxOut};
-use crate::common::types::Message;
+use crate::blockdata::transaction::{Transaction, TxOut};
+use crate::common::types::{Message, Parity};
 use crate::crypto::key::{PrivateKey, PublicKey};
-use crate::crypto::{ecdsa, taproot};
+use crate::crypto::scalar::Scalar;
+use crate::crypto::{ecdsa, schnorr, taproot};
 use crate::key::{Keypair, TapTweak};
 use crate::prelude::*;
 use crate::sighash::{self, EcdsaSighashType, Prevouts, SighashCache};
@@ -45,7 +47,13 @@ use crate::{Amount, FeeRate, TapSighashType};
 #[doc(inline)]
 pub use self::{
     map::{Input, Output, PsbtSighashType},
+    diff::{ChangedValue, MapDiff, PsbtDiff},
     error::Error,
+    external_signer::{ExternalSignError, ExternalSigner, RetryPolicy, SigningProgress},
+    finalizer::{FinalizeError, FinalizeInputError},
+    frost::{CommitmentsMessage, FrostCoordinator, FrostSignError, SharesMessage, SigningRequest},
+    musig2::Musig2SignError,
+    signer::SignerError,
 };
 
 /// A Partially Signed Transaction.
@@ -247,8 +255,23 @@ impl Psbt {
 
     /// Combines this [`Psbt`] with `other` PSBT as described by BIP 174.
     ///
-    /// In accordance with BIP 174 this function is commutative i.e., `A.combine(B) == B.combine(A)`
+    /// In accordance with BIP 174 this function is commutative i.e., `A.combine(B) == B.combine(A)`.
+    /// Key-value pairs present in both PSBTs, such as the same partial signature, are only kept
+    /// once, which lets every cosigner of a multi-signer workflow return a separately-signed PSBT.
+    ///
+    /// # Errors
+    ///
+    /// If the PSBTs don't describe the same unsigned transaction, or if they conflict: a key has a
+    /// different value in each PSBT, or an xpub has inconsistent key sources. This PSBT is left
+    /// unchanged when an error is returned.
     pub fn combine(&mut self, other: Self) -> Result<(), Error> {
+        let mut combined = self.clone();
+        combined.combine_into(other)?;
+        *self = combined;
+        Ok(())
+    }
+
+    fn combine_into(&mut self, other: Self) -> Result<(), Error> {
         if self.unsigned_tx != other.unsigned_tx {
             return Err(Error::UnexpectedUnsignedTx {
                 expected: Box::new(self.unsigned_tx.clone()),
@@ -297,15 +320,16 @@ impl Psbt {
             }
         }
 
-        self.proprietary.extend(other.proprietary);
-        self.unknown.extend(other.unknown);
+        map::combine_map(&mut self.proprietary, other.proprietary)
+            .map_err(|key| Error::CombineConflict(key.to_key()))?;
+        map::combine_map(&mut self.unknown, other.unknown).map_err(Error::CombineConflict)?;
 
         for (self_input, other_input) in self.inputs.iter_mut().zip(other.inputs.into_iter()) {
-            self_input.combine(other_input);
+            self_input.combine(other_input)?;
         }
 
         for (self_output, other_output) in self.outputs.iter_mut().zip(other.outputs.into_iter()) {
-            self_output.combine(other_output);
+            self_output.combine(other_output)?;
         }
 
         Ok(())
@@ -313,8 +337,13 @@ impl Psbt {
 
     /// Attempts to create _all_ the required signatures for this PSBT using `k`.
     ///
-    /// **NOTE**: Taproot inputs are, as yet, not supported by this function. We currently only
-    /// attempt to sign ECDSA inputs.
+    /// P2PKH, P2SH, P2WPKH, P2WSH and nested segwit inputs are signed for the keys of their
+    /// `bip32_derivation`, adding to `partial_sigs`. Taproot inputs are signed for the keys of
+    /// their `tap_key_origins`, adding to `tap_key_sig` for a key path spend by the internal key
+    /// and to `tap_script_sigs` for every leaf hash of the key otherwise.
+    ///
+    /// Keys are requested from `k` by key source, then by public key. Taproot keys are x-only,
+    /// so they are requested with both parities.
     ///
     /// If you just want to sign an input with one specific key consider using `sighash_ecdsa`. This
     /// function does not support scripts that contain `OP_CODESEPARATOR`.
@@ -327,7 +356,34 @@ impl Psbt {
     ///
     /// If an error is returned some signatures may already have been added to the PSBT. Since
     /// `partial_sigs` is a [`BTreeMap`] it is safe to retry, previous sigs will be overwritten.
+    ///
+    /// ECDSA signatures use the plain RFC6979 nonce, see [`Psbt::sign_low_r`] to grind them.
     pub fn sign<K>(&mut self, k: &K) -> Result<SigningKeys, (SigningKeys, SigningErrors)>
+    where
+        K: GetKey,
+    {
+        self.sign_inner(k, false)
+    }
+
+    /// Attempts to create _all_ the required signatures for this PSBT using `k`, grinding ECDSA
+    /// signatures to a low R value.
+    ///
+    /// This is [`Psbt::sign`], except that ECDSA signatures are created with
+    /// [`ecdsa::sign_ecdsa_low_r`], like Bitcoin Core does. They are at most 71 bytes long with
+    /// their sighash type, which keeps the size of the finalized transaction predictable, but
+    /// they differ from the plain RFC6979 signatures other signers produce for the same keys.
+    pub fn sign_low_r<K>(&mut self, k: &K) -> Result<SigningKeys, (SigningKeys, SigningErrors)>
+    where
+        K: GetKey,
+    {
+        self.sign_inner(k, true)
+    }
+
+    fn sign_inner<K>(
+        &mut self,
+        k: &K,
+        low_r: bool,
+    ) -> Result<SigningKeys, (SigningKeys, SigningErrors)>
     where
         K: GetKey,
     {
@@ -339,7 +395,8 @@ impl Psbt {
 
         for i in 0..self.inputs.len() {
             match self.signing_algorithm(i) {
-                Ok(SigningAlgorithm::Ecdsa) => match self.bip32_sign_ecdsa(k, i, &mut cache) {
+                Ok(SigningAlgorithm::Ecdsa) => match self.bip32_sign_ecdsa(k, i, &mut cache, low_r)
+                {
                     Ok(v) => {
                         used.insert(i, v);
                     }
@@ -368,7 +425,7 @@ impl Psbt {
     }
 
     /// Attempts to create all signatures required by this PSBT's `bip32_derivation` field, adding
-    /// them to `partial_sigs`.
+    /// them to `partial_sigs`, ground to a low R value if `low_r` is set.
     ///
     /// # Returns
     ///
@@ -379,6 +436,7 @@ impl Psbt {
         k: &K,
         input_index: usize,
         cache: &mut SighashCache<T>,
+        low_r: bool,
     ) -> Result<Vec<PublicKey>, SignError>
     where
         T: Borrow<Transaction>,
@@ -406,8 +464,12 @@ impl Psbt {
             };
 
             let pk = sk.public_key();
-            let signing_key = EcdsaSigningKey::from(sk.inner);
-            let signature: EcdsaSignature = signing_key.sign(msg.as_bytes());
+            let secret = Scalar::from(&sk.inner);
+            let signature = if low_r {
+                ecdsa::sign_ecdsa_low_r(&msg, &secret)
+            } else {
+                ecdsa::sign_ecdsa(&msg, &secret)
+            };
             let sig = ecdsa::Signature {
                 signature,
                 sighash_type: sighash_ty,
@@ -444,11 +506,23 @@ impl Psbt {
 
         for (&xonly, (leaf_hashes, key_source)) in input.tap_key_origins.iter() {
             let sk = if let Ok(Some(secret_key)) = k.get_key(KeyRequest::Bip32(key_source.clone()))
+            {
+                secret_key
+            } else if let Some(secret_key) =
+                [Parity::Even, Parity::Odd].iter().find_map(|&parity| {
+                    k.get_key(KeyRequest::Pubkey(xonly.public_key(parity)))
+                        .ok()
+                        .flatten()
+                })
             {
                 secret_key
             } else {
                 continue;
             };
+            // A key of another origin would only produce invalid signatures.
+            if sk.public_key().x_only_public_key().0 != xonly {
+                continue;
+            }
 
             // Considering the responsibility of the PSBT's finalizer to extract valid signatures,
             // the goal of this algorithm is to provide signatures to the best of our ability:
@@ -467,8 +541,10 @@ impl Psbt {
                         .tap_tweak(input.tap_merkle_root)
                         .to_inner();
 
-                    let signer = key_pair.to_signing_key();
-                    let signature: SchnorrSignature = signer.sign(msg.as_bytes());
+                    let signature: SchnorrSignature =
+                        schnorr::sign_schnorr(&msg, &key_pair, &[0; 32])
+                            .try_into()
+                            .expect("a BIP340 signature is always valid");
 
                     let signature = taproot::Signature {
                         signature,
@@ -490,12 +566,14 @@ impl Psbt {
 
                 if !leaf_hashes.is_empty() {
                     let key_pair = Keypair::from_secret_key(&sk.inner);
-                    let signer = key_pair.to_signing_key();
                     for lh in leaf_hashes {
                         let (msg, sighash_type) =
                             self.sighash_taproot(input_index, cache, Some(lh))?;
 
-                        let signature: SchnorrSignature = signer.sign(msg.as_bytes());
+                        let signature: SchnorrSignature =
+                            schnorr::sign_schnorr(&msg, &key_pair, &[0; 32])
+                                .try_into()
+                                .expect("a BIP340 signature is always valid");
 
                         let signature = taproot::Signature {
                             signature,
@@ -575,10 +653,7 @@ impl Psbt {
                     .map_err(SignError::SegwitV0Sighash)?;
                 Ok((Message::from_digest(sighash.to_byte_array()), hash_ty))
             }
-            Tr => {
-                // This PSBT signing API is WIP, taproot to come shortly.
-                Err(SignError::Unsupported)
-            }
+            Tr => Err(SignError::WrongSigningAlgorithm), // Checked above.
         }
     }
 
@@ -751,22 +826,44 @@ impl Psbt {
     /// - [`Error::NegativeFee`] if calculated value is negative.
     /// - [`Error::FeeOverflow`] if an integer overflow occurs.
     pub fn fee(&self) -> Result<Amount, Error> {
-        let mut inputs: u64 = 0;
+        let mut inputs = Amount::ZERO;
         for utxo in self.iter_funding_utxos() {
-            inputs = inputs
-                .checked_add(utxo?.value.to_sat())
-                .ok_or(Error::FeeOverflow)?;
+            inputs = inputs.checked_add(utxo?.value).ok_or(Error::FeeOverflow)?;
         }
-        let mut outputs: u64 = 0;
-        for out in &self.unsigned_tx.output {
-            outputs = outputs
-                .checked_add(out.value.to_sat())
-                .ok_or(Error::FeeOverflow)?;
+        let outputs = self
+            .unsigned_tx
+            .output
+            .iter()
+            .map(|out| out.value)
+            .checked_sum()
+            .ok_or(Error::FeeOverflow)?;
+        inputs.checked_sub(outputs).ok_or(Error::NegativeFee)
+    }
+
+    /// Calculates the fee rate the transaction pays once finalized, refusing rates above
+    /// `max_fee_rate`.
+    ///
+    /// The rate is the [`fee`](Psbt::fee) over the
+    /// [`unsigned_tx_weight_upper_bound`](Psbt::unsigned_tx_weight_upper_bound), so the finalized
+    /// transaction pays at least this rate. Signers can use this to refuse to sign transactions
+    /// with absurd fees before any signature exists, see [`Psbt::DEFAULT_MAX_FEE_RATE`].
+    ///
+    /// ## Errors
+    ///
+    /// - [`FeeRateError::Fee`] if the fee can't be calculated, see [`Psbt::fee`].
+    /// - [`FeeRateError::Weight`] if the weight of an input can't be bounded.
+    /// - [`FeeRateError::AbsurdFeeRate`] if the fee rate is higher than `max_fee_rate`.
+    pub fn fee_rate_checked(&self, max_fee_rate: FeeRate) -> Result<FeeRate, FeeRateError> {
+        let fee = self.fee().map_err(FeeRateError::Fee)?;
+        let weight = self
+            .unsigned_tx_weight_upper_bound()
+            .map_err(FeeRateError::Weight)?;
+        let fee_rate =
+            FeeRate::from_sat_per_kwu(fee.to_sat().saturating_mul(1000) / weight.to_wu());
+        if fee_rate > max_fee_rate {
+            return Err(FeeRateError::AbsurdFeeRate(fee_rate));
         }
-        inputs
-            .checked_sub(outputs)
-            .map(Amount::from_sat)
-            .ok_or(Error::NegativeFee)
+        Ok(fee_rate)
     }
 }
 
@@ -980,7 +1077,7 @@ pub enum SignError {
     /// The `scriptPubkey` is not a P2WPKH script.
     NotWpkh,
     /// Sighash computation error (segwit v0 input).
-    SegwitV0Sighash(transaction::InputsIndexError),
+    SegwitV0Sighash(sighash::P2wshError),
     /// Sighash computation error (p2wpkh input).
     P2wpkhSighash(sighash::P2wpkhError),
     /// Sighash computation error (taproot input).
@@ -1125,6 +1222,46 @@ impl std::error::Error for ExtractTxError {
     }
 }
 
+/// This error is returned when computing the fee rate of a [`Psbt`] with
+/// [`Psbt::fee_rate_checked`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum FeeRateError {
+    /// The fee couldn't be calculated.
+    Fee(Error),
+    /// The weight of the finalized transaction couldn't be bounded.
+    Weight(FinalizeError),
+    /// The [`FeeRate`] is higher than the maximum.
+    AbsurdFeeRate(FeeRate),
+}
+
+internals::impl_from_infallible!(FeeRateError);
+
+impl fmt::Display for FeeRateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use FeeRateError::*;
+
+        match *self {
+            Fee(ref e) => write_err!(f, "failed to calculate the fee"; e),
+            Weight(ref e) => write_err!(f, "failed to bound the transaction weight"; e),
+            AbsurdFeeRate(fee_rate) => write!(f, "an absurdly high fee rate of {}", fee_rate),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for FeeRateError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use FeeRateError::*;
+
+        match *self {
+            Fee(ref e) => Some(e),
+            Weight(ref e) => Some(e),
+            AbsurdFeeRate(_) => None,
+        }
+    }
+}
+
 /// Input index out of bounds (actual index, maximum index allowed).
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[non_exhaustive]
@@ -1183,24 +1320,31 @@ impl std::error::Error for IndexOutOfBoundsError {
     }
 }
 
-#[cfg(feature = "base64")]
 mod display_from_str {
     use core::fmt::{self, Display, Formatter};
+    #[cfg(feature = "base64")]
     use core::str::FromStr;
 
+    #[cfg(feature = "base64")]
     use base64::display::Base64Display;
+    #[cfg(feature = "base64")]
     use base64::prelude::{Engine as _, BASE64_STANDARD};
     use internals::write_err;
 
-    use super::{Error, Psbt};
+    use super::Error;
+    #[cfg(feature = "base64")]
+    use super::Psbt;
 
-    /// Error encountered during PSBT decoding from Base64 string.
+    /// Error encountered during PSBT decoding from a Base64 or hex string.
     #[derive(Debug)]
     #[non_exhaustive]
     pub enum PsbtParseError {
         /// Error in internal PSBT data structure.
         PsbtEncoding(Error),
+        /// Error in PSBT hex encoding.
+        Hex(hex::HexToBytesError),
         /// Error in PSBT Base64 encoding.
+        #[cfg(feature = "base64")]
         Base64Encoding(::base64::DecodeError),
     }
 
@@ -1212,6 +1356,8 @@ mod display_from_str {
 
             match *self {
                 PsbtEncoding(ref e) => write_err!(f, "error in internal PSBT data structure"; e),
+                Hex(ref e) => write_err!(f, "error in PSBT hex encoding"; e),
+                #[cfg(feature = "base64")]
                 Base64Encoding(ref e) => write_err!(f, "error in PSBT base64 encoding"; e),
             }
         }
@@ -1224,11 +1370,15 @@ mod display_from_str {
 
             match self {
                 PsbtEncoding(e) => Some(e),
+                Hex(e) => Some(e),
+                #[cfg(feature = "base64")]
                 Base64Encoding(e) => Some(e),
             }
         }
     }
 
+    /// Formats the PSBT as Base64, the encoding used to exchange PSBTs between wallets.
+    #[cfg(feature = "base64")]
     impl Display for Psbt {
         fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
             write!(
@@ -1239,6 +1389,8 @@ mod display_from_str {
         }
     }
 
+    /// Parses a Base64 encoded PSBT.
+    #[cfg(feature = "base64")]
     impl FromStr for Psbt {
         type Err = PsbtParseError;
 
@@ -1250,7 +1402,6 @@ mod display_from_str {
         }
     }
 }
-#[cfg(feature = "base64")]
 pub use self::display_from_str::PsbtParseError;
 
 #[cfg(test)]
@@ -1343,6 +1494,17 @@ mod tests {
             psbt.serialize_hex(),
             "70736274ff01000a0200000000000000000000"
         );
+        assert_eq!(Psbt::from_hex(&psbt.serialize_hex()).unwrap(), psbt);
+        assert!(matches!(
+            Psbt::from_hex("70736274ff0"),
+            Err(PsbtParseError::Hex(_))
+        ));
+        assert!(matches!(
+            Psbt::from_hex("70736274ff"),
+            Err(PsbtParseError::PsbtEncoding(_))
+        ));
+        #[cfg(feature = "base64")]
+        assert_eq!(psbt.to_string().parse::<Psbt>().unwrap(), psbt);
     }
 
     #[test]
@@ -2175,6 +2337,77 @@ mod tests {
         assert!(!rtt.proprietary.is_empty());
     }
 
+    #[test]
+    fn typed_proprietary_keys() {
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        enum Vendor {
+            Nonce,
+            Other(u8),
+        }
+
+        impl From<u8> for Vendor {
+            fn from(byte: u8) -> Self {
+                match byte {
+                    0 => Vendor::Nonce,
+                    other => Vendor::Other(other),
+                }
+            }
+        }
+
+        impl From<Vendor> for u8 {
+            fn from(subtype: Vendor) -> Self {
+                match subtype {
+                    Vendor::Nonce => 0,
+                    Vendor::Other(other) => other,
+                }
+            }
+        }
+
+        let mut psbt = Psbt::from_unsigned_tx(Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![TxIn::default()],
+            output: vec![TxOut::NULL],
+        })
+        .unwrap();
+        let nonce = raw::ProprietaryKey::new(b"vendor".to_vec(), Vendor::Nonce, vec![1]);
+        assert_eq!(
+            psbt.inputs[0].insert_proprietary(nonce.clone(), vec![2; 66]),
+            None
+        );
+        psbt.outputs[0].insert_proprietary(nonce.clone(), vec![3]);
+        psbt.insert_proprietary(
+            raw::ProprietaryKey::new(b"other".to_vec(), 7u8, vec![]),
+            vec![4],
+        );
+        psbt.inputs[0].unknown.insert(
+            raw::Key {
+                type_value: 0xf0,
+                key: vec![5],
+            },
+            vec![6],
+        );
+
+        let psbt = Psbt::deserialize(&psbt.serialize()).unwrap();
+        assert_eq!(psbt.inputs[0].get_proprietary(&nonce), Some(&[2; 66][..]));
+        assert_eq!(psbt.outputs[0].get_proprietary(&nonce), Some(&[3][..]));
+        assert_eq!(psbt.get_proprietary(&nonce), None);
+        assert_eq!(psbt.inputs[0].unknown.len(), 1);
+
+        let other = psbt
+            .proprietary_with_prefix::<Vendor>(b"other")
+            .collect::<Vec<_>>();
+        assert_eq!(other.len(), 1);
+        assert_eq!(other[0].0.subtype, Vendor::Other(7));
+        assert_eq!(other[0].1, [4]);
+        assert_eq!(
+            psbt.inputs[0]
+                .proprietary_with_prefix::<Vendor>(b"other")
+                .count(),
+            0
+        );
+    }
+
     // PSBTs taken from BIP 174 test vectors.
     #[test]
     fn combine_psbts() {
@@ -2204,6 +2437,61 @@ mod tests {
         assert_eq!(psbt1, psbt2);
     }
 
+    #[test]
+    fn combine_psbts_dedup_and_conflicts() {
+        let key = |byte| {
+            PrivateKey::from_slice(&[byte; 32], NetworkKind::Test)
+                .unwrap()
+                .public_key()
+        };
+        let signature = |sighash_type| ecdsa::Signature {
+            signature: k256::ecdsa::Signature::from_scalars([1; 32], [1; 32]).unwrap(),
+            sighash_type,
+        };
+        let base = psbt_with_values(10_000, 9_000);
+
+        // Two cosigners return the PSBT with their own signature, one also echoing the other's.
+        let mut psbt1 = base.clone();
+        psbt1.inputs[0]
+            .partial_sigs
+            .insert(key(1), signature(EcdsaSighashType::All));
+        let mut psbt2 = psbt1.clone();
+        psbt2.inputs[0]
+            .partial_sigs
+            .insert(key(2), signature(EcdsaSighashType::All));
+        psbt1.combine(psbt2.clone()).unwrap();
+        assert_eq!(psbt1, psbt2);
+
+        let mut conflicting = base;
+        conflicting.inputs[0]
+            .partial_sigs
+            .insert(key(1), signature(EcdsaSighashType::None));
+        let partial_sig_key = raw::Key {
+            type_value: 0x02,
+            key: key(1).to_bytes(),
+        };
+        assert!(matches!(
+            psbt1.combine(conflicting.clone()),
+            Err(Error::CombineConflict(raw_key)) if raw_key == partial_sig_key
+        ));
+        assert_eq!(psbt1, psbt2);
+
+        conflicting.inputs[0].partial_sigs.clear();
+        let proprietary_key = raw::ProprietaryKey {
+            prefix: b"test".to_vec(),
+            subtype: 0,
+            key: vec![],
+        };
+        conflicting
+            .proprietary
+            .insert(proprietary_key.clone(), vec![1]);
+        psbt1.proprietary.insert(proprietary_key.clone(), vec![2]);
+        assert!(matches!(
+            psbt1.combine(conflicting),
+            Err(Error::CombineConflict(raw_key)) if raw_key == proprietary_key.to_key()
+        ));
+    }
+
     #[cfg(feature = "rand-std")]
     fn gen_keys() -> (PrivateKey, PublicKey, Secp256k1<All>) {
         use secp256k1::rand::thread_rng;
@@ -2389,4 +2677,165 @@ mod tests {
         assert_eq!(signing_keys.len(), 1);
         assert_eq!(signing_keys[&0], vec![pk]);
     }
+
+    #[test]
+    fn sign_taproot_key_path() {
+        use crate::bip32::DerivationPath;
+        use crate::crypto::schnorr::{verify_schnorr, Signature64};
+        use crate::sighash::Prevouts;
+
+        let xpriv = Xpriv::new_master(NetworkKind::Test, &[0x2a; 32]).unwrap();
+        let path: DerivationPath = "86'/1'/0'/0/0".parse().unwrap();
+        let (internal_key, _) = xpriv
+            .derive_priv(&path)
+            .unwrap()
+            .to_keypair()
+            .x_only_public_key();
+
+        let unsigned_tx = Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![TxIn::default()],
+            output: vec![TxOut::NULL],
+        };
+        let mut psbt = Psbt::from_unsigned_tx(unsigned_tx).unwrap();
+        let utxo = TxOut {
+            value: Amount::from_sat(10_000),
+            script_pubkey: ScriptBuf::new_p2tr(internal_key, None),
+        };
+        psbt.inputs[0].witness_utxo = Some(utxo.clone());
+        psbt.inputs[0].tap_internal_key = Some(internal_key);
+        psbt.inputs[0]
+            .tap_key_origins
+            .insert(internal_key, (vec![], (xpriv.fingerprint(), path)));
+
+        psbt.sign(&xpriv).unwrap();
+
+        // The signature verifies against the output key, as a key path spend requires.
+        let signature = psbt.inputs[0].tap_key_sig.unwrap();
+        assert_eq!(signature.sighash_type, TapSighashType::Default);
+        let sighash = SighashCache::new(&psbt.unsigned_tx)
+            .taproot_key_spend_signature_hash(0, &Prevouts::All(&[utxo]), TapSighashType::Default)
+            .unwrap();
+        let (output_key, _) = internal_key.tap_tweak(None);
+        let msg = Message::from_digest(sighash.to_byte_array());
+        let signature = Signature64::from(signature.signature);
+        assert_eq!(
+            verify_schnorr(&signature, &msg, &output_key.to_inner()),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn sign_every_input_type() {
+        use k256::ecdsa::signature::hazmat::PrehashVerifier;
+
+        use crate::blockdata::opcodes::all::OP_CHECKSIG;
+        use crate::blockdata::script::Builder;
+        use crate::crypto::schnorr::{verify_schnorr, Signature64};
+        use crate::multisig::{multi_script, MultiContext};
+        use crate::taproot::LeafVersion;
+
+        let priv_key = PrivateKey::from_slice(&[0x2a; 32], NetworkKind::Test).unwrap();
+        let pk = priv_key.public_key();
+        let (xonly, _) = pk.x_only_public_key();
+        let (other, _) = PrivateKey::from_slice(&[0x2b; 32], NetworkKind::Test)
+            .unwrap()
+            .public_key()
+            .x_only_public_key();
+        // A key map signs with `KeyRequest::Pubkey`, so the key sources are not used.
+        let mut key_map = BTreeMap::new();
+        key_map.insert(pk, priv_key);
+        let origin = KeySource::default();
+
+        let unsigned_tx = Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![TxIn::default(); 7],
+            output: vec![TxOut::NULL],
+        };
+        let mut psbt = Psbt::from_unsigned_tx(unsigned_tx).unwrap();
+        let utxo = |script_pubkey| {
+            Some(TxOut {
+                value: Amount::from_sat(10_000),
+                script_pubkey,
+            })
+        };
+        let wpkh = ScriptBuf::new_p2wpkh(&pk.wpubkey_hash().unwrap());
+        let multi = multi_script(1, &[pk], MultiContext::Sh).unwrap();
+        let pk_script = Builder::new()
+            .push_key(&pk)
+            .push_opcode(OP_CHECKSIG)
+            .into_script();
+        let leaf_script = Builder::new()
+            .push_x_only_key(&xonly)
+            .push_opcode(OP_CHECKSIG)
+            .into_script();
+        let leaf_hash = TapLeafHash::from_script(&leaf_script, LeafVersion::TapScript);
+
+        let inputs = &mut psbt.inputs;
+        inputs[0].witness_utxo = utxo(ScriptBuf::new_p2pkh(&pk.pubkey_hash()));
+        inputs[1].witness_utxo = utxo(ScriptBuf::new_p2sh(&multi.script_hash()));
+        inputs[1].redeem_script = Some(multi);
+        inputs[2].witness_utxo = utxo(wpkh.clone());
+        inputs[3].witness_utxo = utxo(ScriptBuf::new_p2sh(&wpkh.script_hash()));
+        inputs[3].redeem_script = Some(wpkh);
+        inputs[4].witness_utxo = utxo(ScriptBuf::new_p2wsh(&pk_script.wscript_hash()));
+        inputs[4].witness_script = Some(pk_script);
+        for input in &mut inputs[..5] {
+            input.bip32_derivation.insert(pk, origin.clone());
+        }
+        inputs[5].witness_utxo = utxo(ScriptBuf::new_p2tr(xonly, None));
+        inputs[5].tap_internal_key = Some(xonly);
+        inputs[5]
+            .tap_key_origins
+            .insert(xonly, (vec![], origin.clone()));
+        inputs[6].witness_utxo = utxo(ScriptBuf::new_p2tr(other, None));
+        inputs[6].tap_internal_key = Some(other);
+        inputs[6]
+            .tap_key_origins
+            .insert(xonly, (vec![leaf_hash], origin));
+
+        let signing_keys = psbt.sign(&key_map).unwrap();
+        assert_eq!(signing_keys.len(), 7);
+        assert!(signing_keys.values().all(|keys| keys == &vec![pk]));
+
+        let mut cache = SighashCache::new(&psbt.unsigned_tx);
+        let verifying_key = k256::ecdsa::VerifyingKey::from(pk.inner);
+        for input_index in 0..5 {
+            let (msg, _) = psbt.sighash_ecdsa(input_index, &mut cache).unwrap();
+            let signature = psbt.inputs[input_index].partial_sigs[&pk].signature;
+            assert!(verifying_key
+                .verify_prehash(msg.as_ref(), &signature)
+                .is_ok());
+        }
+
+        let mut low_r = psbt.clone();
+        low_r.sign_low_r(&key_map).unwrap();
+        for input_index in 0..5 {
+            let (msg, _) = low_r.sighash_ecdsa(input_index, &mut cache).unwrap();
+            let signature = low_r.inputs[input_index].partial_sigs[&pk].signature;
+            assert!(signature.r().to_bytes()[0] < 0x80);
+            assert!(verifying_key
+                .verify_prehash(msg.as_ref(), &signature)
+                .is_ok());
+        }
+
+        let (msg, _) = psbt.sighash_taproot(5, &mut cache, None).unwrap();
+        let signature = Signature64::from(psbt.inputs[5].tap_key_sig.unwrap().signature);
+        let (output_key, _) = xonly.tap_tweak(None);
+        assert_eq!(
+            verify_schnorr(&signature, &msg, &output_key.to_inner()),
+            Ok(())
+        );
+        let (msg, _) = psbt
+            .sighash_taproot(6, &mut cache, Some(leaf_hash))
+            .unwrap();
+        let signature = psbt.inputs[6].tap_script_sigs[&(xonly, leaf_hash)].signature;
+        assert_eq!(
+            verify_schnorr(&Signature64::from(signature), &msg, &xonly),
+            Ok(())
+        );
+        assert!(psbt.inputs[6].tap_key_sig.is_none());
+    }
 }
@@ -0,0 +1,425 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! MuSig2 signing of PSBTs.
+//!
+//! Produces the key path signatures of the taproot inputs whose internal key is a [`musig2`]
+//! aggregate key, exchanging everything through the BIP373 fields of the PSBT:
+//!
+//! 1. An updater records the participant keys of the aggregate key of every input with
+//!    [`Psbt::add_musig2_participants`].
+//! 2. Every signer adds its public nonces with [`Psbt::add_musig2_nonces`], keeping the secret
+//!    nonces it returns.
+//! 3. Once the PSBTs of all signers are combined, every signer adds its partial signatures with
+//!    [`Psbt::add_musig2_partial_sigs`].
+//! 4. Once these are combined too, [`Psbt::aggregate_musig2_sigs`] verifies the partial
+//!    signatures and adds the aggregated key path signatures.
+//!
+//! Nonces and partial signatures for script path spends, keyed by a leaf hash, are kept in the
+//! PSBT but not produced here.
+//!
+//! [`musig2`]: crate::crypto::musig2
+
+use core::fmt;
+
+use internals::write_err;
+use rand::{CryptoRng, RngCore};
+
+use super::{IndexOutOfBoundsError, Psbt, SignError};
+use crate::crypto::key::{PublicKey, XOnlyPublicKey};
+use crate::crypto::musig2::{AggNonce, KeyAggContext, Musig2Error, PubNonce, SecNonce, Session};
+use crate::crypto::scalar::Scalar;
+use crate::crypto::sighash::SighashCache;
+use crate::crypto::taproot;
+use crate::prelude::*;
+
+impl Psbt {
+    /// Records `participants` as the keys aggregated, in this order, into the taproot internal
+    /// key of the input at `input_index`.
+    ///
+    /// # Returns
+    ///
+    /// The aggregate key, which keys the nonces and partial signatures of the input.
+    pub fn add_musig2_participants(
+        &mut self,
+        input_index: usize,
+        participants: Vec<PublicKey>,
+    ) -> Result<PublicKey, Musig2SignError> {
+        let input = self.checked_input(input_index)?;
+        let key_agg = KeyAggContext::new(participants.iter().copied())
+            .map_err(|error| Musig2SignError::Musig2 { input: input_index, error })?;
+        if input.tap_internal_key != Some(key_agg.x_only_public_key()) {
+            return Err(Musig2SignError::AggregateKeyMismatch(input_index));
+        }
+
+        let aggregate = key_agg.pubkey();
+        self.inputs[input_index].musig2_participant_pubkeys.insert(aggregate, participants);
+        Ok(aggregate)
+    }
+
+    /// Adds fresh public nonces of the signer with the secret key `secret` for every MuSig2 input
+    /// it takes part in, replacing any previous ones.
+    ///
+    /// # Returns
+    ///
+    /// The secret nonces, by input index, to pass to [`Psbt::add_musig2_partial_sigs`]. They must
+    /// never be used twice.
+    pub fn add_musig2_nonces<R: RngCore + CryptoRng>(
+        &mut self,
+        secret: Scalar,
+        rng: &mut R,
+    ) -> Result<BTreeMap<usize, SecNonce>, Musig2SignError> {
+        let pubkey = secret.base_point_mul();
+
+        let mut sec_nonces = BTreeMap::new();
+        for i in self.musig2_inputs(Some(&pubkey))? {
+            let (aggregate, key_agg) = self.musig2_key_agg(i)?.expect("a MuSig2 input");
+            let mut rand = [0u8; 32];
+            rng.fill_bytes(&mut rand);
+            let sec_nonce = SecNonce::builder(rand, pubkey)
+                .with_secret_key(secret)
+                .with_aggregated_key(key_agg.x_only_public_key())
+                .build();
+            self.inputs[i]
+                .musig2_pub_nonces
+                .insert((pubkey, aggregate, None), sec_nonce.public_nonce());
+            sec_nonces.insert(i, sec_nonce);
+        }
+        Ok(sec_nonces)
+    }
+
+    /// Adds the partial signatures of the signer with the secret key `secret` for every input of
+    /// `sec_nonces`, consuming them.
+    ///
+    /// The public nonces of all participants of these inputs must be present. No partial
+    /// signature is added unless every input can be signed.
+    ///
+    /// # Returns
+    ///
+    /// The indices of the signed inputs.
+    pub fn add_musig2_partial_sigs(
+        &mut self,
+        secret: Scalar,
+        sec_nonces: BTreeMap<usize, SecNonce>,
+    ) -> Result<Vec<usize>, Musig2SignError> {
+        let pubkey = secret.base_point_mul();
+        let tx = self.unsigned_tx.clone();
+        let mut cache = SighashCache::new(&tx);
+
+        let mut partial_sigs = Vec::with_capacity(sec_nonces.len());
+        for (i, sec_nonce) in sec_nonces {
+            self.checked_input(i)?;
+            let (aggregate, key_agg) =
+                self.musig2_key_agg(i)?.ok_or(Musig2SignError::AggregateKeyMismatch(i))?;
+            let pub_nonces = self.musig2_pub_nonces(i, aggregate, key_agg.keys())?;
+            let (msg, _) = self.sighash_taproot(i, &mut cache, None)?;
+
+            let session = Session::new(&key_agg, &AggNonce::sum(pub_nonces), msg.as_bytes());
+            let partial_sig = session
+                .sign(sec_nonce, secret)
+                .map_err(|error| Musig2SignError::Musig2 { input: i, error })?;
+            partial_sigs.push((i, aggregate, partial_sig));
+        }
+
+        let mut signed = Vec::with_capacity(partial_sigs.len());
+        for (i, aggregate, partial_sig) in partial_sigs {
+            self.inputs[i].musig2_partial_sigs.insert((pubkey, aggregate, None), partial_sig);
+            signed.push(i);
+        }
+        Ok(signed)
+    }
+
+    /// Verifies the partial signatures of the MuSig2 inputs signed by all participants and adds
+    /// their aggregated key path signatures.
+    ///
+    /// No signature is added unless every partial signature is valid.
+    ///
+    /// # Returns
+    ///
+    /// The indices of the signed inputs.
+    pub fn aggregate_musig2_sigs(&mut self) -> Result<Vec<usize>, Musig2SignError> {
+        let tx = self.unsigned_tx.clone();
+        let mut cache = SighashCache::new(&tx);
+
+        let mut signatures = vec![];
+        for i in self.musig2_inputs(None)? {
+            let (aggregate, key_agg) = self.musig2_key_agg(i)?.expect("a MuSig2 input");
+            let input = &self.inputs[i];
+            let partial_sigs = key_agg
+                .keys()
+                .iter()
+                .map(|&key| input.musig2_partial_sigs.get(&(key, aggregate, None)))
+                .collect::<Option<Vec<_>>>();
+            let partial_sigs = match partial_sigs {
+                Some(partial_sigs) => partial_sigs,
+                None => continue,
+            };
+            let pub_nonces = self.musig2_pub_nonces(i, aggregate, key_agg.keys())?;
+            let (msg, sighash_type) = self.sighash_taproot(i, &mut cache, None)?;
+
+            let session =
+                Session::new(&key_agg, &AggNonce::sum(pub_nonces.clone()), msg.as_bytes());
+            for ((partial_sig, pub_nonce), key) in
+                partial_sigs.iter().zip(pub_nonces).zip(key_agg.keys())
+            {
+                session
+                    .verify_partial_signature(partial_sig, pub_nonce, key)
+                    .map_err(|error| Musig2SignError::Musig2 { input: i, error })?;
+            }
+            let signature = session.unchecked_aggregate(partial_sigs).try_into().map_err(|_| {
+                Musig2SignError::Musig2 { input: i, error: Musig2Error::InvalidPartialSignature }
+            })?;
+            signatures.push((i, taproot::Signature { signature, sighash_type }));
+        }
+
+        let mut signed = Vec::with_capacity(signatures.len());
+        for (i, signature) in signatures {
+            self.inputs[i].tap_key_sig = Some(signature);
+            signed.push(i);
+        }
+        Ok(signed)
+    }
+
+    /// Returns the indices of the MuSig2 inputs without a key path signature, only those `pubkey`
+    /// takes part in if given.
+    fn musig2_inputs(&self, pubkey: Option<&PublicKey>) -> Result<Vec<usize>, Musig2SignError> {
+        let mut inputs = vec![];
+        for (i, input) in self.inputs.iter().enumerate() {
+            if input.tap_key_sig.is_some() {
+                continue;
+            }
+            if let Some((_, key_agg)) = self.musig2_key_agg(i)? {
+                if pubkey.is_none_or(|pubkey| key_agg.keys().contains(pubkey)) {
+                    inputs.push(i);
+                }
+            }
+        }
+        Ok(inputs)
+    }
+
+    /// Returns the aggregate key of the input at `input_index` and its aggregation context,
+    /// tweaked for key path spends, if its internal key is a MuSig2 aggregate key.
+    fn musig2_key_agg(
+        &self,
+        input_index: usize,
+    ) -> Result<Option<(PublicKey, KeyAggContext)>, Musig2SignError> {
+        let input = &self.inputs[input_index];
+        let internal_key = match input.tap_internal_key {
+            Some(internal_key) => internal_key,
+            None => return Ok(None),
+        };
+        let (aggregate, participants) = match input
+            .musig2_participant_pubkeys
+            .iter()
+            .find(|(aggregate, _)| XOnlyPublicKey::from(**aggregate) == internal_key)
+        {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+
+        let map_err = |error| Musig2SignError::Musig2 { input: input_index, error };
+        let key_agg = KeyAggContext::new(participants.iter().copied()).map_err(map_err)?;
+        if key_agg.pubkey() != *aggregate {
+            return Err(Musig2SignError::AggregateKeyMismatch(input_index));
+        }
+        let key_agg = key_agg.with_taproot_tweak(input.tap_merkle_root).map_err(map_err)?;
+        Ok(Some((*aggregate, key_agg)))
+    }
+
+    /// Returns the key path public nonces of `participants` for the input at `input_index`, in
+    /// their order.
+    fn musig2_pub_nonces(
+        &self,
+        input_index: usize,
+        aggregate: PublicKey,
+        participants: &[PublicKey],
+    ) -> Result<Vec<&PubNonce>, Musig2SignError> {
+        let input = &self.inputs[input_index];
+        participants
+            .iter()
+            .map(|&key| input.musig2_pub_nonces.get(&(key, aggregate, None)))
+            .collect::<Option<Vec<_>>>()
+            .ok_or(Musig2SignError::MissingNonces(input_index))
+    }
+}
+
+/// Errors encountered while signing a PSBT with MuSig2.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Musig2SignError {
+    /// Unable to compute the sighash of an input.
+    Sign(SignError),
+    /// The input index is out of bounds.
+    IndexOutOfBounds(IndexOutOfBoundsError),
+    /// Aggregating the keys or signing an input failed.
+    Musig2 {
+        /// The index of the input.
+        input: usize,
+        /// The error.
+        error: Musig2Error,
+    },
+    /// The participant keys don't aggregate to the internal key of the input with this index.
+    AggregateKeyMismatch(usize),
+    /// The public nonce of a participant is missing for the input with this index.
+    MissingNonces(usize),
+}
+
+internals::impl_from_infallible!(Musig2SignError);
+
+impl fmt::Display for Musig2SignError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use Musig2SignError::*;
+
+        match *self {
+            Sign(ref e) => write_err!(f, "MuSig2 signing"; e),
+            IndexOutOfBounds(ref e) => write_err!(f, "index out of bounds"; e),
+            Musig2 { input, ref error } => write_err!(f, "MuSig2 signing input {}", input; error),
+            AggregateKeyMismatch(input) => write!(
+                f,
+                "the participant keys don't aggregate to the internal key of input {}",
+                input
+            ),
+            MissingNonces(input) => write!(f, "missing public nonces for input {}", input),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Musig2SignError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use Musig2SignError::*;
+
+        match *self {
+            Sign(ref e) => Some(e),
+            IndexOutOfBounds(ref e) => Some(e),
+            Musig2 { ref error, .. } => Some(error),
+            AggregateKeyMismatch(_) | MissingNonces(_) => None,
+        }
+    }
+}
+
+impl From<SignError> for Musig2SignError {
+    fn from(e: SignError) -> Self { Self::Sign(e) }
+}
+
+impl From<IndexOutOfBoundsError> for Musig2SignError {
+    fn from(e: IndexOutOfBoundsError) -> Self { Self::IndexOutOfBounds(e) }
+}
+
+#[cfg(test)]
+mod tests {
+    use hashes::Hash;
+    use k256::schnorr::signature::hazmat::PrehashVerifier as _;
+
+    use super::*;
+    use crate::blockdata::locktime::absolute;
+    use crate::blockdata::script::ScriptBuf;
+    use crate::blockdata::transaction::{self, Transaction, TxIn, TxOut};
+    use crate::crypto::musig2::PartialSignature;
+    use crate::taproot::TapNodeHash;
+    use crate::Amount;
+
+    fn round_trip(psbt: &Psbt) -> Psbt { Psbt::deserialize(&psbt.serialize()).unwrap() }
+
+    #[test]
+    fn musig2_sign_psbt() {
+        let mut rng = rand::thread_rng();
+        let secrets: Vec<Scalar> =
+            (1..=3u8).map(|i| Scalar::reduce_from(&[0x40 + i; 32])).collect();
+        let participants: Vec<PublicKey> = secrets.iter().map(Scalar::base_point_mul).collect();
+        let internal_key = KeyAggContext::new(participants.clone()).unwrap().x_only_public_key();
+        let merkle_root = TapNodeHash::from_byte_array([0x07; 32]);
+
+        let unsigned_tx = Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![TxIn::default(), TxIn::default(), TxIn::default()],
+            output: vec![TxOut::NULL],
+        };
+        let mut psbt = Psbt::from_unsigned_tx(unsigned_tx).unwrap();
+        for (i, root) in [(0, None), (1, Some(merkle_root))] {
+            psbt.inputs[i].witness_utxo = Some(TxOut {
+                value: Amount::from_sat(10_000),
+                script_pubkey: ScriptBuf::new_p2tr(internal_key, root),
+            });
+            psbt.inputs[i].tap_internal_key = Some(internal_key);
+            psbt.inputs[i].tap_merkle_root = root;
+        }
+        // An input spent by another key.
+        let other_key = XOnlyPublicKey::from(Scalar::one().base_point_mul());
+        psbt.inputs[2].witness_utxo = Some(TxOut {
+            value: Amount::from_sat(10_000),
+            script_pubkey: ScriptBuf::new_p2tr(other_key, None),
+        });
+        psbt.inputs[2].tap_internal_key = Some(other_key);
+
+        assert_eq!(
+            psbt.add_musig2_participants(2, participants.clone()),
+            Err(Musig2SignError::AggregateKeyMismatch(2))
+        );
+        for i in 0..2 {
+            psbt.add_musig2_participants(i, participants.clone()).unwrap();
+        }
+        let psbt = round_trip(&psbt);
+
+        // Every signer adds its nonces to its own copy of the PSBT.
+        let mut copies = vec![];
+        let mut all_sec_nonces = vec![];
+        for &secret in &secrets {
+            let mut copy = psbt.clone();
+            let sec_nonces = copy.add_musig2_nonces(secret, &mut rng).unwrap();
+            assert_eq!(sec_nonces.keys().copied().collect::<Vec<_>>(), [0, 1]);
+            copies.push(round_trip(&copy));
+            all_sec_nonces.push(sec_nonces);
+        }
+
+        // Signing is refused until the nonces of all participants are combined.
+        let mut psbt = copies[0].clone();
+        let mut early = psbt.clone();
+        let sec_nonces = early.add_musig2_nonces(secrets[0], &mut rng).unwrap();
+        assert_eq!(
+            early.add_musig2_partial_sigs(secrets[0], sec_nonces),
+            Err(Musig2SignError::MissingNonces(0))
+        );
+        assert!(early.inputs[0].musig2_partial_sigs.is_empty());
+
+        for copy in &copies[1..] {
+            psbt.combine(copy.clone()).unwrap();
+        }
+        let psbt = round_trip(&psbt);
+
+        let mut signed = psbt.clone();
+        for (&secret, sec_nonces) in secrets.iter().zip(all_sec_nonces) {
+            let mut copy = psbt.clone();
+            assert_eq!(copy.add_musig2_partial_sigs(secret, sec_nonces), Ok(vec![0, 1]));
+            signed.combine(round_trip(&copy)).unwrap();
+        }
+
+        let mut forged = signed.clone();
+        let key = (
+            participants[1],
+            *forged.inputs[1].musig2_participant_pubkeys.keys().next().unwrap(),
+            None,
+        );
+        let partial_sig = forged.inputs[1].musig2_partial_sigs[&key];
+        forged.inputs[1]
+            .musig2_partial_sigs
+            .insert(key, PartialSignature(partial_sig.0 + Scalar::one()));
+        assert_eq!(
+            forged.aggregate_musig2_sigs(),
+            Err(Musig2SignError::Musig2 { input: 1, error: Musig2Error::InvalidPartialSignature })
+        );
+        assert!(forged.inputs[0].tap_key_sig.is_none());
+
+        let mut psbt = round_trip(&signed);
+        assert_eq!(psbt.aggregate_musig2_sigs(), Ok(vec![0, 1]));
+        for i in 0..2 {
+            let (msg, _) =
+                psbt.sighash_taproot(i, &mut SighashCache::new(&psbt.unsigned_tx), None).unwrap();
+            let spk = &psbt.inputs[i].witness_utxo.as_ref().unwrap().script_pubkey;
+            let output_key = k256::schnorr::VerifyingKey::from_bytes(&spk.as_bytes()[2..]).unwrap();
+            let signature = psbt.inputs[i].tap_key_sig.unwrap().signature;
+            assert!(output_key.verify_prehash(msg.as_bytes(), &signature).is_ok());
+        }
+        assert_eq!(psbt.aggregate_musig2_sigs(), Ok(vec![]));
+    }
+}
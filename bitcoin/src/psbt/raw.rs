@@ -32,7 +32,7 @@ pub struct Key {
 
 /// A PSBT key-value pair in its raw byte form.
 /// `<keypair> := <key> <value>`
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "serde", serde(crate = "actual_serde"))]
 pub struct Pair {
@@ -176,8 +176,22 @@ impl<Subtype> ProprietaryKey<Subtype>
 where
     Subtype: Copy + From<u8> + Into<u8>,
 {
+    /// Creates a proprietary key with an application specific `prefix`.
+    pub fn new(prefix: Vec<u8>, subtype: Subtype, key: Vec<u8>) -> Self {
+        ProprietaryKey { prefix, subtype, key }
+    }
+
     /// Constructs full [Key] corresponding to this proprietary key type
     pub fn to_key(&self) -> Key { Key { type_value: 0xFC, key: serialize(self) } }
+
+    /// Converts this key to the default subtype, the raw byte used in PSBT maps.
+    pub fn to_raw(&self) -> ProprietaryKey {
+        ProprietaryKey {
+            prefix: self.prefix.clone(),
+            subtype: self.subtype.into(),
+            key: self.key.clone(),
+        }
+    }
 }
 
 impl<Subtype> TryFrom<Key> for ProprietaryKey<Subtype>
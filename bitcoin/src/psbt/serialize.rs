@@ -7,6 +7,7 @@
 //!
 
 use hashes::{hash160, ripemd160, sha256, sha256d, Hash};
+use hex::FromHex;
 // use secp256k1::XOnlyPublicKey;
 
 use super::map::{Input, Map, Output, PsbtSighashType};
@@ -15,12 +16,11 @@ use crate::blockdata::script::ScriptBuf;
 use crate::blockdata::transaction::{Transaction, TxOut};
 use crate::blockdata::witness::Witness;
 use crate::consensus::encode::{self, deserialize_partial, serialize, Decodable, Encodable};
-use crate::crypto::key::PublicKey;
+use crate::crypto::key::{FromSliceError, PublicKey};
+use crate::crypto::musig2::{PartialSignature, PubNonce};
 use crate::crypto::{ecdsa, taproot};
-use crate::psbt::{Error, Psbt};
-use crate::taproot::{
-    ControlBlock, LeafVersion, TapLeafHash, TapNodeHash, TapTree, TaprootBuilder,
-};
+use crate::psbt::{Error, Psbt, PsbtParseError};
+use crate::taproot::{ControlBlock, LeafVersion, TapLeafHash, TapNodeHash, TapTree};
 use crate::VarInt;
 use crate::{prelude::*, XOnlyPublicKey};
 /// A trait for serializing a value as raw data for insertion into PSBT
@@ -42,6 +42,12 @@ impl Psbt {
         self.serialize().to_lower_hex_string()
     }
 
+    /// Deserialize a value from bytes in hex, as produced by [`Psbt::serialize_hex`].
+    pub fn from_hex(s: &str) -> Result<Self, PsbtParseError> {
+        let bytes = Vec::from_hex(s).map_err(PsbtParseError::Hex)?;
+        Psbt::deserialize(&bytes).map_err(PsbtParseError::PsbtEncoding)
+    }
+
     /// Serialize as raw binary data
     pub fn serialize(&self) -> Vec<u8> {
         let mut buf: Vec<u8> = Vec::new();
@@ -262,7 +268,9 @@ impl Serialize for XOnlyPublicKey {
 
 impl Deserialize for XOnlyPublicKey {
     fn deserialize(bytes: &[u8]) -> Result<Self, Error> {
-        XOnlyPublicKey::from_slice(bytes).map_err(|_| Error::InvalidXOnlyPublicKey)
+        // BIP 371 x-only keys are exactly 32 bytes, unlike `from_slice` which also takes 33.
+        let bytes = <&[u8; 32]>::try_from(bytes).map_err(|_| Error::InvalidXOnlyPublicKey)?;
+        XOnlyPublicKey::from_byte_array(bytes).map_err(|_| Error::InvalidXOnlyPublicKey)
     }
 }
 
@@ -305,6 +313,83 @@ impl Deserialize for (XOnlyPublicKey, TapLeafHash) {
     }
 }
 
+// MuSig2 related ser/deser
+impl Serialize for Vec<PublicKey> {
+    fn serialize(&self) -> Vec<u8> {
+        self.iter().flat_map(PublicKey::serialize).collect()
+    }
+}
+
+impl Deserialize for Vec<PublicKey> {
+    fn deserialize(bytes: &[u8]) -> Result<Self, Error> {
+        if !bytes.len().is_multiple_of(33) {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        bytes.chunks(33).map(compressed_key).collect()
+    }
+}
+
+impl Serialize for (PublicKey, PublicKey, Option<TapLeafHash>) {
+    fn serialize(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(98);
+        buf.extend(&self.0.serialize());
+        buf.extend(&self.1.serialize());
+        if let Some(leaf_hash) = self.2 {
+            buf.extend(leaf_hash.as_byte_array());
+        }
+        buf
+    }
+}
+
+impl Deserialize for (PublicKey, PublicKey, Option<TapLeafHash>) {
+    fn deserialize(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() < 66 {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        let a = compressed_key(&bytes[..33])?;
+        let b = compressed_key(&bytes[33..66])?;
+        let c = match &bytes[66..] {
+            [] => None,
+            leaf_hash => Some(Deserialize::deserialize(leaf_hash)?),
+        };
+        Ok((a, b, c))
+    }
+}
+
+impl Serialize for PubNonce {
+    fn serialize(&self) -> Vec<u8> {
+        PubNonce::serialize(self).to_vec()
+    }
+}
+
+impl Deserialize for PubNonce {
+    fn deserialize(bytes: &[u8]) -> Result<Self, Error> {
+        PubNonce::from_slice(bytes).map_err(Error::InvalidMusig2)
+    }
+}
+
+impl Serialize for PartialSignature {
+    fn serialize(&self) -> Vec<u8> {
+        PartialSignature::serialize(self).to_vec()
+    }
+}
+
+impl Deserialize for PartialSignature {
+    fn deserialize(bytes: &[u8]) -> Result<Self, Error> {
+        PartialSignature::from_slice(bytes).map_err(Error::InvalidMusig2)
+    }
+}
+
+/// Parses a compressed public key, the only encoding of the MuSig2 fields.
+fn compressed_key(bytes: &[u8]) -> Result<PublicKey, Error> {
+    if bytes.len() != 33 {
+        return Err(Error::InvalidPublicKey(FromSliceError::InvalidLength(
+            bytes.len(),
+        )));
+    }
+    PublicKey::from_slice(bytes).map_err(Error::InvalidPublicKey)
+}
+
 impl Serialize for ControlBlock {
     fn serialize(&self) -> Vec<u8> {
         ControlBlock::serialize(self)
@@ -364,18 +449,15 @@ impl Serialize for TapTree {
         let capacity = self
             .script_leaves()
             .map(|l| {
-                l.script().len() + VarInt::from(l.script().len()).size() // script version
-            + 1 // merkle branch
-            + 1 // leaf version
+                1 // depth
+                + 1 // leaf version
+                + VarInt::from(l.script().len()).size()
+                + l.script().len()
             })
             .sum::<usize>();
         let mut buf = Vec::with_capacity(capacity);
         for leaf_info in self.script_leaves() {
-            // # Cast Safety:
-            //
-            // TaprootMerkleBranch can only have len atmost 128(TAPROOT_CONTROL_MAX_NODE_COUNT).
-            // safe to cast from usize to u8
-            buf.push(leaf_info.merkle_branch().len() as u8);
+            buf.push(leaf_info.depth());
             buf.push(leaf_info.version().to_consensus());
             leaf_info
                 .script()
@@ -387,24 +469,19 @@ impl Serialize for TapTree {
 }
 
 impl Deserialize for TapTree {
-    fn deserialize(bytes: &[u8]) -> Result<Self, Error> {
-        let mut builder = TaprootBuilder::new();
-        let mut bytes_iter = bytes.iter();
-        while let Some(depth) = bytes_iter.next() {
-            let version = bytes_iter
-                .next()
-                .ok_or(Error::Taproot("Invalid Taproot Builder"))?;
-            let (script, consumed) = deserialize_partial::<ScriptBuf>(bytes_iter.as_slice())?;
-            if consumed > 0 {
-                bytes_iter.nth(consumed - 1);
-            }
-            let leaf_version =
-                LeafVersion::from_consensus(*version).map_err(|_| Error::InvalidLeafVersion)?;
-            builder = builder
-                .add_leaf_with_ver(*depth, script, leaf_version)
-                .map_err(|_| Error::Taproot("Tree not in DFS order"))?;
+    fn deserialize(mut bytes: &[u8]) -> Result<Self, Error> {
+        let mut leaves = Vec::new();
+        while let Some((&depth, rest)) = bytes.split_first() {
+            let (&version, rest) = rest
+                .split_first()
+                .ok_or(Error::Taproot("Missing tap leaf version"))?;
+            let version =
+                LeafVersion::from_consensus(version).map_err(|_| Error::InvalidLeafVersion)?;
+            let (script, consumed) = deserialize_partial::<ScriptBuf>(rest)?;
+            bytes = &rest[consumed..];
+            leaves.push((depth, version, script));
         }
-        TapTree::try_from(builder).map_err(Error::TapTree)
+        TapTree::from_leaves(leaves).map_err(Error::TapTree)
     }
 }
 
@@ -416,6 +493,7 @@ fn key_source_len(key_source: &KeySource) -> usize {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::taproot::{TapTreeError, TaprootBuilder, TaprootBuilderError};
 
     // Composes tree matching a given depth map, filled with dumb script leafs,
     // each of which consists of a single push-int op code, with int value
@@ -466,6 +544,44 @@ mod tests {
         assert_eq!(tree, tree_prime);
     }
 
+    #[test]
+    fn taptree_deserialize_strict() {
+        let bytes = TapTree::try_from(compose_taproot_builder(0x51, &[1, 2, 2]))
+            .unwrap()
+            .serialize();
+        // Leaves are ordered by hash, the depth 1 leaf comes last.
+        assert_eq!(&bytes[..3], &[2, 0xc0, 1]);
+
+        // Dropping the last leaf leaves the tree incomplete.
+        assert!(matches!(
+            TapTree::deserialize(&bytes[..8]),
+            Err(Error::TapTree(TapTreeError::IncompleteTree))
+        ));
+        assert!(matches!(
+            TapTree::deserialize(&[]),
+            Err(Error::TapTree(TapTreeError::IncompleteTree))
+        ));
+        // A truncated leaf.
+        assert!(TapTree::deserialize(&bytes[..bytes.len() - 1]).is_err());
+        assert!(TapTree::deserialize(&bytes[..bytes.len() - 3]).is_err());
+        // A second root.
+        let mut over_complete = bytes.clone();
+        over_complete.extend_from_slice(&[0, 0xc0, 1, 0x51]);
+        assert!(matches!(
+            TapTree::deserialize(&over_complete),
+            Err(Error::TapTree(TapTreeError::InvalidLeaf(_)))
+        ));
+        // Leaves deeper than 128.
+        let mut too_deep = bytes.clone();
+        too_deep[0] = 129;
+        assert!(matches!(
+            TapTree::deserialize(&too_deep),
+            Err(Error::TapTree(TapTreeError::InvalidLeaf(
+                TaprootBuilderError::InvalidMerkleTreeDepth(129)
+            )))
+        ));
+    }
+
     #[test]
     fn can_deserialize_non_standard_psbt_sighash_type() {
         let non_standard_sighash = [222u8, 0u8, 0u8, 0u8]; // 32 byte value.
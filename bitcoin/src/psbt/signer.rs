@@ -0,0 +1,422 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Signing PSBTs with a [`Signer`].
+//!
+//! [`Psbt::sign_with_signer`] signs every input spending the key of a [`Signer`], so the same code
+//! signs with a [`SoftwareSigner`](crate::signer::SoftwareSigner), a hardware device or a
+//! remote key management service. Unlike [`ExternalSigner`](super::ExternalSigner), the signer is
+//! matched by its public key rather than asked for the key at a derivation path, and it signs
+//! ECDSA inputs as well as taproot key path and script path spends.
+//!
+//! With the `async` feature, [`Psbt::sign_with_async_signer`] does the same with an
+//! [`AsyncSigner`].
+//!
+
+use core::fmt;
+
+use internals::write_err;
+use k256::ecdsa::signature::hazmat::PrehashVerifier as _;
+use k256::schnorr::Signature as SchnorrSignature;
+
+use super::{Psbt, SignError, SigningAlgorithm};
+use crate::blockdata::transaction::Transaction;
+use crate::common::types::Message;
+use crate::crypto::key::{PublicKey, TapTweak, XOnlyPublicKey};
+use crate::crypto::schnorr::{self, Signature64};
+use crate::crypto::sighash::{EcdsaSighashType, SighashCache, TapSighashType};
+#[cfg(feature = "async")]
+use crate::crypto::signer::AsyncSigner;
+use crate::crypto::signer::{Signer, TapSpend};
+use crate::crypto::{ecdsa, taproot};
+use crate::prelude::*;
+use crate::TapLeafHash;
+
+/// A signature of an input to request from a [`Signer`].
+pub(super) enum SignatureRequest {
+    Ecdsa {
+        msg: Message,
+        sighash_type: EcdsaSighashType,
+    },
+    Schnorr {
+        msg: Message,
+        sighash_type: TapSighashType,
+        spend: TapSpend,
+        leaf_hash: Option<TapLeafHash>,
+    },
+}
+
+impl Psbt {
+    /// Signs every input spending the key of `signer`, adding the signatures to `partial_sigs`,
+    /// `tap_key_sig` or `tap_script_sigs`.
+    ///
+    /// ECDSA inputs are signed if the public key of the signer is in their `bip32_derivation`.
+    /// Taproot inputs are signed if its x-only key is in their `tap_key_origins`, for a key path
+    /// spend if it is the internal key and for every leaf hash of its origin otherwise. Inputs
+    /// already signed by the key are skipped. Every signature returned by the signer is verified
+    /// before it's added to the input.
+    ///
+    /// # Returns
+    ///
+    /// The indices of the signed inputs. Signing stops at the first error, the signatures of the
+    /// inputs signed before it are kept in the PSBT.
+    pub fn sign_with_signer<S: Signer>(
+        &mut self,
+        signer: &S,
+    ) -> Result<Vec<usize>, SignerError<S::Error>> {
+        let pubkey = signer.pubkey().map_err(SignerError::Signer)?;
+        let tx = self.unsigned_tx.clone(); // clone because we need to mutably borrow when signing.
+        let mut cache = SighashCache::new(&tx);
+
+        let mut signed = vec![];
+        for input_index in 0..self.inputs.len() {
+            let requests = self
+                .signature_requests(input_index, &pubkey, &mut cache)
+                .map_err(|error| SignerError::Sign { input_index, error })?;
+            if requests.is_empty() {
+                continue;
+            }
+            for request in requests {
+                match &request {
+                    SignatureRequest::Ecdsa { msg, .. } => {
+                        let signature = signer.sign_ecdsa(msg).map_err(SignerError::Signer)?;
+                        self.add_ecdsa_signature(input_index, &pubkey, request, signature)?;
+                    }
+                    SignatureRequest::Schnorr { msg, spend, .. } => {
+                        let signature =
+                            signer.sign_schnorr(msg, *spend).map_err(SignerError::Signer)?;
+                        self.add_schnorr_signature(input_index, &pubkey, request, signature)?;
+                    }
+                }
+            }
+            signed.push(input_index);
+        }
+        Ok(signed)
+    }
+
+    /// Signs every input spending the key of the asynchronous `signer`, see
+    /// [`Psbt::sign_with_signer`].
+    ///
+    /// The signatures are requested one at a time, in the order of the inputs.
+    #[cfg(feature = "async")]
+    pub async fn sign_with_async_signer<S: AsyncSigner>(
+        &mut self,
+        signer: &S,
+    ) -> Result<Vec<usize>, SignerError<S::Error>> {
+        let pubkey = signer.pubkey().await.map_err(SignerError::Signer)?;
+        let tx = self.unsigned_tx.clone(); // clone because we need to mutably borrow when signing.
+        let mut cache = SighashCache::new(&tx);
+
+        let mut signed = vec![];
+        for input_index in 0..self.inputs.len() {
+            let requests = self
+                .signature_requests(input_index, &pubkey, &mut cache)
+                .map_err(|error| SignerError::Sign { input_index, error })?;
+            if requests.is_empty() {
+                continue;
+            }
+            for request in requests {
+                match &request {
+                    SignatureRequest::Ecdsa { msg, .. } => {
+                        let signature =
+                            signer.sign_ecdsa(msg).await.map_err(SignerError::Signer)?;
+                        self.add_ecdsa_signature(input_index, &pubkey, request, signature)?;
+                    }
+                    SignatureRequest::Schnorr { msg, spend, .. } => {
+                        let signature =
+                            signer.sign_schnorr(msg, *spend).await.map_err(SignerError::Signer)?;
+                        self.add_schnorr_signature(input_index, &pubkey, request, signature)?;
+                    }
+                }
+            }
+            signed.push(input_index);
+        }
+        Ok(signed)
+    }
+
+    /// Returns the signatures to request for the input at `input_index` from the signer of
+    /// `pubkey`.
+    pub(super) fn signature_requests<T: Borrow<Transaction>>(
+        &self,
+        input_index: usize,
+        pubkey: &PublicKey,
+        cache: &mut SighashCache<T>,
+    ) -> Result<Vec<SignatureRequest>, SignError> {
+        let input = self.checked_input(input_index)?;
+        match self.signing_algorithm(input_index)? {
+            SigningAlgorithm::Ecdsa => {
+                if !input.bip32_derivation.contains_key(pubkey)
+                    || input.partial_sigs.contains_key(pubkey)
+                {
+                    return Ok(vec![]);
+                }
+                let (msg, sighash_type) = self.sighash_ecdsa(input_index, cache)?;
+                Ok(vec![SignatureRequest::Ecdsa { msg, sighash_type }])
+            }
+            SigningAlgorithm::Schnorr => {
+                let (xonly, _) = pubkey.x_only_public_key();
+                let leaf_hashes = match input.tap_key_origins.get(&xonly) {
+                    Some((leaf_hashes, _)) => leaf_hashes,
+                    None => return Ok(vec![]),
+                };
+
+                let mut requests = vec![];
+                if input.tap_internal_key == Some(xonly)
+                    && leaf_hashes.is_empty()
+                    && input.tap_key_sig.is_none()
+                {
+                    let (msg, sighash_type) = self.sighash_taproot(input_index, cache, None)?;
+                    let spend = TapSpend::KeyPath { merkle_root: input.tap_merkle_root };
+                    requests.push(SignatureRequest::Schnorr {
+                        msg,
+                        sighash_type,
+                        spend,
+                        leaf_hash: None,
+                    });
+                }
+                for &leaf_hash in leaf_hashes {
+                    if input.tap_script_sigs.contains_key(&(xonly, leaf_hash)) {
+                        continue;
+                    }
+                    let (msg, sighash_type) =
+                        self.sighash_taproot(input_index, cache, Some(leaf_hash))?;
+                    requests.push(SignatureRequest::Schnorr {
+                        msg,
+                        sighash_type,
+                        spend: TapSpend::ScriptPath,
+                        leaf_hash: Some(leaf_hash),
+                    });
+                }
+                Ok(requests)
+            }
+        }
+    }
+
+    /// Verifies and adds the ECDSA `signature` requested by `request` to the input.
+    pub(super) fn add_ecdsa_signature<E>(
+        &mut self,
+        input_index: usize,
+        pubkey: &PublicKey,
+        request: SignatureRequest,
+        signature: k256::ecdsa::Signature,
+    ) -> Result<(), SignerError<E>> {
+        let (msg, sighash_type) = match request {
+            SignatureRequest::Ecdsa { msg, sighash_type } => (msg, sighash_type),
+            SignatureRequest::Schnorr { .. } => unreachable!("ECDSA signature for a taproot input"),
+        };
+        k256::ecdsa::VerifyingKey::from(pubkey.inner)
+            .verify_prehash(msg.as_ref(), &signature)
+            .map_err(|_| SignerError::InvalidSignature { input_index })?;
+        let signature = ecdsa::Signature { signature, sighash_type };
+        self.inputs[input_index].partial_sigs.insert(*pubkey, signature);
+        Ok(())
+    }
+
+    /// Verifies and adds the BIP340 `signature` requested by `request` to the input.
+    pub(super) fn add_schnorr_signature<E>(
+        &mut self,
+        input_index: usize,
+        pubkey: &PublicKey,
+        request: SignatureRequest,
+        signature: SchnorrSignature,
+    ) -> Result<(), SignerError<E>> {
+        let (msg, sighash_type, spend, leaf_hash) = match request {
+            SignatureRequest::Schnorr { msg, sighash_type, spend, leaf_hash } =>
+                (msg, sighash_type, spend, leaf_hash),
+            SignatureRequest::Ecdsa { .. } => unreachable!("BIP340 signature for an ECDSA input"),
+        };
+        let (xonly, _) = pubkey.x_only_public_key();
+        let key: XOnlyPublicKey = match spend {
+            TapSpend::KeyPath { merkle_root } => xonly.tap_tweak(merkle_root).0.to_inner(),
+            TapSpend::ScriptPath => xonly,
+        };
+        schnorr::verify_schnorr(&Signature64::from_byte_array(signature.to_bytes()), &msg, &key)
+            .map_err(|_| SignerError::InvalidSignature { input_index })?;
+
+        let signature = taproot::Signature { signature, sighash_type };
+        let input = &mut self.inputs[input_index];
+        match leaf_hash {
+            Some(leaf_hash) => {
+                input.tap_script_sigs.insert((xonly, leaf_hash), signature);
+            }
+            None => input.tap_key_sig = Some(signature),
+        }
+        Ok(())
+    }
+}
+
+/// Errors encountered while signing a PSBT with a [`Signer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SignerError<E> {
+    /// Unable to compute the sighash of an input.
+    Sign {
+        /// The index of the input.
+        input_index: usize,
+        /// The sighash error.
+        error: SignError,
+    },
+    /// The signer failed.
+    Signer(E),
+    /// The signer returned a signature which is not valid for the input.
+    InvalidSignature {
+        /// The index of the input.
+        input_index: usize,
+    },
+}
+
+internals::impl_from_infallible!(SignerError<E>);
+
+impl<E: fmt::Display> fmt::Display for SignerError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use SignerError::*;
+
+        match *self {
+            Sign { input_index, ref error } =>
+                write_err!(f, "unable to sign input {}", input_index; error),
+            Signer(ref e) => write!(f, "signer failed: {}", e),
+            InvalidSignature { input_index } =>
+                write!(f, "signer returned an invalid signature for input {}", input_index),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: std::error::Error + 'static> std::error::Error for SignerError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use SignerError::*;
+
+        match *self {
+            Sign { ref error, .. } => Some(error),
+            Signer(ref e) => Some(e),
+            InvalidSignature { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::convert::Infallible;
+
+    use super::*;
+    use crate::bip32::{DerivationPath, Fingerprint, KeySource};
+    use crate::blockdata::locktime::absolute;
+    use crate::blockdata::script::ScriptBuf;
+    use crate::blockdata::transaction::{self, TxIn, TxOut};
+    use crate::crypto::key::PrivateKey;
+    use crate::crypto::signer::SoftwareSigner;
+    use crate::taproot::LeafVersion;
+    use crate::{Amount, NetworkKind};
+
+    /// Signs with the key of the inner signer, but ECDSA signatures with a different key.
+    struct WrongEcdsaKey(SoftwareSigner, SoftwareSigner);
+
+    impl Signer for WrongEcdsaKey {
+        type Error = Infallible;
+
+        fn pubkey(&self) -> Result<PublicKey, Infallible> { self.0.pubkey() }
+
+        fn sign_ecdsa(&self, msg: &Message) -> Result<k256::ecdsa::Signature, Infallible> {
+            self.1.sign_ecdsa(msg)
+        }
+
+        fn sign_schnorr(
+            &self,
+            msg: &Message,
+            spend: TapSpend,
+        ) -> Result<SchnorrSignature, Infallible> {
+            self.0.sign_schnorr(msg, spend)
+        }
+    }
+
+    fn software_signer(byte: u8) -> SoftwareSigner {
+        SoftwareSigner::new(PrivateKey::from_slice(&[byte; 32], NetworkKind::Test).unwrap())
+    }
+
+    /// Returns a PSBT spending a p2wpkh, a p2tr key path and a p2tr script path output of the
+    /// key of `signer`, and an output of another key.
+    fn psbt(signer: &SoftwareSigner) -> Psbt {
+        let pubkey = signer.pubkey().unwrap();
+        let (xonly, _) = pubkey.x_only_public_key();
+        let origin: KeySource = (Fingerprint::default(), DerivationPath::default());
+        let leaf_hash = TapLeafHash::from_script(&ScriptBuf::new(), LeafVersion::TapScript);
+
+        let unsigned_tx = Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![TxIn::default(); 4],
+            output: vec![TxOut::NULL],
+        };
+        let mut psbt = Psbt::from_unsigned_tx(unsigned_tx).unwrap();
+        let utxo = |script_pubkey| Some(TxOut { value: Amount::from_sat(10_000), script_pubkey });
+
+        psbt.inputs[0].witness_utxo = utxo(ScriptBuf::new_p2wpkh(&pubkey.wpubkey_hash().unwrap()));
+        psbt.inputs[0].bip32_derivation.insert(pubkey, origin.clone());
+
+        psbt.inputs[1].witness_utxo = utxo(ScriptBuf::new_p2tr(xonly, None));
+        psbt.inputs[1].tap_internal_key = Some(xonly);
+        psbt.inputs[1].tap_key_origins.insert(xonly, (vec![], origin.clone()));
+
+        let (other, _) = software_signer(0x2b).pubkey().unwrap().x_only_public_key();
+        psbt.inputs[2].witness_utxo = utxo(ScriptBuf::new_p2tr(other, None));
+        psbt.inputs[2].tap_internal_key = Some(other);
+        psbt.inputs[2].tap_key_origins.insert(xonly, (vec![leaf_hash], origin));
+
+        psbt.inputs[3].witness_utxo = utxo(ScriptBuf::new_p2tr(other, None));
+        psbt
+    }
+
+    #[test]
+    fn sign_with_software_signer() {
+        let signer = software_signer(0x2a);
+        let mut psbt = psbt(&signer);
+        assert_eq!(psbt.sign_with_signer(&signer), Ok(vec![0, 1, 2]));
+
+        let pubkey = signer.pubkey().unwrap();
+        assert!(psbt.inputs[0].partial_sigs.contains_key(&pubkey));
+        assert!(psbt.inputs[1].tap_key_sig.is_some());
+        assert_eq!(psbt.inputs[2].tap_script_sigs.len(), 1);
+        assert!(psbt.inputs[2].tap_key_sig.is_none());
+        assert!(psbt.inputs[3].tap_key_sig.is_none());
+
+        // Signed inputs are not signed again.
+        assert_eq!(psbt.sign_with_signer(&signer), Ok(vec![]));
+        assert_eq!(psbt.sign_with_signer(&software_signer(0x2b)), Ok(vec![]));
+    }
+
+    #[test]
+    #[cfg(feature = "async")]
+    fn sign_with_async_signer() {
+        use core::future::Future;
+        use core::task::{Context, Poll, Waker};
+
+        use crate::crypto::signer::Blocking;
+
+        let signer = Blocking(software_signer(0x2a));
+        let mut psbt = psbt(&signer.0);
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut future = Box::pin(psbt.sign_with_async_signer(&signer));
+        assert_eq!(future.as_mut().poll(&mut cx), Poll::Ready(Ok(vec![0, 1, 2])));
+        drop(future);
+
+        let mut expected = self::psbt(&signer.0);
+        expected.sign_with_signer(&signer.0).unwrap();
+        assert_eq!(psbt, expected);
+    }
+
+    #[test]
+    fn sign_with_invalid_signature() {
+        let signer = WrongEcdsaKey(software_signer(0x2a), software_signer(0x2b));
+        let mut psbt = psbt(&signer.0);
+        assert_eq!(
+            psbt.sign_with_signer(&signer),
+            Err(SignerError::InvalidSignature { input_index: 0 })
+        );
+        assert!(psbt.inputs[0].partial_sigs.is_empty());
+
+        psbt.inputs[0].witness_utxo = None;
+        assert!(matches!(
+            psbt.sign_with_signer(&signer),
+            Err(SignerError::Sign { input_index: 0, .. })
+        ));
+    }
+}
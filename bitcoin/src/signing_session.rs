@@ -0,0 +1,863 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Resumable multi-signature signing sessions.
+//!
+//! A [`SigningSession`] holds the state of a coordinator running a MuSig2 or FROST signing
+//! session: the signers, the message, the public nonces collected in the first round and the
+//! partial signatures collected in the second. The session serializes to a versioned container
+//! authenticated with an HMAC-SHA256, so a coordinator can persist it after every message and
+//! resume after a restart.
+//!
+//! The format protects the signers against nonce reuse:
+//!
+//! - Secret nonces are never part of it, they stay with the signers.
+//! - The session records its [`Phase`]. Once [`SigningSession::start_signing`] has been called
+//!   the nonces and the message are frozen, and a resumed session only ever asks the signers to
+//!   sign the exact same request again. A signer whose nonce is lost can't take part anymore,
+//!   the session must then be [restarted](SigningSession::restart) under a new id.
+//! - The MAC detects any change to the stored nonces or message, which could otherwise trick
+//!   the signers into signing two different requests with the same nonces.
+//! - Every change to the session increments its [revision](SigningSession::revision), which the
+//!   MAC covers. The MAC alone can't tell an old serialization from the latest one: restoring a
+//!   session from before [`SigningSession::start_signing`] could freeze a different set of
+//!   nonces, and make the signers sign a second request with them. Coordinators must keep the
+//!   latest revision in storage that can't be rolled back along with the session, and resume
+//!   with [`SigningSession::deserialize_fresh`].
+//!
+//! The serialization format is:
+//!
+//! ```text
+//! magic "MSES" (4 bytes) | version (1 byte) | body | HMAC-SHA256 (32 bytes)
+//! ```
+//!
+//! where the MAC covers everything before it.
+//!
+
+use core::fmt;
+
+use hashes::{sha256, Hash, HashEngine, Hmac, HmacEngine};
+use internals::write_err;
+use io::{BufRead, Write};
+use rand::{CryptoRng, RngCore};
+use subtle::ConstantTimeEq;
+
+use crate::consensus::encode::{self, Decodable, Encodable, VarInt};
+use crate::crypto::frost::{
+    self, FrostError, NonceCommitments, PublicKeyPackage, SignatureShare, SigningPackage,
+};
+use crate::crypto::key::PublicKey;
+use crate::crypto::musig2::{
+    AggNonce, KeyAggContext, Musig2Error, PartialSignature, PubNonce, Session,
+};
+use crate::crypto::scalar::{MaybeScalar, Scalar};
+use crate::crypto::schnorr::Signature64;
+use crate::prelude::*;
+
+/// The magic bytes at the start of every serialized session.
+pub const SESSION_MAGIC: [u8; 4] = *b"MSES";
+
+/// The version of the session format written by this library.
+pub const CURRENT_VERSION: u8 = 1;
+
+/// The key used to derive the MAC key from the storage key.
+const MAC_KEY_TAG: &[u8] = b"Bitcoin signing session";
+
+/// The length of the MAC at the end of a serialized session.
+const MAC_LEN: usize = 32;
+
+/// The round a [`SigningSession`] is in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// The coordinator collects the public nonces of the signers.
+    Nonces,
+    /// The nonces are frozen and the coordinator collects the partial signatures.
+    Signatures,
+}
+
+/// What the signers need, besides their keys and the message, to produce their partial
+/// signature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SigningRequest {
+    /// The aggregate of the MuSig2 public nonces.
+    Musig2(AggNonce),
+    /// The FROST signing package.
+    Frost(SigningPackage),
+}
+
+/// The signers of a session and their public nonces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Signers {
+    /// The MuSig2 keys in aggregation order, and the nonces by position in the keys.
+    Musig2 { keys: Vec<PublicKey>, nonces: BTreeMap<u32, PubNonce> },
+    /// The FROST key and the nonce commitments by signer index.
+    Frost { public: PublicKeyPackage, commitments: BTreeMap<u32, NonceCommitments> },
+}
+
+/// The state of a coordinator signing a message with a MuSig2 or FROST key.
+///
+/// MuSig2 signers are identified by their position in the list of keys, starting at zero. FROST
+/// signers are identified by the index of their key share.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SigningSession {
+    id: [u8; 32],
+    message: [u8; 32],
+    tweak: Option<Scalar>,
+    phase: Phase,
+    /// The number of changes made to the session, across restarts.
+    revision: u64,
+    signers: Signers,
+    partial_signatures: BTreeMap<u32, MaybeScalar>,
+}
+
+impl SigningSession {
+    /// Opens a session signing `message` with the aggregate of the MuSig2 `keys`.
+    ///
+    /// The `tweak` is added to the aggregate key made even, as for a taproot output key.
+    pub fn musig2<R: RngCore + CryptoRng>(
+        keys: Vec<PublicKey>,
+        message: [u8; 32],
+        tweak: Option<Scalar>,
+        rng: &mut R,
+    ) -> Result<Self, SessionError> {
+        key_agg(&keys, tweak)?;
+        Ok(Self::new(Signers::Musig2 { keys, nonces: BTreeMap::new() }, message, tweak, rng))
+    }
+
+    /// Opens a session signing `message` with the FROST key `public`.
+    ///
+    /// The `tweak` is added to the group key made even, as for a taproot output key.
+    pub fn frost<R: RngCore + CryptoRng>(
+        public: PublicKeyPackage,
+        message: [u8; 32],
+        tweak: Option<Scalar>,
+        rng: &mut R,
+    ) -> Self {
+        Self::new(Signers::Frost { public, commitments: BTreeMap::new() }, message, tweak, rng)
+    }
+
+    fn new<R: RngCore + CryptoRng>(
+        signers: Signers,
+        message: [u8; 32],
+        tweak: Option<Scalar>,
+        rng: &mut R,
+    ) -> Self {
+        let mut id = [0u8; 32];
+        rng.fill_bytes(&mut id);
+        SigningSession {
+            id,
+            message,
+            tweak,
+            phase: Phase::Nonces,
+            revision: 0,
+            signers,
+            partial_signatures: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the random identifier of the session.
+    ///
+    /// MuSig2 signers can mix it into their nonces as extra input.
+    pub fn id(&self) -> [u8; 32] { self.id }
+
+    /// Returns the message signed.
+    pub fn message(&self) -> [u8; 32] { self.message }
+
+    /// Returns the round the session is in.
+    pub fn phase(&self) -> Phase { self.phase }
+
+    /// Returns the revision of the session, incremented by every change to it.
+    ///
+    /// A restarted session continues from the revision of the session it replaces.
+    pub fn revision(&self) -> u64 { self.revision }
+
+    /// Records the public nonce of the signer `index`, in its wire encoding: a MuSig2 [`PubNonce`]
+    /// or FROST [`NonceCommitments`].
+    ///
+    /// Receiving the same nonce again is harmless, but a signer can't change their nonce.
+    pub fn add_nonce(&mut self, index: u32, nonce: &[u8]) -> Result<(), SessionError> {
+        if self.phase != Phase::Nonces {
+            return Err(SessionError::NoncesFrozen);
+        }
+        match self.signers {
+            Signers::Musig2 { ref keys, ref mut nonces } => {
+                if index as usize >= keys.len() {
+                    return Err(SessionError::UnknownSigner(index));
+                }
+                let nonce =
+                    PubNonce::from_slice(nonce).map_err(|_| SessionError::InvalidNonce(index))?;
+                insert_nonce(nonces, index, nonce)?;
+            }
+            Signers::Frost { ref public, ref mut commitments } => {
+                if !public.verifying_shares.contains_key(&index) {
+                    return Err(SessionError::UnknownSigner(index));
+                }
+                let nonce = encode::deserialize::<NonceCommitments>(nonce)
+                    .map_err(|_| SessionError::InvalidNonce(index))?;
+                insert_nonce(commitments, index, nonce)?;
+            }
+        }
+        self.revision += 1;
+        Ok(())
+    }
+
+    /// Freezes the nonces and returns the request to send to the signers.
+    ///
+    /// All MuSig2 signers must have sent their nonce. With FROST, the signers who sent their
+    /// nonce commitments sign, and they must be at least as many as the threshold.
+    ///
+    /// Once the nonces are frozen, this returns the same request every time, which is safe to
+    /// send again after a restart.
+    pub fn start_signing(&mut self) -> Result<SigningRequest, SessionError> {
+        if self.phase == Phase::Nonces {
+            match self.signers {
+                Signers::Musig2 { ref keys, ref nonces } => {
+                    if let Some(index) = (0..keys.len() as u32).find(|i| !nonces.contains_key(i)) {
+                        return Err(SessionError::MissingNonce(index));
+                    }
+                }
+                Signers::Frost { ref public, ref commitments } =>
+                    if (commitments.len() as u64) < u64::from(public.threshold) {
+                        return Err(SessionError::NotEnoughSigners {
+                            required: public.threshold,
+                            got: commitments.len(),
+                        });
+                    },
+            }
+            self.phase = Phase::Signatures;
+            self.revision += 1;
+        }
+        Ok(self.request())
+    }
+
+    /// Returns the request to send to the signers, or `None` if the nonces aren't frozen yet.
+    pub fn signing_request(&self) -> Option<SigningRequest> {
+        match self.phase {
+            Phase::Nonces => None,
+            Phase::Signatures => Some(self.request()),
+        }
+    }
+
+    fn request(&self) -> SigningRequest {
+        match self.signers {
+            Signers::Musig2 { ref nonces, .. } =>
+                SigningRequest::Musig2(AggNonce::sum(nonces.values())),
+            Signers::Frost { ref commitments, .. } => SigningRequest::Frost(SigningPackage {
+                message: self.message,
+                commitments: commitments.clone(),
+                tweak: self.tweak,
+            }),
+        }
+    }
+
+    /// Checks and records the 32-byte partial signature of the signer `index`.
+    pub fn add_partial_signature(
+        &mut self,
+        index: u32,
+        partial_signature: &[u8],
+    ) -> Result<(), SessionError> {
+        let partial_signature = match self.phase {
+            Phase::Nonces => return Err(SessionError::NotSigning),
+            Phase::Signatures => MaybeScalar::from_slice(partial_signature)
+                .map_err(|_| SessionError::InvalidPartialSignature(index))?,
+        };
+        match (&self.signers, self.request()) {
+            (Signers::Musig2 { keys, nonces }, SigningRequest::Musig2(agg_nonce)) => {
+                let nonce = nonces.get(&index).ok_or(SessionError::UnknownSigner(index))?;
+                let key_agg = key_agg(keys, self.tweak)?;
+                Session::new(&key_agg, &agg_nonce, &self.message)
+                    .verify_partial_signature(
+                        &PartialSignature(partial_signature),
+                        nonce,
+                        &keys[index as usize],
+                    )
+                    .map_err(|_| SessionError::InvalidPartialSignature(index))?;
+            }
+            (Signers::Frost { public, .. }, SigningRequest::Frost(package)) => {
+                if !package.commitments.contains_key(&index) {
+                    return Err(SessionError::UnknownSigner(index));
+                }
+                package
+                    .verify_share(public, &SignatureShare { index, share: partial_signature })
+                    .map_err(|_| SessionError::InvalidPartialSignature(index))?;
+            }
+            _ => unreachable!("the request matches the scheme"),
+        }
+        self.partial_signatures.insert(index, partial_signature);
+        self.revision += 1;
+        Ok(())
+    }
+
+    /// Combines the partial signatures of all signers into the final signature.
+    pub fn aggregate(&self) -> Result<Signature64, SessionError> {
+        let request = self.signing_request().ok_or(SessionError::NotSigning)?;
+        let signers = match self.signers {
+            Signers::Musig2 { ref nonces, .. } => nonces.keys().copied().collect::<Vec<_>>(),
+            Signers::Frost { ref commitments, .. } => commitments.keys().copied().collect(),
+        };
+        if let Some(index) = signers.into_iter().find(|i| !self.partial_signatures.contains_key(i))
+        {
+            return Err(SessionError::MissingPartialSignature(index));
+        }
+
+        match (&self.signers, request) {
+            (Signers::Musig2 { keys, .. }, SigningRequest::Musig2(agg_nonce)) => {
+                let key_agg = key_agg(keys, self.tweak)?;
+                let partial_signatures = self
+                    .partial_signatures
+                    .values()
+                    .map(|&s| PartialSignature(s))
+                    .collect::<Vec<_>>();
+                Ok(Session::new(&key_agg, &agg_nonce, &self.message)
+                    .unchecked_aggregate(&partial_signatures))
+            }
+            (Signers::Frost { public, .. }, SigningRequest::Frost(package)) => {
+                let shares = self
+                    .partial_signatures
+                    .iter()
+                    .map(|(&index, &share)| SignatureShare { index, share })
+                    .collect::<Vec<_>>();
+                Ok(package.aggregate(public, &shares)?.into())
+            }
+            _ => unreachable!("the request matches the scheme"),
+        }
+    }
+
+    /// Returns a new session for the same signers and message, under a new id and without any
+    /// nonce.
+    ///
+    /// This is the only way to recover from a signer losing their secret nonce after the nonces
+    /// were frozen.
+    pub fn restart<R: RngCore + CryptoRng>(&self, rng: &mut R) -> Self {
+        let signers = match self.signers {
+            Signers::Musig2 { ref keys, .. } =>
+                Signers::Musig2 { keys: keys.clone(), nonces: BTreeMap::new() },
+            Signers::Frost { ref public, .. } =>
+                Signers::Frost { public: public.clone(), commitments: BTreeMap::new() },
+        };
+        let mut session = Self::new(signers, self.message, self.tweak, rng);
+        session.revision = self.revision + 1;
+        session
+    }
+
+    /// Serializes the session in the [`CURRENT_VERSION`] format, authenticated with `key`.
+    pub fn serialize(&self, key: &[u8]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&SESSION_MAGIC);
+        buf.push(CURRENT_VERSION);
+        self.encode(&mut buf).expect("in-memory writers don't error");
+        let mac = compute_mac(key, &buf);
+        buf.extend_from_slice(&mac[..]);
+        buf
+    }
+
+    /// Deserializes a session authenticated with `key`.
+    ///
+    /// This doesn't detect an old serialization of the session being restored, see
+    /// [`SigningSession::deserialize_fresh`].
+    pub fn deserialize(bytes: &[u8], key: &[u8]) -> Result<Self, SessionError> {
+        if bytes.len() < SESSION_MAGIC.len() + 1 + MAC_LEN {
+            return Err(SessionError::TooShort);
+        }
+        let (data, mac) = bytes.split_at(bytes.len() - MAC_LEN);
+        if !bool::from(compute_mac(key, data)[..].ct_eq(mac)) {
+            return Err(SessionError::MacMismatch);
+        }
+        if data[..SESSION_MAGIC.len()] != SESSION_MAGIC {
+            return Err(SessionError::InvalidMagic);
+        }
+
+        let mut r = &data[SESSION_MAGIC.len() + 1..];
+        let session = match data[SESSION_MAGIC.len()] {
+            CURRENT_VERSION => Self::decode(&mut r)?,
+            v => return Err(SessionError::UnsupportedVersion(v)),
+        };
+        if !r.is_empty() {
+            return Err(encode::Error::ParseFailed("trailing data in signing session").into());
+        }
+        Ok(session)
+    }
+
+    /// Deserializes a session authenticated with `key`, which must be at the latest `revision`.
+    ///
+    /// `revision` is the [`SigningSession::revision`] of the session last serialized, as kept by
+    /// the coordinator in storage that can't be rolled back.
+    pub fn deserialize_fresh(
+        bytes: &[u8],
+        key: &[u8],
+        revision: u64,
+    ) -> Result<Self, SessionError> {
+        let session = Self::deserialize(bytes, key)?;
+        if session.revision != revision {
+            return Err(SessionError::StaleSession { expected: revision, got: session.revision });
+        }
+        Ok(session)
+    }
+
+    fn encode<W: Write + ?Sized>(&self, w: &mut W) -> Result<usize, io::Error> {
+        let mut len = self.id.consensus_encode(w)?;
+        len += self.message.consensus_encode(w)?;
+        len += match self.tweak {
+            Some(tweak) => 1u8.consensus_encode(w)? + tweak.serialize().consensus_encode(w)?,
+            None => 0u8.consensus_encode(w)?,
+        };
+        len += match self.phase {
+            Phase::Nonces => 0u8,
+            Phase::Signatures => 1u8,
+        }
+        .consensus_encode(w)?;
+        len += self.revision.consensus_encode(w)?;
+
+        match self.signers {
+            Signers::Musig2 { ref keys, ref nonces } => {
+                len += 0u8.consensus_encode(w)?;
+                len += VarInt::from(keys.len()).consensus_encode(w)?;
+                for key in keys {
+                    len += key.serialize().consensus_encode(w)?;
+                }
+                len += VarInt::from(nonces.len()).consensus_encode(w)?;
+                for (index, nonce) in nonces {
+                    len += index.consensus_encode(w)?;
+                    w.write_all(&nonce.serialize())?;
+                    len += 66;
+                }
+            }
+            Signers::Frost { ref public, ref commitments } => {
+                len += 1u8.consensus_encode(w)?;
+                len += public.threshold.consensus_encode(w)?;
+                len += public.group_key.serialize().consensus_encode(w)?;
+                len += VarInt::from(public.verifying_shares.len()).consensus_encode(w)?;
+                for (index, share) in &public.verifying_shares {
+                    len += index.consensus_encode(w)?;
+                    len += share.serialize().consensus_encode(w)?;
+                }
+                len += frost::encode_map(commitments, w)?;
+            }
+        }
+
+        len += VarInt::from(self.partial_signatures.len()).consensus_encode(w)?;
+        for (index, partial_signature) in &self.partial_signatures {
+            len += index.consensus_encode(w)?;
+            len += partial_signature.serialize().consensus_encode(w)?;
+        }
+        Ok(len)
+    }
+
+    fn decode<R: BufRead + ?Sized>(r: &mut R) -> Result<Self, encode::Error> {
+        let id = Decodable::consensus_decode(r)?;
+        let message = Decodable::consensus_decode(r)?;
+        let tweak = match u8::consensus_decode(r)? {
+            0 => None,
+            1 => Some(
+                Scalar::from_slice(&<[u8; 32]>::consensus_decode(r)?)
+                    .map_err(|_| encode::Error::ParseFailed("invalid tweak"))?,
+            ),
+            _ => return Err(encode::Error::ParseFailed("invalid tweak presence flag")),
+        };
+        let phase = match u8::consensus_decode(r)? {
+            0 => Phase::Nonces,
+            1 => Phase::Signatures,
+            _ => return Err(encode::Error::ParseFailed("invalid signing session phase")),
+        };
+        let revision = u64::consensus_decode(r)?;
+
+        let signers = match u8::consensus_decode(r)? {
+            0 => {
+                let len = VarInt::consensus_decode(r)?.0;
+                let keys = (0..len).map(|_| decode_point(r)).collect::<Result<Vec<_>, _>>()?;
+                let len = VarInt::consensus_decode(r)?.0;
+                let mut nonces = BTreeMap::new();
+                for _ in 0..len {
+                    let index = u32::consensus_decode(r)?;
+                    let mut nonce = [0u8; 66];
+                    r.read_exact(&mut nonce)?;
+                    let nonce = PubNonce::from_slice(&nonce)
+                        .map_err(|_| encode::Error::ParseFailed("invalid public nonce"))?;
+                    nonces.insert(index, nonce);
+                }
+                Signers::Musig2 { keys, nonces }
+            }
+            1 => {
+                let threshold = u32::consensus_decode(r)?;
+                let group_key = decode_point(r)?;
+                let len = VarInt::consensus_decode(r)?.0;
+                let mut verifying_shares = BTreeMap::new();
+                for _ in 0..len {
+                    let index = u32::consensus_decode(r)?;
+                    verifying_shares.insert(index, decode_point(r)?);
+                }
+                let public = PublicKeyPackage { threshold, group_key, verifying_shares };
+                Signers::Frost { public, commitments: frost::decode_map(r)? }
+            }
+            _ => return Err(encode::Error::ParseFailed("unknown signing session scheme")),
+        };
+
+        let len = VarInt::consensus_decode(r)?.0;
+        let mut partial_signatures = BTreeMap::new();
+        for _ in 0..len {
+            let index = u32::consensus_decode(r)?;
+            let partial_signature = MaybeScalar::from_slice(&<[u8; 32]>::consensus_decode(r)?)
+                .map_err(|_| encode::Error::ParseFailed("invalid partial signature"))?;
+            partial_signatures.insert(index, partial_signature);
+        }
+        Ok(SigningSession { id, message, tweak, phase, revision, signers, partial_signatures })
+    }
+}
+
+/// Aggregates the MuSig2 `keys` and applies `tweak` as an X-only tweak.
+fn key_agg(keys: &[PublicKey], tweak: Option<Scalar>) -> Result<KeyAggContext, Musig2Error> {
+    let key_agg = KeyAggContext::new(keys.iter().copied())?;
+    match tweak {
+        Some(tweak) => key_agg.with_xonly_tweak(tweak.into()),
+        None => Ok(key_agg),
+    }
+}
+
+/// Records the `nonce` of the signer `index`, refusing to replace a different nonce.
+fn insert_nonce<T: PartialEq>(
+    nonces: &mut BTreeMap<u32, T>,
+    index: u32,
+    nonce: T,
+) -> Result<(), SessionError> {
+    match nonces.get(&index) {
+        Some(recorded) if *recorded != nonce => Err(SessionError::ConflictingNonce(index)),
+        _ => {
+            nonces.insert(index, nonce);
+            Ok(())
+        }
+    }
+}
+
+/// Decodes a compressed point.
+fn decode_point<R: BufRead + ?Sized>(r: &mut R) -> Result<PublicKey, encode::Error> {
+    PublicKey::from_slice(&<[u8; 33]>::consensus_decode(r)?)
+        .map_err(|_| encode::Error::ParseFailed("invalid point"))
+}
+
+/// Computes the session MAC of `data` with a key derived from `key`.
+fn compute_mac(key: &[u8], data: &[u8]) -> Hmac<sha256::Hash> {
+    let mut key_engine = HmacEngine::<sha256::Hash>::new(MAC_KEY_TAG);
+    key_engine.input(key);
+    let key = Hmac::from_engine(key_engine);
+
+    let mut engine = HmacEngine::<sha256::Hash>::new(&key[..]);
+    engine.input(data);
+    Hmac::from_engine(engine)
+}
+
+/// An error running or resuming a signing session.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum SessionError {
+    /// The data is too short to be a signing session.
+    TooShort,
+    /// The MAC does not match, either the session is corrupted or the key is wrong.
+    MacMismatch,
+    /// The data does not start with [`SESSION_MAGIC`].
+    InvalidMagic,
+    /// The session was written in an unknown version of the format.
+    UnsupportedVersion(u8),
+    /// The session is not at the latest revision, an older serialization was restored.
+    StaleSession {
+        /// The latest revision.
+        expected: u64,
+        /// The revision of the session.
+        got: u64,
+    },
+    /// The session body is malformed.
+    Decode(encode::Error),
+    /// The signer with this index is not part of the session.
+    UnknownSigner(u32),
+    /// The nonce of the signer with this index couldn't be parsed.
+    InvalidNonce(u32),
+    /// The signer with this index already sent a different nonce.
+    ConflictingNonce(u32),
+    /// The nonces are frozen, no nonce can be added anymore.
+    NoncesFrozen,
+    /// The signer with this index hasn't sent their nonce.
+    MissingNonce(u32),
+    /// Not enough FROST signers sent their nonce commitments.
+    NotEnoughSigners {
+        /// The threshold of the key.
+        required: u32,
+        /// The number of signers who sent their nonce commitments.
+        got: usize,
+    },
+    /// The nonces aren't frozen yet, there is nothing to sign.
+    NotSigning,
+    /// The partial signature of the signer with this index is invalid.
+    InvalidPartialSignature(u32),
+    /// The signer with this index hasn't sent their partial signature.
+    MissingPartialSignature(u32),
+    /// The MuSig2 keys or tweak are invalid.
+    Musig2(Musig2Error),
+    /// The FROST signature couldn't be aggregated.
+    Frost(FrostError),
+}
+
+internals::impl_from_infallible!(SessionError);
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use SessionError::*;
+
+        match *self {
+            TooShort => f.write_str("data too short to be a signing session"),
+            MacMismatch => f.write_str("signing session MAC mismatch, wrong key or corrupted data"),
+            InvalidMagic => f.write_str("invalid signing session magic"),
+            UnsupportedVersion(v) => write!(f, "unsupported signing session version {}", v),
+            StaleSession { expected, got } =>
+                write!(f, "stale signing session at revision {}, expected {}", got, expected),
+            Decode(ref e) => write_err!(f, "malformed signing session"; e),
+            UnknownSigner(i) => write!(f, "signer {} is not part of the session", i),
+            InvalidNonce(i) => write!(f, "invalid nonce from signer {}", i),
+            ConflictingNonce(i) => write!(f, "signer {} already sent a different nonce", i),
+            NoncesFrozen => f.write_str("the nonces of the session are frozen"),
+            MissingNonce(i) => write!(f, "missing nonce from signer {}", i),
+            NotEnoughSigners { required, got } =>
+                write!(f, "{} signers sent their nonce, {} are required", got, required),
+            NotSigning => f.write_str("the nonces of the session are not frozen yet"),
+            InvalidPartialSignature(i) => write!(f, "invalid partial signature from signer {}", i),
+            MissingPartialSignature(i) => write!(f, "missing partial signature from signer {}", i),
+            Musig2(ref e) => write_err!(f, "MuSig2 error"; e),
+            Frost(ref e) => write_err!(f, "FROST error"; e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SessionError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use SessionError::*;
+
+        match *self {
+            Decode(ref e) => Some(e),
+            Musig2(ref e) => Some(e),
+            Frost(ref e) => Some(e),
+            TooShort
+            | MacMismatch
+            | InvalidMagic
+            | UnsupportedVersion(_)
+            | StaleSession { .. }
+            | UnknownSigner(_)
+            | InvalidNonce(_)
+            | ConflictingNonce(_)
+            | NoncesFrozen
+            | MissingNonce(_)
+            | NotEnoughSigners { .. }
+            | NotSigning
+            | InvalidPartialSignature(_)
+            | MissingPartialSignature(_) => None,
+        }
+    }
+}
+
+impl From<encode::Error> for SessionError {
+    fn from(e: encode::Error) -> Self { Self::Decode(e) }
+}
+
+impl From<Musig2Error> for SessionError {
+    fn from(e: Musig2Error) -> Self { Self::Musig2(e) }
+}
+
+impl From<FrostError> for SessionError {
+    fn from(e: FrostError) -> Self { Self::Frost(e) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::types::Message;
+    use crate::crypto::frost::{generate_with_dealer, SigningNonces};
+    use crate::crypto::musig2::SecNonce;
+    use crate::crypto::schnorr::verify_schnorr;
+    use crate::G;
+
+    const KEY: &[u8] = &[0x42; 32];
+    const MESSAGE: [u8; 32] = [0x99; 32];
+
+    /// Persists the session and resumes it, as after a restart.
+    fn resume(session: &SigningSession) -> SigningSession {
+        let resumed = SigningSession::deserialize(&session.serialize(KEY), KEY).unwrap();
+        assert_eq!(resumed, *session);
+        resumed
+    }
+
+    #[test]
+    fn musig2() {
+        let mut rng = rand::thread_rng();
+        let secrets = (1..=3).map(|n| Scalar::from_u32(n * 0x1234).unwrap()).collect::<Vec<_>>();
+        let keys = secrets.iter().map(|&secret| secret * G).collect::<Vec<_>>();
+        let tweak = Scalar::from_u32(7).unwrap();
+        let mut session =
+            SigningSession::musig2(keys.clone(), MESSAGE, Some(tweak), &mut rng).unwrap();
+        let output_key = key_agg(&keys, Some(tweak)).unwrap().x_only_public_key();
+
+        let sec_nonces = keys
+            .iter()
+            .map(|&key| {
+                let mut rand = [0u8; 32];
+                rng.fill_bytes(&mut rand);
+                SecNonce::builder(rand, key).with_extra_input(&session.id()).build()
+            })
+            .collect::<Vec<_>>();
+        for (index, sec_nonce) in sec_nonces.iter().enumerate() {
+            assert_eq!(session.signing_request(), None);
+            session = resume(&session);
+            session.add_nonce(index as u32, &sec_nonce.public_nonce().serialize()).unwrap();
+        }
+        let request = session.start_signing().unwrap();
+        let mut session = resume(&session);
+        assert_eq!(session.signing_request(), Some(request.clone()));
+        assert!(matches!(
+            session.add_nonce(0, &sec_nonces[0].public_nonce().serialize()),
+            Err(SessionError::NoncesFrozen)
+        ));
+
+        let agg_nonce = match request {
+            SigningRequest::Musig2(agg_nonce) => agg_nonce,
+            SigningRequest::Frost(_) => panic!("MuSig2 session"),
+        };
+        let key_agg = key_agg(&keys, Some(tweak)).unwrap();
+        let signing = Session::new(&key_agg, &agg_nonce, &MESSAGE);
+        for (index, (sec_nonce, &secret)) in sec_nonces.into_iter().zip(&secrets).enumerate() {
+            assert!(matches!(
+                session.aggregate(),
+                Err(SessionError::MissingPartialSignature(i)) if i as usize == index
+            ));
+            let partial_signature = signing.sign(sec_nonce, secret).unwrap();
+            assert!(matches!(
+                session.add_partial_signature(index as u32, &[0x01; 32]),
+                Err(SessionError::InvalidPartialSignature(_))
+            ));
+            session.add_partial_signature(index as u32, &partial_signature.serialize()).unwrap();
+            session = resume(&session);
+        }
+
+        let signature = session.aggregate().unwrap();
+        let msg = Message::from_digest(MESSAGE);
+        assert_eq!(verify_schnorr(&signature, &msg, &output_key), Ok(()));
+    }
+
+    #[test]
+    fn frost() {
+        let mut rng = rand::thread_rng();
+        let secret = Scalar::from_u32(0xdead).unwrap();
+        let (shares, public) = generate_with_dealer(secret, 2, 3, &mut rng).unwrap();
+        let mut session = SigningSession::frost(public.clone(), MESSAGE, None, &mut rng);
+
+        let signers = [&shares[0], &shares[2]];
+        let nonces = signers.map(|_| SigningNonces::new(&mut rng));
+        session.add_nonce(signers[0].index, &encode::serialize(&nonces[0].commitments())).unwrap();
+        assert!(matches!(
+            session.start_signing(),
+            Err(SessionError::NotEnoughSigners { required: 2, got: 1 })
+        ));
+        assert!(matches!(
+            session.add_nonce(signers[0].index, &encode::serialize(&nonces[1].commitments())),
+            Err(SessionError::ConflictingNonce(_))
+        ));
+        session.add_nonce(signers[1].index, &encode::serialize(&nonces[1].commitments())).unwrap();
+        let package = match session.start_signing().unwrap() {
+            SigningRequest::Frost(package) => package,
+            SigningRequest::Musig2(_) => panic!("FROST session"),
+        };
+
+        let mut session = resume(&session);
+        for (key, nonces) in signers.into_iter().zip(nonces) {
+            let share = frost::sign(&package, key, nonces).unwrap();
+            session.add_partial_signature(share.index, &share.share.serialize()).unwrap();
+            session = resume(&session);
+        }
+        let signature = session.aggregate().unwrap();
+        let output_key = (secret * G).x_only_public_key().0;
+        assert_eq!(verify_schnorr(&signature, &Message::from_digest(MESSAGE), &output_key), Ok(()));
+
+        assert!(matches!(
+            session.restart(&mut rng).add_nonce(9, &[0; 66]),
+            Err(SessionError::UnknownSigner(9))
+        ));
+    }
+
+    #[test]
+    fn restart() {
+        let mut rng = rand::thread_rng();
+        let keys = vec![Scalar::from_u32(1).unwrap() * G, Scalar::from_u32(2).unwrap() * G];
+        let mut session = SigningSession::musig2(keys, MESSAGE, None, &mut rng).unwrap();
+        let nonce = SecNonce::new(&mut rng, *G).public_nonce().serialize();
+        session.add_nonce(0, &nonce).unwrap();
+        assert!(matches!(session.start_signing(), Err(SessionError::MissingNonce(1))));
+        assert!(matches!(
+            session.add_partial_signature(0, &[1; 32]),
+            Err(SessionError::NotSigning)
+        ));
+
+        let restarted = session.restart(&mut rng);
+        assert_ne!(restarted.id(), session.id());
+        assert_eq!(restarted.message(), session.message());
+        assert_eq!(restarted.phase(), Phase::Nonces);
+        assert!(matches!(restarted.aggregate(), Err(SessionError::NotSigning)));
+    }
+
+    #[test]
+    fn authentication() {
+        let mut rng = rand::thread_rng();
+        let keys = vec![Scalar::from_u32(1).unwrap() * G];
+        let session = SigningSession::musig2(keys, MESSAGE, None, &mut rng).unwrap();
+        let mut bytes = session.serialize(KEY);
+        assert_eq!(&bytes[..4], b"MSES");
+        assert_eq!(bytes[4], CURRENT_VERSION);
+        assert!(matches!(
+            SigningSession::deserialize(&bytes, &[0x43; 32]),
+            Err(SessionError::MacMismatch)
+        ));
+
+        // Any other version is rejected, even with a valid MAC.
+        let mut other = bytes[..bytes.len() - MAC_LEN].to_vec();
+        other[4] = CURRENT_VERSION + 1;
+        let mac = compute_mac(KEY, &other);
+        other.extend_from_slice(&mac[..]);
+        assert!(matches!(
+            SigningSession::deserialize(&other, KEY),
+            Err(SessionError::UnsupportedVersion(v)) if v == CURRENT_VERSION + 1
+        ));
+
+        // Changing the message after the nonces are frozen could make the signers reuse them.
+        bytes[5 + 32] ^= 1;
+        assert!(matches!(SigningSession::deserialize(&bytes, KEY), Err(SessionError::MacMismatch)));
+        assert!(matches!(
+            SigningSession::deserialize(&bytes[..20], KEY),
+            Err(SessionError::TooShort)
+        ));
+    }
+
+    #[test]
+    fn replay() {
+        let mut rng = rand::thread_rng();
+        let secret = Scalar::from_u32(0xdead).unwrap();
+        let (shares, public) = generate_with_dealer(secret, 2, 3, &mut rng).unwrap();
+        let mut session = SigningSession::frost(public, MESSAGE, None, &mut rng);
+        let nonces = shares.iter().map(|_| SigningNonces::new(&mut rng)).collect::<Vec<_>>();
+        for (share, nonces) in shares.iter().zip(&nonces).take(2) {
+            session.add_nonce(share.index, &encode::serialize(&nonces.commitments())).unwrap();
+        }
+        let before_signing = session.serialize(KEY);
+        let revision = session.revision();
+        session.start_signing().unwrap();
+        assert_eq!(session.revision(), revision + 1);
+        let latest = session.serialize(KEY);
+
+        // An old session could freeze another set of nonces, signing another package with them.
+        let mut replayed = SigningSession::deserialize(&before_signing, KEY).unwrap();
+        replayed.add_nonce(shares[2].index, &encode::serialize(&nonces[2].commitments())).unwrap();
+        assert_ne!(replayed.start_signing().unwrap(), session.signing_request().unwrap());
+        assert!(matches!(
+            SigningSession::deserialize_fresh(&before_signing, KEY, session.revision()),
+            Err(SessionError::StaleSession { expected, got }) if expected == revision + 1 && got == revision
+        ));
+        assert_eq!(
+            SigningSession::deserialize_fresh(&latest, KEY, session.revision()).unwrap(),
+            session
+        );
+        assert_eq!(session.restart(&mut rng).revision(), session.revision() + 1);
+    }
+}
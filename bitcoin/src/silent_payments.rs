@@ -0,0 +1,802 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! BIP352 silent payments.
+//!
+//! A [`SilentPaymentCode`] is a static code published by a receiver. Every payment to it creates
+//! a fresh taproot output, derived from the code and the inputs of the paying transaction, so the
+//! payments can't be linked to the code or to each other on chain.
+//!
+//! The payer derives the output keys with [`create_outputs`], from the secret keys of its inputs.
+//! The receiver finds the outputs paying it with [`scan`], from its secret scan key and the
+//! outputs spent by the transaction.
+//!
+//! A receiver can tell payments apart by handing out codes with different labels, see
+//! [`SilentPaymentCode::with_label`].
+//!
+
+use core::fmt;
+use core::str::FromStr;
+
+use bech32::primitives::decode::{CheckedHrpstring, CheckedHrpstringError};
+use bech32::{Bech32m, ByteIterExt, Fe32, Fe32IterExt, Hrp};
+use internals::write_err;
+
+use crate::address::KnownHrp;
+use crate::consensus::encode::serialize;
+use crate::crypto::hashes::tagged_hash_to_scalar;
+use crate::crypto::key::{FromSliceError, MaybePublicKey, PublicKey, XOnlyPublicKey};
+use crate::crypto::scalar::{MaybeScalar, Scalar};
+use crate::prelude::*;
+use crate::{Network, OutPoint, Script, Transaction, TxIn, TxOut};
+
+/// The human-readable part of codes on mainnet.
+const HRP_MAINNET: Hrp = Hrp::parse_unchecked("sp");
+/// The human-readable part of codes on testnet and signet.
+const HRP_TESTNETS: Hrp = Hrp::parse_unchecked("tsp");
+/// The human-readable part of codes on regtest.
+const HRP_REGTEST: Hrp = Hrp::parse_unchecked("sprt");
+
+/// The version of the codes written by this library.
+const VERSION: Fe32 = Fe32::Q;
+
+/// The length of the keys of a code, the scan key followed by the spend key.
+const KEYS_LEN: usize = 66;
+
+const INPUTS_TAG: &str = "BIP0352/Inputs";
+const SHARED_SECRET_TAG: &str = "BIP0352/SharedSecret";
+const LABEL_TAG: &str = "BIP0352/Label";
+
+/// The X-coordinate of the BIP341 point with no known discrete logarithm.
+///
+/// Script path spends with this internal key don't contribute to the shared secret, as the
+/// spender can't know its secret key.
+const NUMS_INTERNAL_KEY: [u8; 32] = [
+    0x50, 0x92, 0x9b, 0x74, 0xc1, 0xa0, 0x49, 0x54, 0xb7, 0x8b, 0x4b, 0x60, 0x35, 0xe9, 0x7a, 0x5e,
+    0x07, 0x8a, 0x5a, 0x0f, 0x28, 0xec, 0x96, 0xd5, 0x47, 0xbf, 0xee, 0x9a, 0xce, 0x80, 0x3a, 0xc0,
+];
+
+/// A silent payment code, the scan and spend keys of a receiver.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SilentPaymentCode {
+    scan_key: PublicKey,
+    spend_key: PublicKey,
+    network: KnownHrp,
+}
+
+impl SilentPaymentCode {
+    /// Creates the code of the receiver with the keys `scan_key` and `spend_key` on `network`.
+    pub fn new(scan_key: PublicKey, spend_key: PublicKey, network: impl Into<KnownHrp>) -> Self {
+        SilentPaymentCode { scan_key, spend_key, network: network.into() }
+    }
+
+    /// Returns the key the receiver scans transactions with.
+    pub fn scan_key(&self) -> PublicKey { self.scan_key }
+
+    /// Returns the key the outputs paying the code are derived from.
+    pub fn spend_key(&self) -> PublicKey { self.spend_key }
+
+    /// Returns true if the code is for `network`.
+    pub fn is_valid_for_network(&self, network: Network) -> bool {
+        self.network == KnownHrp::from(network)
+    }
+
+    /// Returns the code with the label `m`, which pays the same receiver under another code.
+    ///
+    /// `scan_secret` is the secret key of the scan key. Outputs paying the labeled code are found
+    /// by passing `m` to [`scan`]. BIP352 reserves the label 0 for change.
+    pub fn with_label(&self, scan_secret: &Scalar, m: u32) -> SilentPaymentCode {
+        SilentPaymentCode { spend_key: labeled_spend_key(scan_secret, &self.spend_key, m), ..*self }
+    }
+
+    fn hrp(&self) -> Hrp {
+        match self.network {
+            KnownHrp::Mainnet => HRP_MAINNET,
+            KnownHrp::Testnets => HRP_TESTNETS,
+            KnownHrp::Regtest => HRP_REGTEST,
+        }
+    }
+}
+
+impl fmt::Display for SilentPaymentCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut keys = [0u8; KEYS_LEN];
+        keys[..33].copy_from_slice(&self.scan_key.serialize());
+        keys[33..].copy_from_slice(&self.spend_key.serialize());
+
+        let hrp = self.hrp();
+        let chars = keys
+            .iter()
+            .copied()
+            .bytes_to_fes()
+            .with_checksum::<Bech32m>(&hrp)
+            .with_witness_version(VERSION)
+            .chars();
+        for c in chars {
+            fmt::Write::write_char(f, c)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for SilentPaymentCode {
+    type Err = ParseSilentPaymentCodeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use ParseSilentPaymentCodeError as E;
+
+        let checked = CheckedHrpstring::new::<Bech32m>(s).map_err(E::Bech32)?;
+        let hrp = checked.hrp();
+        let network = if hrp == HRP_MAINNET {
+            KnownHrp::Mainnet
+        } else if hrp == HRP_TESTNETS {
+            KnownHrp::Testnets
+        } else if hrp == HRP_REGTEST {
+            KnownHrp::Regtest
+        } else {
+            return Err(E::UnknownHrp(hrp.to_lowercase()));
+        };
+
+        // Versions go up to 31, beyond the segwit versions the bech32 crate strips.
+        let fes = checked
+            .data_part_ascii_no_checksum()
+            .iter()
+            .map(|&c| Fe32::from_char(char::from(c)).expect("checked string has valid characters"))
+            .collect::<Vec<_>>();
+        let (version, fes) = fes.split_first().ok_or(E::MissingVersion)?;
+        // The padding must be shorter than a field element and zero.
+        let padding = fes.len() * 5 % 8;
+        if padding >= 5 || fes.last().map_or(0, |fe| fe.to_u8() & ((1 << padding) - 1)) != 0 {
+            return Err(E::InvalidPadding);
+        }
+        let data = fes.iter().copied().fes_to_bytes().collect::<Vec<u8>>();
+        // Later versions only append data, except version 31 which is reserved for an
+        // incompatible format.
+        match version.to_u8() {
+            31 => return Err(E::UnsupportedVersion(31)),
+            0 if data.len() != KEYS_LEN => return Err(E::InvalidLength(data.len())),
+            _ if data.len() < KEYS_LEN => return Err(E::InvalidLength(data.len())),
+            _ => {}
+        }
+
+        let scan_key = PublicKey::from_slice(&data[..33]).map_err(E::InvalidKey)?;
+        let spend_key = PublicKey::from_slice(&data[33..KEYS_LEN]).map_err(E::InvalidKey)?;
+        Ok(SilentPaymentCode { scan_key, spend_key, network })
+    }
+}
+
+/// Returns the tweak of the spend key of the label `m`, for the receiver with the secret scan
+/// key `scan_secret`.
+pub fn label_tweak(scan_secret: &Scalar, m: u32) -> Scalar {
+    tagged_hash_to_scalar(LABEL_TAG, &[&scan_secret.serialize(), &m.to_be_bytes()])
+        .not_zero()
+        .expect("a zero label tweak is practically impossible")
+}
+
+/// Returns the spend key of the code with the label `m`, for the receiver with the secret scan
+/// key `scan_secret` and the spend key `spend_key`.
+pub fn labeled_spend_key(scan_secret: &Scalar, spend_key: &PublicKey, m: u32) -> PublicKey {
+    (*spend_key + label_tweak(scan_secret, m).base_point_mul())
+        .into_option()
+        .expect("a spend key cancelling the label tweak is practically impossible")
+}
+
+/// The secret key of an input of a transaction paying silent payment codes.
+#[derive(Copy, Clone)]
+pub enum InputSecretKey {
+    /// The tweaked secret key of a P2TR key path spend.
+    Taproot(Scalar),
+    /// The secret key of a P2PKH, P2WPKH or P2SH-P2WPKH input, whose public key is compressed.
+    Ecdsa(Scalar),
+}
+
+impl InputSecretKey {
+    /// Returns the secret key of the public key the receiver sees for the input.
+    ///
+    /// The receiver lifts taproot output keys to the point with an even Y-coordinate, so the keys
+    /// of points with an odd Y-coordinate are negated.
+    fn to_scalar(self) -> Scalar {
+        match self {
+            InputSecretKey::Taproot(secret) => {
+                let (_, parity) = secret.base_point_mul().x_only_public_key();
+                secret.negate_if(parity)
+            }
+            InputSecretKey::Ecdsa(secret) => secret,
+        }
+    }
+}
+
+/// Returns the output keys paying `recipients` from a transaction spending `outpoints`.
+///
+/// `outpoints` are the outpoints spent by every input of the transaction, and `input_keys` the
+/// secret keys of the inputs eligible for silent payments. Each recipient is paid with its own
+/// taproot output, whose key is at the same position in the result. Paying the same code twice
+/// creates two different outputs.
+///
+/// # Errors
+///
+/// If there are no outpoints or input keys, or if the input keys add up to zero.
+pub fn create_outputs(
+    outpoints: &[OutPoint],
+    input_keys: &[InputSecretKey],
+    recipients: &[SilentPaymentCode],
+) -> Result<Vec<XOnlyPublicKey>, SilentPaymentError> {
+    if outpoints.is_empty() || input_keys.is_empty() {
+        return Err(SilentPaymentError::NoInputs);
+    }
+    let input_secret = input_keys
+        .iter()
+        .map(|key| key.to_scalar())
+        .sum::<MaybeScalar>()
+        .not_zero()
+        .map_err(|_| SilentPaymentError::InputKeysCancel)?;
+    let secret = input_hash(outpoints, &input_secret.base_point_mul()) * input_secret;
+
+    // The outputs paying the same scan key are numbered from zero.
+    let mut counts = BTreeMap::<[u8; 33], u32>::new();
+    Ok(recipients
+        .iter()
+        .map(|code| {
+            let k = counts.entry(code.scan_key.serialize()).or_insert(0);
+            let shared_secret = secret * code.scan_key;
+            let tweak = output_tweak(&shared_secret, *k);
+            *k += 1;
+            output_key(&code.spend_key, tweak).x_only_public_key().0
+        })
+        .collect())
+}
+
+/// An output of a transaction paying a silent payment code.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SilentPaymentOutput {
+    /// The index of the output in the transaction.
+    pub vout: u32,
+    /// The tweak of the output key, added to the secret spend key to spend the output.
+    pub tweak: Scalar,
+    /// The label of the code paid by the output, if it is labeled.
+    pub label: Option<u32>,
+}
+
+/// Returns the outputs of `tx` paying the receiver with the secret scan key `scan_secret` and
+/// the spend key `spend_key`, or one of its codes with a label in `labels`.
+///
+/// `prevouts` are the outputs spent by the inputs of `tx`, in the same order. They must come
+/// from the receiver's own view of the chain: the outputs found depend on them.
+///
+/// # Errors
+///
+/// If there isn't one prevout for each input.
+pub fn scan(
+    tx: &Transaction,
+    prevouts: &[TxOut],
+    scan_secret: &Scalar,
+    spend_key: &PublicKey,
+    labels: &[u32],
+) -> Result<Vec<SilentPaymentOutput>, SilentPaymentError> {
+    if prevouts.len() != tx.input.len() {
+        return Err(SilentPaymentError::PrevoutsLength {
+            inputs: tx.input.len(),
+            prevouts: prevouts.len(),
+        });
+    }
+    // Transactions spending outputs of future segwit versions are left for future versions of
+    // BIP352.
+    if prevouts.iter().any(|prevout| {
+        prevout.script_pubkey.witness_version().is_some_and(|version| version.to_num() > 1)
+    }) {
+        return Ok(vec![]);
+    }
+
+    let input_key_sum = tx
+        .input
+        .iter()
+        .zip(prevouts)
+        .filter_map(|(input, prevout)| input_public_key(input, prevout))
+        .sum::<MaybePublicKey>();
+    let input_key_sum = match input_key_sum {
+        MaybePublicKey::Valid(key) => key,
+        MaybePublicKey::Infinity => return Ok(vec![]),
+    };
+    let outpoints = tx.input.iter().map(|input| input.previous_output).collect::<Vec<_>>();
+    let shared_secret = (input_hash(&outpoints, &input_key_sum) * *scan_secret) * input_key_sum;
+
+    let label_tweaks = labels.iter().map(|&m| (m, label_tweak(scan_secret, m))).collect::<Vec<_>>();
+    let mut outputs = vec![];
+    // The outputs paying the receiver are numbered from zero across all of its labels.
+    for k in 0.. {
+        let unlabeled = output_tweak(&shared_secret, k);
+        let candidates =
+            core::iter::once((None, unlabeled)).chain(label_tweaks.iter().map(|&(m, label)| {
+                let tweak = (unlabeled + label)
+                    .not_zero()
+                    .expect("a label cancelling the output tweak is practically impossible");
+                (Some(m), tweak)
+            }));
+        let found = candidates.into_iter().find_map(|(label, tweak)| {
+            let key = output_key(spend_key, tweak).serialize_xonly();
+            let vout = tx.output.iter().position(|output| {
+                output.script_pubkey.is_p2tr() && output.script_pubkey.as_bytes()[2..] == key
+            })?;
+            Some(SilentPaymentOutput { vout: vout as u32, tweak, label })
+        });
+        match found {
+            Some(output) => outputs.push(output),
+            None => break,
+        }
+    }
+    Ok(outputs)
+}
+
+/// Returns the hash committing the shared secret to the inputs of the transaction.
+fn input_hash(outpoints: &[OutPoint], input_key_sum: &PublicKey) -> Scalar {
+    let smallest = outpoints.iter().map(serialize).min().expect("transactions have inputs");
+    tagged_hash_to_scalar(INPUTS_TAG, &[&smallest, &input_key_sum.serialize()])
+        .not_zero()
+        .expect("a zero input hash is practically impossible")
+}
+
+/// Returns the tweak of the `k`-th output paying a scan key with the ECDH `shared_secret`.
+fn output_tweak(shared_secret: &PublicKey, k: u32) -> Scalar {
+    tagged_hash_to_scalar(SHARED_SECRET_TAG, &[&shared_secret.serialize(), &k.to_be_bytes()])
+        .not_zero()
+        .expect("a zero output tweak is practically impossible")
+}
+
+/// Returns the output key `spend_key + tweak * G`.
+fn output_key(spend_key: &PublicKey, tweak: Scalar) -> PublicKey {
+    (*spend_key + tweak.base_point_mul())
+        .into_option()
+        .expect("a spend key cancelling the output tweak is practically impossible")
+}
+
+/// Returns the public key `input` contributes to the shared secret, if it is eligible.
+fn input_public_key(input: &TxIn, prevout: &TxOut) -> Option<PublicKey> {
+    let script_pubkey = &prevout.script_pubkey;
+    if script_pubkey.is_p2tr() {
+        let internal_key = input.witness.taproot_control_block().and_then(|cb| cb.get(1..33));
+        if internal_key == Some(&NUMS_INTERNAL_KEY[..]) {
+            return None;
+        }
+        XOnlyPublicKey::from_slice(&script_pubkey.as_bytes()[2..]).ok().map(|key| key.lift_x())
+    } else if script_pubkey.is_p2wpkh() {
+        compressed_public_key(input.witness.last()?)
+    } else if script_pubkey.is_p2sh() {
+        // Only P2SH-P2WPKH, whose script sig pushes the P2WPKH program.
+        let script_sig = input.script_sig.as_bytes();
+        if script_sig.len() != 23 || script_sig[0] != 22 {
+            return None;
+        }
+        if !Script::from_bytes(&script_sig[1..]).is_p2wpkh() {
+            return None;
+        }
+        compressed_public_key(input.witness.last()?)
+    } else if script_pubkey.is_p2pkh() {
+        // The script sig may push other data, the key is the push hashing to the key hash.
+        let pubkey_hash = &script_pubkey.as_bytes()[3..23];
+        input
+            .script_sig
+            .instructions()
+            .filter_map(|instruction| {
+                compressed_public_key(instruction.ok()?.push_bytes()?.as_bytes())
+            })
+            .find(|key| key.pubkey_hash()[..] == *pubkey_hash)
+    } else {
+        None
+    }
+}
+
+/// Parses a compressed public key, uncompressed keys are not eligible.
+fn compressed_public_key(bytes: &[u8]) -> Option<PublicKey> {
+    if bytes.len() != 33 {
+        return None;
+    }
+    PublicKey::from_slice(bytes).ok()
+}
+
+/// An error deriving or scanning for silent payment outputs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SilentPaymentError {
+    /// The transaction has no inputs eligible for silent payments.
+    NoInputs,
+    /// The secret keys of the inputs add up to zero.
+    InputKeysCancel,
+    /// The number of prevouts doesn't match the number of inputs.
+    PrevoutsLength {
+        /// The number of inputs of the transaction.
+        inputs: usize,
+        /// The number of prevouts.
+        prevouts: usize,
+    },
+}
+
+internals::impl_from_infallible!(SilentPaymentError);
+
+impl fmt::Display for SilentPaymentError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use SilentPaymentError::*;
+
+        match *self {
+            NoInputs => f.write_str("no inputs eligible for silent payments"),
+            InputKeysCancel => f.write_str("the secret keys of the inputs add up to zero"),
+            PrevoutsLength { inputs, prevouts } =>
+                write!(f, "{} prevouts given for {} inputs", prevouts, inputs),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SilentPaymentError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use SilentPaymentError::*;
+
+        match *self {
+            NoInputs | InputKeysCancel | PrevoutsLength { .. } => None,
+        }
+    }
+}
+
+/// An error parsing a [`SilentPaymentCode`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ParseSilentPaymentCodeError {
+    /// The code is not a valid bech32m string.
+    Bech32(CheckedHrpstringError),
+    /// The padding of the data is invalid.
+    InvalidPadding,
+    /// The human-readable part is not one of a known network.
+    UnknownHrp(String),
+    /// The code has no version.
+    MissingVersion,
+    /// The version of the code is not supported.
+    UnsupportedVersion(u8),
+    /// The code has the wrong length for its version.
+    InvalidLength(usize),
+    /// One of the keys of the code is invalid.
+    InvalidKey(FromSliceError),
+}
+
+internals::impl_from_infallible!(ParseSilentPaymentCodeError);
+
+impl fmt::Display for ParseSilentPaymentCodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use ParseSilentPaymentCodeError::*;
+
+        match *self {
+            Bech32(ref e) => write_err!(f, "invalid bech32m string"; e),
+            InvalidPadding => f.write_str("invalid silent payment code padding"),
+            UnknownHrp(ref hrp) => write!(f, "unknown silent payment code prefix {}", hrp),
+            MissingVersion => f.write_str("silent payment code has no version"),
+            UnsupportedVersion(v) => write!(f, "unsupported silent payment code version {}", v),
+            InvalidLength(len) => write!(f, "invalid silent payment code length {}", len),
+            InvalidKey(ref e) => write_err!(f, "invalid silent payment code key"; e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseSilentPaymentCodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use ParseSilentPaymentCodeError::*;
+
+        match *self {
+            Bech32(ref e) => Some(e),
+            InvalidKey(ref e) => Some(e),
+            InvalidPadding
+            | UnknownHrp(_)
+            | MissingVersion
+            | UnsupportedVersion(_)
+            | InvalidLength(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hashes::Hash;
+    use hex::test_hex_unwrap as hex;
+
+    use super::*;
+    use crate::blockdata::script::{PushBytesBuf, ScriptBuf};
+    use crate::crypto::key::TweakedPublicKey;
+    use crate::locktime::absolute;
+    use crate::transaction::Version;
+    use crate::{Amount, Txid, Witness};
+
+    fn secret(byte: u8) -> Scalar { Scalar::from_slice(&[byte; 32]).unwrap() }
+
+    fn code(scan: u8, spend: u8) -> SilentPaymentCode {
+        let scan_key = secret(scan).base_point_mul();
+        SilentPaymentCode::new(scan_key, secret(spend).base_point_mul(), Network::Bitcoin)
+    }
+
+    fn outpoint(byte: u8) -> OutPoint { OutPoint::new(Txid::from_byte_array([byte; 32]), 1) }
+
+    /// Returns the transaction paying `outputs` and the outputs it spends.
+    fn transaction(
+        inputs: Vec<(TxIn, TxOut)>,
+        outputs: &[XOnlyPublicKey],
+    ) -> (Transaction, Vec<TxOut>) {
+        let (input, prevouts) = inputs.into_iter().unzip();
+        let output = outputs
+            .iter()
+            .map(|key| TxOut {
+                value: Amount::from_sat(10_000),
+                script_pubkey: ScriptBuf::new_p2tr_tweaked(
+                    TweakedPublicKey::dangerous_assume_tweaked(*key),
+                ),
+            })
+            .collect();
+        let tx = Transaction {
+            version: Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input,
+            output,
+        };
+        (tx, prevouts)
+    }
+
+    fn p2wpkh_input(outpoint: OutPoint, secret: &Scalar) -> (TxIn, TxOut) {
+        let key = secret.base_point_mul();
+        let mut witness = Witness::new();
+        witness.push([0x30; 71]);
+        witness.push(key.serialize());
+        let input = TxIn { previous_output: outpoint, witness, ..Default::default() };
+        let prevout = TxOut {
+            value: Amount::from_sat(20_000),
+            script_pubkey: ScriptBuf::new_p2wpkh(&key.wpubkey_hash().unwrap()),
+        };
+        (input, prevout)
+    }
+
+    fn p2tr_input(outpoint: OutPoint, secret: &Scalar) -> (TxIn, TxOut) {
+        let (key, _) = secret.base_point_mul().x_only_public_key();
+        let mut witness = Witness::new();
+        witness.push([0x40; 64]);
+        let input = TxIn { previous_output: outpoint, witness, ..Default::default() };
+        let prevout = TxOut {
+            value: Amount::from_sat(20_000),
+            script_pubkey: ScriptBuf::new_p2tr_tweaked(TweakedPublicKey::dangerous_assume_tweaked(
+                key,
+            )),
+        };
+        (input, prevout)
+    }
+
+    fn p2pkh_input(outpoint: OutPoint, secret: &Scalar) -> (TxIn, TxOut) {
+        let key = secret.base_point_mul();
+        let script_sig =
+            ScriptBuf::builder().push_slice([0x30; 71]).push_slice(key.serialize()).into_script();
+        let input = TxIn { previous_output: outpoint, script_sig, ..Default::default() };
+        let prevout = TxOut {
+            value: Amount::from_sat(20_000),
+            script_pubkey: ScriptBuf::new_p2pkh(&key.pubkey_hash()),
+        };
+        (input, prevout)
+    }
+
+    fn p2sh_p2wpkh_input(outpoint: OutPoint, secret: &Scalar) -> (TxIn, TxOut) {
+        let (mut input, prevout) = p2wpkh_input(outpoint, secret);
+        let redeem_script = prevout.script_pubkey;
+        let push = PushBytesBuf::try_from(redeem_script.to_bytes()).unwrap();
+        input.script_sig = ScriptBuf::builder().push_slice(push).into_script();
+        let prevout = TxOut { script_pubkey: redeem_script.to_p2sh(), ..prevout };
+        (input, prevout)
+    }
+
+    #[test]
+    fn code_roundtrip() {
+        // From the BIP352 test vectors.
+        let s = "sp1qqgste7k9hx0qftg6qmwlkqtwuy6cycyavzmzj85c6qdfhjdpdjtdgqjuexzk6murw56suy3e0rd2cgqvycxttddwsvgxe2usfpxumr70xc9pkqwv";
+        let code = s.parse::<SilentPaymentCode>().unwrap();
+        assert_eq!(
+            code.scan_key().serialize()[..],
+            hex!("0220bcfac5b99e04ad1a06ddfb016ee13582609d60b6291e98d01a9bc9a16c96d4")[..]
+        );
+        assert_eq!(
+            code.spend_key().serialize()[..],
+            hex!("025cc9856d6f8375350e123978daac200c260cb5b5ae83106cab90484dcd8fcf36")[..]
+        );
+        assert!(code.is_valid_for_network(Network::Bitcoin));
+        assert!(!code.is_valid_for_network(Network::Testnet));
+        assert_eq!(code.to_string(), s);
+        assert_eq!(s.to_uppercase().parse::<SilentPaymentCode>(), Ok(code));
+
+        for network in [Network::Testnet, Network::Signet, Network::Regtest] {
+            let code = SilentPaymentCode::new(code.scan_key(), code.spend_key(), network);
+            let s = code.to_string();
+            assert_eq!(s.parse::<SilentPaymentCode>(), Ok(code));
+            assert!(code.is_valid_for_network(network));
+        }
+    }
+
+    #[test]
+    fn code_parse_errors() {
+        let code = code(1, 2);
+        let keys = [&code.scan_key().serialize()[..], &code.spend_key().serialize()].concat();
+        let encode = |hrp: &str, version: Fe32, data: &[u8]| {
+            data.iter()
+                .copied()
+                .bytes_to_fes()
+                .with_checksum::<Bech32m>(&Hrp::parse(hrp).unwrap())
+                .with_witness_version(version)
+                .chars()
+                .collect::<String>()
+        };
+
+        assert_eq!(encode("sp", Fe32::Q, &keys).parse::<SilentPaymentCode>(), Ok(code));
+        assert_eq!(
+            encode("bc", Fe32::Q, &keys).parse::<SilentPaymentCode>(),
+            Err(ParseSilentPaymentCodeError::UnknownHrp("bc".to_owned()))
+        );
+        assert_eq!(
+            encode("sp", Fe32::Q, &[&keys[..], &[0]].concat()).parse::<SilentPaymentCode>(),
+            Err(ParseSilentPaymentCodeError::InvalidLength(67))
+        );
+        // Later versions may append data.
+        let s = encode("sp", Fe32::P, &[&keys[..], &[0]].concat());
+        assert_eq!(s.parse::<SilentPaymentCode>(), Ok(code));
+        assert_eq!(
+            encode("sp", Fe32::P, &keys[..65]).parse::<SilentPaymentCode>(),
+            Err(ParseSilentPaymentCodeError::InvalidLength(65))
+        );
+        assert_eq!(
+            encode("sp", Fe32::L, &keys).parse::<SilentPaymentCode>(),
+            Err(ParseSilentPaymentCodeError::UnsupportedVersion(31))
+        );
+        assert!(matches!(
+            encode("sp", Fe32::Q, &[0x04; 66]).parse::<SilentPaymentCode>(),
+            Err(ParseSilentPaymentCodeError::InvalidKey(_))
+        ));
+
+        let mut fes = keys.iter().copied().bytes_to_fes().collect::<Vec<_>>();
+        let last = fes.pop().unwrap();
+        fes.push(Fe32::try_from(last.to_u8() | 1).unwrap());
+        let s = fes
+            .into_iter()
+            .with_checksum::<Bech32m>(&HRP_MAINNET)
+            .with_witness_version(Fe32::Q)
+            .chars()
+            .collect::<String>();
+        assert_eq!(
+            s.parse::<SilentPaymentCode>(),
+            Err(ParseSilentPaymentCodeError::InvalidPadding)
+        );
+
+        let mut s = code.to_string();
+        s.pop();
+        s.push('q');
+        assert!(matches!(
+            s.parse::<SilentPaymentCode>(),
+            Err(ParseSilentPaymentCodeError::Bech32(_))
+        ));
+    }
+
+    #[test]
+    fn bip352_vector() {
+        // The "simple send: two inputs" test vector of BIP352.
+        let code = "sp1qqgste7k9hx0qftg6qmwlkqtwuy6cycyavzmzj85c6qdfhjdpdjtdgqjuexzk6murw56suy3e0rd2cgqvycxttddwsvgxe2usfpxumr70xc9pkqwv"
+            .parse::<SilentPaymentCode>()
+            .unwrap();
+        let outpoints = [
+            "f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16:0",
+            "a1075db55d416d3ca199f55b6084e2115b9345e16c5cf302fc80e9d5fbf5d48d:0",
+        ]
+        .map(|s| s.parse::<OutPoint>().unwrap());
+        let input_keys = [
+            "eadc78165ff1f8ea94ad7cfdc54990738a4c53f6e0507b42154201b8e5dff3b1",
+            "93f5ed907ad5b2bdbbdcb5d9116ebc0a4e1f92f910d5260237fa45a9408aad16",
+        ]
+        .map(|s| InputSecretKey::Ecdsa(Scalar::from_slice(&hex!(s)).unwrap()));
+
+        let outputs = create_outputs(&outpoints, &input_keys, &[code]).unwrap();
+        assert_eq!(
+            outputs[0].serialize()[..],
+            hex!("3e9fce73d4e77a4809908e3c3a2e54ee147b9312dc5044a193d1fc85de46e3c1")[..]
+        );
+
+        let scan_secret = Scalar::from_slice(&hex!(
+            "0f694e068028a717f8af6b9411f9a133dd3565258714cc226594b34db90c1f2c"
+        ))
+        .unwrap();
+        let spend_secret = Scalar::from_slice(&hex!(
+            "9d6ad855ce3417ef84e836892e5a56392bfba05fa5d97ccea30e266f540e08b3"
+        ))
+        .unwrap();
+        assert_eq!(scan_secret.base_point_mul(), code.scan_key());
+        assert_eq!(spend_secret.base_point_mul(), code.spend_key());
+
+        let secrets = [
+            "eadc78165ff1f8ea94ad7cfdc54990738a4c53f6e0507b42154201b8e5dff3b1",
+            "93f5ed907ad5b2bdbbdcb5d9116ebc0a4e1f92f910d5260237fa45a9408aad16",
+        ]
+        .map(|s| Scalar::from_slice(&hex!(s)).unwrap());
+        let (tx, prevouts) = transaction(
+            vec![p2pkh_input(outpoints[0], &secrets[0]), p2wpkh_input(outpoints[1], &secrets[1])],
+            &outputs,
+        );
+        let found = scan(&tx, &prevouts, &scan_secret, &code.spend_key(), &[]).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].vout, 0);
+        assert_eq!(found[0].label, None);
+        // The tweaked spend key spends the output.
+        let output_secret = (spend_secret + found[0].tweak).not_zero().unwrap();
+        assert_eq!(output_secret.base_point_mul().x_only_public_key().0, outputs[0]);
+    }
+
+    #[test]
+    fn create_and_scan() {
+        let scan_secret = secret(1);
+        let receiver = code(1, 2);
+        let labeled = receiver.with_label(&scan_secret, 7);
+        let other = code(3, 4);
+
+        let input_secrets = [secret(5), secret(6), secret(7), secret(8)];
+        let inputs = vec![
+            p2wpkh_input(outpoint(2), &input_secrets[0]),
+            p2tr_input(outpoint(1), &input_secrets[1]),
+            p2pkh_input(outpoint(3), &input_secrets[2]),
+            p2sh_p2wpkh_input(outpoint(4), &input_secrets[3]),
+        ];
+        let outpoints = inputs.iter().map(|(input, _)| input.previous_output).collect::<Vec<_>>();
+        let input_keys = [
+            InputSecretKey::Ecdsa(input_secrets[0]),
+            InputSecretKey::Taproot(input_secrets[1]),
+            InputSecretKey::Ecdsa(input_secrets[2]),
+            InputSecretKey::Ecdsa(input_secrets[3]),
+        ];
+        let recipients = [receiver, other, labeled, receiver];
+        let outputs = create_outputs(&outpoints, &input_keys, &recipients).unwrap();
+        assert_ne!(outputs[0], outputs[3]);
+
+        let (tx, prevouts) = transaction(inputs.clone(), &outputs);
+        let found = scan(&tx, &prevouts, &scan_secret, &receiver.spend_key(), &[]).unwrap();
+        // Without the label, the scan stops at the labeled output.
+        assert_eq!(found.iter().map(|output| output.vout).collect::<Vec<_>>(), [0]);
+        let found = scan(&tx, &prevouts, &scan_secret, &receiver.spend_key(), &[3, 7]).unwrap();
+        assert_eq!(found.iter().map(|output| output.vout).collect::<Vec<_>>(), [0, 2, 3]);
+        assert_eq!(
+            found.iter().map(|output| output.label).collect::<Vec<_>>(),
+            [None, Some(7), None]
+        );
+        for output in &found {
+            let output_secret = (secret(2) + output.tweak).not_zero().unwrap();
+            let key = output_secret.base_point_mul().x_only_public_key().0;
+            assert_eq!(key, outputs[output.vout as usize]);
+        }
+        let found = scan(&tx, &prevouts, &secret(3), &other.spend_key(), &[]).unwrap();
+        assert_eq!(found.iter().map(|output| output.vout).collect::<Vec<_>>(), [1]);
+
+        // Spending one of the inputs by script path with the NUMS internal key removes it.
+        let (mut tx, prevouts) = transaction(inputs.clone(), &outputs);
+        let mut control_block = vec![0xc0];
+        control_block.extend_from_slice(&NUMS_INTERNAL_KEY);
+        tx.input[1].witness = Witness::from_slice(&[&[0x51][..], &control_block]);
+        assert_eq!(scan(&tx, &prevouts, &scan_secret, &receiver.spend_key(), &[]), Ok(vec![]));
+
+        // Spending an output of a future segwit version skips the transaction.
+        let (tx, mut prevouts) = transaction(inputs, &outputs);
+        prevouts[0].script_pubkey = ScriptBuf::from_bytes([&[0x52, 0x20][..], &[1; 32]].concat());
+        assert_eq!(scan(&tx, &prevouts, &scan_secret, &receiver.spend_key(), &[]), Ok(vec![]));
+
+        assert_eq!(
+            scan(&tx, &prevouts[1..], &scan_secret, &receiver.spend_key(), &[]),
+            Err(SilentPaymentError::PrevoutsLength { inputs: 4, prevouts: 3 })
+        );
+    }
+
+    #[test]
+    fn create_errors() {
+        let recipients = [code(1, 2)];
+        let key = InputSecretKey::Ecdsa(secret(5));
+        assert_eq!(create_outputs(&[], &[key], &recipients), Err(SilentPaymentError::NoInputs));
+        assert_eq!(
+            create_outputs(&[outpoint(1)], &[], &recipients),
+            Err(SilentPaymentError::NoInputs)
+        );
+        let negated = InputSecretKey::Ecdsa(-secret(5));
+        assert_eq!(
+            create_outputs(&[outpoint(1)], &[key, negated], &recipients),
+            Err(SilentPaymentError::InputKeysCancel)
+        );
+    }
+}
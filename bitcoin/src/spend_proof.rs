@@ -0,0 +1,461 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Proofs that a UTXO is spendable, for auditors.
+//!
+//! An auditor sends the wallet a challenge and the outpoints it wants proven. For each of them,
+//! the wallet answers with a [`SpendProof`]: the witness which spends the UTXO, with its exact
+//! spend path, in a transaction committing to the challenge. The auditor looks the UTXO up on
+//! chain and checks the proof against its scriptPubKey with [`SpendProof::verify`].
+//!
+//! The transaction signed is built like the `to_sign` transaction of BIP322, with the UTXO as a
+//! second input:
+//!
+//! - Input 0 spends the BIP322 [`to_spend`] transaction committing to the challenge. This
+//!   transaction doesn't exist, so the proof can never be mined.
+//! - Input 1 spends the UTXO, its witness is the proof.
+//! - The only output is an empty `OP_RETURN`.
+//!
+//! Signatures must commit to all inputs, with `SIGHASH_ALL` or `SIGHASH_DEFAULT`, otherwise they
+//! would not commit to the challenge and could be reused in a real transaction.
+//!
+//! P2WPKH outputs, taproot key path spends and taproot script path spends of a single key
+//! `<key> OP_CHECKSIG` leaf can be proven.
+//!
+
+use core::fmt;
+
+use hashes::{sha256t_hash_newtype, Hash};
+use k256::ecdsa::signature::hazmat::PrehashVerifier;
+
+use crate::blockdata::locktime::absolute;
+use crate::blockdata::opcodes::all::{OP_CHECKSIG, OP_PUSHBYTES_0, OP_PUSHBYTES_32, OP_RETURN};
+use crate::blockdata::script::{Builder, Script, ScriptBuf};
+use crate::blockdata::transaction::{OutPoint, Sequence, Transaction, TxIn, TxOut, Version};
+use crate::blockdata::witness::Witness;
+use crate::common::types::Message;
+use crate::crypto::ecdsa;
+use crate::crypto::key::{CompressedPublicKey, Keypair, PrivateKey, TapTweak, XOnlyPublicKey};
+use crate::crypto::scalar::Scalar;
+use crate::crypto::schnorr::{self, Signature64};
+use crate::crypto::sighash::{EcdsaSighashType, Prevouts, SighashCache, TapSighashType};
+use crate::crypto::taproot;
+use crate::prelude::*;
+use crate::taproot::{ControlBlock, TapLeafHash, TapNodeHash};
+use crate::Amount;
+
+sha256t_hash_newtype! {
+    pub struct MessageTag = hash_str("BIP0322-signed-message");
+
+    /// Tagged hash with tag \"BIP0322-signed-message\".
+    ///
+    /// The hash of the message signed with BIP322, here the challenge of the auditor.
+    #[hash_newtype(forward)]
+    pub struct MessageHash(_);
+}
+
+/// Returns the BIP322 `to_spend` transaction committing to `challenge`, paying to
+/// `script_pubkey`.
+pub fn to_spend(challenge: &[u8], script_pubkey: &Script) -> Transaction {
+    let message_hash = MessageHash::hash(challenge);
+    Transaction {
+        version: Version::non_standard(0),
+        lock_time: absolute::LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint::null(),
+            script_sig: Builder::new()
+                .push_opcode(OP_PUSHBYTES_0)
+                .push_slice(message_hash.to_byte_array())
+                .into_script(),
+            sequence: Sequence::ZERO,
+            witness: Witness::new(),
+        }],
+        output: vec![TxOut { value: Amount::ZERO, script_pubkey: script_pubkey.to_owned() }],
+    }
+}
+
+/// Returns the transaction signed to prove that `utxo`, at `outpoint`, is spendable.
+///
+/// Input 1 spends the UTXO, the witness of a [`SpendProof`] is the witness of this input. Any
+/// signer can produce it, the prevouts to sign with are returned by [`prevouts`].
+pub fn to_sign(challenge: &[u8], outpoint: OutPoint, utxo: &TxOut) -> Transaction {
+    let input = |previous_output| TxIn {
+        previous_output,
+        script_sig: ScriptBuf::new(),
+        sequence: Sequence::ZERO,
+        witness: Witness::new(),
+    };
+    let to_spend = to_spend(challenge, &utxo.script_pubkey);
+    Transaction {
+        version: Version::non_standard(0),
+        lock_time: absolute::LockTime::ZERO,
+        input: vec![input(OutPoint::new(to_spend.compute_txid(), 0)), input(outpoint)],
+        output: vec![TxOut {
+            value: Amount::ZERO,
+            script_pubkey: Builder::new().push_opcode(OP_RETURN).into_script(),
+        }],
+    }
+}
+
+/// Returns the outputs spent by the inputs of [`to_sign`].
+pub fn prevouts(utxo: &TxOut) -> [TxOut; 2] {
+    [TxOut { value: Amount::ZERO, script_pubkey: utxo.script_pubkey.clone() }, utxo.clone()]
+}
+
+/// How a proven UTXO is spent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpendPath {
+    /// A P2WPKH output.
+    WitnessPublicKeyHash,
+    /// The key path of a taproot output.
+    TaprootKeyPath,
+    /// The script path of a taproot output, through the leaf with this hash.
+    TaprootScriptPath(TapLeafHash),
+}
+
+/// A proof that the UTXO at `outpoint` is spendable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpendProof {
+    /// The proven UTXO.
+    pub outpoint: OutPoint,
+    /// The witness spending the UTXO in the [`to_sign`] transaction.
+    pub witness: Witness,
+}
+
+crate::internal_macros::impl_consensus_encoding!(SpendProof, outpoint, witness);
+
+impl SpendProof {
+    /// Proves that the P2WPKH `utxo` at `outpoint` is spendable with `secret`.
+    pub fn sign_p2wpkh(
+        challenge: &[u8],
+        outpoint: OutPoint,
+        utxo: &TxOut,
+        secret: &PrivateKey,
+    ) -> Result<Self, SpendProofError> {
+        let tx = to_sign(challenge, outpoint, utxo);
+        let sighash = SighashCache::new(&tx)
+            .p2wpkh_signature_hash(1, &utxo.script_pubkey, utxo.value, EcdsaSighashType::All)
+            .map_err(|_| SpendProofError::UnsupportedScript)?;
+        let msg = Message::from_digest(sighash.to_byte_array());
+        let signature = ecdsa::Signature::sighash_all(ecdsa::sign_ecdsa_low_r(
+            &msg,
+            &Scalar::from(&secret.inner),
+        ));
+        let witness = Witness::p2wpkh(&signature, &secret.public_key());
+        SpendProof { outpoint, witness }.verified(challenge, utxo)
+    }
+
+    /// Proves that the taproot `utxo` at `outpoint`, with the script tree `merkle_root`, is
+    /// spendable through its key path with the internal key `keypair`.
+    pub fn sign_key_path(
+        challenge: &[u8],
+        outpoint: OutPoint,
+        utxo: &TxOut,
+        keypair: &Keypair,
+        merkle_root: Option<TapNodeHash>,
+    ) -> Result<Self, SpendProofError> {
+        let sighash = taproot_sighash(challenge, outpoint, utxo, None)?;
+        let keypair = keypair.clone().tap_tweak(merkle_root).to_inner();
+        let signature = schnorr::sign_schnorr(&sighash, &keypair, &[0; 32]);
+        let witness = Witness::from_slice(&[signature.as_byte_array()]);
+        SpendProof { outpoint, witness }.verified(challenge, utxo)
+    }
+
+    /// Proves that the taproot `utxo` at `outpoint` is spendable through its script path with the
+    /// `<key> OP_CHECKSIG` leaf `leaf_script` of `keypair`.
+    pub fn sign_script_path(
+        challenge: &[u8],
+        outpoint: OutPoint,
+        utxo: &TxOut,
+        keypair: &Keypair,
+        leaf_script: &Script,
+        control_block: &ControlBlock,
+    ) -> Result<Self, SpendProofError> {
+        let leaf_hash = TapLeafHash::from_script(leaf_script, control_block.leaf_version);
+        let sighash = taproot_sighash(challenge, outpoint, utxo, Some(leaf_hash))?;
+        let signature = schnorr::sign_schnorr(&sighash, keypair, &[0; 32]);
+        let witness = Witness::from_slice(&[
+            &signature.as_byte_array()[..],
+            leaf_script.as_bytes(),
+            &control_block.serialize(),
+        ]);
+        SpendProof { outpoint, witness }.verified(challenge, utxo)
+    }
+
+    /// Checks the proof against `challenge` and the `utxo` at its outpoint, as found on chain.
+    ///
+    /// Returns how the UTXO is spent.
+    pub fn verify(&self, challenge: &[u8], utxo: &TxOut) -> Result<SpendPath, SpendProofError> {
+        let script_pubkey = &utxo.script_pubkey;
+        if script_pubkey.is_p2wpkh() {
+            let (signature, pubkey) =
+                match (self.witness.len(), self.witness.nth(0), self.witness.nth(1)) {
+                    (2, Some(signature), Some(pubkey)) if pubkey.len() == 33 => (signature, pubkey),
+                    _ => return Err(SpendProofError::InvalidWitness),
+                };
+            let pubkey = CompressedPublicKey::from_slice(pubkey)
+                .map_err(|_| SpendProofError::InvalidWitness)?;
+            if ScriptBuf::new_p2wpkh(&pubkey.wpubkey_hash()) != *script_pubkey {
+                return Err(SpendProofError::KeyMismatch);
+            }
+            let signature = ecdsa::Signature::from_slice(signature)
+                .map_err(|_| SpendProofError::InvalidWitness)?;
+            if signature.sighash_type != EcdsaSighashType::All {
+                return Err(SpendProofError::SighashType);
+            }
+
+            let tx = to_sign(challenge, self.outpoint, utxo);
+            let sighash = SighashCache::new(&tx)
+                .p2wpkh_signature_hash(1, script_pubkey, utxo.value, EcdsaSighashType::All)
+                .expect("the script is P2WPKH");
+            k256::ecdsa::VerifyingKey::from(pubkey.0)
+                .verify_prehash(sighash.as_byte_array(), &signature.signature)
+                .map_err(|_| SpendProofError::InvalidSignature)?;
+            Ok(SpendPath::WitnessPublicKeyHash)
+        } else if script_pubkey.is_p2tr() {
+            let output_key = XOnlyPublicKey::from_slice(&script_pubkey.as_bytes()[2..])
+                .map_err(|_| SpendProofError::KeyMismatch)?;
+            let (signature, key, path) = match self.witness.len() {
+                1 => (&self.witness[0], output_key, SpendPath::TaprootKeyPath),
+                3 => {
+                    let leaf_script = Script::from_bytes(&self.witness[1]);
+                    let control_block = ControlBlock::decode(&self.witness[2])
+                        .map_err(|_| SpendProofError::InvalidWitness)?;
+                    if !control_block.verify_taproot_commitment(output_key, leaf_script) {
+                        return Err(SpendProofError::KeyMismatch);
+                    }
+                    let key =
+                        single_key_leaf(leaf_script).ok_or(SpendProofError::UnsupportedScript)?;
+                    let leaf_hash =
+                        TapLeafHash::from_script(leaf_script, control_block.leaf_version);
+                    (&self.witness[0], key, SpendPath::TaprootScriptPath(leaf_hash))
+                }
+                _ => return Err(SpendProofError::InvalidWitness),
+            };
+            let signature = taproot::Signature::from_slice(signature)
+                .map_err(|_| SpendProofError::InvalidWitness)?;
+            if !matches!(signature.sighash_type, TapSighashType::Default | TapSighashType::All) {
+                return Err(SpendProofError::SighashType);
+            }
+
+            let leaf_hash = match path {
+                SpendPath::TaprootScriptPath(leaf_hash) => Some(leaf_hash),
+                _ => None,
+            };
+            let mut tx = to_sign(challenge, self.outpoint, utxo);
+            let sighash = taproot_sighash_of(&mut tx, utxo, leaf_hash, signature.sighash_type);
+            schnorr::verify_schnorr(&Signature64::from(signature.signature), &sighash, &key)
+                .map_err(|_| SpendProofError::InvalidSignature)?;
+            Ok(path)
+        } else {
+            Err(SpendProofError::UnsupportedScript)
+        }
+    }
+
+    /// Returns the proof if it verifies, catching keys which don't match the UTXO.
+    fn verified(self, challenge: &[u8], utxo: &TxOut) -> Result<Self, SpendProofError> {
+        self.verify(challenge, utxo).map(|_| self)
+    }
+}
+
+/// Returns the `SIGHASH_DEFAULT` taproot sighash of the UTXO input of [`to_sign`].
+fn taproot_sighash(
+    challenge: &[u8],
+    outpoint: OutPoint,
+    utxo: &TxOut,
+    leaf_hash: Option<TapLeafHash>,
+) -> Result<Message, SpendProofError> {
+    if !utxo.script_pubkey.is_p2tr() {
+        return Err(SpendProofError::UnsupportedScript);
+    }
+    let mut tx = to_sign(challenge, outpoint, utxo);
+    Ok(taproot_sighash_of(&mut tx, utxo, leaf_hash, TapSighashType::Default))
+}
+
+fn taproot_sighash_of(
+    tx: &mut Transaction,
+    utxo: &TxOut,
+    leaf_hash: Option<TapLeafHash>,
+    sighash_type: TapSighashType,
+) -> Message {
+    let prevouts = prevouts(utxo);
+    let prevouts = Prevouts::All(&prevouts);
+    let mut cache = SighashCache::new(tx);
+    let sighash = match leaf_hash {
+        None => cache.taproot_key_spend_signature_hash(1, &prevouts, sighash_type),
+        Some(leaf_hash) =>
+            cache.taproot_script_spend_signature_hash(1, &prevouts, leaf_hash, sighash_type),
+    };
+    Message::from_digest(sighash.expect("all prevouts are given").to_byte_array())
+}
+
+/// Returns the key of a `<key> OP_CHECKSIG` leaf script.
+fn single_key_leaf(script: &Script) -> Option<XOnlyPublicKey> {
+    match script.as_bytes() {
+        [push, key @ .., checksig]
+            if *push == OP_PUSHBYTES_32.to_u8() && *checksig == OP_CHECKSIG.to_u8() =>
+            XOnlyPublicKey::from_slice(key).ok(),
+        _ => None,
+    }
+}
+
+/// An error proving or verifying that a UTXO is spendable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SpendProofError {
+    /// The scriptPubKey or leaf script of the UTXO can't be proven.
+    UnsupportedScript,
+    /// The witness is malformed for the scriptPubKey of the UTXO.
+    InvalidWitness,
+    /// The key or leaf script in the witness doesn't match the scriptPubKey of the UTXO.
+    KeyMismatch,
+    /// The signature doesn't commit to all inputs, and so not to the challenge.
+    SighashType,
+    /// The signature is invalid.
+    InvalidSignature,
+}
+
+internals::impl_from_infallible!(SpendProofError);
+
+impl fmt::Display for SpendProofError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use SpendProofError::*;
+
+        match *self {
+            UnsupportedScript => f.write_str("unsupported script for a spend proof"),
+            InvalidWitness => f.write_str("malformed spend proof witness"),
+            KeyMismatch => f.write_str("the spend proof doesn't match the scriptPubKey"),
+            SighashType => f.write_str("the spend proof signature doesn't commit to all inputs"),
+            InvalidSignature => f.write_str("invalid spend proof signature"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SpendProofError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use SpendProofError::*;
+
+        match *self {
+            UnsupportedScript | InvalidWitness | KeyMismatch | SighashType | InvalidSignature =>
+                None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hex::test_hex_unwrap as hex;
+
+    use super::*;
+    use crate::consensus::{deserialize, serialize};
+    use crate::taproot::{LeafVersion, TaprootBuilder};
+    use crate::NetworkKind;
+
+    const CHALLENGE: &[u8] = b"audit 2026-10-17";
+
+    fn outpoint() -> OutPoint { OutPoint::new(Hash::hash(b"utxo"), 1) }
+
+    // https://github.com/bitcoin/bips/blob/master/bip-0322.mediawiki#message-hashing
+    #[test]
+    fn message_hash() {
+        assert_eq!(
+            MessageHash::hash(b"").to_byte_array()[..],
+            hex!("c90c269c4f8fcbe6880f72a721ddfbf1914268a794cbb21cfafee13770ae19f1")[..]
+        );
+        assert_eq!(
+            MessageHash::hash(b"Hello World").to_byte_array()[..],
+            hex!("f0eb03b1a75ac6d9847f55c624a99169b5dccba2a31f5b23bea77ba270de0a7a")[..]
+        );
+    }
+
+    #[test]
+    fn p2wpkh() {
+        let secret = PrivateKey::from_slice(&[0x11; 32], NetworkKind::Main).unwrap();
+        let wpkh = secret.public_key().wpubkey_hash().unwrap();
+        let utxo =
+            TxOut { value: Amount::from_sat(50_000), script_pubkey: ScriptBuf::new_p2wpkh(&wpkh) };
+
+        let proof = SpendProof::sign_p2wpkh(CHALLENGE, outpoint(), &utxo, &secret).unwrap();
+        assert_eq!(proof.verify(CHALLENGE, &utxo), Ok(SpendPath::WitnessPublicKeyHash));
+        assert_eq!(deserialize::<SpendProof>(&serialize(&proof)).unwrap(), proof);
+
+        // The proof commits to the challenge, the outpoint and the amount.
+        assert_eq!(proof.verify(b"another audit", &utxo), Err(SpendProofError::InvalidSignature));
+        let moved = SpendProof { outpoint: OutPoint::new(outpoint().txid, 0), ..proof.clone() };
+        assert_eq!(moved.verify(CHALLENGE, &utxo), Err(SpendProofError::InvalidSignature));
+        let other = TxOut { value: Amount::from_sat(50_001), ..utxo.clone() };
+        assert_eq!(proof.verify(CHALLENGE, &other), Err(SpendProofError::InvalidSignature));
+
+        let wrong_key = PrivateKey::from_slice(&[0x22; 32], NetworkKind::Main).unwrap();
+        assert_eq!(
+            SpendProof::sign_p2wpkh(CHALLENGE, outpoint(), &utxo, &wrong_key),
+            Err(SpendProofError::KeyMismatch)
+        );
+    }
+
+    #[test]
+    fn taproot() {
+        let internal = Keypair::from_seckey_slice(&[0x33; 32]).unwrap();
+        let leaf_key = Keypair::from_seckey_slice(&[0x44; 32]).unwrap();
+        let leaf_script = Builder::new()
+            .push_x_only_key(&leaf_key.x_only_public_key().0)
+            .push_opcode(OP_CHECKSIG)
+            .into_script();
+        let spend_info = TaprootBuilder::new()
+            .add_leaf(0, leaf_script.clone())
+            .unwrap()
+            .finalize(internal.x_only_public_key().0)
+            .unwrap();
+        let utxo = TxOut {
+            value: Amount::from_sat(100_000),
+            script_pubkey: ScriptBuf::new_p2tr(
+                internal.x_only_public_key().0,
+                spend_info.merkle_root(),
+            ),
+        };
+
+        let proof = SpendProof::sign_key_path(
+            CHALLENGE,
+            outpoint(),
+            &utxo,
+            &internal,
+            spend_info.merkle_root(),
+        )
+        .unwrap();
+        assert_eq!(proof.verify(CHALLENGE, &utxo), Ok(SpendPath::TaprootKeyPath));
+        assert_eq!(proof.verify(b"another audit", &utxo), Err(SpendProofError::InvalidSignature));
+        // Without the script tree, the key doesn't match the output key.
+        assert_eq!(
+            SpendProof::sign_key_path(CHALLENGE, outpoint(), &utxo, &internal, None),
+            Err(SpendProofError::InvalidSignature)
+        );
+
+        let control_block =
+            spend_info.control_block(&(leaf_script.clone(), LeafVersion::TapScript)).unwrap();
+        let proof = SpendProof::sign_script_path(
+            CHALLENGE,
+            outpoint(),
+            &utxo,
+            &leaf_key,
+            &leaf_script,
+            &control_block,
+        )
+        .unwrap();
+        let leaf_hash = TapLeafHash::from_script(&leaf_script, LeafVersion::TapScript);
+        assert_eq!(proof.verify(CHALLENGE, &utxo), Ok(SpendPath::TaprootScriptPath(leaf_hash)));
+
+        // A signature which doesn't commit to the challenge input.
+        let mut witness = proof.witness.to_vec();
+        witness[0].push(TapSighashType::AllPlusAnyoneCanPay as u8);
+        let weak = SpendProof { witness: Witness::from_slice(&witness), ..proof };
+        assert_eq!(weak.verify(CHALLENGE, &utxo), Err(SpendProofError::SighashType));
+    }
+
+    #[test]
+    fn unsupported() {
+        let utxo =
+            TxOut { value: Amount::ZERO, script_pubkey: ScriptBuf::new_p2sh(&Hash::all_zeros()) };
+        let proof = SpendProof { outpoint: outpoint(), witness: Witness::new() };
+        assert_eq!(proof.verify(CHALLENGE, &utxo), Err(SpendProofError::UnsupportedScript));
+    }
+}
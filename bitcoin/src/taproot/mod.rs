@@ -6,6 +6,7 @@
 //!
 
 pub mod merkle_branch;
+pub mod policy;
 pub mod serialized_signature;
 
 use core::cmp::Reverse;
@@ -17,8 +18,11 @@ use internals::write_err;
 use io::Write;
 use k256::{NonZeroScalar, Secp256k1};
 
+use crate::blockdata::script::limits::{self, LimitError, ScriptContext};
 use crate::consensus::Encodable;
-use crate::crypto::key::{TapTweak, TweakedPublicKey, UntweakedPublicKey, XOnlyPublicKey};
+use crate::crypto::key::{
+    taproot_tweak_pubkey, TapTweak, TweakedPublicKey, UntweakedPublicKey, XOnlyPublicKey,
+};
 use crate::{prelude::*, Scalar};
 use crate::{CryptoError, Parity};
 use crate::{Script, ScriptBuf};
@@ -469,13 +473,18 @@ impl TaprootBuilder {
     }
 
     /// Adds a leaf script at `depth` to the builder with script version `ver`. Errors if the leaves
-    /// are not provided in DFS walk order. The depth of the root node is 0.
+    /// are not provided in DFS walk order, or if a tapscript breaks the consensus limits and could
+    /// never be spent. The depth of the root node is 0.
     pub fn add_leaf_with_ver(
         self,
         depth: u8,
         script: ScriptBuf,
         ver: LeafVersion,
     ) -> Result<Self, TaprootBuilderError> {
+        if ver == LeafVersion::TapScript {
+            limits::check_script(&script, ScriptContext::Tapscript)
+                .map_err(TaprootBuilderError::UnspendableLeaf)?;
+        }
         let leaf = NodeInfo::new_leaf_with_ver(script, ver);
         self.insert(leaf, depth)
     }
@@ -715,6 +724,47 @@ impl std::error::Error for HiddenNodesError {
     }
 }
 
+/// Error happening when a [`TapTree`] is reconstructed from its leaves.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TapTreeError {
+    /// A leaf is too deep, out of DFS walk order or completes the tree more than once.
+    InvalidLeaf(TaprootBuilderError),
+    /// The leaves do not make a complete tree, or there are none.
+    IncompleteTree,
+}
+
+internals::impl_from_infallible!(TapTreeError);
+
+impl fmt::Display for TapTreeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use TapTreeError::*;
+
+        match *self {
+            InvalidLeaf(ref e) => write_err!(f, "invalid tap tree leaf"; e),
+            IncompleteTree => f.write_str("the leaves do not make a complete tap tree"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TapTreeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use TapTreeError::*;
+
+        match self {
+            InvalidLeaf(e) => Some(e),
+            IncompleteTree => None,
+        }
+    }
+}
+
+impl From<TaprootBuilderError> for TapTreeError {
+    fn from(e: TaprootBuilderError) -> Self {
+        Self::InvalidLeaf(e)
+    }
+}
+
 /// Taproot Tree representing a complete binary tree without any hidden nodes.
 ///
 /// This is in contrast to [`NodeInfo`], which allows hidden nodes.
@@ -737,6 +787,35 @@ impl From<TapTree> for NodeInfo {
 }
 
 impl TapTree {
+    /// Reconstructs a tree from its `(depth, leaf_version, script)` leaves in DFS walk order.
+    ///
+    /// This is the form in which PSBTs (BIP371) carry trees, and [`TapTree::script_leaves`]
+    /// yields the leaves of a tree in an order accepted here.
+    ///
+    /// # Errors
+    ///
+    /// [`TapTreeError::InvalidLeaf`] as soon as a leaf can't be added, and
+    /// [`TapTreeError::IncompleteTree`] if the leaves don't make a complete tree.
+    pub fn from_leaves<I>(leaves: I) -> Result<TapTree, TapTreeError>
+    where
+        I: IntoIterator<Item = (u8, LeafVersion, ScriptBuf)>,
+    {
+        let mut builder = TaprootBuilder::new();
+        for (depth, version, script) in leaves {
+            builder = builder.add_leaf_with_ver(depth, script, version)?;
+        }
+        builder
+            .try_into_taptree()
+            .map_err(|_| TapTreeError::IncompleteTree)
+    }
+
+    /// Converts the tree into a finalizable [`TaprootBuilder`].
+    pub fn into_builder(self) -> TaprootBuilder {
+        TaprootBuilder {
+            branch: vec![Some(self.0)],
+        }
+    }
+
     /// Gets the reference to inner [`NodeInfo`] of this tree root.
     pub fn node_info(&self) -> &NodeInfo {
         &self.0
@@ -775,6 +854,13 @@ impl TryFrom<TaprootBuilder> for TapTree {
     }
 }
 
+impl From<TapTree> for TaprootBuilder {
+    #[inline]
+    fn from(tree: TapTree) -> Self {
+        tree.into_builder()
+    }
+}
+
 impl TryFrom<NodeInfo> for TapTree {
     type Error = HiddenNodesError;
 
@@ -1161,6 +1247,22 @@ impl<'leaf> ScriptLeaf<'leaf> {
         self.merkle_branch
     }
 
+    /// Obtains the depth of the leaf in the tree, the root being at depth 0.
+    pub fn depth(&self) -> u8 {
+        // A merkle branch has at most 128 nodes.
+        self.merkle_branch.len() as u8
+    }
+
+    /// Computes the [`TapLeafHash`] of the leaf, committing to its script and version.
+    pub fn leaf_hash(&self) -> TapLeafHash {
+        TapLeafHash::from_script(self.script, self.version)
+    }
+
+    /// Computes the [`TapNodeHash`] of the leaf, the hash the merkle branch starts from.
+    pub fn node_hash(&self) -> TapNodeHash {
+        TapNodeHash::from(self.leaf_hash())
+    }
+
     /// Obtains a script leaf from the leaf node if the leaf is not hidden.
     pub fn from_leaf_node(leaf_node: &'leaf LeafNode) -> Option<Self> {
         let (script, ver) = leaf_node.leaf.as_script()?;
@@ -1267,15 +1369,32 @@ impl ControlBlock {
             // Recalculate the curr hash as parent hash
             curr_hash = TapNodeHash::from_node_hashes(curr_hash, *elem);
         }
-        // compute the taptweak
-        let tweak =
-            TapTweakHash::from_key_and_tweak(self.internal_key, Some(curr_hash)).to_scalar();
-        self.internal_key
-            .tweak_add_check(output_key.clone(), self.output_key_parity, tweak)
-            .expect("checking tweaked add should not fail")
+        // compute the output key, its parity must match the control block
+        match taproot_tweak_pubkey(self.internal_key, Some(curr_hash)) {
+            Ok((key, parity)) => key.to_inner() == output_key && parity == self.output_key_parity,
+            Err(_) => false,
+        }
     }
 }
 
+/// Verifies that `control_block`, a serialized [`ControlBlock`], proves that `script` is committed
+/// to by `output_key`.
+///
+/// With [`Witness::tapscript`](crate::Witness::tapscript) and
+/// [`Witness::taproot_control_block`](crate::Witness::taproot_control_block), this checks the
+/// commitment of a script path spend. Full verification must also execute the script.
+///
+/// # Returns
+///
+/// Whether the commitment holds, or an error if the control block is malformed.
+pub fn verify_taproot_commitment(
+    output_key: XOnlyPublicKey,
+    script: &Script,
+    control_block: &[u8],
+) -> Result<bool, TaprootError> {
+    Ok(ControlBlock::decode(control_block)?.verify_taproot_commitment(output_key, script))
+}
+
 /// Inner type representing future (non-tapscript) leaf versions. See [`LeafVersion::Future`].
 ///
 /// NB: NO PUBLIC CONSTRUCTOR!
@@ -1447,6 +1566,8 @@ pub enum TaprootBuilderError {
     InvalidInternalKey(CryptoError),
     /// Called finalize on a empty tree.
     EmptyTree,
+    /// A tapscript leaf breaks the consensus limits.
+    UnspendableLeaf(LimitError),
 }
 
 internals::impl_from_infallible!(TaprootBuilderError);
@@ -1477,6 +1598,7 @@ impl fmt::Display for TaprootBuilderError {
             EmptyTree => {
                 write!(f, "Called finalize on an empty tree")
             }
+            UnspendableLeaf(ref e) => write_err!(f, "unspendable tapscript leaf"; e),
         }
     }
 }
@@ -1488,6 +1610,7 @@ impl std::error::Error for TaprootBuilderError {
 
         match self {
             InvalidInternalKey(e) => Some(e),
+            UnspendableLeaf(e) => Some(e),
             InvalidMerkleTreeDepth(_) | NodeNotInDfsOrder | OverCompleteTree | EmptyTree => None,
         }
     }
@@ -1573,7 +1696,7 @@ mod test {
     use hex::FromHex;
 
     use super::*;
-    use crate::sighash::{TapSighash, TapSighashTag};
+    use crate::sighash::{ScriptPath, TapSighash, TapSighashTag};
     use crate::{Address, KnownHrp};
     extern crate serde_json;
 
@@ -1683,6 +1806,36 @@ mod test {
         );
     }
 
+    #[test]
+    fn leaf_hashes() {
+        let future = LeafVersion::from_consensus(0xc2).unwrap();
+        let tree = TaprootBuilder::new()
+            .add_leaf(1, ScriptBuf::from_hex("51").unwrap())
+            .unwrap()
+            .add_leaf_with_ver(1, ScriptBuf::from_hex("51").unwrap(), future)
+            .unwrap()
+            .try_into_taptree()
+            .unwrap();
+
+        let leaves = tree.script_leaves().collect::<Vec<_>>();
+        assert_ne!(leaves[0].leaf_hash(), leaves[1].leaf_hash());
+        for leaf in leaves {
+            let leaf_hash = TapLeafHash::from_script(leaf.script(), leaf.version());
+            assert_eq!(leaf.leaf_hash(), leaf_hash);
+            assert_eq!(
+                ScriptPath::new(leaf.script(), leaf.version()).leaf_hash(),
+                leaf_hash
+            );
+            let root = leaf
+                .merkle_branch()
+                .iter()
+                .fold(leaf.node_hash(), |node, sibling| {
+                    TapNodeHash::from_node_hashes(node, *sibling)
+                });
+            assert_eq!(root, tree.root_hash());
+        }
+    }
+
     fn _verify_tap_commitments(out_spk_hex: &str, script_hex: &str, control_block_hex: &str) {
         let out_pk = XOnlyPublicKey::from_str(&out_spk_hex[4..]).unwrap();
         let out_pk = TweakedPublicKey::dangerous_assume_tweaked(out_pk);
@@ -1743,6 +1896,60 @@ mod test {
         _verify_tap_commitments("512093c7378d96518a75448821c4f7c8f4bae7ce60f804d03d1f0628dd5dd0f5de51", "04ffffffff203455139bf238a3067bd72ed77e0ab8db590330f55ed58dba7366b53bf4734279ba04feffffff87ab", "c1a0eb12e60a52614986c623cbb6621dcdba3a47e3be6b37e032b7a11c7b98f400c9a5cd1f6c8a81f5648e39f9810591df1c9a8f1fe97c92e03ecd7c0c016c951983e05473c6e8238cb4c780ea2ce62552b2a3eee068ceffc00517cd7b97e10dad");
     }
 
+    #[test]
+    fn verify_script_path_witness() {
+        let internal_key = UntweakedPublicKey::from_str(
+            "93c7378d96518a75448821c4f7c8f4bae7ce60f804d03d1f0628dd5dd0f5de51",
+        )
+        .unwrap();
+        let scripts = [
+            ScriptBuf::from_hex("51").unwrap(),
+            ScriptBuf::from_hex("52").unwrap(),
+        ];
+        let spend_info = TaprootSpendInfo::with_huffman_tree(
+            internal_key,
+            scripts.iter().map(|script| (1, script.clone())),
+        )
+        .unwrap();
+        let output_key = spend_info.output_key().to_inner();
+
+        let script = &scripts[1];
+        let control_block = spend_info
+            .control_block(&(script.clone(), LeafVersion::TapScript))
+            .unwrap();
+        let mut witness = crate::Witness::from_slice(&[
+            &[0x01][..],
+            script.as_bytes(),
+            &control_block.serialize(),
+        ]);
+        for annex in [false, true] {
+            if annex {
+                witness.push([TAPROOT_ANNEX_PREFIX, 0x00]);
+            }
+            let witness_script = witness.tapscript().unwrap();
+            let witness_control_block = witness.taproot_control_block().unwrap();
+            assert_eq!(witness_script, script.as_script());
+            assert_eq!(
+                verify_taproot_commitment(output_key, witness_script, witness_control_block),
+                Ok(true)
+            );
+            assert_eq!(
+                verify_taproot_commitment(output_key, &scripts[0], witness_control_block),
+                Ok(false)
+            );
+        }
+
+        assert_eq!(
+            verify_taproot_commitment(output_key, script, &[0xc0; 34]),
+            Err(TaprootError::InvalidControlBlockSize(34))
+        );
+        // A key path spend has no control block.
+        assert_eq!(
+            crate::Witness::from_slice(&[[0u8; 64]]).taproot_control_block(),
+            None
+        );
+    }
+
     #[test]
     fn build_huffman_tree() {
         let internal_key = UntweakedPublicKey::from_str(
@@ -1791,7 +1998,18 @@ mod test {
         for (_weights, script) in script_weights {
             let ver_script = (script, LeafVersion::TapScript);
             let ctrl_block = tree_info.control_block(&ver_script).unwrap();
-            assert!(ctrl_block.verify_taproot_commitment(output_key.to_inner(), &ver_script.0))
+            assert!(ctrl_block.verify_taproot_commitment(output_key.to_inner(), &ver_script.0));
+
+            // The control block doesn't prove another script, or the same one with the wrong
+            // output key parity or internal key.
+            let other = ScriptBuf::from_hex("56").unwrap();
+            assert!(!ctrl_block.verify_taproot_commitment(output_key.to_inner(), &other));
+            let mut wrong = ctrl_block.clone();
+            wrong.output_key_parity = wrong.output_key_parity ^ Parity::Odd;
+            assert!(!wrong.verify_taproot_commitment(output_key.to_inner(), &ver_script.0));
+            let mut wrong = ctrl_block;
+            wrong.internal_key = output_key.to_inner();
+            assert!(!wrong.verify_taproot_commitment(output_key.to_inner(), &ver_script.0));
         }
     }
 
@@ -1865,6 +2083,73 @@ mod test {
         }
     }
 
+    #[test]
+    fn taptree_from_leaves() {
+        let builder = TaprootBuilder::new()
+            .add_leaf(1, ScriptBuf::from_hex("51").unwrap())
+            .unwrap()
+            .add_leaf(2, ScriptBuf::from_hex("52").unwrap())
+            .unwrap()
+            .add_leaf_with_ver(
+                2,
+                ScriptBuf::from_hex("53").unwrap(),
+                LeafVersion::from_consensus(0xc2).unwrap(),
+            )
+            .unwrap();
+        let tree = TapTree::try_from(builder).unwrap();
+
+        let leaves = tree
+            .script_leaves()
+            .map(|l| (l.depth(), l.version(), l.script().to_owned()))
+            .collect::<Vec<_>>();
+        // Leaves are ordered by hash, the depth 1 leaf comes last.
+        assert_eq!(leaves.iter().map(|l| l.0).collect::<Vec<_>>(), [2, 2, 1]);
+        assert_eq!(TapTree::from_leaves(leaves.clone()), Ok(tree.clone()));
+        assert_eq!(
+            TapTree::from_leaves(leaves[..2].to_vec()),
+            Err(TapTreeError::IncompleteTree)
+        );
+        assert_eq!(
+            TapTree::from_leaves(leaves[1..].to_vec()),
+            Err(TapTreeError::InvalidLeaf(
+                TaprootBuilderError::NodeNotInDfsOrder
+            ))
+        );
+        assert_eq!(
+            TapTree::from_leaves(vec![]),
+            Err(TapTreeError::IncompleteTree)
+        );
+        let mut over_complete = leaves.clone();
+        over_complete.push((
+            0,
+            LeafVersion::TapScript,
+            ScriptBuf::from_hex("54").unwrap(),
+        ));
+        assert_eq!(
+            TapTree::from_leaves(over_complete),
+            Err(TapTreeError::InvalidLeaf(
+                TaprootBuilderError::OverCompleteTree
+            ))
+        );
+
+        // The builder of a tree is finalized and commits to the same root.
+        let builder = TaprootBuilder::from(tree.clone());
+        assert!(builder.is_finalizable());
+        assert_eq!(TapTree::try_from(builder), Ok(tree.clone()));
+
+        #[cfg(feature = "serde")]
+        {
+            let json = serde_json::to_value(&tree).unwrap();
+            assert_eq!(
+                serde_json::from_value::<TapTree>(json.clone()).unwrap(),
+                tree
+            );
+            // The first two leaves don't make a complete tree.
+            let incomplete = serde_json::Value::Array(json.as_array().unwrap()[..4].to_vec());
+            assert!(serde_json::from_value::<TapTree>(incomplete).is_err());
+        }
+    }
+
     #[test]
     #[cfg(feature = "serde")]
     fn test_leaf_version_serde() {
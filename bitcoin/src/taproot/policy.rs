@@ -0,0 +1,918 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Taproot policy compiler.
+//!
+//! This module compiles spending policies written in a small policy language into a taproot
+//! output. The language supports the following fragments:
+//!
+//! * `pk(KEY)`: a signature by the 32 byte hex x-only public key `KEY`.
+//! * `after(N)`: the absolute timelock `N` has expired.
+//! * `older(N)`: the relative timelock `N` has expired.
+//! * `and(X,Y,...)`: all of the sub-policies are satisfied.
+//! * `or(X,Y,...)`: any of the sub-policies is satisfied. Each sub-policy can be prefixed with a
+//!   relative probability of being used, e.g. `or(9@pk(A),1@pk(B))`. The default is `1`.
+//! * `thresh(K,X,Y,...)`: at least `K` of the sub-policies are satisfied.
+//!
+//! Compiling a policy rewrites it as a list of alternative spending conditions, each with the
+//! probability of being used. The most likely single-key condition becomes the key path, every
+//! other condition becomes a tapscript leaf, and the leaves are arranged in a Huffman tree so
+//! that likely conditions are cheap to spend. Thresholds of keys compile into a single
+//! `OP_CHECKSIGADD` leaf.
+//!
+//! [`Policy::analyze`] reports the resulting spend paths along with their timelocks, witness
+//! weights and warnings, e.g. about paths that can be spent without a signature.
+//!
+
+use core::fmt;
+use core::str::FromStr;
+
+use internals::write_err;
+
+use super::{LeafVersion, TapLeafHash, TaprootBuilderError, TaprootSpendInfo};
+use crate::blockdata::locktime::absolute;
+use crate::blockdata::opcodes::all::*;
+use crate::blockdata::script::{Builder, ScriptBuf};
+use crate::blockdata::transaction::Sequence;
+use crate::blockdata::weight::Weight;
+use crate::consensus::encode::VarInt;
+use crate::crypto::key::XOnlyPublicKey;
+use crate::prelude::*;
+
+/// The maximum number of spending conditions a policy may expand to.
+pub const MAX_CONDITIONS: usize = 1000;
+
+/// The size of a BIP340 signature with the default sighash type.
+const SCHNORR_SIGNATURE_SIZE: usize = 64;
+
+/// The size of a key path witness: the item count and the signature with its length.
+const KEY_PATH_WITNESS_SIZE: u64 = 1 + 1 + SCHNORR_SIGNATURE_SIZE as u64;
+
+/// The unspendable BIP341 "nothing up my sleeve" point `H`, used as the internal key when no
+/// single-key spending condition is available.
+pub(crate) const NUMS_KEY: [u8; 32] = [
+    0x50, 0x92, 0x9b, 0x74, 0xc1, 0xa0, 0x49, 0x54, 0xb7, 0x8b, 0x4b, 0x60, 0x35, 0xe9, 0x7a, 0x5e,
+    0x07, 0x8a, 0x5a, 0x0f, 0x28, 0xec, 0x96, 0xd5, 0x47, 0xbf, 0xee, 0x9a, 0xce, 0x80, 0x3a, 0xc0,
+];
+
+/// A spending policy.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Policy {
+    /// A signature by the key.
+    Key(XOnlyPublicKey),
+    /// An absolute timelock.
+    After(absolute::LockTime),
+    /// A relative timelock.
+    Older(Sequence),
+    /// All of the sub-policies.
+    And(Vec<Policy>),
+    /// Any of the sub-policies, each with its relative probability of being used.
+    Or(Vec<(u32, Policy)>),
+    /// At least `k` of the sub-policies.
+    Thresh(usize, Vec<Policy>),
+}
+
+impl Policy {
+    /// Compiles the policy into a taproot output.
+    pub fn compile(&self) -> Result<TaprootSpendInfo, PolicyError> { self.plan()?.spend_info() }
+
+    /// Analyzes the ways in which the compiled policy can be spent.
+    ///
+    /// The report lists every spend path of the taproot output returned by [`Policy::compile`],
+    /// most likely first, together with what is needed to satisfy it, the weight of its witness
+    /// and any warnings about it.
+    pub fn analyze(&self) -> Result<SpendReport, PolicyError> {
+        let plan = self.plan()?;
+        let info = plan.spend_info()?;
+
+        let mut paths = vec![];
+        if let Some(probability) = plan.key_path {
+            paths.push(SpendPath {
+                kind: PathKind::KeyPath,
+                probability,
+                keys: vec![(1, vec![plan.internal_key])],
+                after: None,
+                older: None,
+                satisfaction_weight: Weight::from_wu(KEY_PATH_WITNESS_SIZE),
+                warnings: vec![],
+            });
+        }
+        for (script, (probability, condition)) in plan.leaves {
+            let control_block = info
+                .control_block(&(script.clone(), LeafVersion::TapScript))
+                .expect("every leaf is in the tree");
+            let (after, after_warning) = condition.after();
+            let (older, older_warning) = condition.older();
+            let mut warnings = vec![];
+            if condition.keys.is_empty() {
+                warnings.push(SpendWarning::NoSignature);
+            }
+            warnings.extend(after_warning);
+            warnings.extend(older_warning);
+
+            // One signature or empty signature per key, then the script and the control block.
+            let mut items = 2;
+            let mut size = 0;
+            for (k, keys) in &condition.keys {
+                items += keys.len();
+                size += k * SCHNORR_SIGNATURE_SIZE + keys.len();
+            }
+            size += VarInt(script.len() as u64).size() + script.len();
+            size += VarInt(control_block.size() as u64).size() + control_block.size();
+            size += VarInt(items as u64).size();
+
+            paths.push(SpendPath {
+                kind: PathKind::ScriptPath {
+                    leaf_hash: TapLeafHash::from_script(&script, LeafVersion::TapScript),
+                    depth: control_block.merkle_branch.len(),
+                },
+                probability,
+                keys: condition.keys,
+                after,
+                older,
+                satisfaction_weight: Weight::from_wu(size as u64),
+                warnings,
+            });
+        }
+        paths.sort_by(|a, b| b.probability.total_cmp(&a.probability));
+
+        Ok(SpendReport { internal_key: plan.internal_key, paths })
+    }
+
+    /// Splits the policy into the key path and the tapscript leaves.
+    fn plan(&self) -> Result<Plan, PolicyError> {
+        let mut conditions = self.conditions()?;
+
+        // The most likely single-key condition becomes the key path.
+        let key_path = conditions
+            .iter()
+            .filter_map(|(p, c)| c.single_key().map(|key| (*p, key)))
+            .max_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, key)| key);
+        let mut key_path_probability = None;
+        if let Some(key) = key_path {
+            let p = conditions.iter().filter(|(_, c)| c.single_key() == Some(key)).map(|(p, _)| p);
+            key_path_probability = Some(p.sum());
+            conditions.retain(|(_, c)| c.single_key() != Some(key));
+        }
+        let internal_key = match key_path {
+            Some(key) => key,
+            None => XOnlyPublicKey::from_slice(&NUMS_KEY).expect("valid NUMS point"),
+        };
+
+        // Merge identical leaves.
+        let mut leaves = BTreeMap::<ScriptBuf, (f64, Condition)>::new();
+        for (p, condition) in conditions {
+            leaves.entry(condition.to_script()).or_insert((0.0, condition)).0 += p;
+        }
+
+        Ok(Plan { internal_key, key_path: key_path_probability, leaves })
+    }
+
+    /// Expands the policy into alternative spending conditions, with the probability of each.
+    fn conditions(&self) -> Result<Vec<(f64, Condition)>, PolicyError> {
+        match *self {
+            Policy::Key(key) =>
+                Ok(vec![(1.0, Condition { keys: vec![(1, vec![key])], ..Default::default() })]),
+            Policy::After(lock_time) =>
+                Ok(vec![(1.0, Condition { after: vec![lock_time], ..Default::default() })]),
+            Policy::Older(sequence) =>
+                Ok(vec![(1.0, Condition { older: vec![sequence], ..Default::default() })]),
+            Policy::And(ref subs) => {
+                let mut conditions = vec![(1.0, Condition::default())];
+                for sub in subs {
+                    let sub = sub.conditions()?;
+                    if conditions.len() * sub.len() > MAX_CONDITIONS {
+                        return Err(PolicyError::TooManyConditions);
+                    }
+                    conditions = conditions
+                        .iter()
+                        .flat_map(|(p, c)| sub.iter().map(move |(q, d)| (p * q, c.and(d))))
+                        .collect();
+                }
+                Ok(conditions)
+            }
+            Policy::Or(ref subs) => {
+                let total = subs.iter().map(|(w, _)| f64::from(*w)).sum::<f64>();
+                let mut conditions = vec![];
+                for (weight, sub) in subs {
+                    let p = f64::from(*weight) / total;
+                    conditions.extend(sub.conditions()?.into_iter().map(|(q, c)| (p * q, c)));
+                    if conditions.len() > MAX_CONDITIONS {
+                        return Err(PolicyError::TooManyConditions);
+                    }
+                }
+                Ok(conditions)
+            }
+            Policy::Thresh(k, ref subs) => {
+                // Policies built by hand aren't checked by the parser.
+                if k == 0 || k > subs.len() {
+                    return Err(PolicyError::InvalidThreshold { k, n: subs.len() });
+                }
+                let keys = subs
+                    .iter()
+                    .map(|sub| match *sub {
+                        Policy::Key(key) => Some(key),
+                        _ => None,
+                    })
+                    .collect::<Option<Vec<_>>>();
+                match keys {
+                    Some(keys) =>
+                        Ok(vec![(1.0, Condition { keys: vec![(k, keys)], ..Default::default() })]),
+                    None if k == subs.len() => Policy::And(subs.clone()).conditions(),
+                    None if k == 1 =>
+                        Policy::Or(subs.iter().map(|sub| (1, sub.clone())).collect()).conditions(),
+                    None => {
+                        let combinations = combinations(subs.len(), k)?;
+                        let p = 1.0 / combinations.len() as f64;
+                        let mut conditions = vec![];
+                        for combination in combinations {
+                            let and = Policy::And(
+                                combination.into_iter().map(|i| subs[i].clone()).collect(),
+                            );
+                            conditions
+                                .extend(and.conditions()?.into_iter().map(|(q, c)| (p * q, c)));
+                            if conditions.len() > MAX_CONDITIONS {
+                                return Err(PolicyError::TooManyConditions);
+                            }
+                        }
+                        Ok(conditions)
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Returns every way of choosing `k` of the indices `0..n`, in lexicographic order.
+fn combinations(n: usize, k: usize) -> Result<Vec<Vec<usize>>, PolicyError> {
+    let mut result = vec![];
+    let mut current = (0..k).collect::<Vec<_>>();
+    loop {
+        if result.len() == MAX_CONDITIONS {
+            return Err(PolicyError::TooManyConditions);
+        }
+        result.push(current.clone());
+
+        // Advance the rightmost index which can still move to the right.
+        let i = match (0..k).rev().find(|&i| current[i] < n - k + i) {
+            Some(i) => i,
+            None => return Ok(result),
+        };
+        current[i] += 1;
+        for j in i + 1..k {
+            current[j] = current[j - 1] + 1;
+        }
+    }
+}
+
+/// A policy split into the key path and the tapscript leaves.
+struct Plan {
+    internal_key: XOnlyPublicKey,
+    /// The probability of the key path, `None` if the internal key is unspendable.
+    key_path: Option<f64>,
+    /// The tapscript leaves, with their probability and the condition they enforce.
+    leaves: BTreeMap<ScriptBuf, (f64, Condition)>,
+}
+
+impl Plan {
+    /// Returns the taproot output of the plan, arranging the leaves in a Huffman tree.
+    fn spend_info(&self) -> Result<TaprootSpendInfo, PolicyError> {
+        if self.leaves.is_empty() {
+            return Ok(TaprootSpendInfo::new_key_spend(self.internal_key, None));
+        }
+        let weights = self
+            .leaves
+            .iter()
+            .map(|(script, (p, _))| (((p * 1_000_000.0 + 0.5) as u32).max(1), script.clone()));
+        Ok(TaprootSpendInfo::with_huffman_tree(self.internal_key, weights)?)
+    }
+}
+
+/// A single spending condition: a conjunction of timelocks and key thresholds.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct Condition {
+    after: Vec<absolute::LockTime>,
+    older: Vec<Sequence>,
+    /// Pairs of a threshold and the keys it applies to.
+    keys: Vec<(usize, Vec<XOnlyPublicKey>)>,
+}
+
+impl Condition {
+    /// Returns the conjunction of `self` and `other`.
+    fn and(&self, other: &Condition) -> Condition {
+        let mut c = self.clone();
+        c.after.extend_from_slice(&other.after);
+        c.older.extend_from_slice(&other.older);
+        c.keys.extend_from_slice(&other.keys);
+        c
+    }
+
+    /// Returns the key if this condition is a single signature and nothing else.
+    fn single_key(&self) -> Option<XOnlyPublicKey> {
+        match self.keys.as_slice() {
+            [(1, keys)] if keys.len() == 1 && self.after.is_empty() && self.older.is_empty() =>
+                Some(keys[0]),
+            _ => None,
+        }
+    }
+
+    /// Returns the absolute timelock required by this condition.
+    ///
+    /// Mixing block heights and timestamps makes the condition unsatisfiable, in which case the
+    /// warning is returned instead.
+    fn after(&self) -> (Option<absolute::LockTime>, Option<SpendWarning>) {
+        let heights = self.after.iter().filter(|t| t.is_block_height()).count();
+        if heights != 0 && heights != self.after.len() {
+            return (None, Some(SpendWarning::MixedAbsoluteTimelocks));
+        }
+        (self.after.iter().copied().max_by_key(|t| t.to_consensus_u32()), None)
+    }
+
+    /// Returns the relative timelock required by this condition.
+    ///
+    /// Mixing block counts and time intervals makes the condition unsatisfiable, in which case
+    /// the warning is returned instead.
+    fn older(&self) -> (Option<Sequence>, Option<SpendWarning>) {
+        let heights = self.older.iter().filter(|s| s.is_height_locked()).count();
+        if heights != 0 && heights != self.older.len() {
+            return (None, Some(SpendWarning::MixedRelativeTimelocks));
+        }
+        (self.older.iter().copied().max_by_key(|s| s.to_consensus_u32()), None)
+    }
+
+    /// Returns the tapscript enforcing this condition.
+    fn to_script(&self) -> ScriptBuf {
+        let mut builder = Builder::new();
+        let timelocks = self.after.len() + self.older.len();
+        for (i, lock_time) in self.after.iter().enumerate() {
+            builder = builder.push_lock_time(*lock_time).push_opcode(OP_CLTV);
+            if !self.keys.is_empty() || i + 1 < timelocks {
+                builder = builder.push_opcode(OP_DROP);
+            }
+        }
+        for (i, sequence) in self.older.iter().enumerate() {
+            builder = builder.push_sequence(*sequence).push_opcode(OP_CSV);
+            if !self.keys.is_empty() || self.after.len() + i + 1 < timelocks {
+                builder = builder.push_opcode(OP_DROP);
+            }
+        }
+
+        for (i, (k, keys)) in self.keys.iter().enumerate() {
+            let last = i + 1 == self.keys.len();
+            if keys.len() == 1 {
+                builder = builder.push_x_only_key(&keys[0]);
+                builder = builder.push_opcode(if last { OP_CHECKSIG } else { OP_CHECKSIGVERIFY });
+            } else {
+                builder = builder.push_x_only_key(&keys[0]).push_opcode(OP_CHECKSIG);
+                for key in &keys[1..] {
+                    builder = builder.push_x_only_key(key).push_opcode(OP_CHECKSIGADD);
+                }
+                builder = builder.push_int(*k as i64);
+                builder = builder.push_opcode(if last { OP_NUMEQUAL } else { OP_NUMEQUALVERIFY });
+            }
+        }
+        builder.into_script()
+    }
+}
+
+/// The ways in which a compiled policy can be spent, see [`Policy::analyze`].
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[cfg_attr(feature = "serde", serde(crate = "actual_serde"))]
+pub struct SpendReport {
+    /// The internal key of the taproot output, unspendable if there is no key path.
+    pub internal_key: XOnlyPublicKey,
+    /// The spend paths, most likely first.
+    pub paths: Vec<SpendPath>,
+}
+
+/// A single way of spending a compiled policy.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[cfg_attr(feature = "serde", serde(crate = "actual_serde"))]
+pub struct SpendPath {
+    /// Whether this is the key path or a script path.
+    pub kind: PathKind,
+    /// The probability of this path being used, according to the policy.
+    pub probability: f64,
+    /// The required signatures, as pairs of a threshold and the keys it applies to.
+    pub keys: Vec<(usize, Vec<XOnlyPublicKey>)>,
+    /// The absolute timelock which must have expired.
+    pub after: Option<absolute::LockTime>,
+    /// The relative timelock which must have expired.
+    pub older: Option<Sequence>,
+    /// The weight of the witness satisfying this path, assuming default sighash signatures.
+    pub satisfaction_weight: Weight,
+    /// Problems with this path.
+    pub warnings: Vec<SpendWarning>,
+}
+
+/// The kind of a [`SpendPath`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[cfg_attr(feature = "serde", serde(crate = "actual_serde"))]
+pub enum PathKind {
+    /// A signature by the internal key.
+    KeyPath,
+    /// A tapscript leaf.
+    ScriptPath {
+        /// The hash of the leaf.
+        leaf_hash: TapLeafHash,
+        /// The depth of the leaf in the tree.
+        depth: usize,
+    },
+}
+
+/// A problem with a [`SpendPath`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[cfg_attr(feature = "serde", serde(crate = "actual_serde"))]
+#[non_exhaustive]
+pub enum SpendWarning {
+    /// The path requires no signature: anyone can spend it once the timelocks expire, and the
+    /// witness of a transaction spending it can be changed by third parties.
+    NoSignature,
+    /// The path mixes block height and timestamp absolute timelocks and can never be satisfied.
+    MixedAbsoluteTimelocks,
+    /// The path mixes block and time based relative timelocks and can never be satisfied.
+    MixedRelativeTimelocks,
+}
+
+impl fmt::Display for SpendWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use SpendWarning::*;
+
+        match *self {
+            NoSignature => f.write_str("spendable without a signature"),
+            MixedAbsoluteTimelocks => f.write_str("mixes height and time absolute timelocks"),
+            MixedRelativeTimelocks => f.write_str("mixes height and time relative timelocks"),
+        }
+    }
+}
+
+impl fmt::Display for Policy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Policy::Key(ref key) => write!(f, "pk({:x})", key),
+            Policy::After(lock_time) => write!(f, "after({})", lock_time.to_consensus_u32()),
+            Policy::Older(sequence) => write!(f, "older({})", sequence.to_consensus_u32()),
+            Policy::And(ref subs) => {
+                f.write_str("and(")?;
+                for (i, sub) in subs.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{}", sub)?;
+                }
+                f.write_str(")")
+            }
+            Policy::Or(ref subs) => {
+                // Probabilities are only written out if they are not all equal to one.
+                let weighted = subs.iter().any(|(weight, _)| *weight != 1);
+                f.write_str("or(")?;
+                for (i, (weight, sub)) in subs.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    if weighted {
+                        write!(f, "{}@", weight)?;
+                    }
+                    write!(f, "{}", sub)?;
+                }
+                f.write_str(")")
+            }
+            Policy::Thresh(k, ref subs) => {
+                write!(f, "thresh({}", k)?;
+                for sub in subs {
+                    write!(f, ",{}", sub)?;
+                }
+                f.write_str(")")
+            }
+        }
+    }
+}
+
+impl FromStr for Policy {
+    type Err = PolicyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser { s, pos: 0 };
+        let policy = parser.policy()?;
+        parser.skip_whitespace();
+        match parser.peek() {
+            None => Ok(policy),
+            Some(found) => Err(PolicyError::UnexpectedChar { position: parser.pos, found }),
+        }
+    }
+}
+
+/// A recursive descent parser for the policy language.
+struct Parser<'a> {
+    s: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn skip_whitespace(&mut self) {
+        let rest = &self.s[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn peek(&self) -> Option<char> { self.s[self.pos..].chars().next() }
+
+    /// Consumes the character `c`, after any whitespace.
+    fn expect(&mut self, c: char) -> Result<(), PolicyError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(found) if found == c => {
+                self.pos += c.len_utf8();
+                Ok(())
+            }
+            Some(found) => Err(PolicyError::UnexpectedChar { position: self.pos, found }),
+            None => Err(PolicyError::UnexpectedEnd),
+        }
+    }
+
+    /// Consumes the next run of alphanumeric characters, after any whitespace.
+    fn token(&mut self) -> &'a str {
+        self.skip_whitespace();
+        let rest = &self.s[self.pos..];
+        let len = rest.find(|c: char| !c.is_ascii_alphanumeric()).unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+
+    fn number<T: FromStr>(&mut self) -> Result<T, PolicyError> {
+        let token = self.token();
+        token.parse().map_err(|_| PolicyError::InvalidNumber(token.to_owned()))
+    }
+
+    fn policy(&mut self) -> Result<Policy, PolicyError> {
+        let name = self.token();
+        self.expect('(')?;
+        let policy = match name {
+            "pk" => {
+                let token = self.token();
+                let key = XOnlyPublicKey::from_str(token)
+                    .map_err(|_| PolicyError::InvalidKey(token.to_owned()))?;
+                Policy::Key(key)
+            }
+            "after" => {
+                let n = self.number::<u32>()?;
+                if n == 0 || n >= 0x8000_0000 {
+                    return Err(PolicyError::InvalidTimelock(n));
+                }
+                Policy::After(absolute::LockTime::from_consensus(n))
+            }
+            "older" => {
+                let n = self.number::<u32>()?;
+                if n == 0 || !Sequence::from_consensus(n).is_relative_lock_time() {
+                    return Err(PolicyError::InvalidTimelock(n));
+                }
+                Policy::Older(Sequence::from_consensus(n))
+            }
+            "and" => Policy::And(self.list(Parser::policy)?),
+            "or" => Policy::Or(self.list(Parser::weighted)?),
+            "thresh" => {
+                let k = self.number()?;
+                self.expect(',')?;
+                let subs = self.list(Parser::policy)?;
+                if k == 0 || k > subs.len() {
+                    return Err(PolicyError::InvalidThreshold { k, n: subs.len() });
+                }
+                Policy::Thresh(k, subs)
+            }
+            _ => return Err(PolicyError::UnknownFragment(name.to_owned())),
+        };
+        self.expect(')')?;
+        Ok(policy)
+    }
+
+    /// Parses a sub-policy of `or`, with an optional `N@` probability prefix.
+    fn weighted(&mut self) -> Result<(u32, Policy), PolicyError> {
+        let start = self.pos;
+        let token = self.token();
+        self.skip_whitespace();
+        if self.peek() != Some('@') {
+            self.pos = start;
+            return Ok((1, self.policy()?));
+        }
+        self.pos += 1;
+        match token.parse() {
+            Ok(0) => Err(PolicyError::ZeroProbability),
+            Ok(weight) => Ok((weight, self.policy()?)),
+            Err(_) => Err(PolicyError::InvalidNumber(token.to_owned())),
+        }
+    }
+
+    /// Parses at least two comma separated items.
+    fn list<T>(
+        &mut self,
+        item: fn(&mut Self) -> Result<T, PolicyError>,
+    ) -> Result<Vec<T>, PolicyError> {
+        let mut items = vec![item(self)?];
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some(',') {
+                break;
+            }
+            self.pos += 1;
+            items.push(item(self)?);
+        }
+        if items.len() < 2 {
+            return Err(PolicyError::TooFewArguments);
+        }
+        Ok(items)
+    }
+}
+
+/// An error parsing or compiling a policy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PolicyError {
+    /// The policy ended unexpectedly.
+    UnexpectedEnd,
+    /// An unexpected character was found.
+    UnexpectedChar {
+        /// The byte offset of the character in the policy.
+        position: usize,
+        /// The unexpected character.
+        found: char,
+    },
+    /// Unknown policy fragment.
+    UnknownFragment(String),
+    /// Invalid x-only public key.
+    InvalidKey(String),
+    /// Invalid number.
+    InvalidNumber(String),
+    /// Timelock out of range, or with the disable flag set.
+    InvalidTimelock(u32),
+    /// Threshold of zero or larger than the number of sub-policies.
+    InvalidThreshold {
+        /// The threshold.
+        k: usize,
+        /// The number of sub-policies.
+        n: usize,
+    },
+    /// A sub-policy of `or` has a probability of zero.
+    ZeroProbability,
+    /// `and`, `or` or `thresh` has fewer than two sub-policies.
+    TooFewArguments,
+    /// The policy expands to more than [`MAX_CONDITIONS`] spending conditions.
+    TooManyConditions,
+    /// Building the taproot tree failed.
+    Taproot(TaprootBuilderError),
+}
+
+internals::impl_from_infallible!(PolicyError);
+
+impl fmt::Display for PolicyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use PolicyError::*;
+
+        match *self {
+            UnexpectedEnd => f.write_str("unexpected end of policy"),
+            UnexpectedChar { position, found } =>
+                write!(f, "unexpected character '{}' at position {}", found, position),
+            UnknownFragment(ref name) => write!(f, "unknown policy fragment '{}'", name),
+            InvalidKey(ref key) => write!(f, "invalid x-only public key '{}'", key),
+            InvalidNumber(ref n) => write!(f, "invalid number '{}'", n),
+            InvalidTimelock(n) => write!(f, "invalid timelock {}", n),
+            InvalidThreshold { k, n } => write!(f, "invalid threshold {} of {}", k, n),
+            ZeroProbability => f.write_str("zero probability in or"),
+            TooFewArguments => f.write_str("and, or and thresh need at least two sub-policies"),
+            TooManyConditions =>
+                write!(f, "policy expands to more than {} spending conditions", MAX_CONDITIONS),
+            Taproot(ref e) => write_err!(f, "building the taproot tree failed"; e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PolicyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use PolicyError::*;
+
+        match *self {
+            Taproot(ref e) => Some(e),
+            UnexpectedEnd
+            | UnexpectedChar { .. }
+            | UnknownFragment(_)
+            | InvalidKey(_)
+            | InvalidNumber(_)
+            | InvalidTimelock(_)
+            | InvalidThreshold { .. }
+            | ZeroProbability
+            | TooFewArguments
+            | TooManyConditions => None,
+        }
+    }
+}
+
+impl From<TaprootBuilderError> for PolicyError {
+    fn from(e: TaprootBuilderError) -> Self { Self::Taproot(e) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::taproot::TapNodeHash;
+
+    fn key(i: u8) -> XOnlyPublicKey {
+        crate::crypto::key::Keypair::from_seckey_slice(&[i; 32]).unwrap().x_only_public_key().0
+    }
+
+    #[test]
+    fn parse_roundtrip() {
+        let s = format!(
+            "or(9@pk({:x}),1@and(thresh(2,pk({:x}),pk({:x}),older(144)),after(800000)))",
+            key(1),
+            key(2),
+            key(3)
+        );
+        let policy = s.parse::<Policy>().unwrap();
+        assert_eq!(policy.to_string(), s);
+        let spaced = format!(" or ( 9 @ pk({:x}) , pk({:x}) ) ", key(1), key(2));
+        let expected = Policy::Or(vec![(9, Policy::Key(key(1))), (1, Policy::Key(key(2)))]);
+        assert_eq!(spaced.parse::<Policy>(), Ok(expected));
+
+        assert_eq!("pk(00)".parse::<Policy>(), Err(PolicyError::InvalidKey("00".to_owned())));
+        assert_eq!("after(0)".parse::<Policy>(), Err(PolicyError::InvalidTimelock(0)));
+        assert_eq!("foo(1)".parse::<Policy>(), Err(PolicyError::UnknownFragment("foo".to_owned())));
+        assert_eq!("older(1".parse::<Policy>(), Err(PolicyError::UnexpectedEnd));
+        assert_eq!(
+            "older(1))".parse::<Policy>(),
+            Err(PolicyError::UnexpectedChar { position: 8, found: ')' })
+        );
+        assert_eq!(
+            format!("thresh(3,pk({:x}),pk({:x}))", key(1), key(2)).parse::<Policy>(),
+            Err(PolicyError::InvalidThreshold { k: 3, n: 2 })
+        );
+        assert_eq!("and(older(1))".parse::<Policy>(), Err(PolicyError::TooFewArguments));
+
+        let older = Policy::Older(Sequence::from_consensus(1));
+        for k in [0, 3] {
+            let policy = Policy::Thresh(k, vec![Policy::Key(key(1)), older.clone()]);
+            assert_eq!(policy.compile(), Err(PolicyError::InvalidThreshold { k, n: 2 }));
+        }
+    }
+
+    #[test]
+    fn compile_key_path() {
+        let policy = Policy::Or(vec![(1, Policy::Key(key(1))), (9, Policy::Key(key(2)))]);
+        let info = policy.compile().unwrap();
+        assert_eq!(info.internal_key(), key(2));
+
+        let leaf = Builder::new().push_x_only_key(&key(1)).push_opcode(OP_CHECKSIG).into_script();
+        assert_eq!(
+            info.merkle_root(),
+            Some(TapNodeHash::from_script(&leaf, LeafVersion::TapScript))
+        );
+
+        let info = Policy::Key(key(1)).compile().unwrap();
+        assert_eq!(info.internal_key(), key(1));
+        assert_eq!(info.merkle_root(), None);
+    }
+
+    #[test]
+    fn compile_script_paths() {
+        // 2-of-3 with a timelocked recovery key and no key path.
+        let policy = Policy::Or(vec![
+            (
+                9,
+                Policy::Thresh(
+                    2,
+                    vec![Policy::Key(key(1)), Policy::Key(key(2)), Policy::Key(key(3))],
+                ),
+            ),
+            (
+                1,
+                Policy::And(vec![
+                    Policy::Key(key(4)),
+                    Policy::Older(Sequence::from_consensus(144)),
+                ]),
+            ),
+        ]);
+        let info = policy.compile().unwrap();
+        assert_eq!(info.internal_key(), XOnlyPublicKey::from_slice(&NUMS_KEY).unwrap());
+
+        let multi = Builder::new()
+            .push_x_only_key(&key(1))
+            .push_opcode(OP_CHECKSIG)
+            .push_x_only_key(&key(2))
+            .push_opcode(OP_CHECKSIGADD)
+            .push_x_only_key(&key(3))
+            .push_opcode(OP_CHECKSIGADD)
+            .push_int(2)
+            .push_opcode(OP_NUMEQUAL)
+            .into_script();
+        let recovery = Builder::new()
+            .push_sequence(Sequence::from_consensus(144))
+            .push_opcode(OP_CSV)
+            .push_opcode(OP_DROP)
+            .push_x_only_key(&key(4))
+            .push_opcode(OP_CHECKSIG)
+            .into_script();
+        let script_map = info.script_map();
+        assert_eq!(script_map.len(), 2);
+        assert!(script_map.contains_key(&(multi, LeafVersion::TapScript)));
+        assert!(script_map.contains_key(&(recovery, LeafVersion::TapScript)));
+
+        // A threshold of non-key policies expands into every combination.
+        let policy = Policy::Thresh(
+            2,
+            vec![
+                Policy::Key(key(1)),
+                Policy::After(absolute::LockTime::from_consensus(800_000)),
+                Policy::Older(Sequence::from_consensus(144)),
+            ],
+        );
+        assert_eq!(policy.conditions().unwrap().len(), 3);
+        assert_eq!(policy.compile().unwrap().script_map().len(), 3);
+    }
+
+    #[test]
+    fn too_many_conditions() {
+        assert_eq!(combinations(4, 2).unwrap().len(), 6);
+        assert_eq!(combinations(3, 3).unwrap(), vec![vec![0, 1, 2]]);
+
+        let subs = (1..=20)
+            .map(|i| {
+                Policy::And(vec![Policy::Key(key(i)), Policy::Older(Sequence::from_consensus(1))])
+            })
+            .collect();
+        assert_eq!(Policy::Thresh(10, subs).compile(), Err(PolicyError::TooManyConditions));
+    }
+
+    #[test]
+    fn analyze() {
+        let policy = Policy::Or(vec![(1, Policy::Key(key(1))), (9, Policy::Key(key(2)))]);
+        let report = policy.analyze().unwrap();
+        assert_eq!(report.internal_key, key(2));
+        assert_eq!(report.paths.len(), 2);
+        assert_eq!(report.paths[0].kind, PathKind::KeyPath);
+        assert_eq!(report.paths[0].probability, 0.9);
+        assert_eq!(report.paths[0].satisfaction_weight, Weight::from_wu(66));
+        // Signature, script, control block with no merkle branch and the item count.
+        let path = &report.paths[1];
+        assert_eq!(
+            path.kind,
+            PathKind::ScriptPath {
+                leaf_hash: TapLeafHash::from_script(
+                    &Builder::new().push_x_only_key(&key(1)).push_opcode(OP_CHECKSIG).into_script(),
+                    LeafVersion::TapScript
+                ),
+                depth: 0,
+            }
+        );
+        assert_eq!(path.keys, vec![(1, vec![key(1)])]);
+        assert_eq!(path.satisfaction_weight, Weight::from_wu(65 + 35 + 34 + 1));
+        assert!(path.warnings.is_empty());
+
+        let policy = Policy::Or(vec![
+            (
+                9,
+                Policy::Thresh(
+                    2,
+                    vec![Policy::Key(key(1)), Policy::Key(key(2)), Policy::Key(key(3))],
+                ),
+            ),
+            (
+                1,
+                Policy::And(vec![
+                    Policy::Key(key(4)),
+                    Policy::Older(Sequence::from_consensus(144)),
+                ]),
+            ),
+        ]);
+        let report = policy.analyze().unwrap();
+        assert_eq!(report.internal_key, XOnlyPublicKey::from_slice(&NUMS_KEY).unwrap());
+        assert_eq!(report.paths.len(), 2);
+        let multi = &report.paths[0];
+        assert_eq!(multi.keys, vec![(2, vec![key(1), key(2), key(3)])]);
+        // Two signatures and an empty one, script, control block at depth one and the item count.
+        assert_eq!(multi.satisfaction_weight, Weight::from_wu(131 + 105 + 66 + 1));
+        let recovery = &report.paths[1];
+        assert_eq!(recovery.older, Some(Sequence::from_consensus(144)));
+        assert_eq!(recovery.after, None);
+
+        let policy = Policy::Or(vec![
+            (1, Policy::Key(key(1))),
+            (1, Policy::After(absolute::LockTime::from_consensus(800_000))),
+            (
+                1,
+                Policy::And(vec![
+                    Policy::Key(key(2)),
+                    Policy::After(absolute::LockTime::from_consensus(800_000)),
+                    Policy::After(absolute::LockTime::from_consensus(1_700_000_000)),
+                ]),
+            ),
+        ]);
+        let report = policy.analyze().unwrap();
+        let warnings = report.paths.iter().map(|p| p.warnings.clone()).collect::<Vec<_>>();
+        assert!(warnings.contains(&vec![SpendWarning::NoSignature]));
+        assert!(warnings.contains(&vec![SpendWarning::MixedAbsoluteTimelocks]));
+        let timelocked = report.paths.iter().find(|p| p.keys.is_empty()).unwrap();
+        assert_eq!(timelocked.after, Some(absolute::LockTime::from_consensus(800_000)));
+    }
+}
@@ -0,0 +1,354 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Partial transaction templates.
+//!
+//! In maker/taker protocols, such as splicing or dual funding, a maker offers a part of a
+//! transaction: some of its inputs and outputs, signed with a sighash type that decides what the
+//! taker may add to it. A [`TxTemplate`] is such an offer, and its [`TemplateHash`] is a canonical
+//! identifier that orders, acknowledgements and signatures can commit to.
+//!
+//! The hash is built from the same `sha_prevouts`, `sha_amounts`, `sha_scriptpubkeys`,
+//! `sha_sequences` and `sha_outputs` components as a BIP341 signature hash, so it commits to
+//! everything the maker's signatures will commit to. What the taker may add follows the sighash
+//! type of the template:
+//!
+//! * without `ANYONECANPAY` the template inputs are all the inputs, in order;
+//! * with `ANYONECANPAY` other inputs may be added;
+//! * `ALL` fixes all the outputs, `NONE` none of them, and `SINGLE` the output at the index of
+//!   each template input.
+//!
+//! [`TxTemplate::check_transaction`] checks that a completed transaction honours the template.
+//!
+
+use core::fmt;
+
+use hashes::{sha256t_hash_newtype, Hash, HashEngine};
+
+use crate::blockdata::locktime::absolute;
+use crate::blockdata::script::ScriptBuf;
+use crate::blockdata::transaction::{self, OutPoint, Sequence, Transaction, TxIn, TxOut};
+use crate::blockdata::witness::Witness;
+use crate::consensus::Encodable;
+use crate::crypto::sighash::{sha_encoded, TapSighashType};
+use crate::impl_thirty_two_byte_hash;
+use crate::prelude::*;
+
+sha256t_hash_newtype! {
+    pub struct TemplateTag = hash_str("TxTemplate");
+
+    /// Taproot-style tagged hash with tag \"TxTemplate\".
+    ///
+    /// This is the canonical identifier of a [`TxTemplate`].
+    #[hash_newtype(forward)]
+    pub struct TemplateHash(_);
+}
+
+impl_thirty_two_byte_hash!(TemplateHash);
+
+/// An input of a [`TxTemplate`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TemplateInput {
+    /// The output spent by the input.
+    pub previous_output: OutPoint,
+    /// The sequence number of the input.
+    pub sequence: Sequence,
+    /// The spent output, committed to by taproot signatures.
+    pub spent_output: TxOut,
+}
+
+/// A part of a transaction offered by a maker, see the [module docs](self).
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TxTemplate {
+    version: transaction::Version,
+    lock_time: absolute::LockTime,
+    inputs: Vec<TemplateInput>,
+    outputs: Vec<TxOut>,
+    sighash_type: TapSighashType,
+}
+
+impl TxTemplate {
+    /// Creates a template of `inputs` and `outputs`, signed with `sighash_type`.
+    ///
+    /// A `NONE` template can't have outputs, as its signatures don't commit to any, and a
+    /// `SINGLE` template must have exactly one output per input.
+    pub fn new(
+        version: transaction::Version,
+        lock_time: absolute::LockTime,
+        inputs: Vec<TemplateInput>,
+        outputs: Vec<TxOut>,
+        sighash_type: TapSighashType,
+    ) -> Result<Self, TemplateError> {
+        if inputs.is_empty() {
+            return Err(TemplateError::NoInputs);
+        }
+        match sighash_type.split_anyonecanpay_flag().0 {
+            TapSighashType::None if !outputs.is_empty() =>
+                return Err(TemplateError::UncommittedOutputs),
+            TapSighashType::Single if outputs.len() != inputs.len() =>
+                return Err(TemplateError::UnpairedOutputs {
+                    inputs: inputs.len(),
+                    outputs: outputs.len(),
+                }),
+            _ => {}
+        }
+        Ok(TxTemplate { version, lock_time, inputs, outputs, sighash_type })
+    }
+
+    /// Returns the version of the transaction.
+    pub fn version(&self) -> transaction::Version { self.version }
+
+    /// Returns the lock time of the transaction.
+    pub fn lock_time(&self) -> absolute::LockTime { self.lock_time }
+
+    /// Returns the offered inputs.
+    pub fn inputs(&self) -> &[TemplateInput] { &self.inputs }
+
+    /// Returns the offered outputs.
+    pub fn outputs(&self) -> &[TxOut] { &self.outputs }
+
+    /// Returns the sighash type the inputs are signed with.
+    pub fn sighash_type(&self) -> TapSighashType { self.sighash_type }
+
+    /// Returns the canonical hash of the template.
+    ///
+    /// The hash commits to the version, the lock time, the sighash type, and the BIP341
+    /// components of the inputs and outputs.
+    pub fn template_hash(&self) -> TemplateHash {
+        let mut engine = TemplateHash::engine();
+        self.version.consensus_encode(&mut engine).expect("engines don't error");
+        self.lock_time.consensus_encode(&mut engine).expect("engines don't error");
+        engine.input(&[self.sighash_type as u8]);
+        let inputs = &self.inputs;
+        engine.input(sha_encoded(inputs.iter().map(|input| &input.previous_output)).as_ref());
+        engine.input(sha_encoded(inputs.iter().map(|input| &input.spent_output.value)).as_ref());
+        engine.input(
+            sha_encoded(inputs.iter().map(|input| &input.spent_output.script_pubkey)).as_ref(),
+        );
+        engine.input(sha_encoded(inputs.iter().map(|input| &input.sequence)).as_ref());
+        engine.input(sha_encoded(&self.outputs).as_ref());
+        TemplateHash::from_engine(engine)
+    }
+
+    /// Returns the unsigned transaction of the template inputs and outputs, to be completed by
+    /// the taker.
+    pub fn to_unsigned_tx(&self) -> Transaction {
+        let input = self
+            .inputs
+            .iter()
+            .map(|input| TxIn {
+                previous_output: input.previous_output,
+                script_sig: ScriptBuf::new(),
+                sequence: input.sequence,
+                witness: Witness::new(),
+            })
+            .collect();
+        Transaction {
+            version: self.version,
+            lock_time: self.lock_time,
+            input,
+            output: self.outputs.clone(),
+        }
+    }
+
+    /// Checks that `tx` is a completion of the template allowed by its sighash type.
+    pub fn check_transaction(&self, tx: &Transaction) -> Result<(), TemplateError> {
+        if tx.version != self.version || tx.lock_time != self.lock_time {
+            return Err(TemplateError::HeaderMismatch);
+        }
+        let (base_type, anyone_can_pay) = self.sighash_type.split_anyonecanpay_flag();
+        if !anyone_can_pay && tx.input.len() != self.inputs.len() {
+            return Err(TemplateError::ExtraInputs);
+        }
+
+        for (index, input) in self.inputs.iter().enumerate() {
+            let tx_index = tx
+                .input
+                .iter()
+                .position(|txin| txin.previous_output == input.previous_output)
+                .filter(|&tx_index| anyone_can_pay || tx_index == index)
+                .ok_or(TemplateError::InputMismatch(index))?;
+            if tx.input[tx_index].sequence != input.sequence {
+                return Err(TemplateError::InputMismatch(index));
+            }
+            if base_type == TapSighashType::Single
+                && tx.output.get(tx_index) != Some(&self.outputs[index])
+            {
+                return Err(TemplateError::OutputMismatch);
+            }
+        }
+
+        match base_type {
+            TapSighashType::Default | TapSighashType::All if tx.output != self.outputs =>
+                Err(TemplateError::OutputMismatch),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// An invalid [`TxTemplate`], or a transaction not honouring one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TemplateError {
+    /// The template has no inputs.
+    NoInputs,
+    /// A `NONE` template has outputs, which its signatures don't commit to.
+    UncommittedOutputs,
+    /// A `SINGLE` template doesn't have exactly one output per input.
+    UnpairedOutputs {
+        /// The number of inputs.
+        inputs: usize,
+        /// The number of outputs.
+        outputs: usize,
+    },
+    /// The version or lock time of the transaction differ from the template.
+    HeaderMismatch,
+    /// The transaction has inputs besides the template inputs, without `ANYONECANPAY`.
+    ExtraInputs,
+    /// The template input at this index is missing, misplaced or has a different sequence.
+    InputMismatch(usize),
+    /// The outputs committed to by the template are missing or misplaced.
+    OutputMismatch,
+}
+
+internals::impl_from_infallible!(TemplateError);
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use TemplateError::*;
+
+        match *self {
+            NoInputs => f.write_str("transaction template has no inputs"),
+            UncommittedOutputs => f.write_str("SIGHASH_NONE transaction template has outputs"),
+            UnpairedOutputs { inputs, outputs } => write!(
+                f,
+                "SIGHASH_SINGLE transaction template has {} inputs but {} outputs",
+                inputs, outputs
+            ),
+            HeaderMismatch =>
+                f.write_str("transaction version or lock time differs from the template"),
+            ExtraInputs => f.write_str("transaction has inputs not in the template"),
+            InputMismatch(index) =>
+                write!(f, "transaction doesn't match input {} of the template", index),
+            OutputMismatch => f.write_str("transaction doesn't match the outputs of the template"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TemplateError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use TemplateError::*;
+
+        match *self {
+            NoInputs
+            | UncommittedOutputs
+            | UnpairedOutputs { .. }
+            | HeaderMismatch
+            | ExtraInputs
+            | InputMismatch(_)
+            | OutputMismatch => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash_types::Txid;
+    use crate::Amount;
+
+    fn input(vout: u32) -> TemplateInput {
+        TemplateInput {
+            previous_output: OutPoint { txid: Txid::all_zeros(), vout },
+            sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+            spent_output: TxOut { value: Amount::from_sat(1000), script_pubkey: ScriptBuf::new() },
+        }
+    }
+
+    fn output(sat: u64) -> TxOut {
+        TxOut { value: Amount::from_sat(sat), script_pubkey: ScriptBuf::new() }
+    }
+
+    fn template(outputs: Vec<TxOut>, sighash_type: TapSighashType) -> TxTemplate {
+        let (version, lock_time) = (transaction::Version::TWO, absolute::LockTime::ZERO);
+        TxTemplate::new(version, lock_time, vec![input(0), input(1)], outputs, sighash_type)
+            .unwrap()
+    }
+
+    #[test]
+    fn new() {
+        let (version, lock_time) = (transaction::Version::TWO, absolute::LockTime::ZERO);
+        assert_eq!(
+            TxTemplate::new(version, lock_time, vec![], vec![], TapSighashType::All),
+            Err(TemplateError::NoInputs)
+        );
+        assert_eq!(
+            TxTemplate::new(
+                version,
+                lock_time,
+                vec![input(0)],
+                vec![output(1)],
+                TapSighashType::None
+            ),
+            Err(TemplateError::UncommittedOutputs)
+        );
+        assert_eq!(
+            TxTemplate::new(version, lock_time, vec![input(0)], vec![], TapSighashType::Single),
+            Err(TemplateError::UnpairedOutputs { inputs: 1, outputs: 0 })
+        );
+    }
+
+    #[test]
+    fn template_hash() {
+        let all = template(vec![output(500), output(600)], TapSighashType::All);
+        let single = template(vec![output(500), output(600)], TapSighashType::Single);
+        assert_eq!(all.template_hash(), all.clone().template_hash());
+        assert_ne!(all.template_hash(), single.template_hash());
+
+        let mut inputs = all.inputs().to_vec();
+        inputs.swap(0, 1);
+        let swapped = TxTemplate::new(
+            all.version(),
+            all.lock_time(),
+            inputs,
+            all.outputs().to_vec(),
+            all.sighash_type(),
+        )
+        .unwrap();
+        assert_ne!(all.template_hash(), swapped.template_hash());
+    }
+
+    #[test]
+    fn check_transaction() {
+        let all = template(vec![output(500)], TapSighashType::All);
+        let mut tx = all.to_unsigned_tx();
+        assert_eq!(all.check_transaction(&tx), Ok(()));
+        tx.output.push(output(100));
+        assert_eq!(all.check_transaction(&tx), Err(TemplateError::OutputMismatch));
+        tx.output.pop();
+        tx.input.swap(0, 1);
+        assert_eq!(all.check_transaction(&tx), Err(TemplateError::InputMismatch(0)));
+        tx.input.push(TxIn::default());
+        assert_eq!(all.check_transaction(&tx), Err(TemplateError::ExtraInputs));
+        tx.lock_time = absolute::LockTime::from_consensus(1);
+        assert_eq!(all.check_transaction(&tx), Err(TemplateError::HeaderMismatch));
+
+        // The taker adds an input and an output to the maker's inputs and outputs.
+        let single =
+            template(vec![output(500), output(600)], TapSighashType::SinglePlusAnyoneCanPay);
+        let mut tx = single.to_unsigned_tx();
+        tx.input.insert(0, TxIn::default());
+        tx.output.insert(0, output(700));
+        assert_eq!(single.check_transaction(&tx), Ok(()));
+        tx.output.swap(1, 2);
+        assert_eq!(single.check_transaction(&tx), Err(TemplateError::OutputMismatch));
+
+        let none = template(vec![], TapSighashType::NonePlusAnyoneCanPay);
+        let mut tx = none.to_unsigned_tx();
+        tx.input[1].sequence = Sequence::MAX;
+        assert_eq!(none.check_transaction(&tx), Err(TemplateError::InputMismatch(1)));
+        tx.input[1].sequence = Sequence::ENABLE_RBF_NO_LOCKTIME;
+        tx.output.push(output(100));
+        assert_eq!(none.check_transaction(&tx), Ok(()));
+    }
+}
@@ -0,0 +1,642 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Timelocked vaults.
+//!
+//! A vault protects coins with a chain of pre-signed transactions:
+//!
+//! 1. Coins are deposited to the vault output, a taproot key path spend of the vault key.
+//! 2. Withdrawing starts with the unvault transaction, moving the coins to the unvault output.
+//! 3. After [`VaultConfig::delay`] blocks, the hot key spends the unvault output anywhere with
+//!    the spend transaction.
+//! 4. Until then, the cancel transaction moves the coins back to the cold key.
+//!
+//! The unvault and cancel transactions are signed with the vault key as soon as the coins are
+//! deposited, after which the vault key should be deleted: the coins can then only move through
+//! the unvault transaction, and every unvault gives the holder of the cancel transaction, e.g. a
+//! watchtower given the [`WatchtowerData`], the delay to cancel it.
+//!
+//! The unvault output has the unspendable BIP341 point `H` as internal key, and two leaves: the
+//! cancel leaf `pk(vault_key)` and the spend leaf `and_v(v:pk(hot_key),older(delay))`.
+//!
+//! The cancel transaction pays its fee at [`VaultConfig::fee_rate`], which may be too low by the
+//! time it is needed. Its signature uses `SIGHASH_SINGLE|SIGHASH_ANYONECANPAY`, committing only to
+//! its own input and the cold output, so that the watchtower can bump its fee by adding inputs and
+//! a change output before broadcasting it.
+//!
+//! Every transaction is built as a PSBT with the taproot fields and key origins needed by
+//! [`Psbt::sign`], and finalized with the `finalize_*` methods of [`Vault`] once signed.
+//!
+
+use core::fmt;
+
+use internals::write_err;
+#[cfg(feature = "chacha20poly1305")]
+use rand::{CryptoRng, RngCore};
+
+use crate::bip32::KeySource;
+use crate::blockdata::opcodes::all::{OP_CHECKSIG, OP_CHECKSIGVERIFY, OP_CSV};
+use crate::blockdata::script::{Builder, ScriptBuf};
+use crate::blockdata::transaction::{
+    InputWeightPrediction, OutPoint, Sequence, Transaction, TxOut, Txid,
+};
+use crate::blockdata::tx_builder::{TxBuilder, TxBuilderError};
+use crate::blockdata::witness::Witness;
+use crate::crypto::key::XOnlyPublicKey;
+use crate::multisig_setup::descriptor_checksum;
+use crate::prelude::*;
+use crate::psbt::Psbt;
+use crate::taproot::policy::NUMS_KEY;
+use crate::taproot::{
+    ControlBlock, LeafVersion, TapLeafHash, TapTree, TaprootBuilder, TaprootSpendInfo,
+};
+#[cfg(feature = "chacha20poly1305")]
+use crate::watchtower::Appointment;
+use crate::{FeeRate, TapSighashType};
+
+/// A key of a vault, with its origin so that PSBT signers can find it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VaultKey {
+    /// The x-only public key.
+    pub key: XOnlyPublicKey,
+    /// The master key fingerprint and the derivation path of the key.
+    pub origin: KeySource,
+}
+
+impl VaultKey {
+    /// Returns the key in descriptor notation, e.g. `[d34db33f/86'/0'/0']<hex>`.
+    fn descriptor_key(&self) -> String {
+        let (fingerprint, path) = &self.origin;
+        if path.is_master() {
+            format!("[{}]{}", fingerprint, self.key)
+        } else {
+            format!("[{}/{}]{}", fingerprint, path, self.key)
+        }
+    }
+}
+
+/// The keys and parameters of a vault.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VaultConfig {
+    /// The key of the vault output, signing the unvault and cancel transactions.
+    pub vault_key: VaultKey,
+    /// The key spending the unvault output after the delay.
+    pub hot_key: VaultKey,
+    /// The key receiving the coins of a cancelled unvault.
+    pub cold_key: VaultKey,
+    /// The number of blocks the unvault output must be confirmed for before the hot key can
+    /// spend it.
+    pub delay: u16,
+    /// The fee rate of every transaction of the chain.
+    pub fee_rate: FeeRate,
+}
+
+/// A vault, generating its transaction chain.
+#[derive(Clone, Debug)]
+pub struct Vault {
+    config: VaultConfig,
+    cancel_script: ScriptBuf,
+    spend_script: ScriptBuf,
+    unvault_tree: TapTree,
+    unvault_info: TaprootSpendInfo,
+}
+
+impl Vault {
+    /// Creates a vault from its configuration.
+    ///
+    /// # Errors
+    ///
+    /// [`VaultError::InvalidDelay`] if the delay is zero.
+    pub fn new(config: VaultConfig) -> Result<Self, VaultError> {
+        if config.delay == 0 {
+            return Err(VaultError::InvalidDelay);
+        }
+        let cancel_script = Builder::new()
+            .push_x_only_key(&config.vault_key.key)
+            .push_opcode(OP_CHECKSIG)
+            .into_script();
+        let spend_script = Builder::new()
+            .push_x_only_key(&config.hot_key.key)
+            .push_opcode(OP_CHECKSIGVERIFY)
+            .push_int(i64::from(config.delay))
+            .push_opcode(OP_CSV)
+            .into_script();
+        let builder = TaprootBuilder::new()
+            .add_leaf(1, cancel_script.clone())
+            .and_then(|builder| builder.add_leaf(1, spend_script.clone()))
+            .expect("two leaves at depth one form a valid tree");
+        let unvault_tree = builder.clone().try_into_taptree().expect("the tree is complete");
+        let unvault_info = builder.finalize(nums_key()).expect("the tree is complete");
+        Ok(Vault { config, cancel_script, spend_script, unvault_tree, unvault_info })
+    }
+
+    /// Returns the configuration of the vault.
+    pub fn config(&self) -> &VaultConfig { &self.config }
+
+    /// Returns the script pubkey of the vault output, to deposit coins to.
+    pub fn vault_script_pubkey(&self) -> ScriptBuf {
+        ScriptBuf::new_p2tr(self.config.vault_key.key, None)
+    }
+
+    /// Returns the script pubkey of the unvault output.
+    pub fn unvault_script_pubkey(&self) -> ScriptBuf {
+        ScriptBuf::new_p2tr(nums_key(), self.unvault_info.merkle_root())
+    }
+
+    /// Returns the script pubkey receiving the coins of a cancelled unvault.
+    pub fn cold_script_pubkey(&self) -> ScriptBuf {
+        ScriptBuf::new_p2tr(self.config.cold_key.key, None)
+    }
+
+    /// Returns the output descriptor of the vault output, including its checksum.
+    pub fn vault_descriptor(&self) -> String {
+        with_checksum(format!("tr({})", self.config.vault_key.descriptor_key()))
+    }
+
+    /// Returns the output descriptor of the unvault output, including its checksum.
+    ///
+    /// The descriptor has the form `tr(H,{pk(VAULT),and_v(v:pk(HOT),older(DELAY))})#checksum`.
+    pub fn unvault_descriptor(&self) -> String {
+        with_checksum(format!(
+            "tr({},{{pk({}),and_v(v:pk({}),older({}))}})",
+            nums_key(),
+            self.config.vault_key.descriptor_key(),
+            self.config.hot_key.descriptor_key(),
+            self.config.delay,
+        ))
+    }
+
+    /// Returns the output descriptor of the cold output, including its checksum.
+    pub fn cold_descriptor(&self) -> String {
+        with_checksum(format!("tr({})", self.config.cold_key.descriptor_key()))
+    }
+
+    /// Returns the PSBT of the unvault transaction, spending the vault `utxo` at `outpoint`.
+    ///
+    /// # Errors
+    ///
+    /// If `utxo` isn't paid to the vault output or is too small to pay the fee.
+    pub fn unvault_psbt(&self, outpoint: OutPoint, utxo: TxOut) -> Result<Psbt, VaultError> {
+        if utxo.script_pubkey != self.vault_script_pubkey() {
+            return Err(VaultError::NotVaultOutput);
+        }
+        let (tx, _) = TxBuilder::new(self.config.fee_rate)
+            .add_input(outpoint, utxo.clone(), InputWeightPrediction::P2TR_KEY_DEFAULT_SIGHASH)
+            .drain_to(self.unvault_script_pubkey())
+            .build()?;
+
+        let mut psbt = psbt(tx.into_transaction());
+        let vault_key = &self.config.vault_key;
+        let input = psbt.inputs.first_mut().ok_or(VaultError::MissingInput)?;
+        input.witness_utxo = Some(utxo);
+        input.tap_internal_key = Some(vault_key.key);
+        input.tap_key_origins.insert(vault_key.key, (vec![], vault_key.origin.clone()));
+
+        let output = psbt.outputs.first_mut().ok_or(VaultError::MissingUnvaultOutput)?;
+        output.tap_internal_key = Some(nums_key());
+        output.tap_tree = Some(self.unvault_tree.clone());
+        for (key, script) in [
+            (&self.config.vault_key, &self.cancel_script),
+            (&self.config.hot_key, &self.spend_script),
+        ] {
+            output.tap_key_origins.insert(key.key, (vec![leaf_hash(script)], key.origin.clone()));
+        }
+        Ok(psbt)
+    }
+
+    /// Returns the PSBT of the cancel transaction of `unvault_tx`.
+    ///
+    /// The transaction is signed with `SIGHASH_SINGLE|SIGHASH_ANYONECANPAY`, so that inputs and
+    /// outputs can be appended to it to bump its fee.
+    pub fn cancel_psbt(&self, unvault_tx: &Transaction) -> Result<Psbt, VaultError> {
+        let cold_key = &self.config.cold_key;
+        let mut psbt = self.unvault_spend_psbt(
+            unvault_tx,
+            &self.config.vault_key,
+            &self.cancel_script,
+            Sequence::ENABLE_RBF_NO_LOCKTIME,
+            TapSighashType::SinglePlusAnyoneCanPay,
+            self.cold_script_pubkey(),
+        )?;
+        let output = psbt.outputs.first_mut().ok_or(VaultError::NotCancelTransaction)?;
+        output.tap_internal_key = Some(cold_key.key);
+        output.tap_key_origins.insert(cold_key.key, (vec![], cold_key.origin.clone()));
+        Ok(psbt)
+    }
+
+    /// Returns the PSBT of the spend transaction of `unvault_tx`, paying `destination`.
+    ///
+    /// The transaction can only be mined once the unvault transaction has been confirmed for
+    /// [`VaultConfig::delay`] blocks.
+    pub fn spend_psbt(
+        &self,
+        unvault_tx: &Transaction,
+        destination: ScriptBuf,
+    ) -> Result<Psbt, VaultError> {
+        self.unvault_spend_psbt(
+            unvault_tx,
+            &self.config.hot_key,
+            &self.spend_script,
+            Sequence::from_height(self.config.delay),
+            TapSighashType::Default,
+            destination,
+        )
+    }
+
+    /// Extracts the unvault transaction from its signed PSBT.
+    pub fn finalize_unvault(&self, psbt: Psbt) -> Result<Transaction, VaultError> {
+        let input = psbt.inputs.first().ok_or(VaultError::MissingInput)?;
+        let signature = input.tap_key_sig.ok_or(VaultError::MissingSignature)?;
+        finalize(psbt, Witness::p2tr_key_spend(&signature))
+    }
+
+    /// Extracts the cancel transaction from its signed PSBT.
+    pub fn finalize_cancel(&self, psbt: Psbt) -> Result<Transaction, VaultError> {
+        self.finalize_script_path(psbt, &self.config.vault_key, &self.cancel_script)
+    }
+
+    /// Extracts the spend transaction from its signed PSBT.
+    pub fn finalize_spend(&self, psbt: Psbt) -> Result<Transaction, VaultError> {
+        self.finalize_script_path(psbt, &self.config.hot_key, &self.spend_script)
+    }
+
+    /// Returns what a watchtower needs to cancel `unvault_tx` with the signed `cancel_tx`.
+    pub fn watchtower_data(
+        &self,
+        unvault_tx: &Transaction,
+        cancel_tx: Transaction,
+    ) -> Result<WatchtowerData, VaultError> {
+        let (outpoint, _) = self.unvault_output(unvault_tx)?;
+        let cold_script_pubkey = self.cold_script_pubkey();
+        if cancel_tx.input.first().map(|input| input.previous_output) != Some(outpoint)
+            || cancel_tx.output.iter().any(|output| output.script_pubkey != cold_script_pubkey)
+        {
+            return Err(VaultError::NotCancelTransaction);
+        }
+        Ok(WatchtowerData { unvault_txid: outpoint.txid, delay: self.config.delay, cancel_tx })
+    }
+
+    /// Returns the outpoint and output of the unvault output of `unvault_tx`.
+    fn unvault_output(&self, unvault_tx: &Transaction) -> Result<(OutPoint, TxOut), VaultError> {
+        let script_pubkey = self.unvault_script_pubkey();
+        let vout = unvault_tx
+            .output
+            .iter()
+            .position(|output| output.script_pubkey == script_pubkey)
+            .ok_or(VaultError::MissingUnvaultOutput)?;
+        let outpoint = OutPoint::new(unvault_tx.compute_txid(), vout as u32);
+        Ok((outpoint, unvault_tx.output[vout].clone()))
+    }
+
+    /// Returns the PSBT of a transaction spending the unvault output of `unvault_tx` through the
+    /// leaf `script` of `key` signed with `sighash_type`, sweeping it to `script_pubkey`.
+    fn unvault_spend_psbt(
+        &self,
+        unvault_tx: &Transaction,
+        key: &VaultKey,
+        script: &ScriptBuf,
+        sequence: Sequence,
+        sighash_type: TapSighashType,
+        script_pubkey: ScriptBuf,
+    ) -> Result<Psbt, VaultError> {
+        let (outpoint, utxo) = self.unvault_output(unvault_tx)?;
+        let control_block = self.control_block(script);
+        let signature_len = match sighash_type {
+            TapSighashType::Default => 64,
+            _ => 65,
+        };
+        let prediction =
+            InputWeightPrediction::new(0, [signature_len, script.len(), control_block.size()]);
+        let (tx, _) = TxBuilder::new(self.config.fee_rate)
+            .sequence(sequence)
+            .add_input(outpoint, utxo.clone(), prediction)
+            .drain_to(script_pubkey)
+            .build()?;
+
+        let mut psbt = psbt(tx.into_transaction());
+        let input = psbt.inputs.first_mut().ok_or(VaultError::MissingInput)?;
+        input.witness_utxo = Some(utxo);
+        if sighash_type != TapSighashType::Default {
+            input.sighash_type = Some(sighash_type.into());
+        }
+        input.tap_internal_key = Some(nums_key());
+        input.tap_merkle_root = self.unvault_info.merkle_root();
+        input.tap_scripts.insert(control_block, (script.clone(), LeafVersion::TapScript));
+        input.tap_key_origins.insert(key.key, (vec![leaf_hash(script)], key.origin.clone()));
+        Ok(psbt)
+    }
+
+    /// Extracts a transaction spending the leaf `script` of `key` from its signed PSBT.
+    fn finalize_script_path(
+        &self,
+        psbt: Psbt,
+        key: &VaultKey,
+        script: &ScriptBuf,
+    ) -> Result<Transaction, VaultError> {
+        let signature = psbt
+            .inputs
+            .first()
+            .ok_or(VaultError::MissingInput)?
+            .tap_script_sigs
+            .get(&(key.key, leaf_hash(script)))
+            .ok_or(VaultError::MissingSignature)?;
+        let witness = Witness::from_slice(&[
+            signature.to_vec(),
+            script.to_bytes(),
+            self.control_block(script).serialize(),
+        ]);
+        finalize(psbt, witness)
+    }
+
+    fn control_block(&self, script: &ScriptBuf) -> ControlBlock {
+        self.unvault_info
+            .control_block(&(script.clone(), LeafVersion::TapScript))
+            .expect("the script is a leaf of the unvault tree")
+    }
+}
+
+/// What a watchtower needs to protect a vault from an unauthorized unvault.
+///
+/// Once the unvault transaction `unvault_txid` is seen, the watchtower checks with the owner
+/// whether the withdrawal is expected, and broadcasts `cancel_tx` otherwise. It has `delay`
+/// blocks from the confirmation of the unvault transaction to do so.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WatchtowerData {
+    /// The txid of the unvault transaction.
+    pub unvault_txid: Txid,
+    /// The delay before the unvault output can be spent by the hot key.
+    pub delay: u16,
+    /// The signed cancel transaction.
+    pub cancel_tx: Transaction,
+}
+
+crate::internal_macros::impl_consensus_encoding!(WatchtowerData, unvault_txid, delay, cancel_tx);
+
+#[cfg(feature = "chacha20poly1305")]
+impl WatchtowerData {
+    /// Returns the appointment handing the cancel transaction to a watchtower, which can only
+    /// decrypt it once the unvault transaction is published.
+    pub fn appointment<R: RngCore + CryptoRng>(&self, rng: &mut R) -> Appointment {
+        Appointment::new(&self.unvault_txid, &self.cancel_tx, rng)
+    }
+}
+
+fn nums_key() -> XOnlyPublicKey { XOnlyPublicKey::from_slice(&NUMS_KEY).expect("valid NUMS point") }
+
+fn leaf_hash(script: &ScriptBuf) -> TapLeafHash {
+    TapLeafHash::from_script(script, LeafVersion::TapScript)
+}
+
+fn with_checksum(desc: String) -> String {
+    let checksum = descriptor_checksum(&desc).expect("descriptor only uses valid characters");
+    format!("{}#{}", desc, checksum)
+}
+
+fn psbt(tx: Transaction) -> Psbt {
+    Psbt::from_unsigned_tx(tx).expect("built transactions are unsigned")
+}
+
+fn finalize(mut psbt: Psbt, witness: Witness) -> Result<Transaction, VaultError> {
+    psbt.inputs.first_mut().ok_or(VaultError::MissingInput)?.final_script_witness = Some(witness);
+    Ok(psbt.extract_tx_unchecked_fee_rate())
+}
+
+/// An error generating the transaction chain of a vault.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum VaultError {
+    /// The delay is zero.
+    InvalidDelay,
+    /// The output isn't paid to the vault output.
+    NotVaultOutput,
+    /// The transaction doesn't pay to the unvault output.
+    MissingUnvaultOutput,
+    /// The transaction doesn't move the unvault output to the cold output.
+    NotCancelTransaction,
+    /// The PSBT has no input.
+    MissingInput,
+    /// The PSBT lacks the signature of the key spending it.
+    MissingSignature,
+    /// Error building a transaction of the chain.
+    Build(TxBuilderError),
+}
+
+internals::impl_from_infallible!(VaultError);
+
+impl fmt::Display for VaultError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use VaultError::*;
+
+        match *self {
+            InvalidDelay => f.write_str("the unvault delay is zero"),
+            NotVaultOutput => f.write_str("the output isn't paid to the vault"),
+            MissingUnvaultOutput =>
+                f.write_str("the transaction doesn't pay to the unvault output"),
+            NotCancelTransaction => f.write_str("the transaction doesn't cancel the unvault"),
+            MissingInput => f.write_str("the PSBT has no input"),
+            MissingSignature => f.write_str("the PSBT isn't signed"),
+            Build(ref e) => write_err!(f, "building a vault transaction"; e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for VaultError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use VaultError::*;
+
+        match *self {
+            Build(ref e) => Some(e),
+            InvalidDelay | NotVaultOutput | MissingUnvaultOutput | NotCancelTransaction
+            | MissingInput | MissingSignature => None,
+        }
+    }
+}
+
+impl From<TxBuilderError> for VaultError {
+    fn from(e: TxBuilderError) -> Self { Self::Build(e) }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::str::FromStr;
+
+    use hashes::Hash;
+
+    use super::*;
+    use crate::bip32::{DerivationPath, Xpriv, Xpub};
+    use crate::blockdata::transaction::TxIn;
+    use crate::common::types::Message;
+    use crate::consensus::{deserialize, serialize};
+    use crate::crypto::schnorr;
+    use crate::crypto::sighash::{Prevouts, SighashCache};
+    use crate::{Amount, NetworkKind};
+
+    fn master() -> Xpriv { Xpriv::new_master(NetworkKind::Test, &[0x5a; 32]).unwrap() }
+
+    fn key(master: &Xpriv, path: &str) -> VaultKey {
+        let path = DerivationPath::from_str(path).unwrap();
+        let xpub = Xpub::from_priv(&master.derive_priv(&path).unwrap());
+        VaultKey { key: xpub.to_x_only_pub(), origin: (master.fingerprint(), path) }
+    }
+
+    fn vault() -> Vault {
+        let master = master();
+        Vault::new(VaultConfig {
+            vault_key: key(&master, "86'/1'/0'/0/0"),
+            hot_key: key(&master, "86'/1'/1'/0/0"),
+            cold_key: key(&master, "86'/1'/2'/0/0"),
+            delay: 144,
+            fee_rate: FeeRate::from_sat_per_vb_u32(2),
+        })
+        .unwrap()
+    }
+
+    fn deposit(vault: &Vault) -> (OutPoint, TxOut) {
+        let utxo = TxOut {
+            value: Amount::from_sat(1_000_000),
+            script_pubkey: vault.vault_script_pubkey(),
+        };
+        (OutPoint::new(Txid::from_byte_array([0x11; 32]), 0), utxo)
+    }
+
+    // Checks that `tx` spends `utxo` through the witness of its single input.
+    fn check_spend(tx: &Transaction, utxo: &TxOut) {
+        let witness = &tx.input[0].witness;
+        let output_key = XOnlyPublicKey::from_slice(&utxo.script_pubkey.as_bytes()[2..]).unwrap();
+        let (key, leaf_hash) = match witness.len() {
+            1 => (output_key, None),
+            _ => {
+                let script = crate::Script::from_bytes(&witness[1]);
+                let control_block = ControlBlock::decode(&witness[2]).unwrap();
+                assert!(control_block.verify_taproot_commitment(output_key, script));
+                let key = XOnlyPublicKey::from_slice(&script.as_bytes()[1..33]).unwrap();
+                (key, Some(TapLeafHash::from_script(script, control_block.leaf_version)))
+            }
+        };
+        let signature = crate::crypto::taproot::Signature::from_slice(&witness[0]).unwrap();
+        let all = [utxo];
+        let prevouts = match signature.sighash_type {
+            TapSighashType::SinglePlusAnyoneCanPay => Prevouts::One(0, utxo),
+            _ => Prevouts::All(&all),
+        };
+        let mut cache = SighashCache::new(tx);
+        let sighash = match leaf_hash {
+            None => cache.taproot_key_spend_signature_hash(0, &prevouts, signature.sighash_type),
+            Some(leaf_hash) => cache.taproot_script_spend_signature_hash(
+                0,
+                &prevouts,
+                leaf_hash,
+                signature.sighash_type,
+            ),
+        }
+        .unwrap();
+        let msg = Message::from_digest(sighash.to_byte_array());
+        schnorr::verify_schnorr(&signature.signature.into(), &msg, &key).unwrap();
+    }
+
+    #[test]
+    fn descriptors() {
+        let vault = vault();
+        let fingerprint = master().fingerprint();
+        let vault_descriptor = vault.vault_descriptor();
+        assert!(vault_descriptor.starts_with(&format!("tr([{}/86'/1'/0'/0/0]", fingerprint)));
+
+        let unvault = vault.unvault_descriptor();
+        let (desc, checksum) = unvault.split_once('#').unwrap();
+        assert_eq!(descriptor_checksum(desc).unwrap(), checksum);
+        assert!(desc.starts_with(
+            "tr(50929b74c1a04954b78b4b6035e97a5e078a5a0f28ec96d547bfee9ace803ac0,{pk("
+        ));
+        assert!(desc.ends_with(&format!("{}),older(144))}})", vault.config().hot_key.key)));
+    }
+
+    #[test]
+    fn chain() {
+        let vault = vault();
+        let master = master();
+        let (outpoint, utxo) = deposit(&vault);
+
+        let mut psbt = vault.unvault_psbt(outpoint, utxo.clone()).unwrap();
+        assert!(psbt.outputs[0].tap_tree.is_some());
+        psbt.sign(&master).unwrap();
+        let unvault_tx = vault.finalize_unvault(psbt).unwrap();
+        check_spend(&unvault_tx, &utxo);
+        let unvault_utxo = unvault_tx.output[0].clone();
+        assert_eq!(unvault_utxo.script_pubkey, vault.unvault_script_pubkey());
+
+        // The cancel transaction can be signed before the unvault transaction is broadcast.
+        let mut psbt = vault.cancel_psbt(&unvault_tx).unwrap();
+        assert_eq!(vault.finalize_cancel(psbt.clone()), Err(VaultError::MissingSignature));
+        psbt.sign(&master).unwrap();
+        let cancel_tx = vault.finalize_cancel(psbt).unwrap();
+        check_spend(&cancel_tx, &unvault_utxo);
+        assert_eq!(cancel_tx.output[0].script_pubkey, vault.cold_script_pubkey());
+        assert!(cancel_tx.output[0].value < unvault_utxo.value);
+
+        // The watchtower can bump the fee of the cancel transaction without invalidating it.
+        let mut bumped_tx = cancel_tx.clone();
+        bumped_tx.input.push(TxIn {
+            previous_output: OutPoint::new(Txid::from_byte_array([0x22; 32]), 0),
+            ..Default::default()
+        });
+        bumped_tx.output.push(TxOut {
+            value: Amount::from_sat(10_000),
+            script_pubkey: ScriptBuf::new_p2tr(vault.config().hot_key.key, None),
+        });
+        check_spend(&bumped_tx, &unvault_utxo);
+
+        let destination = ScriptBuf::new_p2tr(vault.config().hot_key.key, None);
+        let mut psbt = vault.spend_psbt(&unvault_tx, destination).unwrap();
+        psbt.sign(&master).unwrap();
+        let spend_tx = vault.finalize_spend(psbt).unwrap();
+        check_spend(&spend_tx, &unvault_utxo);
+        assert_eq!(spend_tx.input[0].sequence, Sequence::from_height(144));
+        assert!(spend_tx.input[0].sequence.is_relative_lock_time());
+
+        let data = vault.watchtower_data(&unvault_tx, cancel_tx.clone()).unwrap();
+        assert_eq!(data.unvault_txid, unvault_tx.compute_txid());
+        assert_eq!(deserialize::<WatchtowerData>(&serialize(&data)).unwrap(), data);
+        #[cfg(feature = "chacha20poly1305")]
+        {
+            let appointment = data.appointment(&mut rand::thread_rng());
+            assert_eq!(appointment.check(&unvault_tx).unwrap(), Some(cancel_tx.clone()));
+        }
+        assert_eq!(
+            vault.watchtower_data(&unvault_tx, spend_tx.clone()),
+            Err(VaultError::NotCancelTransaction)
+        );
+        assert_eq!(
+            vault.watchtower_data(&cancel_tx, spend_tx),
+            Err(VaultError::MissingUnvaultOutput)
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        let vault = vault();
+        let empty = Psbt::from_unsigned_tx(Transaction {
+            version: crate::blockdata::transaction::Version::TWO,
+            lock_time: crate::absolute::LockTime::ZERO,
+            input: vec![],
+            output: vec![],
+        })
+        .unwrap();
+        assert_eq!(vault.finalize_unvault(empty.clone()), Err(VaultError::MissingInput));
+        assert_eq!(vault.finalize_cancel(empty), Err(VaultError::MissingInput));
+
+        let (outpoint, mut utxo) = deposit(&vault);
+        utxo.script_pubkey = vault.cold_script_pubkey();
+        assert_eq!(vault.unvault_psbt(outpoint, utxo).map(|_| ()), Err(VaultError::NotVaultOutput));
+
+        let (outpoint, mut utxo) = deposit(&vault);
+        utxo.value = Amount::from_sat(100);
+        assert!(matches!(
+            vault.unvault_psbt(outpoint, utxo),
+            Err(VaultError::Build(TxBuilderError::InsufficientFunds { .. }))
+        ));
+
+        let mut config = vault.config().clone();
+        config.delay = 0;
+        assert_eq!(Vault::new(config).map(|_| ()), Err(VaultError::InvalidDelay));
+    }
+}
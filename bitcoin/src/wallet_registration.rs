@@ -0,0 +1,453 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Hardware wallet registration.
+//!
+//! Before signing for a multisig wallet, hardware wallets require the wallet to be registered so
+//! they can check change outputs and show addresses. This module generates the registration
+//! payloads for a [`MultisigSetup`] and parses the devices' responses:
+//!
+//! * [`LedgerWalletPolicy`]: a Ledger wallet policy (version 2), with the wallet id the device
+//!   returns on registration. [`LedgerRegistration`] parses the device's response.
+//! * [`ColdcardFile`]: a Coldcard multisig text file, which can be both generated and parsed, for
+//!   example to check a file exported back from the device.
+//!
+
+use core::fmt;
+use core::str::FromStr;
+
+use hashes::{sha256, Hash, HashEngine};
+use internals::write_err;
+
+use crate::bip32::{self, DerivationPath, Fingerprint, KeySource, Xpub};
+use crate::consensus::encode::{Encodable, VarInt};
+use crate::multisig_setup::MultisigSetup;
+use crate::prelude::*;
+
+/// The version byte of Ledger wallet policies.
+pub const LEDGER_POLICY_VERSION: u8 = 2;
+
+/// The maximum length in bytes of a Ledger wallet policy name.
+pub const LEDGER_MAX_NAME_LEN: usize = 64;
+
+/// A Ledger wallet policy, as registered with Ledger's Bitcoin app.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LedgerWalletPolicy {
+    name: String,
+    descriptor_template: String,
+    keys_info: Vec<String>,
+}
+
+impl LedgerWalletPolicy {
+    /// Creates the wallet policy of `setup`, registered under `name`.
+    ///
+    /// The name must be between 1 and [`LEDGER_MAX_NAME_LEN`] printable ASCII characters.
+    pub fn from_setup(setup: &MultisigSetup, name: &str) -> Result<Self, RegistrationError> {
+        if name.is_empty()
+            || name.len() > LEDGER_MAX_NAME_LEN
+            || !name.bytes().all(|b| (0x20..0x7f).contains(&b))
+        {
+            return Err(RegistrationError::InvalidName);
+        }
+
+        let placeholders =
+            (0..setup.cosigners().len()).map(|i| format!("@{}/**", i)).collect::<Vec<_>>();
+        let descriptor_template =
+            format!("wsh(sortedmulti({},{}))", setup.threshold(), placeholders.join(","));
+        let keys_info = setup
+            .cosigners()
+            .iter()
+            .map(|c| {
+                if c.origin.1.is_master() {
+                    format!("[{}]{}", c.origin.0, c.xpub)
+                } else {
+                    format!("[{}/{}]{}", c.origin.0, c.origin.1, c.xpub)
+                }
+            })
+            .collect();
+
+        Ok(LedgerWalletPolicy { name: name.to_owned(), descriptor_template, keys_info })
+    }
+
+    /// Returns the name the policy is registered under.
+    pub fn name(&self) -> &str { &self.name }
+
+    /// Returns the descriptor template, e.g. `wsh(sortedmulti(2,@0/**,@1/**))`.
+    pub fn descriptor_template(&self) -> &str { &self.descriptor_template }
+
+    /// Returns the key information vector, one `[origin]xpub` entry per key placeholder.
+    pub fn keys_info(&self) -> &[String] { &self.keys_info }
+
+    /// Serializes the policy as sent to the device during registration.
+    ///
+    /// The descriptor template is committed to by its SHA256 hash and the keys by the root of
+    /// their Merkle tree.
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut buf = vec![LEDGER_POLICY_VERSION, self.name.len() as u8];
+        buf.extend_from_slice(self.name.as_bytes());
+        VarInt::from(self.descriptor_template.len())
+            .consensus_encode(&mut buf)
+            .expect("vecs don't error");
+        buf.extend_from_slice(sha256::Hash::hash(self.descriptor_template.as_bytes()).as_ref());
+        VarInt::from(self.keys_info.len()).consensus_encode(&mut buf).expect("vecs don't error");
+        buf.extend_from_slice(self.keys_merkle_root().as_ref());
+        buf
+    }
+
+    /// Returns the wallet id, the SHA256 hash of the serialized policy.
+    pub fn id(&self) -> sha256::Hash { sha256::Hash::hash(&self.serialize()) }
+
+    /// Returns the root of the Merkle tree of the keys information vector.
+    pub fn keys_merkle_root(&self) -> sha256::Hash {
+        let leaves = self
+            .keys_info
+            .iter()
+            .map(|key| {
+                let mut engine = sha256::Hash::engine();
+                engine.input(&[0x00]);
+                engine.input(key.as_bytes());
+                sha256::Hash::from_engine(engine)
+            })
+            .collect::<Vec<_>>();
+        ledger_merkle_root(&leaves)
+    }
+}
+
+/// Computes the root of Ledger's Merkle tree over the leaf hashes `leaves`.
+///
+/// The left subtree holds the largest power of two of leaves strictly smaller than their number.
+fn ledger_merkle_root(leaves: &[sha256::Hash]) -> sha256::Hash {
+    match leaves.len() {
+        0 => sha256::Hash::all_zeros(),
+        1 => leaves[0],
+        n => {
+            let split = 1 << (usize::BITS - 1 - (n - 1).leading_zeros());
+            let mut engine = sha256::Hash::engine();
+            engine.input(&[0x01]);
+            engine.input(ledger_merkle_root(&leaves[..split]).as_ref());
+            engine.input(ledger_merkle_root(&leaves[split..]).as_ref());
+            sha256::Hash::from_engine(engine)
+        }
+    }
+}
+
+/// A Ledger device's response to a wallet policy registration.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct LedgerRegistration {
+    /// The id of the registered wallet policy.
+    pub wallet_id: sha256::Hash,
+    /// The proof of registration, to be passed back to the device along with the policy.
+    pub hmac: [u8; 32],
+}
+
+impl LedgerRegistration {
+    /// Parses the device's response to registering `policy`.
+    ///
+    /// The response is the 32 byte wallet id followed by the 32 byte HMAC.
+    pub fn from_response(
+        response: &[u8],
+        policy: &LedgerWalletPolicy,
+    ) -> Result<Self, RegistrationError> {
+        if response.len() != 64 {
+            return Err(RegistrationError::InvalidResponseLength(response.len()));
+        }
+        let wallet_id = sha256::Hash::from_slice(&response[..32]).expect("32 bytes");
+        if wallet_id != policy.id() {
+            return Err(RegistrationError::WalletIdMismatch);
+        }
+        let mut hmac = [0u8; 32];
+        hmac.copy_from_slice(&response[32..]);
+        Ok(LedgerRegistration { wallet_id, hmac })
+    }
+}
+
+/// A Coldcard multisig wallet file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ColdcardFile {
+    /// The name of the wallet.
+    pub name: String,
+    /// The number of signatures required to spend.
+    pub threshold: usize,
+    /// The address format, e.g. `P2WSH`.
+    pub format: String,
+    /// The key origin and xpub of every cosigner.
+    pub keys: Vec<(KeySource, Xpub)>,
+}
+
+impl ColdcardFile {
+    /// Creates the Coldcard file of `setup`, for the wallet named `name`.
+    ///
+    /// Coldcard truncates wallet names to 20 characters.
+    pub fn from_setup(setup: &MultisigSetup, name: &str) -> Self {
+        ColdcardFile {
+            name: name.to_owned(),
+            threshold: setup.threshold(),
+            format: "P2WSH".to_owned(),
+            keys: setup.cosigners().iter().map(|c| (c.origin.clone(), c.xpub)).collect(),
+        }
+    }
+
+    /// Returns true if the file describes the same wallet as `setup`, ignoring the name and
+    /// the order of the keys.
+    pub fn matches(&self, setup: &MultisigSetup) -> bool {
+        let mut keys =
+            self.keys.iter().map(|(origin, xpub)| (origin, xpub.encode())).collect::<Vec<_>>();
+        let mut expected =
+            setup.cosigners().iter().map(|c| (&c.origin, c.xpub.encode())).collect::<Vec<_>>();
+        keys.sort();
+        expected.sort();
+        self.threshold == setup.threshold()
+            && self.format.eq_ignore_ascii_case("P2WSH")
+            && keys == expected
+    }
+}
+
+impl fmt::Display for ColdcardFile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "# Multisig setup file")?;
+        writeln!(f, "Name: {}", self.name)?;
+        writeln!(f, "Policy: {} of {}", self.threshold, self.keys.len())?;
+
+        let shared = match self.keys.split_first() {
+            Some((((_, first), _), rest)) if rest.iter().all(|((_, path), _)| path == first) =>
+                Some(first),
+            _ => None,
+        };
+        if let Some(path) = shared {
+            writeln!(f, "Derivation: {}", ColdcardPath(path))?;
+        }
+        writeln!(f, "Format: {}", self.format)?;
+
+        for ((fingerprint, path), xpub) in &self.keys {
+            writeln!(f)?;
+            if shared.is_none() {
+                writeln!(f, "Derivation: {}", ColdcardPath(path))?;
+            }
+            writeln!(f, "{:X}: {}", fingerprint, xpub)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for ColdcardFile {
+    type Err = RegistrationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut name = None;
+        let mut policy = None;
+        let mut format = "P2SH".to_owned();
+        let mut derivation = DerivationPath::master();
+        let mut keys = vec![];
+
+        for line in s.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#')) {
+            let (key, value) = match line.split_once(':') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => return Err(RegistrationError::InvalidLine(line.to_owned())),
+            };
+            match key.to_ascii_lowercase().as_str() {
+                "name" => name = Some(value.to_owned()),
+                "policy" => policy = Some(parse_policy(value)?),
+                "format" => format = value.to_uppercase(),
+                "derivation" => {
+                    let path = value.strip_prefix('m').unwrap_or(value);
+                    let path = path.strip_prefix('/').unwrap_or(path);
+                    derivation = path.parse().map_err(RegistrationError::Bip32)?;
+                }
+                _ => {
+                    let fingerprint = Fingerprint::from_hex(key)
+                        .map_err(|_| RegistrationError::InvalidLine(line.to_owned()))?;
+                    let xpub = value.parse().map_err(RegistrationError::Bip32)?;
+                    keys.push(((fingerprint, derivation.clone()), xpub));
+                }
+            }
+        }
+
+        let name = name.ok_or(RegistrationError::MissingField("Name"))?;
+        let (threshold, total) = policy.ok_or(RegistrationError::MissingField("Policy"))?;
+        if threshold == 0 || threshold > total || total != keys.len() {
+            return Err(RegistrationError::InvalidPolicy);
+        }
+        Ok(ColdcardFile { name, threshold, format, keys })
+    }
+}
+
+/// Parses a Coldcard policy of the form `M of N` or `M/N`.
+fn parse_policy(s: &str) -> Result<(usize, usize), RegistrationError> {
+    let (m, n) = s
+        .split_once(" of ")
+        .or_else(|| s.split_once('/'))
+        .ok_or(RegistrationError::InvalidPolicy)?;
+    match (m.trim().parse(), n.trim().parse()) {
+        (Ok(m), Ok(n)) => Ok((m, n)),
+        _ => Err(RegistrationError::InvalidPolicy),
+    }
+}
+
+/// Formats a derivation path the way Coldcard expects it, with a leading `m`.
+struct ColdcardPath<'a>(&'a DerivationPath);
+
+impl fmt::Display for ColdcardPath<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.0.is_master() {
+            f.write_str("m")
+        } else {
+            write!(f, "m/{}", self.0)
+        }
+    }
+}
+
+/// An error generating a registration payload or parsing a device's response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RegistrationError {
+    /// The wallet name is empty, too long or not printable ASCII.
+    InvalidName,
+    /// The device's response has an unexpected length.
+    InvalidResponseLength(usize),
+    /// The device registered a different wallet policy.
+    WalletIdMismatch,
+    /// A required field is missing from a Coldcard file.
+    MissingField(&'static str),
+    /// A line of a Coldcard file could not be parsed.
+    InvalidLine(String),
+    /// The policy of a Coldcard file is invalid or does not match its keys.
+    InvalidPolicy,
+    /// Parsing a derivation path or an xpub failed.
+    Bip32(bip32::Error),
+}
+
+internals::impl_from_infallible!(RegistrationError);
+
+impl fmt::Display for RegistrationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use RegistrationError::*;
+
+        match *self {
+            InvalidName => f.write_str("invalid wallet name"),
+            InvalidResponseLength(len) => write!(f, "invalid device response length {}", len),
+            WalletIdMismatch => f.write_str("device registered a different wallet policy"),
+            MissingField(field) => write!(f, "missing field {}", field),
+            InvalidLine(ref line) => write!(f, "invalid line: {}", line),
+            InvalidPolicy => f.write_str("invalid multisig policy"),
+            Bip32(ref e) => write_err!(f, "invalid key"; e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for RegistrationError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use RegistrationError::*;
+
+        match *self {
+            Bip32(ref e) => Some(e),
+            InvalidName
+            | InvalidResponseLength(_)
+            | WalletIdMismatch
+            | MissingField(_)
+            | InvalidLine(_)
+            | InvalidPolicy => None,
+        }
+    }
+}
+
+impl From<bip32::Error> for RegistrationError {
+    fn from(e: bip32::Error) -> Self { Self::Bip32(e) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bip32::Xpriv;
+    use crate::multisig_setup::CosignerKey;
+    use crate::Network;
+
+    const CHALLENGE: [u8; 32] = [0x42; 32];
+
+    fn setup() -> MultisigSetup {
+        let path = DerivationPath::from_str("48h/1h/0h/2h").unwrap();
+        let cosigners = (1..=3u8)
+            .map(|i| {
+                let master = Xpriv::new_master(Network::Testnet, &[i; 32]).unwrap();
+                CosignerKey::new(&master, &path, &CHALLENGE, &[i; 32]).unwrap()
+            })
+            .collect();
+        MultisigSetup::new(2, cosigners, Network::Testnet, &CHALLENGE).unwrap()
+    }
+
+    #[test]
+    fn merkle_root() {
+        let leaves = (0..5u8).map(|i| sha256::Hash::hash(&[i])).collect::<Vec<_>>();
+        let node = |l: sha256::Hash, r: sha256::Hash| {
+            let mut engine = sha256::Hash::engine();
+            engine.input(&[0x01]);
+            engine.input(l.as_ref());
+            engine.input(r.as_ref());
+            sha256::Hash::from_engine(engine)
+        };
+
+        assert_eq!(ledger_merkle_root(&leaves[..1]), leaves[0]);
+        assert_eq!(ledger_merkle_root(&leaves[..2]), node(leaves[0], leaves[1]));
+        assert_eq!(ledger_merkle_root(&leaves[..3]), node(node(leaves[0], leaves[1]), leaves[2]));
+        assert_eq!(
+            ledger_merkle_root(&leaves),
+            node(node(node(leaves[0], leaves[1]), node(leaves[2], leaves[3])), leaves[4])
+        );
+    }
+
+    #[test]
+    fn ledger_policy() {
+        let setup = setup();
+        let policy = LedgerWalletPolicy::from_setup(&setup, "Cold storage").unwrap();
+        assert_eq!(policy.descriptor_template(), "wsh(sortedmulti(2,@0/**,@1/**,@2/**))");
+        assert!(policy.keys_info()[0]
+            .starts_with(&format!("[{}/48'/1'/0'/2']tpub", setup.cosigners()[0].origin.0)));
+
+        let serialized = policy.serialize();
+        assert_eq!(serialized[0], LEDGER_POLICY_VERSION);
+        assert_eq!(&serialized[2..14], b"Cold storage");
+        // Version, name, template length and hash, key count and Merkle root.
+        assert_eq!(serialized.len(), 2 + 12 + 1 + 32 + 1 + 32);
+
+        let mut response = policy.id().to_byte_array().to_vec();
+        response.extend_from_slice(&[0xaa; 32]);
+        let registration = LedgerRegistration::from_response(&response, &policy).unwrap();
+        assert_eq!(registration.hmac, [0xaa; 32]);
+
+        response[0] ^= 1;
+        assert_eq!(
+            LedgerRegistration::from_response(&response, &policy),
+            Err(RegistrationError::WalletIdMismatch)
+        );
+        assert_eq!(
+            LedgerRegistration::from_response(&response[..32], &policy),
+            Err(RegistrationError::InvalidResponseLength(32))
+        );
+        assert_eq!(LedgerWalletPolicy::from_setup(&setup, ""), Err(RegistrationError::InvalidName));
+    }
+
+    #[test]
+    fn coldcard_roundtrip() {
+        let setup = setup();
+        let file = ColdcardFile::from_setup(&setup, "vault");
+        let parsed = file.to_string().parse::<ColdcardFile>().unwrap();
+        assert_eq!(parsed, file);
+        assert!(parsed.matches(&setup));
+
+        // Per-key derivation lines, a different key order and comments as Coldcard exports them.
+        let (origin, xpub) = &file.keys[1];
+        let mut text = format!(
+            "# Coldcard Multisig setup file (exported)\nName: vault\nPolicy: 2 / 3\nFormat: p2wsh\n\nDerivation: m/{}\n{:X}: {}\n",
+            origin.1, origin.0, xpub
+        );
+        for (origin, xpub) in [&file.keys[0], &file.keys[2]] {
+            text.push_str(&format!("Derivation: m/{}\n{}: {}\n", origin.1, origin.0, xpub));
+        }
+        let exported = text.parse::<ColdcardFile>().unwrap();
+        assert!(exported.matches(&setup));
+
+        let wrong_policy = text.replace("2 / 3", "2 / 4");
+        assert_eq!(wrong_policy.parse::<ColdcardFile>(), Err(RegistrationError::InvalidPolicy));
+        let no_name = text.replace("Name: vault\n", "");
+        assert_eq!(no_name.parse::<ColdcardFile>(), Err(RegistrationError::MissingField("Name")));
+    }
+}
@@ -0,0 +1,605 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Watch-only wallets.
+//!
+//! A [`WatchOnlyWallet`] watches a set of scripts. Its confirmed outputs are the [`WalletState`]
+//! maintained by the chain scanner, see the [`checkpoint`](crate::checkpoint) module, and the
+//! transactions of its mempool are added as the backend reports them. From both it computes the
+//! [`Balance`] of the wallet with the semantics of Bitcoin Core's `getbalances`, so balances are
+//! displayed the same way whatever the wallet software.
+//!
+//! As blocks and mempool transactions are applied, the wallet reports what happened to its
+//! transactions as [`WalletEvent`]s, to the [`EventSink`] of the application.
+//!
+
+use hashes::{sha256t_hash_newtype, Hash};
+
+use crate::amount::Amount;
+use crate::blockdata::block::Block;
+use crate::blockdata::script::{Script, ScriptBuf};
+use crate::blockdata::transaction::{OutPoint, Transaction, Txid};
+use crate::checkpoint::{BlockId, Update, Utxo, WalletState};
+use crate::prelude::*;
+
+sha256t_hash_newtype! {
+    pub struct WalletIdTag = hash_str("WalletId");
+
+    /// Tagged hash with tag \"WalletId\", identifying a wallet.
+    ///
+    /// This is the hash of the script pubkey of the first external address of the wallet, at
+    /// index 0 of the receive chain of its descriptor. Wallets with the same id watch the same
+    /// addresses whatever the software that created them, so applications can key their storage
+    /// and logs with it.
+    #[hash_newtype(forward)]
+    pub struct WalletId(_);
+}
+
+impl WalletId {
+    /// Computes the id of the wallet whose first external script pubkey is `script_pubkey`.
+    pub fn from_first_script_pubkey(script_pubkey: &Script) -> WalletId {
+        WalletId::hash(script_pubkey.as_bytes())
+    }
+}
+
+/// The balance of a wallet, returned by [`WatchOnlyWallet::balance`].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Balance {
+    /// The value of the confirmed outputs, minus the ones spent by unconfirmed transactions.
+    pub confirmed: Amount,
+    /// The value of the unconfirmed outputs of transactions spending only outputs of the wallet,
+    /// like the change of its own payments.
+    ///
+    /// Such outputs can't be double spent by anyone else, they are as safe to spend as confirmed
+    /// ones.
+    pub trusted_pending: Amount,
+    /// The value of the unconfirmed outputs of transactions spending outputs of others.
+    ///
+    /// The sender can still double spend them.
+    pub untrusted_pending: Amount,
+}
+
+impl Balance {
+    /// Returns the value the wallet can safely spend, the confirmed and trusted pending value.
+    pub fn trusted(&self) -> Amount { self.confirmed + self.trusted_pending }
+
+    /// Returns the whole balance, including the untrusted pending value.
+    pub fn total(&self) -> Amount { self.trusted() + self.untrusted_pending }
+}
+
+/// An event of a [`WatchOnlyWallet`], passed to its [`EventSink`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WalletEvent {
+    /// A transaction paying to or spending from the wallet was seen for the first time, in the
+    /// mempool or in a block.
+    TxReceived {
+        /// The txid of the transaction.
+        txid: Txid,
+    },
+    /// A transaction of the wallet was confirmed.
+    TxConfirmed {
+        /// The txid of the transaction.
+        txid: Txid,
+        /// The height of the block which confirmed the transaction.
+        height: u32,
+    },
+    /// An unconfirmed transaction of the wallet was replaced by a conflicting transaction.
+    ///
+    /// The unconfirmed descendants of the replaced transaction are replaced too.
+    TxReplaced {
+        /// The txid of the replaced transaction.
+        txid: Txid,
+        /// The txid of the conflicting transaction.
+        by: Txid,
+    },
+    /// An output of the wallet was spent, by a transaction seen for the first time.
+    OutputSpent {
+        /// The spent output.
+        outpoint: OutPoint,
+        /// The txid of the spending transaction.
+        by: Txid,
+    },
+}
+
+/// A receiver of the events of a [`WatchOnlyWallet`].
+///
+/// The sink is called while the wallet is updated, so it should only hand the event over, to a
+/// queue or a channel for example, rather than process it.
+pub trait EventSink {
+    /// Receives `event`.
+    fn notify(&mut self, event: WalletEvent);
+}
+
+/// Drops the events.
+impl EventSink for () {
+    fn notify(&mut self, _: WalletEvent) {}
+}
+
+/// Collects the events.
+impl EventSink for Vec<WalletEvent> {
+    fn notify(&mut self, event: WalletEvent) { self.push(event) }
+}
+
+/// Sends the events over a channel, dropping them if the receiver is gone.
+#[cfg(feature = "std")]
+impl EventSink for std::sync::mpsc::Sender<WalletEvent> {
+    fn notify(&mut self, event: WalletEvent) { let _ = self.send(event); }
+}
+
+impl<S: EventSink + ?Sized> EventSink for &mut S {
+    fn notify(&mut self, event: WalletEvent) { (**self).notify(event) }
+}
+
+impl<S: EventSink + ?Sized> EventSink for Box<S> {
+    fn notify(&mut self, event: WalletEvent) { (**self).notify(event) }
+}
+
+/// A wallet which watches scripts without holding their keys.
+///
+/// The wallet reports its [`WalletEvent`]s to its sink `S`, which drops them by default.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WatchOnlyWallet<S = ()> {
+    id: Option<WalletId>,
+    scripts: BTreeSet<ScriptBuf>,
+    state: WalletState,
+    unconfirmed: BTreeMap<Txid, Transaction>,
+    sink: S,
+}
+
+impl WatchOnlyWallet {
+    /// Creates a wallet watching `scripts`, which didn't scan any block yet.
+    ///
+    /// The first script is the first external script pubkey of the wallet, which identifies it,
+    /// see [`WatchOnlyWallet::wallet_id`].
+    pub fn new<I: IntoIterator<Item = ScriptBuf>>(scripts: I) -> Self {
+        Self::with_state(scripts, WalletState::new())
+    }
+
+    /// Creates a wallet watching `scripts`, with the state of a previous scan.
+    ///
+    /// The state is typically recovered from a [`CheckpointLog`](crate::checkpoint::CheckpointLog).
+    pub fn with_state<I: IntoIterator<Item = ScriptBuf>>(scripts: I, state: WalletState) -> Self {
+        let mut scripts = scripts.into_iter().peekable();
+        WatchOnlyWallet {
+            id: scripts.peek().map(|script| WalletId::from_first_script_pubkey(script)),
+            scripts: scripts.collect(),
+            state,
+            unconfirmed: BTreeMap::new(),
+            sink: (),
+        }
+    }
+}
+
+impl<S: EventSink> WatchOnlyWallet<S> {
+    /// Returns the wallet, reporting its events to `sink`.
+    pub fn with_sink<T: EventSink>(self, sink: T) -> WatchOnlyWallet<T> {
+        WatchOnlyWallet {
+            id: self.id,
+            scripts: self.scripts,
+            state: self.state,
+            unconfirmed: self.unconfirmed,
+            sink,
+        }
+    }
+
+    /// Returns the id of the wallet, or `None` if it watches no script.
+    pub fn wallet_id(&self) -> Option<WalletId> { self.id }
+
+    /// Returns the event sink of the wallet.
+    pub fn sink(&self) -> &S { &self.sink }
+
+    /// Returns the event sink of the wallet, mutably.
+    pub fn sink_mut(&mut self) -> &mut S { &mut self.sink }
+
+    /// Returns the confirmed state of the wallet.
+    pub fn state(&self) -> &WalletState { &self.state }
+
+    /// Returns true if the wallet watches `script`.
+    pub fn is_mine(&self, script: &Script) -> bool { self.scripts.contains(script) }
+
+    /// Returns the unconfirmed transactions of the wallet.
+    pub fn unconfirmed(&self) -> impl Iterator<Item = &Transaction> { self.unconfirmed.values() }
+
+    /// Applies an update of the chain scanner.
+    ///
+    /// An unconfirmed transaction is removed when one of its outputs is received or one of its
+    /// inputs is spent, because it either confirmed or was replaced. The descendants of a replaced
+    /// transaction are not removed, the mempool reports them with
+    /// [`WatchOnlyWallet::remove_unconfirmed`].
+    ///
+    /// Updates don't tell which transaction spent an output, so they emit no events. Prefer
+    /// [`WatchOnlyWallet::apply_block`] when the whole block is available.
+    pub fn apply(&mut self, update: &Update) {
+        match *update {
+            Update::Tip(_) => {}
+            Update::Receive { outpoint, .. } => {
+                self.unconfirmed.remove(&outpoint.txid);
+            }
+            Update::Spend { outpoint } => self
+                .unconfirmed
+                .retain(|_, tx| tx.input.iter().all(|input| input.previous_output != outpoint)),
+        }
+        self.state.apply(update);
+    }
+
+    /// Applies the block at `height`, the next block of the chain.
+    ///
+    /// The outputs paying to the wallet are received and the outputs of the wallet spent by the
+    /// block are spent. Unconfirmed transactions conflicting with the block are replaced.
+    pub fn apply_block(&mut self, block: &Block, height: u32) {
+        for tx in &block.txdata {
+            let txid = tx.compute_txid();
+            self.replace_conflicts(tx, txid);
+
+            let seen = self.unconfirmed.remove(&txid).is_some();
+            if !seen && !self.is_relevant(tx) {
+                continue;
+            }
+            if !seen {
+                self.notify_received(tx, txid);
+            }
+            for input in &tx.input {
+                if self.state.utxos.contains_key(&input.previous_output) {
+                    self.state.apply(&Update::Spend { outpoint: input.previous_output });
+                }
+            }
+            for (vout, txout) in tx.output.iter().enumerate() {
+                if self.is_mine(&txout.script_pubkey) {
+                    let outpoint = OutPoint::new(txid, vout as u32);
+                    let utxo = Utxo { txout: txout.clone(), height };
+                    self.state.apply(&Update::Receive { outpoint, utxo });
+                }
+            }
+            self.sink.notify(WalletEvent::TxConfirmed { txid, height });
+        }
+        self.state.apply(&Update::Tip(BlockId { height, hash: block.block_hash() }));
+    }
+
+    /// Adds a transaction seen in the mempool.
+    ///
+    /// Unconfirmed transactions spending the same outputs are replaced by it.
+    ///
+    /// Returns false, and ignores the transaction, if it neither pays to the wallet nor spends
+    /// from it.
+    pub fn add_unconfirmed(&mut self, tx: Transaction) -> bool {
+        if !self.is_relevant(&tx) {
+            return false;
+        }
+        let txid = tx.compute_txid();
+        if !self.unconfirmed.contains_key(&txid) {
+            self.replace_conflicts(&tx, txid);
+            self.notify_received(&tx, txid);
+            self.unconfirmed.insert(txid, tx);
+        }
+        true
+    }
+
+    /// Removes a transaction evicted from the mempool, and its unconfirmed descendants.
+    pub fn remove_unconfirmed(&mut self, txid: Txid) { self.remove_with_descendants(txid); }
+
+    /// Removes the unconfirmed transaction `txid` and its descendants, and returns their txids.
+    fn remove_with_descendants(&mut self, txid: Txid) -> Vec<Txid> {
+        let mut removed = Vec::new();
+        let mut pending = vec![txid];
+        while let Some(txid) = pending.pop() {
+            if self.unconfirmed.remove(&txid).is_some() {
+                removed.push(txid);
+                pending.extend(
+                    self.unconfirmed
+                        .iter()
+                        .filter(|(_, tx)| {
+                            tx.input.iter().any(|input| input.previous_output.txid == txid)
+                        })
+                        .map(|(child, _)| *child),
+                );
+            }
+        }
+        removed
+    }
+
+    /// Replaces the unconfirmed transactions spending the same outputs as `tx`.
+    fn replace_conflicts(&mut self, tx: &Transaction, txid: Txid) {
+        let spent = tx.input.iter().map(|input| input.previous_output).collect::<BTreeSet<_>>();
+        let conflicts = self
+            .unconfirmed
+            .iter()
+            .filter(|&(&other, other_tx)| {
+                other != txid
+                    && other_tx.input.iter().any(|input| spent.contains(&input.previous_output))
+            })
+            .map(|(&other, _)| other)
+            .collect::<Vec<_>>();
+        for conflict in conflicts {
+            for replaced in self.remove_with_descendants(conflict) {
+                self.sink.notify(WalletEvent::TxReplaced { txid: replaced, by: txid });
+            }
+        }
+    }
+
+    /// Emits the events of `tx`, seen for the first time.
+    fn notify_received(&mut self, tx: &Transaction, txid: Txid) {
+        self.sink.notify(WalletEvent::TxReceived { txid });
+        for input in &tx.input {
+            if self.owns(input.previous_output) {
+                let outpoint = input.previous_output;
+                self.sink.notify(WalletEvent::OutputSpent { outpoint, by: txid });
+            }
+        }
+    }
+
+    /// Returns true if `tx` pays to the wallet or spends from it.
+    fn is_relevant(&self, tx: &Transaction) -> bool {
+        tx.output.iter().any(|output| self.is_mine(&output.script_pubkey))
+            || tx.input.iter().any(|input| self.owns(input.previous_output))
+    }
+
+    /// Returns the balance of the wallet.
+    ///
+    /// As in Bitcoin Core, an unconfirmed transaction is trusted if all its inputs spend
+    /// confirmed outputs of the wallet or outputs of trusted transactions paying to the wallet.
+    /// Outputs spent by unconfirmed transactions are not part of the balance.
+    pub fn balance(&self) -> Balance {
+        let spent = self
+            .unconfirmed
+            .values()
+            .flat_map(|tx| tx.input.iter().map(|input| input.previous_output))
+            .collect::<BTreeSet<_>>();
+
+        let mut balance = Balance::default();
+        for (outpoint, utxo) in &self.state.utxos {
+            if !spent.contains(outpoint) {
+                balance.confirmed += utxo.txout.value;
+            }
+        }
+
+        let mut trusted = BTreeMap::new();
+        for (&txid, tx) in &self.unconfirmed {
+            let is_trusted = self.is_trusted(txid, &mut trusted);
+            for (vout, output) in tx.output.iter().enumerate() {
+                if !self.is_mine(&output.script_pubkey)
+                    || spent.contains(&OutPoint::new(txid, vout as u32))
+                {
+                    continue;
+                }
+                if is_trusted {
+                    balance.trusted_pending += output.value;
+                } else {
+                    balance.untrusted_pending += output.value;
+                }
+            }
+        }
+        balance
+    }
+
+    /// Returns true if `outpoint` is a confirmed or unconfirmed output of the wallet.
+    fn owns(&self, outpoint: OutPoint) -> bool {
+        self.state.utxos.contains_key(&outpoint)
+            || self
+                .unconfirmed
+                .get(&outpoint.txid)
+                .and_then(|tx| tx.output.get(outpoint.vout as usize))
+                .is_some_and(|output| self.is_mine(&output.script_pubkey))
+    }
+
+    /// Returns true if the unconfirmed transaction `txid` is trusted, memoizing in `trusted`.
+    fn is_trusted(&self, txid: Txid, trusted: &mut BTreeMap<Txid, bool>) -> bool {
+        if let Some(&is_trusted) = trusted.get(&txid) {
+            return is_trusted;
+        }
+        let tx = &self.unconfirmed[&txid];
+        let is_trusted = tx.input.iter().all(|input| {
+            let prevout = input.previous_output;
+            if self.state.utxos.contains_key(&prevout) {
+                return true;
+            }
+            self.unconfirmed.contains_key(&prevout.txid)
+                && self.owns(prevout)
+                && self.is_trusted(prevout.txid, trusted)
+        });
+        trusted.insert(txid, is_trusted);
+        is_trusted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hashes::Hash;
+
+    use super::*;
+    use crate::blockdata::block::{self, BlockHash, TxMerkleNode};
+    use crate::blockdata::locktime::absolute;
+    use crate::blockdata::transaction::{self, TxIn, TxOut};
+    use crate::crypto::key::WPubkeyHash;
+    use crate::pow::CompactTarget;
+
+    fn script(n: u8) -> ScriptBuf { ScriptBuf::from_bytes(vec![n]) }
+
+    fn tx(inputs: &[OutPoint], outputs: &[(u8, u64)]) -> Transaction {
+        Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: inputs
+                .iter()
+                .map(|&previous_output| TxIn { previous_output, ..Default::default() })
+                .collect(),
+            output: outputs
+                .iter()
+                .map(|&(n, sat)| TxOut { value: Amount::from_sat(sat), script_pubkey: script(n) })
+                .collect(),
+        }
+    }
+
+    /// A wallet watching script 1, with a confirmed output of 100 sat.
+    fn wallet() -> (WatchOnlyWallet, OutPoint) {
+        let mut wallet = WatchOnlyWallet::new([script(1)]);
+        let outpoint = OutPoint::new(Txid::from_byte_array([1; 32]), 0);
+        let txout = TxOut { value: Amount::from_sat(100), script_pubkey: script(1) };
+        wallet.apply(&Update::Receive { outpoint, utxo: Utxo { txout, height: 10 } });
+        (wallet, outpoint)
+    }
+
+    fn balance(confirmed: u64, trusted_pending: u64, untrusted_pending: u64) -> Balance {
+        Balance {
+            confirmed: Amount::from_sat(confirmed),
+            trusted_pending: Amount::from_sat(trusted_pending),
+            untrusted_pending: Amount::from_sat(untrusted_pending),
+        }
+    }
+
+    #[test]
+    fn wallet_id() {
+        let first = ScriptBuf::new_p2wpkh(&WPubkeyHash::all_zeros());
+        let id = WalletId::from_first_script_pubkey(&first);
+        assert_eq!(
+            id.to_string(),
+            "d0fb49e648a1e5825add0337cb8ab2479e8c55bc5dd6f24e070dcae72f36a8ef"
+        );
+
+        assert_eq!(WatchOnlyWallet::new([first.clone(), script(1)]).wallet_id(), Some(id));
+        // The id depends on which script is first.
+        let wallet = WatchOnlyWallet::new([script(1), first]).with_sink(Vec::new());
+        assert_eq!(wallet.wallet_id(), Some(WalletId::from_first_script_pubkey(&script(1))));
+        assert_eq!(WatchOnlyWallet::new([]).wallet_id(), None);
+    }
+
+    #[test]
+    fn pending_balances() {
+        let (mut wallet, outpoint) = wallet();
+        assert_eq!(wallet.balance(), balance(100, 0, 0));
+
+        // A payment from someone else.
+        let foreign = OutPoint::new(Txid::from_byte_array([2; 32]), 0);
+        let incoming = tx(&[foreign], &[(1, 30), (2, 70)]);
+        assert!(wallet.add_unconfirmed(incoming.clone()));
+        assert_eq!(wallet.balance(), balance(100, 0, 30));
+        assert!(!wallet.add_unconfirmed(tx(&[foreign], &[(2, 100)])));
+
+        // Our own payment, with change.
+        let payment = tx(&[outpoint], &[(2, 60), (1, 39)]);
+        assert!(wallet.add_unconfirmed(payment.clone()));
+        assert_eq!(wallet.balance(), balance(0, 39, 30));
+        assert_eq!(wallet.balance().trusted(), Amount::from_sat(39));
+        assert_eq!(wallet.balance().total(), Amount::from_sat(69));
+
+        // Spending the change keeps trust, spending the incoming payment doesn't.
+        let change = OutPoint::new(payment.compute_txid(), 1);
+        let child = tx(&[change], &[(1, 38)]);
+        wallet.add_unconfirmed(child.clone());
+        assert_eq!(wallet.balance(), balance(0, 38, 30));
+        let mixed = tx(&[change, OutPoint::new(incoming.compute_txid(), 0)], &[(1, 67)]);
+        let mut other = wallet.clone();
+        other.remove_unconfirmed(child.compute_txid());
+        other.add_unconfirmed(mixed);
+        assert_eq!(other.balance(), balance(0, 0, 67));
+
+        // Evicting the payment evicts its descendants.
+        wallet.remove_unconfirmed(payment.compute_txid());
+        assert_eq!(wallet.unconfirmed().count(), 1);
+        assert_eq!(wallet.balance(), balance(100, 0, 30));
+    }
+
+    #[test]
+    fn confirmation() {
+        let (mut wallet, outpoint) = wallet();
+        let payment = tx(&[outpoint], &[(2, 60), (1, 39)]);
+        wallet.add_unconfirmed(payment.clone());
+
+        // The payment confirms: its change is received and its input spent.
+        let change = OutPoint::new(payment.compute_txid(), 1);
+        let txout = payment.output[1].clone();
+        wallet.apply(&Update::Receive { outpoint: change, utxo: Utxo { txout, height: 11 } });
+        wallet.apply(&Update::Spend { outpoint });
+        assert_eq!(wallet.unconfirmed().count(), 0);
+        assert_eq!(wallet.balance(), balance(39, 0, 0));
+
+        // A payment without change is removed when its input is spent.
+        let (mut wallet, outpoint) = self::wallet();
+        wallet.add_unconfirmed(tx(&[outpoint], &[(2, 99)]));
+        assert_eq!(wallet.balance(), balance(0, 0, 0));
+        wallet.apply(&Update::Spend { outpoint });
+        assert_eq!(wallet.unconfirmed().count(), 0);
+    }
+
+    fn block(txdata: Vec<Transaction>) -> Block {
+        let header = block::Header {
+            version: block::Version::TWO,
+            prev_blockhash: BlockHash::all_zeros(),
+            merkle_root: TxMerkleNode::all_zeros(),
+            time: 0,
+            bits: CompactTarget::from_consensus(0),
+            nonce: 0,
+        };
+        Block { header, txdata }
+    }
+
+    #[test]
+    fn events() {
+        let (wallet, outpoint) = wallet();
+        let mut wallet = wallet.with_sink(Vec::new());
+
+        let payment = tx(&[outpoint], &[(2, 60), (1, 39)]);
+        let txid = payment.compute_txid();
+        wallet.add_unconfirmed(payment.clone());
+        wallet.add_unconfirmed(payment);
+        assert_eq!(
+            wallet.sink(),
+            &[WalletEvent::TxReceived { txid }, WalletEvent::OutputSpent { outpoint, by: txid }]
+        );
+
+        // A fee bump replaces the payment and its child.
+        let child = tx(&[OutPoint::new(txid, 1)], &[(1, 38)]);
+        wallet.add_unconfirmed(child.clone());
+        wallet.sink_mut().clear();
+        let bump = tx(&[outpoint], &[(2, 60), (1, 30)]);
+        let by = bump.compute_txid();
+        wallet.add_unconfirmed(bump.clone());
+        assert_eq!(
+            wallet.sink()[..2],
+            [
+                WalletEvent::TxReplaced { txid, by },
+                WalletEvent::TxReplaced { txid: child.compute_txid(), by },
+            ]
+        );
+        assert_eq!(wallet.unconfirmed().collect::<Vec<_>>(), [&bump]);
+
+        // The fee bump confirms, with a payment not seen before.
+        let incoming = tx(&[OutPoint::new(Txid::from_byte_array([2; 32]), 0)], &[(1, 25)]);
+        let incoming_txid = incoming.compute_txid();
+        wallet.sink_mut().clear();
+        wallet.apply_block(&block(vec![bump, incoming]), 11);
+        assert_eq!(
+            wallet.sink(),
+            &[
+                WalletEvent::TxConfirmed { txid: by, height: 11 },
+                WalletEvent::TxReceived { txid: incoming_txid },
+                WalletEvent::TxConfirmed { txid: incoming_txid, height: 11 },
+            ]
+        );
+        assert_eq!(wallet.balance(), balance(55, 0, 0));
+        assert_eq!(wallet.state().tip.map(|tip| tip.height), Some(11));
+    }
+
+    #[test]
+    fn block_conflicts() {
+        let (wallet, outpoint) = wallet();
+        let mut wallet = wallet.with_sink(Vec::new());
+        let payment = tx(&[outpoint], &[(2, 99)]);
+        wallet.add_unconfirmed(payment.clone());
+
+        // Another spend of the same output is mined.
+        let conflict = tx(&[outpoint], &[(3, 90)]);
+        let by = conflict.compute_txid();
+        wallet.sink_mut().clear();
+        wallet.apply_block(&block(vec![conflict]), 11);
+        assert_eq!(
+            wallet.sink(),
+            &[
+                WalletEvent::TxReplaced { txid: payment.compute_txid(), by },
+                WalletEvent::TxReceived { txid: by },
+                WalletEvent::OutputSpent { outpoint, by },
+                WalletEvent::TxConfirmed { txid: by, height: 11 },
+            ]
+        );
+        assert_eq!(wallet.unconfirmed().count(), 0);
+        assert_eq!(wallet.balance(), Balance::default());
+    }
+}
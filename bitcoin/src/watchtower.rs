@@ -0,0 +1,288 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Watchtower appointments.
+//!
+//! A watchtower watches the chain on behalf of a wallet and broadcasts a reaction transaction,
+//! such as a vault cancel transaction or a Lightning justice transaction, when a triggering
+//! transaction confirms. To keep the watchtower from learning anything before that, the reaction
+//! transaction is handed over in an [`Appointment`]: an [`EncryptedBlob`] keyed by the txid of
+//! the triggering transaction, and indexed by the [`Locator`], the first half of that txid.
+//!
+//! The watchtower matches the locators of its appointments against the transactions it sees and
+//! can only decrypt a blob once its triggering transaction is published.
+//!
+//! The blob is the consensus encoding of the reaction transaction, encrypted with
+//! ChaCha20-Poly1305 under the key `SHA256(txid)`. Its format is:
+//!
+//! ```text
+//! version (1 byte) | nonce (12 bytes) | encrypted transaction | Poly1305 tag (16 bytes)
+//! ```
+//!
+//! where the version and the random nonce are authenticated as associated data. The txid is
+//! taken in its consensus byte order, both for the key and the locator.
+//!
+
+use core::fmt;
+
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use hashes::{sha256, Hash};
+use internals::{impl_array_newtype, write_err};
+use io::{BufRead, Write};
+use rand::{CryptoRng, RngCore};
+
+use crate::blockdata::transaction::{Transaction, Txid};
+use crate::consensus::encode::{self, Decodable, Encodable};
+use crate::internal_macros::impl_bytes_newtype;
+use crate::prelude::*;
+
+/// The version of the blob format written by this library.
+pub const CURRENT_VERSION: u8 = 1;
+
+/// The length of the blob header: the version and the nonce.
+const HEADER_LEN: usize = 1 + 12;
+
+/// The length of the Poly1305 tag of a blob.
+const TAG_LEN: usize = 16;
+
+/// The first 16 bytes of the txid of a triggering transaction.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Locator([u8; 16]);
+impl_array_newtype!(Locator, u8, 16);
+impl_bytes_newtype!(Locator, 16);
+
+impl Locator {
+    /// Returns the locator of the transaction with txid `txid`.
+    pub fn from_txid(txid: &Txid) -> Self {
+        let mut locator = [0; 16];
+        locator.copy_from_slice(&txid.as_byte_array()[..16]);
+        Locator(locator)
+    }
+}
+
+impl Encodable for Locator {
+    fn consensus_encode<W: Write + ?Sized>(&self, w: &mut W) -> Result<usize, io::Error> {
+        self.0.consensus_encode(w)
+    }
+}
+
+impl Decodable for Locator {
+    fn consensus_decode<R: BufRead + ?Sized>(r: &mut R) -> Result<Self, encode::Error> {
+        Ok(Locator(Decodable::consensus_decode(r)?))
+    }
+}
+
+/// A reaction transaction encrypted with the txid of its triggering transaction.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct EncryptedBlob(Vec<u8>);
+
+impl EncryptedBlob {
+    /// Encrypts `reaction_tx` with the txid `trigger_txid` of its triggering transaction.
+    pub fn encrypt<R: RngCore + CryptoRng>(
+        trigger_txid: &Txid,
+        reaction_tx: &Transaction,
+        rng: &mut R,
+    ) -> Self {
+        let mut blob = Vec::with_capacity(HEADER_LEN + reaction_tx.total_size() + TAG_LEN);
+        blob.push(CURRENT_VERSION);
+        let mut nonce = [0u8; 12];
+        rng.fill_bytes(&mut nonce);
+        blob.extend_from_slice(&nonce);
+
+        let ciphertext = cipher(trigger_txid)
+            .encrypt(
+                &Nonce::from(nonce),
+                Payload { msg: &encode::serialize(reaction_tx), aad: &blob },
+            )
+            .expect("transactions are much shorter than the ChaCha20 limit");
+        blob.extend_from_slice(&ciphertext);
+        EncryptedBlob(blob)
+    }
+
+    /// Decrypts the reaction transaction with the txid `trigger_txid` of its triggering
+    /// transaction.
+    pub fn decrypt(&self, trigger_txid: &Txid) -> Result<Transaction, WatchtowerError> {
+        if self.0.len() < HEADER_LEN + TAG_LEN {
+            return Err(WatchtowerError::TooShort);
+        }
+        match self.0[0] {
+            CURRENT_VERSION => {}
+            v => return Err(WatchtowerError::UnsupportedVersion(v)),
+        }
+        let (header, ciphertext) = self.0.split_at(HEADER_LEN);
+        let nonce = Nonce::from(<[u8; 12]>::try_from(&header[1..]).expect("12 bytes"));
+        let tx = cipher(trigger_txid)
+            .decrypt(&nonce, Payload { msg: ciphertext, aad: header })
+            .map_err(|_| WatchtowerError::Decryption)?;
+        Ok(encode::deserialize(&tx)?)
+    }
+
+    /// Returns the serialized blob.
+    pub fn as_bytes(&self) -> &[u8] { &self.0 }
+
+    /// Creates a blob from its serialization, checked when decrypted.
+    pub fn from_bytes(bytes: Vec<u8>) -> Self { EncryptedBlob(bytes) }
+}
+
+impl Encodable for EncryptedBlob {
+    fn consensus_encode<W: Write + ?Sized>(&self, w: &mut W) -> Result<usize, io::Error> {
+        self.0.consensus_encode(w)
+    }
+}
+
+impl Decodable for EncryptedBlob {
+    fn consensus_decode<R: BufRead + ?Sized>(r: &mut R) -> Result<Self, encode::Error> {
+        Ok(EncryptedBlob(Decodable::consensus_decode(r)?))
+    }
+}
+
+/// A reaction transaction handed to a watchtower.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Appointment {
+    /// The locator of the triggering transaction.
+    pub locator: Locator,
+    /// The encrypted reaction transaction.
+    pub blob: EncryptedBlob,
+}
+
+crate::internal_macros::impl_consensus_encoding!(Appointment, locator, blob);
+
+impl Appointment {
+    /// Creates the appointment broadcasting `reaction_tx` once the transaction with txid
+    /// `trigger_txid` is published.
+    pub fn new<R: RngCore + CryptoRng>(
+        trigger_txid: &Txid,
+        reaction_tx: &Transaction,
+        rng: &mut R,
+    ) -> Self {
+        Appointment {
+            locator: Locator::from_txid(trigger_txid),
+            blob: EncryptedBlob::encrypt(trigger_txid, reaction_tx, rng),
+        }
+    }
+
+    /// Returns the reaction transaction if `tx` is the triggering transaction.
+    ///
+    /// Returns `Ok(None)` if `tx` doesn't match the locator of the appointment, and an error if
+    /// it does but the blob doesn't decrypt with its txid.
+    pub fn check(&self, tx: &Transaction) -> Result<Option<Transaction>, WatchtowerError> {
+        let txid = tx.compute_txid();
+        if Locator::from_txid(&txid) != self.locator {
+            return Ok(None);
+        }
+        self.blob.decrypt(&txid).map(Some)
+    }
+}
+
+/// Returns the cipher keyed with the txid of a triggering transaction.
+///
+/// Every blob has its own random nonce so the same trigger can have several appointments.
+fn cipher(trigger_txid: &Txid) -> ChaCha20Poly1305 {
+    let key = sha256::Hash::hash(trigger_txid.as_byte_array());
+    ChaCha20Poly1305::new(&Key::from(key.to_byte_array()))
+}
+
+/// An error decrypting an encrypted blob.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum WatchtowerError {
+    /// The data is too short to be an encrypted blob.
+    TooShort,
+    /// The blob was written by a newer, unknown, version of the format.
+    UnsupportedVersion(u8),
+    /// The blob failed authentication, either it was tampered with or the txid is wrong.
+    Decryption,
+    /// The decrypted transaction is malformed.
+    Decode(encode::Error),
+}
+
+internals::impl_from_infallible!(WatchtowerError);
+
+impl fmt::Display for WatchtowerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use WatchtowerError::*;
+
+        match *self {
+            TooShort => f.write_str("data too short to be an encrypted blob"),
+            UnsupportedVersion(v) => write!(f, "unsupported encrypted blob version {}", v),
+            Decryption => f.write_str("blob decryption failed, wrong txid or corrupted blob"),
+            Decode(ref e) => write_err!(f, "malformed reaction transaction"; e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for WatchtowerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use WatchtowerError::*;
+
+        match *self {
+            Decode(ref e) => Some(e),
+            TooShort | UnsupportedVersion(_) | Decryption => None,
+        }
+    }
+}
+
+impl From<encode::Error> for WatchtowerError {
+    fn from(e: encode::Error) -> Self { Self::Decode(e) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockdata::locktime::absolute;
+    use crate::blockdata::transaction::{self, OutPoint, TxIn, TxOut};
+    use crate::consensus::{deserialize, serialize};
+
+    fn tx(vout: u32, previous_output: OutPoint) -> Transaction {
+        Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![TxIn { previous_output, ..Default::default() }],
+            output: vec![TxOut::NULL; vout as usize + 1],
+        }
+    }
+
+    #[test]
+    fn appointment() {
+        let mut rng = rand::thread_rng();
+        let trigger = tx(0, OutPoint::null());
+        let txid = trigger.compute_txid();
+        let reaction = tx(0, OutPoint::new(txid, 0));
+
+        let appointment = Appointment::new(&txid, &reaction, &mut rng);
+        assert_eq!(appointment.locator.as_bytes()[..], txid.as_byte_array()[..16]);
+        let appointment: Appointment = deserialize(&serialize(&appointment)).unwrap();
+
+        assert_eq!(appointment.check(&reaction).unwrap(), None);
+        assert_eq!(appointment.check(&trigger).unwrap(), Some(reaction.clone()));
+
+        // Every blob has its own nonce.
+        let other = Appointment::new(&txid, &reaction, &mut rng);
+        assert_eq!(other.locator, appointment.locator);
+        assert_ne!(other.blob, appointment.blob);
+    }
+
+    #[test]
+    fn invalid_blobs() {
+        let mut rng = rand::thread_rng();
+        let trigger = tx(0, OutPoint::null());
+        let txid = trigger.compute_txid();
+        let blob = EncryptedBlob::encrypt(&txid, &tx(1, OutPoint::new(txid, 0)), &mut rng);
+
+        let other_txid = tx(1, OutPoint::null()).compute_txid();
+        assert!(matches!(blob.decrypt(&other_txid), Err(WatchtowerError::Decryption)));
+
+        let mut bytes = blob.as_bytes().to_vec();
+        bytes[HEADER_LEN] ^= 1;
+        let tampered = EncryptedBlob::from_bytes(bytes.clone());
+        assert!(matches!(tampered.decrypt(&txid), Err(WatchtowerError::Decryption)));
+
+        bytes[0] = 2;
+        let version = EncryptedBlob::from_bytes(bytes);
+        assert!(matches!(version.decrypt(&txid), Err(WatchtowerError::UnsupportedVersion(2))));
+
+        let short = EncryptedBlob::from_bytes(vec![CURRENT_VERSION; HEADER_LEN + TAG_LEN - 1]);
+        assert!(matches!(short.decrypt(&txid), Err(WatchtowerError::TooShort)));
+    }
+}
@@ -18,6 +18,8 @@ use ::serde::{Deserialize, Serialize};
 use internals::error::InputString;
 use internals::write_err;
 
+use crate::{FeeRate, Weight};
+
 /// A set of denominations in which amounts can be expressed.
 ///
 /// # Examples
@@ -1044,6 +1046,25 @@ impl Amount {
     /// Returns [None] if overflow occurred.
     pub fn checked_rem(self, rhs: u64) -> Option<Amount> { self.0.checked_rem(rhs).map(Amount) }
 
+    /// Checked division by weight.
+    ///
+    /// Computes the fee rate paying `self` for `weight`, rounding down.
+    /// Returns [None] if overflow occurred or if `weight` is zero.
+    pub fn checked_div_by_weight(self, weight: Weight) -> Option<FeeRate> {
+        let sat_kwu = self.0.checked_mul(1000)?.checked_div(weight.to_wu())?;
+        Some(FeeRate::from_sat_per_kwu(sat_kwu))
+    }
+
+    /// Saturating addition.
+    ///
+    /// Computes `self + rhs`, returning [Amount::MAX] if overflow occurred.
+    pub fn saturating_add(self, rhs: Amount) -> Amount { Self(self.0.saturating_add(rhs.0)) }
+
+    /// Saturating subtraction.
+    ///
+    /// Computes `self - rhs`, returning [Amount::ZERO] if overflow occurred.
+    pub fn saturating_sub(self, rhs: Amount) -> Amount { Self(self.0.saturating_sub(rhs.0)) }
+
     /// Unchecked addition.
     ///
     /// Computes `self + rhs`.  Panics in debug mode, wraps in release mode.
@@ -1160,8 +1181,7 @@ impl TryFrom<SignedAmount> for Amount {
 
 impl core::iter::Sum for Amount {
     fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
-        let sats: u64 = iter.map(|amt| amt.0).sum();
-        Amount::from_sat(sats)
+        iter.checked_sum().expect("Amount addition error")
     }
 }
 
@@ -1432,6 +1452,20 @@ impl SignedAmount {
         self.0.checked_rem(rhs).map(SignedAmount)
     }
 
+    /// Saturating addition.
+    ///
+    /// Computes `self + rhs`, saturating at the numeric bounds instead of overflowing.
+    pub fn saturating_add(self, rhs: SignedAmount) -> SignedAmount {
+        Self(self.0.saturating_add(rhs.0))
+    }
+
+    /// Saturating subtraction.
+    ///
+    /// Computes `self - rhs`, saturating at the numeric bounds instead of overflowing.
+    pub fn saturating_sub(self, rhs: SignedAmount) -> SignedAmount {
+        Self(self.0.saturating_sub(rhs.0))
+    }
+
     /// Unchecked addition.
     ///
     /// Computes `self + rhs`.  Panics in debug mode, wraps in release mode.
@@ -1561,8 +1595,7 @@ impl TryFrom<Amount> for SignedAmount {
 
 impl core::iter::Sum for SignedAmount {
     fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
-        let sats: i64 = iter.map(|amt| amt.0).sum();
-        SignedAmount::from_sat(sats)
+        iter.checked_sum().expect("SignedAmount addition error")
     }
 }
 
@@ -2099,6 +2132,9 @@ mod tests {
         assert!(result.is_err());
         let result = panic::catch_unwind(|| Amount::from_sat(8446744073709551615) * 3);
         assert!(result.is_err());
+        let result =
+            panic::catch_unwind(|| [Amount::MAX, Amount::ONE_SAT].into_iter().sum::<Amount>());
+        assert!(result.is_err());
     }
 
     #[test]
@@ -2115,6 +2151,28 @@ mod tests {
         assert_eq!(ssat(-6).checked_div(2), Some(ssat(-3)));
     }
 
+    #[test]
+    fn saturating_arithmetic() {
+        let sat = Amount::from_sat;
+        let ssat = SignedAmount::from_sat;
+
+        assert_eq!(Amount::MAX.saturating_add(sat(1)), Amount::MAX);
+        assert_eq!(sat(1).saturating_sub(sat(2)), Amount::ZERO);
+        assert_eq!(sat(3).saturating_sub(sat(2)), sat(1));
+        assert_eq!(SignedAmount::MAX.saturating_add(ssat(1)), SignedAmount::MAX);
+        assert_eq!(SignedAmount::MIN.saturating_sub(ssat(1)), SignedAmount::MIN);
+    }
+
+    #[test]
+    fn checked_div_by_weight() {
+        let weight = Weight::from_vb(100).unwrap();
+        let fee_rate = Amount::from_sat(1000).checked_div_by_weight(weight).unwrap();
+        assert_eq!(fee_rate, FeeRate::from_sat_per_vb(10).unwrap());
+
+        assert_eq!(Amount::from_sat(1000).checked_div_by_weight(Weight::ZERO), None);
+        assert_eq!(Amount::MAX.checked_div_by_weight(weight), None);
+    }
+
     #[test]
     #[cfg(not(debug_assertions))]
     fn unchecked_amount_add() {
@@ -69,7 +69,24 @@ impl FeeRate {
     pub const fn to_sat_per_vb_floor(self) -> u64 { self.0 / (1000 / 4) }
 
     /// Converts to sat/vB rounding up.
-    pub const fn to_sat_per_vb_ceil(self) -> u64 { (self.0 + (1000 / 4 - 1)) / (1000 / 4) }
+    pub const fn to_sat_per_vb_ceil(self) -> u64 {
+        let sat_vb = self.0 / (1000 / 4);
+        if self.0 % (1000 / 4) == 0 {
+            sat_vb
+        } else {
+            sat_vb + 1
+        }
+    }
+
+    /// Checked addition.
+    ///
+    /// Computes `self + rhs` returning `None` if overflow occurred.
+    pub fn checked_add(self, rhs: FeeRate) -> Option<Self> { self.0.checked_add(rhs.0).map(Self) }
+
+    /// Checked subtraction.
+    ///
+    /// Computes `self - rhs` returning `None` if overflow occurred.
+    pub fn checked_sub(self, rhs: FeeRate) -> Option<Self> { self.0.checked_sub(rhs.0).map(Self) }
 
     /// Checked multiplication.
     ///
@@ -127,7 +144,7 @@ impl Mul<FeeRate> for Weight {
     type Output = Amount;
 
     fn mul(self, rhs: FeeRate) -> Self::Output {
-        Amount::from_sat((rhs.to_sat_per_kwu() * self.to_wu() + 999) / 1000)
+        rhs.checked_mul_by_weight(self).expect("fee calculation overflow")
     }
 }
 
@@ -140,7 +157,9 @@ impl Mul<Weight> for FeeRate {
 impl Div<Weight> for Amount {
     type Output = FeeRate;
 
-    fn div(self, rhs: Weight) -> Self::Output { FeeRate(self.to_sat() * 1000 / rhs.to_wu()) }
+    fn div(self, rhs: Weight) -> Self::Output {
+        self.checked_div_by_weight(rhs).expect("fee rate calculation overflow")
+    }
 }
 
 crate::impl_parse_str_from_int_infallible!(FeeRate, u64, from_sat_per_kwu);
@@ -224,4 +243,25 @@ mod tests {
         let fee_rate = FeeRate(10).checked_div(0);
         assert!(fee_rate.is_none());
     }
+
+    #[test]
+    fn checked_add_sub_test() {
+        assert_eq!(FeeRate(10).checked_add(FeeRate(5)), Some(FeeRate(15)));
+        assert_eq!(FeeRate::MAX.checked_add(FeeRate(1)), None);
+        assert_eq!(FeeRate(10).checked_sub(FeeRate(5)), Some(FeeRate(5)));
+        assert_eq!(FeeRate(5).checked_sub(FeeRate(10)), None);
+    }
+
+    #[test]
+    fn to_sat_per_vb_ceil_max_test() {
+        assert_eq!(FeeRate::MAX.to_sat_per_vb_ceil(), u64::MAX / 250 + 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn weight_mul_overflow_panic_test() { let _ = FeeRate::MAX * Weight::from_wu(2); }
+
+    #[test]
+    #[should_panic]
+    fn amount_div_overflow_panic_test() { let _ = Amount::MAX / Weight::from_wu(1); }
 }
@@ -100,7 +100,12 @@ impl Weight {
 
     /// Converts to vB rounding up.
     pub const fn to_vbytes_ceil(self) -> u64 {
-        (self.0 + Self::WITNESS_SCALE_FACTOR - 1) / Self::WITNESS_SCALE_FACTOR
+        let vb = self.0 / Self::WITNESS_SCALE_FACTOR;
+        if self.0 % Self::WITNESS_SCALE_FACTOR == 0 {
+            vb
+        } else {
+            vb + 1
+        }
     }
 
     /// Checked addition.
@@ -123,6 +128,21 @@ impl Weight {
     /// Computes `self / rhs` returning `None` if `rhs == 0`.
     pub fn checked_div(self, rhs: u64) -> Option<Self> { self.0.checked_div(rhs).map(Self) }
 
+    /// Saturating addition.
+    ///
+    /// Computes `self + rhs` returning [`Weight::MAX`] if an overflow occurred.
+    pub fn saturating_add(self, rhs: Self) -> Self { Self(self.0.saturating_add(rhs.0)) }
+
+    /// Saturating subtraction.
+    ///
+    /// Computes `self - rhs` returning [`Weight::ZERO`] if an overflow occurred.
+    pub fn saturating_sub(self, rhs: Self) -> Self { Self(self.0.saturating_sub(rhs.0)) }
+
+    /// Saturating multiplication.
+    ///
+    /// Computes `self * rhs` returning [`Weight::MAX`] if an overflow occurred.
+    pub fn saturating_mul(self, rhs: u64) -> Self { Self(self.0.saturating_mul(rhs)) }
+
     /// Scale by witness factor.
     ///
     /// Computes `self * WITNESS_SCALE_FACTOR` returning `None` if an overflow occurred.
@@ -253,6 +273,27 @@ mod tests {
         assert_eq!(None, result);
     }
 
+    #[test]
+    fn saturating() {
+        assert_eq!(Weight::MAX.saturating_add(Weight(1)), Weight::MAX);
+        assert_eq!(Weight(1).saturating_sub(Weight(2)), Weight::ZERO);
+        assert_eq!(Weight::MAX.saturating_mul(2), Weight::MAX);
+        assert_eq!(Weight(2).saturating_mul(2), Weight(4));
+    }
+
+    #[test]
+    fn to_vb_ceil_max() {
+        assert_eq!(Weight::MAX.to_vbytes_ceil(), u64::MAX / 4 + 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn add_overflow_panic() { let _ = Weight::MAX + Weight(1); }
+
+    #[test]
+    #[should_panic]
+    fn sum_overflow_panic() { let _ = [Weight::MAX, Weight(1)].iter().sum::<Weight>(); }
+
     #[test]
     fn scale_by_witness_factor() {
         let result = Weight(1).scale_by_witness_factor().expect("expected weight unit");
@@ -270,37 +311,43 @@ impl From<Weight> for u64 {
 impl Add for Weight {
     type Output = Weight;
 
-    fn add(self, rhs: Weight) -> Self::Output { Weight(self.0 + rhs.0) }
+    fn add(self, rhs: Weight) -> Self::Output {
+        self.checked_add(rhs).expect("Weight addition error")
+    }
 }
 
 impl AddAssign for Weight {
-    fn add_assign(&mut self, rhs: Self) { self.0 += rhs.0 }
+    fn add_assign(&mut self, rhs: Self) { *self = *self + rhs }
 }
 
 impl Sub for Weight {
     type Output = Weight;
 
-    fn sub(self, rhs: Weight) -> Self::Output { Weight(self.0 - rhs.0) }
+    fn sub(self, rhs: Weight) -> Self::Output {
+        self.checked_sub(rhs).expect("Weight subtraction error")
+    }
 }
 
 impl SubAssign for Weight {
-    fn sub_assign(&mut self, rhs: Self) { self.0 -= rhs.0 }
+    fn sub_assign(&mut self, rhs: Self) { *self = *self - rhs }
 }
 
 impl Mul<u64> for Weight {
     type Output = Weight;
 
-    fn mul(self, rhs: u64) -> Self::Output { Weight(self.0 * rhs) }
+    fn mul(self, rhs: u64) -> Self::Output {
+        self.checked_mul(rhs).expect("Weight multiplication error")
+    }
 }
 
 impl Mul<Weight> for u64 {
     type Output = Weight;
 
-    fn mul(self, rhs: Weight) -> Self::Output { Weight(self * rhs.0) }
+    fn mul(self, rhs: Weight) -> Self::Output { rhs * self }
 }
 
 impl MulAssign<u64> for Weight {
-    fn mul_assign(&mut self, rhs: u64) { self.0 *= rhs }
+    fn mul_assign(&mut self, rhs: u64) { *self = *self * rhs }
 }
 
 impl Div<u64> for Weight {
@@ -324,7 +371,7 @@ impl core::iter::Sum for Weight {
     where
         I: Iterator<Item = Self>,
     {
-        Weight(iter.map(Weight::to_wu).sum())
+        iter.fold(Weight::ZERO, |acc, weight| acc + weight)
     }
 }
 